@@ -0,0 +1,311 @@
+use std::convert::TryInto;
+use sha2::{Digest, Sha256};
+use crate::column::{real_string_type, ColumnType};
+use crate::context::ParseContext;
+use crate::errors::AnonymizeError;
+use crate::encode::encode_event;
+use crate::event::{Event, EventData};
+use crate::filter::TableRule;
+use crate::rows::value_spans;
+use crate::table_map::{TableMapColumn, TableMapEvent};
+
+// What a masked value becomes. Every mask is derived from the value and the salt only, so equal
+// values stay equal across events and tables, and joins on masked columns still line up.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Mask {
+    // hex digits of the value's SHA-256 for strings, its first bytes for integers
+    Hash,
+    // this text, cut or padded with spaces, for strings; this number for integers
+    Fixed(String),
+    // letters for letters, digits for digits and the rest kept, so the value keeps its shape:
+    // 'Ann-Marie 42' may become 'Kzb-Wqnro 17'; integers keep their number of digits
+    Fake,
+}
+
+// Masks column values of rows events in place, so a binlog can be shared without the data in
+// it: each value is replaced by one of the same length in bytes, so events keep their length
+// and offsets and only their checksums are recomputed. String and integer columns can be
+// masked; their names come from the table map (binlog_row_metadata=FULL), or are @1, @2...
+//
+//     let mut anonymizer = Anonymizer::new().mask("shop.users", "email", Mask::Hash);
+//     for event in BinlogFile::from_path(path)?.events() {
+//         let event = event?;
+//         out.write_all(&anonymizer.anonymize(&event, &event.parsed(&mut ctx)?)?)?;
+//     }
+#[derive(Debug, Default)]
+pub struct Anonymizer {
+    rules: Vec<(TableRule, String, Mask)>,
+    salt: Vec<u8>,
+    // the table maps and, from the FormatDescriptionEvent, how long table ids are
+    context: ParseContext,
+}
+
+impl Anonymizer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // `column` of the tables matching the LIKE pattern `table`, as in TableRule::wild
+    pub fn mask(self, table: &str, column: &str, mask: Mask) -> Self {
+        self.mask_rule(TableRule::wild(table), column, mask)
+    }
+
+    pub fn mask_rule(mut self, table: TableRule, column: &str, mask: Mask) -> Self {
+        self.rules.push((table, column.to_owned(), mask));
+        self
+    }
+
+    // mixed into every hash, so masked values can't be matched against hashes of guesses
+    pub fn salt(mut self, salt: &[u8]) -> Self {
+        self.salt = salt.to_vec();
+        self
+    }
+
+    // The event's bytes, with the masked values of a rows event replaced and its checksum
+    // recomputed; other events as they are.
+    pub fn anonymize(&mut self, event: &Event, data: &EventData) -> Result<Vec<u8>, AnonymizeError> {
+        self.context.observe(data);
+        let rows = match data {
+            EventData::TransactionPayloadEvent { .. } if !self.rules.is_empty() => return Err(AnonymizeError::CompressedPayload),
+            EventData::WriteRowsEvent(rows) | EventData::UpdateRowsEvent(rows) | EventData::DeleteRowsEvent(rows) => rows,
+            _ => return Ok(event.to_bytes()?),
+        };
+        let table_map = self.context.table_map(rows.table_id).ok_or(AnonymizeError::UnknownTableId(rows.table_id))?;
+        let masks = self.column_masks(table_map)?;
+        if masks.iter().all(Option::is_none) {
+            return Ok(event.to_bytes()?);
+        }
+        let mut payload = event.try_data()?.to_vec();
+        for (i, start, end) in value_spans(event.type_code(), event.try_data()?, table_map, self.context.table_id_len(event.type_code()))? {
+            if let Some(mask) = &masks[i] {
+                mask_value(&mut payload[start..end], &table_map.columns[i], mask, &self.salt)?;
+            }
+        }
+        Ok(encode_event(event.header(), &payload, event.offset(), event.checksum().is_some())?)
+    }
+
+    // the mask of each column of the table, the first rule naming it winning
+    fn column_masks(&self, table_map: &TableMapEvent) -> Result<Vec<Option<&Mask>>, AnonymizeError> {
+        let rules: Vec<_> = self.rules.iter()
+            .filter(|(table, _, _)| table.matches(&table_map.schema, &table_map.table))
+            .collect();
+        let mut masks = Vec::with_capacity(table_map.columns.len());
+        for (i, column) in table_map.columns.iter().enumerate() {
+            let index_name = format!("@{}", i + 1);
+            let mask = rules.iter()
+                .find(|(_, name, _)| *name == index_name || column.name.as_deref() == Some(name.as_str()))
+                .map(|(_, _, mask)| mask);
+            if mask.is_some() && !is_maskable(column) {
+                return Err(AnonymizeError::UnsupportedColumn {
+                    column: format!("{}.{}.{}", table_map.schema, table_map.table, column.name.as_deref().unwrap_or(&index_name)),
+                    column_type: column.real_type(),
+                });
+            }
+            masks.push(mask);
+        }
+        Ok(masks)
+    }
+}
+
+fn is_maskable(column: &TableMapColumn) -> bool {
+    integer_width(column).is_some() || length_prefix_len(column).is_some()
+}
+
+fn integer_width(column: &TableMapColumn) -> Option<usize> {
+    match column.column_type {
+        ColumnType::Tiny => Some(1),
+        ColumnType::Short => Some(2),
+        ColumnType::Int24 => Some(3),
+        ColumnType::Long => Some(4),
+        ColumnType::LongLong => Some(8),
+        _ => None,
+    }
+}
+
+// the bytes before a string value holding its length, as parse_value reads them
+fn length_prefix_len(column: &TableMapColumn) -> Option<usize> {
+    match column.column_type {
+        ColumnType::VarChar | ColumnType::VarString => Some(if column.meta < 256 { 1 } else { 2 }),
+        ColumnType::String => match real_string_type(column.meta) {
+            (ColumnType::String, len) => Some(if len < 256 { 1 } else { 2 }),
+            _ => None,
+        },
+        ColumnType::Blob | ColumnType::TinyBlob | ColumnType::MediumBlob | ColumnType::LongBlob => Some(usize::from(column.meta)),
+        _ => None,
+    }
+}
+
+fn mask_value(value: &mut [u8], column: &TableMapColumn, mask: &Mask, salt: &[u8]) -> Result<(), AnonymizeError> {
+    if let Some(width) = integer_width(column) {
+        return mask_integer(value, width, column.unsigned, mask, salt);
+    }
+    let prefix = length_prefix_len(column).unwrap_or(0);
+    let text = &mut value[prefix..];
+    let masked = match mask {
+        Mask::Hash => hex(&digest(salt, text, 0)).bytes().cycle().take(text.len()).collect(),
+        Mask::Fixed(fixed) => {
+            // cut on a character boundary, so the value stays valid UTF-8
+            let mut end = fixed.len().min(text.len());
+            while !fixed.is_char_boundary(end) {
+                end -= 1;
+            }
+            let mut masked = fixed.as_bytes()[..end].to_vec();
+            masked.resize(text.len(), b' ');
+            masked
+        }
+        Mask::Fake => fake_text(text, salt),
+    };
+    text.copy_from_slice(&masked);
+    Ok(())
+}
+
+fn mask_integer(value: &mut [u8], width: usize, unsigned: bool, mask: &Mask, salt: &[u8]) -> Result<(), AnonymizeError> {
+    let bits = width as u32 * 8;
+    let (min, max) = if unsigned { (0, (1i128 << bits) - 1) } else { (-(1i128 << (bits - 1)), (1i128 << (bits - 1)) - 1) };
+    let mut raw = [0u8; 16];
+    raw[..width].copy_from_slice(value);
+    let mut original = i128::from_le_bytes(raw);
+    if !unsigned && original > max {
+        // sign extension
+        original -= 1i128 << bits;
+    }
+    let hash = u64::from_le_bytes(digest(salt, value, 0)[..8].try_into().expect("8 bytes"));
+    let masked = match mask {
+        Mask::Hash => i128::from(hash) & ((1i128 << bits) - 1),
+        Mask::Fixed(fixed) => fixed.trim().parse::<i128>()
+            .ok()
+            .filter(|n| (min..=max).contains(n))
+            .ok_or_else(|| AnonymizeError::FixedValueOutOfRange(fixed.clone(), width))?,
+        Mask::Fake => {
+            let digits = original.unsigned_abs().to_string().len() as u32;
+            let low = if digits == 1 { 0 } else { 10u128.pow(digits - 1) };
+            let fake = (low + u128::from(hash) % (10u128.pow(digits) - low)) as i128;
+            (if original < 0 { -fake } else { fake }).clamp(min, max)
+        }
+    };
+    value.copy_from_slice(&masked.to_le_bytes()[..width]);
+    Ok(())
+}
+
+// same-length text with the shape of `text`; characters outside ASCII become as many letters
+// as they had bytes
+fn fake_text(text: &[u8], salt: &[u8]) -> Vec<u8> {
+    let mut random = Random { seed: digest(salt, text, 0), block: [0; 32], index: 32, counter: 0 };
+    text.iter()
+        .map(|b| match b {
+            b'a'..=b'z' => b'a' + random.below(26),
+            b'A'..=b'Z' => b'A' + random.below(26),
+            b'0'..=b'9' => b'0' + random.below(10),
+            b if b.is_ascii() => *b,
+            _ => b'a' + random.below(26),
+        })
+        .collect()
+}
+
+// bytes from SHA-256 of the seed and a counter
+struct Random {
+    seed: [u8; 32],
+    block: [u8; 32],
+    index: usize,
+    counter: u64,
+}
+
+impl Random {
+    fn below(&mut self, n: u8) -> u8 {
+        if self.index == self.block.len() {
+            self.counter += 1;
+            self.block = digest(&self.seed, &[], self.counter);
+            self.index = 0;
+        }
+        self.index += 1;
+        self.block[self.index - 1] % n
+    }
+}
+
+fn digest(salt: &[u8], value: &[u8], counter: u64) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update(salt);
+    hasher.update(value);
+    hasher.update(counter.to_le_bytes());
+    hasher.finalize().into()
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::anonymize::{Anonymizer, Mask};
+    use crate::binlog_file::BinlogFile;
+    use crate::context::ParseContext;
+    use crate::errors::AnonymizeError;
+    use crate::event::EventData;
+    use crate::value::Value;
+
+    fn anonymize(anonymizer: Anonymizer) -> Result<Vec<u8>, AnonymizeError> {
+        let mut anonymizer = anonymizer;
+        let mut ctx = ParseContext::new();
+        let mut bytes = b"\xfebin".to_vec();
+        for event in BinlogFile::from_path("tests/asset/mysql-bin.100746").unwrap().events() {
+            let event = event.unwrap();
+            bytes.extend(anonymizer.anonymize(&event, &event.parsed(&mut ctx)?)?);
+        }
+        Ok(bytes)
+    }
+
+    fn rows(bytes: &[u8]) -> Vec<Vec<Value>> {
+        let path = std::env::temp_dir().join(format!("anonymized-{}-{}", std::process::id(), bytes.len()));
+        std::fs::write(&path, bytes).unwrap();
+        let mut file = BinlogFile::from_path(&path).unwrap();
+        assert!(file.verify().unwrap().is_ok());
+        let mut ctx = ParseContext::new();
+        let mut rows = vec![];
+        for event in file.events() {
+            let event = event.unwrap();
+            if let EventData::WriteRowsEvent(event_rows) | EventData::UpdateRowsEvent(event_rows) = event.parsed(&mut ctx).unwrap() {
+                for row in event_rows.rows {
+                    let image = row.after.unwrap();
+                    rows.push(image.values().iter().map(|v| v.clone().unwrap()).collect());
+                }
+            }
+        }
+        std::fs::remove_file(&path).unwrap();
+        rows
+    }
+
+    #[test]
+    fn test_mask_in_place() {
+        //given
+        let original = std::fs::read("tests/asset/mysql-bin.100746").unwrap();
+        let anonymizer = || Anonymizer::new().mask("test.users", "@2", Mask::Fake).mask("test.%", "@3", Mask::Fixed("7".to_owned()));
+
+        //when
+        let masked = anonymize(anonymizer()).unwrap();
+        let again = anonymize(anonymizer()).unwrap();
+        let hashed = anonymize(Anonymizer::new().mask("test.users", "@2", Mask::Hash).salt(b"pepper")).unwrap();
+        let too_big = anonymize(Anonymizer::new().mask("test.users", "@3", Mask::Fixed("300000000000".to_owned())));
+
+        //then
+        assert_eq!(masked.len(), original.len());
+        assert_eq!(masked, again);
+        assert_eq!(rows(&original)[0], vec![Value::Int(1), Value::String("alice".to_owned()), Value::Int(30)]);
+        let masked_rows = rows(&masked);
+        assert_eq!(masked_rows.len(), 3);
+        for (masked, original) in masked_rows.iter().zip(rows(&original)) {
+            assert_eq!(masked[0], original[0]);
+            match (&masked[1], &original[1]) {
+                (Value::String(masked), Value::String(original)) => {
+                    assert_ne!(masked, original);
+                    assert_eq!(masked.len(), original.len());
+                    assert!(masked.bytes().all(|b| b.is_ascii_lowercase()));
+                }
+                other => panic!("{:?}", other),
+            }
+        }
+        assert_eq!(masked_rows[0][2], Value::Int(7));
+        assert!(matches!(&rows(&hashed)[0][1], Value::String(s) if s.len() == 5 && s.bytes().all(|b| b.is_ascii_hexdigit())));
+        assert!(matches!(too_big, Err(AnonymizeError::FixedValueOutOfRange(..))));
+    }
+}
@@ -0,0 +1,285 @@
+use std::convert::TryFrom;
+use std::sync::Arc;
+use arrow_array::{
+    ArrayRef, BinaryArray, BooleanArray, Date32Array, Decimal128Array, DurationMicrosecondArray, Float32Array,
+    Float64Array, Int16Array, Int32Array, Int64Array, Int8Array, RecordBatch, StringArray, TimestampMicrosecondArray,
+    UInt16Array, UInt32Array, UInt64Array, UInt8Array,
+};
+use arrow_schema::{DataType, Field, Schema, SchemaRef, TimeUnit};
+use crate::column::ColumnType;
+use crate::errors::ExportError;
+use crate::export::plain_json;
+use crate::rows::{RowsEvent, RowsEventKind};
+use crate::table_map::{TableMapColumn, TableMapEvent};
+use crate::utils::{days_from_civil, unscaled_decimal};
+use crate::value::Value;
+
+// The Arrow type of a column: integers by width and signedness, DECIMAL up to 38 digits as
+// Decimal128 (wider as strings), DATE as Date32, TIME as a Duration since it can be negative or
+// over 24 hours, DATETIME as a timestamp without time zone and TIMESTAMP as one in UTC, BIT(1)
+// as a boolean and wider BITs as UInt64, ENUM and SET as their labels, JSON and binary strings
+// as Binary.
+pub fn data_type(column: &TableMapColumn) -> DataType {
+    let unsigned = column.unsigned;
+    match column.real_type() {
+        ColumnType::Tiny if unsigned => DataType::UInt8,
+        ColumnType::Tiny => DataType::Int8,
+        ColumnType::Short if unsigned => DataType::UInt16,
+        ColumnType::Short => DataType::Int16,
+        ColumnType::Int24 | ColumnType::Long if unsigned => DataType::UInt32,
+        ColumnType::Int24 | ColumnType::Long => DataType::Int32,
+        ColumnType::LongLong if unsigned => DataType::UInt64,
+        ColumnType::LongLong => DataType::Int64,
+        ColumnType::Float => DataType::Float32,
+        ColumnType::Double => DataType::Float64,
+        ColumnType::Decimal | ColumnType::NewDecimal => {
+            let (precision, scale) = ((column.meta >> 8) as u8, (column.meta & 0xff) as i8);
+            if precision <= 38 { DataType::Decimal128(precision, scale) } else { DataType::Utf8 }
+        }
+        ColumnType::Year => DataType::UInt16,
+        ColumnType::Date | ColumnType::NewDate => DataType::Date32,
+        ColumnType::Time | ColumnType::Time2 => DataType::Duration(TimeUnit::Microsecond),
+        ColumnType::DateTime | ColumnType::DateTime2 => DataType::Timestamp(TimeUnit::Microsecond, None),
+        ColumnType::Timestamp | ColumnType::Timestamp2 => DataType::Timestamp(TimeUnit::Microsecond, Some("UTC".into())),
+        ColumnType::Bit if column.meta == 1 => DataType::Boolean,
+        ColumnType::Bit => DataType::UInt64,
+        ColumnType::Json | ColumnType::Geometry => DataType::Binary,
+        ColumnType::String | ColumnType::VarChar | ColumnType::VarString if column.charset == Some(63) => DataType::Binary,
+        t if t.is_blob() && column.charset.is_none_or(|c| c == 63) => DataType::Binary,
+        _ => DataType::Utf8,
+    }
+}
+
+// An "_op" column (c, u or d) and one nullable field per column, named @1, @2.. when the table
+// map has no names.
+pub fn table_schema(table_map: &TableMapEvent) -> Schema {
+    let mut fields = vec![Field::new("_op", DataType::Utf8, false)];
+    fields.extend(table_map.columns.iter().enumerate().map(|(i, column)| {
+        let name = column.name.clone().unwrap_or_else(|| format!("@{}", i + 1));
+        Field::new(name, data_type(column), true)
+    }));
+    Schema::new(fields)
+}
+
+// Collects the row changes of one table into columns and hands them out as RecordBatches. Each
+// change is one row holding its after image, or the before image for deletes, with columns a
+// MINIMAL image leaves out as nulls.
+//
+//     let mut batch = RecordBatchBuilder::new(table_map);
+//     batch.append(&rows);
+//     if batch.len() >= 8192 {
+//         writer.write(&batch.finish()?)?;
+//     }
+pub struct RecordBatchBuilder {
+    table_map: TableMapEvent,
+    schema: SchemaRef,
+    ops: Vec<&'static str>,
+    // column-major, one Vec per column of the table
+    columns: Vec<Vec<Option<Value>>>,
+}
+
+impl RecordBatchBuilder {
+    pub fn new(table_map: &TableMapEvent) -> Self {
+        RecordBatchBuilder {
+            table_map: table_map.clone(),
+            schema: Arc::new(table_schema(table_map)),
+            ops: vec![],
+            columns: vec![vec![]; table_map.columns.len()],
+        }
+    }
+
+    pub fn schema(&self) -> SchemaRef {
+        self.schema.clone()
+    }
+
+    pub fn len(&self) -> usize {
+        self.ops.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ops.is_empty()
+    }
+
+    // adds the changes of `rows`, which must be of this builder's table
+    pub fn append(&mut self, rows: &RowsEvent) {
+        let op = match rows.kind {
+            RowsEventKind::Write => "c",
+            RowsEventKind::Update => "u",
+            RowsEventKind::Delete => "d",
+        };
+        for change in &rows.rows {
+            let image = match change.after.as_ref().or(change.before.as_ref()) {
+                Some(image) => image,
+                None => continue,
+            };
+            self.ops.push(op);
+            for (i, column) in self.columns.iter_mut().enumerate() {
+                column.push(image.get(i).cloned());
+            }
+        }
+    }
+
+    // the rows appended since the last call
+    pub fn finish(&mut self) -> Result<RecordBatch, ExportError> {
+        let mut arrays: Vec<ArrayRef> = vec![Arc::new(StringArray::from(std::mem::take(&mut self.ops)))];
+        let columns = std::mem::replace(&mut self.columns, vec![vec![]; self.table_map.columns.len()]);
+        for (i, cells) in columns.into_iter().enumerate() {
+            let column = &self.table_map.columns[i];
+            let array = column_array(&data_type(column), column, &cells)
+                .ok_or_else(|| ExportError::UnexpectedValue { column: self.schema.field(i + 1).name().clone() })?;
+            arrays.push(array?);
+        }
+        Ok(RecordBatch::try_new(self.schema.clone(), arrays)?)
+    }
+}
+
+// None when a value doesn't fit the column's type
+fn column_array(data_type: &DataType, column: &TableMapColumn, cells: &[Option<Value>]) -> Option<Result<ArrayRef, ExportError>> {
+    fn collect<T>(cells: &[Option<Value>], f: impl Fn(&Value) -> Option<T>) -> Option<Vec<Option<T>>> {
+        cells.iter()
+            .map(|cell| match cell {
+                None | Some(Value::Null) => Some(None),
+                Some(value) => f(value).map(Some),
+            })
+            .collect()
+    }
+    let int = |v: &Value| match v {
+        Value::Int(v) => Some(*v),
+        Value::UInt(v) => i64::try_from(*v).ok(),
+        _ => None,
+    };
+    let uint = |v: &Value| match v {
+        Value::UInt(v) => Some(*v),
+        Value::Int(v) => u64::try_from(*v).ok(),
+        Value::Year(v) => Some(u64::from(*v)),
+        Value::Bit(v) => Some(v.iter().take(8).fold(0u64, |n, b| n << 8 | u64::from(*b))),
+        _ => None,
+    };
+    let array: ArrayRef = match data_type {
+        DataType::Int8 => Arc::new(Int8Array::from(collect(cells, |v| i8::try_from(int(v)?).ok())?)),
+        DataType::Int16 => Arc::new(Int16Array::from(collect(cells, |v| i16::try_from(int(v)?).ok())?)),
+        DataType::Int32 => Arc::new(Int32Array::from(collect(cells, |v| i32::try_from(int(v)?).ok())?)),
+        DataType::Int64 => Arc::new(Int64Array::from(collect(cells, int)?)),
+        DataType::UInt8 => Arc::new(UInt8Array::from(collect(cells, |v| u8::try_from(uint(v)?).ok())?)),
+        DataType::UInt16 => Arc::new(UInt16Array::from(collect(cells, |v| u16::try_from(uint(v)?).ok())?)),
+        DataType::UInt32 => Arc::new(UInt32Array::from(collect(cells, |v| u32::try_from(uint(v)?).ok())?)),
+        DataType::UInt64 => Arc::new(UInt64Array::from(collect(cells, uint)?)),
+        DataType::Float32 => Arc::new(Float32Array::from(collect(cells, |v| match v {
+            Value::Float(v) => Some(*v),
+            _ => None,
+        })?)),
+        DataType::Float64 => Arc::new(Float64Array::from(collect(cells, |v| match v {
+            Value::Double(v) => Some(*v),
+            Value::Float(v) => Some(f64::from(*v)),
+            _ => None,
+        })?)),
+        DataType::Decimal128(precision, scale) => {
+            let values = collect(cells, |v| match v {
+                Value::Decimal(text) => unscaled_decimal(text, *scale as u8),
+                _ => None,
+            })?;
+            match Decimal128Array::from(values).with_precision_and_scale(*precision, *scale) {
+                Ok(array) => Arc::new(array),
+                Err(e) => return Some(Err(e.into())),
+            }
+        }
+        DataType::Date32 => Arc::new(Date32Array::from(collect(cells, |v| match v {
+            Value::Date { year, month, day } => i32::try_from(days_from_civil(i64::from(*year), *month, *day)).ok(),
+            _ => None,
+        })?)),
+        DataType::Duration(_) => Arc::new(DurationMicrosecondArray::from(collect(cells, |v| match v {
+            Value::Time { negative, hours, minutes, seconds, micros } => {
+                let micros = ((i64::from(*hours) * 60 + i64::from(*minutes)) * 60 + i64::from(*seconds)) * 1_000_000 + i64::from(*micros);
+                Some(if *negative { -micros } else { micros })
+            }
+            _ => None,
+        })?)),
+        DataType::Timestamp(_, zone) => {
+            let values = collect(cells, |v| match v {
+                Value::DateTime { year, month, day, hour, minute, second, micros } => {
+                    let seconds = days_from_civil(i64::from(*year), *month, *day) * 86400
+                        + i64::from(*hour) * 3600 + i64::from(*minute) * 60 + i64::from(*second);
+                    Some(seconds * 1_000_000 + i64::from(*micros))
+                }
+                Value::Timestamp { seconds, micros } => Some(i64::from(*seconds) * 1_000_000 + i64::from(*micros)),
+                _ => None,
+            })?;
+            Arc::new(TimestampMicrosecondArray::from(values).with_timezone_opt(zone.clone()))
+        }
+        DataType::Boolean => Arc::new(BooleanArray::from(collect(cells, |v| match v {
+            Value::Bit(v) => Some(v.iter().any(|b| *b != 0)),
+            _ => None,
+        })?)),
+        DataType::Binary => Arc::new(collect(cells, |v| match v {
+            Value::Bytes(v) | Value::Json(v) => Some(v.clone()),
+            Value::String(v) => Some(v.clone().into_bytes()),
+            _ => None,
+        })?.into_iter().collect::<BinaryArray>()),
+        _ => Arc::new(StringArray::from(collect(cells, |v| Some(match v {
+            Value::String(v) | Value::Decimal(v) => v.clone(),
+            Value::Bytes(v) => String::from_utf8_lossy(v).into_owned(),
+            Value::Enum(index) => column.enum_values.as_ref()
+                .and_then(|labels| labels.get((*index as usize).wrapping_sub(1)).cloned())
+                .unwrap_or_else(|| index.to_string()),
+            Value::Set(bits) => match &column.set_values {
+                Some(labels) => labels.iter().enumerate()
+                    .filter(|(i, _)| *i < 64 && bits & (1 << i) != 0)
+                    .map(|(_, l)| l.as_str())
+                    .collect::<Vec<_>>()
+                    .join(","),
+                None => bits.to_string(),
+            },
+            value => match plain_json(value) {
+                serde_json::Value::String(s) => s,
+                other => other.to_string(),
+            },
+        }))?)),
+    };
+    Some(Ok(array))
+}
+
+#[cfg(test)]
+mod tests {
+    use arrow_array::{Array, Date32Array, Int32Array, StringArray};
+    use arrow_schema::DataType;
+    use crate::arrow::RecordBatchBuilder;
+    use crate::context::ParseContext;
+    use crate::event::EventData;
+    use crate::event::tests::{parse_events, users_rows, users_table_map};
+
+    #[test]
+    fn test_record_batch_from_rows() {
+        //given
+        // test.users (id INT, created DATE NULL) with column names
+        let table_map = users_table_map(&[3, 10], &[], 0x02, b"\x04\x0b\x02id\x07created");
+        let write_rows = users_rows(2, &[0x03, 0x00, 7, 0, 0, 0, 0x3f, 0xd0, 0x0f, 0x02, 8, 0, 0, 0]);
+        let delete_rows = users_rows(2, &[0x03, 0x00, 7, 0, 0, 0, 0x3f, 0xd0, 0x0f]);
+        let mut ctx = ParseContext::new();
+        let data: Vec<EventData> = parse_events(16, &[(19, table_map), (30, write_rows), (32, delete_rows)]).iter()
+            .map(|event| event.parsed(&mut ctx).unwrap())
+            .collect();
+        let mut builder = match &data[0] {
+            EventData::TableMapEvent(table_map) => RecordBatchBuilder::new(table_map),
+            data => panic!("{:?}", data),
+        };
+
+        //when
+        for rows in &data[1..] {
+            if let EventData::WriteRowsEvent(rows) | EventData::DeleteRowsEvent(rows) = rows {
+                builder.append(rows);
+            }
+        }
+        let batch = builder.finish().unwrap();
+
+        //then
+        assert_eq!(batch.num_rows(), 3);
+        assert!(builder.is_empty());
+        assert_eq!(batch.schema().field(2).data_type(), &DataType::Date32);
+        let ops = batch.column(0).as_any().downcast_ref::<StringArray>().unwrap();
+        assert_eq!(ops.iter().collect::<Vec<_>>(), vec![Some("c"), Some("c"), Some("d")]);
+        let ids = batch.column_by_name("id").unwrap().as_any().downcast_ref::<Int32Array>().unwrap();
+        assert_eq!(ids.values().to_vec(), vec![7, 8, 7]);
+        let created = batch.column_by_name("created").unwrap().as_any().downcast_ref::<Date32Array>().unwrap();
+        assert_eq!(created.iter().collect::<Vec<_>>(), vec![Some(19753), None, Some(19753)]);
+    }
+}
@@ -0,0 +1,131 @@
+use std::io;
+use std::net::{Shutdown, TcpStream};
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use futures_core::Stream;
+use tokio::sync::{mpsc, oneshot};
+use crate::client::ReplicationClient;
+use crate::errors::{BinlogFileError, ClientError};
+use crate::event::Event;
+
+// how many events the connection's thread reads ahead of the consumer
+const EVENT_BUFFER: usize = 64;
+
+impl ReplicationClient {
+    // connect() for async code: the stream is a futures Stream, to use with StreamExt,
+    // select! and the like
+    //
+    //     let mut events = ReplicationClient::new("db1:3306").user("repl").connect_async().await?;
+    //     while let Some(event) = events.next().await {
+    //         handle(event?);
+    //     }
+    pub async fn connect_async(&self) -> Result<AsyncBinlogStream, ClientError> {
+        let client = self.clone();
+        let (ready_tx, ready_rx) = oneshot::channel();
+        std::thread::Builder::new()
+            .name("binlog-stream".to_owned())
+            .spawn(move || {
+                let connect = || -> Result<_, ClientError> {
+                    let stream = client.connect()?;
+                    Ok((stream.socket()?, stream))
+                };
+                let (socket, stream) = match connect() {
+                    Ok(connected) => connected,
+                    Err(e) => {
+                        let _ = ready_tx.send(Err(e));
+                        return;
+                    }
+                };
+                let (events_tx, events_rx) = mpsc::channel(EVENT_BUFFER);
+                if ready_tx.send(Ok((socket, events_rx))).is_err() {
+                    return;
+                }
+                for event in stream {
+                    if events_tx.blocking_send(event).is_err() {
+                        break;
+                    }
+                }
+            })?;
+        let (socket, events) = ready_rx
+            .await
+            .map_err(|_| io::Error::other("the connection's thread panicked"))??;
+        Ok(AsyncBinlogStream { socket, events })
+    }
+}
+
+// A BinlogStream for async code. The blocking protocol runs on a thread of its own, which
+// reads up to EVENT_BUFFER events ahead and otherwise waits, leaving the rest to TCP flow
+// control; dropping the stream closes the connection and ends the thread.
+pub struct AsyncBinlogStream {
+    socket: TcpStream,
+    events: mpsc::Receiver<Result<Event, BinlogFileError>>,
+}
+
+impl Stream for AsyncBinlogStream {
+    type Item = Result<Event, BinlogFileError>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.events.poll_recv(cx)
+    }
+}
+
+impl Drop for AsyncBinlogStream {
+    fn drop(&mut self) {
+        // unblocks the thread if it's waiting on the server
+        let _ = self.socket.shutdown(Shutdown::Both);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Read;
+    use futures_util::StreamExt;
+    use crate::client::tests::{accept_login, dump_packets, fake_server, read_packet, write_packet};
+    use crate::client::ReplicationClient;
+    use crate::errors::ClientError;
+    use crate::event::TypeCode;
+
+    #[tokio::test]
+    async fn test_stream_events_asynchronously() {
+        //given
+        let (addr, server) = fake_server(|mut stream| {
+            accept_login(&mut stream, "CRC32");
+            read_packet(&mut stream);
+            write_packet(&mut stream, 1, &[0, 0, 0, 2, 0, 0, 0]);
+            read_packet(&mut stream);
+            for (seq, packet) in dump_packets().iter().enumerate() {
+                write_packet(&mut stream, seq as u8 + 1, packet);
+            }
+            // the client closes the connection once dropped
+            assert_eq!(stream.read(&mut [0; 1]).unwrap(), 0);
+        });
+
+        //when
+        let events = ReplicationClient::new(&addr).connect_async().await.unwrap();
+        let types: Vec<_> = events.take(4).map(|e| e.unwrap().type_code()).collect().await;
+        server.join().unwrap();
+
+        //then
+        assert_eq!(types, vec![
+            TypeCode::RotateEvent,
+            TypeCode::FormatDescriptionEvent,
+            TypeCode::PreviousGtidsLogEvent,
+            TypeCode::AnonymousGtidLogEvent,
+        ]);
+    }
+
+    #[tokio::test]
+    async fn test_connect_error_is_returned() {
+        //given
+        let (addr, server) = fake_server(|mut stream| {
+            write_packet(&mut stream, 0, &[0xff, 0x10, 0x04, b'n', b'o']);
+        });
+
+        //when
+        let result = ReplicationClient::new(&addr).connect_async().await;
+        server.join().unwrap();
+
+        //then
+        assert!(matches!(result, Err(ClientError::Server { code: 1040, .. })));
+    }
+}
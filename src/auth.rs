@@ -0,0 +1,183 @@
+use rsa::pkcs1::DecodeRsaPublicKey;
+use rsa::pkcs8::DecodePublicKey;
+use rsa::{Oaep, RsaPublicKey};
+use sha1::{Digest, Sha1};
+use sha2::Sha256;
+use crate::errors::ClientError;
+use crate::protocol::{handshake_response, parse_err_packet, read_nul_string, Connection, Handshake};
+
+pub(crate) const MYSQL_NATIVE_PASSWORD: &str = "mysql_native_password";
+pub(crate) const CACHING_SHA2_PASSWORD: &str = "caching_sha2_password";
+
+const AUTH_MORE_DATA: u8 = 0x01;
+const AUTH_SWITCH_REQUEST: u8 = 0xfe;
+
+// caching_sha2_password's requests and answers inside AuthMoreData
+const REQUEST_PUBLIC_KEY: u8 = 0x02;
+const FAST_AUTH_SUCCESS: u8 = 0x03;
+const PERFORM_FULL_AUTH: u8 = 0x04;
+
+// what the exchange needs besides the handshake
+pub(crate) struct Credentials<'a> {
+    pub(crate) user: &'a str,
+    pub(crate) password: &'a str,
+    // PEM of the server's RSA key, saving a round trip and the trust-on-first-use of asking for it
+    pub(crate) server_public_key: Option<&'a str>,
+    // over TLS the password may be sent as is
+    pub(crate) secure: bool,
+}
+
+// Sends the handshake response and answers the server until it accepts or refuses the login.
+// The server may ask to switch to another plugin, with a fresh challenge, at any point.
+// https://dev.mysql.com/doc/dev/mysql-server/latest/page_protocol_connection_phase.html
+pub(crate) fn authenticate(
+    conn: &mut Connection,
+    handshake: &Handshake,
+    capabilities: u32,
+    credentials: &Credentials<'_>,
+) -> Result<(), ClientError> {
+    // servers without CLIENT_PLUGIN_AUTH only know the native scramble
+    let mut plugin = match handshake.auth_plugin_name.as_str() {
+        "" => MYSQL_NATIVE_PASSWORD.to_owned(),
+        plugin => plugin.to_owned(),
+    };
+    let mut challenge = handshake.auth_plugin_data.clone();
+    let response = auth_response(&plugin, credentials.password, &challenge)?;
+    conn.write_packet(&handshake_response(capabilities, credentials.user, &response, &plugin))?;
+    loop {
+        let packet = conn.read_packet()?;
+        match packet.first() {
+            Some(0x00) => return Ok(()),
+            Some(0xff) => return Err(parse_err_packet(&packet)),
+            Some(&AUTH_SWITCH_REQUEST) => {
+                let mut reader = &packet[1..];
+                plugin = read_nul_string(&mut reader).ok_or(ClientError::BadPacket("auth switch request"))?;
+                challenge = reader.strip_suffix(&[0]).unwrap_or(reader).to_vec();
+                conn.write_packet(&auth_response(&plugin, credentials.password, &challenge)?)?;
+            }
+            Some(&AUTH_MORE_DATA) if plugin == CACHING_SHA2_PASSWORD => {
+                caching_sha2_more_data(conn, &packet[1..], &challenge, credentials)?;
+            }
+            _ => return Err(ClientError::BadPacket("authentication")),
+        }
+    }
+}
+
+// The server answers the scramble with FAST_AUTH_SUCCESS when it has the password cached, and
+// with PERFORM_FULL_AUTH when it needs the password itself: in clear text over TLS, otherwise
+// encrypted with its RSA key, which is asked for unless given.
+// https://dev.mysql.com/doc/dev/mysql-server/latest/page_caching_sha2_authentication_exchanges.html
+fn caching_sha2_more_data(
+    conn: &mut Connection,
+    data: &[u8],
+    challenge: &[u8],
+    credentials: &Credentials<'_>,
+) -> Result<(), ClientError> {
+    match data {
+        [FAST_AUTH_SUCCESS] => Ok(()),
+        [PERFORM_FULL_AUTH] if credentials.secure => {
+            let mut password = credentials.password.as_bytes().to_vec();
+            password.push(0);
+            conn.write_packet(&password)
+        }
+        [PERFORM_FULL_AUTH] => match credentials.server_public_key {
+            Some(pem) => conn.write_packet(&encrypt_password(credentials.password, challenge, pem)?),
+            None => conn.write_packet(&[REQUEST_PUBLIC_KEY]),
+        },
+        pem => {
+            let pem = std::str::from_utf8(pem).map_err(|_| ClientError::BadPacket("public key"))?;
+            conn.write_packet(&encrypt_password(credentials.password, challenge, pem)?)
+        }
+    }
+}
+
+// The first answer to the server's challenge for `plugin`, sent in the handshake response or
+// after an AuthSwitchRequest. An empty password is always an empty answer.
+pub(crate) fn auth_response(plugin: &str, password: &str, challenge: &[u8]) -> Result<Vec<u8>, ClientError> {
+    if password.is_empty() {
+        return Ok(vec![]);
+    }
+    match plugin {
+        MYSQL_NATIVE_PASSWORD => Ok(scramble_native_password(password, challenge)),
+        CACHING_SHA2_PASSWORD => Ok(scramble_caching_sha2(password, challenge)),
+        _ => Err(ClientError::UnsupportedAuthPlugin(plugin.to_owned())),
+    }
+}
+
+// SHA1(password) XOR SHA1(challenge + SHA1(SHA1(password)))
+// https://dev.mysql.com/doc/dev/mysql-server/latest/page_protocol_connection_phase_authentication_methods_native_password_authentication.html
+fn scramble_native_password(password: &str, challenge: &[u8]) -> Vec<u8> {
+    let stage1 = Sha1::digest(password.as_bytes());
+    let stage2 = Sha1::digest(stage1);
+    let mut hasher = Sha1::new();
+    hasher.update(challenge);
+    hasher.update(stage2);
+    let mask = hasher.finalize();
+    stage1.iter().zip(mask.iter()).map(|(a, b)| a ^ b).collect()
+}
+
+// SHA256(password) XOR SHA256(SHA256(SHA256(password)) + challenge)
+fn scramble_caching_sha2(password: &str, challenge: &[u8]) -> Vec<u8> {
+    let stage1 = Sha256::digest(password.as_bytes());
+    let stage2 = Sha256::digest(stage1);
+    let mut hasher = Sha256::new();
+    hasher.update(stage2);
+    hasher.update(challenge);
+    let mask = hasher.finalize();
+    stage1.iter().zip(mask.iter()).map(|(a, b)| a ^ b).collect()
+}
+
+// the NUL-terminated password XORed with the repeated challenge, then RSA-OAEP encrypted
+fn encrypt_password(password: &str, challenge: &[u8], pem: &str) -> Result<Vec<u8>, ClientError> {
+    // servers send PKCS#8; keys copied from an older server's files may be PKCS#1
+    let key = RsaPublicKey::from_public_key_pem(pem)
+        .or_else(|_| RsaPublicKey::from_pkcs1_pem(pem))
+        .map_err(|e| ClientError::PublicKey(e.to_string()))?;
+    let mut plain = password.as_bytes().to_vec();
+    plain.push(0);
+    if !challenge.is_empty() {
+        for (i, b) in plain.iter_mut().enumerate() {
+            *b ^= challenge[i % challenge.len()];
+        }
+    }
+    key.encrypt(&mut rand_core::OsRng, Oaep::new::<Sha1>(), &plain)
+        .map_err(|e| ClientError::PublicKey(e.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::auth::{auth_response, CACHING_SHA2_PASSWORD, MYSQL_NATIVE_PASSWORD};
+    use crate::errors::ClientError;
+
+    fn hex(bytes: &[u8]) -> String {
+        bytes.iter().map(|b| format!("{:02x}", b)).collect()
+    }
+
+    #[test]
+    fn test_native_password_scramble() {
+        //given
+        let challenge: Vec<u8> = (1..=20).collect();
+
+        //when
+        let scrambled = auth_response(MYSQL_NATIVE_PASSWORD, "secret", &challenge).unwrap();
+        let empty = auth_response(MYSQL_NATIVE_PASSWORD, "", &challenge).unwrap();
+        let unknown = auth_response("authentication_kerberos_client", "secret", &challenge);
+
+        //then
+        assert_eq!(hex(&scrambled), "b32bb3a583e1340c0a1108d58b1be49781ad8c2f");
+        assert!(empty.is_empty());
+        assert!(matches!(unknown, Err(ClientError::UnsupportedAuthPlugin(_))));
+    }
+
+    #[test]
+    fn test_caching_sha2_scramble() {
+        //given
+        let challenge: Vec<u8> = (1..=20).collect();
+
+        //when
+        let scrambled = auth_response(CACHING_SHA2_PASSWORD, "secret", &challenge).unwrap();
+
+        //then
+        assert_eq!(hex(&scrambled), "746ebe205d56a0707acb3e796e834e0dd7b1d61743b26bd5202c7a623230c7c9");
+    }
+}
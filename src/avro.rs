@@ -0,0 +1,451 @@
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::io::{Read, Write};
+use std::net::TcpStream;
+use serde_json::{json, Value as Json};
+use crate::column::ColumnType;
+use crate::errors::ExportError;
+use crate::event::{Event, EventData};
+use crate::export::plain_json;
+use crate::rows::{RowImage, RowsEventKind};
+use crate::table_map::{TableMapColumn, TableMapEvent};
+use crate::utils::{days_from_civil, unscaled_decimal};
+use crate::value::Value;
+
+// Confluent's wire format: this byte, the schema id (u32 big endian), then the Avro datum
+const CONFLUENT_MAGIC: u8 = 0;
+
+// How a column is written, derived from its table map type; every field is a union with null.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum AvroType {
+    Boolean,
+    Int,
+    Long,
+    Float,
+    Double,
+    String,
+    Bytes,
+    // days since the epoch
+    Date,
+    // microseconds since midnight, negative and beyond 24h for MySQL's TIME
+    TimeMicros,
+    // DATETIME, which has no time zone
+    LocalTimestampMicros,
+    // TIMESTAMP, UTC
+    TimestampMicros,
+    // two's complement unscaled value; precisions over 38 digits are written as strings
+    Decimal { precision: u8, scale: u8 },
+}
+
+impl AvroType {
+    fn of(column: &TableMapColumn) -> Self {
+        match column.real_type() {
+            ColumnType::Tiny | ColumnType::Short | ColumnType::Int24 | ColumnType::Year => AvroType::Int,
+            ColumnType::Long if column.unsigned => AvroType::Long,
+            ColumnType::Long => AvroType::Int,
+            ColumnType::LongLong if column.unsigned => AvroType::Decimal { precision: 20, scale: 0 },
+            ColumnType::LongLong => AvroType::Long,
+            ColumnType::Float => AvroType::Float,
+            ColumnType::Double => AvroType::Double,
+            ColumnType::Decimal | ColumnType::NewDecimal => {
+                let (precision, scale) = ((column.meta >> 8) as u8, (column.meta & 0xff) as u8);
+                if precision <= 38 { AvroType::Decimal { precision, scale } } else { AvroType::String }
+            }
+            ColumnType::Date | ColumnType::NewDate => AvroType::Date,
+            ColumnType::Time | ColumnType::Time2 => AvroType::TimeMicros,
+            ColumnType::DateTime | ColumnType::DateTime2 => AvroType::LocalTimestampMicros,
+            ColumnType::Timestamp | ColumnType::Timestamp2 => AvroType::TimestampMicros,
+            ColumnType::Bit if column.meta == 1 => AvroType::Boolean,
+            ColumnType::Bit => AvroType::Long,
+            ColumnType::Json | ColumnType::Geometry => AvroType::Bytes,
+            t if t.is_blob() && column.charset.is_some_and(|c| c != 63) => AvroType::String,
+            t if t.is_blob() => AvroType::Bytes,
+            _ => AvroType::String,
+        }
+    }
+
+    fn schema(self) -> Json {
+        match self {
+            AvroType::Boolean => json!("boolean"),
+            AvroType::Int => json!("int"),
+            AvroType::Long => json!("long"),
+            AvroType::Float => json!("float"),
+            AvroType::Double => json!("double"),
+            AvroType::String => json!("string"),
+            AvroType::Bytes => json!("bytes"),
+            AvroType::Date => json!({"type": "int", "logicalType": "date"}),
+            AvroType::TimeMicros => json!({"type": "long", "logicalType": "time-micros"}),
+            AvroType::LocalTimestampMicros => json!({"type": "long", "logicalType": "local-timestamp-micros"}),
+            AvroType::TimestampMicros => json!({"type": "long", "logicalType": "timestamp-micros"}),
+            AvroType::Decimal { precision, scale } => json!({"type": "bytes", "logicalType": "decimal", "precision": precision, "scale": scale}),
+        }
+    }
+}
+
+// Where schemas are registered to get the id written before each datum.
+pub trait SchemaRegistry {
+    // the id of `schema` under `subject`, registering it if it's new
+    fn register(&mut self, subject: &str, schema: &str) -> Result<u32, ExportError>;
+}
+
+// A Confluent-compatible schema registry over plain HTTP, e.g. "http://localhost:8081".
+#[derive(Debug, Clone)]
+pub struct HttpSchemaRegistry {
+    host: String,
+    // subject and schema -> id, to register each schema once
+    ids: HashMap<(String, String), u32>,
+}
+
+impl HttpSchemaRegistry {
+    pub fn new(url: &str) -> Self {
+        let host = url.strip_prefix("http://").unwrap_or(url).trim_end_matches('/').to_owned();
+        HttpSchemaRegistry { host, ids: HashMap::new() }
+    }
+}
+
+impl SchemaRegistry for HttpSchemaRegistry {
+    fn register(&mut self, subject: &str, schema: &str) -> Result<u32, ExportError> {
+        let key = (subject.to_owned(), schema.to_owned());
+        if let Some(id) = self.ids.get(&key) {
+            return Ok(*id);
+        }
+        let body = serde_json::to_string(&json!({ "schema": schema }))?;
+        // HTTP/1.0 so the answer is neither chunked nor kept alive
+        let request = format!(
+            "POST /subjects/{}/versions HTTP/1.0\r\nHost: {}\r\nContent-Type: application/vnd.schemaregistry.v1+json\r\nContent-Length: {}\r\n\r\n{}",
+            subject, self.host, body.len(), body
+        );
+        let mut stream = TcpStream::connect(&self.host)?;
+        stream.write_all(request.as_bytes())?;
+        let mut response = String::new();
+        stream.read_to_string(&mut response)?;
+        let (head, body) = response.split_once("\r\n\r\n").unwrap_or((&response, ""));
+        let ok = head.split_whitespace().nth(1).is_some_and(|status| status.starts_with('2'));
+        let answer: Json = serde_json::from_str(body).unwrap_or_default();
+        match answer["id"].as_u64() {
+            Some(id) if ok => {
+                self.ids.insert(key, id as u32);
+                Ok(id as u32)
+            }
+            _ => Err(ExportError::SchemaRegistry(answer["message"].as_str().unwrap_or(head).to_owned())),
+        }
+    }
+}
+
+// A rows event's changes in Avro binary encoding, one datum per row, written against `schema`
+#[derive(Debug, Clone, PartialEq)]
+pub struct AvroRecord {
+    // <schema>.<table>-value
+    pub subject: String,
+    pub schema: String,
+    // prefixed with the magic byte and schema id when a registry is used
+    pub payload: Vec<u8>,
+}
+
+// Turns row changes into Avro records of this shape, with one schema per table (and per
+// layout, as the table changes), named after the table in the database's namespace:
+//
+//     {"type": "record", "name": "Envelope", "namespace": "<schema>.<table>", "fields": [
+//         {"name": "before", "type": ["null", <the row record>], "default": null},
+//         {"name": "after", "type": ["null", <the row record>], "default": null},
+//         {"name": "op", "type": "string"}, // c, u or d
+//         {"name": "ts_ms", "type": "long"},
+//         {"name": "offset", "type": "long"}]}
+//
+// Column values are typed from the table map: DATE, TIME, DATETIME and TIMESTAMP as the matching
+// logical types in microseconds, DECIMAL as the decimal logical type, BIGINT UNSIGNED as
+// decimal(20, 0), binary and JSON columns as bytes, ENUM and SET as their labels.
+pub struct AvroEncoder {
+    tables: HashMap<u64, TableMapEvent>,
+    registry: Option<Box<dyn SchemaRegistry>>,
+}
+
+impl Default for AvroEncoder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl AvroEncoder {
+    pub fn new() -> Self {
+        AvroEncoder { tables: HashMap::new(), registry: None }
+    }
+
+    // register schemas with `registry` and prefix payloads with their ids
+    pub fn schema_registry<S: SchemaRegistry + 'static>(mut self, registry: S) -> Self {
+        self.registry = Some(Box::new(registry));
+        self
+    }
+
+    pub fn encode(&mut self, event: &Event, data: &EventData) -> Result<Vec<AvroRecord>, ExportError> {
+        let rows = match data {
+            EventData::TableMapEvent(table_map) => {
+                self.tables.insert(table_map.table_id, table_map.clone());
+                return Ok(vec![]);
+            }
+            EventData::WriteRowsEvent(rows) | EventData::UpdateRowsEvent(rows) | EventData::DeleteRowsEvent(rows) => rows,
+            _ => return Ok(vec![]),
+        };
+        let table_map = match self.tables.get(&rows.table_id) {
+            Some(table_map) => table_map,
+            None => return Ok(vec![]),
+        };
+        let subject = format!("{}.{}-value", table_map.schema, table_map.table);
+        let schema = table_schema(table_map).to_string();
+        let prefix = match &mut self.registry {
+            Some(registry) => {
+                let id = registry.register(&subject, &schema)?;
+                let mut prefix = vec![CONFLUENT_MAGIC];
+                prefix.extend_from_slice(&id.to_be_bytes());
+                prefix
+            }
+            None => vec![],
+        };
+        let op = match rows.kind {
+            RowsEventKind::Write => "c",
+            RowsEventKind::Update => "u",
+            RowsEventKind::Delete => "d",
+        };
+        rows.rows.iter().map(|change| {
+            let mut payload = prefix.clone();
+            for image in [&change.before, &change.after] {
+                match image {
+                    Some(image) => {
+                        write_long(&mut payload, 1);
+                        write_row(&mut payload, image, table_map)?;
+                    }
+                    None => write_long(&mut payload, 0),
+                }
+            }
+            write_bytes(&mut payload, op.as_bytes());
+            write_long(&mut payload, i64::from(event.timestamp()) * 1000);
+            write_long(&mut payload, event.offset() as i64);
+            Ok(AvroRecord { subject: subject.clone(), schema: schema.clone(), payload })
+        }).collect()
+    }
+}
+
+// the envelope schema of a table's row changes, see AvroEncoder
+pub fn table_schema(table_map: &TableMapEvent) -> Json {
+    let fields: Vec<Json> = table_map.columns.iter().enumerate()
+        .map(|(i, column)| json!({
+            "name": field_name(table_map, i),
+            "type": ["null", AvroType::of(column).schema()],
+            "default": null,
+        }))
+        .collect();
+    let row = json!({"type": "record", "name": avro_name(&table_map.table), "fields": fields});
+    json!({
+        "type": "record",
+        "name": "Envelope",
+        "namespace": format!("{}.{}", avro_name(&table_map.schema), avro_name(&table_map.table)),
+        "fields": [
+            {"name": "before", "type": ["null", row], "default": null},
+            {"name": "after", "type": ["null", avro_name(&table_map.table)], "default": null},
+            {"name": "op", "type": "string"},
+            {"name": "ts_ms", "type": "long"},
+            {"name": "offset", "type": "long"},
+        ],
+    })
+}
+
+// Avro names are [A-Za-z_][A-Za-z0-9_]*
+fn avro_name(name: &str) -> String {
+    let mut avro: String = name.chars().map(|c| if c.is_ascii_alphanumeric() { c } else { '_' }).collect();
+    if !avro.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_') {
+        avro.insert(0, '_');
+    }
+    avro
+}
+
+fn field_name(table_map: &TableMapEvent, index: usize) -> String {
+    match table_map.columns.get(index).and_then(|c| c.name.as_ref()) {
+        Some(name) => avro_name(name),
+        None => format!("_{}", index + 1),
+    }
+}
+
+fn write_row(out: &mut Vec<u8>, image: &RowImage, table_map: &TableMapEvent) -> Result<(), ExportError> {
+    for (i, column) in table_map.columns.iter().enumerate() {
+        match image.get(i) {
+            // left out of a MINIMAL image, or NULL
+            None | Some(Value::Null) => write_long(out, 0),
+            Some(value) => {
+                write_long(out, 1);
+                write_value(out, AvroType::of(column), value, column)
+                    .ok_or_else(|| ExportError::UnexpectedValue { column: field_name(table_map, i) })?;
+            }
+        }
+    }
+    Ok(())
+}
+
+fn write_value(out: &mut Vec<u8>, avro_type: AvroType, value: &Value, column: &TableMapColumn) -> Option<()> {
+    match (avro_type, value) {
+        (AvroType::Boolean, Value::Bit(v)) => out.push(u8::from(v.iter().any(|b| *b != 0))),
+        (AvroType::Int | AvroType::Long, Value::Int(v)) => write_long(out, *v),
+        (AvroType::Int | AvroType::Long, Value::UInt(v)) => write_long(out, i64::try_from(*v).ok()?),
+        (AvroType::Int, Value::Year(v)) => write_long(out, i64::from(*v)),
+        (AvroType::Long, Value::Bit(v)) => write_long(out, v.iter().take(8).fold(0u64, |n, b| n << 8 | u64::from(*b)) as i64),
+        (AvroType::Float, Value::Float(v)) => out.extend_from_slice(&v.to_le_bytes()),
+        (AvroType::Double, Value::Double(v)) => out.extend_from_slice(&v.to_le_bytes()),
+        (AvroType::Decimal { scale, .. }, Value::Decimal(v)) => write_bytes(out, &decimal_bytes(v, scale)?),
+        (AvroType::Decimal { .. }, Value::UInt(v)) => write_bytes(out, &twos_complement(i128::from(*v))),
+        (AvroType::Date, Value::Date { year, month, day }) => write_long(out, days_from_civil(i64::from(*year), *month, *day)),
+        (AvroType::TimeMicros, Value::Time { negative, hours, minutes, seconds, micros }) => {
+            let micros = ((i64::from(*hours) * 60 + i64::from(*minutes)) * 60 + i64::from(*seconds)) * 1_000_000 + i64::from(*micros);
+            write_long(out, if *negative { -micros } else { micros });
+        }
+        (AvroType::LocalTimestampMicros, Value::DateTime { year, month, day, hour, minute, second, micros }) => {
+            let seconds = days_from_civil(i64::from(*year), *month, *day) * 86400
+                + i64::from(*hour) * 3600 + i64::from(*minute) * 60 + i64::from(*second);
+            write_long(out, seconds * 1_000_000 + i64::from(*micros));
+        }
+        (AvroType::TimestampMicros, Value::Timestamp { seconds, micros }) => {
+            write_long(out, i64::from(*seconds) * 1_000_000 + i64::from(*micros));
+        }
+        (AvroType::Bytes, Value::Bytes(v) | Value::Json(v)) => write_bytes(out, v),
+        (AvroType::String, Value::Bytes(v)) => write_bytes(out, v),
+        (AvroType::String, Value::Enum(index)) => {
+            let label = column.enum_values.as_ref()
+                .and_then(|labels| labels.get((*index as usize).wrapping_sub(1)).cloned())
+                .unwrap_or_else(|| index.to_string());
+            write_bytes(out, label.as_bytes());
+        }
+        (AvroType::String, Value::Set(bits)) => {
+            let labels = match &column.set_values {
+                Some(labels) => labels.iter().enumerate()
+                    .filter(|(i, _)| *i < 64 && bits & (1 << i) != 0)
+                    .map(|(_, l)| l.as_str())
+                    .collect::<Vec<_>>()
+                    .join(","),
+                None => bits.to_string(),
+            };
+            write_bytes(out, labels.as_bytes());
+        }
+        (AvroType::String, value) => match plain_json(value) {
+            Json::String(s) => write_bytes(out, s.as_bytes()),
+            other => write_bytes(out, other.to_string().as_bytes()),
+        },
+        _ => return None,
+    }
+    Some(())
+}
+
+// the unscaled value of a DECIMAL's text, as the decimal logical type wants it
+fn decimal_bytes(text: &str, scale: u8) -> Option<Vec<u8>> {
+    Some(twos_complement(unscaled_decimal(text, scale)?))
+}
+
+// big endian, in as few bytes as keep the sign
+fn twos_complement(v: i128) -> Vec<u8> {
+    let bytes = v.to_be_bytes();
+    let mut start = 0;
+    while start < 15 {
+        let redundant = (bytes[start] == 0x00 && bytes[start + 1] & 0x80 == 0) || (bytes[start] == 0xff && bytes[start + 1] & 0x80 != 0);
+        if !redundant {
+            break;
+        }
+        start += 1;
+    }
+    bytes[start..].to_vec()
+}
+
+// ints and longs are zigzag varints
+fn write_long(out: &mut Vec<u8>, v: i64) {
+    let mut n = ((v << 1) ^ (v >> 63)) as u64;
+    while n >= 0x80 {
+        out.push((n as u8) | 0x80);
+        n >>= 7;
+    }
+    out.push(n as u8);
+}
+
+fn write_bytes(out: &mut Vec<u8>, bytes: &[u8]) {
+    write_long(out, bytes.len() as i64);
+    out.extend_from_slice(bytes);
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+    use crate::avro::{decimal_bytes, table_schema, write_long, AvroEncoder, HttpSchemaRegistry};
+    use crate::context::ParseContext;
+    use crate::event::{Event, EventData};
+    use crate::event::tests::{parse_events, users_rows, users_table_map};
+
+    // test.users (id INT, created DATE) with column names, and one row inserted
+    fn users_insert() -> Vec<Event> {
+        let table_map = users_table_map(&[3, 10], &[], 0x00, b"\x04\x0b\x02id\x07created");
+        let write_rows = users_rows(2, &[0x03, 0x00, 7, 0, 0, 0, 0x3f, 0xd0, 0x0f]);
+        parse_events(16, &[(19, table_map), (30, write_rows)])
+    }
+
+    #[test]
+    fn test_encode_row_changes() {
+        //given
+        let events = users_insert();
+        let mut ctx = ParseContext::new();
+        let table_map = match events[0].parsed(&mut ctx).unwrap() {
+            EventData::TableMapEvent(table_map) => table_map,
+            data => panic!("{:?}", data),
+        };
+        let mut encoder = AvroEncoder::new();
+
+        //when
+        let records: Vec<_> = events.iter().flat_map(|e| encoder.encode(e, &e.parsed(&mut ctx).unwrap()).unwrap()).collect();
+
+        //then
+        let schema = table_schema(&table_map);
+        assert_eq!(schema["namespace"], "test.users");
+        assert_eq!(schema["fields"][0]["type"][1]["fields"][1]["type"][1], serde_json::json!({"type": "int", "logicalType": "date"}));
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].subject, "test.users-value");
+        assert_eq!(records[0].schema, schema.to_string());
+        let mut expected = vec![0, 2, 2, 14, 2];
+        write_long(&mut expected, 19753);
+        expected.extend_from_slice(&[2, b'c']);
+        write_long(&mut expected, 16000);
+        write_long(&mut expected, 4);
+        assert_eq!(records[0].payload, expected);
+    }
+
+    #[test]
+    fn test_decimal_bytes() {
+        //given
+        let values = [("1.5", 2), ("-0.01", 2), ("127", 0), ("128", 0)];
+
+        //when
+        let bytes: Vec<_> = values.iter().map(|(v, scale)| decimal_bytes(v, *scale).unwrap()).collect();
+
+        //then
+        assert_eq!(bytes, vec![vec![0, 150], vec![0xff], vec![127], vec![0, 128]]);
+        assert_eq!(decimal_bytes("1.234", 2), None);
+    }
+
+    #[test]
+    fn test_schema_registry_ids_prefix_payloads() {
+        //given
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let server = std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut request = vec![0u8; 4096];
+            let n = stream.read(&mut request).unwrap();
+            stream.write_all(b"HTTP/1.0 200 OK\r\nContent-Type: application/json\r\n\r\n{\"id\":5}").unwrap();
+            String::from_utf8_lossy(&request[..n]).into_owned()
+        });
+        let events = users_insert();
+        let mut ctx = ParseContext::new();
+        let mut encoder = AvroEncoder::new().schema_registry(HttpSchemaRegistry::new(&url));
+
+        //when
+        let records: Vec<_> = events.iter().flat_map(|e| encoder.encode(e, &e.parsed(&mut ctx).unwrap()).unwrap()).collect();
+        let request = server.join().unwrap();
+
+        //then
+        assert!(request.starts_with("POST /subjects/test.users-value/versions HTTP/1.0\r\n"), "{}", request);
+        assert_eq!(records[0].payload[..6], [0, 0, 0, 0, 5, 0]);
+    }
+}
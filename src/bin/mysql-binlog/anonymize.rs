@@ -0,0 +1,102 @@
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use clap::Args;
+use mysql_binlog_parser_rust::anonymize::{Anonymizer, Mask};
+use crate::input;
+
+#[derive(Args)]
+pub(crate) struct AnonymizeArgs {
+    /// The binlog file to anonymize; - reads standard input
+    file: PathBuf,
+    /// DB.TABLE.COLUMN=hash, =fake or =fixed:TEXT; repeatable, DB and TABLE may have % and _
+    /// wildcards, and COLUMN is @1, @2... for tables logged without column names
+    #[arg(long = "mask", value_name = "RULE", value_parser = parse_mask, required = true)]
+    masks: Vec<(String, String, Mask)>,
+    /// Mixed into every mask, so values can't be recovered by masking guesses the same way
+    #[arg(long, default_value = "")]
+    salt: String,
+    /// Where to write the anonymized binlog; - is standard output
+    #[arg(short, long, value_name = "FILE", default_value = "-")]
+    output: PathBuf,
+}
+
+// Writes the binlog with the masked columns' values replaced in place. To share it as JSON or
+// SQL instead, pipe it to `mysql-binlog dump -`.
+pub(crate) fn run(args: &AnonymizeArgs, out: &mut dyn Write) -> crate::Result<()> {
+    let mut anonymizer = Anonymizer::new().salt(args.salt.as_bytes());
+    for (table, column, mask) in &args.masks {
+        anonymizer = anonymizer.mask(table, column, mask.clone());
+    }
+    let mut file;
+    let writer: &mut dyn Write = if args.output == Path::new("-") {
+        out
+    } else {
+        file = BufWriter::new(File::create(&args.output)?);
+        &mut file
+    };
+    writer.write_all(b"\xfebin")?;
+    let mut ctx = crate::parse_context();
+    for event in input::events(&args.file) {
+        let event = event?;
+        writer.write_all(&anonymizer.anonymize(&event, &event.parsed(&mut ctx)?)?)?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+// shop.users.email=fixed:x@example.com -> ("shop.users", "email", Mask::Fixed("x@example.com"))
+fn parse_mask(text: &str) -> Result<(String, String, Mask), String> {
+    let bad = || format!("expected DB.TABLE.COLUMN=hash, =fake or =fixed:TEXT, got {:?}", text);
+    let (name, mask) = text.split_once('=').ok_or_else(bad)?;
+    let (table, column) = name.rsplit_once('.').filter(|(table, _)| table.contains('.')).ok_or_else(bad)?;
+    let mask = match mask.split_once(':') {
+        Some(("fixed", fixed)) => Mask::Fixed(fixed.to_owned()),
+        _ if mask == "hash" => Mask::Hash,
+        _ if mask == "fake" => Mask::Fake,
+        _ => return Err(bad()),
+    };
+    Ok((table.to_owned(), column.to_owned(), mask))
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::Parser;
+    use mysql_binlog_parser_rust::anonymize::Mask;
+    use crate::{Cli, Command};
+    use super::parse_mask;
+
+    #[test]
+    fn test_anonymize() {
+        //given
+        let path = std::env::temp_dir().join(format!("mysql-binlog-anonymize-{}", std::process::id()));
+        let path_arg = path.to_str().unwrap();
+        let cli = Cli::parse_from(["mysql-binlog", "anonymize", "--mask", "test.users.@2=fixed:x", "-o", path_arg, "tests/asset/mysql-bin.100746"]);
+
+        //when
+        match &cli.command {
+            Command::Anonymize(args) => super::run(args, &mut vec![]).unwrap(),
+            _ => unreachable!(),
+        }
+        let cli = Cli::parse_from(["mysql-binlog", "dump", "-v", path_arg]);
+        let mut out = vec![];
+        match &cli.command {
+            Command::Dump(args) => crate::dump::run(args, &mut out).unwrap(),
+            _ => unreachable!(),
+        }
+        std::fs::remove_file(&path).unwrap();
+
+        //then
+        let text = String::from_utf8(out).unwrap();
+        assert!(!text.contains("'alice'"));
+        assert!(text.contains("###   @2='x    '"), "{}", text);
+    }
+
+    #[test]
+    fn test_parse_mask() {
+        assert_eq!(parse_mask("shop.users.email=hash"), Ok(("shop.users".to_owned(), "email".to_owned(), Mask::Hash)));
+        assert_eq!(parse_mask("%.%.phone=fixed:0:0"), Ok(("%.%".to_owned(), "phone".to_owned(), Mask::Fixed("0:0".to_owned()))));
+        assert!(parse_mask("users.email=fake").is_err());
+        assert!(parse_mask("shop.users.email=scramble").is_err());
+    }
+}
@@ -0,0 +1,78 @@
+use std::convert::TryFrom;
+
+// `YYYY-MM-DD`, `YYYY-MM-DD HH:MM` or `YYYY-MM-DD HH:MM:SS`, with a space or a T, as seconds
+// since the epoch
+pub(crate) fn parse_datetime(text: &str) -> Result<u32, String> {
+    let invalid = || format!("expected YYYY-MM-DD HH:MM:SS, got {:?}", text);
+    let (date, time) = text.split_once([' ', 'T']).unwrap_or((text, "00:00:00"));
+    let date: Vec<i64> = date.split('-').map(str::parse).collect::<Result<_, _>>().map_err(|_| invalid())?;
+    let time: Vec<i64> = time.split(':').map(str::parse).collect::<Result<_, _>>().map_err(|_| invalid())?;
+    let (year, month, day) = match date[..] {
+        [year, month, day] if (1..=12).contains(&month) && (1..=31).contains(&day) => (year, month, day),
+        _ => return Err(invalid()),
+    };
+    let (hour, minute, second) = match time[..] {
+        [hour, minute] => (hour, minute, 0),
+        [hour, minute, second] => (hour, minute, second),
+        _ => return Err(invalid()),
+    };
+    if hour > 23 || minute > 59 || second > 59 {
+        return Err(invalid());
+    }
+    let seconds = days_from_civil(year, month, day) * 86400 + hour * 3600 + minute * 60 + second;
+    u32::try_from(seconds).map_err(|_| format!("{:?} is outside the range of binlog timestamps", text))
+}
+
+// `YYYY-MM-DD HH:MM:SS` in UTC
+pub(crate) fn format_datetime(seconds: u32) -> String {
+    let seconds = i64::from(seconds);
+    let (year, month, day) = civil_from_days(seconds.div_euclid(86400));
+    let time = seconds.rem_euclid(86400);
+    format!("{:04}-{:02}-{:02} {:02}:{:02}:{:02}", year, month, day, time / 3600, time / 60 % 60, time % 60)
+}
+
+// days since 1970-01-01 in the proleptic Gregorian calendar, and back
+// http://howardhinnant.github.io/date_algorithms.html
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let y = if month <= 2 { year - 1 } else { year };
+    let era = y.div_euclid(400);
+    let yoe = y - era * 400;
+    let doy = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146097 + doe - 719468
+}
+
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    (if month <= 2 { yoe + era * 400 + 1 } else { yoe + era * 400 }, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::datetime::{format_datetime, parse_datetime};
+
+    #[test]
+    fn test_parse_datetime() {
+        //given
+        let texts = ["2021-10-01 00:00:00", "2021-10-01T09:30", "2021-10-01", "2000-02-29 23:59:59"];
+
+        //when
+        let parsed: Vec<_> = texts.iter().map(|text| parse_datetime(text)).collect();
+
+        //then
+        assert_eq!(parsed, [Ok(1633046400), Ok(1633080600), Ok(1633046400), Ok(951868799)]);
+        assert!(parse_datetime("2021-13-01").is_err());
+        assert!(parse_datetime("2021-10-01 24:00").is_err());
+        assert!(parse_datetime("1969-12-31 23:59:59").is_err());
+        assert!(parse_datetime("yesterday").is_err());
+        assert_eq!(format_datetime(1633080600), "2021-10-01 09:30:00");
+        assert_eq!(format_datetime(951868799), "2000-02-29 23:59:59");
+    }
+}
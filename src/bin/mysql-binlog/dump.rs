@@ -0,0 +1,153 @@
+use std::io::Write;
+use std::path::PathBuf;
+use clap::Args;
+use crate::filter::FilterArgs;
+use crate::output::OutputArgs;
+use crate::remote::RemoteArgs;
+
+#[derive(Args)]
+pub(crate) struct DumpArgs {
+    /// Binlog files, read in the order given; - reads standard input. With --host, the names
+    /// of the server's binlogs to read, all of them from the oldest when none are given
+    #[arg(required_unless_present = "host")]
+    files: Vec<PathBuf>,
+    #[command(flatten)]
+    remote: RemoteArgs,
+    /// With --host, wait for new events at the end instead of exiting
+    #[arg(long, requires = "host")]
+    stop_never: bool,
+    #[command(flatten)]
+    filter: FilterArgs,
+    #[command(flatten)]
+    output: OutputArgs,
+}
+
+pub(crate) fn run(args: &DumpArgs, out: &mut dyn Write) -> crate::Result<()> {
+    if args.remote.is_remote() {
+        return args.output.write(args.remote.events(&args.filter, &args.files, args.stop_never)?, "", out);
+    }
+    let file = args.files[0].file_name().map_or_else(Default::default, |name| name.to_string_lossy());
+    args.output.write(args.filter.events(&args.files)?, &file, out)
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::Parser;
+    use crate::{Cli, Command};
+
+    fn try_dump(args: &[&str]) -> crate::Result<String> {
+        let cli = Cli::parse_from([&["mysql-binlog", "dump"], args].concat());
+        let mut out = vec![];
+        match &cli.command {
+            Command::Dump(args) => super::run(args, &mut out)?,
+            _ => unreachable!(),
+        }
+        Ok(String::from_utf8(out)?)
+    }
+
+    fn dump(args: &[&str]) -> String {
+        try_dump(args).unwrap()
+    }
+
+    #[test]
+    fn test_dump_text_and_json() {
+        //given
+        let path = "tests/asset/mysql-bin.100746";
+
+        //when
+        let text = dump(&["-vv", path]);
+        let hexdump = dump(&["--hexdump", path]);
+        let json = dump(&["--format", "json", path]);
+
+        //then
+        assert!(text.starts_with("/*!50530 SET @@SESSION.PSEUDO_SLAVE_MODE=1*/;\n"));
+        assert!(text.contains("\n# at 4\n"));
+        assert!(text.ends_with("# End of log file\n/*!50003 SET COMPLETION_TYPE=@OLD_COMPLETION_TYPE*/;\n/*!50530 SET @@SESSION.PSEUDO_SLAVE_MODE=0*/;\n"));
+        assert!(!text.contains("# Position  Timestamp"));
+        assert!(hexdump.contains("# at 4\n#211001  0:00:00 server id 1  end_log_pos 123 CRC32 0x2224b0c9 \n# Position  Timestamp"));
+        assert_eq!(json.lines().count(), 15);
+        assert!(json.lines().all(|line| serde_json::from_str::<serde_json::Value>(line).is_ok()));
+    }
+
+    #[test]
+    fn test_dump_sql_debezium_and_maxwell() {
+        //given
+        let path = "tests/asset/mysql-bin.100746";
+
+        //when
+        let sql = dump(&["--format", "sql", path]);
+        let debezium = dump(&["--format", "debezium", "--topic-prefix", "dbserver1", path]);
+        let maxwell = dump(&["--format", "maxwell", path]);
+
+        //then
+        assert_eq!(sql.lines().count(), 3);
+        assert!(sql.starts_with("INSERT INTO `test`.`users` (`id`, `name`, `age`) "), "{}", sql);
+        assert_eq!(debezium.lines().count(), 3);
+        let record: serde_json::Value = serde_json::from_str(debezium.lines().next().unwrap()).unwrap();
+        assert_eq!(record["topic"], "dbserver1.test.users");
+        assert_eq!(record["value"]["source"]["file"], "mysql-bin.100746");
+        // named from the CREATE TABLE, as the binlog has no column names of its own
+        assert_eq!(record["value"]["after"]["name"], "alice");
+        assert_eq!(maxwell.lines().count(), 3);
+        let records: Vec<serde_json::Value> = maxwell.lines().map(|line| serde_json::from_str(line).unwrap()).collect();
+        assert_eq!(records[0]["data"]["name"], "alice");
+        assert_eq!(records[2]["old"]["name"], "bob");
+    }
+
+    #[test]
+    fn test_dump_database_and_table_filters() {
+        //given
+        let path = "tests/asset/mysql-bin.100746";
+
+        //when
+        let users = dump(&["-v", "--table", "users", path]);
+        let other_table = dump(&["-v", "--table", "test.orders", path]);
+        let other_database = dump(&["-v", "-d", "shop%", path]);
+
+        //then
+        assert!(users.contains("### INSERT INTO `test`.`users`"));
+        assert!(users.contains("CREATE TABLE `users`"));
+        assert!(!other_table.contains("Table_map"));
+        assert!(other_table.contains("CREATE TABLE `users`"));
+        assert!(!other_database.contains("CREATE TABLE `users`"));
+        assert!(!other_database.contains("Table_map"));
+        assert!(other_database.contains("Xid = 42"));
+    }
+
+    #[test]
+    fn test_dump_datetime_window() {
+        //given
+        let path = "tests/asset/mysql-bin.100746";
+
+        //when
+        let all = dump(&["--format", "json", "--start-datetime", "2021-10-01 00:00:00", path]);
+        let after = dump(&["--format", "json", "--start-datetime", "2021-10-01 00:00:01", path, path]);
+        let before = dump(&["--format", "json", "--stop-datetime", "2021-10-01", path, path]);
+
+        //then
+        assert_eq!(all.lines().count(), 15);
+        assert_eq!(after.lines().count(), 2);
+        assert!(after.lines().all(|line| line.contains("FormatDescriptionEvent")));
+        assert_eq!(before.lines().count(), 1);
+    }
+
+    #[test]
+    fn test_dump_position_window() {
+        //given
+        let path = "tests/asset/mysql-bin.100746";
+
+        //when
+        let transaction = dump(&["-v", "--start-position", "740", "--stop-position", "1017", path]);
+        let first_file = dump(&["--format", "json", "--stop-position", "219", path, path]);
+        let error = try_dump(&["--start-position", "600", path]).unwrap_err().to_string();
+
+        //then
+        assert!(transaction.contains("# at 4\n"));
+        assert!(!transaction.contains("# at 709\n"));
+        assert!(transaction.contains("# at 740\n"));
+        assert!(transaction.contains("### UPDATE `test`.`users`"));
+        assert!(!transaction.contains("# at 1017\n"));
+        assert_eq!(first_file.lines().count(), 15 + 3);
+        assert!(error.contains("the events around it start at 529 and 601"), "{}", error);
+    }
+}
@@ -0,0 +1,190 @@
+use std::path::{Path, PathBuf};
+use clap::Args;
+use mysql_binlog_parser_rust::errors::BinlogFileError;
+use mysql_binlog_parser_rust::event::{Event, TypeCode};
+use mysql_binlog_parser_rust::filter::{EventFilter, FilteredEvents};
+use mysql_binlog_parser_rust::gtid::GtidSet;
+use crate::datetime::parse_datetime;
+use crate::input;
+
+// Which events the subcommands read, shared through #[command(flatten)].
+#[derive(Args)]
+pub(crate) struct FilterArgs {
+    /// Only statements and row changes in this database; repeatable, % and _ are wildcards
+    #[arg(short, long = "database", value_name = "NAME")]
+    databases: Vec<String>,
+    /// Only row changes of this table, as db.table or a bare table name in any database;
+    /// repeatable, % and _ are wildcards
+    #[arg(long = "table", value_name = "NAME")]
+    tables: Vec<String>,
+    /// Skip events before this time, YYYY-MM-DD HH:MM:SS in UTC like the printed event headers
+    #[arg(long, value_name = "DATETIME", value_parser = parse_datetime)]
+    start_datetime: Option<u32>,
+    /// Stop at the first event at or after this time, in the last file read
+    #[arg(long, value_name = "DATETIME", value_parser = parse_datetime)]
+    stop_datetime: Option<u32>,
+    /// Start at the event at this offset of the first file
+    #[arg(long, value_name = "OFFSET")]
+    start_position: Option<u64>,
+    /// Stop at the first event at or past this offset of the last file
+    #[arg(long, value_name = "OFFSET")]
+    stop_position: Option<u64>,
+    /// Only the transactions with these GTIDs, e.g. 3e11fa47-71ca-11e1-9e33-c80aa9429562:23-25
+    #[arg(long, value_name = "GTID_SET")]
+    include_gtids: Option<GtidSet>,
+    /// Skip the transactions with these GTIDs
+    #[arg(long, value_name = "GTID_SET")]
+    exclude_gtids: Option<GtidSet>,
+}
+
+impl FilterArgs {
+    // The selected events of `files`. As with mysqlbinlog, every FormatDescriptionEvent is
+    // kept whatever its time or position, since the events after it are decoded and replayed
+    // with it.
+    pub(crate) fn events<'a>(&self, files: &'a [PathBuf]) -> crate::Result<impl Iterator<Item = Result<Event, BinlogFileError>> + 'a> {
+        if let (Some(position), Some(first)) = (self.start_position, files.first()) {
+            check_start_position(first, position)?;
+        }
+        let (start_position, stop_position) = (self.start_position, self.stop_position);
+        let last = files.len().saturating_sub(1);
+        let events = files.iter().enumerate().flat_map(move |(i, path)| Positions {
+            inner: input::events(path),
+            start: start_position.filter(|_| i == 0),
+            stop: stop_position.filter(|_| i == last),
+        });
+        Ok(self.select(events))
+    }
+
+    // The events of a stream that isn't read file by file, like a followed binlog or a server's,
+    // selected by everything but the positions, which are left to the caller.
+    pub(crate) fn select<'a, It>(&self, events: It) -> impl Iterator<Item = Result<Event, BinlogFileError>> + 'a where
+        It: Iterator<Item = Result<Event, BinlogFileError>> + 'a
+    {
+        let (start, stop) = (self.start_datetime, self.stop_datetime);
+        let events = events
+            .take_while(move |e| !matches!(e, Ok(e) if stop.is_some_and(|t| e.timestamp() >= t) && !is_format_description(e)))
+            .filter(move |e| !matches!(e, Ok(e) if start.is_some_and(|t| e.timestamp() < t) && !is_format_description(e)));
+        FilteredEvents::new(events, self.event_filter())
+    }
+
+    pub(crate) fn start_position(&self) -> Option<u64> {
+        self.start_position
+    }
+
+    pub(crate) fn stop_position(&self) -> Option<u64> {
+        self.stop_position
+    }
+
+    pub(crate) fn exclude_gtids(&self) -> Option<&GtidSet> {
+        self.exclude_gtids.as_ref()
+    }
+
+    fn event_filter(&self) -> EventFilter {
+        let mut filter = EventFilter::new();
+        for database in &self.databases {
+            filter = filter.include_wild_schema(database);
+        }
+        for table in &self.tables {
+            filter = if has_schema(table) {
+                filter.include_wild_table(table)
+            } else {
+                filter.include_wild_table(&format!("%.{}", table))
+            };
+        }
+        if let Some(gtids) = &self.include_gtids {
+            filter = filter.include_gtids(gtids.clone());
+        }
+        if let Some(gtids) = &self.exclude_gtids {
+            filter = filter.exclude_gtids(gtids.clone());
+        }
+        filter
+    }
+}
+
+// whether there's an unescaped `.`, as TableRule::wild splits the pattern
+fn has_schema(pattern: &str) -> bool {
+    let mut escaped = false;
+    for c in pattern.chars() {
+        match c {
+            '\\' if !escaped => escaped = true,
+            '.' if !escaped => return true,
+            _ => escaped = false,
+        }
+    }
+    false
+}
+
+// Events of one file from `start` and before `stop`. Reaching the end or an event past `start`
+// without one at it is an error, so a mistyped offset doesn't silently give nothing.
+struct Positions<It> {
+    inner: It,
+    start: Option<u64>,
+    stop: Option<u64>,
+}
+
+impl<It> Iterator for Positions<It> where
+    It: Iterator<Item = Result<Event, BinlogFileError>>
+{
+    type Item = Result<Event, BinlogFileError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let event = match self.inner.next() {
+                Some(Ok(event)) => event,
+                Some(Err(e)) => return Some(Err(e)),
+                None => return self.start.take().map(|start| Err(BinlogFileError::BadPosition(start))),
+            };
+            if is_format_description(&event) {
+                return Some(Ok(event));
+            }
+            if self.stop.is_some_and(|stop| event.offset() >= stop) {
+                return None;
+            }
+            match self.start {
+                Some(start) if event.offset() < start => continue,
+                Some(start) if event.offset() > start => return Some(Err(BinlogFileError::BadPosition(start))),
+                _ => {
+                    self.start = None;
+                    return Some(Ok(event));
+                }
+            }
+        }
+    }
+}
+
+// Reads up to `position` of a file to say where the events around it start when it's not an
+// event's own offset. Standard input can only be read once and is checked as it's dumped.
+pub(crate) fn check_start_position(path: &Path, position: u64) -> crate::Result<()> {
+    if path == Path::new("-") {
+        return Ok(());
+    }
+    let mut previous = None;
+    for event in input::events(path) {
+        let offset = event?.offset();
+        if offset == position {
+            return Ok(());
+        }
+        if offset > position {
+            let message = match previous {
+                Some(previous) => format!(
+                    "--start-position {} is not the start of an event in {}; the events around it start at {} and {}",
+                    position, path.display(), previous, offset,
+                ),
+                None => format!(
+                    "--start-position {} is before the first event of {}, at {}",
+                    position, path.display(), offset,
+                ),
+            };
+            return Err(message.into());
+        }
+        previous = Some(offset);
+    }
+    Err(match previous {
+        Some(last) => format!("--start-position {} is past the last event of {}, at {}", position, path.display(), last),
+        None => format!("--start-position {} is past the end of {}, which has no events", position, path.display()),
+    }.into())
+}
+
+fn is_format_description(event: &Event) -> bool {
+    event.type_code() == TypeCode::FormatDescriptionEvent
+}
@@ -0,0 +1,152 @@
+use std::collections::{BTreeMap, HashMap};
+use std::io::Write;
+use std::path::PathBuf;
+use clap::Args;
+use mysql_binlog_parser_rust::context::ParseContext;
+use mysql_binlog_parser_rust::event::EventData;
+use mysql_binlog_parser_rust::rows::RowsEvent;
+use mysql_binlog_parser_rust::sql::{Flashback, KeyColumns};
+use crate::filter::FilterArgs;
+
+#[derive(Args)]
+pub(crate) struct FlashbackArgs {
+    /// Binlog files with the changes to undo, read in the order given; - reads standard input
+    #[arg(required = true)]
+    files: Vec<PathBuf>,
+    #[command(flatten)]
+    filter: FilterArgs,
+    /// Write the script to this file instead of standard output
+    #[arg(short, long, value_name = "FILE")]
+    output: Option<PathBuf>,
+    /// Match rows on every column of the before image instead of the primary key, so rows
+    /// changed since are left alone
+    #[arg(long)]
+    all_columns: bool,
+    /// Only count the rows that would be reverted, by table
+    #[arg(long)]
+    dry_run: bool,
+}
+
+// SQL undoing the row changes of the selected events, newest transaction first, as binlog2sql
+// --flashback does. The binlog must have full row images (binlog_row_image=FULL).
+pub(crate) fn run(args: &FlashbackArgs, out: &mut dyn Write) -> crate::Result<()> {
+    let key_columns = if args.all_columns { KeyColumns::AllColumns } else { KeyColumns::PrimaryKey };
+    let mut flashback = Flashback::new().key_columns(key_columns);
+    let mut summary = Summary::default();
+    let mut ctx = crate::parse_context();
+    for event in args.filter.events(&args.files)? {
+        let event = event?;
+        let data = event.parsed(&mut ctx)?;
+        if let EventData::WriteRowsEvent(rows) | EventData::UpdateRowsEvent(rows) | EventData::DeleteRowsEvent(rows) = &data {
+            if !args.dry_run {
+                check_column_names(&ctx, rows)?;
+            }
+        }
+        summary.add(&data);
+        flashback.write_event(&event, &data);
+    }
+    if args.dry_run {
+        let transactions = flashback.finish().len();
+        summary.write(transactions, out)?;
+        return Ok(());
+    }
+    match &args.output {
+        Some(path) => std::fs::write(path, flashback.script())?,
+        None => out.write_all(flashback.script().as_bytes())?,
+    }
+    Ok(())
+}
+
+// SQL naming columns @1, @2... can't be run, so rather than write it, say what's missing.
+fn check_column_names(ctx: &ParseContext, rows: &RowsEvent) -> crate::Result<()> {
+    match ctx.table_map(rows.table_id) {
+        Some(table_map) if table_map.columns.iter().any(|c| c.name.is_none()) => Err(format!(
+            "the column names of {}.{} aren't known: the binlog wasn't written with binlog_row_metadata=FULL, \
+            and its CREATE TABLE wasn't read first",
+            table_map.schema, table_map.table,
+        ).into()),
+        _ => Ok(()),
+    }
+}
+
+#[derive(Default)]
+struct Summary {
+    // by (schema, table): inserted, updated and deleted rows
+    tables: BTreeMap<(String, String), (u64, u64, u64)>,
+    table_names: HashMap<u64, (String, String)>,
+}
+
+impl Summary {
+    fn add(&mut self, data: &EventData) {
+        let (table_id, rows) = match data {
+            EventData::TableMapEvent(table_map) => {
+                let name = (table_map.schema.clone(), table_map.table.clone());
+                self.table_names.insert(table_map.table_id, name);
+                return;
+            }
+            EventData::WriteRowsEvent(rows) => (rows.table_id, (rows.rows.len() as u64, 0, 0)),
+            EventData::UpdateRowsEvent(rows) => (rows.table_id, (0, rows.rows.len() as u64, 0)),
+            EventData::DeleteRowsEvent(rows) => (rows.table_id, (0, 0, rows.rows.len() as u64)),
+            _ => return,
+        };
+        if let Some(name) = self.table_names.get(&table_id) {
+            let counts = self.tables.entry(name.clone()).or_default();
+            counts.0 += rows.0;
+            counts.1 += rows.1;
+            counts.2 += rows.2;
+        }
+    }
+
+    fn write(&self, transactions: usize, out: &mut dyn Write) -> std::io::Result<()> {
+        let mut total = 0;
+        for ((schema, table), (inserted, updated, deleted)) in &self.tables {
+            writeln!(out, "{}.{}: {} inserted row(s) to delete, {} updated to restore, {} deleted to insert again",
+                schema, table, inserted, updated, deleted)?;
+            total += inserted + updated + deleted;
+        }
+        writeln!(out, "{} row(s) in {} transaction(s) would be reverted", total, transactions)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::Parser;
+    use crate::{Cli, Command};
+
+    fn flashback(args: &[&str]) -> String {
+        try_flashback(args).unwrap()
+    }
+
+    fn try_flashback(args: &[&str]) -> Result<String, String> {
+        let cli = Cli::parse_from([&["mysql-binlog", "flashback"], args].concat());
+        let mut out = vec![];
+        match &cli.command {
+            Command::Flashback(args) => super::run(args, &mut out).map_err(|e| e.to_string())?,
+            _ => unreachable!(),
+        }
+        Ok(String::from_utf8(out).unwrap())
+    }
+
+    #[test]
+    fn test_flashback() {
+        //given
+        let path = "tests/asset/mysql-bin.100746";
+
+        //when
+        let script = flashback(&[path]);
+        let unnamed = try_flashback(&["--start-position", "740", path]);
+        let dry_run = flashback(&["--dry-run", "--table", "users", path]);
+        let excluded = flashback(&["--dry-run", "--include-gtids", "11111111-1111-1111-1111-111111111111:1", path]);
+
+        //then
+        assert!(script.starts_with("BEGIN;\nUPDATE `test`.`users` SET `id`="), "{}", script);
+        assert!(!script.contains("`@1`"));
+        assert!(script.ends_with("COMMIT;\n"));
+        assert_eq!(script.matches("BEGIN;").count(), 2);
+        assert_eq!(unnamed.unwrap_err(), "the column names of test.users aren't known: the binlog wasn't written with \
+            binlog_row_metadata=FULL, and its CREATE TABLE wasn't read first");
+        assert_eq!(dry_run, "test.users: 2 inserted row(s) to delete, 1 updated to restore, 0 deleted to insert again\n\
+            3 row(s) in 2 transaction(s) would be reverted\n");
+        assert_eq!(excluded, "0 row(s) in 0 transaction(s) would be reverted\n");
+    }
+}
@@ -0,0 +1,143 @@
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use clap::Args;
+use mysql_binlog_parser_rust::event::EventData;
+use mysql_binlog_parser_rust::gtid::{format_uuid, GtidSet};
+use crate::input;
+
+#[derive(Args)]
+pub(crate) struct GtidArgs {
+    /// Binlog files; - reads standard input
+    #[arg(required = true)]
+    files: Vec<PathBuf>,
+    /// Only say which files have these transactions and where, e.g. 3e11fa47-71ca-11e1-9e33-c80aa9429562:23
+    #[arg(long, value_name = "GTID_SET")]
+    find: Option<GtidSet>,
+}
+
+// The GTIDs of each file: its Previous_gtids, everything executed by its end, and its first
+// and last GTID with their offsets.
+pub(crate) fn run(args: &GtidArgs, out: &mut dyn Write) -> crate::Result<()> {
+    for path in &args.files {
+        let gtids = read(path)?;
+        match &args.find {
+            Some(find) => {
+                for (sid, gno, offset) in gtids.transactions.iter().filter(|(sid, gno, _)| find.contains(sid, *gno)) {
+                    writeln!(out, "{}: {}:{} at {}", path.display(), format_uuid(sid), gno, offset)?;
+                }
+            }
+            None => gtids.write(path, out)?,
+        }
+    }
+    Ok(())
+}
+
+struct FileGtids {
+    previous: Option<GtidSet>,
+    executed: GtidSet,
+    // sid, gno and offset of each GTID event
+    transactions: Vec<([u8; 16], i64, u64)>,
+    anonymous: usize,
+}
+
+fn read(path: &Path) -> crate::Result<FileGtids> {
+    let mut ctx = crate::parse_context();
+    let mut gtids = FileGtids { previous: None, executed: GtidSet::new(), transactions: vec![], anonymous: 0 };
+    for event in input::events(path) {
+        let event = event?;
+        match event.parsed(&mut ctx)? {
+            EventData::PreviousGtidsLogEvent { gtids: previous } => {
+                gtids.executed.add_set(&previous);
+                gtids.previous = Some(previous);
+            }
+            EventData::GtidLogEvent(gtid) => {
+                gtids.executed.add(gtid.sid, gtid.gno);
+                gtids.transactions.push((gtid.sid, gtid.gno, event.offset()));
+            }
+            EventData::AnonymousGtidLogEvent(_) => gtids.anonymous += 1,
+            _ => {}
+        }
+    }
+    Ok(gtids)
+}
+
+impl FileGtids {
+    fn write(&self, path: &Path, out: &mut dyn Write) -> std::io::Result<()> {
+        writeln!(out, "{}", path.display())?;
+        match &self.previous {
+            Some(previous) if !previous.is_empty() => writeln!(out, "  Previous_gtids: {}", previous)?,
+            Some(_) => writeln!(out, "  Previous_gtids: (empty)")?,
+            None => writeln!(out, "  Previous_gtids: (none, written before MySQL 5.6)")?,
+        }
+        writeln!(out, "  Executed:       {}", if self.executed.is_empty() { "(empty)".to_owned() } else { self.executed.to_string() })?;
+        if let (Some(first), Some(last)) = (self.transactions.first(), self.transactions.last()) {
+            writeln!(out, "  First GTID:     {}:{} at {}", format_uuid(&first.0), first.1, first.2)?;
+            writeln!(out, "  Last GTID:      {}:{} at {}", format_uuid(&last.0), last.1, last.2)?;
+        }
+        writeln!(out, "  Transactions:   {} with a GTID, {} anonymous", self.transactions.len(), self.anonymous)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::Parser;
+    use mysql_binlog_parser_rust::checksum::crc32;
+    use crate::{Cli, Command};
+
+    fn gtid(args: &[&str]) -> String {
+        let cli = Cli::parse_from([&["mysql-binlog", "gtid"], args].concat());
+        let mut out = vec![];
+        match &cli.command {
+            Command::Gtid(args) => super::run(args, &mut out).unwrap(),
+            _ => unreachable!(),
+        }
+        String::from_utf8(out).unwrap()
+    }
+
+    // the fixture with its anonymous GTIDs turned into aaaaaaaa-...:5, :6 and :7
+    fn with_gtids(path: &std::path::Path) {
+        let mut bytes = std::fs::read("tests/asset/mysql-bin.100746").unwrap();
+        let (mut offset, mut gno) = (4, 5i64);
+        while offset < bytes.len() {
+            let len = u32::from_le_bytes([bytes[offset + 9], bytes[offset + 10], bytes[offset + 11], bytes[offset + 12]]) as usize;
+            if bytes[offset + 4] == 34 {
+                let event = &mut bytes[offset..offset + len];
+                event[4] = 33;
+                event[20..36].copy_from_slice(&[0xaa; 16]);
+                event[36..44].copy_from_slice(&gno.to_le_bytes());
+                let checksum = crc32(&event[..19], &event[19..len - 4]);
+                event[len - 4..].copy_from_slice(&checksum.to_le_bytes());
+                gno += 1;
+            }
+            offset += len;
+        }
+        std::fs::write(path, bytes).unwrap();
+    }
+
+    #[test]
+    fn test_gtid() {
+        //given
+        let path = std::env::temp_dir().join(format!("mysql-binlog-gtid-{}", std::process::id()));
+        with_gtids(&path);
+        let path_arg = path.to_str().unwrap();
+
+        //when
+        let anonymous = gtid(&["tests/asset/mysql-bin.100746"]);
+        let summary = gtid(&[path_arg]);
+        let found = gtid(&["--find", "aaaaaaaa-aaaa-aaaa-aaaa-aaaaaaaaaaaa:6-9", path_arg]);
+        std::fs::remove_file(&path).unwrap();
+
+        //then
+        assert_eq!(anonymous, "tests/asset/mysql-bin.100746\n  \
+            Previous_gtids: (empty)\n  \
+            Executed:       (empty)\n  \
+            Transactions:   0 with a GTID, 3 anonymous\n");
+        assert!(summary.contains("  Executed:       aaaaaaaa-aaaa-aaaa-aaaa-aaaaaaaaaaaa:5-7\n"), "{}", summary);
+        assert!(summary.contains("  First GTID:     aaaaaaaa-aaaa-aaaa-aaaa-aaaaaaaaaaaa:5 at 154\n"));
+        assert!(summary.contains("  Last GTID:      aaaaaaaa-aaaa-aaaa-aaaa-aaaaaaaaaaaa:7 at 740\n"));
+        assert_eq!(found, format!(
+            "{0}: aaaaaaaa-aaaa-aaaa-aaaa-aaaaaaaaaaaa:6 at 464\n{0}: aaaaaaaa-aaaa-aaaa-aaaa-aaaaaaaaaaaa:7 at 740\n",
+            path_arg,
+        ));
+    }
+}
@@ -0,0 +1,21 @@
+use std::io::Read;
+use std::path::Path;
+use mysql_binlog_parser_rust::binlog_reader::BinlogReader;
+use mysql_binlog_parser_rust::errors::BinlogFileError;
+use mysql_binlog_parser_rust::event::Event;
+
+// The events of a binlog file, `-` being standard input. Compressed archives are decompressed
+// as they're read.
+pub(crate) fn events(path: &Path) -> Box<dyn Iterator<Item = Result<Event, BinlogFileError>>> {
+    match open(path) {
+        Ok(reader) => Box::new(reader),
+        Err(e) => Box::new(std::iter::once(Err(e))),
+    }
+}
+
+fn open(path: &Path) -> Result<BinlogReader<Box<dyn Read + Send>>, BinlogFileError> {
+    if path == Path::new("-") {
+        return BinlogReader::from_reader(Box::new(std::io::stdin()) as Box<dyn Read + Send>);
+    }
+    BinlogReader::from_path(path)
+}
@@ -0,0 +1,116 @@
+// mysql-binlog, reading MySQL binary logs with this crate:
+//
+//     mysql-binlog dump -vv mysql-bin.000042
+//     mysql-binlog dump --format json mysql-bin.000042 mysql-bin.000043 | jq .
+//     mysql-binlog dump -vv --host db1 -u repl -p secret --start-position 1234 mysql-bin.000042
+//     mysql-binlog dump -vv --table 'shop.orders_%' mysql-bin.000042
+//     mysql-binlog dump --format debezium --topic-prefix dbserver1 mysql-bin.000042 | jq .value
+//     mysql-binlog flashback --table shop.orders --start-datetime '2024-01-31 09:00' mysql-bin.000042 > undo.sql
+//     mysql-binlog gtid --find 3e11fa47-71ca-11e1-9e33-c80aa9429562:23 /var/lib/mysql/mysql-bin.0*
+//     mysql-binlog stats --start-datetime '2024-01-31 09:00' mysql-bin.000042
+//     mysql-binlog tail -v --table shop.orders /var/lib/mysql/mysql-bin.000042
+//     mysql-binlog tail --format json --host db1 -u repl -p secret
+//     mysql-binlog anonymize --mask shop.users.email=hash -o shareable.000042 mysql-bin.000042
+//     mysql-binlog verify --quiet /backups/binlogs/*.gz || alert
+mod anonymize;
+mod datetime;
+mod dump;
+mod filter;
+mod flashback;
+mod gtid;
+mod input;
+mod output;
+mod remote;
+mod split;
+mod stats;
+mod tail;
+mod verify;
+
+use std::process::ExitCode;
+use clap::{Parser, Subcommand};
+use mysql_binlog_parser_rust::context::ParseContext;
+use mysql_binlog_parser_rust::schema::SchemaTracker;
+
+pub(crate) type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
+
+#[derive(Parser)]
+#[command(name = "mysql-binlog", version, about = "Reads MySQL binary logs")]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Print the events of binlog files, as mysqlbinlog does or as JSON
+    Dump(dump::DumpArgs),
+    /// Write SQL undoing the row changes of binlog files, newest first
+    Flashback(flashback::FlashbackArgs),
+    /// Print the GTIDs of binlog files, or which of them have given transactions
+    Gtid(gtid::GtidArgs),
+    /// Summarize binlog files: event types, changes per table, transaction sizes, time span
+    Stats(stats::StatsArgs),
+    /// Cut a binlog into smaller binlogs on transaction boundaries
+    Split(split::SplitArgs),
+    /// Print events as they're written to the active binlog or by a server, like tail -f
+    Tail(tail::TailArgs),
+    /// Write a copy of a binlog with the values of given columns masked
+    Anonymize(anonymize::AnonymizeArgs),
+    /// Check binlog files for corruption and truncation, failing if any has problems
+    Verify(verify::VerifyArgs),
+}
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+    let stdout = std::io::stdout();
+    let mut out = stdout.lock();
+    let result = match &cli.command {
+        Command::Dump(args) => dump::run(args, &mut out),
+        Command::Flashback(args) => flashback::run(args, &mut out),
+        Command::Gtid(args) => gtid::run(args, &mut out),
+        Command::Stats(args) => stats::run(args, &mut out),
+        Command::Split(args) => split::run(args, &mut out),
+        Command::Tail(args) => tail::run(args, &mut out),
+        Command::Anonymize(args) => anonymize::run(args, &mut out),
+        Command::Verify(args) => verify::run(args, &mut out),
+    };
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        // the reader went away, e.g. `| head`
+        Err(e) if is_broken_pipe(e.as_ref()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("mysql-binlog: {}", error_chain(e.as_ref()));
+            ExitCode::FAILURE
+        }
+    }
+}
+
+// the error and what caused it, e.g. "error parsing event: body ends while reading table id"
+pub(crate) fn error_chain(error: &(dyn std::error::Error + 'static)) -> String {
+    let mut text = error.to_string();
+    let mut source = error.source();
+    while let Some(e) = source {
+        text.push_str(": ");
+        text.push_str(&e.to_string());
+        source = e.source();
+    }
+    text
+}
+
+// What the subcommands decode events with. The schema tracker follows the CREATE and ALTER
+// TABLE statements read, so binlogs without binlog_row_metadata=FULL still have column names.
+pub(crate) fn parse_context() -> ParseContext {
+    ParseContext::new().track_schema(SchemaTracker::new())
+}
+
+fn is_broken_pipe(mut error: &(dyn std::error::Error + 'static)) -> bool {
+    loop {
+        if let Some(e) = error.downcast_ref::<std::io::Error>() {
+            return e.kind() == std::io::ErrorKind::BrokenPipe;
+        }
+        match error.source() {
+            Some(source) => error = source,
+            None => return false,
+        }
+    }
+}
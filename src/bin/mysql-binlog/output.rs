@@ -0,0 +1,116 @@
+use std::io::Write;
+use clap::{ArgAction, Args, ValueEnum};
+use mysql_binlog_parser_rust::debezium::DebeziumEnvelopes;
+use mysql_binlog_parser_rust::errors::BinlogFileError;
+use mysql_binlog_parser_rust::event::{Event, EventData};
+use mysql_binlog_parser_rust::export::{Granularity, NdjsonWriter};
+use mysql_binlog_parser_rust::handler;
+use mysql_binlog_parser_rust::maxwell::MaxwellRecords;
+use mysql_binlog_parser_rust::mysqlbinlog::{Base64Output, MysqlbinlogWriter};
+use mysql_binlog_parser_rust::sql::SqlStatements;
+
+// How dump and tail print events.
+#[derive(Args)]
+pub(crate) struct OutputArgs {
+    #[arg(long, value_enum, default_value_t = Format::Text)]
+    format: Format,
+    /// Show row changes as ### pseudo-SQL; given twice, with column types
+    #[arg(short, long, action = ArgAction::Count)]
+    verbose: u8,
+    /// Whether text output also has row events as BINLOG statements, to be replayed
+    #[arg(long, value_enum, default_value_t = Base64Mode::Auto)]
+    base64_output: Base64Mode,
+    /// With text output, also each event's bytes, the header field by field
+    #[arg(long)]
+    hexdump: bool,
+    /// With JSON output, a line per changed row instead of per event
+    #[arg(long)]
+    rows: bool,
+    /// With Debezium output, the connector's topic.prefix the topics start with
+    #[arg(long, default_value = "mysql")]
+    topic_prefix: String,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Format {
+    /// as mysqlbinlog prints it
+    Text,
+    /// an object per line
+    Json,
+    /// INSERT, UPDATE and DELETE statements replaying the row changes
+    Sql,
+    /// a Debezium change event per row, {"topic", "key", "value"} per line
+    Debezium,
+    /// a Maxwell record per row, written when its transaction commits
+    Maxwell,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Base64Mode {
+    Auto,
+    DecodeRows,
+}
+
+impl OutputArgs {
+    // `file` is the binlog the events start in, for the formats that say where rows come from;
+    // a server's stream starts with a Rotate naming it instead.
+    pub(crate) fn write<It>(&self, events: It, file: &str, out: &mut dyn Write) -> crate::Result<()> where
+        It: Iterator<Item = Result<Event, BinlogFileError>>
+    {
+        let mut ctx = crate::parse_context();
+        match self.format {
+            Format::Text => {
+                let base64_output = match self.base64_output {
+                    Base64Mode::Auto => Base64Output::Auto,
+                    Base64Mode::DecodeRows => Base64Output::DecodeRows,
+                };
+                let mut writer = MysqlbinlogWriter::new(out)
+                    .verbosity(self.verbose)
+                    .base64_output(base64_output)
+                    .hexdump(self.hexdump);
+                handler::run(events, &mut ctx, &mut writer)?;
+                writer.finish()?;
+            }
+            Format::Json => {
+                let granularity = if self.rows { Granularity::Rows } else { Granularity::Events };
+                let mut writer = NdjsonWriter::new(out).granularity(granularity);
+                handler::run(events, &mut ctx, &mut writer)?;
+                writer.flush()?;
+            }
+            Format::Sql | Format::Debezium | Format::Maxwell => {
+                let mut records = match self.format {
+                    Format::Sql => Records::Sql(SqlStatements::new()),
+                    Format::Debezium => Records::Debezium(DebeziumEnvelopes::new(&self.topic_prefix, file)),
+                    _ => Records::Maxwell(MaxwellRecords::new()),
+                };
+                for event in events {
+                    let event = event?;
+                    for line in records.lines(&event, &event.parsed(&mut ctx)?) {
+                        writeln!(out, "{}", line)?;
+                    }
+                }
+                out.flush()?;
+            }
+        }
+        Ok(())
+    }
+}
+
+// The serializers that turn events into lines of their own, rather than writing them.
+enum Records {
+    Sql(SqlStatements),
+    Debezium(DebeziumEnvelopes),
+    Maxwell(MaxwellRecords),
+}
+
+impl Records {
+    fn lines(&mut self, event: &Event, data: &EventData) -> Vec<String> {
+        match self {
+            Records::Sql(sql) => sql.statements(event, data),
+            Records::Debezium(envelopes) => envelopes.records(event, data).into_iter()
+                .map(|record| serde_json::json!({"topic": record.topic, "key": record.key, "value": record.value}).to_string())
+                .collect(),
+            Records::Maxwell(maxwell) => maxwell.records(event, data).iter().map(|record| record.to_string()).collect(),
+        }
+    }
+}
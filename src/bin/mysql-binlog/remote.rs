@@ -0,0 +1,157 @@
+use std::path::PathBuf;
+use clap::Args;
+use mysql_binlog_parser_rust::client::ReplicationClient;
+use mysql_binlog_parser_rust::errors::BinlogFileError;
+use mysql_binlog_parser_rust::event::{Event, EventData, TypeCode};
+use mysql_binlog_parser_rust::position::BinlogPosition;
+use crate::filter::FilterArgs;
+
+// A server to read the binlog from as a replica would, instead of files.
+#[derive(Args)]
+pub(crate) struct RemoteArgs {
+    /// Read from this server, HOST or HOST:PORT, connecting as a replica
+    #[arg(long, value_name = "HOST[:PORT]")]
+    host: Option<String>,
+    /// User with the REPLICATION SLAVE and REPLICATION CLIENT privileges
+    #[arg(short, long, default_value = "root", requires = "host")]
+    user: String,
+    #[arg(short, long, requires = "host")]
+    password: Option<String>,
+    /// Server id to register with; must differ from those of the server and its other replicas
+    #[arg(long, default_value_t = 65535, requires = "host")]
+    server_id: u32,
+}
+
+impl RemoteArgs {
+    pub(crate) fn is_remote(&self) -> bool {
+        self.host.is_some()
+    }
+
+    // the client for --host, None when reading files
+    pub(crate) fn client(&self) -> Option<ReplicationClient> {
+        let host = self.host.as_ref()?;
+        let addr = if host.contains(':') { host.clone() } else { format!("{}:3306", host) };
+        let client = ReplicationClient::new(&addr)
+            .user(&self.user)
+            .password(self.password.as_deref().unwrap_or(""))
+            .server_id(self.server_id);
+        Some(client)
+    }
+
+    // The events of the server's binlog `files`, named as SHOW BINARY LOGS lists them, or of all
+    // of its binlogs from the oldest when none are given, as mysqlbinlog --read-from-remote-server
+    // reads them. --start-position is sent with the first file and --exclude-gtids as the
+    // replica's executed set, so the server skips what's before them. The stream ends after
+    // the last file given, or where the server is up to, unless `stop_never` keeps it waiting
+    // for new events.
+    pub(crate) fn events(&self, filter: &FilterArgs, files: &[PathBuf], stop_never: bool) -> crate::Result<impl Iterator<Item = Result<Event, BinlogFileError>>> {
+        let client = self.client().ok_or("--host is needed to read from a server")?;
+        let names: Vec<String> = files.iter().map(|f| f.to_string_lossy().into_owned()).collect();
+        let first = names.first().map_or("", String::as_str);
+        if names.is_empty() && (filter.start_position().is_some() || filter.stop_position().is_some()) {
+            return Err("--start-position and --stop-position need the binlog files they're offsets of".into());
+        }
+        let mut start = BinlogPosition::new(first, filter.start_position().unwrap_or(4));
+        if let Some(executed) = filter.exclude_gtids() {
+            start = start.with_gtid_set(executed.clone());
+        }
+        let stream = client
+            .start_position(start)
+            .non_blocking(!stop_never)
+            .skip_heartbeats(true)
+            .connect()?;
+        let files = ServerFiles {
+            inner: stream,
+            current: first.to_owned(),
+            last: names.last().cloned(),
+            stop: filter.stop_position(),
+            finished: false,
+        };
+        Ok(filter.select(files))
+    }
+}
+
+// Ends a server's stream after the last file asked for, at the Rotate out of it or at
+// --stop-position in it. The file being read is followed through the Rotate events, including
+// the artificial one the server starts with.
+struct ServerFiles<It> {
+    inner: It,
+    current: String,
+    last: Option<String>,
+    stop: Option<u64>,
+    finished: bool,
+}
+
+impl<It> Iterator for ServerFiles<It> where
+    It: Iterator<Item = Result<Event, BinlogFileError>>
+{
+    type Item = Result<Event, BinlogFileError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.finished {
+            return None;
+        }
+        let event = match self.inner.next()? {
+            Ok(event) => event,
+            Err(e) => return Some(Err(e)),
+        };
+        let last = match &self.last {
+            Some(last) => last,
+            None => return Some(Ok(event)),
+        };
+        let in_last = self.current == *last;
+        if in_last && self.stop.is_some_and(|stop| event.offset() >= stop) && event.type_code() != TypeCode::FormatDescriptionEvent {
+            self.finished = true;
+            return None;
+        }
+        if event.type_code() == TypeCode::RotateEvent {
+            let next_file = match Event::parse_event_data_by_type_code(TypeCode::RotateEvent, event.data()) {
+                Ok(Some(EventData::RotateEvent { next_file, .. })) => next_file,
+                Ok(_) => return Some(Ok(event)),
+                Err(e) => return Some(Err(e.into())),
+            };
+            if BinlogPosition::new(&next_file, 0) > BinlogPosition::new(last, u64::MAX) {
+                self.finished = true;
+            }
+            self.current = next_file;
+        }
+        Some(Ok(event))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use mysql_binlog_parser_rust::binlog_file::BinlogFile;
+    use mysql_binlog_parser_rust::event::TypeCode;
+    use super::ServerFiles;
+
+    #[test]
+    fn test_server_files_end_after_the_last_file() {
+        //given
+        let path = "tests/asset/mysql-bin.100746";
+        let events = || {
+            let file = || BinlogFile::from_path(path).unwrap().events().collect::<Vec<_>>();
+            file().into_iter().chain(file())
+        };
+        let files = |last: Option<&str>, stop: Option<u64>| ServerFiles {
+            inner: events(),
+            current: "mysql-bin.100746".to_owned(),
+            last: last.map(str::to_owned),
+            stop,
+            finished: false,
+        };
+
+        //when
+        let all: Vec<_> = files(None, None).map(|e| e.unwrap()).collect();
+        let one_file: Vec<_> = files(Some("mysql-bin.100746"), None).map(|e| e.unwrap()).collect();
+        let stopped: Vec<_> = files(Some("mysql-bin.100746"), Some(740)).map(|e| e.unwrap()).collect();
+        let next_file: Vec<_> = files(Some("mysql-bin.100747"), Some(740)).map(|e| e.unwrap()).collect();
+
+        //then
+        assert_eq!(all.len(), 30);
+        assert_eq!(one_file.len(), 15);
+        assert_eq!(one_file.last().unwrap().type_code(), TypeCode::RotateEvent);
+        assert_eq!(stopped.len(), 9);
+        assert_eq!(next_file.len(), 15 + 9);
+    }
+}
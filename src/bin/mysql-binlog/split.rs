@@ -0,0 +1,137 @@
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use clap::Args;
+use mysql_binlog_parser_rust::errors::{BinlogWriteError, EncodeError, SplitError};
+use mysql_binlog_parser_rust::split::Splitter;
+use crate::input;
+
+#[derive(Args)]
+pub(crate) struct SplitArgs {
+    /// The binlog file to split; - reads standard input
+    file: PathBuf,
+    /// Where to write the pieces, named after the input: mysql-bin.000042.000001, ...
+    #[arg(short, long, default_value = ".")]
+    output_dir: PathBuf,
+    /// Start a new piece before a transaction would take the current one past this size, e.g. 64M
+    #[arg(long, value_name = "BYTES", value_parser = parse_size, required_unless_present_any = ["max_transactions", "max_duration"])]
+    max_size: Option<u64>,
+    /// Start a new piece once the current one has this many transactions
+    #[arg(long, value_name = "COUNT")]
+    max_transactions: Option<usize>,
+    /// Start a new piece with the first transaction this long after the current one's first
+    #[arg(long, value_name = "SECONDS")]
+    max_duration: Option<u32>,
+}
+
+// Cuts a binlog into pieces that are binlogs of their own, with a Splitter: each starts with
+// the magic and the input's FormatDescriptionEvent and, when the input had one, a
+// Previous_gtids of everything before it, and ends with a Rotate to the next piece.
+// Transactions are never cut, so one bigger than --max-size gets a piece to itself. Positions
+// are counted afresh in each piece.
+pub(crate) fn run(args: &SplitArgs, out: &mut dyn Write) -> crate::Result<()> {
+    let name = match args.file.file_name() {
+        Some(name) if args.file != Path::new("-") => name.to_string_lossy().into_owned(),
+        _ => "binlog".to_owned(),
+    };
+    let mut splitter = Splitter::new();
+    if let Some(max) = args.max_size {
+        splitter = splitter.max_size(max);
+    }
+    if let Some(max) = args.max_transactions {
+        splitter = splitter.max_transactions(max);
+    }
+    if let Some(max) = args.max_duration {
+        splitter = splitter.max_duration(max);
+    }
+    let summary = splitter.split(&name, input::events(&args.file), |count| {
+        let piece = format!("{}.{:06}", name, count);
+        let writer = BufWriter::new(File::create(args.output_dir.join(&piece))?);
+        Ok((piece, writer))
+    });
+    let summary = match summary {
+        Err(SplitError::BinlogWriteError(BinlogWriteError::EncodeError(EncodeError::PositionOverflow(_)))) => {
+            return Err("piece grew past 4 GiB; use a smaller --max-size".into())
+        }
+        result => result?,
+    };
+    for piece in &summary.pieces {
+        writeln!(out, "{}: {} transaction(s), {} bytes", args.output_dir.join(&piece.name).display(), piece.transactions, piece.size)?;
+    }
+    Ok(())
+}
+
+// a byte count with an optional K, M or G suffix, in powers of 1024
+fn parse_size(text: &str) -> Result<u64, String> {
+    let (digits, unit) = match text.char_indices().find(|(_, c)| !c.is_ascii_digit()) {
+        Some((i, _)) => text.split_at(i),
+        None => (text, ""),
+    };
+    let shift = match unit.to_ascii_uppercase().as_str() {
+        "" | "B" => 0,
+        "K" | "KB" | "KIB" => 10,
+        "M" | "MB" | "MIB" => 20,
+        "G" | "GB" | "GIB" => 30,
+        _ => return Err(format!("expected a size like 64M, got {:?}", text)),
+    };
+    let number: u64 = digits.parse().map_err(|_| format!("expected a size like 64M, got {:?}", text))?;
+    number.checked_mul(1 << shift).ok_or_else(|| format!("{} is too big", text))
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::Parser;
+    use mysql_binlog_parser_rust::binlog_file::BinlogFile;
+    use mysql_binlog_parser_rust::event::TypeCode;
+    use crate::{Cli, Command};
+    use super::parse_size;
+
+    #[test]
+    fn test_split_by_transactions() {
+        //given
+        let dir = std::env::temp_dir().join(format!("mysql-binlog-split-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let dir_arg = dir.to_str().unwrap();
+        let cli = Cli::parse_from(["mysql-binlog", "split", "--max-transactions", "2", "-o", dir_arg, "tests/asset/mysql-bin.100746"]);
+        let mut out = vec![];
+
+        //when
+        match &cli.command {
+            Command::Split(args) => super::run(args, &mut out).unwrap(),
+            _ => unreachable!(),
+        }
+        let mut first = BinlogFile::from_path(dir.join("mysql-bin.100746.000001")).unwrap();
+        let mut second = BinlogFile::from_path(dir.join("mysql-bin.100746.000002")).unwrap();
+        let (first_report, second_report) = (first.verify().unwrap(), second.verify().unwrap());
+        let first_types: Vec<TypeCode> = first.events().map(|e| e.unwrap().type_code()).collect();
+        let second_types: Vec<TypeCode> = second.events().map(|e| e.unwrap().type_code()).collect();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        //then
+        assert_eq!(String::from_utf8(out).unwrap().lines().count(), 2);
+        assert!(first_report.is_ok(), "{:?}", first_report.problems);
+        assert!(second_report.is_ok(), "{:?}", second_report.problems);
+        assert_eq!(first_types.first(), Some(&TypeCode::FormatDescriptionEvent));
+        assert_eq!(first_types.last(), Some(&TypeCode::RotateEvent));
+        assert_eq!(first_types.len(), 1 + 1 + 2 + 5 + 1);
+        assert_eq!(second_types, [
+            TypeCode::FormatDescriptionEvent,
+            TypeCode::PreviousGtidsLogEvent,
+            TypeCode::AnonymousGtidLogEvent,
+            TypeCode::QueryEvent,
+            TypeCode::TableMapEvent,
+            TypeCode::UpdateRowsEventV2,
+            TypeCode::XidEvent,
+            TypeCode::RotateEvent,
+        ]);
+    }
+
+    #[test]
+    fn test_parse_size() {
+        assert_eq!(parse_size("4096"), Ok(4096));
+        assert_eq!(parse_size("64M"), Ok(64 << 20));
+        assert_eq!(parse_size("1gib"), Ok(1 << 30));
+        assert!(parse_size("12X").is_err());
+        assert!(parse_size("M").is_err());
+    }
+}
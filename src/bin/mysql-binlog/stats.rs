@@ -0,0 +1,256 @@
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, HashMap};
+use std::io::Write;
+use std::path::PathBuf;
+use clap::{Args, ValueEnum};
+use mysql_binlog_parser_rust::event::{Event, EventData};
+use mysql_binlog_parser_rust::transaction::{Transaction, TransactionItem, Transactions};
+use crate::datetime::format_datetime;
+use crate::filter::FilterArgs;
+
+#[derive(Args)]
+pub(crate) struct StatsArgs {
+    /// Binlog files, read in the order given; - reads standard input
+    #[arg(required = true)]
+    files: Vec<PathBuf>,
+    #[command(flatten)]
+    filter: FilterArgs,
+    #[arg(long, value_enum, default_value_t = Format::Text)]
+    format: Format,
+}
+
+// Not the bin's OutputArgs: those print events, and the report is a summary of them.
+#[derive(Clone, Copy, ValueEnum)]
+enum Format {
+    /// tables for reading
+    Text,
+    /// one object, with byte counts unscaled and times as Unix timestamps
+    Json,
+}
+
+pub(crate) fn run(args: &StatsArgs, out: &mut dyn Write) -> crate::Result<()> {
+    let mut ctx = crate::parse_context();
+    let mut stats = Stats::default();
+    for item in Transactions::new(args.filter.events(&args.files)?) {
+        match item? {
+            TransactionItem::Transaction(transaction) => {
+                stats.add_transaction(&transaction);
+                for event in transaction.events() {
+                    stats.add_event(event, &event.parsed(&mut ctx)?);
+                }
+            }
+            TransactionItem::Event(event) => stats.add_event(&event, &event.parsed(&mut ctx)?),
+        }
+    }
+    match args.format {
+        Format::Text => stats.write(out)?,
+        Format::Json => writeln!(out, "{}", stats.to_json())?,
+    }
+    Ok(())
+}
+
+#[derive(Default)]
+struct Stats {
+    // by type name
+    event_types: BTreeMap<String, Counter>,
+    // by (schema, table)
+    tables: BTreeMap<(String, String), TableCounter>,
+    table_names: HashMap<u64, (String, String)>,
+    // bytes of each transaction, and the start of the biggest
+    transaction_sizes: Vec<u64>,
+    largest_transaction: Option<(u64, u64)>,
+    first_timestamp: Option<u32>,
+    last_timestamp: Option<u32>,
+}
+
+#[derive(Default)]
+struct Counter {
+    events: u64,
+    bytes: u64,
+}
+
+#[derive(Default)]
+struct TableCounter {
+    inserted: u64,
+    updated: u64,
+    deleted: u64,
+    bytes: u64,
+}
+
+impl Stats {
+    fn add_event(&mut self, event: &Event, data: &EventData) {
+        let bytes = u64::from(event.event_length());
+        let counter = self.event_types.entry(format!("{:?}", event.type_code())).or_default();
+        counter.events += 1;
+        counter.bytes += bytes;
+        // artificial events, e.g. the Rotate starting a relay log, have no time
+        if event.timestamp() != 0 {
+            self.first_timestamp = Some(self.first_timestamp.map_or(event.timestamp(), |t| t.min(event.timestamp())));
+            self.last_timestamp = Some(self.last_timestamp.map_or(event.timestamp(), |t| t.max(event.timestamp())));
+        }
+        let (table_id, rows) = match data {
+            EventData::TableMapEvent(table_map) => {
+                let name = (table_map.schema.clone(), table_map.table.clone());
+                self.table_names.insert(table_map.table_id, name);
+                (table_map.table_id, None)
+            }
+            EventData::WriteRowsEvent(rows) => (rows.table_id, Some((rows.rows.len() as u64, 0, 0))),
+            EventData::UpdateRowsEvent(rows) => (rows.table_id, Some((0, rows.rows.len() as u64, 0))),
+            EventData::DeleteRowsEvent(rows) => (rows.table_id, Some((0, 0, rows.rows.len() as u64))),
+            _ => return,
+        };
+        if let Some(name) = self.table_names.get(&table_id) {
+            let table = self.tables.entry(name.clone()).or_default();
+            table.bytes += bytes;
+            if let Some((inserted, updated, deleted)) = rows {
+                table.inserted += inserted;
+                table.updated += updated;
+                table.deleted += deleted;
+            }
+        }
+    }
+
+    fn add_transaction(&mut self, transaction: &Transaction) {
+        let bytes = transaction.events().iter().map(|e| u64::from(e.event_length())).sum();
+        if self.largest_transaction.is_none_or(|(largest, _)| bytes > largest) {
+            self.largest_transaction = Some((bytes, transaction.start_offset()));
+        }
+        self.transaction_sizes.push(bytes);
+    }
+
+    fn write(&self, out: &mut dyn Write) -> std::io::Result<()> {
+        if let (Some(first), Some(last)) = (self.first_timestamp, self.last_timestamp) {
+            writeln!(out, "Time span: {} to {} UTC ({}s)", format_datetime(first), format_datetime(last), last - first)?;
+        }
+        let events: u64 = self.event_types.values().map(|c| c.events).sum();
+        let bytes: u64 = self.event_types.values().map(|c| c.bytes).sum();
+        writeln!(out, "Events: {}, {}", events, human_bytes(bytes))?;
+
+        let mut event_types: Vec<_> = self.event_types.iter().collect();
+        event_types.sort_by_key(|(_, counter)| Reverse(counter.bytes));
+        writeln!(out, "\n{:<28} {:>10} {:>12}", "Event type", "Count", "Bytes")?;
+        for (name, counter) in event_types {
+            writeln!(out, "{:<28} {:>10} {:>12}", name, counter.events, human_bytes(counter.bytes))?;
+        }
+
+        if !self.tables.is_empty() {
+            let mut tables: Vec<_> = self.tables.iter().collect();
+            tables.sort_by_key(|(_, counter)| Reverse(counter.bytes));
+            writeln!(out, "\n{:<40} {:>10} {:>10} {:>10} {:>12}", "Table", "Inserted", "Updated", "Deleted", "Bytes")?;
+            for ((schema, table), counter) in tables {
+                let name = format!("{}.{}", schema, table);
+                writeln!(out, "{:<40} {:>10} {:>10} {:>10} {:>12}",
+                    name, counter.inserted, counter.updated, counter.deleted, human_bytes(counter.bytes))?;
+            }
+        }
+
+        let mut sizes = self.transaction_sizes.clone();
+        sizes.sort_unstable();
+        writeln!(out, "\nTransactions: {}, {}", sizes.len(), human_bytes(sizes.iter().sum()))?;
+        if let Some((largest, offset)) = self.largest_transaction {
+            let percentile = |p: usize| human_bytes(sizes[(sizes.len() * p).div_ceil(100).max(1) - 1]);
+            writeln!(out, "  min {}, p50 {}, p90 {}, p99 {}, max {} (at {})",
+                human_bytes(sizes[0]), percentile(50), percentile(90), percentile(99), human_bytes(largest), offset)?;
+        }
+        Ok(())
+    }
+
+    fn to_json(&self) -> serde_json::Value {
+        let event_types: serde_json::Map<_, _> = self.event_types.iter()
+            .map(|(name, c)| (name.clone(), serde_json::json!({"events": c.events, "bytes": c.bytes})))
+            .collect();
+        let tables: serde_json::Map<_, _> = self.tables.iter()
+            .map(|((schema, table), c)| (format!("{}.{}", schema, table), serde_json::json!({
+                "inserted": c.inserted, "updated": c.updated, "deleted": c.deleted, "bytes": c.bytes,
+            })))
+            .collect();
+        let mut sizes = self.transaction_sizes.clone();
+        sizes.sort_unstable();
+        let mut transactions = serde_json::json!({"count": sizes.len(), "bytes": sizes.iter().sum::<u64>()});
+        if let Some((largest, offset)) = self.largest_transaction {
+            let percentile = |p: usize| sizes[(sizes.len() * p).div_ceil(100).max(1) - 1];
+            transactions["min"] = sizes[0].into();
+            transactions["p50"] = percentile(50).into();
+            transactions["p90"] = percentile(90).into();
+            transactions["p99"] = percentile(99).into();
+            transactions["max"] = largest.into();
+            transactions["largest_offset"] = offset.into();
+        }
+        serde_json::json!({
+            "first_timestamp": self.first_timestamp,
+            "last_timestamp": self.last_timestamp,
+            "events": self.event_types.values().map(|c| c.events).sum::<u64>(),
+            "bytes": self.event_types.values().map(|c| c.bytes).sum::<u64>(),
+            "event_types": event_types,
+            "tables": tables,
+            "transactions": transactions,
+        })
+    }
+}
+
+fn human_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KiB", "MiB", "GiB", "TiB"];
+    if bytes < 1024 {
+        return format!("{} B", bytes);
+    }
+    let mut value = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", value, UNITS[unit])
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::Parser;
+    use crate::{Cli, Command};
+
+    fn stats(args: &[&str]) -> String {
+        let cli = Cli::parse_from([&["mysql-binlog", "stats"], args].concat());
+        let mut out = vec![];
+        match &cli.command {
+            Command::Stats(args) => super::run(args, &mut out).unwrap(),
+            _ => unreachable!(),
+        }
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn test_stats() {
+        //given
+        let path = "tests/asset/mysql-bin.100746";
+
+        //when
+        let text = stats(&[path]);
+
+        //then
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines[0], "Time span: 2021-10-01 00:00:00 to 2021-10-01 00:00:00 UTC (0s)");
+        assert_eq!(lines[1], "Events: 15, 1.0 KiB");
+        assert!(lines.contains(&"QueryEvent                            3        389 B"));
+        assert!(lines.contains(&"test.users                                        2          1          0        217 B"));
+        assert!(lines.contains(&"Transactions: 3, 863 B"));
+        assert!(lines.contains(&"  min 276 B, p50 277 B, p90 310 B, p99 310 B, max 310 B (at 154)"));
+    }
+
+    #[test]
+    fn test_stats_as_json() {
+        //given
+        let path = "tests/asset/mysql-bin.100746";
+
+        //when
+        let json = stats(&["--format", "json", path]);
+
+        //then
+        let report: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(report["first_timestamp"], 1633046400);
+        assert_eq!(report["events"], 15);
+        assert_eq!(report["event_types"]["QueryEvent"], serde_json::json!({"events": 3, "bytes": 389}));
+        assert_eq!(report["tables"]["test.users"], serde_json::json!({"inserted": 2, "updated": 1, "deleted": 0, "bytes": 217}));
+        assert_eq!(report["transactions"], serde_json::json!({
+            "count": 3, "bytes": 863, "min": 276, "p50": 277, "p90": 310, "p99": 310, "max": 310, "largest_offset": 154,
+        }));
+    }
+}
@@ -0,0 +1,145 @@
+use std::io::Write;
+use std::path::PathBuf;
+use std::time::Duration;
+use clap::Args;
+use mysql_binlog_parser_rust::binlog_file::BinlogFile;
+use mysql_binlog_parser_rust::errors::BinlogFileError;
+use mysql_binlog_parser_rust::event::{Event, TypeCode};
+use mysql_binlog_parser_rust::follow::BinlogFollower;
+use mysql_binlog_parser_rust::position::BinlogPosition;
+use crate::output::OutputArgs;
+use crate::filter::{check_start_position, FilterArgs};
+use crate::remote::RemoteArgs;
+
+#[derive(Args)]
+pub(crate) struct TailArgs {
+    /// The binlog file the server is writing, followed on into the files it rotates to
+    #[arg(required_unless_present = "host", conflicts_with = "host")]
+    file: Option<PathBuf>,
+    #[command(flatten)]
+    remote: RemoteArgs,
+    /// Print from the start of the current file instead of only the events written from now on
+    #[arg(long, conflicts_with = "start_position")]
+    from_start: bool,
+    /// With a file, exit after this many seconds without a new event instead of waiting for ever
+    #[arg(long, value_name = "SECONDS", value_parser = parse_seconds, conflicts_with = "host")]
+    idle_timeout: Option<Duration>,
+    #[command(flatten)]
+    filter: FilterArgs,
+    #[command(flatten)]
+    output: OutputArgs,
+}
+
+// Prints events as they're written, like `tail -f`. --start-position is an offset of the file
+// being followed, or of the server's current file. Output is flushed line by line, as standard
+// output is line buffered.
+pub(crate) fn run(args: &TailArgs, out: &mut dyn Write) -> crate::Result<()> {
+    if args.filter.stop_position().is_some() {
+        return Err("tail follows the binlog across files, so --stop-position has no file to apply to; use --stop-datetime".into());
+    }
+    if let Some(path) = &args.file {
+        let start = match args.filter.start_position() {
+            Some(position) => {
+                check_start_position(path, position)?;
+                position
+            }
+            None if args.from_start => 4,
+            None => BinlogFile::from_path(path)?.verify()?.end_offset,
+        };
+        let mut follower = BinlogFollower::new(path)?;
+        if let Some(timeout) = args.idle_timeout {
+            follower = follower.idle_timeout(timeout);
+        }
+        let file = path.file_name().map_or_else(Default::default, |name| name.to_string_lossy());
+        return args.output.write(args.filter.select(CatchUp { inner: follower, start: Some(start) }), &file, out);
+    }
+    let client = args.remote.client().ok_or("give a binlog file or --host")?;
+    let current = client.current_position()?;
+    let start = match args.filter.start_position() {
+        Some(position) => BinlogPosition::new(&current.file, position),
+        None if args.from_start => BinlogPosition::new(&current.file, 4),
+        None => current,
+    };
+    let events = client.start_position(start).skip_heartbeats(true).reconnecting();
+    args.output.write(args.filter.select(events), "", out)
+}
+
+// Skips the events of the first file before `start`, except its FormatDescriptionEvent, which
+// the events after it are decoded with.
+struct CatchUp<It> {
+    inner: It,
+    start: Option<u64>,
+}
+
+impl<It> Iterator for CatchUp<It> where
+    It: Iterator<Item = Result<Event, BinlogFileError>>
+{
+    type Item = Result<Event, BinlogFileError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let event = match self.inner.next()? {
+                Ok(event) => event,
+                Err(e) => return Some(Err(e)),
+            };
+            match self.start {
+                Some(start) if event.offset() < start => {
+                    // the file ended with it, so `start` was its end
+                    if event.type_code() == TypeCode::RotateEvent {
+                        self.start = None;
+                    }
+                    if event.type_code() == TypeCode::FormatDescriptionEvent {
+                        return Some(Ok(event));
+                    }
+                }
+                _ => {
+                    self.start = None;
+                    return Some(Ok(event));
+                }
+            }
+        }
+    }
+}
+
+fn parse_seconds(text: &str) -> Result<Duration, String> {
+    text.parse()
+        .ok()
+        .and_then(|seconds| Duration::try_from_secs_f64(seconds).ok())
+        .ok_or_else(|| format!("expected a number of seconds, got {:?}", text))
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::Parser;
+    use crate::{Cli, Command};
+
+    fn run(args: &[&str]) -> String {
+        let cli = Cli::parse_from([&["mysql-binlog"], args].concat());
+        let mut out = vec![];
+        match &cli.command {
+            Command::Tail(args) => super::run(args, &mut out).unwrap(),
+            Command::Dump(args) => crate::dump::run(args, &mut out).unwrap(),
+            _ => unreachable!(),
+        }
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn test_tail_file() {
+        //given
+        let path = "tests/asset/mysql-bin.100746";
+
+        //when
+        let from_start = run(&["tail", "-v", "--from-start", "--idle-timeout", "0.1", path]);
+        let from_position = run(&["tail", "-v", "--start-position", "740", "--idle-timeout", "0.1", path]);
+        let from_end = run(&["tail", "-v", "--idle-timeout", "0.1", path]);
+
+        //then
+        assert_eq!(from_start, run(&["dump", "-v", path]));
+        assert!(from_position.contains("# at 4\n"));
+        assert!(!from_position.contains("# at 709\n"));
+        assert!(from_position.contains("### UPDATE `test`.`users`"));
+        assert!(from_end.contains("# at 4\n"));
+        assert!(!from_end.contains("# at 123\n"));
+    }
+}
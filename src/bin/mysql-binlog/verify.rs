@@ -0,0 +1,123 @@
+use std::io::{Cursor, Read, Write};
+use std::path::{Path, PathBuf};
+use clap::Args;
+use mysql_binlog_parser_rust::binlog_file::BinlogFile;
+use mysql_binlog_parser_rust::binlog_reader::BinlogReader;
+use mysql_binlog_parser_rust::errors::BinlogFileError;
+use mysql_binlog_parser_rust::verify::VerifyReport;
+use crate::datetime::format_datetime;
+
+#[derive(Args)]
+pub(crate) struct VerifyArgs {
+    /// Binlog files; - reads standard input
+    #[arg(required = true)]
+    files: Vec<PathBuf>,
+    /// Only print the files with problems
+    #[arg(short, long)]
+    quiet: bool,
+}
+
+// Checks each file's magic, event headers, next positions, checksums and that it ends with a
+// Rotate or Stop event. Any problem, or a file that can't be read, fails the command once all
+// the files have been checked.
+pub(crate) fn run(args: &VerifyArgs, out: &mut dyn Write) -> crate::Result<()> {
+    let mut failed = 0;
+    for path in &args.files {
+        match verify(path) {
+            Ok(report) if report.is_ok() => {
+                if !args.quiet {
+                    writeln!(out, "{}: OK, {}", path.display(), summary(&report))?;
+                }
+            }
+            Ok(report) => {
+                failed += 1;
+                writeln!(out, "{}: {} problem(s), {}", path.display(), report.problems.len(), summary(&report))?;
+                for (offset, problem) in &report.problems {
+                    writeln!(out, "  at {}: {}", offset, problem)?;
+                }
+            }
+            Err(e) => {
+                failed += 1;
+                writeln!(out, "{}: {}", path.display(), crate::error_chain(&e))?;
+            }
+        }
+    }
+    out.flush()?;
+    if failed > 0 {
+        return Err(format!("{} of {} file(s) failed verification", failed, args.files.len()).into());
+    }
+    Ok(())
+}
+
+fn verify(path: &Path) -> Result<VerifyReport, BinlogFileError> {
+    if path != Path::new("-") {
+        match BinlogFile::from_path(path) {
+            Err(BinlogFileError::Compressed(_)) => {}
+            file => return file?.verify(),
+        }
+    }
+    // standard input and compressed archives are read into memory, as verifying seeks about
+    let reader = if path == Path::new("-") {
+        BinlogReader::from_reader(Box::new(std::io::stdin()) as Box<dyn Read + Send>)?
+    } else {
+        BinlogReader::from_path(path)?
+    };
+    let mut bytes = b"\xfebin".to_vec();
+    reader.into_inner().read_to_end(&mut bytes)?;
+    BinlogFile::from_reader(Cursor::new(bytes))?.verify()
+}
+
+fn summary(report: &VerifyReport) -> String {
+    let mut text = format!("{} events, {} bytes", report.event_count, report.end_offset);
+    if let (Some(first), Some(last)) = (report.first_timestamp, report.last_timestamp) {
+        text.push_str(&format!(", {} to {} UTC", format_datetime(first), format_datetime(last)));
+    }
+    text.push_str(&format!(", checksums {:?}", report.checksum_algorithm));
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::Parser;
+    use crate::{Cli, Command};
+
+    fn verify(args: &[&str]) -> (String, Option<String>) {
+        let cli = Cli::parse_from([&["mysql-binlog", "verify"], args].concat());
+        let mut out = vec![];
+        let result = match &cli.command {
+            Command::Verify(args) => super::run(args, &mut out),
+            _ => unreachable!(),
+        };
+        (String::from_utf8(out).unwrap(), result.err().map(|e| e.to_string()))
+    }
+
+    #[test]
+    fn test_verify_files() {
+        //given
+        let dir = std::env::temp_dir().join(format!("mysql-binlog-verify-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let mut bytes = std::fs::read("tests/asset/mysql-bin.100746").unwrap();
+        bytes.truncate(1040);
+        let truncated = dir.join("mysql-bin.000002");
+        std::fs::write(&truncated, bytes).unwrap();
+        let not_binlog = dir.join("notes.txt");
+        std::fs::write(&not_binlog, "hello").unwrap();
+        let (truncated, not_binlog) = (truncated.to_str().unwrap(), not_binlog.to_str().unwrap());
+
+        //when
+        let (clean, clean_error) = verify(&["tests/asset/mysql-bin.100746"]);
+        let (all, error) = verify(&["--quiet", "tests/asset/mysql-bin.100746", truncated, not_binlog]);
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        //then
+        assert_eq!(clean, "tests/asset/mysql-bin.100746: OK, 15 events, 1064 bytes, \
+            2021-10-01 00:00:00 to 2021-10-01 00:00:00 UTC, checksums Crc32\n");
+        assert_eq!(clean_error, None);
+        let lines: Vec<&str> = all.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert!(lines[0].starts_with(&format!("{}: 1 problem(s), 14 events", truncated)));
+        assert_eq!(lines[1], "  at 1017: event of 47 bytes cut off after 23");
+        assert!(lines[2].starts_with(&format!("{}: bad magic value", not_binlog)));
+        assert_eq!(error.as_deref(), Some("2 of 3 file(s) failed verification"));
+    }
+}
@@ -1,25 +1,35 @@
-use std::io::{Seek, Read};
+use std::io::{Seek, Read, SeekFrom};
 use std::path::Path;
 use std::fs::File;
-use crate::errors::BinlogFileError;
+use std::sync::{Arc, Mutex};
+use crate::encryption;
+use crate::checksum::{verify_crc32, ChecksumAlgorithm, ChecksumVerifier};
+use crate::compression::Compression;
+use crate::errors::{BinlogFileError, EventParseError};
+use crate::utils::read_full;
+use crate::event::{Event, EventData, EventHeader, TypeCode, LOG_EVENT_BINLOG_IN_USE_F};
+use crate::gtid::{GtidSet, ResumeCheck};
+use crate::options::ParseMode;
+use crate::filter::{EventFilter, FilteredEvents};
+use crate::transaction::{GtidTracker, Transactions};
+use crate::verify::{self, VerifyReport};
 
 pub struct BinlogFile<I: Seek + Read> {
     file: I,
-    event_set_start_offset: u64
+    event_set_start_offset: u64,
+    mode: ParseMode,
 }
 
 impl BinlogFile<File> {
     pub fn from_path<P: AsRef<Path>>(path: P) -> Result<Self, BinlogFileError> {
-        // need it?
-        // match path.as_ref().extension() {
-        //     Some(s) => {
-        //         if s.to_str() == "gz" {
-        //
-        //         }
-        //     }
-        //     _ => {}
-        // }
-        let file = File::open(path.as_ref()).map_err(BinlogFileError::OpenError)?;
+        let mut file = File::open(path.as_ref()).map_err(BinlogFileError::OpenError)?;
+        let mut head = [0u8; 4];
+        let filled = read_full(&mut file, &mut head)?;
+        match Compression::detect(path.as_ref(), &head[..filled]) {
+            Compression::None => {}
+            compression => return Err(BinlogFileError::Compressed(compression)),
+        }
+        file.seek(SeekFrom::Start(0))?;
         Self::from_reader(file)
     }
 }
@@ -31,20 +41,572 @@ impl<I> BinlogFile<I> where
         // https://dev.mysql.com/doc/internals/en/binary-log-structure-and-contents.html
         let mut magic_number_bytes = [0u8; 4];
         reader.read_exact(&mut magic_number_bytes)?;
-        if magic_number_bytes != [0xfe, 0x62, 0x69, 0x6e] {
-            return Err(BinlogFileError::BadMagic(magic_number_bytes));
-        }
+        encryption::check_magic(magic_number_bytes, &mut reader)?;
 
         Ok(BinlogFile {
             file: reader,
-            event_set_start_offset: 4
+            event_set_start_offset: 4,
+            mode: ParseMode::Strict,
         })
     }
+
+    pub fn parse_mode(mut self, mode: ParseMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    pub fn event_set_start_offset(&self) -> u64 {
+        self.event_set_start_offset
+    }
+
+    pub fn into_inner(self) -> I {
+        self.file
+    }
+
+    pub fn events(&mut self) -> EventIter<'_, I> {
+        let offset = self.event_set_start_offset;
+        EventIter {
+            file: &mut self.file,
+            offset,
+            seeked: false,
+            finished: false,
+            checksum: ChecksumVerifier::default(),
+            mode: self.mode,
+            last_timestamp: None,
+            gtids: GtidTracker::new(),
+        }
+    }
+
+    // start iterating at a known event boundary, e.g. from SHOW MASTER STATUS or a checkpoint
+    pub fn events_from(&mut self, pos: u64) -> Result<EventIter<'_, I>, BinlogFileError> {
+        let mut events = self.events();
+        events.seek_to(pos)?;
+        Ok(events)
+    }
+
+    pub fn events_since(&mut self, timestamp: u32) -> Result<EventIter<'_, I>, BinlogFileError> {
+        let mut events = self.events();
+        events.seek_to_timestamp(timestamp)?;
+        Ok(events)
+    }
+
+    pub fn events_from_gtid_set(&mut self, executed: &GtidSet) -> Result<EventIter<'_, I>, BinlogFileError> {
+        let mut events = self.events();
+        events.seek_to_gtid(executed)?;
+        Ok(events)
+    }
+
+    pub fn headers(&mut self) -> HeaderIter<'_, I> {
+        let offset = self.event_set_start_offset;
+        HeaderIter {
+            file: &mut self.file,
+            offset,
+            finished: false,
+            mode: self.mode,
+        }
+    }
+
+    // Events from this iterator only carry their headers; bodies are read from the file when
+    // data() is first called, so filtering on header fields doesn't copy discarded payloads.
+    // A bad checksum is an error from try_data() then, in strict mode, rather than from next().
+    pub fn lazy_events(self) -> LazyEventIter<I> where
+        I: Send + 'static
+    {
+        LazyEventIter {
+            source: Arc::new(Mutex::new(self.file)),
+            offset: self.event_set_start_offset,
+            finished: false,
+            checksum: ChecksumVerifier::default(),
+            mode: self.mode,
+        }
+    }
+
+    // Whether the server still has this file open (or crashed before closing it), from the in-use
+    // flag of its FormatDescriptionEvent: an active file is better read with BinlogFollower.
+    pub fn is_in_use(&mut self) -> Result<bool, BinlogFileError> {
+        self.file.seek(SeekFrom::Start(self.event_set_start_offset))?;
+        Ok(match read_header(&mut self.file, self.event_set_start_offset)? {
+            Some(header) => {
+                let header = EventHeader::parse(&header);
+                header.type_code == TypeCode::FormatDescriptionEvent && header.flags & LOG_EVENT_BINLOG_IN_USE_F != 0
+            }
+            None => false,
+        })
+    }
+
+    // Walks the whole file checking headers, next_position links, checksums and that it ends
+    // with Rotate/Stop. Problems go into the report; only I/O failures are errors.
+    pub fn verify(&mut self) -> Result<VerifyReport, BinlogFileError> {
+        verify::verify(&mut self.file, self.event_set_start_offset)
+    }
+
+    // the GTIDs executed before the file; None when it has no Previous_gtids, as before MySQL 5.6
+    pub fn previous_gtids(&mut self) -> Result<Option<GtidSet>, BinlogFileError> {
+        for event in self.events() {
+            let event = event?;
+            match event.type_code() {
+                TypeCode::FormatDescriptionEvent | TypeCode::StartEncryptionEvent => {}
+                TypeCode::PreviousGtidsLogEvent => {
+                    return match Event::parse_event_data_by_type_code(event.type_code(), event.try_data()?)? {
+                        Some(EventData::PreviousGtidsLogEvent { gtids }) => Ok(Some(gtids)),
+                        _ => Ok(None),
+                    };
+                }
+                _ => break,
+            }
+        }
+        Ok(None)
+    }
+
+    // whether a reader that has executed `checkpoint` can resume from this file without
+    // skipping transactions that are only in older, perhaps purged, files
+    pub fn check_resume(&mut self, checkpoint: &GtidSet) -> Result<ResumeCheck, BinlogFileError> {
+        Ok(match self.previous_gtids()? {
+            Some(previous_gtids) => ResumeCheck::new(checkpoint, &previous_gtids),
+            None => ResumeCheck::Unknown,
+        })
+    }
+
+    pub fn read_event_at(&mut self, offset: u64) -> Result<Event, BinlogFileError> {
+        match self.events_from(offset)?.next() {
+            Some(event) => event,
+            None => Err(BinlogFileError::BadPosition(offset)),
+        }
+    }
+}
+
+pub struct EventIter<'a, I: Seek + Read> {
+    file: &'a mut I,
+    offset: u64,
+    seeked: bool,
+    finished: bool,
+    checksum: ChecksumVerifier,
+    mode: ParseMode,
+    last_timestamp: Option<u32>,
+    gtids: GtidTracker,
+}
+
+impl<'a, I> EventIter<'a, I> where
+    I: Seek + Read
+{
+    // offset of the event the next call to next() will return
+    pub fn offset(&self) -> u64 {
+        self.offset
+    }
+
+    // reposition the iterator, checking that `pos` really is the start of an event
+    pub fn seek_to(&mut self, pos: u64) -> Result<(), BinlogFileError> {
+        if pos < 4 {
+            return Err(BinlogFileError::BadPosition(pos));
+        }
+        self.file.seek(SeekFrom::Start(pos))?;
+        let mut header = [0u8; EventHeader::LEN];
+        self.file.read_exact(&mut header).map_err(|_| BinlogFileError::BadPosition(pos))?;
+        if !EventHeader::parse(&header).is_plausible_at(pos) {
+            return Err(BinlogFileError::BadPosition(pos));
+        }
+
+        self.reposition(pos)
+    }
+
+    // Position at the first event written at or after `timestamp` (seconds since the epoch),
+    // like mysqlbinlog --start-datetime. Only headers are read on the way there. Past the last
+    // event the iterator is left at the end of the file.
+    pub fn seek_to_timestamp(&mut self, timestamp: u32) -> Result<(), BinlogFileError> {
+        let mut pos = 4;
+        loop {
+            self.file.seek(SeekFrom::Start(pos))?;
+            let header = match read_header(self.file, pos)? {
+                Some(header) => EventHeader::parse(&header),
+                None => break,
+            };
+            if header.timestamp >= timestamp {
+                break;
+            }
+            pos += u64::from(header.checked_length(pos, self.mode)?);
+        }
+
+        self.reposition(pos)
+    }
+
+    // Position at the first transaction whose GTID isn't in `executed`, for resuming from a
+    // replica's gtid_executed. Anonymous transactions can't be matched against the set, so the
+    // first one counts as not executed. Only headers and GTID event bodies are read.
+    pub fn seek_to_gtid(&mut self, executed: &GtidSet) -> Result<(), BinlogFileError> {
+        let mut pos = 4;
+        loop {
+            self.file.seek(SeekFrom::Start(pos))?;
+            let header = match read_header(self.file, pos)? {
+                Some(header) => EventHeader::parse(&header),
+                None => break,
+            };
+            match header.type_code {
+                TypeCode::AnonymousGtidLogEvent => break,
+                TypeCode::GtidLogEvent => {
+                    // flags, then the 16 byte server uuid and 8 byte transaction number
+                    let mut body = [0u8; 25];
+                    self.file.read_exact(&mut body)?;
+                    let mut sid = [0u8; 16];
+                    sid.copy_from_slice(&body[1..17]);
+                    let mut gno = [0u8; 8];
+                    gno.copy_from_slice(&body[17..25]);
+                    if !executed.contains(&sid, i64::from_le_bytes(gno)) {
+                        break;
+                    }
+                }
+                _ => {}
+            }
+            pos += u64::from(header.checked_length(pos, self.mode)?);
+        }
+
+        self.reposition(pos)?;
+        // everything skipped is in `executed`
+        self.gtids = GtidTracker::with_executed(executed.clone());
+        Ok(())
+    }
+
+    // The GTIDs executed up to the last transaction read completely, once known: from the
+    // file's Previous_gtids, or the set given to seek_to_gtid(). Seeking elsewhere past the
+    // Previous_gtids loses it.
+    pub fn executed_gtids(&self) -> Option<&GtidSet> {
+        self.gtids.executed()
+    }
+
+    // the checksum algorithm events are being verified against, from the last FormatDescriptionEvent
+    pub fn checksum_algorithm(&self) -> ChecksumAlgorithm {
+        self.checksum.algorithm()
+    }
+
+    fn reposition(&mut self, pos: u64) -> Result<(), BinlogFileError> {
+        // events past the FormatDescriptionEvent are checksummed according to it
+        if pos > 4 {
+            self.file.seek(SeekFrom::Start(4))?;
+            if let Some(header) = read_header(self.file, 4)? {
+                if EventHeader::parse(&header).type_code == TypeCode::FormatDescriptionEvent {
+                    let mut event = Event::parse(&mut (&header[..]).chain(&mut *self.file), 4)?;
+                    self.checksum.check(&header, &mut event, self.mode)?;
+                }
+            }
+        }
+
+        self.offset = pos;
+        self.seeked = false;
+        self.finished = false;
+        self.gtids = GtidTracker::new();
+        Ok(())
+    }
+
+    // a filter's start position is checked per event; use events_from() to jump straight to it
+    pub fn with_filter(self, filter: EventFilter) -> FilteredEvents<Self> {
+        FilteredEvents::new(self, filter)
+    }
+
+    pub fn transactions(self) -> Transactions<Self> {
+        Transactions::new(self)
+    }
+
+    // keep going past damaged stretches of the file instead of stopping at the first error
+    pub fn recovering(self) -> RecoveringEventIter<'a, I> {
+        RecoveringEventIter {
+            events: self,
+            finished: false,
+        }
+    }
+
+    // The first offset after `from` that looks like the start of an event, or the end of the
+    // file if there is none. Candidates must have a known type code, a length that fits in the
+    // file, a next_position pointing right past themselves, a timestamp no more than a day before
+    // the last good event and, when the file is checksummed, a valid CRC32.
+    fn find_plausible_event(&mut self, from: u64) -> Result<u64, BinlogFileError> {
+        const WINDOW: usize = 64 * 1024;
+        let file_len = self.file.seek(SeekFrom::End(0))?;
+        let mut window = vec![0u8; WINDOW + EventHeader::LEN];
+        let mut pos = from + 1;
+        while pos + EventHeader::LEN as u64 <= file_len {
+            self.file.seek(SeekFrom::Start(pos))?;
+            let filled = read_full(self.file, &mut window)?;
+            for i in 0..=filled - EventHeader::LEN {
+                let mut header = [0u8; EventHeader::LEN];
+                header.copy_from_slice(&window[i..i + EventHeader::LEN]);
+                if self.is_resync_candidate(&header, pos + i as u64, file_len)? {
+                    return Ok(pos + i as u64);
+                }
+            }
+            pos += (filled - EventHeader::LEN + 1) as u64;
+        }
+        Ok(file_len)
+    }
+
+    fn is_resync_candidate(&mut self, bytes: &[u8; EventHeader::LEN], offset: u64, file_len: u64) -> Result<bool, BinlogFileError> {
+        const MAX_CLOCK_SKEW: u32 = 24 * 60 * 60;
+        let header = EventHeader::parse(bytes);
+        if !header.is_plausible_at(offset)
+            || header.next_position == 0
+            || offset + u64::from(header.event_length) > file_len
+        {
+            return Ok(false);
+        }
+        if let Some(last) = self.last_timestamp {
+            if header.timestamp < last.saturating_sub(MAX_CLOCK_SKEW) {
+                return Ok(false);
+            }
+        }
+        if self.checksum.algorithm() == ChecksumAlgorithm::Crc32 {
+            let mut body = vec![0u8; header.event_length as usize - EventHeader::LEN];
+            self.file.seek(SeekFrom::Start(offset + EventHeader::LEN as u64))?;
+            self.file.read_exact(&mut body)?;
+            return Ok(verify_crc32(offset, bytes, &body).is_ok());
+        }
+        Ok(true)
+    }
+
+    fn read_event(&mut self) -> Result<Option<Event>, BinlogFileError> {
+        if !self.seeked {
+            self.file.seek(SeekFrom::Start(self.offset))?;
+            self.seeked = true;
+        }
+
+        let header = match read_header(self.file, self.offset)? {
+            Some(header) => header,
+            None => return Ok(None),
+        };
+        let mut event = Event::parse_with_mode(&mut (&header[..]).chain(&mut *self.file), self.offset, self.mode)?;
+        self.checksum.check(&header, &mut event, self.mode)?;
+        self.offset += u64::from(event.event_length());
+        self.last_timestamp = Some(event.timestamp());
+        self.gtids.track_read(&event, self.mode)?;
+        Ok(Some(event))
+    }
+}
+
+impl<I> Iterator for EventIter<'_, I> where
+    I: Seek + Read
+{
+    type Item = Result<Event, BinlogFileError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.finished {
+            return None;
+        }
+        match self.read_event() {
+            Ok(Some(event)) => Some(Ok(event)),
+            Ok(None) => {
+                self.finished = true;
+                None
+            }
+            Err(e) => {
+                self.finished = true;
+                Some(Err(e))
+            }
+        }
+    }
+}
+
+// an event, or a stretch of bytes that couldn't be parsed and was stepped over
+#[derive(Debug)]
+pub enum Recovered {
+    Event(Event),
+    Skipped { start: u64, end: u64, error: BinlogFileError },
+}
+
+pub struct RecoveringEventIter<'a, I: Seek + Read> {
+    events: EventIter<'a, I>,
+    finished: bool,
+}
+
+impl<I> Iterator for RecoveringEventIter<'_, I> where
+    I: Seek + Read
+{
+    type Item = Result<Recovered, BinlogFileError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.finished {
+            return None;
+        }
+        let error = match self.events.read_event() {
+            Ok(Some(event)) => return Some(Ok(Recovered::Event(event))),
+            Ok(None) => {
+                self.finished = true;
+                return None;
+            }
+            // the file itself can't be read, so there's nothing to scan
+            Err(e @ BinlogFileError::Io(_)) => {
+                self.finished = true;
+                return Some(Err(e));
+            }
+            Err(e) => e,
+        };
+
+        let start = self.events.offset;
+        match self.events.find_plausible_event(start) {
+            Ok(end) => {
+                self.events.offset = end;
+                self.events.seeked = false;
+                Some(Ok(Recovered::Skipped { start, end, error }))
+            }
+            Err(e) => {
+                self.finished = true;
+                Some(Err(e))
+            }
+        }
+    }
+}
+
+// distinguishes a clean end of file (None) from an event cut off mid-header
+pub(crate) fn read_header<R: Read>(reader: &mut R, offset: u64) -> Result<Option<[u8; EventHeader::LEN]>, BinlogFileError> {
+    let mut header = [0u8; EventHeader::LEN];
+    let filled = read_full(reader, &mut header)?;
+    if filled == 0 {
+        return Ok(None);
+    }
+    if filled < header.len() {
+        return Err(EventParseError::TruncatedEvent {
+            offset,
+            expected_len: EventHeader::LEN as u32,
+            available: filled as u64,
+        }.into());
+    }
+    Ok(Some(header))
+}
+
+pub struct LazyEventIter<I: Seek + Read> {
+    source: Arc<Mutex<I>>,
+    offset: u64,
+    finished: bool,
+    checksum: ChecksumVerifier,
+    mode: ParseMode,
+}
+
+impl<I> LazyEventIter<I> where
+    I: Seek + Read + Send + 'static
+{
+    pub fn with_filter(self, filter: EventFilter) -> FilteredEvents<Self> {
+        FilteredEvents::new(self, filter)
+    }
+
+    pub fn transactions(self) -> Transactions<Self> {
+        Transactions::new(self)
+    }
+
+    fn read_event(&mut self) -> Result<Option<Event>, BinlogFileError> {
+        let (header_bytes, file_len) = {
+            let mut file = self.source.lock()
+                .map_err(|_| std::io::Error::other("binlog reader lock poisoned"))?;
+            let file_len = file.seek(SeekFrom::End(0))?;
+            file.seek(SeekFrom::Start(self.offset))?;
+            match read_header(&mut *file, self.offset)? {
+                Some(header) => (header, file_len),
+                None => return Ok(None),
+            }
+        };
+        let mut header = EventHeader::parse(&header_bytes);
+        header.event_length = header.checked_length(self.offset, self.mode)?;
+        // the body isn't read yet, so a cut off one has to be caught here
+        let available = file_len - self.offset;
+        if available < u64::from(header.event_length) {
+            return Err(EventParseError::TruncatedEvent { offset: self.offset, expected_len: header.event_length, available }.into());
+        }
+
+        // other bodies are checked in strict mode when they're first read
+        let mut event = Event::deferred(header, self.offset, self.source.clone(), self.mode == ParseMode::Strict);
+        if header.type_code == TypeCode::FormatDescriptionEvent {
+            self.checksum.check(&header_bytes, &mut event, self.mode)?;
+        } else {
+            self.checksum.detect(&mut event)?;
+        }
+        self.offset += u64::from(header.event_length);
+        Ok(Some(event))
+    }
+}
+
+impl<I> Iterator for LazyEventIter<I> where
+    I: Seek + Read + Send + 'static
+{
+    type Item = Result<Event, BinlogFileError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.finished {
+            return None;
+        }
+        match self.read_event() {
+            Ok(Some(event)) => Some(Ok(event)),
+            Ok(None) => {
+                self.finished = true;
+                None
+            }
+            Err(e) => {
+                self.finished = true;
+                Some(Err(e))
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct HeaderRecord {
+    pub offset: u64,
+    pub header: EventHeader,
+}
+
+// Reads only the common header of each event and seeks over the body, for building
+// offset/type/time indexes without paying for the payloads.
+pub struct HeaderIter<'a, I: Seek + Read> {
+    file: &'a mut I,
+    offset: u64,
+    finished: bool,
+    mode: ParseMode,
+}
+
+impl<I> HeaderIter<'_, I> where
+    I: Seek + Read
+{
+    fn read_header_record(&mut self) -> Result<Option<HeaderRecord>, BinlogFileError> {
+        self.file.seek(SeekFrom::Start(self.offset))?;
+        let mut header = match read_header(self.file, self.offset)? {
+            Some(header) => EventHeader::parse(&header),
+            None => return Ok(None),
+        };
+        header.event_length = header.checked_length(self.offset, self.mode)?;
+
+        let record = HeaderRecord { offset: self.offset, header };
+        self.offset += u64::from(header.event_length);
+        Ok(Some(record))
+    }
+}
+
+impl<I> Iterator for HeaderIter<'_, I> where
+    I: Seek + Read
+{
+    type Item = Result<HeaderRecord, BinlogFileError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.finished {
+            return None;
+        }
+        match self.read_header_record() {
+            Ok(Some(record)) => Some(Ok(record)),
+            Ok(None) => {
+                self.finished = true;
+                None
+            }
+            Err(e) => {
+                self.finished = true;
+                Some(Err(e))
+            }
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::binlog_file::BinlogFile;
+    use std::io::Cursor;
+    use crate::binlog_file::{BinlogFile, Recovered};
+    use crate::checksum::ChecksumAlgorithm;
+    use crate::errors::{BinlogFileError, EventParseError};
+    use crate::event::TypeCode;
+    use crate::binlog_writer::BinlogWriter;
+    use crate::gtid::{GtidSet, ResumeCheck};
+    use crate::options::ParseMode;
 
     #[test]
     fn test_open_binlog_file() {
@@ -57,4 +619,317 @@ mod tests {
         //then
         assert_eq!(binlog_file.event_set_start_offset, 4);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_iterate_events() {
+        //given
+        let path = "tests/asset/mysql-bin.100746";
+        let mut binlog_file = BinlogFile::from_path(path).unwrap();
+
+        //when
+        let events: Vec<_> = binlog_file.events().collect::<Result<_, _>>().unwrap();
+
+        //then
+        assert_eq!(events.len(), 15);
+        assert_eq!(events[0].type_code(), TypeCode::FormatDescriptionEvent);
+        assert_eq!(events[0].offset(), 4);
+        assert_eq!(events[1].offset(), events[0].next_position());
+        assert_eq!(events.last().unwrap().type_code(), TypeCode::RotateEvent);
+    }
+
+    #[test]
+    fn test_events_from_position() {
+        //given
+        let path = "tests/asset/mysql-bin.100746";
+        let mut binlog_file = BinlogFile::from_path(path).unwrap();
+        let third = binlog_file.events().nth(2).unwrap().unwrap();
+
+        //when
+        let events: Vec<_> = binlog_file.events_from(third.offset()).unwrap().collect::<Result<_, _>>().unwrap();
+        let misaligned = binlog_file.events_from(third.offset() + 1);
+
+        //then
+        assert_eq!(events.len(), 13);
+        assert_eq!(events[0].offset(), third.offset());
+        assert_eq!(events[0].type_code(), TypeCode::AnonymousGtidLogEvent);
+        assert!(misaligned.is_err());
+    }
+
+    #[test]
+    fn test_read_event_at() {
+        //given
+        let path = "tests/asset/mysql-bin.100746";
+        let mut binlog_file = BinlogFile::from_path(path).unwrap();
+        let offsets: Vec<u64> = binlog_file.events().map(|e| e.unwrap().offset()).collect();
+
+        //when
+        let rotate = binlog_file.read_event_at(offsets[14]).unwrap();
+        let fde = binlog_file.read_event_at(4).unwrap();
+
+        //then
+        assert_eq!(rotate.type_code(), TypeCode::RotateEvent);
+        assert_eq!(rotate.offset(), offsets[14]);
+        assert_eq!(fde.type_code(), TypeCode::FormatDescriptionEvent);
+    }
+
+    #[test]
+    fn test_scan_headers() {
+        //given
+        let path = "tests/asset/mysql-bin.100746";
+        let mut binlog_file = BinlogFile::from_path(path).unwrap();
+        let events: Vec<_> = binlog_file.events().map(|e| e.unwrap().header()).collect();
+
+        //when
+        let headers: Vec<_> = binlog_file.headers().collect::<Result<_, _>>().unwrap();
+
+        //then
+        assert_eq!(headers.len(), events.len());
+        assert_eq!(headers.iter().map(|r| r.header).collect::<Vec<_>>(), events);
+        assert_eq!(headers[1].offset, u64::from(headers[0].header.next_position));
+    }
+
+    #[test]
+    fn test_lazy_events() {
+        //given
+        let path = "tests/asset/mysql-bin.100746";
+        let eager: Vec<_> = BinlogFile::from_path(path).unwrap().events().map(|e| e.unwrap()).collect();
+
+        //when
+        let lazy: Vec<_> = BinlogFile::from_path(path).unwrap().lazy_events().map(|e| e.unwrap()).collect();
+
+        //then
+        assert_eq!(lazy.len(), eager.len());
+        assert!(!lazy[5].is_loaded());
+        assert_eq!(lazy[5].data(), eager[5].data());
+        assert!(lazy[5].is_loaded());
+        assert!(!lazy[6].is_loaded());
+    }
+
+    #[test]
+    fn test_lazy_events_check_length_and_checksum() {
+        //given
+        let mut bytes = std::fs::read("tests/asset/mysql-bin.100746").unwrap();
+        // the CREATE TABLE statement at 219, and the Rotate at 1017 cut short
+        bytes[219 + 100] ^= 0xff;
+        bytes.truncate(1040);
+        let open = |mode| BinlogFile::from_reader(Cursor::new(bytes.clone())).unwrap().parse_mode(mode);
+
+        //when
+        let strict: Vec<_> = open(ParseMode::Strict).lazy_events().collect();
+        let lenient: Vec<_> = open(ParseMode::Lenient).lazy_events().collect();
+
+        //then
+        assert_eq!(strict.len(), 15);
+        assert!(matches!(
+            strict.last().unwrap(),
+            Err(BinlogFileError::EventParseError(EventParseError::TruncatedEvent { offset: 1017, expected_len: 47, available: 23 }))
+        ));
+        let events: Vec<_> = strict.iter().take(14).map(|e| e.as_ref().unwrap()).collect();
+        assert!(matches!(events[3].try_data(), Err(EventParseError::ChecksumMismatch { offset: 219, .. })));
+        assert!(events.iter().filter(|e| e.offset() != 219).all(|e| e.try_data().is_ok()));
+        assert!(lenient[3].as_ref().unwrap().try_data().is_ok());
+        assert!(lenient.last().unwrap().is_err());
+    }
+
+    #[test]
+    fn test_seek_to_timestamp() {
+        //given
+        let mut bytes = vec![0xfe, 0x62, 0x69, 0x6e];
+        for (timestamp, xid) in [(100u32, 1u64), (200, 2), (300, 3)] {
+            bytes.extend_from_slice(&timestamp.to_le_bytes());
+            bytes.extend_from_slice(&[16, 1, 0, 0, 0, 27, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
+            bytes.extend_from_slice(&xid.to_le_bytes());
+        }
+        let mut binlog_file = BinlogFile::from_reader(Cursor::new(bytes)).unwrap();
+
+        //when
+        let since: Vec<_> = binlog_file.events_since(150).unwrap().map(|e| e.unwrap().timestamp()).collect();
+        let exact = binlog_file.events_since(100).unwrap().count();
+        let future = binlog_file.events_since(1000).unwrap().count();
+
+        //then
+        assert_eq!(since, vec![200, 300]);
+        assert_eq!(exact, 3);
+        assert_eq!(future, 0);
+    }
+
+    #[test]
+    fn test_seek_to_gtid() {
+        //given
+        let sid = [0x3e; 16];
+        let mut bytes = vec![0xfe, 0x62, 0x69, 0x6e];
+        for gno in 1..=3i64 {
+            bytes.extend_from_slice(&[0, 0, 0, 0, 33, 1, 0, 0, 0, 44, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1]);
+            bytes.extend_from_slice(&sid);
+            bytes.extend_from_slice(&gno.to_le_bytes());
+            bytes.extend_from_slice(&[0, 0, 0, 0, 16, 1, 0, 0, 0, 27, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
+            bytes.extend_from_slice(&(gno as u64).to_le_bytes());
+        }
+        let mut binlog_file = BinlogFile::from_reader(Cursor::new(bytes)).unwrap();
+        let mut executed = GtidSet::new();
+        executed.add_interval(sid, 1, 3);
+
+        //when
+        let mut events = binlog_file.events_from_gtid_set(&executed).unwrap();
+        let rest: Vec<_> = events.by_ref().map(|e| e.unwrap()).collect();
+        let executed_after = events.executed_gtids().cloned();
+        let everything = binlog_file.events_from_gtid_set(&GtidSet::new()).unwrap().count();
+
+        //then
+        assert_eq!(executed_after.unwrap().intervals(&sid), &[(1, 4)]);
+        assert_eq!(rest.len(), 2);
+        assert_eq!(rest[0].type_code(), TypeCode::GtidLogEvent);
+        assert_eq!(rest[1].data(), &3u64.to_le_bytes());
+        assert_eq!(everything, 6);
+    }
+
+    #[test]
+    fn test_check_resume() {
+        //given
+        let mut fixture = BinlogFile::from_path("tests/asset/mysql-bin.100746").unwrap();
+        let events: Vec<_> = fixture.events().take(2).map(|e| e.unwrap()).collect();
+        let previous_gtids: GtidSet = "3e11fa47-71ca-11e1-9e33-c80aa9429562:1-10".parse().unwrap();
+        let mut writer = BinlogWriter::new(vec![], &events[0]).unwrap();
+        writer.write(events[1].header(), &previous_gtids.encode()).unwrap();
+        let mut binlog_file = BinlogFile::from_reader(Cursor::new(writer.finish().unwrap())).unwrap();
+        let checkpoint: GtidSet = "3e11fa47-71ca-11e1-9e33-c80aa9429562:1-5".parse().unwrap();
+
+        //when
+        let read = binlog_file.previous_gtids().unwrap();
+        let check = binlog_file.check_resume(&checkpoint).unwrap();
+        let fixture_check = fixture.check_resume(&GtidSet::new()).unwrap();
+
+        //then
+        assert_eq!(read, Some(previous_gtids));
+        assert_eq!(check, ResumeCheck::Missing("3e11fa47-71ca-11e1-9e33-c80aa9429562:6-10".parse().unwrap()));
+        assert_eq!(fixture_check, ResumeCheck::Safe);
+    }
+
+    #[test]
+    fn test_checksum_mismatch() {
+        //given
+        let mut bytes = std::fs::read("tests/asset/mysql-bin.100746").unwrap();
+        let mut events = BinlogFile::from_reader(Cursor::new(bytes.clone())).unwrap();
+        let mut iter = events.events();
+        iter.next().unwrap().unwrap();
+        let algorithm = iter.checksum_algorithm();
+        let offsets: Vec<u64> = iter.map(|e| e.unwrap().offset()).collect();
+        // flip a byte of the CREATE TABLE statement
+        bytes[offsets[2] as usize + 100] ^= 0xff;
+        let mut corrupted = BinlogFile::from_reader(Cursor::new(bytes)).unwrap();
+
+        //when
+        let result: Result<Vec<_>, _> = corrupted.events().collect();
+        let resumed = corrupted.events_from(offsets[2]).unwrap().next().unwrap();
+
+        //then
+        assert_eq!(algorithm, ChecksumAlgorithm::Crc32);
+        assert!(matches!(
+            result,
+            Err(BinlogFileError::EventParseError(EventParseError::ChecksumMismatch { offset, .. })) if offset == offsets[2]
+        ));
+        assert!(resumed.is_err());
+    }
+
+    #[test]
+    fn test_lenient_checksum_mismatch() {
+        //given
+        let mut bytes = std::fs::read("tests/asset/mysql-bin.100746").unwrap();
+        // the CREATE TABLE statement at 219
+        bytes[219 + 100] ^= 0xff;
+        let mut binlog_file = BinlogFile::from_reader(Cursor::new(bytes)).unwrap().parse_mode(ParseMode::Lenient);
+
+        //when
+        let events: Vec<_> = binlog_file.events().map(|e| e.unwrap()).collect();
+
+        //then
+        assert_eq!(events.len(), 15);
+        assert!(matches!(events[3].diagnostics(), [EventParseError::ChecksumMismatch { offset: 219, .. }]));
+        assert!(events.iter().filter(|e| e.offset() != 219).all(|e| e.diagnostics().is_empty()));
+    }
+
+    #[test]
+    fn test_truncated_final_event() {
+        //given
+        let mut bytes = std::fs::read("tests/asset/mysql-bin.100746").unwrap();
+        bytes.truncate(1040);
+        let mut binlog_file = BinlogFile::from_reader(Cursor::new(bytes)).unwrap();
+
+        //when
+        let results: Vec<_> = binlog_file.events().collect();
+
+        //then
+        assert_eq!(results.len(), 15);
+        let error = results.last().unwrap().as_ref().unwrap_err();
+        assert_eq!(error.truncated_at(), Some(1017));
+        assert!(matches!(
+            error,
+            BinlogFileError::EventParseError(EventParseError::TruncatedEvent { expected_len: 47, available: 23, .. })
+        ));
+    }
+
+    #[test]
+    fn test_binlog_in_use_flag() {
+        //given
+        let mut bytes = std::fs::read("tests/asset/mysql-bin.100746").unwrap();
+        let mut closed = BinlogFile::from_reader(Cursor::new(bytes.clone())).unwrap();
+        // the server checksums the FDE with this flag cleared, so the file still verifies
+        bytes[4 + 17] |= 0x1;
+        let mut open = BinlogFile::from_reader(Cursor::new(bytes)).unwrap();
+
+        //when
+        let closed_in_use = closed.is_in_use().unwrap();
+        let open_in_use = open.is_in_use().unwrap();
+        let open_fde = open.events().next().unwrap().unwrap();
+
+        //then
+        assert!(!closed_in_use);
+        assert!(open_in_use);
+        assert!(open_fde.is_binlog_in_use());
+    }
+
+    #[test]
+    fn test_event_length_validation() {
+        //given
+        let mut bytes = std::fs::read("tests/asset/mysql-bin.100746").unwrap();
+        // event_length of the first BEGIN query (at 529) zeroed; next_position still says 601
+        bytes[529 + 9..529 + 13].copy_from_slice(&0u32.to_le_bytes());
+
+        //when
+        let strict: Result<Vec<_>, _> = BinlogFile::from_reader(Cursor::new(bytes.clone())).unwrap().events().collect();
+        let lenient: Vec<_> = BinlogFile::from_reader(Cursor::new(bytes)).unwrap()
+            .parse_mode(ParseMode::Lenient)
+            .headers()
+            .map(|r| r.unwrap())
+            .collect();
+
+        //then
+        assert!(matches!(
+            strict,
+            Err(BinlogFileError::EventParseError(EventParseError::BadEventLength { offset: 529, event_length: 0 }))
+        ));
+        assert_eq!(lenient.len(), 15);
+        assert_eq!(lenient[5].header.event_length, 72);
+    }
+
+    #[test]
+    fn test_recovering_events_skip_damaged_bytes() {
+        //given
+        let mut bytes = std::fs::read("tests/asset/mysql-bin.100746").unwrap();
+        // clobber the header of the BEGIN query at 529
+        bytes[529..529 + 19].copy_from_slice(&[0xff; 19]);
+        let mut binlog_file = BinlogFile::from_reader(Cursor::new(bytes)).unwrap();
+
+        //when
+        let results: Vec<_> = binlog_file.events().recovering().map(|r| r.unwrap()).collect();
+
+        //then
+        assert_eq!(results.len(), 15);
+        assert!(matches!(
+            &results[5],
+            Recovered::Skipped { start: 529, end: 601, error: BinlogFileError::EventParseError(EventParseError::BadEventLength { .. }) }
+        ));
+        assert!(matches!(&results[6], Recovered::Event(e) if e.type_code() == TypeCode::TableMapEvent && e.offset() == 601));
+        assert!(matches!(&results[14], Recovered::Event(e) if e.type_code() == TypeCode::RotateEvent));
+    }
+}
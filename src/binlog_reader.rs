@@ -0,0 +1,207 @@
+use std::io::Read;
+use std::path::Path;
+use crate::binlog_file::read_header;
+use crate::encryption::{self, BINLOG_MAGIC};
+use crate::utils::read_full;
+use crate::checksum::ChecksumVerifier;
+use crate::compression;
+use crate::errors::{BinlogFileError, EventParseError};
+use crate::event::{Event, EventHeader, TypeCode};
+use crate::filter::{EventFilter, FilteredEvents};
+use crate::options::ParseMode;
+use crate::gtid::GtidSet;
+use crate::transaction::{GtidTracker, Transactions};
+
+// Forward-only counterpart of BinlogFile for readers that can't seek, such as stdin or a pipe
+// (`mysqlbinlog --raw ... | my-tool`). Only the event being read is held in memory.
+pub struct BinlogReader<R: Read> {
+    reader: R,
+    offset: u64,
+    finished: bool,
+    checksum: ChecksumVerifier,
+    mode: ParseMode,
+    gtids: GtidTracker,
+}
+
+impl BinlogReader<Box<dyn Read + Send>> {
+    // opens a binlog file, decompressing gzip (and with the features, zstd and xz) archives as they're read
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Result<Self, BinlogFileError> {
+        let (_, reader) = compression::open(path.as_ref())?;
+        Self::from_reader(reader)
+    }
+}
+
+impl<R> BinlogReader<R> where
+    R: Read
+{
+    pub fn from_reader(mut reader: R) -> Result<Self, BinlogFileError> {
+        // https://dev.mysql.com/doc/internals/en/binary-log-structure-and-contents.html
+        let mut magic_number_bytes = [0u8; 4];
+        reader.read_exact(&mut magic_number_bytes)?;
+        encryption::check_magic(magic_number_bytes, &mut reader)?;
+
+        Ok(BinlogReader {
+            reader,
+            offset: 4,
+            finished: false,
+            checksum: ChecksumVerifier::default(),
+            mode: ParseMode::Strict,
+            gtids: GtidTracker::new(),
+        })
+    }
+
+    pub fn parse_mode(mut self, mode: ParseMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    // offset of the event the next call to next() will return
+    pub fn offset(&self) -> u64 {
+        self.offset
+    }
+
+    // the GTIDs executed up to the last transaction read completely, once a Previous_gtids was read
+    pub fn executed_gtids(&self) -> Option<&GtidSet> {
+        self.gtids.executed()
+    }
+
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+
+    pub fn with_filter(self, filter: EventFilter) -> FilteredEvents<Self> {
+        FilteredEvents::new(self, filter)
+    }
+
+    pub fn transactions(self) -> Transactions<Self> {
+        Transactions::new(self)
+    }
+
+    fn read_event(&mut self) -> Result<Option<Event>, BinlogFileError> {
+        let mut header = match read_header(&mut self.reader, self.offset)? {
+            Some(header) => header,
+            None => return Ok(None),
+        };
+        if is_concatenated_binlog(&header) {
+            // what was read is the magic and most of the next file's first header
+            header.copy_within(4.., 0);
+            let filled = read_full(&mut self.reader, &mut header[EventHeader::LEN - 4..])?;
+            if filled < 4 {
+                return Err(EventParseError::TruncatedEvent {
+                    offset: 4,
+                    expected_len: EventHeader::LEN as u32,
+                    available: (EventHeader::LEN - 4 + filled) as u64,
+                }.into());
+            }
+            self.offset = 4;
+            self.checksum = ChecksumVerifier::default();
+        }
+        let mut event = Event::parse_with_mode(&mut (&header[..]).chain(&mut self.reader), self.offset, self.mode)?;
+        self.checksum.check(&header, &mut event, self.mode)?;
+        self.offset += u64::from(event.event_length());
+        self.gtids.track_read(&event, self.mode)?;
+        Ok(Some(event))
+    }
+}
+
+// `mysqlbinlog --raw` to stdout and some backup tools write several binlogs back to back. A new
+// one shows as its magic where an event header was expected, followed by the header of its
+// FormatDescriptionEvent (or StartEventV3 from servers before 5.0).
+pub(crate) fn is_concatenated_binlog(header: &[u8]) -> bool {
+    header.len() > 8
+        && header[..4] == BINLOG_MAGIC
+        && matches!(TypeCode::from_byte(header[8]), TypeCode::FormatDescriptionEvent | TypeCode::StartEventV3)
+}
+
+impl<R> Iterator for BinlogReader<R> where
+    R: Read
+{
+    type Item = Result<Event, BinlogFileError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.finished {
+            return None;
+        }
+        match self.read_event() {
+            Ok(Some(event)) => Some(Ok(event)),
+            Ok(None) => {
+                self.finished = true;
+                None
+            }
+            Err(e) => {
+                self.finished = true;
+                Some(Err(e))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::binlog_file::BinlogFile;
+    use crate::binlog_reader::BinlogReader;
+    use crate::errors::BinlogFileError;
+    use crate::event::TypeCode;
+    use crate::parser::Parser;
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+    use std::io::Write;
+
+    #[test]
+    fn test_read_from_non_seekable_reader() {
+        //given
+        let path = "tests/asset/mysql-bin.100746";
+        let bytes = std::fs::read(path).unwrap();
+        let expected: Vec<_> = BinlogFile::from_path(path).unwrap().events().map(|e| e.unwrap().header()).collect();
+
+        //when
+        let reader = BinlogReader::from_reader(&bytes[..]).unwrap();
+        let headers: Vec<_> = reader.map(|e| e.unwrap().header()).collect();
+
+        //then
+        assert_eq!(headers, expected);
+    }
+
+    #[test]
+    fn test_read_gzipped_binlog() {
+        //given
+        let path = "tests/asset/mysql-bin.100746";
+        let expected: Vec<_> = BinlogFile::from_path(path).unwrap().events().map(|e| e.unwrap().header()).collect();
+        let gz_path = std::env::temp_dir().join(format!("binlog-reader-{}.gz", std::process::id()));
+        let mut encoder = GzEncoder::new(std::fs::File::create(&gz_path).unwrap(), Compression::default());
+        encoder.write_all(&std::fs::read(path).unwrap()).unwrap();
+        encoder.finish().unwrap();
+
+        //when
+        let headers: Vec<_> = BinlogReader::from_path(&gz_path).unwrap().map(|e| e.unwrap().header()).collect();
+        let seekable = BinlogFile::from_path(&gz_path);
+        std::fs::remove_file(&gz_path).unwrap();
+
+        //then
+        assert_eq!(headers, expected);
+        assert!(matches!(seekable, Err(BinlogFileError::Compressed(crate::compression::Compression::Gzip))));
+    }
+
+    #[test]
+    fn test_read_concatenated_binlogs() {
+        //given
+        let bytes = std::fs::read("tests/asset/mysql-bin.100746").unwrap();
+        let mut concatenated = bytes.clone();
+        concatenated.extend_from_slice(&bytes);
+
+        //when
+        let events: Vec<_> = BinlogReader::from_reader(&concatenated[..]).unwrap().map(|e| e.unwrap()).collect();
+        let mut parser = Parser::new();
+        let mut fed = vec![];
+        for chunk in concatenated.chunks(5) {
+            fed.extend(parser.feed(chunk).unwrap());
+        }
+
+        //then
+        assert_eq!(events.len(), 30);
+        assert_eq!(events[15].type_code(), TypeCode::FormatDescriptionEvent);
+        assert_eq!(events[15].offset(), 4);
+        assert_eq!(events[29].offset(), 1017);
+        assert_eq!(fed.iter().map(|e| e.offset()).collect::<Vec<_>>(), events.iter().map(|e| e.offset()).collect::<Vec<_>>());
+    }
+}
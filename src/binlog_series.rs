@@ -0,0 +1,255 @@
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::BufReader;
+use std::path::{Component, Path, PathBuf};
+use crate::binlog_reader::BinlogReader;
+use crate::errors::BinlogFileError;
+use crate::event::{Event, EventData, TypeCode};
+use crate::gtid::GtidSet;
+use crate::options::ParseMode;
+use crate::position::BinlogPosition;
+use crate::transaction::GtidTracker;
+
+// A run of binlog files read as one continuous event stream. At the end of each file the
+// series moves on to the file named by its Rotate event, or else to the next listed file.
+pub struct BinlogSeries {
+    dir: PathBuf,
+    queue: VecDeque<PathBuf>,
+    reader: Option<BinlogReader<BufReader<File>>>,
+    current_file: Option<PathBuf>,
+    rotate_to: Option<PathBuf>,
+    gtids: GtidTracker,
+    finished: bool,
+}
+
+impl BinlogSeries {
+    // starts at `path` and follows Rotate events to its siblings in the same directory
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Self {
+        Self::from_files(vec![path.as_ref().to_path_buf()])
+    }
+
+    // every `<base_name>.NNNNNN` file in `dir`, in sequence order
+    pub fn from_dir<P: AsRef<Path>>(dir: P, base_name: &str) -> Result<Self, BinlogFileError> {
+        let prefix = format!("{}.", base_name);
+        let mut files: Vec<(u64, PathBuf)> = vec![];
+        for entry in std::fs::read_dir(dir.as_ref()).map_err(BinlogFileError::OpenError)? {
+            let entry = entry?;
+            let name = entry.file_name();
+            let sequence = name.to_str()
+                .and_then(|n| n.strip_prefix(&prefix))
+                .filter(|s| !s.is_empty() && s.bytes().all(|b| b.is_ascii_digit()))
+                .and_then(|s| s.parse().ok());
+            if let Some(sequence) = sequence {
+                files.push((sequence, entry.path()));
+            }
+        }
+        files.sort();
+        let mut series = Self::from_files(files.into_iter().map(|(_, path)| path).collect());
+        series.dir = dir.as_ref().to_path_buf();
+        Ok(series)
+    }
+
+    // the files listed in a server's index file (log-bin.index), the way mysqld tracks its logs
+    pub fn from_index_file<P: AsRef<Path>>(path: P) -> Result<Self, BinlogFileError> {
+        Ok(Self::from_files(read_index_file(path)?))
+    }
+
+    pub(crate) fn from_files(files: Vec<PathBuf>) -> Self {
+        let dir = files.first()
+            .and_then(|f| f.parent())
+            .map(Path::to_path_buf)
+            .unwrap_or_default();
+        BinlogSeries {
+            dir,
+            queue: files.into(),
+            reader: None,
+            current_file: None,
+            rotate_to: None,
+            gtids: GtidTracker::new(),
+            finished: false,
+        }
+    }
+
+    // the file the last returned event came from
+    pub fn current_file(&self) -> Option<&Path> {
+        self.current_file.as_deref()
+    }
+
+    // offset in current_file() of the event the next call to next() will return
+    pub fn offset(&self) -> Option<u64> {
+        self.reader.as_ref().map(|r| r.offset())
+    }
+
+    // checkpoint for resuming after the last returned event
+    pub fn position(&self) -> Option<BinlogPosition> {
+        if let Some(next) = &self.rotate_to {
+            return Some(BinlogPosition::new(&file_name(next), 4));
+        }
+        Some(BinlogPosition::new(&file_name(self.current_file.as_ref()?), self.offset()?))
+    }
+
+    // the GTIDs executed up to the last transaction read completely, once a Previous_gtids was read
+    pub fn executed_gtids(&self) -> Option<&GtidSet> {
+        self.gtids.executed()
+    }
+
+    fn next_file(&mut self) -> Option<PathBuf> {
+        match self.rotate_to.take() {
+            Some(path) => {
+                if self.queue.front() == Some(&path) {
+                    self.queue.pop_front();
+                }
+                // a Rotate naming a file that isn't there means the copy of the series ends here
+                if path.exists() { Some(path) } else { None }
+            }
+            None => self.queue.pop_front(),
+        }
+    }
+
+    fn read_event(&mut self) -> Result<Option<Event>, BinlogFileError> {
+        loop {
+            if let Some(reader) = &mut self.reader {
+                match reader.next() {
+                    Some(event) => {
+                        let event = event?;
+                        self.gtids.track_read(&event, ParseMode::Strict)?;
+                        if event.type_code() == TypeCode::RotateEvent {
+                            if let Some(EventData::RotateEvent { next_file, .. }) =
+                                Event::parse_event_data_by_type_code(TypeCode::RotateEvent, event.try_data()?)?
+                            {
+                                self.rotate_to = Some(self.dir.join(next_file));
+                            }
+                        }
+                        return Ok(Some(event));
+                    }
+                    None => self.reader = None,
+                }
+            }
+
+            let path = match self.next_file() {
+                Some(path) => path,
+                None => return Ok(None),
+            };
+            let file = File::open(&path).map_err(BinlogFileError::OpenError)?;
+            self.reader = Some(BinlogReader::from_reader(BufReader::new(file))?);
+            self.current_file = Some(path);
+        }
+    }
+}
+
+pub(crate) fn file_name(path: &Path) -> String {
+    path.file_name().map_or_else(String::new, |n| n.to_string_lossy().into_owned())
+}
+
+// One path per line; relative entries (mysqld writes `./mysql-bin.000001`) are resolved
+// against the directory holding the index.
+pub fn read_index_file<P: AsRef<Path>>(path: P) -> Result<Vec<PathBuf>, BinlogFileError> {
+    let contents = std::fs::read_to_string(path.as_ref()).map_err(BinlogFileError::OpenError)?;
+    let base = path.as_ref().parent().unwrap_or_else(|| Path::new(""));
+    Ok(parse_index(&contents, base))
+}
+
+pub fn parse_index(contents: &str, base: &Path) -> Vec<PathBuf> {
+    contents.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(|line| {
+            let entry = Path::new(line);
+            let path = if entry.is_absolute() { entry.to_path_buf() } else { base.join(entry) };
+            path.components().filter(|c| *c != Component::CurDir).collect()
+        })
+        .collect()
+}
+
+impl Iterator for BinlogSeries {
+    type Item = Result<Event, BinlogFileError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.finished {
+            return None;
+        }
+        match self.read_event() {
+            Ok(Some(event)) => Some(Ok(event)),
+            Ok(None) => {
+                self.finished = true;
+                None
+            }
+            Err(e) => {
+                self.finished = true;
+                Some(Err(e))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+    use crate::binlog_series::{parse_index, BinlogSeries};
+    use crate::event::TypeCode;
+    use crate::event::tests::event_bytes;
+
+    fn write_series(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("binlog-series-{}-{}", name, std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let mut rotate = 4u64.to_le_bytes().to_vec();
+        rotate.extend_from_slice(b"mysql-bin.000002");
+        let mut first = vec![0xfe, 0x62, 0x69, 0x6e];
+        first.extend(event_bytes(16, &1u64.to_le_bytes()));
+        first.extend(event_bytes(4, &rotate));
+        let mut second = vec![0xfe, 0x62, 0x69, 0x6e];
+        second.extend(event_bytes(16, &2u64.to_le_bytes()));
+        std::fs::write(dir.join("mysql-bin.000001"), first).unwrap();
+        std::fs::write(dir.join("mysql-bin.000002"), second).unwrap();
+        std::fs::write(dir.join("mysql-bin.index"), "./mysql-bin.000001\n./mysql-bin.000002\n").unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_follow_rotate_across_files() {
+        //given
+        let dir = write_series("rotate");
+        let mut series = BinlogSeries::from_path(dir.join("mysql-bin.000001"));
+
+        //when
+        let first: Vec<_> = series.by_ref().take(2).map(|e| e.unwrap().type_code()).collect();
+        let last = series.next().unwrap().unwrap();
+
+        //then
+        assert_eq!(first, vec![TypeCode::XidEvent, TypeCode::RotateEvent]);
+        assert_eq!(last.type_code(), TypeCode::XidEvent);
+        assert_eq!(last.offset(), 4);
+        assert_eq!(series.current_file(), Some(dir.join("mysql-bin.000002").as_path()));
+        assert_eq!(series.position().unwrap().to_string(), "mysql-bin.000002:31");
+        assert!(series.next().is_none());
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_series_from_dir() {
+        //given
+        let dir = write_series("dir");
+
+        //when
+        let events: Vec<_> = BinlogSeries::from_dir(&dir, "mysql-bin").unwrap().collect::<Result<_, _>>().unwrap();
+
+        //then
+        assert_eq!(events.len(), 3);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_series_from_index_file() {
+        //given
+        let dir = write_series("index");
+
+        //when
+        let files = parse_index("./mysql-bin.000001\n\n/var/lib/mysql/mysql-bin.000002\n", &dir);
+        let events: Vec<_> = BinlogSeries::from_index_file(dir.join("mysql-bin.index")).unwrap().collect::<Result<_, _>>().unwrap();
+
+        //then
+        assert_eq!(files, vec![dir.join("mysql-bin.000001"), PathBuf::from("/var/lib/mysql/mysql-bin.000002")]);
+        assert_eq!(events.len(), 3);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
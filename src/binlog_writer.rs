@@ -0,0 +1,134 @@
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use crate::checksum::ChecksumAlgorithm;
+use crate::encode::encode_event;
+use crate::encryption::BINLOG_MAGIC;
+use crate::errors::BinlogWriteError;
+use crate::event::{Event, EventHeader, TypeCode, LOG_EVENT_BINLOG_IN_USE_F};
+
+// Writes a binlog file: the magic, a FormatDescriptionEvent, then the events appended to it,
+// each given the next_position it ends at in the new file. Events get a CRC32 when the
+// FormatDescriptionEvent announces binlog_checksum=CRC32, whether or not they had one where
+// they were read.
+//
+//     let mut writer = BinlogWriter::create("filtered.000001", &format_description)?;
+//     for event in events {
+//         writer.write_event(&event?)?;
+//     }
+//     writer.finish()?;
+pub struct BinlogWriter<W: Write> {
+    writer: W,
+    offset: u64,
+    checksum: bool,
+}
+
+impl BinlogWriter<BufWriter<File>> {
+    pub fn create<P: AsRef<Path>>(path: P, format_description: &Event) -> Result<Self, BinlogWriteError> {
+        Self::new(BufWriter::new(File::create(path)?), format_description)
+    }
+}
+
+impl<W> BinlogWriter<W> where
+    W: Write
+{
+    // Starts the binlog with `format_description`, marked as a closed file, as the server
+    // leaves it after a rotation.
+    pub fn new(mut writer: W, format_description: &Event) -> Result<Self, BinlogWriteError> {
+        if format_description.type_code() != TypeCode::FormatDescriptionEvent {
+            return Err(BinlogWriteError::NotFormatDescription(format_description.type_code()));
+        }
+        let body = format_description.raw_body()?;
+        let algorithm = ChecksumAlgorithm::from_format_description(body);
+        // the FormatDescriptionEvent has a checksum of its own whenever it names an algorithm
+        let payload = match algorithm {
+            Some(_) => &body[..body.len() - ChecksumAlgorithm::CRC32_LEN],
+            None => body,
+        };
+        let mut header = format_description.header();
+        header.flags &= !LOG_EVENT_BINLOG_IN_USE_F;
+        writer.write_all(&BINLOG_MAGIC)?;
+        let mut binlog = BinlogWriter { writer, offset: BINLOG_MAGIC.len() as u64, checksum: algorithm.is_some() };
+        binlog.write(header, payload)?;
+        binlog.checksum = algorithm == Some(ChecksumAlgorithm::Crc32);
+        Ok(binlog)
+    }
+
+    // Appends `event` with its body as it is, returning the offset it now starts at.
+    pub fn write_event(&mut self, event: &Event) -> Result<u64, BinlogWriteError> {
+        self.write(event.header(), event.try_data()?)
+    }
+
+    // Appends an event of `header` and `payload`, its length, next_position and checksum made
+    // to match, returning the offset it starts at.
+    pub fn write(&mut self, header: EventHeader, payload: &[u8]) -> Result<u64, BinlogWriteError> {
+        let offset = self.offset;
+        let bytes = encode_event(header, payload, offset, self.checksum)?;
+        self.writer.write_all(&bytes)?;
+        self.offset += bytes.len() as u64;
+        Ok(offset)
+    }
+
+    // where the next event will start, i.e. the size of the file so far
+    pub fn offset(&self) -> u64 {
+        self.offset
+    }
+
+    // whether the events written get a CRC32
+    pub fn has_checksum(&self) -> bool {
+        self.checksum
+    }
+
+    pub fn get_ref(&self) -> &W {
+        &self.writer
+    }
+
+    pub fn finish(mut self) -> Result<W, BinlogWriteError> {
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::binlog_file::BinlogFile;
+    use crate::errors::BinlogWriteError;
+    use crate::event::{EventHeader, TypeCode};
+    use super::BinlogWriter;
+
+    #[test]
+    fn test_write_binlog() {
+        //given
+        let path = "tests/asset/mysql-bin.100746";
+        let file = std::fs::read(path).unwrap();
+        let events: Vec<_> = BinlogFile::from_path(path).unwrap().events().map(|e| e.unwrap()).collect();
+
+        //when
+        let mut copy = BinlogWriter::new(vec![], &events[0]).unwrap();
+        for event in &events[1..] {
+            copy.write_event(event).unwrap();
+        }
+        let copy = copy.finish().unwrap();
+        // the transaction at 464 left out, and a Rotate made up at the end
+        let mut writer = BinlogWriter::new(vec![], &events[0]).unwrap();
+        for event in events[1..].iter().filter(|e| e.offset() < 464 || e.offset() >= 740) {
+            writer.write_event(event).unwrap();
+        }
+        let header = EventHeader { timestamp: 0, type_code: TypeCode::RotateEvent, server_id: 1, event_length: 0, next_position: 0, flags: 0 };
+        let rotate_offset = writer.write(header, b"\x04\0\0\0\0\0\0\0mysql-bin.000002").unwrap();
+        let end = writer.offset();
+        let filtered = writer.finish().unwrap();
+        let not_first = BinlogWriter::new(vec![], &events[1]);
+
+        //then
+        assert_eq!(copy, file);
+        let mut filtered = BinlogFile::from_reader(std::io::Cursor::new(filtered)).unwrap();
+        let report = filtered.verify().unwrap();
+        assert!(report.is_ok(), "{:?}", report.problems);
+        assert_eq!(report.end_offset, end);
+        let offsets: Vec<_> = filtered.events().map(|e| e.unwrap().offset()).collect();
+        assert_eq!(offsets.len(), 15 - 5 + 1);
+        assert_eq!(offsets.last(), Some(&rotate_offset));
+        assert!(matches!(not_first, Err(BinlogWriteError::NotFormatDescription(TypeCode::PreviousGtidsLogEvent))));
+    }
+}
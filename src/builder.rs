@@ -0,0 +1,568 @@
+use std::convert::TryFrom;
+use crate::column::ColumnType;
+use crate::encode::encode_event;
+use crate::errors::{BuildError, EncodeError};
+use crate::event::{Event, EventHeader, TypeCode};
+use crate::rows::RowsEventKind;
+use crate::schema::{ColumnDefinition, TableDefinition};
+use crate::table_map::{TableMapColumn, TableMapEvent};
+use crate::utils::{write_bitmap, write_lenenc_int};
+use crate::value::{write_value, Value};
+
+const STMT_END_F: u16 = 0x0001;
+// table map flags as 5.7 writes them: TM_BIT_LEN_EXACT_F
+const TABLE_MAP_FLAGS: u16 = 0x0001;
+// what Gtid_log_event::FLAG_MAY_HAVE_SBR is set on: transactions that aren't only rows events
+const FLAG_MAY_HAVE_SBR: u8 = 0x01;
+const LOGICAL_TIMESTAMP_TYPECODE: u8 = 2;
+// collations of the columns built: utf8mb4_0900_ai_ci for text, binary for the rest
+const UTF8MB4: u16 = 255;
+const BINARY: u16 = 63;
+
+// Events made up in code, for tools that author binlogs of their own: replay injectors, chaos
+// tests, format converters. The events built aren't anywhere in a binlog yet and have no
+// checksum; BinlogWriter gives them their offset and next_position, and a CRC32 when the
+// binlog has them.
+//
+//     let rows = WriteRowsEventBuilder::new(users, 100).row(vec![Value::UInt(1), Value::Null]);
+//     writer.write_event(&GtidEventBuilder::new(sid, 42).build()?)?;
+//     writer.write_event(&QueryEventBuilder::new("BEGIN").schema("shop").build()?)?;
+//     writer.write_event(&rows.table_map_event()?)?;
+//     writer.write_event(&rows.build()?)?;
+//     writer.write_event(&XidEventBuilder::new(7).build()?)?;
+
+// https://dev.mysql.com/doc/internals/en/query-event.html, without status variables
+#[derive(Debug, Clone)]
+pub struct QueryEventBuilder {
+    query: String,
+    schema: String,
+    thread_id: u32,
+    exec_time: u32,
+    error_code: u16,
+    timestamp: u32,
+    server_id: u32,
+}
+
+impl QueryEventBuilder {
+    pub fn new(query: &str) -> Self {
+        QueryEventBuilder {
+            query: query.to_owned(),
+            schema: String::new(),
+            thread_id: 1,
+            exec_time: 0,
+            error_code: 0,
+            timestamp: 0,
+            server_id: 1,
+        }
+    }
+
+    // the default schema the statement ran in
+    pub fn schema(mut self, schema: &str) -> Self {
+        self.schema = schema.to_owned();
+        self
+    }
+
+    pub fn thread_id(mut self, thread_id: u32) -> Self {
+        self.thread_id = thread_id;
+        self
+    }
+
+    pub fn exec_time(mut self, exec_time: u32) -> Self {
+        self.exec_time = exec_time;
+        self
+    }
+
+    pub fn error_code(mut self, error_code: u16) -> Self {
+        self.error_code = error_code;
+        self
+    }
+
+    pub fn timestamp(mut self, timestamp: u32) -> Self {
+        self.timestamp = timestamp;
+        self
+    }
+
+    pub fn server_id(mut self, server_id: u32) -> Self {
+        self.server_id = server_id;
+        self
+    }
+
+    pub fn build(&self) -> Result<Event, BuildError> {
+        let mut payload = self.thread_id.to_le_bytes().to_vec();
+        payload.extend_from_slice(&self.exec_time.to_le_bytes());
+        payload.push(u8::try_from(self.schema.len()).map_err(|_| EncodeError::NameTooLong(self.schema.clone()))?);
+        payload.extend_from_slice(&self.error_code.to_le_bytes());
+        // no status variables
+        payload.extend_from_slice(&0u16.to_le_bytes());
+        payload.extend_from_slice(self.schema.as_bytes());
+        payload.push(0);
+        payload.extend_from_slice(self.query.as_bytes());
+        event(TypeCode::QueryEvent, self.timestamp, self.server_id, &payload)
+    }
+}
+
+// https://dev.mysql.com/doc/dev/mysql-server/latest/classbinary__log_1_1Gtid__event.html
+// A 5.7 GTID event with the logical clock, by default that of a transaction depending on the
+// one before it; commit timestamps and the transaction length make it an 8.0 one.
+#[derive(Debug, Clone)]
+pub struct GtidEventBuilder {
+    sid: Option<[u8; 16]>,
+    gno: i64,
+    rows_only: bool,
+    last_committed: i64,
+    sequence_number: i64,
+    commit_timestamps: Option<(u64, u64)>,
+    transaction_length: Option<u64>,
+    timestamp: u32,
+    server_id: u32,
+}
+
+impl GtidEventBuilder {
+    pub fn new(sid: [u8; 16], gno: i64) -> Self {
+        GtidEventBuilder { sid: Some(sid), gno, ..Self::anonymous() }
+    }
+
+    // the Anonymous_gtid event that starts transactions when gtid_mode is OFF
+    pub fn anonymous() -> Self {
+        GtidEventBuilder {
+            sid: None,
+            gno: 0,
+            rows_only: true,
+            last_committed: 0,
+            sequence_number: 1,
+            commit_timestamps: None,
+            transaction_length: None,
+            timestamp: 0,
+            server_id: 1,
+        }
+    }
+
+    // whether the transaction is only rows events, as opposed to DDL or statements; the
+    // default
+    pub fn rows_only(mut self, rows_only: bool) -> Self {
+        self.rows_only = rows_only;
+        self
+    }
+
+    pub fn logical_clock(mut self, last_committed: i64, sequence_number: i64) -> Self {
+        self.last_committed = last_committed;
+        self.sequence_number = sequence_number;
+        self
+    }
+
+    // microseconds since the epoch when this server and the original one committed it
+    pub fn commit_timestamps(mut self, immediate: u64, original: u64) -> Self {
+        self.commit_timestamps = Some((immediate, original));
+        self
+    }
+
+    // the size of the whole transaction in bytes, GTID event included; needs commit timestamps
+    pub fn transaction_length(mut self, transaction_length: u64) -> Self {
+        self.transaction_length = Some(transaction_length);
+        self
+    }
+
+    pub fn timestamp(mut self, timestamp: u32) -> Self {
+        self.timestamp = timestamp;
+        self
+    }
+
+    pub fn server_id(mut self, server_id: u32) -> Self {
+        self.server_id = server_id;
+        self
+    }
+
+    pub fn build(&self) -> Result<Event, BuildError> {
+        let type_code = if self.sid.is_some() { TypeCode::GtidLogEvent } else { TypeCode::AnonymousGtidLogEvent };
+        let mut payload = vec![if self.rows_only { 0 } else { FLAG_MAY_HAVE_SBR }];
+        payload.extend_from_slice(&self.sid.unwrap_or_default());
+        payload.extend_from_slice(&self.gno.to_le_bytes());
+        payload.push(LOGICAL_TIMESTAMP_TYPECODE);
+        payload.extend_from_slice(&self.last_committed.to_le_bytes());
+        payload.extend_from_slice(&self.sequence_number.to_le_bytes());
+        if let Some((immediate, original)) = self.commit_timestamps {
+            // 7 bytes each, the original left out when it's the same, as the top bit says
+            if immediate == original {
+                payload.extend_from_slice(&immediate.to_le_bytes()[..7]);
+            } else {
+                payload.extend_from_slice(&(immediate | 1 << 55).to_le_bytes()[..7]);
+                payload.extend_from_slice(&original.to_le_bytes()[..7]);
+            }
+            if let Some(transaction_length) = self.transaction_length {
+                write_lenenc_int(&mut payload, transaction_length);
+            }
+        }
+        event(type_code, self.timestamp, self.server_id, &payload)
+    }
+}
+
+// the Xid event that commits a transaction
+#[derive(Debug, Clone)]
+pub struct XidEventBuilder {
+    xid: u64,
+    timestamp: u32,
+    server_id: u32,
+}
+
+impl XidEventBuilder {
+    pub fn new(xid: u64) -> Self {
+        XidEventBuilder { xid, timestamp: 0, server_id: 1 }
+    }
+
+    pub fn timestamp(mut self, timestamp: u32) -> Self {
+        self.timestamp = timestamp;
+        self
+    }
+
+    pub fn server_id(mut self, server_id: u32) -> Self {
+        self.server_id = server_id;
+        self
+    }
+
+    pub fn build(&self) -> Result<Event, BuildError> {
+        event(TypeCode::XidEvent, self.timestamp, self.server_id, &self.xid.to_le_bytes())
+    }
+}
+
+// Rows events of a table, laid out from its definition as the server would: each column's type
+// code and metadata come from its declared type, like VARCHAR(20) as a VARCHAR of 80 bytes,
+// with text in utf8mb4. ENUM, SET, BIT, JSON and spatial columns aren't supported. Values are
+// given as RowsEvent gives them back, one per column and Value::Null for NULL, and the table
+// map that has to come before the rows is that of binlog_row_metadata=FULL.
+#[derive(Debug, Clone)]
+pub struct WriteRowsEventBuilder {
+    rows: RowsBuilder,
+}
+
+#[derive(Debug, Clone)]
+pub struct UpdateRowsEventBuilder {
+    rows: RowsBuilder,
+}
+
+#[derive(Debug, Clone)]
+pub struct DeleteRowsEventBuilder {
+    rows: RowsBuilder,
+}
+
+impl WriteRowsEventBuilder {
+    pub fn new(table: &TableDefinition, table_id: u64) -> Self {
+        WriteRowsEventBuilder { rows: RowsBuilder::new(RowsEventKind::Write, table, table_id) }
+    }
+
+    pub fn row(mut self, values: Vec<Value>) -> Self {
+        self.rows.images.push(values);
+        self
+    }
+
+    // Whether this is the last rows event of its statement, the default. Large statements are
+    // logged as several rows events, only the last of which has STMT_END_F.
+    pub fn end_of_statement(mut self, end: bool) -> Self {
+        self.rows.end_of_statement = end;
+        self
+    }
+
+    pub fn timestamp(mut self, timestamp: u32) -> Self {
+        self.rows.timestamp = timestamp;
+        self
+    }
+
+    pub fn server_id(mut self, server_id: u32) -> Self {
+        self.rows.server_id = server_id;
+        self
+    }
+
+    pub fn table_map_event(&self) -> Result<Event, BuildError> {
+        self.rows.table_map_event()
+    }
+
+    pub fn build(&self) -> Result<Event, BuildError> {
+        self.rows.build()
+    }
+}
+
+impl UpdateRowsEventBuilder {
+    pub fn new(table: &TableDefinition, table_id: u64) -> Self {
+        UpdateRowsEventBuilder { rows: RowsBuilder::new(RowsEventKind::Update, table, table_id) }
+    }
+
+    pub fn row(mut self, before: Vec<Value>, after: Vec<Value>) -> Self {
+        self.rows.images.push(before);
+        self.rows.images.push(after);
+        self
+    }
+
+    pub fn end_of_statement(mut self, end: bool) -> Self {
+        self.rows.end_of_statement = end;
+        self
+    }
+
+    pub fn timestamp(mut self, timestamp: u32) -> Self {
+        self.rows.timestamp = timestamp;
+        self
+    }
+
+    pub fn server_id(mut self, server_id: u32) -> Self {
+        self.rows.server_id = server_id;
+        self
+    }
+
+    pub fn table_map_event(&self) -> Result<Event, BuildError> {
+        self.rows.table_map_event()
+    }
+
+    pub fn build(&self) -> Result<Event, BuildError> {
+        self.rows.build()
+    }
+}
+
+impl DeleteRowsEventBuilder {
+    pub fn new(table: &TableDefinition, table_id: u64) -> Self {
+        DeleteRowsEventBuilder { rows: RowsBuilder::new(RowsEventKind::Delete, table, table_id) }
+    }
+
+    pub fn row(mut self, values: Vec<Value>) -> Self {
+        self.rows.images.push(values);
+        self
+    }
+
+    pub fn end_of_statement(mut self, end: bool) -> Self {
+        self.rows.end_of_statement = end;
+        self
+    }
+
+    pub fn timestamp(mut self, timestamp: u32) -> Self {
+        self.rows.timestamp = timestamp;
+        self
+    }
+
+    pub fn server_id(mut self, server_id: u32) -> Self {
+        self.rows.server_id = server_id;
+        self
+    }
+
+    pub fn table_map_event(&self) -> Result<Event, BuildError> {
+        self.rows.table_map_event()
+    }
+
+    pub fn build(&self) -> Result<Event, BuildError> {
+        self.rows.build()
+    }
+}
+
+#[derive(Debug, Clone)]
+struct RowsBuilder {
+    kind: RowsEventKind,
+    table: TableDefinition,
+    table_id: u64,
+    // the after image of inserts, the before of deletes, both of updates
+    images: Vec<Vec<Value>>,
+    end_of_statement: bool,
+    timestamp: u32,
+    server_id: u32,
+}
+
+impl RowsBuilder {
+    fn new(kind: RowsEventKind, table: &TableDefinition, table_id: u64) -> Self {
+        RowsBuilder {
+            kind,
+            table: table.clone(),
+            table_id,
+            images: vec![],
+            end_of_statement: true,
+            timestamp: 0,
+            server_id: 1,
+        }
+    }
+
+    fn table_map_event(&self) -> Result<Event, BuildError> {
+        let table_map = table_map(self.table_id, &self.table)?;
+        event(TypeCode::TableMapEvent, self.timestamp, self.server_id, &table_map.to_bytes()?)
+    }
+
+    // https://dev.mysql.com/doc/internals/en/rows-event.html, version 2 with every column in
+    // the images
+    fn build(&self) -> Result<Event, BuildError> {
+        let (type_code, bitmaps) = match self.kind {
+            RowsEventKind::Write => (TypeCode::WriteRowsEventV2, 1),
+            RowsEventKind::Update => (TypeCode::UpdateRowsEventV2, 2),
+            RowsEventKind::Delete => (TypeCode::DeleteRowsEventV2, 1),
+        };
+        let table_map = table_map(self.table_id, &self.table)?;
+        let columns = &table_map.columns;
+        let mut data = self.table_id.to_le_bytes()[..6].to_vec();
+        data.extend_from_slice(&(if self.end_of_statement { STMT_END_F } else { 0 }).to_le_bytes());
+        // the length of the (empty) extra data, counting itself
+        data.extend_from_slice(&2u16.to_le_bytes());
+        write_lenenc_int(&mut data, columns.len() as u64);
+        for _ in 0..bitmaps {
+            write_bitmap(&mut data, &vec![true; columns.len()]);
+        }
+        for image in &self.images {
+            if image.len() != columns.len() {
+                return Err(BuildError::ColumnCount {
+                    table: format!("{}.{}", table_map.schema, table_map.table),
+                    expected: columns.len(),
+                    actual: image.len(),
+                });
+            }
+            write_bitmap(&mut data, &image.iter().map(|v| *v == Value::Null).collect::<Vec<_>>());
+            for (column, value) in columns.iter().zip(image) {
+                let written = match value {
+                    Value::Null => column.nullable,
+                    value => write_value(&mut data, column, value),
+                };
+                if !written {
+                    return Err(BuildError::ValueMismatch {
+                        column: column.name.clone().unwrap_or_default(),
+                        value: value.to_string(),
+                    });
+                }
+            }
+        }
+        event(type_code, self.timestamp, self.server_id, &data)
+    }
+}
+
+// an event of `payload` at no offset in particular
+fn event(type_code: TypeCode, timestamp: u32, server_id: u32, payload: &[u8]) -> Result<Event, BuildError> {
+    let header = EventHeader { timestamp, type_code, server_id, event_length: 0, next_position: 0, flags: 0 };
+    let bytes = encode_event(header, payload, 0, false)?;
+    Ok(Event::parse(&mut &bytes[..], 0)?)
+}
+
+fn table_map(table_id: u64, table: &TableDefinition) -> Result<TableMapEvent, BuildError> {
+    let mut table_map = TableMapEvent {
+        table_id,
+        flags: TABLE_MAP_FLAGS,
+        schema: table.schema.clone(),
+        table: table.name.clone(),
+        columns: table.columns.iter().map(column).collect::<Result<_, _>>()?,
+        primary_key: vec![],
+    };
+    table_map.apply_table_definition(table);
+    Ok(table_map)
+}
+
+fn column(definition: &ColumnDefinition) -> Result<TableMapColumn, BuildError> {
+    let data_type = definition.data_type.to_uppercase();
+    let (name, args) = match data_type.split_once('(') {
+        Some((name, rest)) => (name.trim(), rest.split(')').next().unwrap_or("")),
+        None => (data_type.split(' ').next().unwrap_or(""), ""),
+    };
+    let args: Vec<u16> = args.split(',').filter_map(|arg| arg.trim().parse().ok()).collect();
+    let arg = |i: usize, default: u16| args.get(i).copied().unwrap_or(default);
+    // CHAR and BINARY lengths above 255 share the type byte of the STRING metadata
+    let string_meta = |len: u16| (u16::from(ColumnType::String.to_byte()) ^ ((len & 0x300) >> 4)) << 8 | (len & 0xff);
+    let (column_type, meta, charset) = match name {
+        "TINYINT" | "BOOL" | "BOOLEAN" => (ColumnType::Tiny, 0, None),
+        "SMALLINT" => (ColumnType::Short, 0, None),
+        "MEDIUMINT" => (ColumnType::Int24, 0, None),
+        "INT" | "INTEGER" => (ColumnType::Long, 0, None),
+        "BIGINT" => (ColumnType::LongLong, 0, None),
+        "FLOAT" => (ColumnType::Float, 4, None),
+        "DOUBLE" | "REAL" => (ColumnType::Double, 8, None),
+        "DECIMAL" | "NUMERIC" | "DEC" => (ColumnType::NewDecimal, arg(0, 10) << 8 | arg(1, 0), None),
+        "YEAR" => (ColumnType::Year, 0, None),
+        "DATE" => (ColumnType::Date, 0, None),
+        "TIME" => (ColumnType::Time2, arg(0, 0), None),
+        "DATETIME" => (ColumnType::DateTime2, arg(0, 0), None),
+        "TIMESTAMP" => (ColumnType::Timestamp2, arg(0, 0), None),
+        "CHAR" => (ColumnType::String, string_meta(arg(0, 1) * 4), Some(UTF8MB4)),
+        "BINARY" => (ColumnType::String, string_meta(arg(0, 1)), Some(BINARY)),
+        "VARCHAR" => (ColumnType::VarChar, arg(0, 0) * 4, Some(UTF8MB4)),
+        "VARBINARY" => (ColumnType::VarChar, arg(0, 0), Some(BINARY)),
+        "TINYTEXT" => (ColumnType::Blob, 1, Some(UTF8MB4)),
+        "TEXT" => (ColumnType::Blob, 2, Some(UTF8MB4)),
+        "MEDIUMTEXT" => (ColumnType::Blob, 3, Some(UTF8MB4)),
+        "LONGTEXT" => (ColumnType::Blob, 4, Some(UTF8MB4)),
+        "TINYBLOB" => (ColumnType::Blob, 1, Some(BINARY)),
+        "BLOB" => (ColumnType::Blob, 2, Some(BINARY)),
+        "MEDIUMBLOB" => (ColumnType::Blob, 3, Some(BINARY)),
+        "LONGBLOB" => (ColumnType::Blob, 4, Some(BINARY)),
+        _ => {
+            return Err(BuildError::UnsupportedColumnType {
+                column: definition.name.clone(),
+                data_type: definition.data_type.clone(),
+            });
+        }
+    };
+    Ok(TableMapColumn {
+        column_type,
+        meta,
+        nullable: definition.nullable,
+        unsigned: false,
+        charset,
+        name: Some(definition.name.clone()),
+        enum_values: None,
+        set_values: None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::errors::BuildError;
+    use crate::event::{Event, EventData, TypeCode};
+    use crate::rows::RowsEvent;
+    use crate::schema::{SchemaProvider, SchemaTracker};
+    use crate::table_map::TableMapEvent;
+    use crate::value::Value;
+    use super::{GtidEventBuilder, QueryEventBuilder, UpdateRowsEventBuilder, WriteRowsEventBuilder};
+
+    #[test]
+    fn test_build_query_and_gtid_events() {
+        //given
+        let query = QueryEventBuilder::new("DROP TABLE t").schema("shop").thread_id(7).timestamp(1633046400).server_id(2);
+        let gtid = GtidEventBuilder::new([0xaa; 16], 42).rows_only(false).logical_clock(3, 4).commit_timestamps(10, 5).transaction_length(300);
+
+        //when
+        let query = query.build().unwrap();
+        let gtid = gtid.build().unwrap();
+        let anonymous = GtidEventBuilder::anonymous().build().unwrap();
+
+        //then
+        assert_eq!((query.timestamp(), query.server_id()), (1633046400, 2));
+        assert!(matches!(
+            Event::parse_event_data_by_type_code(query.type_code(), query.data()).unwrap(),
+            Some(EventData::QueryEvent { thread_id: 7, schema, query, .. }) if schema == "shop" && query == "DROP TABLE t"
+        ));
+        match Event::parse_event_data_by_type_code(gtid.type_code(), gtid.data()).unwrap() {
+            Some(EventData::GtidLogEvent(gtid)) => {
+                assert_eq!((gtid.sid, gtid.gno, gtid.flags), ([0xaa; 16], 42, 1));
+                assert_eq!((gtid.last_committed, gtid.sequence_number), (Some(3), Some(4)));
+                assert_eq!((gtid.immediate_commit_timestamp, gtid.original_commit_timestamp), (Some(10), Some(5)));
+                assert_eq!(gtid.transaction_length, Some(300));
+            }
+            other => panic!("expected a GTID, got {:?}", other),
+        }
+        assert_eq!(anonymous.type_code(), TypeCode::AnonymousGtidLogEvent);
+        assert_eq!(anonymous.body_len(), 42);
+    }
+
+    #[test]
+    fn test_build_rows_events() {
+        //given
+        let mut tracker = SchemaTracker::new();
+        tracker.apply_query("shop", "CREATE TABLE users (id INT UNSIGNED PRIMARY KEY, name VARCHAR(20))").unwrap();
+        let users = tracker.table_definition("shop", "users").unwrap();
+        let alice = vec![Value::UInt(1), Value::String("alice".to_owned())];
+        let nobody = vec![Value::UInt(1), Value::Null];
+
+        //when
+        let insert = WriteRowsEventBuilder::new(users, 108).row(alice.clone()).end_of_statement(false);
+        let table_map = TableMapEvent::parse(insert.table_map_event().unwrap().data()).unwrap();
+        let inserted = RowsEvent::parse(TypeCode::WriteRowsEventV2, insert.build().unwrap().data(), &table_map).unwrap();
+        let update = UpdateRowsEventBuilder::new(users, 108).row(alice.clone(), nobody.clone()).build().unwrap();
+        let updated = RowsEvent::parse(TypeCode::UpdateRowsEventV2, update.data(), &table_map).unwrap();
+        let short = WriteRowsEventBuilder::new(users, 108).row(vec![Value::UInt(1)]).build();
+
+        //then
+        assert_eq!(table_map.table_id, 108);
+        assert_eq!(table_map.column_names(), Some(vec!["id".to_owned(), "name".to_owned()]));
+        assert_eq!(inserted.flags, 0);
+        assert_eq!(inserted.rows[0].after.as_ref().unwrap().values(), &[Some(alice[0].clone()), Some(alice[1].clone())]);
+        assert_eq!(update.type_code(), TypeCode::UpdateRowsEventV2);
+        assert_eq!(updated.flags, 1);
+        assert_eq!(updated.rows[0].after.as_ref().unwrap().get(1), Some(&Value::Null));
+        assert!(matches!(short, Err(BuildError::ColumnCount { expected: 2, actual: 1, .. })));
+    }
+}
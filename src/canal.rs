@@ -0,0 +1,298 @@
+use std::collections::HashMap;
+use std::time::{SystemTime, UNIX_EPOCH};
+use serde_json::{json, Map, Value as Json};
+use crate::column::ColumnType;
+use crate::event::{Event, EventData};
+use crate::export::plain_json;
+use crate::rows::{RowImage, RowsEventKind};
+use crate::table_map::{TableMapColumn, TableMapEvent};
+use crate::utils::{base64, civil_from_days};
+use crate::value::Value;
+
+// collation id of the binary "charset", telling BINARY/VARBINARY/BLOB from CHAR/VARCHAR/TEXT
+const BINARY_COLLATION: u16 = 63;
+
+// Canal's FlatMessage, the JSON its Kafka/RocketMQ connectors send with flatMessage=true: one per
+// rows event, with every value as a string.
+//
+//     {"id":1,"database":"test","table":"users","pkNames":["id"],"isDdl":false,"type":"UPDATE",
+//      "es":1589373515000,"ts":1589373515477,"sql":"","sqlType":{"id":4,"name":12},
+//      "mysqlType":{"id":"int","name":"varchar"},"data":[{"id":"1","name":"new"}],"old":[{"name":"old"}]}
+//
+// DDL comes as a message with isDdl and the statement in "sql". Binary values are base64, ENUM and
+// SET their labels when the table map carries them, TIMESTAMP in UTC. mysqlType only has what the
+// table map tells: no lengths, and VARCHAR/TEXT over BINARY/BLOB unless the charset is known.
+// Canal's protobuf Entry format isn't produced.
+#[derive(Debug, Default)]
+pub struct CanalFlatMessages {
+    tables: HashMap<u64, TableMapEvent>,
+    next_id: u64,
+}
+
+impl CanalFlatMessages {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // the message for a rows event or DDL statement, None for other events
+    pub fn message(&mut self, event: &Event, data: &EventData) -> Option<Json> {
+        let es = u64::from(event.timestamp()) * 1000;
+        let ts = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_millis() as u64).unwrap_or(0);
+        let rows = match data {
+            EventData::TableMapEvent(table_map) => {
+                self.tables.insert(table_map.table_id, table_map.clone());
+                return None;
+            }
+            EventData::QueryEvent { schema, query, .. } => {
+                let kind = ddl_type(query)?;
+                self.next_id += 1;
+                return Some(json!({
+                    "id": self.next_id,
+                    "database": schema,
+                    "table": "",
+                    "pkNames": null,
+                    "isDdl": true,
+                    "type": kind,
+                    "es": es,
+                    "ts": ts,
+                    "sql": query,
+                    "sqlType": null,
+                    "mysqlType": null,
+                    "data": null,
+                    "old": null,
+                }));
+            }
+            EventData::WriteRowsEvent(rows) | EventData::UpdateRowsEvent(rows) | EventData::DeleteRowsEvent(rows) => rows,
+            _ => return None,
+        };
+        let table_map = self.tables.get(&rows.table_id)?;
+        let kind = match rows.kind {
+            RowsEventKind::Write => "INSERT",
+            RowsEventKind::Update => "UPDATE",
+            RowsEventKind::Delete => "DELETE",
+        };
+        let mut data = vec![];
+        let mut old = vec![];
+        for change in &rows.rows {
+            match (rows.kind, &change.before, &change.after) {
+                (RowsEventKind::Update, Some(before), Some(after)) => {
+                    data.push(row_json(after, table_map));
+                    old.push(old_json(before, after, table_map));
+                }
+                (_, _, Some(image)) | (_, Some(image), None) => data.push(row_json(image, table_map)),
+                _ => {}
+            }
+        }
+        let names = (0..table_map.columns.len()).map(|i| column_name(table_map, i));
+        let sql_type: Map<String, Json> = names.clone().zip(&table_map.columns).map(|(name, c)| (name, sql_type(c).into())).collect();
+        let mysql_type: Map<String, Json> = names.zip(&table_map.columns).map(|(name, c)| (name, mysql_type(c).into())).collect();
+        let pk_names: Vec<String> = table_map.primary_key.iter().map(|&i| column_name(table_map, i)).collect();
+        self.next_id += 1;
+        Some(json!({
+            "id": self.next_id,
+            "database": table_map.schema,
+            "table": table_map.table,
+            "pkNames": if pk_names.is_empty() { Json::Null } else { pk_names.into() },
+            "isDdl": false,
+            "type": kind,
+            "es": es,
+            "ts": ts,
+            "sql": "",
+            "sqlType": sql_type,
+            "mysqlType": mysql_type,
+            "data": data,
+            "old": if old.is_empty() { Json::Null } else { old.into() },
+        }))
+    }
+}
+
+// Canal's event type for a DDL statement, None for BEGIN, COMMIT and DML
+fn ddl_type(query: &str) -> Option<&'static str> {
+    let mut words = query.split_whitespace().map(|w| w.to_ascii_uppercase());
+    let first = words.next()?;
+    let second = words.next().unwrap_or_default();
+    let index = second == "INDEX" || (matches!(second.as_str(), "UNIQUE" | "FULLTEXT" | "SPATIAL") && words.next().is_some_and(|w| w == "INDEX"));
+    Some(match first.as_str() {
+        "CREATE" if index => "CINDEX",
+        "DROP" if index => "DINDEX",
+        "CREATE" => "CREATE",
+        "ALTER" => "ALTER",
+        "DROP" => "ERASE",
+        "TRUNCATE" => "TRUNCATE",
+        "RENAME" => "RENAME",
+        _ => return None,
+    })
+}
+
+fn column_name(table_map: &TableMapEvent, index: usize) -> String {
+    match table_map.columns.get(index).and_then(|c| c.name.as_ref()) {
+        Some(name) => name.clone(),
+        None => format!("@{}", index + 1),
+    }
+}
+
+fn row_json(image: &RowImage, table_map: &TableMapEvent) -> Json {
+    let columns: Map<String, Json> = image.values().iter().enumerate()
+        .filter_map(|(i, value)| Some((column_name(table_map, i), canal_value(value.as_ref()?, table_map.columns.get(i)))))
+        .collect();
+    Json::Object(columns)
+}
+
+fn old_json(before: &RowImage, after: &RowImage, table_map: &TableMapEvent) -> Json {
+    let columns: Map<String, Json> = before.values().iter().enumerate()
+        .filter(|(i, value)| after.values().get(*i).is_some_and(|after| after.is_some() && after != *value))
+        .filter_map(|(i, value)| Some((column_name(table_map, i), canal_value(value.as_ref()?, table_map.columns.get(i)))))
+        .collect();
+    Json::Object(columns)
+}
+
+fn canal_value(value: &Value, column: Option<&TableMapColumn>) -> Json {
+    let text = match value {
+        Value::Null => return Json::Null,
+        Value::Bytes(v) | Value::Json(v) => base64(v),
+        Value::Timestamp { seconds, micros } => {
+            let (year, month, day) = civil_from_days(i64::from(*seconds) / 86400);
+            let time = seconds % 86400;
+            let fraction = if *micros == 0 { String::new() } else { format!(".{:06}", micros).trim_end_matches('0').to_owned() };
+            format!("{:04}-{:02}-{:02} {:02}:{:02}:{:02}{}", year, month, day, time / 3600, time / 60 % 60, time % 60, fraction)
+        }
+        Value::Bit(v) => v.iter().take(8).fold(0u64, |n, b| n << 8 | u64::from(*b)).to_string(),
+        Value::Enum(index) => match column.and_then(|c| c.enum_values.as_ref()) {
+            Some(labels) => labels.get((*index as usize).wrapping_sub(1)).cloned().unwrap_or_default(),
+            None => index.to_string(),
+        },
+        Value::Set(bits) => match column.and_then(|c| c.set_values.as_ref()) {
+            Some(labels) => labels.iter().enumerate()
+                .filter(|(i, _)| *i < 64 && bits & (1 << i) != 0)
+                .map(|(_, l)| l.as_str())
+                .collect::<Vec<_>>()
+                .join(","),
+            None => bits.to_string(),
+        },
+        _ => match plain_json(value) {
+            Json::String(s) => s,
+            other => other.to_string(),
+        },
+    };
+    text.into()
+}
+
+fn is_binary(column: &TableMapColumn) -> bool {
+    column.charset == Some(BINARY_COLLATION)
+}
+
+fn mysql_type(column: &TableMapColumn) -> String {
+    let name = match column.real_type() {
+        ColumnType::Tiny => "tinyint",
+        ColumnType::Short => "smallint",
+        ColumnType::Int24 => "mediumint",
+        ColumnType::Long => "int",
+        ColumnType::LongLong => "bigint",
+        ColumnType::Float => "float",
+        ColumnType::Double => "double",
+        ColumnType::Decimal | ColumnType::NewDecimal => return format!("decimal({},{})", column.meta >> 8, column.meta & 0xff),
+        ColumnType::Year => "year",
+        ColumnType::Date | ColumnType::NewDate => "date",
+        ColumnType::Time | ColumnType::Time2 => "time",
+        ColumnType::DateTime | ColumnType::DateTime2 => "datetime",
+        ColumnType::Timestamp | ColumnType::Timestamp2 => "timestamp",
+        ColumnType::VarChar | ColumnType::VarString if is_binary(column) => "varbinary",
+        ColumnType::VarChar | ColumnType::VarString => "varchar",
+        ColumnType::String if is_binary(column) => "binary",
+        ColumnType::String => "char",
+        ColumnType::Enum => "enum",
+        ColumnType::Set => "set",
+        // the metadata is the number of length bytes
+        t if t.is_blob() => {
+            let size = match column.meta {
+                1 => "tiny",
+                3 => "medium",
+                4 => "long",
+                _ => "",
+            };
+            return format!("{}{}", size, if is_binary(column) { "blob" } else { "text" });
+        }
+        ColumnType::Json => "json",
+        ColumnType::Bit => "bit",
+        ColumnType::Geometry => "geometry",
+        _ => "unknown",
+    };
+    if column.unsigned && column.column_type.is_numeric() {
+        format!("{} unsigned", name)
+    } else {
+        name.to_owned()
+    }
+}
+
+// java.sql.Types, as Canal reports them
+fn sql_type(column: &TableMapColumn) -> i32 {
+    match column.real_type() {
+        ColumnType::Tiny => -6,
+        ColumnType::Short => 5,
+        ColumnType::Int24 | ColumnType::Long => 4,
+        ColumnType::LongLong => -5,
+        ColumnType::Float => 7,
+        ColumnType::Double => 8,
+        ColumnType::Decimal | ColumnType::NewDecimal => 3,
+        ColumnType::Date | ColumnType::NewDate => 91,
+        ColumnType::Time | ColumnType::Time2 => 92,
+        ColumnType::DateTime | ColumnType::DateTime2 | ColumnType::Timestamp | ColumnType::Timestamp2 => 93,
+        ColumnType::VarChar | ColumnType::VarString if is_binary(column) => -3,
+        ColumnType::String if is_binary(column) => -2,
+        ColumnType::String | ColumnType::Enum | ColumnType::Set => 1,
+        t if t.is_blob() && is_binary(column) => 2004,
+        t if t.is_blob() => 2005,
+        ColumnType::Bit => -7,
+        ColumnType::Geometry => -2,
+        _ => 12,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::canal::{ddl_type, CanalFlatMessages};
+    use crate::context::ParseContext;
+    use crate::event::Event;
+    use crate::event::tests::{parse_events, users_rows, users_table_map};
+
+    #[test]
+    fn test_flat_messages() {
+        //given
+        // test.users (id INT PRIMARY KEY, created DATE) with column names
+        let table_map = users_table_map(&[3, 10], &[], 0x00, b"\x04\x0b\x02id\x07created\x08\x01\x00");
+        let update_rows = users_rows(2, &[0x03, 0x03, 0x00, 7, 0, 0, 0, 0x3f, 0xd0, 0x0f, 0x00, 7, 0, 0, 0, 0x41, 0xd0, 0x0f]);
+        let mut query = vec![0, 0, 0, 0, 0, 0, 0, 0, 4, 0, 0, 0, 0];
+        query.extend_from_slice(b"test\x00ALTER TABLE users ADD name TEXT");
+        let events: Vec<Event> = parse_events(16, &[(19, table_map), (31, update_rows), (2, query)]);
+        let mut ctx = ParseContext::new();
+        let mut canal = CanalFlatMessages::new();
+
+        //when
+        let messages: Vec<_> = events.iter().map(|e| canal.message(e, &e.parsed(&mut ctx).unwrap())).collect();
+
+        //then
+        assert_eq!(messages[0], None);
+        let update = messages[1].as_ref().unwrap();
+        assert_eq!(update["type"], "UPDATE");
+        assert_eq!(update["es"], 16000);
+        assert_eq!(update["pkNames"], serde_json::json!(["id"]));
+        assert_eq!(update["mysqlType"], serde_json::json!({"id": "int", "created": "date"}));
+        assert_eq!(update["sqlType"], serde_json::json!({"id": 4, "created": 91}));
+        assert_eq!(update["data"], serde_json::json!([{"id": "7", "created": "2024-02-01"}]));
+        assert_eq!(update["old"], serde_json::json!([{"created": "2024-01-31"}]));
+        let ddl = messages[2].as_ref().unwrap();
+        assert_eq!((ddl["isDdl"].as_bool(), ddl["type"].as_str(), ddl["id"].as_u64()), (Some(true), Some("ALTER"), Some(2)));
+    }
+
+    #[test]
+    fn test_ddl_type() {
+        //given
+        let queries = ["create unique index i on t (a)", "DROP TABLE t", "BEGIN"];
+
+        //when
+        let types: Vec<_> = queries.iter().map(|q| ddl_type(q)).collect();
+
+        //then
+        assert_eq!(types, vec![Some("CINDEX"), Some("ERASE"), None]);
+    }
+}
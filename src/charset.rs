@@ -0,0 +1,227 @@
+use std::str::FromStr;
+use encoding_rs::Encoding;
+
+// https://dev.mysql.com/doc/refman/8.0/en/charset-charsets.html
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Charset {
+    Utf8,
+    Utf8mb4,
+    Latin1,
+    Latin2,
+    Ascii,
+    Binary,
+    Gbk,
+    Gb2312,
+    Gb18030,
+    Big5,
+    Sjis,
+    Cp932,
+    EucKr,
+    Ujis,
+    Cp1250,
+    Cp1251,
+    Cp1256,
+    Cp1257,
+    Greek,
+    Hebrew,
+    Latin5,
+    Latin7,
+    Koi8r,
+    Koi8u,
+    Tis620,
+    Cp866,
+    MacRoman,
+    Ucs2,
+    Utf16,
+    Utf16le,
+    Utf32,
+}
+
+impl Charset {
+    // https://github.com/mysql/mysql-server/blob/8.0/strings (SELECT id, character_set_name FROM information_schema.collations)
+    pub fn from_collation_id(id: u16) -> Option<Self> {
+        let charset = match id {
+            1 | 84 => Charset::Big5,
+            2 | 9 | 21 | 27 | 77 => Charset::Latin2,
+            5 | 8 | 15 | 31 | 47 | 48 | 49 | 94 => Charset::Latin1,
+            7 | 74 => Charset::Koi8r,
+            11 | 65 => Charset::Ascii,
+            12 | 91 => Charset::Ujis,
+            13 | 88 => Charset::Sjis,
+            14 | 23 | 50 | 51 | 52 => Charset::Cp1251,
+            16 | 71 => Charset::Hebrew,
+            18 | 89 => Charset::Tis620,
+            19 | 85 => Charset::EucKr,
+            20 | 41 | 42 | 79 => Charset::Latin7,
+            22 | 75 => Charset::Koi8u,
+            24 | 86 => Charset::Gb2312,
+            25 | 70 => Charset::Greek,
+            26 | 34 | 44 | 66 | 99 => Charset::Cp1250,
+            28 | 87 => Charset::Gbk,
+            29 | 58 | 59 => Charset::Cp1257,
+            30 | 78 => Charset::Latin5,
+            33 | 76 | 83 | 192..=215 | 223 => Charset::Utf8,
+            35 | 90 | 128..=151 | 159 => Charset::Ucs2,
+            36 | 68 => Charset::Cp866,
+            39 | 53 => Charset::MacRoman,
+            45 | 46 | 224..=247 | 255..=323 => Charset::Utf8mb4,
+            54 | 55 | 101..=124 => Charset::Utf16,
+            56 | 62 => Charset::Utf16le,
+            57 | 67 => Charset::Cp1256,
+            60 | 61 | 160..=183 => Charset::Utf32,
+            63 => Charset::Binary,
+            95 | 96 => Charset::Cp932,
+            248..=250 => Charset::Gb18030,
+            _ => return None,
+        };
+        Some(charset)
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Charset::Utf8 => "utf8",
+            Charset::Utf8mb4 => "utf8mb4",
+            Charset::Latin1 => "latin1",
+            Charset::Latin2 => "latin2",
+            Charset::Ascii => "ascii",
+            Charset::Binary => "binary",
+            Charset::Gbk => "gbk",
+            Charset::Gb2312 => "gb2312",
+            Charset::Gb18030 => "gb18030",
+            Charset::Big5 => "big5",
+            Charset::Sjis => "sjis",
+            Charset::Cp932 => "cp932",
+            Charset::EucKr => "euckr",
+            Charset::Ujis => "ujis",
+            Charset::Cp1250 => "cp1250",
+            Charset::Cp1251 => "cp1251",
+            Charset::Cp1256 => "cp1256",
+            Charset::Cp1257 => "cp1257",
+            Charset::Greek => "greek",
+            Charset::Hebrew => "hebrew",
+            Charset::Latin5 => "latin5",
+            Charset::Latin7 => "latin7",
+            Charset::Koi8r => "koi8r",
+            Charset::Koi8u => "koi8u",
+            Charset::Tis620 => "tis620",
+            Charset::Cp866 => "cp866",
+            Charset::MacRoman => "macroman",
+            Charset::Ucs2 => "ucs2",
+            Charset::Utf16 => "utf16",
+            Charset::Utf16le => "utf16le",
+            Charset::Utf32 => "utf32",
+        }
+    }
+
+    // mysql's latin1 is really cp1252, see https://dev.mysql.com/doc/refman/8.0/en/charset-we-sets.html
+    fn encoding(self) -> Option<&'static Encoding> {
+        let encoding = match self {
+            Charset::Utf8 | Charset::Utf8mb4 => encoding_rs::UTF_8,
+            Charset::Latin1 | Charset::Ascii => encoding_rs::WINDOWS_1252,
+            Charset::Latin2 => encoding_rs::ISO_8859_2,
+            Charset::Gbk | Charset::Gb2312 => encoding_rs::GBK,
+            Charset::Gb18030 => encoding_rs::GB18030,
+            Charset::Big5 => encoding_rs::BIG5,
+            Charset::Sjis | Charset::Cp932 => encoding_rs::SHIFT_JIS,
+            Charset::EucKr => encoding_rs::EUC_KR,
+            Charset::Ujis => encoding_rs::EUC_JP,
+            Charset::Cp1250 => encoding_rs::WINDOWS_1250,
+            Charset::Cp1251 => encoding_rs::WINDOWS_1251,
+            Charset::Cp1256 => encoding_rs::WINDOWS_1256,
+            Charset::Cp1257 => encoding_rs::WINDOWS_1257,
+            Charset::Greek => encoding_rs::ISO_8859_7,
+            Charset::Hebrew => encoding_rs::ISO_8859_8,
+            Charset::Latin5 => encoding_rs::WINDOWS_1254,
+            Charset::Latin7 => encoding_rs::ISO_8859_13,
+            Charset::Koi8r => encoding_rs::KOI8_R,
+            Charset::Koi8u => encoding_rs::KOI8_U,
+            Charset::Tis620 => encoding_rs::WINDOWS_874,
+            Charset::Cp866 => encoding_rs::IBM866,
+            Charset::MacRoman => encoding_rs::MACINTOSH,
+            Charset::Ucs2 | Charset::Utf16 => encoding_rs::UTF_16BE,
+            Charset::Utf16le => encoding_rs::UTF_16LE,
+            Charset::Binary | Charset::Utf32 => return None,
+        };
+        Some(encoding)
+    }
+
+    // None when the bytes aren't valid in this charset (or it's binary), so callers can fall back to raw bytes
+    pub fn decode(self, bytes: &[u8]) -> Option<String> {
+        match self {
+            Charset::Binary => None,
+            Charset::Utf32 => bytes.chunks(4)
+                .map(|c| match c {
+                    [a, b, c, d] => char::from_u32(u32::from_be_bytes([*a, *b, *c, *d])),
+                    _ => None,
+                })
+                .collect(),
+            _ => self.encoding()?
+                .decode_without_bom_handling_and_without_replacement(bytes)
+                .map(|s| s.into_owned()),
+        }
+    }
+}
+
+impl FromStr for Charset {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let charset = match s.to_ascii_lowercase().as_str() {
+            "utf8" | "utf8mb3" => Charset::Utf8,
+            "utf8mb4" => Charset::Utf8mb4,
+            "latin1" => Charset::Latin1,
+            "latin2" => Charset::Latin2,
+            "ascii" => Charset::Ascii,
+            "binary" => Charset::Binary,
+            "gbk" => Charset::Gbk,
+            "gb2312" => Charset::Gb2312,
+            "gb18030" => Charset::Gb18030,
+            "big5" => Charset::Big5,
+            "sjis" => Charset::Sjis,
+            "cp932" => Charset::Cp932,
+            "euckr" => Charset::EucKr,
+            "ujis" => Charset::Ujis,
+            "cp1250" => Charset::Cp1250,
+            "cp1251" => Charset::Cp1251,
+            "cp1256" => Charset::Cp1256,
+            "cp1257" => Charset::Cp1257,
+            "greek" => Charset::Greek,
+            "hebrew" => Charset::Hebrew,
+            "latin5" => Charset::Latin5,
+            "latin7" => Charset::Latin7,
+            "koi8r" => Charset::Koi8r,
+            "koi8u" => Charset::Koi8u,
+            "tis620" => Charset::Tis620,
+            "cp866" => Charset::Cp866,
+            "macroman" => Charset::MacRoman,
+            "ucs2" => Charset::Ucs2,
+            "utf16" => Charset::Utf16,
+            "utf16le" => Charset::Utf16le,
+            "utf32" => Charset::Utf32,
+            _ => return Err(format!("unknown charset {}", s)),
+        };
+        Ok(charset)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::charset::Charset;
+
+    #[test]
+    fn test_decode_latin1_and_utf8() {
+        //given
+        let latin1 = Charset::from_collation_id(8).unwrap();
+        let utf8mb4 = Charset::from_collation_id(255).unwrap();
+
+        //when
+        let decoded_latin1 = latin1.decode(b"caf\xe9");
+        let decoded_utf8mb4 = utf8mb4.decode(b"caf\xe9");
+
+        //then
+        assert_eq!(latin1, Charset::Latin1);
+        assert_eq!(decoded_latin1, Some("café".to_owned()));
+        assert_eq!(decoded_utf8mb4, None);
+        assert_eq!("LATIN1".parse::<Charset>(), Ok(Charset::Latin1));
+    }
+}
@@ -0,0 +1,142 @@
+use crate::errors::EventParseError;
+use crate::event::{Event, EventHeader, TypeCode, LOG_EVENT_BINLOG_IN_USE_F};
+use crate::options::ParseMode;
+
+// binlog_checksum, announced in the FormatDescriptionEvent and applied to every event after it
+// https://dev.mysql.com/doc/refman/8.0/en/replication-options-binary-log.html#sysvar_binlog_checksum
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ChecksumAlgorithm {
+    Off,
+    Crc32,
+    Unknown(u8),
+}
+
+impl ChecksumAlgorithm {
+    pub const CRC32_LEN: usize = 4;
+
+    pub fn from_byte(b: u8) -> Self {
+        match b {
+            0 => ChecksumAlgorithm::Off,
+            1 => ChecksumAlgorithm::Crc32,
+            b => ChecksumAlgorithm::Unknown(b),
+        }
+    }
+
+    // Servers from 5.6.1 on end the FormatDescriptionEvent body with the algorithm byte and
+    // (whatever the algorithm) its own 4 byte checksum; None for older ones, which have neither.
+    pub fn from_format_description(data: &[u8]) -> Option<Self> {
+        let server_version = data.get(2..52).map_or(&[][..], |v| v.split(|b| *b == 0).next().unwrap_or(v));
+        if !has_checksum_alg(server_version) || data.len() < 57 + Self::CRC32_LEN + 1 {
+            return None;
+        }
+        Some(Self::from_byte(data[data.len() - Self::CRC32_LEN - 1]))
+    }
+}
+
+pub(crate) fn has_checksum_alg(server_version: &[u8]) -> bool {
+    let version = String::from_utf8_lossy(server_version);
+    let mut numbers = version.split(|c: char| !c.is_ascii_digit()).map(|n| n.parse::<u32>().unwrap_or(0));
+    let major = numbers.next().unwrap_or(0);
+    let minor = numbers.next().unwrap_or(0);
+    let patch = numbers.next().unwrap_or(0);
+    // MariaDB had checksums first, from 5.3
+    if version.contains("MariaDB") {
+        return (major, minor) >= (5, 3);
+    }
+    (major, minor, patch) >= (5, 6, 1)
+}
+
+// the CRC32 covers the header and the body up to the checksum itself
+pub fn crc32(header: &[u8], body: &[u8]) -> u32 {
+    let mut hasher = crc32fast::Hasher::new();
+    hasher.update(header);
+    hasher.update(body);
+    hasher.finalize()
+}
+
+pub fn verify_crc32(offset: u64, header: &[u8; EventHeader::LEN], body: &[u8]) -> Result<(), EventParseError> {
+    let split = body.len().checked_sub(ChecksumAlgorithm::CRC32_LEN)
+        .ok_or(EventParseError::ChecksumMismatch { offset, expected: 0, actual: 0 })?;
+    let (payload, checksum) = body.split_at(split);
+    let expected = u32::from_le_bytes([checksum[0], checksum[1], checksum[2], checksum[3]]);
+    let actual = crc32(header, payload);
+    if expected != actual {
+        return Err(EventParseError::ChecksumMismatch { offset, expected, actual });
+    }
+    Ok(())
+}
+
+// Follows the algorithm across FormatDescriptionEvents and checks each event read after one.
+#[derive(Debug, Clone)]
+pub(crate) struct ChecksumVerifier {
+    algorithm: ChecksumAlgorithm,
+}
+
+impl Default for ChecksumVerifier {
+    fn default() -> Self {
+        ChecksumVerifier { algorithm: ChecksumAlgorithm::Off }
+    }
+}
+
+impl ChecksumVerifier {
+    // for streams whose events before the first FormatDescriptionEvent are already checksummed,
+    // like the artificial RotateEvent a server starts a dump with
+    pub(crate) fn with_algorithm(algorithm: ChecksumAlgorithm) -> Self {
+        ChecksumVerifier { algorithm }
+    }
+
+    pub(crate) fn algorithm(&self) -> ChecksumAlgorithm {
+        self.algorithm
+    }
+
+    // Verifies `event` and hides its checksum from data(). A FormatDescriptionEvent is checked
+    // against the algorithm it announces, which then applies to the events after it.
+    // In lenient mode a mismatch is recorded on the event instead.
+    pub(crate) fn check(&mut self, header: &[u8; EventHeader::LEN], event: &mut Event, mode: ParseMode) -> Result<(), EventParseError> {
+        self.detect(event)?;
+        if self.algorithm == ChecksumAlgorithm::Crc32 {
+            let mut header = *header;
+            if event.type_code() == TypeCode::FormatDescriptionEvent {
+                // the in-use flag is flipped in place when the file is closed, so the server
+                // checksums the FDE as if it were clear
+                header[17] &= !(LOG_EVENT_BINLOG_IN_USE_F as u8);
+            }
+            match verify_crc32(event.offset(), &header, event.raw_body()?) {
+                Err(e @ EventParseError::ChecksumMismatch { .. }) if mode == ParseMode::Lenient => event.add_diagnostic(e),
+                result => result?,
+            }
+        }
+        Ok(())
+    }
+
+    // like check() but without reading the body, except for a FormatDescriptionEvent's
+    pub(crate) fn detect(&mut self, event: &mut Event) -> Result<(), EventParseError> {
+        if event.type_code() == TypeCode::FormatDescriptionEvent {
+            let algorithm = ChecksumAlgorithm::from_format_description(event.raw_body()?);
+            self.algorithm = algorithm.unwrap_or(ChecksumAlgorithm::Off);
+            if algorithm.is_some() {
+                event.set_checksum_len(ChecksumAlgorithm::CRC32_LEN);
+            }
+        } else if self.algorithm == ChecksumAlgorithm::Crc32 {
+            event.set_checksum_len(ChecksumAlgorithm::CRC32_LEN);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::checksum::crc32;
+
+    #[test]
+    fn test_crc32() {
+        //given
+        let (header, body) = b"123456789".split_at(4);
+
+        //when
+        let checksum = crc32(header, body);
+
+        //then
+        assert_eq!(checksum, 0xcbf4_3926);
+    }
+}
@@ -0,0 +1,1705 @@
+use std::net::TcpStream;
+use std::time::{Duration, Instant};
+use byteorder::{LittleEndian, WriteBytesExt};
+use rand_core::RngCore;
+use crate::auth::{self, Credentials};
+use crate::checksum::{ChecksumAlgorithm, ChecksumVerifier};
+use crate::errors::{BinlogFileError, ClientError, EventParseError};
+use crate::event::{Event, EventData, EventHeader, TypeCode};
+use crate::gtid::{format_uuid, parse_uuid, GtidSet, MariaDbGtidList, ResumeCheck};
+use crate::options::ParseMode;
+use crate::position::BinlogPosition;
+#[cfg(feature = "tls")]
+use crate::protocol::{ssl_request, CLIENT_SSL};
+#[cfg(feature = "tls")]
+use crate::tls::TlsConfig;
+use crate::transaction::{role, GtidTracker, Role, Transactions};
+use crate::protocol::{
+    is_eof, parse_err_packet, Connection, Handshake, CLIENT_LONG_PASSWORD, CLIENT_PLUGIN_AUTH,
+    CLIENT_PROTOCOL_41, CLIENT_SECURE_CONNECTION, CLIENT_TRANSACTIONS, COM_BINLOG_DUMP, COM_BINLOG_DUMP_GTID,
+    COM_REGISTER_SLAVE,
+};
+
+// Connects to a server as a replica and streams its binlog from a file and position, the way
+// `mysqlbinlog --read-from-remote-server` does. The account needs REPLICATION SLAVE.
+//
+//     let events = ReplicationClient::new("db1:3306")
+//         .user("repl")
+//         .server_id(4242)
+//         .start_position(BinlogPosition::new("mysql-bin.000123", 4))
+//         .connect()?;
+//
+// A start position carrying a GTID set (see start_gtid_set()) is dumped by GTID instead: the
+// server sends every transaction not in the set, whichever file it's in. MariaDB sources,
+// told apart by their version string, are started by their own GTIDs with start_mariadb_gtid().
+#[derive(Debug, Clone)]
+pub struct ReplicationClient {
+    addr: String,
+    user: String,
+    password: String,
+    server_public_key: Option<String>,
+    server_id: u32,
+    start: BinlogPosition,
+    report_host: Option<(String, u16)>,
+    replica_uuid: Option<String>,
+    semi_sync: bool,
+    heartbeat_period: Option<Duration>,
+    skip_heartbeats: bool,
+    non_blocking: bool,
+    failover_hosts: Vec<String>,
+    retry: RetryPolicy,
+    mariadb_gtid_state: Option<MariaDbGtidList>,
+    #[cfg(feature = "tls")]
+    tls: Option<TlsConfig>,
+}
+
+impl ReplicationClient {
+    pub fn new(addr: &str) -> Self {
+        ReplicationClient {
+            addr: addr.to_owned(),
+            user: "root".to_owned(),
+            password: String::new(),
+            server_public_key: None,
+            server_id: 65535,
+            // an empty file name starts from the oldest binlog the server still has
+            start: BinlogPosition::new("", 4),
+            report_host: None,
+            replica_uuid: None,
+            semi_sync: false,
+            heartbeat_period: None,
+            skip_heartbeats: false,
+            non_blocking: false,
+            failover_hosts: vec![],
+            retry: RetryPolicy::default(),
+            mariadb_gtid_state: None,
+            #[cfg(feature = "tls")]
+            tls: None,
+        }
+    }
+
+    pub fn user(mut self, user: &str) -> Self {
+        self.user = user.to_owned();
+        self
+    }
+
+    pub fn password(mut self, password: &str) -> Self {
+        self.password = password.to_owned();
+        self
+    }
+
+    // PEM of the server's RSA public key (its caching_sha2_password_public_key_path), used to
+    // send the password to caching_sha2_password accounts without TLS; otherwise the key is
+    // asked from the server, trusting whatever answers
+    pub fn server_public_key(mut self, pem: &str) -> Self {
+        self.server_public_key = Some(pem.to_owned());
+        self
+    }
+
+    // Must differ from the server_id of the source and of every other replica: the source
+    // drops an existing replica's connection when another registers with the same id.
+    pub fn server_id(mut self, server_id: u32) -> Self {
+        self.server_id = server_id;
+        self
+    }
+
+    // what SHOW REPLICAS on the source lists for this client, like report_host and report_port
+    pub fn report_host(mut self, host: &str, port: u16) -> Self {
+        self.report_host = Some((host.to_owned(), port));
+        self
+    }
+
+    // the server_uuid the source sees, like a replica's own; it identifies the replica in
+    // SHOW REPLICAS and, like server_id, must be unique among them
+    pub fn replica_uuid(mut self, uuid: &str) -> Self {
+        self.replica_uuid = Some(uuid.to_owned());
+        self
+    }
+
+    pub fn start_position(mut self, position: BinlogPosition) -> Self {
+        self.start = position;
+        self
+    }
+
+    // resume after the transactions in `executed`, e.g. a checkpointed @@gtid_executed
+    pub fn start_gtid_set(mut self, executed: GtidSet) -> Self {
+        self.start = BinlogPosition::new("", 4).with_gtid_set(executed);
+        self
+    }
+
+    // Have the source end the stream once it has sent everything it has, rather than wait for
+    // more, like `mysqlbinlog --read-from-remote-server` without --stop-never; for catch-up jobs
+    pub fn non_blocking(mut self, enabled: bool) -> Self {
+        self.non_blocking = enabled;
+        self
+    }
+
+    // Servers a reconnecting() stream moves on to, in turn, when it can't reconnect to the one
+    // it was reading from, e.g. the replicas a primary may fail over to. Their binlog files
+    // have names and positions of their own, so the stream only moves when it knows its GTIDs
+    // (see start_gtid_set()) and resumes on the new server by GTID.
+    pub fn failover_hosts(mut self, addrs: &[&str]) -> Self {
+        self.failover_hosts = addrs.iter().map(|addr| (*addr).to_owned()).collect();
+        self
+    }
+
+    // how a reconnecting() stream retries; see RetryPolicy
+    pub fn retry_policy(mut self, policy: RetryPolicy) -> Self {
+        self.retry = policy;
+        self
+    }
+
+    // Resume a MariaDB source after the last GTID of each domain in `state`, e.g. a checkpointed
+    // @@gtid_slave_pos; like MASTER_USE_GTID, the source finds the file and position itself.
+    pub fn start_mariadb_gtid(mut self, state: MariaDbGtidList) -> Self {
+        self.mariadb_gtid_state = Some(state);
+        self
+    }
+
+    // Acknowledge transactions as a semi-synchronous replica when the source has the semi-sync
+    // plugin enabled, so its commits wait for this client to have received them. The stream
+    // falls back to asynchronous replication otherwise; see BinlogStream::is_semi_sync().
+    pub fn semi_sync(mut self, enabled: bool) -> Self {
+        self.semi_sync = enabled;
+        self
+    }
+
+    // Have the source send a HeartbeatLogEvent whenever it has had nothing else to send for
+    // `period`, like MASTER_HEARTBEAT_PERIOD. Without heartbeats an idle source and a dead
+    // connection look the same; see BinlogStream::last_received().
+    pub fn heartbeat_period(mut self, period: Duration) -> Self {
+        self.heartbeat_period = Some(period);
+        self
+    }
+
+    // keep heartbeats out of the stream; they still count for last_received()
+    pub fn skip_heartbeats(mut self, skip: bool) -> Self {
+        self.skip_heartbeats = skip;
+        self
+    }
+
+    // refuse to connect unless the session can be encrypted with `config`
+    #[cfg(feature = "tls")]
+    pub fn tls(mut self, config: TlsConfig) -> Self {
+        self.tls = Some(config);
+        self
+    }
+
+    pub fn connect(&self) -> Result<BinlogStream, ClientError> {
+        let stream = TcpStream::connect(&self.addr)?;
+        stream.set_nodelay(true)?;
+        #[cfg(feature = "async")]
+        let socket = stream.try_clone()?;
+        let (mut conn, handshake) = self.login(stream)?;
+        let mariadb = handshake.server_version.contains("MariaDB");
+        if let (Some(state), false) = (&self.mariadb_gtid_state, mariadb) {
+            return Err(ClientError::MariaDbGtidOnMysql(state.to_string()));
+        }
+        let checksum = self.negotiate_checksum(&mut conn)?;
+        if mariadb {
+            // without it the source rewrites its GTID and annotate events for MySQL 5.5 replicas
+            conn.execute(&format!("SET @mariadb_slave_capability = {}", MARIA_SLAVE_CAPABILITY_MINE))?;
+        }
+        if let Some(uuid) = &self.replica_uuid {
+            let uuid = parse_uuid(uuid).map_err(|_| ClientError::BadReplicaUuid(uuid.clone()))?;
+            let uuid = format_uuid(&uuid);
+            conn.execute(&format!("SET @slave_uuid = '{}', @replica_uuid = '{}'", uuid, uuid))?;
+        }
+        let semi_sync = self.semi_sync && self.enable_semi_sync(&mut conn)?;
+        if let Some(period) = self.heartbeat_period {
+            conn.execute(&format!("SET @master_heartbeat_period = {}", period.as_nanos()))?;
+        }
+        self.register_replica(&mut conn)?;
+        match &self.mariadb_gtid_state {
+            Some(state) => self.binlog_dump_mariadb_gtid(&mut conn, state)?,
+            None => self.binlog_dump(&mut conn)?,
+        }
+        Ok(BinlogStream {
+            conn,
+            #[cfg(feature = "async")]
+            socket,
+            mariadb,
+            position: self.start.clone(),
+            checksum: ChecksumVerifier::with_algorithm(checksum),
+            semi_sync,
+            skip_heartbeats: self.skip_heartbeats,
+            last_received: None,
+            resume_position: self.start.clone(),
+            gtids: self.start.gtid_set.clone().map_or_else(GtidTracker::new, GtidTracker::with_executed),
+            finished: false,
+        })
+    }
+
+    // Where the server is writing its binlog now, from SHOW BINARY LOG STATUS (SHOW MASTER
+    // STATUS before MySQL 8.2), e.g. to start a stream with only what's written from here on.
+    pub fn current_position(&self) -> Result<BinlogPosition, ClientError> {
+        let (mut conn, _) = self.login(TcpStream::connect(&self.addr)?)?;
+        let rows = match conn.query("SHOW BINARY LOG STATUS") {
+            Err(ClientError::Server { code: ER_PARSE_ERROR, .. }) => conn.query("SHOW MASTER STATUS")?,
+            rows => rows?,
+        };
+        let row = rows.first().ok_or(ClientError::BinlogDisabled)?;
+        match (row.first(), row.get(1)) {
+            (Some(Some(file)), Some(Some(pos))) => {
+                let pos = pos.parse().map_err(|_| ClientError::BadPacket("binary log status"))?;
+                Ok(BinlogPosition::new(file, pos))
+            }
+            _ => Err(ClientError::BadPacket("binary log status")),
+        }
+    }
+
+    // the GTIDs the server has purged from its binlogs, @@gtid_purged
+    pub fn gtid_purged(&self) -> Result<GtidSet, ClientError> {
+        let (mut conn, _) = self.login(TcpStream::connect(&self.addr)?)?;
+        let rows = conn.query("SELECT @@GLOBAL.gtid_purged")?;
+        match rows.first().and_then(|row| row.first()) {
+            Some(Some(purged)) => purged.parse().map_err(|_| ClientError::BadPacket("gtid_purged")),
+            _ => Err(ClientError::BadPacket("gtid_purged")),
+        }
+    }
+
+    // whether a stream can resume from `checkpoint` without needing transactions the server
+    // has purged, which start_gtid_set() would fail on
+    pub fn check_resume(&self, checkpoint: &GtidSet) -> Result<ResumeCheck, ClientError> {
+        Ok(ResumeCheck::new(checkpoint, &self.gtid_purged()?))
+    }
+
+    // the connection phase: the server's handshake, TLS if configured, and the login
+    fn login(&self, stream: TcpStream) -> Result<(Connection, Handshake), ClientError> {
+        let mut conn = Connection::new(Box::new(stream));
+        let handshake = Handshake::parse(&conn.read_packet()?)?;
+        let capabilities = handshake.capabilities
+            & (CLIENT_LONG_PASSWORD | CLIENT_PROTOCOL_41 | CLIENT_TRANSACTIONS | CLIENT_SECURE_CONNECTION | CLIENT_PLUGIN_AUTH);
+        let (mut conn, capabilities, secure) = self.start_tls(conn, &handshake, capabilities)?;
+        self.authenticate(&mut conn, &handshake, capabilities, secure)?;
+        Ok((conn, handshake))
+    }
+
+    // A stream that survives dropped connections: it reconnects, backing off between failed
+    // attempts, and resumes from the last complete transaction, skipping the events it had
+    // already returned.
+    pub fn reconnecting(self) -> ReconnectingStream {
+        let mut hosts = vec![self.addr.clone()];
+        hosts.extend(self.failover_hosts.iter().cloned());
+        ReconnectingStream {
+            resume: self.start.clone(),
+            client: self,
+            stream: None,
+            hosts,
+            host: 0,
+            delivered: None,
+            replaying: false,
+            in_flight: 0,
+            failover_skip: None,
+            attempts: 0,
+            finished: false,
+        }
+    }
+
+    // https://dev.mysql.com/doc/dev/mysql-server/latest/page_protocol_connection_phase_tls.html
+    #[cfg(feature = "tls")]
+    fn start_tls(&self, mut conn: Connection, handshake: &Handshake, capabilities: u32) -> Result<(Connection, u32, bool), ClientError> {
+        let tls = match &self.tls {
+            Some(tls) => tls,
+            None => return Ok((conn, capabilities, false)),
+        };
+        if handshake.capabilities & CLIENT_SSL == 0 {
+            return Err(ClientError::TlsNotSupported);
+        }
+        let capabilities = capabilities | CLIENT_SSL;
+        conn.write_packet(&ssl_request(capabilities))?;
+        let conn = conn.upgrade(|stream| tls.connect(stream, self.host()))?;
+        Ok((conn, capabilities, true))
+    }
+
+    #[cfg(not(feature = "tls"))]
+    fn start_tls(&self, conn: Connection, _handshake: &Handshake, capabilities: u32) -> Result<(Connection, u32, bool), ClientError> {
+        Ok((conn, capabilities, false))
+    }
+
+    fn authenticate(&self, conn: &mut Connection, handshake: &Handshake, capabilities: u32, secure: bool) -> Result<(), ClientError> {
+        let credentials = Credentials {
+            user: &self.user,
+            password: &self.password,
+            server_public_key: self.server_public_key.as_deref(),
+            secure,
+        };
+        auth::authenticate(conn, handshake, capabilities, &credentials)
+    }
+
+    // A server with binlog_checksum on only streams to replicas that say they can take the
+    // checksums, by setting @master_binlog_checksum; servers before 5.6.2 have neither.
+    fn negotiate_checksum(&self, conn: &mut Connection) -> Result<ChecksumAlgorithm, ClientError> {
+        let rows = match conn.query("SELECT @@global.binlog_checksum") {
+            Ok(rows) => rows,
+            Err(ClientError::Server { code: ER_UNKNOWN_SYSTEM_VARIABLE, .. }) => return Ok(ChecksumAlgorithm::Off),
+            Err(e) => return Err(e),
+        };
+        let algorithm = checksum_algorithm(rows.first().and_then(|row| row.first()).and_then(|value| value.as_deref()))?;
+        conn.execute("SET @master_binlog_checksum = @@global.binlog_checksum")?;
+        Ok(algorithm)
+    }
+
+    // The source only adds semi-sync headers for replicas that set @rpl_semi_sync_slave (or
+    // @rpl_semi_sync_replica from 8.0.26 on), and only while its plugin is enabled.
+    fn enable_semi_sync(&self, conn: &mut Connection) -> Result<bool, ClientError> {
+        let rows = conn.query("SHOW VARIABLES LIKE 'rpl_semi_sync_%_enabled'")?;
+        let enabled = rows.iter().any(|row| match row.as_slice() {
+            [Some(name), Some(value)] => {
+                (name.ends_with("master_enabled") || name.ends_with("source_enabled")) && value == "ON"
+            }
+            _ => false,
+        });
+        if enabled {
+            conn.execute("SET @rpl_semi_sync_slave = 1, @rpl_semi_sync_replica = 1")?;
+        }
+        Ok(enabled)
+    }
+
+    // the host part of addr, which the server's certificate must name
+    #[cfg(feature = "tls")]
+    fn host(&self) -> &str {
+        let host = self.addr.rsplit_once(':').map_or(self.addr.as_str(), |(host, _)| host);
+        host.trim_start_matches('[').trim_end_matches(']')
+    }
+
+    // https://dev.mysql.com/doc/dev/mysql-server/latest/page_protocol_com_register_slave.html
+    fn register_replica(&self, conn: &mut Connection) -> Result<(), ClientError> {
+        let mut body = vec![];
+        body.write_u32::<LittleEndian>(self.server_id)?;
+        let (host, port) = self.report_host.as_ref().map_or(("", 0), |(host, port)| (host.as_str(), *port));
+        body.push(host.len().min(255) as u8);
+        body.extend_from_slice(&host.as_bytes()[..host.len().min(255)]);
+        // report_user and report_password, which the source only shows with show_replica_auth_info
+        body.extend_from_slice(&[0, 0]);
+        body.write_u16::<LittleEndian>(port)?;
+        // replication rank and source id, both unused
+        body.write_u32::<LittleEndian>(0)?;
+        body.write_u32::<LittleEndian>(0)?;
+        conn.write_command(COM_REGISTER_SLAVE, &body)?;
+        conn.read_ok()
+    }
+
+    // https://dev.mysql.com/doc/dev/mysql-server/latest/page_protocol_com_binlog_dump.html
+    fn binlog_dump(&self, conn: &mut Connection) -> Result<(), ClientError> {
+        if let Some(executed) = &self.start.gtid_set {
+            return self.binlog_dump_gtid(conn, executed);
+        }
+        let mut body = vec![];
+        body.write_u32::<LittleEndian>(self.start.pos as u32)?;
+        body.write_u16::<LittleEndian>(self.dump_flags())?;
+        body.write_u32::<LittleEndian>(self.server_id)?;
+        body.extend_from_slice(self.start.file.as_bytes());
+        conn.write_command(COM_BINLOG_DUMP, &body)
+    }
+
+    fn dump_flags(&self) -> u16 {
+        if self.non_blocking {
+            BINLOG_DUMP_NON_BLOCK
+        } else {
+            0
+        }
+    }
+
+    // MariaDB takes the GTID state as session variables and a dump from no file in particular
+    // https://mariadb.com/kb/en/com_binlog_dump/
+    fn binlog_dump_mariadb_gtid(&self, conn: &mut Connection, state: &MariaDbGtidList) -> Result<(), ClientError> {
+        conn.execute(&format!("SET @slave_connect_state = '{}'", state))?;
+        conn.execute("SET @slave_gtid_strict_mode = 0")?;
+        conn.execute("SET @slave_gtid_ignore_duplicates = 0")?;
+        let mut body = vec![];
+        body.write_u32::<LittleEndian>(4)?;
+        body.write_u16::<LittleEndian>(self.dump_flags())?;
+        body.write_u32::<LittleEndian>(self.server_id)?;
+        conn.write_command(COM_BINLOG_DUMP, &body)
+    }
+
+    // https://dev.mysql.com/doc/dev/mysql-server/latest/page_protocol_com_binlog_dump_gtid.html
+    fn binlog_dump_gtid(&self, conn: &mut Connection, executed: &GtidSet) -> Result<(), ClientError> {
+        let gtid_data = executed.encode();
+        let mut body = vec![];
+        body.write_u16::<LittleEndian>(self.dump_flags() | BINLOG_THROUGH_GTID)?;
+        body.write_u32::<LittleEndian>(self.server_id)?;
+        body.write_u32::<LittleEndian>(self.start.file.len() as u32)?;
+        body.extend_from_slice(self.start.file.as_bytes());
+        body.write_u64::<LittleEndian>(self.start.pos)?;
+        body.write_u32::<LittleEndian>(gtid_data.len() as u32)?;
+        body.extend_from_slice(&gtid_data);
+        conn.write_command(COM_BINLOG_DUMP_GTID, &body)
+    }
+}
+
+// COM_BINLOG_DUMP flags: an EOF packet instead of waiting at the end of the binlog, and
+// (COM_BINLOG_DUMP_GTID only) the GTID set is sent along with the file and position
+const BINLOG_DUMP_NON_BLOCK: u16 = 0x01;
+const BINLOG_THROUGH_GTID: u16 = 0x04;
+
+pub(crate) const ER_UNKNOWN_SYSTEM_VARIABLE: u16 = 1193;
+const ER_PARSE_ERROR: u16 = 1064;
+
+// MARIA_SLAVE_CAPABILITY_GTID: the replica understands GTID events and annotate rows events
+const MARIA_SLAVE_CAPABILITY_MINE: u8 = 4;
+
+// first byte of the semi-sync header before each event, and of the ACK packets answering it
+const SEMI_SYNC_INDICATOR: u8 = 0xef;
+const SEMI_SYNC_ACK_REQUESTED: u8 = 0x01;
+
+// The events a server sends after COM_BINLOG_DUMP. The stream opens with an artificial
+// RotateEvent naming the file and, from the second event on, follows it across rotations. It
+// ends with an EOF packet from the server or with the connection; an ERR packet from the
+// server comes out as ClientError::Server, as the stream's last item.
+pub struct BinlogStream {
+    conn: Connection,
+    // the TCP connection under conn, whatever it's wrapped in, to close it from another thread
+    #[cfg(feature = "async")]
+    socket: TcpStream,
+    mariadb: bool,
+    position: BinlogPosition,
+    checksum: ChecksumVerifier,
+    semi_sync: bool,
+    skip_heartbeats: bool,
+    last_received: Option<Instant>,
+    resume_position: BinlogPosition,
+    gtids: GtidTracker,
+    finished: bool,
+}
+
+impl BinlogStream {
+    // where the next event will come from
+    pub fn position(&self) -> &BinlogPosition {
+        &self.position
+    }
+
+    // where a new connection should start so that no transaction is cut in two: the end of the
+    // last one read completely (rows events need the table maps at their transaction's start)
+    pub fn resume_position(&self) -> &BinlogPosition {
+        &self.resume_position
+    }
+
+    // The GTIDs executed up to the last transaction read completely: the start set when started
+    // by GTID, else from the first Previous_gtids the source sent, if it sent one.
+    pub fn executed_gtids(&self) -> Option<&GtidSet> {
+        self.gtids.executed()
+    }
+
+    // whether the source is MariaDB, whose GTIDs come as MariaDbGtidEvent and MariaDbGtidListEvent
+    pub fn is_mariadb(&self) -> bool {
+        self.mariadb
+    }
+
+    // whether the source treats this stream as a semi-sync replica
+    pub fn is_semi_sync(&self) -> bool {
+        self.semi_sync
+    }
+
+    // when the last event or heartbeat arrived; with a heartbeat period set, nothing for
+    // several periods means the connection is stalled rather than the source idle
+    pub fn last_received(&self) -> Option<Instant> {
+        self.last_received
+    }
+
+    // the server's binlog_checksum, verified and stripped from every event
+    pub fn checksum_algorithm(&self) -> ChecksumAlgorithm {
+        self.checksum.algorithm()
+    }
+
+    pub fn transactions(self) -> Transactions<Self> {
+        Transactions::new(self)
+    }
+
+    #[cfg(feature = "async")]
+    pub(crate) fn socket(&self) -> std::io::Result<TcpStream> {
+        self.socket.try_clone()
+    }
+
+    // None once the server ends the dump with an EOF packet
+    fn read_event(&mut self) -> Result<Option<Event>, BinlogFileError> {
+        let packet = self.conn.read_packet()?;
+        let event_bytes = match packet.split_first() {
+            Some((0x00, event_bytes)) => event_bytes,
+            Some((0xff, _)) => return Err(parse_err_packet(&packet).into()),
+            Some((0xfe, _)) if is_eof(&packet) => return Ok(None),
+            _ => return Err(ClientError::BadPacket("binlog event").into()),
+        };
+        self.last_received = Some(Instant::now());
+        // https://dev.mysql.com/doc/refman/8.0/en/replication-semisync.html
+        let mut needs_ack = false;
+        let event_bytes = match event_bytes {
+            [SEMI_SYNC_INDICATOR, flags, event_bytes @ ..] if self.semi_sync => {
+                needs_ack = flags & SEMI_SYNC_ACK_REQUESTED != 0;
+                event_bytes
+            }
+            event_bytes => event_bytes,
+        };
+        let event = decode_event(event_bytes, &mut self.position, &mut self.checksum)?;
+        self.track_transaction(&event)?;
+        if needs_ack {
+            self.send_ack()?;
+        }
+        Ok(Some(event))
+    }
+
+    // tells the source everything up to the current position has been received
+    fn send_ack(&mut self) -> Result<(), ClientError> {
+        let mut body = vec![];
+        body.write_u64::<LittleEndian>(self.position.pos)?;
+        body.extend_from_slice(self.position.file.as_bytes());
+        self.conn.write_command(SEMI_SYNC_INDICATOR, &body)
+    }
+
+    // Follows transaction boundaries: the GTID of each transaction joins the executed set once
+    // its commit has been read, and resume_position() moves to just after it. The position
+    // carries the set only when the stream was started by GTID, so that resuming a stream
+    // started by file and position stays that way.
+    fn track_transaction(&mut self, event: &Event) -> Result<(), EventParseError> {
+        if self.gtids.track(event)? {
+            if self.position.gtid_set.is_some() {
+                self.position.gtid_set = self.gtids.executed().cloned();
+            }
+            self.resume_position = self.position.clone();
+        }
+        Ok(())
+    }
+}
+
+pub struct ReconnectingStream {
+    client: ReplicationClient,
+    stream: Option<BinlogStream>,
+    resume: BinlogPosition,
+    // the client's addr then its failover hosts, and which of them `stream` is (or was) from
+    hosts: Vec<String>,
+    host: usize,
+    // the stream position after the last event returned
+    delivered: Option<BinlogPosition>,
+    // reconnected and not yet past `delivered`
+    replaying: bool,
+    // events returned of the transaction in progress
+    in_flight: usize,
+    // moved to another host, whose positions can't be compared with `delivered`: how many
+    // events of the first transaction it sends were already returned
+    failover_skip: Option<usize>,
+    attempts: u32,
+    finished: bool,
+}
+
+impl ReconnectingStream {
+    // the connection events are currently read from, if any
+    pub fn stream(&self) -> Option<&BinlogStream> {
+        self.stream.as_ref()
+    }
+
+    // where the stream would resume if the connection dropped now
+    pub fn resume_position(&self) -> &BinlogPosition {
+        &self.resume
+    }
+
+    // the server events are read from, or will be once reconnected
+    pub fn current_host(&self) -> &str {
+        &self.hosts[self.host]
+    }
+
+    pub fn transactions(self) -> Transactions<Self> {
+        Transactions::new(self)
+    }
+
+    fn connect(&mut self) -> Result<(), ClientError> {
+        let previous = self.host;
+        loop {
+            let mut client = self.client.clone();
+            client.addr = self.hosts[self.host].clone();
+            let start = match &self.resume.gtid_set {
+                Some(executed) if self.host != previous => BinlogPosition::new("", 4).with_gtid_set(executed.clone()),
+                _ => self.resume.clone(),
+            };
+            match client.start_position(start).connect() {
+                Ok(stream) => {
+                    if self.host == previous {
+                        self.replaying = self.delivered.is_some();
+                    } else {
+                        self.replaying = false;
+                        self.failover_skip = Some(self.in_flight);
+                    }
+                    self.stream = Some(stream);
+                    return Ok(());
+                }
+                Err(e) if (self.client.retry.retryable)(&e) && self.attempts < self.client.retry.max_attempts => {
+                    if self.resume.gtid_set.is_some() {
+                        self.host = (self.host + 1) % self.hosts.len();
+                    }
+                    std::thread::sleep(self.client.retry.delay(self.attempts));
+                    self.attempts += 1;
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    // An event the previous connection already returned: anything up to where it got, which
+    // includes the artificial Rotate and FormatDescriptionEvent a new dump starts with.
+    fn is_replayed(&mut self, event: &Event, position: &BinlogPosition) -> bool {
+        let delivered = match (&self.delivered, self.replaying) {
+            (Some(delivered), true) => delivered,
+            _ => return false,
+        };
+        let replayed = is_heartbeat(event.type_code())
+            || BinlogPosition::new(&position.file, position.pos) <= BinlogPosition::new(&delivered.file, delivered.pos);
+        if !replayed {
+            self.replaying = false;
+        }
+        replayed
+    }
+
+    // After a failover: the new host's Rotate and FormatDescriptionEvent are its own and go
+    // through, the first events of the transaction that was cut short don't.
+    fn is_replayed_after_failover(&mut self, event: &Event) -> bool {
+        let remaining = match self.failover_skip {
+            Some(remaining) => remaining,
+            None => return false,
+        };
+        if matches!(role(event), Ok(Role::Standalone)) {
+            return false;
+        }
+        if remaining == 0 {
+            self.failover_skip = None;
+            return false;
+        }
+        self.failover_skip = Some(remaining - 1);
+        true
+    }
+}
+
+impl Iterator for ReconnectingStream {
+    type Item = Result<Event, BinlogFileError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while !self.finished {
+            if self.stream.is_none() {
+                if let Err(e) = self.connect() {
+                    self.finished = true;
+                    return Some(Err(e.into()));
+                }
+            }
+            let next = self.stream.as_mut()?.next();
+            match next {
+                Some(Ok(event)) => {
+                    self.attempts = 0;
+                    let stream = self.stream.as_ref()?;
+                    let position = stream.position().clone();
+                    let at_boundary = stream.resume_position().file == position.file && stream.resume_position().pos == position.pos;
+                    self.resume = stream.resume_position().clone();
+                    if self.is_replayed(&event, &position) || self.is_replayed_after_failover(&event) {
+                        continue;
+                    }
+                    if at_boundary {
+                        self.in_flight = 0;
+                    } else if !matches!(role(&event), Ok(Role::Standalone)) {
+                        self.in_flight += 1;
+                    }
+                    self.delivered = Some(position);
+                    return Some(Ok(event));
+                }
+                Some(Err(BinlogFileError::Client(e))) if (self.client.retry.retryable)(&e) => self.stream = None,
+                Some(Err(e)) => {
+                    self.finished = true;
+                    return Some(Err(e));
+                }
+                // only a non-blocking dump ends with an EOF on purpose
+                None if self.client.non_blocking => self.finished = true,
+                None => self.stream = None,
+            }
+        }
+        None
+    }
+}
+
+// How a ReconnectingStream deals with failures, both of the connection it reads from and of
+// its attempts to connect again. Attempts are counted from the last event received, and wait
+// initial_backoff, growing by `multiplier` each time up to max_backoff. With jitter, each wait
+// is shortened by a random part of up to that fraction, so replicas that lost the same source
+// don't all come back at once.
+//
+//     let policy = RetryPolicy::new()
+//         .max_attempts(50)
+//         .initial_backoff(Duration::from_millis(500))
+//         .jitter(0.5)
+//         .retry_if(|e| matches!(e, ClientError::Io(_) | ClientError::Server { code: 1040, .. }));
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    max_attempts: u32,
+    initial_backoff: Duration,
+    max_backoff: Duration,
+    multiplier: f64,
+    jitter: f64,
+    retryable: fn(&ClientError) -> bool,
+}
+
+impl Default for RetryPolicy {
+    // 10 attempts from 100ms, doubling up to 30s, without jitter
+    fn default() -> Self {
+        RetryPolicy {
+            max_attempts: 10,
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_secs(30),
+            multiplier: 2.0,
+            jitter: 0.0,
+            retryable: is_transient,
+        }
+    }
+}
+
+impl RetryPolicy {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // attempts to reconnect before giving up with the last error; 0 never reconnects
+    pub fn max_attempts(mut self, attempts: u32) -> Self {
+        self.max_attempts = attempts;
+        self
+    }
+
+    pub fn initial_backoff(mut self, backoff: Duration) -> Self {
+        self.initial_backoff = backoff;
+        self
+    }
+
+    pub fn max_backoff(mut self, backoff: Duration) -> Self {
+        self.max_backoff = backoff;
+        self
+    }
+
+    pub fn multiplier(mut self, multiplier: f64) -> Self {
+        self.multiplier = multiplier.max(1.0);
+        self
+    }
+
+    // between 0 (every wait as computed) and 1 (anywhere from nothing to the computed wait)
+    pub fn jitter(mut self, jitter: f64) -> Self {
+        self.jitter = jitter.clamp(0.0, 1.0);
+        self
+    }
+
+    // which errors are worth another attempt; by default I/O errors, i.e. dropped and refused
+    // connections, as errors from the server itself would only repeat
+    pub fn retry_if(mut self, retryable: fn(&ClientError) -> bool) -> Self {
+        self.retryable = retryable;
+        self
+    }
+
+    // the wait before attempt `attempt` + 1
+    pub fn delay(&self, attempt: u32) -> Duration {
+        let exponential = self.initial_backoff.as_secs_f64() * self.multiplier.powi(attempt.min(1000) as i32);
+        let delay = exponential.min(self.max_backoff.as_secs_f64());
+        let random = f64::from(rand_core::OsRng.next_u32()) / f64::from(u32::MAX);
+        Duration::from_secs_f64(delay * (1.0 - self.jitter * random))
+    }
+}
+
+fn is_transient(error: &ClientError) -> bool {
+    matches!(error, ClientError::Io(_))
+}
+
+// Parses an event as sent in a dump, checking its checksum, and moves `position` past it
+pub(crate) fn decode_event(
+    event_bytes: &[u8],
+    position: &mut BinlogPosition,
+    checksum: &mut ChecksumVerifier,
+) -> Result<Event, BinlogFileError> {
+    let mut header = [0u8; EventHeader::LEN];
+    header.copy_from_slice(event_bytes.get(..EventHeader::LEN).ok_or(ClientError::BadPacket("binlog event"))?);
+    let parsed_header = EventHeader::parse(&header);
+    // artificial events have no next_position and aren't in the file at all; a heartbeat's
+    // is where the source is up to
+    let offset = match parsed_header.next_position.checked_sub(parsed_header.event_length) {
+        Some(offset) if parsed_header.next_position != 0 => u64::from(offset),
+        _ => position.pos,
+    };
+
+    let mut event = Event::parse(&mut &event_bytes[..], offset)?;
+    checksum.check(&header, &mut event, ParseMode::Strict)?;
+    if event.next_position() != 0 {
+        position.pos = event.next_position();
+    }
+    if event.type_code() == TypeCode::RotateEvent {
+        if let Some(EventData::RotateEvent { position: pos, next_file }) =
+            Event::parse_event_data_by_type_code(TypeCode::RotateEvent, event.data())?
+        {
+            position.file = next_file;
+            position.pos = pos;
+        }
+    }
+    Ok(event)
+}
+
+// what the server's binlog_checksum means for the events it sends
+pub(crate) fn checksum_algorithm(binlog_checksum: Option<&str>) -> Result<ChecksumAlgorithm, ClientError> {
+    match binlog_checksum {
+        None | Some("NONE") => Ok(ChecksumAlgorithm::Off),
+        Some("CRC32") => Ok(ChecksumAlgorithm::Crc32),
+        Some(other) => Err(ClientError::UnsupportedChecksum(other.to_owned())),
+    }
+}
+
+fn is_heartbeat(type_code: TypeCode) -> bool {
+    matches!(type_code, TypeCode::HeartbeatLogEvent | TypeCode::HeartbeatLogEventV2)
+}
+
+impl Iterator for BinlogStream {
+    type Item = Result<Event, BinlogFileError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.finished {
+            return None;
+        }
+        loop {
+            match self.read_event() {
+                Ok(Some(event)) if self.skip_heartbeats && is_heartbeat(event.type_code()) => continue,
+                Ok(Some(event)) => return Some(Ok(event)),
+                Ok(None) => {
+                    self.finished = true;
+                    return None;
+                }
+                Err(e) => {
+                    self.finished = true;
+                    return Some(Err(e));
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use std::io::{Read, Write};
+    use std::net::{TcpListener, TcpStream};
+    use std::thread::JoinHandle;
+    use std::time::{Duration, Instant};
+    use rsa::pkcs8::{EncodePublicKey, LineEnding};
+    use rsa::{Oaep, RsaPrivateKey};
+    use crate::binlog_file::BinlogFile;
+    use crate::checksum::{crc32, ChecksumAlgorithm};
+    use crate::client::{ReplicationClient, RetryPolicy};
+    use crate::context::ParseContext;
+    use crate::errors::{BinlogFileError, ClientError};
+    use crate::event::TypeCode;
+    use crate::gtid::{GtidSet, MariaDbGtidList, ResumeCheck};
+    use crate::position::BinlogPosition;
+    use crate::rows::RowsEventKind;
+    use crate::transaction::TransactionItem;
+
+    pub(crate) fn write_packet<W: Write>(stream: &mut W, seq: u8, payload: &[u8]) {
+        let len = (payload.len() as u32).to_le_bytes();
+        stream.write_all(&[len[0], len[1], len[2], seq]).unwrap();
+        stream.write_all(payload).unwrap();
+    }
+
+    pub(crate) fn read_packet<R: Read>(stream: &mut R) -> Vec<u8> {
+        let mut header = [0u8; 4];
+        stream.read_exact(&mut header).unwrap();
+        let mut payload = vec![0u8; u32::from_le_bytes([header[0], header[1], header[2], 0]) as usize];
+        stream.read_exact(&mut payload).unwrap();
+        payload
+    }
+
+    pub(crate) fn handshake(plugin: &str, challenge: &[u8; 20]) -> Vec<u8> {
+        server_handshake("8.0.36", plugin, challenge)
+    }
+
+    pub(crate) fn server_handshake(server_version: &str, plugin: &str, challenge: &[u8; 20]) -> Vec<u8> {
+        let mut packet = vec![10];
+        packet.extend_from_slice(server_version.as_bytes());
+        packet.push(0);
+        packet.extend_from_slice(&7u32.to_le_bytes());
+        packet.extend_from_slice(&challenge[..8]);
+        packet.push(0);
+        packet.extend_from_slice(&0xffffu16.to_le_bytes());
+        packet.push(45);
+        packet.extend_from_slice(&2u16.to_le_bytes());
+        packet.extend_from_slice(&0x00ffu16.to_le_bytes());
+        packet.push(21);
+        packet.extend_from_slice(&[0; 10]);
+        packet.extend_from_slice(&challenge[8..]);
+        packet.push(0);
+        packet.extend_from_slice(plugin.as_bytes());
+        packet.push(0);
+        packet
+    }
+
+    // The events of the test binlog as a server would send them after COM_BINLOG_DUMP from
+    // the start of the file: an artificial Rotate, then everything after the magic.
+    pub(crate) fn dump_packets() -> Vec<Vec<u8>> {
+        let mut packets = vec![artificial_rotate("mysql-bin.100746", 4)[1..].to_vec()];
+        let bytes = std::fs::read("tests/asset/mysql-bin.100746").unwrap();
+        for event in BinlogFile::from_path("tests/asset/mysql-bin.100746").unwrap().events() {
+            let event = event.unwrap();
+            let start = event.offset() as usize;
+            packets.push(bytes[start..start + event.event_length() as usize].to_vec());
+        }
+        packets.iter().map(|p| [&[0u8][..], p].concat()).collect()
+    }
+
+    // a text result set of `rows`; clients here don't look at the column definitions
+    pub(crate) fn write_result_set(stream: &mut TcpStream, rows: &[&[Option<&str>]]) {
+        let columns = rows.first().map_or(1, |row| row.len());
+        write_packet(stream, 1, &[columns as u8]);
+        for seq in 0..columns {
+            write_packet(stream, seq as u8 + 2, b"\x03def\0\0\0\x01v\0\x0c\x21\0\0\0\0\0\xfd\0\0\0\0\0");
+        }
+        write_packet(stream, columns as u8 + 2, &[0xfe, 0, 0, 2, 0]);
+        for (seq, row) in rows.iter().enumerate() {
+            let mut packet = vec![];
+            for value in row.iter() {
+                match value {
+                    Some(value) => {
+                        packet.push(value.len() as u8);
+                        packet.extend_from_slice(value.as_bytes());
+                    }
+                    None => packet.push(0xfb),
+                }
+            }
+            write_packet(stream, (columns + seq) as u8 + 3, &packet);
+        }
+        write_packet(stream, (columns + rows.len()) as u8 + 3, &[0xfe, 0, 0, 2, 0]);
+    }
+
+    // the CRC32-checksummed RotateEvent a dump from `file` at `pos` starts with, as a packet
+    pub(crate) fn artificial_rotate(file: &str, pos: u64) -> Vec<u8> {
+        let mut rotate = vec![0, 0, 0, 0, 4, 1, 0, 0, 0];
+        rotate.extend_from_slice(&(19 + 8 + file.len() as u32 + 4).to_le_bytes());
+        rotate.extend_from_slice(&[0, 0, 0, 0, 0x20, 0]);
+        rotate.extend_from_slice(&pos.to_le_bytes());
+        rotate.extend_from_slice(file.as_bytes());
+        let checksum = crc32(&rotate[..19], &rotate[19..]);
+        rotate.extend_from_slice(&checksum.to_le_bytes());
+        [&[0u8][..], &rotate].concat()
+    }
+
+    // the handshake and OK of a passwordless login, then the checksum negotiation
+    pub(crate) fn accept_login(stream: &mut TcpStream, binlog_checksum: &str) {
+        write_packet(stream, 0, &handshake("mysql_native_password", &[1; 20]));
+        read_packet(stream);
+        write_packet(stream, 2, &[0, 0, 0, 2, 0, 0, 0]);
+        answer_checksum_queries(stream, binlog_checksum);
+    }
+
+    // answers the client's SELECT @@global.binlog_checksum and SET @master_binlog_checksum
+    pub(crate) fn answer_checksum_queries(stream: &mut TcpStream, binlog_checksum: &str) {
+        let select = read_packet(stream);
+        assert_eq!(&select[1..], b"SELECT @@global.binlog_checksum");
+        write_result_set(stream, &[&[Some(binlog_checksum)]]);
+        let set = read_packet(stream);
+        assert!(set[1..].starts_with(b"SET @master_binlog_checksum"));
+        write_packet(stream, 1, &[0, 0, 0, 2, 0, 0, 0]);
+    }
+
+    // an event packet without a checksum, as sent after binlog_checksum NONE
+    pub(crate) fn event_packet(type_code: u8, next_position: u32, body: &[u8]) -> Vec<u8> {
+        let mut packet = vec![0, 0, 0, 0, 0, type_code, 1, 0, 0, 0];
+        packet.extend_from_slice(&(19 + body.len() as u32).to_le_bytes());
+        packet.extend_from_slice(&next_position.to_le_bytes());
+        packet.extend_from_slice(&[0, 0]);
+        packet.extend_from_slice(body);
+        packet
+    }
+
+    // accepts `sessions` connections one after the other, handing each to `script` with its index
+    pub(crate) fn fake_server_sessions<F>(sessions: usize, mut script: F) -> (String, JoinHandle<()>) where
+        F: FnMut(usize, TcpStream) + Send + 'static
+    {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        let handle = std::thread::spawn(move || {
+            for session in 0..sessions {
+                let (stream, _) = listener.accept().unwrap();
+                script(session, stream);
+            }
+        });
+        (addr, handle)
+    }
+
+    // accepts one connection and hands it to `script`
+    pub(crate) fn fake_server<F>(script: F) -> (String, JoinHandle<()>) where
+        F: FnOnce(TcpStream) + Send + 'static
+    {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        let handle = std::thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            script(stream);
+        });
+        (addr, handle)
+    }
+
+    #[test]
+    fn test_stream_binlog_from_server() {
+        //given
+        let (addr, server) = fake_server(|mut stream| {
+            write_packet(&mut stream, 0, &handshake("mysql_native_password", &[1; 20]));
+            let response = read_packet(&mut stream);
+            assert!(response[32..].starts_with(b"repl\0"));
+            write_packet(&mut stream, 2, &[0, 0, 0, 2, 0, 0, 0]);
+            answer_checksum_queries(&mut stream, "CRC32");
+            let register = read_packet(&mut stream);
+            assert_eq!(register[0], 0x15);
+            assert_eq!(&register[1..5], &4242u32.to_le_bytes());
+            write_packet(&mut stream, 1, &[0, 0, 0, 2, 0, 0, 0]);
+            let dump = read_packet(&mut stream);
+            assert_eq!(dump[0], 0x12);
+            assert_eq!(&dump[11..], b"mysql-bin.100746");
+            for (seq, packet) in dump_packets().iter().enumerate() {
+                write_packet(&mut stream, seq as u8 + 1, packet);
+            }
+        });
+        let client = ReplicationClient::new(&addr)
+            .user("repl")
+            .server_id(4242)
+            .start_position(BinlogPosition::new("mysql-bin.100746", 4));
+
+        //when
+        let mut stream = client.connect().unwrap();
+        let events: Vec<_> = stream.by_ref().take(16).map(|e| e.unwrap()).collect();
+        let after = stream.next().unwrap();
+        server.join().unwrap();
+
+        //then
+        assert_eq!(events[0].type_code(), TypeCode::RotateEvent);
+        assert_eq!(events[1].type_code(), TypeCode::FormatDescriptionEvent);
+        assert_eq!(events[1].offset(), 4);
+        assert_eq!(events[6].offset(), 529);
+        assert_eq!(stream.checksum_algorithm(), ChecksumAlgorithm::Crc32);
+        assert_eq!(stream.position(), &BinlogPosition::new("mysql-bin.100747", 4));
+        assert!(after.is_err());
+        assert!(stream.next().is_none());
+    }
+
+    #[test]
+    fn test_stream_transactions() {
+        //given
+        let (addr, server) = fake_server(|mut stream| {
+            write_packet(&mut stream, 0, &handshake("mysql_native_password", &[1; 20]));
+            read_packet(&mut stream);
+            write_packet(&mut stream, 2, &[0, 0, 0, 2, 0, 0, 0]);
+            answer_checksum_queries(&mut stream, "CRC32");
+            read_packet(&mut stream);
+            write_packet(&mut stream, 1, &[0, 0, 0, 2, 0, 0, 0]);
+            read_packet(&mut stream);
+            for (seq, packet) in dump_packets().iter().enumerate() {
+                write_packet(&mut stream, seq as u8 + 1, packet);
+            }
+        });
+        let client = ReplicationClient::new(&addr).start_position(BinlogPosition::new("mysql-bin.100746", 4));
+
+        //when
+        let transactions: Vec<_> = client.connect().unwrap().transactions()
+            .take_while(|item| item.is_ok())
+            .filter_map(|item| match item.unwrap() {
+                TransactionItem::Transaction(t) => Some(t),
+                TransactionItem::Event(_) => None,
+            })
+            .collect();
+        server.join().unwrap();
+        let mut ctx = ParseContext::new();
+        let changes: Vec<_> = transactions.iter().map(|t| t.table_changes(&mut ctx).unwrap()).collect();
+
+        //then
+        assert_eq!(transactions.len(), 3);
+        assert!(transactions.iter().all(|t| t.gtid().unwrap().is_none() && t.timestamp() == 1633046400));
+        assert!(changes[0].is_empty());
+        assert_eq!(changes[1][0].changes[0].0, RowsEventKind::Write);
+        assert_eq!(changes[2][0].changes[0].0, RowsEventKind::Update);
+        assert!(transactions[2].xid().unwrap().is_some());
+    }
+
+    #[test]
+    fn test_current_position_falls_back_to_show_master_status() {
+        //given
+        let (addr, server) = fake_server_sessions(2, |session, mut stream| {
+            write_packet(&mut stream, 0, &handshake("mysql_native_password", &[1; 20]));
+            read_packet(&mut stream);
+            write_packet(&mut stream, 2, &[0, 0, 0, 2, 0, 0, 0]);
+            let query = read_packet(&mut stream);
+            assert_eq!(&query[1..], b"SHOW BINARY LOG STATUS");
+            if session == 0 {
+                write_result_set(&mut stream, &[&[Some("mysql-bin.000123"), Some("4567"), Some(""), Some("")]]);
+                return;
+            }
+            write_packet(&mut stream, 1, b"\xff\x28\x04#42000You have an error in your SQL syntax");
+            let query = read_packet(&mut stream);
+            assert_eq!(&query[1..], b"SHOW MASTER STATUS");
+            write_result_set(&mut stream, &[]);
+        });
+        let client = ReplicationClient::new(&addr);
+
+        //when
+        let current = client.current_position();
+        let disabled = client.current_position();
+        server.join().unwrap();
+
+        //then
+        assert_eq!(current.unwrap(), BinlogPosition::new("mysql-bin.000123", 4567));
+        assert!(matches!(disabled, Err(ClientError::BinlogDisabled)));
+    }
+
+    #[test]
+    fn test_check_resume_against_gtid_purged() {
+        //given
+        let (addr, server) = fake_server_sessions(2, |_, mut stream| {
+            write_packet(&mut stream, 0, &handshake("mysql_native_password", &[1; 20]));
+            read_packet(&mut stream);
+            write_packet(&mut stream, 2, &[0, 0, 0, 2, 0, 0, 0]);
+            let query = read_packet(&mut stream);
+            assert_eq!(&query[1..], b"SELECT @@GLOBAL.gtid_purged");
+            write_result_set(&mut stream, &[&[Some("3e11fa47-71ca-11e1-9e33-c80aa9429562:1-10")]]);
+        });
+        let client = ReplicationClient::new(&addr);
+
+        //when
+        let behind = client.check_resume(&"3e11fa47-71ca-11e1-9e33-c80aa9429562:1-7".parse().unwrap());
+        let caught_up = client.check_resume(&"3e11fa47-71ca-11e1-9e33-c80aa9429562:1-12".parse().unwrap());
+        server.join().unwrap();
+
+        //then
+        assert_eq!(behind.unwrap(), ResumeCheck::Missing("3e11fa47-71ca-11e1-9e33-c80aa9429562:8-10".parse().unwrap()));
+        assert!(caught_up.unwrap().is_safe());
+    }
+
+    #[test]
+    fn test_stream_binlog_by_gtid_set() {
+        //given
+        let sid = [0x3e; 16];
+        let mut executed = GtidSet::new();
+        executed.add_interval(sid, 1, 3);
+        let expected_gtid_data = executed.encode();
+        let (addr, server) = fake_server(move |mut stream| {
+            write_packet(&mut stream, 0, &handshake("mysql_native_password", &[1; 20]));
+            read_packet(&mut stream);
+            write_packet(&mut stream, 2, &[0, 0, 0, 2, 0, 0, 0]);
+            answer_checksum_queries(&mut stream, "NONE");
+            read_packet(&mut stream);
+            write_packet(&mut stream, 1, &[0, 0, 0, 2, 0, 0, 0]);
+            let dump = read_packet(&mut stream);
+            assert_eq!(dump[0], 0x1e);
+            assert_eq!(&dump[1..3], &4u16.to_le_bytes());
+            assert_eq!(&dump[7..11], &0u32.to_le_bytes());
+            assert_eq!(&dump[11..19], &4u64.to_le_bytes());
+            assert_eq!(&dump[23..], &expected_gtid_data[..]);
+            let mut xid = vec![0, 0, 0, 0, 0, 16, 1, 0, 0, 0, 27, 0, 0, 0, 0, 0, 0, 0, 0, 0];
+            xid.extend_from_slice(&9u64.to_le_bytes());
+            for gno in 3..=4i64 {
+                let mut gtid = vec![0, 0, 0, 0, 0, 33, 1, 0, 0, 0, 44, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1];
+                gtid.extend_from_slice(&sid);
+                gtid.extend_from_slice(&gno.to_le_bytes());
+                write_packet(&mut stream, 1, &gtid);
+                if gno == 3 {
+                    write_packet(&mut stream, 2, &xid);
+                }
+            }
+        });
+
+        //when
+        let mut stream = ReplicationClient::new(&addr).start_gtid_set(executed).connect().unwrap();
+        let first = stream.next().unwrap().unwrap();
+        let before_commit = stream.position().gtid_set.clone().unwrap();
+        stream.next().unwrap().unwrap();
+        stream.next().unwrap().unwrap();
+        server.join().unwrap();
+
+        //then
+        assert_eq!(first.type_code(), TypeCode::GtidLogEvent);
+        assert_eq!(before_commit.intervals(&sid), &[(1, 3)]);
+        // gno 4 has started but not committed yet
+        assert_eq!(stream.position().gtid_set.as_ref().unwrap().intervals(&sid), &[(1, 4)]);
+    }
+
+    #[test]
+    fn test_native_password_with_auth_switch() {
+        //given
+        let (addr, server) = fake_server(|mut stream| {
+            write_packet(&mut stream, 0, &handshake("mysql_native_password", &[1; 20]));
+            let response = read_packet(&mut stream);
+            // user, then the length-prefixed 20 byte scramble
+            assert_eq!(&response[32..37], b"repl\0");
+            assert_eq!(response[37], 20);
+            let mut switch = vec![0xfe];
+            switch.extend_from_slice(b"mysql_native_password\0");
+            switch.extend_from_slice(&(1..=20).collect::<Vec<u8>>());
+            switch.push(0);
+            write_packet(&mut stream, 2, &switch);
+            let scramble = read_packet(&mut stream);
+            assert_eq!(scramble[0], 0xb3);
+            assert_eq!(scramble.len(), 20);
+            write_packet(&mut stream, 4, &[0, 0, 0, 2, 0, 0, 0]);
+            read_packet(&mut stream);
+            let mut err = vec![0xff];
+            err.extend_from_slice(&1227u16.to_le_bytes());
+            err.extend_from_slice(b"#42000Access denied; you need the REPLICATION SLAVE privilege");
+            write_packet(&mut stream, 1, &err);
+        });
+
+        //when
+        let result = ReplicationClient::new(&addr).user("repl").password("secret").connect();
+        server.join().unwrap();
+
+        //then
+        assert!(matches!(result, Err(ClientError::Server { code: 1227, .. })));
+    }
+
+    #[test]
+    fn test_caching_sha2_full_auth_with_requested_key() {
+        //given
+        let key = RsaPrivateKey::new(&mut rand_core::OsRng, 1024).unwrap();
+        let pem = key.to_public_key().to_public_key_pem(LineEnding::LF).unwrap();
+        let (addr, server) = fake_server(move |mut stream| {
+            write_packet(&mut stream, 0, &handshake("caching_sha2_password", &[1; 20]));
+            let response = read_packet(&mut stream);
+            assert_eq!(response[37], 32);
+            write_packet(&mut stream, 2, &[0x01, 0x04]);
+            assert_eq!(read_packet(&mut stream), vec![0x02]);
+            write_packet(&mut stream, 4, &[&[0x01][..], pem.as_bytes()].concat());
+            let encrypted = read_packet(&mut stream);
+            let decrypted = key.decrypt(Oaep::new::<sha1::Sha1>(), &encrypted).unwrap();
+            let password: Vec<u8> = decrypted.iter().map(|b| b ^ 1).collect();
+            assert_eq!(password, b"secret\0");
+            write_packet(&mut stream, 6, &[0, 0, 0, 2, 0, 0, 0]);
+            read_packet(&mut stream);
+            write_packet(&mut stream, 1, &[0xff, 0xcb, 0x04, b'n', b'o']);
+        });
+
+        //when
+        let result = ReplicationClient::new(&addr).user("repl").password("secret").connect();
+        server.join().unwrap();
+
+        //then
+        assert!(matches!(result, Err(ClientError::Server { code: 1227, .. })));
+    }
+
+    #[test]
+    fn test_semi_sync_acks_requested_events() {
+        //given
+        let (addr, server) = fake_server(|mut stream| {
+            write_packet(&mut stream, 0, &handshake("mysql_native_password", &[1; 20]));
+            read_packet(&mut stream);
+            write_packet(&mut stream, 2, &[0, 0, 0, 2, 0, 0, 0]);
+            answer_checksum_queries(&mut stream, "CRC32");
+            assert!(read_packet(&mut stream)[1..].starts_with(b"SHOW VARIABLES"));
+            write_result_set(&mut stream, &[
+                &[Some("rpl_semi_sync_replica_enabled"), Some("OFF")],
+                &[Some("rpl_semi_sync_source_enabled"), Some("ON")],
+            ]);
+            assert!(read_packet(&mut stream)[1..].starts_with(b"SET @rpl_semi_sync_slave = 1"));
+            write_packet(&mut stream, 1, &[0, 0, 0, 2, 0, 0, 0]);
+            read_packet(&mut stream);
+            write_packet(&mut stream, 1, &[0, 0, 0, 2, 0, 0, 0]);
+            read_packet(&mut stream);
+            // ask for an ACK after the XID ending at 1017
+            for (seq, packet) in dump_packets().iter().enumerate() {
+                let flags = if seq == 14 { 0x01 } else { 0x00 };
+                write_packet(&mut stream, seq as u8 + 1, &[&[0x00, 0xef, flags][..], &packet[1..]].concat());
+            }
+            let ack = read_packet(&mut stream);
+            assert_eq!(ack[0], 0xef);
+            assert_eq!(&ack[1..9], &1017u64.to_le_bytes());
+            assert_eq!(&ack[9..], b"mysql-bin.100746");
+        });
+
+        //when
+        let mut stream = ReplicationClient::new(&addr)
+            .start_position(BinlogPosition::new("mysql-bin.100746", 4))
+            .semi_sync(true)
+            .connect()
+            .unwrap();
+        let events: Vec<_> = stream.by_ref().take(16).map(|e| e.unwrap()).collect();
+        server.join().unwrap();
+
+        //then
+        assert!(stream.is_semi_sync());
+        assert_eq!(events[14].type_code(), TypeCode::XidEvent);
+        assert_eq!(events[15].type_code(), TypeCode::RotateEvent);
+    }
+
+    #[test]
+    fn test_heartbeats_are_counted_and_skipped() {
+        //given
+        let (addr, server) = fake_server(|mut stream| {
+            write_packet(&mut stream, 0, &handshake("mysql_native_password", &[1; 20]));
+            read_packet(&mut stream);
+            write_packet(&mut stream, 2, &[0, 0, 0, 2, 0, 0, 0]);
+            answer_checksum_queries(&mut stream, "NONE");
+            assert_eq!(&read_packet(&mut stream)[1..], b"SET @master_heartbeat_period = 1500000000");
+            write_packet(&mut stream, 1, &[0, 0, 0, 2, 0, 0, 0]);
+            read_packet(&mut stream);
+            write_packet(&mut stream, 1, &[0, 0, 0, 2, 0, 0, 0]);
+            read_packet(&mut stream);
+            write_packet(&mut stream, 1, &event_packet(27, 1017, b"mysql-bin.100746"));
+            write_packet(&mut stream, 2, &event_packet(16, 1044, &9u64.to_le_bytes()));
+        });
+        let client = ReplicationClient::new(&addr)
+            .start_position(BinlogPosition::new("mysql-bin.100746", 900))
+            .heartbeat_period(Duration::from_millis(1500))
+            .skip_heartbeats(true);
+
+        //when
+        let mut stream = client.connect().unwrap();
+        let before = Instant::now();
+        let event = stream.next().unwrap().unwrap();
+        server.join().unwrap();
+
+        //then
+        assert_eq!(event.type_code(), TypeCode::XidEvent);
+        assert_eq!(event.offset(), 1017);
+        assert_eq!(stream.position().pos, 1044);
+        assert!(stream.last_received().unwrap() >= before);
+    }
+
+    #[test]
+    fn test_reconnect_resumes_at_transaction_start_without_duplicates() {
+        //given
+        let expected: Vec<_> = dump_packets().iter().map(|p| p[14..18].to_vec()).collect();
+        let (addr, server) = fake_server_sessions(2, |session, mut stream| {
+            accept_login(&mut stream, "CRC32");
+            read_packet(&mut stream);
+            write_packet(&mut stream, 1, &[0, 0, 0, 2, 0, 0, 0]);
+            let dump = read_packet(&mut stream);
+            let packets = dump_packets();
+            if session == 0 {
+                // drop the connection in the middle of the transaction starting at 464
+                for packet in &packets[..8] {
+                    write_packet(&mut stream, 1, packet);
+                }
+            } else {
+                assert_eq!(&dump[1..5], &464u32.to_le_bytes());
+                write_packet(&mut stream, 1, &artificial_rotate("mysql-bin.100746", 464));
+                write_packet(&mut stream, 2, &packets[1]);
+                for packet in &packets[5..] {
+                    write_packet(&mut stream, 3, packet);
+                }
+            }
+        });
+
+        //when
+        let mut stream = ReplicationClient::new(&addr)
+            .start_position(BinlogPosition::new("mysql-bin.100746", 4))
+            .reconnecting();
+        let events: Vec<_> = stream.by_ref().take(16).map(|e| e.unwrap()).collect();
+        server.join().unwrap();
+
+        //then
+        let next_positions: Vec<_> = events.iter().map(|e| (e.next_position() as u32).to_le_bytes().to_vec()).collect();
+        assert_eq!(next_positions, expected);
+        assert_eq!(stream.resume_position(), &BinlogPosition::new("mysql-bin.100747", 4));
+    }
+
+    #[test]
+    fn test_register_with_replica_identity() {
+        //given
+        let (addr, server) = fake_server(|mut stream| {
+            accept_login(&mut stream, "NONE");
+            let set = read_packet(&mut stream);
+            assert_eq!(&set[1..], &b"SET @slave_uuid = '3e11fa47-71ca-11e1-9e33-c80aa9429562', @replica_uuid = '3e11fa47-71ca-11e1-9e33-c80aa9429562'"[..]);
+            write_packet(&mut stream, 1, &[0, 0, 0, 2, 0, 0, 0]);
+            let register = read_packet(&mut stream);
+            write_packet(&mut stream, 1, &[0xff, 0xcb, 0x04, b'n', b'o']);
+
+            let mut expected = vec![0x15];
+            expected.extend_from_slice(&77u32.to_le_bytes());
+            expected.push(8);
+            expected.extend_from_slice(b"cdc-1.db");
+            expected.extend_from_slice(&[0, 0]);
+            expected.extend_from_slice(&3307u16.to_le_bytes());
+            expected.extend_from_slice(&[0; 8]);
+            assert_eq!(register, expected);
+        });
+
+        //when
+        let result = ReplicationClient::new(&addr)
+            .server_id(77)
+            .report_host("cdc-1.db", 3307)
+            .replica_uuid("3E11FA47-71CA-11E1-9E33-C80AA9429562")
+            .connect();
+        server.join().unwrap();
+
+        //then
+        assert!(matches!(result, Err(ClientError::Server { code: 1227, .. })));
+    }
+
+    #[test]
+    fn test_stream_from_mariadb_by_gtid() {
+        //given
+        let state: MariaDbGtidList = "0-1-100,1-2-5".parse().unwrap();
+        let (addr, server) = fake_server(|mut stream| {
+            write_packet(&mut stream, 0, &server_handshake("5.5.5-10.6.12-MariaDB-log", "mysql_native_password", &[1; 20]));
+            read_packet(&mut stream);
+            write_packet(&mut stream, 2, &[0, 0, 0, 2, 0, 0, 0]);
+            answer_checksum_queries(&mut stream, "NONE");
+            let mut statements = vec![];
+            for _ in 0..5 {
+                let packet = read_packet(&mut stream);
+                if packet[0] == 0x03 {
+                    statements.push(String::from_utf8(packet[1..].to_vec()).unwrap());
+                }
+                write_packet(&mut stream, 1, &[0, 0, 0, 2, 0, 0, 0]);
+            }
+            let dump = read_packet(&mut stream);
+            assert_eq!(statements, vec![
+                "SET @mariadb_slave_capability = 4",
+                "SET @slave_connect_state = '0-1-100,1-2-5'",
+                "SET @slave_gtid_strict_mode = 0",
+                "SET @slave_gtid_ignore_duplicates = 0",
+            ]);
+            assert_eq!(dump[0], 0x12);
+            assert_eq!(&dump[1..5], &4u32.to_le_bytes());
+            assert_eq!(dump.len(), 11);
+
+            let mut gtid = 101u64.to_le_bytes().to_vec();
+            gtid.extend_from_slice(&[0, 0, 0, 0, 0]);
+            write_packet(&mut stream, 1, &event_packet(162, 300, &gtid));
+        });
+
+        //when
+        let mut stream = ReplicationClient::new(&addr).start_mariadb_gtid(state).connect().unwrap();
+        let event = stream.next().unwrap().unwrap();
+        server.join().unwrap();
+
+        //then
+        assert!(stream.is_mariadb());
+        assert_eq!(event.type_code(), TypeCode::MariaDbGtidEvent);
+        assert_eq!(stream.position().pos, 300);
+    }
+
+    #[test]
+    fn test_mariadb_gtid_start_refused_by_mysql() {
+        //given
+        let (addr, server) = fake_server(|mut stream| {
+            write_packet(&mut stream, 0, &handshake("mysql_native_password", &[1; 20]));
+            read_packet(&mut stream);
+            write_packet(&mut stream, 2, &[0, 0, 0, 2, 0, 0, 0]);
+        });
+
+        //when
+        let result = ReplicationClient::new(&addr).start_mariadb_gtid("0-1-100".parse().unwrap()).connect();
+        server.join().unwrap();
+
+        //then
+        assert!(matches!(result, Err(ClientError::MariaDbGtidOnMysql(ref state)) if state == "0-1-100"));
+    }
+
+    #[test]
+    fn test_servers_without_binlog_checksum_stream_unchecksummed_events() {
+        //given
+        let (addr, server) = fake_server(|mut stream| {
+            write_packet(&mut stream, 0, &handshake("mysql_native_password", &[1; 20]));
+            read_packet(&mut stream);
+            write_packet(&mut stream, 2, &[0, 0, 0, 2, 0, 0, 0]);
+            // MySQL before 5.6.2 doesn't have the variable, and isn't asked to SET it
+            let select = read_packet(&mut stream);
+            assert_eq!(&select[1..], b"SELECT @@global.binlog_checksum");
+            let mut err = vec![0xff];
+            err.extend_from_slice(&1193u16.to_le_bytes());
+            err.extend_from_slice(b"#HY000Unknown system variable 'binlog_checksum'");
+            write_packet(&mut stream, 1, &err);
+            let register = read_packet(&mut stream);
+            assert_eq!(register[0], 0x15);
+            write_packet(&mut stream, 1, &[0, 0, 0, 2, 0, 0, 0]);
+            read_packet(&mut stream);
+            let rotate = [&4u64.to_le_bytes()[..], b"mysql-bin.000001"].concat();
+            write_packet(&mut stream, 1, &event_packet(4, 0, &rotate));
+            write_packet(&mut stream, 2, &[0xfe, 0, 0, 2, 0]);
+        });
+
+        //when
+        let mut stream = ReplicationClient::new(&addr).connect().unwrap();
+        let events: Vec<_> = stream.by_ref().map(|e| e.unwrap()).collect();
+        server.join().unwrap();
+
+        //then
+        assert_eq!(stream.checksum_algorithm(), ChecksumAlgorithm::Off);
+        assert_eq!(events.len(), 1);
+        assert_eq!(&events[0].data()[8..], b"mysql-bin.000001");
+        assert_eq!(stream.position(), &BinlogPosition::new("mysql-bin.000001", 4));
+    }
+
+    #[test]
+    fn test_unknown_binlog_checksum_is_refused() {
+        //given
+        let (addr, server) = fake_server(|mut stream| {
+            write_packet(&mut stream, 0, &handshake("mysql_native_password", &[1; 20]));
+            read_packet(&mut stream);
+            write_packet(&mut stream, 2, &[0, 0, 0, 2, 0, 0, 0]);
+            read_packet(&mut stream);
+            write_result_set(&mut stream, &[&[Some("XXHASH64")]]);
+        });
+
+        //when
+        let result = ReplicationClient::new(&addr).connect();
+        server.join().unwrap();
+
+        //then
+        assert!(matches!(result, Err(ClientError::UnsupportedChecksum(ref name)) if name == "XXHASH64"));
+    }
+
+    #[test]
+    fn test_server_err_and_eof_end_the_stream() {
+        //given
+        let (addr, server) = fake_server_sessions(2, |session, mut stream| {
+            accept_login(&mut stream, "CRC32");
+            read_packet(&mut stream);
+            write_packet(&mut stream, 1, &[0, 0, 0, 2, 0, 0, 0]);
+            read_packet(&mut stream);
+            write_packet(&mut stream, 1, &dump_packets()[0]);
+            if session == 0 {
+                let mut err = vec![0xff];
+                err.extend_from_slice(&1236u16.to_le_bytes());
+                err.extend_from_slice(b"#HY000Could not find first log file name in binary log index file");
+                write_packet(&mut stream, 2, &err);
+            } else {
+                write_packet(&mut stream, 2, &[0xfe, 0, 0, 2, 0]);
+            }
+        });
+        let client = ReplicationClient::new(&addr);
+
+        //when
+        let failed: Vec<_> = client.connect().unwrap().collect();
+        let ended: Vec<_> = client.connect().unwrap().collect();
+        server.join().unwrap();
+
+        //then
+        assert_eq!(failed.len(), 2);
+        assert!(matches!(
+            &failed[1],
+            Err(BinlogFileError::Client(ClientError::Server { code: 1236, message, .. })) if message.starts_with("Could not find")
+        ));
+        assert_eq!(ended.len(), 1);
+        assert!(ended[0].is_ok());
+    }
+
+    #[test]
+    fn test_non_blocking_dump_ends_at_eof() {
+        //given
+        let (addr, server) = fake_server(|mut stream| {
+            accept_login(&mut stream, "CRC32");
+            read_packet(&mut stream);
+            write_packet(&mut stream, 1, &[0, 0, 0, 2, 0, 0, 0]);
+            let dump = read_packet(&mut stream);
+            assert_eq!(&dump[5..7], &1u16.to_le_bytes());
+            let packets = dump_packets();
+            for (seq, packet) in packets.iter().enumerate() {
+                write_packet(&mut stream, seq as u8 + 1, packet);
+            }
+            write_packet(&mut stream, packets.len() as u8 + 1, &[0xfe, 0, 0, 2, 0]);
+        });
+
+        //when
+        let events: Vec<_> = ReplicationClient::new(&addr).non_blocking(true).reconnecting().collect();
+        server.join().unwrap();
+
+        //then
+        assert_eq!(events.len(), dump_packets().len());
+        assert!(events.iter().all(|e| e.is_ok()));
+    }
+
+    #[test]
+    fn test_failover_resumes_by_gtid_on_another_host() {
+        //given
+        let sid = [0x3e; 16];
+        let gtid = move |gno: i64, next_position: u32| {
+            let mut body = vec![1];
+            body.extend_from_slice(&sid);
+            body.extend_from_slice(&gno.to_le_bytes());
+            event_packet(33, next_position, &body)
+        };
+        let query = |sql: &str, next_position: u32| event_packet(2, next_position, &[&[0; 14][..], sql.as_bytes()].concat());
+        let xid = |next_position: u32| event_packet(16, next_position, &9u64.to_le_bytes());
+        let mut rotate = 4u64.to_le_bytes().to_vec();
+        rotate.extend_from_slice(b"binlog.000009");
+        let rotate = event_packet(4, 0, &rotate);
+        let (primary, primary_server) = fake_server(move |mut stream| {
+            accept_login(&mut stream, "NONE");
+            read_packet(&mut stream);
+            write_packet(&mut stream, 1, &[0, 0, 0, 2, 0, 0, 0]);
+            read_packet(&mut stream);
+            // the primary goes away in the middle of gno 2
+            for packet in [gtid(1, 200), query("BEGIN", 300), xid(400), gtid(2, 500), query("BEGIN", 600)].iter() {
+                write_packet(&mut stream, 1, packet);
+            }
+        });
+        let (replica, replica_server) = fake_server(move |mut stream| {
+            accept_login(&mut stream, "NONE");
+            read_packet(&mut stream);
+            write_packet(&mut stream, 1, &[0, 0, 0, 2, 0, 0, 0]);
+            let dump = read_packet(&mut stream);
+            let mut executed = GtidSet::new();
+            executed.add(sid, 1);
+            assert_eq!(dump[0], 0x1e);
+            assert_eq!(&dump[7..11], &0u32.to_le_bytes());
+            assert_eq!(&dump[23..], &executed.encode()[..]);
+            for packet in [rotate, gtid(2, 1200), query("BEGIN", 1300), xid(1400)].iter() {
+                write_packet(&mut stream, 1, packet);
+            }
+        });
+
+        //when
+        let mut stream = ReplicationClient::new(&primary)
+            .start_gtid_set(GtidSet::new())
+            .failover_hosts(&[&replica])
+            .reconnecting();
+        let events: Vec<_> = stream.by_ref().take(7).map(|e| e.unwrap()).collect();
+        primary_server.join().unwrap();
+        replica_server.join().unwrap();
+
+        //then
+        let types: Vec<_> = events.iter().map(|e| e.type_code()).collect();
+        assert_eq!(types, vec![
+            TypeCode::GtidLogEvent,
+            TypeCode::QueryEvent,
+            TypeCode::XidEvent,
+            TypeCode::GtidLogEvent,
+            TypeCode::QueryEvent,
+            TypeCode::RotateEvent,
+            TypeCode::XidEvent,
+        ]);
+        assert_eq!(events[6].next_position(), 1400);
+        assert_eq!(stream.current_host(), replica);
+        assert_eq!(stream.resume_position().gtid_set.as_ref().unwrap().intervals(&sid), &[(1, 3)]);
+    }
+
+    #[test]
+    fn test_retry_policy_delays() {
+        //given
+        let policy = RetryPolicy::new()
+            .initial_backoff(Duration::from_millis(200))
+            .max_backoff(Duration::from_secs(1))
+            .multiplier(3.0);
+        let jittered = policy.clone().jitter(0.5);
+
+        //when
+        let delays: Vec<_> = (0..4).map(|attempt| policy.delay(attempt)).collect();
+        let jittered: Vec<_> = (0..100).map(|_| jittered.delay(1)).collect();
+
+        //then
+        assert_eq!(delays[0], Duration::from_millis(200));
+        assert_eq!(delays[1].as_millis(), 600);
+        assert_eq!(delays[2..], [Duration::from_secs(1), Duration::from_secs(1)]);
+        assert!(jittered.iter().all(|d| d.as_millis() >= 300 && d.as_millis() <= 600));
+        assert!(jittered.iter().any(|d| *d != jittered[0]));
+    }
+
+    #[test]
+    fn test_retry_policy_decides_what_is_retried() {
+        //given
+        let (addr, server) = fake_server_sessions(2, |_, mut stream| {
+            write_packet(&mut stream, 0, &[0xff, 0x10, 0x04, b'n', b'o']);
+        });
+        let policy = RetryPolicy::new()
+            .initial_backoff(Duration::from_millis(1))
+            .retry_if(|e| matches!(e, ClientError::Server { code: 1040, .. }))
+            .max_attempts(1);
+
+        //when
+        let started = Instant::now();
+        let result = ReplicationClient::new(&addr).retry_policy(policy).reconnecting().next();
+        server.join().unwrap();
+
+        //then
+        assert!(matches!(result, Some(Err(BinlogFileError::Client(ClientError::Server { code: 1040, .. })))));
+        assert!(started.elapsed() < Duration::from_secs(5));
+    }
+}
@@ -0,0 +1,208 @@
+use std::io::Read;
+use byteorder::{LittleEndian, ReadBytesExt};
+use serde_derive::{Deserialize, Serialize};
+
+// https://dev.mysql.com/doc/internals/en/com-query-response.html#column-type
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum ColumnType {
+    Decimal,
+    Tiny,
+    Short,
+    Long,
+    Float,
+    Double,
+    Null,
+    Timestamp,
+    LongLong,
+    Int24,
+    Date,
+    Time,
+    DateTime,
+    Year,
+    NewDate,
+    VarChar,
+    Bit,
+    Timestamp2,
+    DateTime2,
+    Time2,
+    Json,
+    NewDecimal,
+    Enum,
+    Set,
+    TinyBlob,
+    MediumBlob,
+    LongBlob,
+    Blob,
+    VarString,
+    String,
+    Geometry,
+    Unknown(u8),
+}
+
+impl ColumnType {
+    pub fn from_byte(b: u8) -> Self {
+        match b {
+            0 => ColumnType::Decimal,
+            1 => ColumnType::Tiny,
+            2 => ColumnType::Short,
+            3 => ColumnType::Long,
+            4 => ColumnType::Float,
+            5 => ColumnType::Double,
+            6 => ColumnType::Null,
+            7 => ColumnType::Timestamp,
+            8 => ColumnType::LongLong,
+            9 => ColumnType::Int24,
+            10 => ColumnType::Date,
+            11 => ColumnType::Time,
+            12 => ColumnType::DateTime,
+            13 => ColumnType::Year,
+            14 => ColumnType::NewDate,
+            15 => ColumnType::VarChar,
+            16 => ColumnType::Bit,
+            17 => ColumnType::Timestamp2,
+            18 => ColumnType::DateTime2,
+            19 => ColumnType::Time2,
+            245 => ColumnType::Json,
+            246 => ColumnType::NewDecimal,
+            247 => ColumnType::Enum,
+            248 => ColumnType::Set,
+            249 => ColumnType::TinyBlob,
+            250 => ColumnType::MediumBlob,
+            251 => ColumnType::LongBlob,
+            252 => ColumnType::Blob,
+            253 => ColumnType::VarString,
+            254 => ColumnType::String,
+            255 => ColumnType::Geometry,
+            b => ColumnType::Unknown(b),
+        }
+    }
+
+    pub fn to_byte(self) -> u8 {
+        match self {
+            ColumnType::Decimal => 0,
+            ColumnType::Tiny => 1,
+            ColumnType::Short => 2,
+            ColumnType::Long => 3,
+            ColumnType::Float => 4,
+            ColumnType::Double => 5,
+            ColumnType::Null => 6,
+            ColumnType::Timestamp => 7,
+            ColumnType::LongLong => 8,
+            ColumnType::Int24 => 9,
+            ColumnType::Date => 10,
+            ColumnType::Time => 11,
+            ColumnType::DateTime => 12,
+            ColumnType::Year => 13,
+            ColumnType::NewDate => 14,
+            ColumnType::VarChar => 15,
+            ColumnType::Bit => 16,
+            ColumnType::Timestamp2 => 17,
+            ColumnType::DateTime2 => 18,
+            ColumnType::Time2 => 19,
+            ColumnType::Json => 245,
+            ColumnType::NewDecimal => 246,
+            ColumnType::Enum => 247,
+            ColumnType::Set => 248,
+            ColumnType::TinyBlob => 249,
+            ColumnType::MediumBlob => 250,
+            ColumnType::LongBlob => 251,
+            ColumnType::Blob => 252,
+            ColumnType::VarString => 253,
+            ColumnType::String => 254,
+            ColumnType::Geometry => 255,
+            ColumnType::Unknown(b) => b,
+        }
+    }
+
+    // number of metadata bytes this type carries in a TableMapEvent
+    // https://dev.mysql.com/doc/internals/en/table-map-event.html
+    pub fn metadata_len(self) -> usize {
+        match self {
+            ColumnType::Float | ColumnType::Double | ColumnType::Blob | ColumnType::TinyBlob
+            | ColumnType::MediumBlob | ColumnType::LongBlob | ColumnType::Geometry | ColumnType::Json
+            | ColumnType::Timestamp2 | ColumnType::DateTime2 | ColumnType::Time2 => 1,
+            ColumnType::VarChar | ColumnType::VarString | ColumnType::Bit | ColumnType::NewDecimal
+            | ColumnType::String | ColumnType::Enum | ColumnType::Set => 2,
+            _ => 0,
+        }
+    }
+
+    // Reads the type specific metadata into a single u16. Two byte metadata keeps the byte order
+    // the server wrote: little endian for VARCHAR lengths, (first byte << 8 | second byte) otherwise.
+    pub fn read_metadata<R: Read>(self, reader: &mut R) -> std::io::Result<u16> {
+        match self {
+            ColumnType::VarChar | ColumnType::VarString => reader.read_u16::<LittleEndian>(),
+            _ => match self.metadata_len() {
+                1 => Ok(u16::from(reader.read_u8()?)),
+                2 => {
+                    let b0 = reader.read_u8()?;
+                    let b1 = reader.read_u8()?;
+                    Ok(u16::from(b0) << 8 | u16::from(b1))
+                }
+                _ => Ok(0),
+            },
+        }
+    }
+
+    // the inverse of read_metadata
+    pub fn write_metadata(self, meta: u16, out: &mut Vec<u8>) {
+        match self {
+            ColumnType::VarChar | ColumnType::VarString => out.extend_from_slice(&meta.to_le_bytes()),
+            _ => match self.metadata_len() {
+                1 => out.push(meta as u8),
+                2 => out.extend_from_slice(&meta.to_be_bytes()),
+                _ => {}
+            },
+        }
+    }
+
+    pub fn is_numeric(self) -> bool {
+        matches!(
+            self,
+            ColumnType::Tiny | ColumnType::Short | ColumnType::Int24 | ColumnType::Long | ColumnType::LongLong
+                | ColumnType::NewDecimal | ColumnType::Decimal | ColumnType::Float | ColumnType::Double
+        )
+    }
+
+    pub fn is_blob(self) -> bool {
+        matches!(
+            self,
+            ColumnType::Blob | ColumnType::TinyBlob | ColumnType::MediumBlob | ColumnType::LongBlob
+        )
+    }
+}
+
+// STRING columns hide ENUM/SET and lengths above 255 inside their two metadata bytes
+// https://github.com/mysql/mysql-server/blob/5.7/sql/log_event.cc (log_event_print_value)
+pub fn real_string_type(meta: u16) -> (ColumnType, u16) {
+    let byte0 = meta >> 8;
+    let byte1 = meta & 0xff;
+    if byte0 != 0 {
+        if (byte0 & 0x30) != 0x30 {
+            let length = byte1 | (((byte0 & 0x30) ^ 0x30) << 4);
+            return (ColumnType::from_byte((byte0 | 0x30) as u8), length);
+        }
+        return (ColumnType::from_byte(byte0 as u8), byte1);
+    }
+    (ColumnType::String, byte1)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::column::{real_string_type, ColumnType};
+
+    #[test]
+    fn test_real_string_type() {
+        //given
+        let char_255x4 = 0xce_fc;
+        let enum_2 = (247 << 8) | 2;
+
+        //when
+        let (char_type, char_len) = real_string_type(char_255x4);
+        let (enum_type, enum_len) = real_string_type(enum_2);
+
+        //then
+        assert_eq!((char_type, char_len), (ColumnType::String, 1020));
+        assert_eq!((enum_type, enum_len), (ColumnType::Enum, 2));
+    }
+}
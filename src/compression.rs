@@ -0,0 +1,111 @@
+use std::fs::File;
+use std::io::{BufRead, BufReader, Read};
+use std::path::Path;
+use flate2::read::MultiGzDecoder;
+use crate::errors::BinlogFileError;
+
+// How an archived binlog is compressed, from its magic bytes or failing that its extension.
+// zstd and xz archives need the `zstd` and `xz` features to be read.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Compression {
+    None,
+    Gzip,
+    Zstd,
+    Xz,
+}
+
+impl Compression {
+    pub fn detect(path: &Path, head: &[u8]) -> Self {
+        if head.starts_with(&[0x1f, 0x8b]) {
+            return Compression::Gzip;
+        }
+        if head.starts_with(&[0x28, 0xb5, 0x2f, 0xfd]) {
+            return Compression::Zstd;
+        }
+        if head.starts_with(&[0xfd, b'7', b'z', b'X', b'Z', 0x00]) {
+            return Compression::Xz;
+        }
+        match path.extension().and_then(|e| e.to_str()) {
+            Some("gz") => Compression::Gzip,
+            Some("zst") => Compression::Zstd,
+            Some("xz") => Compression::Xz,
+            _ => Compression::None,
+        }
+    }
+}
+
+// the contents of `path`, decompressed on the fly
+pub(crate) fn open(path: &Path) -> Result<(Compression, Box<dyn Read + Send>), BinlogFileError> {
+    let file = File::open(path).map_err(BinlogFileError::OpenError)?;
+    let mut reader = BufReader::new(file);
+    let compression = Compression::detect(path, reader.fill_buf()?);
+    let reader: Box<dyn Read + Send> = match compression {
+        Compression::None => Box::new(reader),
+        // `gzip -c a b > ab.gz` and rotated archives can hold several members
+        Compression::Gzip => Box::new(MultiGzDecoder::new(reader)),
+        #[cfg(feature = "zstd")]
+        Compression::Zstd => Box::new(zstd::stream::read::Decoder::with_buffer(reader)?),
+        #[cfg(feature = "xz")]
+        Compression::Xz => Box::new(xz2::bufread::XzDecoder::new_multi_decoder(reader)),
+        #[allow(unreachable_patterns)]
+        compression => return Err(BinlogFileError::CompressionNotEnabled(compression)),
+    };
+    Ok((compression, reader))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+    use crate::compression::Compression;
+
+    #[test]
+    fn test_detect_compression() {
+        //given
+        let head = [0xfe, 0x62, 0x69, 0x6e];
+
+        //when
+        let plain = Compression::detect(Path::new("mysql-bin.000001"), &head);
+        let by_extension = Compression::detect(Path::new("mysql-bin.000001.zst"), &[]);
+        let by_magic = Compression::detect(Path::new("archive"), &[0xfd, b'7', b'z', b'X', b'Z', 0x00]);
+
+        //then
+        assert_eq!(plain, Compression::None);
+        assert_eq!(by_extension, Compression::Zstd);
+        assert_eq!(by_magic, Compression::Xz);
+    }
+
+    #[cfg(feature = "zstd")]
+    #[test]
+    fn test_read_zstd_binlog() {
+        //given
+        let bytes = std::fs::read("tests/asset/mysql-bin.100746").unwrap();
+        let path = std::env::temp_dir().join(format!("binlog-compression-{}.zst", std::process::id()));
+        std::fs::write(&path, zstd::encode_all(&bytes[..], 3).unwrap()).unwrap();
+
+        //when
+        let events: Vec<_> = crate::binlog_reader::BinlogReader::from_path(&path).unwrap().map(|e| e.unwrap()).collect();
+        std::fs::remove_file(&path).unwrap();
+
+        //then
+        assert_eq!(events.len(), 15);
+    }
+
+    #[cfg(feature = "xz")]
+    #[test]
+    fn test_read_xz_binlog() {
+        //given
+        use std::io::Write;
+        let bytes = std::fs::read("tests/asset/mysql-bin.100746").unwrap();
+        let path = std::env::temp_dir().join(format!("binlog-compression-{}.xz", std::process::id()));
+        let mut encoder = xz2::write::XzEncoder::new(std::fs::File::create(&path).unwrap(), 6);
+        encoder.write_all(&bytes).unwrap();
+        encoder.finish().unwrap();
+
+        //when
+        let events: Vec<_> = crate::binlog_reader::BinlogReader::from_path(&path).unwrap().map(|e| e.unwrap()).collect();
+        std::fs::remove_file(&path).unwrap();
+
+        //then
+        assert_eq!(events.len(), 15);
+    }
+}
@@ -0,0 +1,94 @@
+use std::collections::HashMap;
+use crate::errors::EventParseError;
+use crate::event::{EventData, TypeCode};
+use crate::options::ParseOptions;
+use crate::rows::RowsEvent;
+use crate::schema::SchemaTracker;
+use crate::table_map::TableMapEvent;
+
+// State carried between events of one binlog: the table maps rows events refer to by id, the
+// post-header lengths from the FormatDescriptionEvent and, optionally, a schema tracker that
+// fills in column names for binlog_row_metadata=MINIMAL.
+#[derive(Debug, Clone, Default)]
+pub struct ParseContext {
+    options: ParseOptions,
+    table_maps: HashMap<u64, TableMapEvent>,
+    post_header_lengths: Vec<u8>,
+    schema: Option<SchemaTracker>,
+}
+
+impl ParseContext {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_options(options: ParseOptions) -> Self {
+        ParseContext {
+            options,
+            ..Self::default()
+        }
+    }
+
+    pub fn track_schema(mut self, tracker: SchemaTracker) -> Self {
+        self.schema = Some(tracker);
+        self
+    }
+
+    pub fn options(&self) -> &ParseOptions {
+        &self.options
+    }
+
+    pub fn schema(&self) -> Option<&SchemaTracker> {
+        self.schema.as_ref()
+    }
+
+    pub fn table_map(&self, table_id: u64) -> Option<&TableMapEvent> {
+        self.table_maps.get(&table_id)
+    }
+
+    // the post-header length the last FormatDescriptionEvent gave events of `type_code`
+    pub fn post_header_len(&self, type_code: TypeCode) -> Option<u8> {
+        let index = usize::from(type_code.to_byte()).checked_sub(1)?;
+        self.post_header_lengths.get(index).copied()
+    }
+
+    // https://dev.mysql.com/doc/dev/mysql-server/latest/classbinary__log_1_1Table__map__event.html
+    // servers before 5.1.4 wrote 4 byte table ids, which shows as a 6 byte post-header
+    pub(crate) fn table_id_len(&self, type_code: TypeCode) -> usize {
+        match self.post_header_len(type_code) {
+            Some(6) => 4,
+            _ => 6,
+        }
+    }
+
+    pub fn add_table_map(&mut self, mut table_map: TableMapEvent) {
+        if let Some(schema) = &self.schema {
+            table_map.resolve_with(schema);
+        }
+        self.table_maps.insert(table_map.table_id, table_map);
+    }
+
+    pub(crate) fn parse_rows(&self, type_code: TypeCode, table_id: u64, data: &[u8]) -> Result<RowsEvent, EventParseError> {
+        let table_map = self.table_map(table_id).ok_or(EventParseError::UnknownTableId(table_id))?;
+        RowsEvent::parse_with_table_id_len(type_code, data, table_map, &self.options, self.table_id_len(type_code))
+    }
+
+    pub(crate) fn observe(&mut self, event_data: &EventData) {
+        match event_data {
+            EventData::TableMapEvent(table_map) => self.add_table_map(table_map.clone()),
+            EventData::QueryEvent { schema, query, .. } => {
+                if let Some(tracker) = &mut self.schema {
+                    // DDL the tracker can't follow shouldn't stop the stream from being read
+                    let _ = tracker.apply_query(schema, query);
+                }
+            }
+            EventData::FormatDescriptionEvent { post_header_lengths, .. } => {
+                self.post_header_lengths = post_header_lengths.clone();
+                self.table_maps.clear();
+            }
+            // table ids are only stable within one binlog file
+            EventData::RotateEvent { .. } => self.table_maps.clear(),
+            _ => {}
+        }
+    }
+}
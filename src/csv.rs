@@ -0,0 +1,285 @@
+use std::collections::HashMap;
+use std::io::Write;
+use serde_json::Value as Json;
+use crate::errors::ExportError;
+use crate::event::{Event, EventData};
+use crate::export::plain_json;
+use crate::handler::EventHandler;
+use crate::rows::{RowsEvent, RowsEventKind};
+use crate::table_map::{TableMapColumn, TableMapEvent};
+use crate::utils::civil_from_days;
+use crate::value::Value;
+
+// When CsvWriter puts a field in double quotes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Quoting {
+    // fields holding the delimiter, a quote or a line break, and non-NULL values that read as the
+    // null representation (an empty string when NULL is written as nothing)
+    #[default]
+    Necessary,
+    Always,
+    // for TSV-style loads; fields are written as they are
+    Never,
+}
+
+// Writes the rows of one table as CSV, with a header of its column names ("@1", "@2"... when the
+// table map has none). Inserted and updated rows are written as their after image and deleted
+// rows as their before image; columns left out of a MINIMAL image are written as NULL.
+//
+//     let mut writer = CsvWriter::new(std::io::stdout().lock(), "shop", "orders")
+//         .delimiter(b'\t')
+//         .null("\\N");
+//     handler::run(BinlogFile::from_path(path)?.events(), &mut ParseContext::new(), &mut writer)?;
+pub struct CsvWriter<W: Write> {
+    writer: W,
+    schema: String,
+    table: String,
+    delimiter: u8,
+    null: String,
+    quoting: Quoting,
+    header: bool,
+    operation: bool,
+    tables: HashMap<u64, TableMapEvent>,
+    header_written: bool,
+}
+
+impl<W: Write> CsvWriter<W> {
+    pub fn new(writer: W, schema: &str, table: &str) -> Self {
+        CsvWriter {
+            writer,
+            schema: schema.to_owned(),
+            table: table.to_owned(),
+            delimiter: b',',
+            null: String::new(),
+            quoting: Quoting::default(),
+            header: true,
+            operation: false,
+            tables: HashMap::new(),
+            header_written: false,
+        }
+    }
+
+    pub fn delimiter(mut self, delimiter: u8) -> Self {
+        self.delimiter = delimiter;
+        self
+    }
+
+    // how NULL is written, e.g. \N for LOAD DATA and COPY; empty by default
+    pub fn null(mut self, null: &str) -> Self {
+        self.null = null.to_owned();
+        self
+    }
+
+    pub fn quoting(mut self, quoting: Quoting) -> Self {
+        self.quoting = quoting;
+        self
+    }
+
+    pub fn header(mut self, header: bool) -> Self {
+        self.header = header;
+        self
+    }
+
+    // adds a first column "op" of insert, update or delete
+    pub fn operation_column(mut self, operation: bool) -> Self {
+        self.operation = operation;
+        self
+    }
+
+    pub fn write_event(&mut self, _event: &Event, data: &EventData) -> Result<(), ExportError> {
+        match data {
+            EventData::TableMapEvent(table_map) if table_map.schema == self.schema && table_map.table == self.table => {
+                self.tables.insert(table_map.table_id, table_map.clone());
+                Ok(())
+            }
+            EventData::WriteRowsEvent(rows) | EventData::UpdateRowsEvent(rows) | EventData::DeleteRowsEvent(rows) => {
+                self.write_rows(rows)
+            }
+            _ => Ok(()),
+        }
+    }
+
+    pub fn flush(&mut self) -> Result<(), ExportError> {
+        Ok(self.writer.flush()?)
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+
+    fn write_rows(&mut self, rows: &RowsEvent) -> Result<(), ExportError> {
+        let table_map = match self.tables.get(&rows.table_id) {
+            Some(table_map) => table_map,
+            None => return Ok(()),
+        };
+        let mut lines = vec![];
+        if self.header && !self.header_written {
+            let mut fields: Vec<Option<String>> = vec![];
+            if self.operation {
+                fields.push(Some("op".to_owned()));
+            }
+            fields.extend((0..table_map.columns.len()).map(|i| Some(column_name(table_map, i))));
+            lines.push(fields);
+        }
+        let operation = match rows.kind {
+            RowsEventKind::Write => "insert",
+            RowsEventKind::Update => "update",
+            RowsEventKind::Delete => "delete",
+        };
+        for change in &rows.rows {
+            let image = match rows.kind {
+                RowsEventKind::Delete => change.before.as_ref(),
+                _ => change.after.as_ref(),
+            };
+            let image = match image {
+                Some(image) => image,
+                None => continue,
+            };
+            let mut fields = vec![];
+            if self.operation {
+                fields.push(Some(operation.to_owned()));
+            }
+            fields.extend((0..table_map.columns.len()).map(|i| {
+                image.get(i).and_then(|value| csv_text(value, table_map.columns.get(i)))
+            }));
+            lines.push(fields);
+        }
+        for fields in &lines {
+            self.write_line(fields)?;
+        }
+        self.header_written |= !lines.is_empty();
+        Ok(())
+    }
+
+    // None is NULL
+    fn write_line(&mut self, fields: &[Option<String>]) -> Result<(), ExportError> {
+        for (i, field) in fields.iter().enumerate() {
+            if i > 0 {
+                self.writer.write_all(&[self.delimiter])?;
+            }
+            let field = match field {
+                Some(field) => field,
+                None => {
+                    self.writer.write_all(self.null.as_bytes())?;
+                    continue;
+                }
+            };
+            let quote = match self.quoting {
+                Quoting::Always => true,
+                Quoting::Never => false,
+                Quoting::Necessary => {
+                    *field == self.null || field.bytes().any(|b| b == self.delimiter || matches!(b, b'"' | b'\r' | b'\n'))
+                }
+            };
+            if quote {
+                write!(self.writer, "\"{}\"", field.replace('"', "\"\""))?;
+            } else {
+                self.writer.write_all(field.as_bytes())?;
+            }
+        }
+        self.writer.write_all(b"\n")?;
+        Ok(())
+    }
+}
+
+impl<W: Write> EventHandler for CsvWriter<W> {
+    type Error = ExportError;
+
+    fn on_event(&mut self, event: &Event, data: &EventData) -> Result<(), Self::Error> {
+        self.write_event(event, data)
+    }
+}
+
+fn column_name(table_map: &TableMapEvent, index: usize) -> String {
+    match table_map.columns.get(index).and_then(|c| c.name.as_ref()) {
+        Some(name) => name.clone(),
+        None => format!("@{}", index + 1),
+    }
+}
+
+// A value as the text a spreadsheet or LOAD DATA would take: ENUM and SET as their labels when
+// the table map has them, TIMESTAMP in UTC, and bytes in hex. None for NULL.
+fn csv_text(value: &Value, column: Option<&TableMapColumn>) -> Option<String> {
+    let text = match value {
+        Value::Null => return None,
+        Value::Timestamp { seconds, micros } => {
+            let (year, month, day) = civil_from_days(i64::from(*seconds) / 86400);
+            let time = seconds % 86400;
+            let fraction = if *micros == 0 { String::new() } else { format!(".{:06}", micros).trim_end_matches('0').to_owned() };
+            format!("{:04}-{:02}-{:02} {:02}:{:02}:{:02}{}", year, month, day, time / 3600, time / 60 % 60, time % 60, fraction)
+        }
+        Value::Enum(index) => match column.and_then(|c| c.enum_values.as_ref()) {
+            Some(labels) => labels.get((*index as usize).wrapping_sub(1)).cloned().unwrap_or_default(),
+            None => index.to_string(),
+        },
+        Value::Set(bits) => match column.and_then(|c| c.set_values.as_ref()) {
+            Some(labels) => labels.iter().enumerate()
+                .filter(|(i, _)| *i < 64 && bits & (1 << i) != 0)
+                .map(|(_, l)| l.as_str())
+                .collect::<Vec<_>>()
+                .join(","),
+            None => bits.to_string(),
+        },
+        _ => match plain_json(value) {
+            Json::String(s) => s,
+            Json::Null => return None,
+            other => other.to_string(),
+        },
+    };
+    Some(text)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::context::ParseContext;
+    use crate::csv::{CsvWriter, Quoting};
+    use crate::event::Event;
+    use crate::event::tests::{rows_body, table_map_body, timed_event_bytes, users_rows, users_table_map};
+    use crate::handler::run;
+
+    fn export(mut writer: CsvWriter<Vec<u8>>) -> String {
+        // users (id INT, name VARCHAR(20)) and another table, orders (id INT)
+        let users = users_table_map(&[3, 15], &[20, 0], 0x00, &[4, 8, 2, b'i', b'd', 4, b'n', b'a', b'm', b'e']);
+        let orders = table_map_body(109, "test", "orders", &[3], &[], 0x00, &[]);
+        let mut insert = users_rows(2, &[0x03]);
+        insert.extend_from_slice(&[0x00, 7, 0, 0, 0, 6, b'a', b',', b'"', b'b', b'"', b'c']);
+        insert.extend_from_slice(&[0x02, 8, 0, 0, 0]);
+        insert.extend_from_slice(&[0x00, 9, 0, 0, 0, 0]);
+        let delete = users_rows(2, &[0x01, 0x02, 8, 0, 0, 0]);
+        let other = rows_body(109, 1, &[0x01, 0x00, 1, 0, 0, 0]);
+        let mut bytes = vec![];
+        for (type_code, body) in [(19, users), (19, orders), (30, insert), (32, delete), (30, other)] {
+            bytes.extend(timed_event_bytes(16, type_code, &body, 0));
+        }
+        let mut reader = &bytes[..];
+        let events = std::iter::from_fn(|| {
+            if reader.is_empty() { None } else { Some(Event::parse(&mut reader, 4).map_err(Into::into)) }
+        });
+        run(events, &mut ParseContext::new(), &mut writer).unwrap();
+        String::from_utf8(writer.into_inner()).unwrap()
+    }
+
+    #[test]
+    fn test_csv_rows_of_one_table() {
+        //given
+        let writer = CsvWriter::new(vec![], "test", "users").operation_column(true);
+
+        //when
+        let output = export(writer);
+
+        //then
+        assert_eq!(output, "op,id,name\ninsert,7,\"a,\"\"b\"\"c\"\ninsert,8,\ninsert,9,\"\"\ndelete,8,\n");
+    }
+
+    #[test]
+    fn test_tsv_with_null_marker() {
+        //given
+        let writer = CsvWriter::new(vec![], "test", "users").delimiter(b'\t').null("\\N").quoting(Quoting::Never).header(false);
+
+        //when
+        let output = export(writer);
+
+        //then
+        assert_eq!(output, "7\ta,\"b\"c\n8\t\\N\n9\t\n8\t\\N\n");
+    }
+}
@@ -0,0 +1,277 @@
+use std::collections::HashMap;
+use std::time::{SystemTime, UNIX_EPOCH};
+use serde_json::{json, Map, Value as Json};
+use crate::column::ColumnType;
+use crate::event::{Event, EventData};
+use crate::gtid::{format_uuid, MariaDbGtid};
+use crate::rows::{RowImage, RowsEventKind};
+use crate::table_map::{TableMapColumn, TableMapEvent};
+use crate::utils::{base64, civil_from_days, days_from_civil};
+use crate::value::Value;
+
+// One change event as the Debezium MySQL connector would produce it, without the Kafka Connect
+// schema (schemas.enable=false), to be sent to `topic`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DebeziumRecord {
+    // <server name>.<database>.<table>
+    pub topic: String,
+    // the primary key columns of the row, None for tables without one
+    pub key: Option<Json>,
+    // {"before", "after", "source", "op", "ts_ms", "transaction"}
+    pub value: Json,
+}
+
+// Turns rows events into Debezium change events, following the connector's defaults:
+// DATE as days since the epoch, TIME as microseconds, DATETIME as epoch milliseconds (or
+// microseconds with a fractional part over 3 digits), TIMESTAMP as an ISO-8601 UTC string,
+// binary values base64, ENUM and SET as their labels when the table map carries them. DECIMAL
+// is a string, as with decimal.handling.mode=string. JSON columns are left in MySQL's binary
+// encoding, base64 like other bytes. Columns without a name in the table map are called @1, @2..
+//
+//     let mut envelopes = DebeziumEnvelopes::new("dbserver1", "mysql-bin.000003");
+//     for event in BinlogFile::from_path(path)?.events() {
+//         let event = event?;
+//         for record in envelopes.records(&event, &event.parsed(&mut ctx)?) {
+//             producer.send(&record.topic, record.key, record.value)?;
+//         }
+//     }
+pub struct DebeziumEnvelopes {
+    server_name: String,
+    file: String,
+    gtid: Option<String>,
+    tables: HashMap<u64, TableMapEvent>,
+}
+
+impl DebeziumEnvelopes {
+    // `server_name` is the connector's topic.prefix; `file` the binlog the events start in,
+    // which rotate events then keep up to date
+    pub fn new(server_name: &str, file: &str) -> Self {
+        DebeziumEnvelopes {
+            server_name: server_name.to_owned(),
+            file: file.to_owned(),
+            gtid: None,
+            tables: HashMap::new(),
+        }
+    }
+
+    // the records for the rows in `data`, none for other events
+    pub fn records(&mut self, event: &Event, data: &EventData) -> Vec<DebeziumRecord> {
+        let rows = match data {
+            EventData::TableMapEvent(table_map) => {
+                self.tables.insert(table_map.table_id, table_map.clone());
+                return vec![];
+            }
+            EventData::RotateEvent { next_file, .. } => {
+                self.file = next_file.clone();
+                return vec![];
+            }
+            EventData::GtidLogEvent(gtid) => {
+                self.gtid = Some(format!("{}:{}", format_uuid(&gtid.sid), gtid.gno));
+                return vec![];
+            }
+            EventData::AnonymousGtidLogEvent(_) => {
+                self.gtid = None;
+                return vec![];
+            }
+            EventData::MariaDbGtidEvent { domain_id, seq_no, .. } => {
+                let gtid = MariaDbGtid { domain_id: *domain_id, server_id: event.server_id(), seq_no: *seq_no };
+                self.gtid = Some(gtid.to_string());
+                return vec![];
+            }
+            EventData::WriteRowsEvent(rows) | EventData::UpdateRowsEvent(rows) | EventData::DeleteRowsEvent(rows) => rows,
+            _ => return vec![],
+        };
+        let table_map = match self.tables.get(&rows.table_id) {
+            Some(table_map) => table_map,
+            None => return vec![],
+        };
+        let op = match rows.kind {
+            RowsEventKind::Write => "c",
+            RowsEventKind::Update => "u",
+            RowsEventKind::Delete => "d",
+        };
+        let ts_ms = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_millis() as u64).unwrap_or(0);
+        rows.rows.iter().enumerate().map(|(row, change)| {
+            let before = change.before.as_ref().map(|image| row_json(image, table_map)).unwrap_or_default();
+            let after = change.after.as_ref().map(|image| row_json(image, table_map)).unwrap_or_default();
+            let key = change.after.as_ref().or(change.before.as_ref()).and_then(|image| key_json(image, table_map));
+            let value = json!({
+                "before": before,
+                "after": after,
+                "source": {
+                    "version": env!("CARGO_PKG_VERSION"),
+                    "connector": "mysql",
+                    "name": self.server_name,
+                    "ts_ms": u64::from(event.timestamp()) * 1000,
+                    "snapshot": "false",
+                    "db": table_map.schema,
+                    "sequence": null,
+                    "table": table_map.table,
+                    "server_id": event.server_id(),
+                    "gtid": self.gtid,
+                    "file": self.file,
+                    "pos": event.offset(),
+                    "row": row,
+                    "thread": null,
+                    "query": null,
+                },
+                "op": op,
+                "ts_ms": ts_ms,
+                "transaction": null,
+            });
+            DebeziumRecord {
+                topic: format!("{}.{}.{}", self.server_name, table_map.schema, table_map.table),
+                key,
+                value,
+            }
+        }).collect()
+    }
+}
+
+fn column_name(table_map: &TableMapEvent, index: usize) -> String {
+    match table_map.columns.get(index).and_then(|c| c.name.as_ref()) {
+        Some(name) => name.clone(),
+        None => format!("@{}", index + 1),
+    }
+}
+
+// the columns present in the image; those binlog_row_image=MINIMAL leaves out are missing
+fn row_json(image: &RowImage, table_map: &TableMapEvent) -> Json {
+    let columns: Map<String, Json> = image.values().iter().enumerate()
+        .filter_map(|(i, value)| {
+            let value = value.as_ref()?;
+            Some((column_name(table_map, i), debezium_value(value, table_map.columns.get(i))))
+        })
+        .collect();
+    Json::Object(columns)
+}
+
+fn key_json(image: &RowImage, table_map: &TableMapEvent) -> Option<Json> {
+    if table_map.primary_key.is_empty() {
+        return None;
+    }
+    let key: Map<String, Json> = table_map.primary_key.iter()
+        .map(|&i| {
+            let value = image.get(i).map(|v| debezium_value(v, table_map.columns.get(i))).unwrap_or_default();
+            (column_name(table_map, i), value)
+        })
+        .collect();
+    Some(Json::Object(key))
+}
+
+fn debezium_value(value: &Value, column: Option<&TableMapColumn>) -> Json {
+    match value {
+        Value::Null => Json::Null,
+        Value::Int(v) => (*v).into(),
+        Value::UInt(v) => (*v).into(),
+        Value::Float(v) => f64::from(*v).into(),
+        Value::Double(v) => (*v).into(),
+        Value::Decimal(v) | Value::String(v) => v.as_str().into(),
+        Value::Bytes(v) | Value::Json(v) => base64(v).into(),
+        Value::Year(v) => (*v).into(),
+        // zero dates have no epoch equivalent
+        Value::Date { month: 0, .. } | Value::Date { day: 0, .. } => Json::Null,
+        Value::DateTime { month: 0, .. } | Value::DateTime { day: 0, .. } => Json::Null,
+        Value::Date { year, month, day } => days_from_civil(i64::from(*year), *month, *day).into(),
+        Value::Time { negative, hours, minutes, seconds, micros } => {
+            let micros = ((i64::from(*hours) * 60 + i64::from(*minutes)) * 60 + i64::from(*seconds)) * 1_000_000 + i64::from(*micros);
+            (if *negative { -micros } else { micros }).into()
+        }
+        Value::DateTime { year, month, day, hour, minute, second, micros } => {
+            let seconds = days_from_civil(i64::from(*year), *month, *day) * 86400
+                + i64::from(*hour) * 3600 + i64::from(*minute) * 60 + i64::from(*second);
+            let micros = seconds * 1_000_000 + i64::from(*micros);
+            match column {
+                Some(c) if c.column_type == ColumnType::DateTime2 && c.meta > 3 => micros.into(),
+                _ => micros.div_euclid(1000).into(),
+            }
+        }
+        Value::Timestamp { seconds, micros } => {
+            let days = i64::from(*seconds) / 86400;
+            let time = i64::from(*seconds) % 86400;
+            let (year, month, day) = civil_from_days(days);
+            let fraction = if *micros == 0 { String::new() } else { format!(".{:06}", micros).trim_end_matches('0').to_owned() };
+            format!("{:04}-{:02}-{:02}T{:02}:{:02}:{:02}{}Z", year, month, day, time / 3600, time / 60 % 60, time % 60, fraction).into()
+        }
+        // BIT(1) is a boolean, wider BITs bytes
+        Value::Bit(v) => match column {
+            Some(c) if c.meta == 1 => v.iter().any(|b| *b != 0).into(),
+            _ => base64(v).into(),
+        },
+        Value::Enum(index) => match column.and_then(|c| c.enum_values.as_ref()) {
+            Some(labels) => labels.get((*index as usize).wrapping_sub(1)).map(|l| l.as_str()).unwrap_or("").into(),
+            None => (*index).into(),
+        },
+        Value::Set(bits) => match column.and_then(|c| c.set_values.as_ref()) {
+            Some(labels) => labels.iter().enumerate()
+                .filter(|(i, _)| *i < 64 && bits & (1 << i) != 0)
+                .map(|(_, l)| l.as_str())
+                .collect::<Vec<_>>()
+                .join(",")
+                .into(),
+            None => (*bits).into(),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::context::ParseContext;
+    use crate::debezium::DebeziumEnvelopes;
+    use crate::event::Event;
+    use crate::event::tests::{parse_events, users_rows, users_table_map};
+    use crate::utils::{base64, civil_from_days, days_from_civil};
+
+    #[test]
+    fn test_row_change_envelopes() {
+        //given
+        let mut gtid = vec![0x01];
+        gtid.extend_from_slice(&[0x3e; 16]);
+        gtid.extend_from_slice(&7i64.to_le_bytes());
+        // test.users (id INT PRIMARY KEY, created DATE) with column names
+        let table_map = users_table_map(&[3, 10], &[], 0x00, b"\x04\x0b\x02id\x07created\x08\x01\x00");
+        let row = [0x03, 0x00, 7, 0, 0, 0, 0x3f, 0xd0, 0x0f];
+        let write_rows = users_rows(2, &row);
+        let delete_rows = users_rows(2, &row);
+        let events: Vec<Event> = parse_events(16, &[(33, gtid), (19, table_map), (30, write_rows), (32, delete_rows)]);
+        let mut ctx = ParseContext::new();
+        let mut envelopes = DebeziumEnvelopes::new("dbserver1", "mysql-bin.000003");
+
+        //when
+        let records: Vec<_> = events.iter()
+            .flat_map(|e| envelopes.records(e, &e.parsed(&mut ctx).unwrap()))
+            .collect();
+
+        //then
+        assert_eq!(records.len(), 2);
+        let insert = &records[0];
+        assert_eq!(insert.topic, "dbserver1.test.users");
+        assert_eq!(insert.key, Some(serde_json::json!({"id": 7})));
+        assert_eq!(insert.value["op"], "c");
+        assert_eq!(insert.value["before"], serde_json::Value::Null);
+        assert_eq!(insert.value["after"], serde_json::json!({"id": 7, "created": 19753}));
+        let source = &insert.value["source"];
+        assert_eq!(source["file"], "mysql-bin.000003");
+        assert_eq!(source["pos"], 4);
+        assert_eq!(source["ts_ms"], 16000);
+        assert_eq!(source["gtid"], "3e3e3e3e-3e3e-3e3e-3e3e-3e3e3e3e3e3e:7");
+        assert_eq!((source["db"].as_str(), source["table"].as_str()), (Some("test"), Some("users")));
+        assert_eq!(records[1].value["op"], "d");
+        assert_eq!(records[1].value["before"], serde_json::json!({"id": 7, "created": 19753}));
+        assert_eq!(records[1].value["after"], serde_json::Value::Null);
+    }
+
+    #[test]
+    fn test_value_conversions() {
+        //given
+        let dates = [(1970, 1, 1), (2000, 3, 1), (1969, 12, 31)];
+
+        //when
+        let days: Vec<i64> = dates.iter().map(|(y, m, d)| days_from_civil(*y, *m, *d)).collect();
+
+        //then
+        assert_eq!(days, vec![0, 11017, -1]);
+        assert_eq!(civil_from_days(11017), (2000, 3, 1));
+        assert_eq!((base64(b"ab"), base64(b"abc"), base64(b"a")), ("YWI=".to_owned(), "YWJj".to_owned(), "YQ==".to_owned()));
+    }
+}
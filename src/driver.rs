@@ -0,0 +1,246 @@
+use crate::checksum::ChecksumVerifier;
+use crate::client::{checksum_algorithm, decode_event, ER_UNKNOWN_SYSTEM_VARIABLE};
+use crate::errors::{BinlogFileError, ClientError};
+use crate::event::Event;
+use crate::position::BinlogPosition;
+
+// Binlog streams over connections from the mysql and mysql_async crates, for applications that
+// already log in, set up TLS and pool connections with them. The driver sends the dump request
+// and reads the packets; the events are parsed here, as ReplicationClient's are.
+//
+//     let conn = mysql::Conn::new(opts)?;
+//     let request = mysql::BinlogRequest::new(4242).with_filename(&b"mysql-bin.000123"[..]).with_pos(4u64);
+//     for event in driver::binlog_stream(conn, request)? {
+//         ...
+//     }
+
+// The driver's events, serialized back as the server sent them, then parsed and followed. The
+// position is known from the artificial RotateEvent the dump starts with.
+struct Decoder {
+    position: BinlogPosition,
+    checksum: ChecksumVerifier,
+}
+
+impl Decoder {
+    fn new(binlog_checksum: Option<String>) -> Result<Self, ClientError> {
+        Ok(Decoder {
+            position: BinlogPosition::new("", 4),
+            checksum: ChecksumVerifier::with_algorithm(checksum_algorithm(binlog_checksum.as_deref())?),
+        })
+    }
+
+    fn decode<E>(&mut self, event: E) -> Result<Event, BinlogFileError> where
+        E: FnOnce(&mut Vec<u8>) -> std::io::Result<()>
+    {
+        let mut bytes = vec![];
+        event(&mut bytes).map_err(ClientError::from)?;
+        decode_event(&bytes, &mut self.position, &mut self.checksum)
+    }
+}
+
+#[cfg(feature = "mysql")]
+pub use self::sync::{binlog_stream, MysqlBinlogStream};
+
+#[cfg(feature = "mysql_async")]
+pub use self::nonblocking::{binlog_stream_async, MysqlAsyncBinlogStream};
+
+#[cfg(feature = "mysql")]
+mod sync {
+    use mysql::binlog::BinlogVersion;
+    use mysql::prelude::Queryable;
+    use crate::driver::{Decoder, ER_UNKNOWN_SYSTEM_VARIABLE};
+    use crate::errors::{BinlogFileError, ClientError};
+    use crate::event::Event;
+    use crate::position::BinlogPosition;
+
+    // Turns `conn` into a stream of the binlog `request` asks for. The account needs
+    // REPLICATION SLAVE, and the connection is the stream's until it's dropped.
+    pub fn binlog_stream(mut conn: mysql::Conn, request: mysql::BinlogRequest<'_>) -> Result<MysqlBinlogStream, ClientError> {
+        let binlog_checksum = match conn.query_first::<Option<String>, _>("SELECT @@global.binlog_checksum") {
+            Ok(row) => row.flatten(),
+            Err(mysql::Error::MySqlError(e)) if e.code == ER_UNKNOWN_SYSTEM_VARIABLE => None,
+            Err(e) => return Err(driver_error(e)),
+        };
+        let decoder = Decoder::new(binlog_checksum)?;
+        let inner = conn.get_binlog_stream(request).map_err(driver_error)?;
+        Ok(MysqlBinlogStream { inner, decoder })
+    }
+
+    pub struct MysqlBinlogStream {
+        inner: mysql::BinlogStream,
+        decoder: Decoder,
+    }
+
+    impl MysqlBinlogStream {
+        // where the next event will come from
+        pub fn position(&self) -> &BinlogPosition {
+            &self.decoder.position
+        }
+    }
+
+    impl Iterator for MysqlBinlogStream {
+        type Item = Result<Event, BinlogFileError>;
+
+        fn next(&mut self) -> Option<Self::Item> {
+            let event = match self.inner.next()? {
+                Ok(event) => event,
+                Err(e) => return Some(Err(driver_error(e).into())),
+            };
+            Some(self.decoder.decode(|bytes| event.write(BinlogVersion::Version4, bytes)))
+        }
+    }
+
+    fn driver_error(error: mysql::Error) -> ClientError {
+        match error {
+            mysql::Error::IoError(e) => ClientError::Io(e),
+            mysql::Error::MySqlError(e) => ClientError::Server { code: e.code, sql_state: Some(e.state), message: e.message },
+            e => ClientError::Driver(e.to_string()),
+        }
+    }
+}
+
+#[cfg(feature = "mysql_async")]
+mod nonblocking {
+    use std::pin::Pin;
+    use std::task::{Context, Poll};
+    use futures_core::Stream;
+    use mysql_async::binlog::BinlogVersion;
+    use mysql_async::prelude::Queryable;
+    use crate::driver::{Decoder, ER_UNKNOWN_SYSTEM_VARIABLE};
+    use crate::errors::{BinlogFileError, ClientError};
+    use crate::event::Event;
+    use crate::position::BinlogPosition;
+
+    // binlog_stream() for a mysql_async connection
+    pub async fn binlog_stream_async(
+        mut conn: mysql_async::Conn,
+        request: mysql_async::BinlogStreamRequest<'_>,
+    ) -> Result<MysqlAsyncBinlogStream, ClientError> {
+        let binlog_checksum = match conn.query_first::<Option<String>, _>("SELECT @@global.binlog_checksum").await {
+            Ok(row) => row.flatten(),
+            Err(mysql_async::Error::Server(e)) if e.code == ER_UNKNOWN_SYSTEM_VARIABLE => None,
+            Err(e) => return Err(driver_error(e)),
+        };
+        let decoder = Decoder::new(binlog_checksum)?;
+        let inner = conn.get_binlog_stream(request).await.map_err(driver_error)?;
+        Ok(MysqlAsyncBinlogStream { inner, decoder })
+    }
+
+    pub struct MysqlAsyncBinlogStream {
+        inner: mysql_async::BinlogStream,
+        decoder: Decoder,
+    }
+
+    impl MysqlAsyncBinlogStream {
+        // where the next event will come from
+        pub fn position(&self) -> &BinlogPosition {
+            &self.decoder.position
+        }
+    }
+
+    impl Stream for MysqlAsyncBinlogStream {
+        type Item = Result<Event, BinlogFileError>;
+
+        fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+            let this = &mut *self;
+            match Pin::new(&mut this.inner).poll_next(cx) {
+                Poll::Ready(Some(Ok(event))) => {
+                    Poll::Ready(Some(this.decoder.decode(|bytes| event.write(BinlogVersion::Version4, bytes))))
+                }
+                Poll::Ready(Some(Err(e))) => Poll::Ready(Some(Err(driver_error(e).into()))),
+                Poll::Ready(None) => Poll::Ready(None),
+                Poll::Pending => Poll::Pending,
+            }
+        }
+    }
+
+    fn driver_error(error: mysql_async::Error) -> ClientError {
+        match error {
+            mysql_async::Error::Io(mysql_async::IoError::Io(e)) => ClientError::Io(e),
+            mysql_async::Error::Server(e) => ClientError::Server { code: e.code, sql_state: Some(e.state), message: e.message },
+            e => ClientError::Driver(e.to_string()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::TcpStream;
+    use crate::client::tests::{dump_packets, fake_server, handshake, read_packet, write_packet, write_result_set};
+    use crate::event::TypeCode;
+    use crate::position::BinlogPosition;
+
+    // what the drivers' binlog streams ask a server, answered with the test binlog
+    fn serve_dump(mut stream: TcpStream) {
+        write_packet(&mut stream, 0, &handshake("mysql_native_password", &[1; 20]));
+        read_packet(&mut stream);
+        write_packet(&mut stream, 2, &[0, 0, 0, 2, 0, 0, 0]);
+        assert_eq!(&read_packet(&mut stream)[1..], b"SELECT @@global.binlog_checksum");
+        write_result_set(&mut stream, &[&[Some("CRC32")]]);
+        assert!(read_packet(&mut stream)[1..].starts_with(b"SET @master_binlog_checksum"));
+        write_packet(&mut stream, 1, &[0, 0, 0, 2, 0, 0, 0]);
+        assert_eq!(read_packet(&mut stream)[0], 0x15);
+        write_packet(&mut stream, 1, &[0, 0, 0, 2, 0, 0, 0]);
+        assert_eq!(read_packet(&mut stream)[0], 0x12);
+        for (seq, packet) in dump_packets().iter().enumerate() {
+            write_packet(&mut stream, seq as u8 + 1, packet);
+        }
+    }
+
+    #[cfg(feature = "mysql")]
+    #[test]
+    fn test_stream_over_mysql_conn() {
+        //given
+        let (addr, server) = fake_server(serve_dump);
+        let port = addr.rsplit_once(':').unwrap().1.parse().unwrap();
+        let opts = mysql::OptsBuilder::new()
+            .ip_or_hostname(Some("127.0.0.1"))
+            .tcp_port(port)
+            .user(Some("repl"))
+            .prefer_socket(false)
+            .max_allowed_packet(Some(1 << 24));
+        let conn = mysql::Conn::new(opts).unwrap();
+
+        //when
+        let mut stream = crate::driver::binlog_stream(conn, mysql::BinlogRequest::new(4242)).unwrap();
+        let events: Vec<_> = stream.by_ref().take(16).map(|e| e.unwrap()).collect();
+        server.join().unwrap();
+
+        //then
+        assert_eq!(events[0].type_code(), TypeCode::RotateEvent);
+        assert_eq!(events[1].type_code(), TypeCode::FormatDescriptionEvent);
+        assert_eq!(events[6].offset(), 529);
+        assert_eq!(stream.position(), &BinlogPosition::new("mysql-bin.100747", 4));
+    }
+
+    #[cfg(feature = "mysql_async")]
+    #[tokio::test]
+    async fn test_stream_over_mysql_async_conn() {
+        use futures_util::StreamExt;
+
+        //given
+        let (addr, server) = fake_server(serve_dump);
+        let port = addr.rsplit_once(':').unwrap().1.parse().unwrap();
+        let opts = mysql_async::OptsBuilder::default()
+            .ip_or_hostname("127.0.0.1")
+            .tcp_port(port)
+            .user(Some("repl"))
+            .prefer_socket(false)
+            .max_allowed_packet(Some(1 << 24))
+            .wait_timeout(Some(28800));
+        let conn = mysql_async::Conn::new(opts).await.unwrap();
+
+        //when
+        let mut stream = crate::driver::binlog_stream_async(conn, mysql_async::BinlogStreamRequest::new(4242)).await.unwrap();
+        let mut events = vec![];
+        for _ in 0..16 {
+            events.push(stream.next().await.unwrap().unwrap());
+        }
+        server.join().unwrap();
+
+        //then
+        assert_eq!(events[1].type_code(), TypeCode::FormatDescriptionEvent);
+        assert_eq!(events[6].offset(), 529);
+        assert_eq!(stream.position(), &BinlogPosition::new("mysql-bin.100747", 4));
+    }
+}
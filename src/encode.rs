@@ -0,0 +1,84 @@
+use std::convert::TryFrom;
+use crate::checksum::{crc32, ChecksumAlgorithm};
+use crate::errors::EncodeError;
+use crate::event::EventHeader;
+
+// The bytes of an event at `offset` with `payload` as its body, for tools that change events
+// and write them back out. The header's event_length and next_position are made to match the
+// payload and offset, whatever they were, and with `checksum` a CRC32 of the header and payload
+// follows it, as with binlog_checksum=CRC32. An event encoded at the offset it was read from,
+// with the body it had, comes out as it was stored.
+//
+//     let mut body = event.data().to_vec();
+//     body[..4].copy_from_slice(&thread_id.to_le_bytes());
+//     writer.write_all(&encode_event(event.header(), &body, offset, event.checksum().is_some())?)?;
+pub fn encode_event(mut header: EventHeader, payload: &[u8], offset: u64, checksum: bool) -> Result<Vec<u8>, EncodeError> {
+    let len = EventHeader::LEN + payload.len() + if checksum { ChecksumAlgorithm::CRC32_LEN } else { 0 };
+    header.event_length = u32::try_from(len).ok()
+        .filter(|len| *len <= EventHeader::MAX_EVENT_LENGTH)
+        .ok_or(EncodeError::EventTooLong(len))?;
+    let end = offset + len as u64;
+    header.next_position = u32::try_from(end).map_err(|_| EncodeError::PositionOverflow(end))?;
+    let mut bytes = Vec::with_capacity(len);
+    bytes.extend_from_slice(&header.to_bytes());
+    bytes.extend_from_slice(payload);
+    if checksum {
+        let crc = crc32(&bytes[..EventHeader::LEN], payload);
+        bytes.extend_from_slice(&crc.to_le_bytes());
+    }
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::binlog_file::BinlogFile;
+    use crate::checksum::verify_crc32;
+    use crate::errors::EncodeError;
+    use crate::event::{Event, EventHeader};
+    use super::encode_event;
+
+    #[test]
+    fn test_encode_gives_untouched_events_back() {
+        //given
+        let path = "tests/asset/mysql-bin.100746";
+        let file = std::fs::read(path).unwrap();
+
+        //when
+        let bytes: Vec<u8> = BinlogFile::from_path(path).unwrap()
+            .events()
+            .flat_map(|e| {
+                let event = e.unwrap();
+                event.encode(event.offset()).unwrap()
+            })
+            .collect();
+
+        //then
+        assert_eq!(bytes, file[4..]);
+    }
+
+    #[test]
+    fn test_encode_changed_event() {
+        //given
+        let query = BinlogFile::from_path("tests/asset/mysql-bin.100746").unwrap()
+            .events()
+            .map(|e| e.unwrap())
+            .find(|event| event.offset() == 219)
+            .unwrap();
+        let mut body = query.data().to_vec();
+        body.extend_from_slice(b" COMMENT 'moved'");
+
+        //when
+        let bytes = encode_event(query.header(), &body, 1000, true).unwrap();
+        let moved = Event::parse(&mut &bytes[..], 1000).unwrap();
+        let too_far = encode_event(query.header(), &body, u64::from(u32::MAX), false);
+
+        //then
+        assert_eq!(moved.event_length() as usize, EventHeader::LEN + body.len() + 4);
+        assert_eq!(moved.next_position(), 1000 + bytes.len() as u64);
+        assert_eq!(moved.data()[..body.len()], body[..]);
+        let mut header = [0u8; EventHeader::LEN];
+        header.copy_from_slice(&bytes[..EventHeader::LEN]);
+        assert!(verify_crc32(1000, &header, &bytes[EventHeader::LEN..]).is_ok());
+        assert!(matches!(too_far, Err(EncodeError::PositionOverflow(_))));
+    }
+}
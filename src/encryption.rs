@@ -0,0 +1,233 @@
+use std::io::Read;
+use crate::errors::{BinlogFileError, EventParseError};
+use crate::utils::{read_full, read_utf8};
+
+pub const BINLOG_MAGIC: [u8; 4] = [0xfe, 0x62, 0x69, 0x6e];
+// replaces BINLOG_MAGIC in files written with binlog_encryption=ON (8.0.14+)
+pub const ENCRYPTED_BINLOG_MAGIC: [u8; 4] = [0xfd, 0x62, 0x69, 0x6e];
+
+const KEY_ID: u8 = 1;
+const ENCRYPTED_FILE_PASSWORD: u8 = 2;
+const IV: u8 = 3;
+
+// The header at the start of an encrypted binlog. The file password, decrypted with the keyring
+// key named by key_id, and the IV are what the rest of the file is AES-CTR encrypted with.
+// https://dev.mysql.com/doc/dev/mysql-server/latest/classRpl__encryption__header__v1.html
+#[derive(Debug, Clone, PartialEq)]
+pub struct EncryptionHeader {
+    pub version: u8,
+    pub key_id: String,
+    pub encrypted_password: [u8; 32],
+    pub iv: [u8; 16],
+}
+
+impl EncryptionHeader {
+    // magic included; the events start right after it
+    pub const LEN: usize = 512;
+
+    // `bytes` follows the magic
+    pub fn parse(bytes: &[u8]) -> Result<Self, EventParseError> {
+        let (&version, mut fields) = bytes.split_first().ok_or(EventParseError::UnexpectedEof { field: "version" })?;
+        if version != 1 {
+            return Err(EventParseError::UnsupportedType { field: "encryption header version", type_code: version });
+        }
+        let mut header = EncryptionHeader {
+            version,
+            key_id: String::new(),
+            encrypted_password: [0; 32],
+            iv: [0; 16],
+        };
+        // the fields are followed by zero padding
+        while let Some((&field_type, rest)) = fields.split_first() {
+            fields = rest;
+            match field_type {
+                0 => break,
+                KEY_ID => {
+                    let (&len, rest) = fields.split_first().ok_or(EventParseError::UnexpectedEof { field: "key_id" })?;
+                    let key_id = rest.get(..usize::from(len)).ok_or(EventParseError::BadLength {
+                        field: "key_id",
+                        len: u64::from(len),
+                        available: rest.len() as u64,
+                    })?;
+                    header.key_id = read_utf8(key_id.to_vec(), "key_id")?;
+                    fields = &rest[usize::from(len)..];
+                }
+                ENCRYPTED_FILE_PASSWORD => fields = take_array(fields, &mut header.encrypted_password, "encrypted_password")?,
+                IV => fields = take_array(fields, &mut header.iv, "iv")?,
+                other => return Err(EventParseError::UnsupportedType { field: "encryption header field", type_code: other }),
+            }
+        }
+        Ok(header)
+    }
+}
+
+#[cfg(feature = "encryption")]
+impl EncryptionHeader {
+    // the password the file is encrypted with; `key` is the keyring key named by key_id
+    pub fn file_password(&self, key: &[u8; 32]) -> [u8; 32] {
+        use aes::cipher::{block_padding::NoPadding, BlockDecryptMut, KeyIvInit};
+
+        let mut password = self.encrypted_password;
+        cbc::Decryptor::<aes::Aes256>::new(key.into(), (&self.iv).into())
+            .decrypt_padded_mut::<NoPadding>(&mut password)
+            .expect("32 bytes are a whole number of blocks");
+        password
+    }
+}
+
+// Plaintext view of an encrypted binlog: the events after the encryption header decrypted with
+// AES-256-CTR, starting with the usual magic, so it can be handed to BinlogFile or BinlogReader.
+// Seeking works when the underlying reader can seek.
+#[cfg(feature = "encryption")]
+pub struct DecryptingReader<R> {
+    inner: R,
+    cipher: ctr::Ctr128BE<aes::Aes256>,
+}
+
+#[cfg(feature = "encryption")]
+impl<R: Read> DecryptingReader<R> {
+    // reads the encryption header from the start of `inner`
+    pub fn new(mut inner: R, key: &[u8; 32]) -> Result<Self, BinlogFileError> {
+        use aes::cipher::KeyIvInit;
+        use sha2::{Digest, Sha512};
+
+        let mut header = [0u8; EncryptionHeader::LEN];
+        inner.read_exact(&mut header)?;
+        let mut magic = [0u8; 4];
+        magic.copy_from_slice(&header[..4]);
+        if magic != ENCRYPTED_BINLOG_MAGIC {
+            return Err(BinlogFileError::BadMagic(magic));
+        }
+        let header = EncryptionHeader::parse(&header[4..])?;
+        // the file key and IV are the first 48 bytes of the password's SHA-512
+        let digest = Sha512::digest(header.file_password(key));
+        let cipher = ctr::Ctr128BE::<aes::Aes256>::new(digest[..32].into(), digest[32..48].into());
+        Ok(DecryptingReader { inner, cipher })
+    }
+
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+}
+
+#[cfg(feature = "encryption")]
+impl<R: Read> Read for DecryptingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        use aes::cipher::StreamCipher;
+
+        let read = self.inner.read(buf)?;
+        self.cipher.apply_keystream(&mut buf[..read]);
+        Ok(read)
+    }
+}
+
+#[cfg(feature = "encryption")]
+impl<R: Read + std::io::Seek> std::io::Seek for DecryptingReader<R> {
+    fn seek(&mut self, pos: std::io::SeekFrom) -> std::io::Result<u64> {
+        use aes::cipher::StreamCipherSeek;
+        use std::io::SeekFrom;
+
+        let header_len = EncryptionHeader::LEN as u64;
+        let pos = match pos {
+            SeekFrom::Start(pos) => SeekFrom::Start(pos + header_len),
+            pos => pos,
+        };
+        let file_pos = self.inner.seek(pos)?;
+        let pos = file_pos.checked_sub(header_len)
+            .ok_or_else(|| std::io::Error::new(std::io::ErrorKind::InvalidInput, "seek into the encryption header"))?;
+        self.cipher.seek(pos);
+        Ok(pos)
+    }
+}
+
+fn take_array<'a>(bytes: &'a [u8], into: &mut [u8], field: &'static str) -> Result<&'a [u8], EventParseError> {
+    let value = bytes.get(..into.len()).ok_or(EventParseError::UnexpectedEof { field })?;
+    into.copy_from_slice(value);
+    Ok(&bytes[into.len()..])
+}
+
+// Checks the four bytes a binlog starts with; an encrypted binlog fails with the key it needs.
+// `rest` is read only in that case, for the remainder of the encryption header.
+pub(crate) fn check_magic<R: Read>(magic: [u8; 4], rest: &mut R) -> Result<(), BinlogFileError> {
+    match magic {
+        BINLOG_MAGIC => Ok(()),
+        ENCRYPTED_BINLOG_MAGIC => {
+            let mut header = [0u8; EncryptionHeader::LEN - 4];
+            let filled = read_full(rest, &mut header)?;
+            let header = EncryptionHeader::parse(&header[..filled])?;
+            Err(BinlogFileError::EncryptedBinlog { key_id: header.key_id })
+        }
+        magic => Err(BinlogFileError::BadMagic(magic)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::binlog_reader::BinlogReader;
+    use crate::encryption::{EncryptionHeader, ENCRYPTED_BINLOG_MAGIC};
+    use crate::errors::BinlogFileError;
+
+    #[test]
+    fn test_encrypted_binlog_names_its_key() {
+        //given
+        let key_id = b"MySQLReplicationKey_0b2b8dda-6c7c-11ec-8b1c-0242ac110002_1";
+        let mut bytes = ENCRYPTED_BINLOG_MAGIC.to_vec();
+        bytes.extend_from_slice(&[1, 1, key_id.len() as u8]);
+        bytes.extend_from_slice(key_id);
+        bytes.push(2);
+        bytes.extend_from_slice(&[0xaa; 32]);
+        bytes.push(3);
+        bytes.extend_from_slice(&[0xbb; 16]);
+        bytes.resize(EncryptionHeader::LEN, 0);
+        // encrypted events
+        bytes.extend_from_slice(&[0x5c; 100]);
+
+        //when
+        let header = EncryptionHeader::parse(&bytes[4..EncryptionHeader::LEN]).unwrap();
+        let result = BinlogReader::from_reader(&bytes[..]);
+
+        //then
+        assert_eq!(header.iv, [0xbb; 16]);
+        assert!(matches!(result, Err(BinlogFileError::EncryptedBinlog { key_id: ref id }) if id.as_bytes() == key_id));
+    }
+
+    #[cfg(feature = "encryption")]
+    #[test]
+    fn test_decrypt_binlog() {
+        //given
+        use aes::cipher::{block_padding::NoPadding, BlockEncryptMut, KeyIvInit, StreamCipher};
+        use sha2::{Digest, Sha512};
+        use crate::binlog_file::BinlogFile;
+        use crate::encryption::DecryptingReader;
+        use std::io::Cursor;
+
+        let key = [7u8; 32];
+        let password = [9u8; 32];
+        let iv = [3u8; 16];
+        let mut encrypted_password = password;
+        cbc::Encryptor::<aes::Aes256>::new((&key).into(), (&iv).into())
+            .encrypt_padded_mut::<NoPadding>(&mut encrypted_password, 32)
+            .unwrap();
+        let mut bytes = ENCRYPTED_BINLOG_MAGIC.to_vec();
+        bytes.extend_from_slice(&[1, 1, 3]);
+        bytes.extend_from_slice(b"k_1");
+        bytes.push(2);
+        bytes.extend_from_slice(&encrypted_password);
+        bytes.push(3);
+        bytes.extend_from_slice(&iv);
+        bytes.resize(EncryptionHeader::LEN, 0);
+        let mut events = std::fs::read("tests/asset/mysql-bin.100746").unwrap();
+        let digest = Sha512::digest(password);
+        ctr::Ctr128BE::<aes::Aes256>::new(digest[..32].into(), digest[32..48].into()).apply_keystream(&mut events);
+        bytes.extend(events);
+
+        //when
+        let mut binlog_file = BinlogFile::from_reader(DecryptingReader::new(Cursor::new(bytes), &key).unwrap()).unwrap();
+        let events: Vec<_> = binlog_file.events().map(|e| e.unwrap()).collect();
+        let rotate = binlog_file.read_event_at(1017).unwrap();
+
+        //then
+        assert_eq!(events.len(), 15);
+        assert_eq!(rotate.type_code(), crate::event::TypeCode::RotateEvent);
+    }
+}
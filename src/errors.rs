@@ -1,4 +1,7 @@
 use thiserror::Error;
+use crate::column::ColumnType;
+use crate::event::TypeCode;
+use crate::compression::Compression;
 
 #[derive(Error, Debug)]
 pub enum BinlogFileError {
@@ -6,14 +9,270 @@ pub enum BinlogFileError {
     EventParseError(#[from] EventParseError),
     #[error("bad magic value at start of binlog: got {0:?}")]
     BadMagic([u8; 4]),
+    #[error("binlog is encrypted with keyring key {key_id}")]
+    EncryptedBinlog { key_id: String },
     #[error("error opening binlog file")]
     OpenError(std::io::Error),
     #[error("other I/O error reading binlog file")]
     Io(#[from] std::io::Error),
+    #[error("position {0} is not the start of an event")]
+    BadPosition(u64),
+    #[error("binlog is {0:?}-compressed and can't be seeked; read it with BinlogReader::from_path")]
+    Compressed(Compression),
+    #[error("reading {0:?}-compressed binlogs needs the matching cargo feature")]
+    CompressionNotEnabled(Compression),
+    #[error("error reading binlog from the server")]
+    Client(#[from] ClientError),
+}
+
+impl BinlogFileError {
+    // offset of an event cut off by the end of the input, which may still be being written;
+    // reading again from there later can succeed
+    pub fn truncated_at(&self) -> Option<u64> {
+        match self {
+            BinlogFileError::EventParseError(EventParseError::TruncatedEvent { offset, .. }) => Some(*offset),
+            _ => None,
+        }
+    }
 }
 
 #[derive(Error, Debug)]
 pub enum EventParseError {
     #[error("I/O error reading column: {0:?}")]
     Io(#[from] std::io::Error),
+    #[error("unsupported {field} type {type_code}")]
+    UnsupportedType { field: &'static str, type_code: u8 },
+    #[error("body ends while reading {field}")]
+    UnexpectedEof { field: &'static str },
+    #[error("{field} claims {len} bytes but only {available} are left")]
+    BadLength { field: &'static str, len: u64, available: u64 },
+    #[error("{field} is not valid UTF-8")]
+    BadUtf8 { field: &'static str },
+    #[error("{field} from the table map is impossible: {meta}")]
+    BadMetadata { field: &'static str, meta: u64 },
+    // wraps a failure decoding a body with where in the file it happened
+    #[error("in {type_code:?} event at {offset} ({event_length} bytes): {source}")]
+    InEvent { offset: u64, type_code: TypeCode, event_length: u32, source: Box<EventParseError> },
+    #[error("unexpected event type {0:?}")]
+    UnexpectedEventType(TypeCode),
+    #[error("rows event for table id {0} has {2} columns but its table map has {1}")]
+    ColumnCountMismatch(u64, usize, usize),
+    #[error("rows event refers to table id {0} with no preceding table map")]
+    UnknownTableId(u64),
+    #[error("rows event for table id {0} has a row with no columns present")]
+    NoColumnsPresent(u64),
+    #[error("event at {offset} has impossible length {event_length}")]
+    BadEventLength { offset: u64, event_length: u32 },
+    #[error("event at {offset} should end at {expected} but its next_position is {actual}")]
+    NextPositionMismatch { offset: u64, expected: u64, actual: u32 },
+    #[error("event at {offset} is {expected_len} bytes but only {available} are available")]
+    TruncatedEvent { offset: u64, expected_len: u32, available: u64 },
+    #[error("event at {offset} has unknown type code {type_code}")]
+    UnknownEventType { offset: u64, type_code: u8 },
+    #[error("checksum mismatch in event at {offset}: stored {expected:#010x}, computed {actual:#010x}")]
+    ChecksumMismatch { offset: u64, expected: u32, actual: u32 },
+}
+
+#[derive(Error, Debug)]
+pub enum SchemaError {
+    #[error("error parsing event")]
+    EventParseError(#[from] EventParseError),
+    #[error("error parsing DDL statement: {0}")]
+    DdlParseError(#[from] sqlparser::parser::ParserError),
+    #[error("DDL references unknown table {0}.{1}")]
+    UnknownTable(String, String),
+    #[error("DDL references unknown column {0} of table {1}.{2}")]
+    UnknownColumn(String, String, String),
+    #[error("error (de)serializing schema snapshot")]
+    SnapshotError(#[from] serde_json::Error),
+    #[error("unsupported schema snapshot version {0}")]
+    UnsupportedSnapshotVersion(u32),
+}
+
+#[derive(Error, Debug)]
+pub enum ExportError {
+    #[error("error reading or parsing events to export")]
+    Binlog(#[from] BinlogFileError),
+    #[error("I/O error writing exported events")]
+    Io(#[from] std::io::Error),
+    #[error("error serializing an exported event")]
+    Json(#[from] serde_json::Error),
+    #[error("value of column {column} doesn't fit its type in the exported schema")]
+    UnexpectedValue { column: String },
+    #[error("schema registry error: {0}")]
+    SchemaRegistry(String),
+    #[cfg(feature = "arrow")]
+    #[error("error building Arrow arrays")]
+    Arrow(#[from] arrow_schema::ArrowError),
+    #[cfg(feature = "parquet")]
+    #[error("error writing Parquet")]
+    Parquet(#[from] parquet::errors::ParquetError),
+}
+
+#[derive(Error, Debug)]
+pub enum AnonymizeError {
+    #[error("error parsing event to anonymize")]
+    EventParseError(#[from] EventParseError),
+    #[error("can't mask {column}, a {column_type:?} column; only strings and integers can be masked")]
+    UnsupportedColumn { column: String, column_type: ColumnType },
+    #[error("fixed value {0:?} isn't a number that fits a {1}-byte integer column")]
+    FixedValueOutOfRange(String, usize),
+    #[error("rows event refers to table id {0} with no preceding table map")]
+    UnknownTableId(u64),
+    #[error("rows event for table id {0} has a row with no columns present")]
+    NoColumnsPresent(u64),
+    #[error("rows in compressed transaction payloads can't be masked in place")]
+    CompressedPayload,
+    #[error("error encoding the anonymized event")]
+    EncodeError(#[from] EncodeError),
+}
+
+#[derive(Error, Debug)]
+pub enum EncodeError {
+    #[error("error reading the body of the event to encode")]
+    EventParseError(#[from] EventParseError),
+    #[error("event of {0} bytes is longer than an event can be")]
+    EventTooLong(usize),
+    #[error("event would end at {0}, past the 4 GiB a next_position can point to")]
+    PositionOverflow(u64),
+    #[error("name {0:?} is longer than the 255 bytes an event has room for")]
+    NameTooLong(String),
+}
+
+#[derive(Error, Debug)]
+pub enum BinlogWriteError {
+    #[error("error reading the event to write")]
+    EventParseError(#[from] EventParseError),
+    #[error("error encoding event")]
+    EncodeError(#[from] EncodeError),
+    #[error("I/O error writing binlog")]
+    Io(#[from] std::io::Error),
+    #[error("a binlog starts with a FormatDescriptionEvent, not a {0:?}")]
+    NotFormatDescription(TypeCode),
+}
+
+#[derive(Error, Debug)]
+pub enum RewriteError {
+    #[error("error reading the binlog to rewrite")]
+    BinlogFileError(#[from] BinlogFileError),
+    #[error("error parsing event to rewrite")]
+    EventParseError(#[from] EventParseError),
+    #[error("error writing the rewritten binlog")]
+    BinlogWriteError(#[from] BinlogWriteError),
+    #[error("the binlog to rewrite has no events")]
+    Empty,
+    #[error("name {0:?} is too long for a binlog event")]
+    NameTooLong(String),
+}
+
+#[derive(Error, Debug)]
+pub enum SplitError {
+    #[error("error reading the binlog to split")]
+    BinlogFileError(#[from] BinlogFileError),
+    #[error("error parsing event to split")]
+    EventParseError(#[from] EventParseError),
+    #[error("error writing a piece of the split binlog")]
+    BinlogWriteError(#[from] BinlogWriteError),
+    #[error("error creating a piece of the split binlog")]
+    Io(#[from] std::io::Error),
+    #[error("the binlog has no FormatDescriptionEvent")]
+    NoFormatDescription,
+}
+
+#[derive(Error, Debug)]
+pub enum MergeError {
+    #[error("error reading the binlogs to merge")]
+    BinlogFileError(#[from] BinlogFileError),
+    #[error("error parsing event to merge")]
+    EventParseError(#[from] EventParseError),
+    #[error("error writing the merged binlog")]
+    BinlogWriteError(#[from] BinlogWriteError),
+    #[error("binlog {0} doesn't start with a FormatDescriptionEvent")]
+    NoFormatDescription(String),
+    #[error("binlog {0} has another event format than the first one merged")]
+    IncompatibleFormat(String),
+    #[error("there are no binlogs to merge")]
+    Empty,
+}
+
+#[derive(Error, Debug)]
+pub enum BuildError {
+    #[error("error applying DDL to the tables being built")]
+    SchemaError(#[from] SchemaError),
+    #[error("rows for table {0}.{1}, which no CREATE TABLE query has defined")]
+    UnknownTable(String, String),
+    #[error("column {column} has type {data_type}, which rows can't be built for")]
+    UnsupportedColumnType { column: String, data_type: String },
+    #[error("row of {actual} values for {table}, which has {expected} columns")]
+    ColumnCount { table: String, expected: usize, actual: usize },
+    #[error("value {value} doesn't fit column {column}")]
+    ValueMismatch { column: String, value: String },
+    #[error("error parsing a built event")]
+    EventParseError(#[from] EventParseError),
+    #[error("error encoding a built event")]
+    EncodeError(#[from] EncodeError),
+    #[error("error writing the built binlog")]
+    BinlogWriteError(#[from] BinlogWriteError),
+    #[error("I/O error writing the built binlog")]
+    Io(#[from] std::io::Error),
+}
+
+#[derive(Error, Debug, PartialEq)]
+pub enum GtidParseError {
+    #[error("bad server uuid {0:?}")]
+    BadUuid(String),
+    #[error("bad GTID interval {0:?}")]
+    BadInterval(String),
+    #[error("bad MariaDB GTID {0:?}, expected <domain>-<server>-<seq_no>")]
+    BadMariaDbGtid(String),
+}
+
+#[derive(Error, Debug, PartialEq)]
+pub enum PositionParseError {
+    #[error("expected <file>:<position>, got {0:?}")]
+    MissingPosition(String),
+    #[error("bad binlog position {0:?}")]
+    BadPosition(String),
+    #[error("bad GTID set in position")]
+    Gtid(#[from] GtidParseError),
+}
+
+#[derive(Error, Debug)]
+pub enum CheckpointError {
+    #[error("I/O error reading or writing checkpoint")]
+    Io(#[from] std::io::Error),
+    #[error("bad checkpoint contents")]
+    Parse(#[from] PositionParseError),
+}
+
+#[derive(Error, Debug)]
+pub enum ClientError {
+    #[error("I/O error talking to the server")]
+    Io(#[from] std::io::Error),
+    // e.g. 1236 (ER_MASTER_FATAL_ERROR_READING_BINLOG) when the binlog asked for is gone
+    #[error("server error {code}: {message}")]
+    Server { code: u16, sql_state: Option<String>, message: String },
+    #[error("malformed {0} packet from the server")]
+    BadPacket(&'static str),
+    #[error("the server sent a packet of more than {0} bytes")]
+    PacketTooLarge(usize),
+    #[error("unsupported authentication plugin {0:?}")]
+    UnsupportedAuthPlugin(String),
+    #[error("can't encrypt the password with the server's RSA public key: {0}")]
+    PublicKey(String),
+    #[error("bad replica uuid {0:?}")]
+    BadReplicaUuid(String),
+    #[error("unsupported binlog_checksum {0:?}")]
+    UnsupportedChecksum(String),
+    #[error("the server is MySQL, which can't start from the MariaDB GTID position {0}")]
+    MariaDbGtidOnMysql(String),
+    // from the mysql or mysql_async crate, for what has no counterpart here
+    #[error("MySQL driver error: {0}")]
+    Driver(String),
+    #[error("TLS was asked for but the server doesn't support it")]
+    TlsNotSupported,
+    #[error("TLS error: {0}")]
+    Tls(String),
+    #[error("binary logging is off on the server")]
+    BinlogDisabled,
 }
@@ -1,11 +1,25 @@
-use crate::errors::EventParseError;
-use std::io::{Read, Cursor};
+use crate::errors::{EncodeError, EventParseError};
+use std::io::{Read, Cursor, Seek, SeekFrom};
 use byteorder::{LittleEndian, ReadBytesExt};
+use serde::{Deserializer, Serializer};
+use serde_derive::{Deserialize, Serialize};
 use core::fmt;
+use std::borrow::Cow;
 use std::fmt::Debug;
+use std::sync::{Arc, Mutex, OnceLock};
+use crate::context::ParseContext;
+use crate::gtid::{GtidSet, MariaDbGtid, MariaDbGtidList};
+use crate::checksum::{crc32, has_checksum_alg, verify_crc32, ChecksumAlgorithm};
+use crate::encode::encode_event;
+use crate::options::ParseMode;
+use crate::payload::PayloadEvents;
+use crate::rows::{RowsEvent, RowsEventKind};
+use crate::table_map::TableMapEvent;
+use crate::pretty::PrettyEvent;
+use crate::utils::{read_full, read_lenenc_int, read_sized, read_utf8, utc_datetime, FieldContext};
 
 // https://dev.mysql.com/doc/internals/en/event-classes-and-types.html
-#[derive(Debug, Clone, Copy, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum TypeCode {
     UnknownEvent,
     StartEventV3,
@@ -43,10 +57,30 @@ pub enum TypeCode {
     GtidLogEvent,
     AnonymousGtidLogEvent,
     PreviousGtidsLogEvent,
+    TransactionContextEvent,
+    ViewChangeEvent,
+    XaPrepareLogEvent,
+    PartialUpdateRowsEvent,
+    TransactionPayloadEvent,
+    HeartbeatLogEventV2,
+    // MariaDB's own types, numbered from 160 so they never collide with MySQL's
+    // https://mariadb.com/kb/en/2-binlog-event-header/
+    AnnotateRowsEvent = 160,
+    BinlogCheckpointEvent,
+    MariaDbGtidEvent,
+    MariaDbGtidListEvent,
+    StartEncryptionEvent,
+    QueryCompressedEvent,
+    WriteRowsCompressedEventV1,
+    UpdateRowsCompressedEventV1,
+    DeleteRowsCompressedEventV1,
+    WriteRowsCompressedEvent,
+    UpdateRowsCompressedEvent,
+    DeleteRowsCompressedEvent,
 }
 
 impl TypeCode {
-    fn from_byte(b: u8) -> Self {
+    pub fn from_byte(b: u8) -> Self {
         match b {
             0 => TypeCode::UnknownEvent,
             1 => TypeCode::StartEventV3,
@@ -84,9 +118,127 @@ impl TypeCode {
             33 => TypeCode::GtidLogEvent,
             34 => TypeCode::AnonymousGtidLogEvent,
             35 => TypeCode::PreviousGtidsLogEvent,
+            36 => TypeCode::TransactionContextEvent,
+            37 => TypeCode::ViewChangeEvent,
+            38 => TypeCode::XaPrepareLogEvent,
+            39 => TypeCode::PartialUpdateRowsEvent,
+            40 => TypeCode::TransactionPayloadEvent,
+            41 => TypeCode::HeartbeatLogEventV2,
+            160 => TypeCode::AnnotateRowsEvent,
+            161 => TypeCode::BinlogCheckpointEvent,
+            162 => TypeCode::MariaDbGtidEvent,
+            163 => TypeCode::MariaDbGtidListEvent,
+            164 => TypeCode::StartEncryptionEvent,
+            165 => TypeCode::QueryCompressedEvent,
+            166 => TypeCode::WriteRowsCompressedEventV1,
+            167 => TypeCode::UpdateRowsCompressedEventV1,
+            168 => TypeCode::DeleteRowsCompressedEventV1,
+            169 => TypeCode::WriteRowsCompressedEvent,
+            170 => TypeCode::UpdateRowsCompressedEvent,
+            171 => TypeCode::DeleteRowsCompressedEvent,
             _ => TypeCode::UnknownEvent
         }
     }
+
+    pub fn to_byte(self) -> u8 {
+        self as u8
+    }
+}
+
+// common header flags
+// https://dev.mysql.com/doc/dev/mysql-server/latest/group__group__cs__binglog__event__header__flags.html
+// set on the FormatDescriptionEvent while the server has the file open, cleared when it's closed
+pub const LOG_EVENT_BINLOG_IN_USE_F: u16 = 0x1;
+pub const LOG_EVENT_THREAD_SPECIFIC_F: u16 = 0x4;
+pub const LOG_EVENT_SUPPRESS_USE_F: u16 = 0x8;
+pub const LOG_EVENT_ARTIFICIAL_F: u16 = 0x20;
+pub const LOG_EVENT_RELAY_LOG_F: u16 = 0x40;
+pub const LOG_EVENT_IGNORABLE_F: u16 = 0x80;
+
+// MariaDbGtidEvent flags
+pub const MARIADB_GTID_STANDALONE: u8 = 0x01;
+const MARIADB_GTID_GROUP_COMMIT_ID: u8 = 0x02;
+
+// https://dev.mysql.com/doc/internals/en/binary-log-versions.html
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct EventHeader {
+    pub timestamp: u32,
+    pub type_code: TypeCode,
+    pub server_id: u32,
+    pub event_length: u32,
+    pub next_position: u32,
+    pub flags: u16,
+}
+
+impl EventHeader {
+    pub const LEN: usize = 19;
+
+    pub fn parse(bytes: &[u8; EventHeader::LEN]) -> Self {
+        EventHeader {
+            timestamp: u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]),
+            type_code: TypeCode::from_byte(bytes[4]),
+            server_id: u32::from_le_bytes([bytes[5], bytes[6], bytes[7], bytes[8]]),
+            event_length: u32::from_le_bytes([bytes[9], bytes[10], bytes[11], bytes[12]]),
+            next_position: u32::from_le_bytes([bytes[13], bytes[14], bytes[15], bytes[16]]),
+            flags: u16::from_le_bytes([bytes[17], bytes[18]]),
+        }
+    }
+
+    pub fn to_bytes(&self) -> [u8; EventHeader::LEN] {
+        let mut bytes = [0u8; EventHeader::LEN];
+        bytes[0..4].copy_from_slice(&self.timestamp.to_le_bytes());
+        bytes[4] = self.type_code.to_byte();
+        bytes[5..9].copy_from_slice(&self.server_id.to_le_bytes());
+        bytes[9..13].copy_from_slice(&self.event_length.to_le_bytes());
+        bytes[13..17].copy_from_slice(&self.next_position.to_le_bytes());
+        bytes[17..19].copy_from_slice(&self.flags.to_le_bytes());
+        bytes
+    }
+
+    // The event length to trust for an event at `offset`. Lenient mode falls back to the
+    // distance to next_position when event_length itself is impossible.
+    pub fn checked_length(&self, offset: u64, mode: ParseMode) -> Result<u32, EventParseError> {
+        let valid = |len: u32| len as usize >= EventHeader::LEN && len <= EventHeader::MAX_EVENT_LENGTH;
+        let end = (offset as u32).wrapping_add(self.event_length);
+        match mode {
+            ParseMode::Strict => {
+                if !valid(self.event_length) {
+                    return Err(EventParseError::BadEventLength { offset, event_length: self.event_length });
+                }
+                if self.next_position != 0 && self.next_position != end {
+                    return Err(EventParseError::NextPositionMismatch {
+                        offset,
+                        expected: offset + u64::from(self.event_length),
+                        actual: self.next_position,
+                    });
+                }
+                Ok(self.event_length)
+            }
+            ParseMode::Lenient => {
+                if valid(self.event_length) {
+                    return Ok(self.event_length);
+                }
+                let distance = self.next_position.wrapping_sub(offset as u32);
+                if self.next_position != 0 && valid(distance) {
+                    Ok(distance)
+                } else {
+                    Err(EventParseError::BadEventLength { offset, event_length: self.event_length })
+                }
+            }
+        }
+    }
+
+    // events can't exceed max_allowed_packet, whose ceiling is 1GB
+    pub const MAX_EVENT_LENGTH: u32 = 1 << 30;
+
+    // whether this could be the header of an event starting at `offset`; next_position is 0
+    // for some artificial events and only 32 bits wide, so it's compared modulo 2^32
+    pub fn is_plausible_at(&self, offset: u64) -> bool {
+        self.type_code != TypeCode::UnknownEvent
+            && self.event_length as usize >= EventHeader::LEN
+            && self.event_length <= EventHeader::MAX_EVENT_LENGTH
+            && (self.next_position == 0 || self.next_position == (offset as u32).wrapping_add(self.event_length))
+    }
 }
 
 // https://dev.mysql.com/doc/internals/en/event-structure.html
@@ -97,26 +249,200 @@ pub struct Event {
     pub event_length: u32,
     next_position: u32,
     flags: u16,
-    data: Vec<u8>,
+    data: EventBody,
     offset: u64,
+    // trailing CRC32 bytes kept in the body but hidden from data()
+    checksum_len: usize,
+    diagnostics: Vec<EventParseError>,
+}
+
+// Where a deferred event body can be fetched from once someone asks for it.
+pub trait BodySource: Send + Sync {
+    fn read_body(&self, offset: u64, len: usize) -> std::io::Result<Vec<u8>>;
+}
+
+impl<I> BodySource for Mutex<I> where
+    I: Seek + Read + Send
+{
+    fn read_body(&self, offset: u64, len: usize) -> std::io::Result<Vec<u8>> {
+        let mut reader = self.lock()
+            .map_err(|_| std::io::Error::other("binlog reader lock poisoned"))?;
+        reader.seek(SeekFrom::Start(offset))?;
+        let mut body = vec![0u8; len];
+        reader.read_exact(&mut body)?;
+        Ok(body)
+    }
+}
+
+enum EventBody {
+    Loaded(Vec<u8>),
+    Deferred {
+        source: Arc<dyn BodySource>,
+        offset: u64,
+        len: usize,
+        // whether a CRC32 stored after the body is checked when it's read
+        verify_checksum: bool,
+        loaded: OnceLock<Vec<u8>>,
+    },
 }
 
+// https://dev.mysql.com/doc/dev/mysql-server/latest/classbinary__log_1_1Gtid__event.html
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GtidEvent {
+    pub flags: u8,
+    #[serde(with = "crate::serde_repr::uuid")]
+    pub sid: [u8; 16],
+    pub gno: i64,
+    pub last_committed: Option<i64>,
+    pub sequence_number: Option<i64>,
+    pub immediate_commit_timestamp: Option<u64>,
+    pub original_commit_timestamp: Option<u64>,
+    pub transaction_length: Option<u64>,
+}
+
+// server uuid and its [start, end) gno intervals
+pub type SidIntervals = ([u8; 16], Vec<(i64, i64)>);
+
+// a user variable's value type (STRING/REAL/INT/DECIMAL_RESULT), charset and raw bytes
+pub type UserVarValue = (u8, u32, Vec<u8>);
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum EventData {
+    StartEventV3 {
+        binlog_version: u16,
+        server_version: String,
+        create_timestamp: u32,
+    },
     FormatDescriptionEvent {
         binlog_version: u16,
         server_version: String,
         create_timestamp: u32,
-        common_header_len: u8
+        common_header_len: u8,
+        // indexed by type code - 1
+        post_header_lengths: Vec<u8>,
+    },
+    QueryEvent {
+        thread_id: u32,
+        exec_time: u32,
+        error_code: u16,
+        schema: String,
+        query: String,
     },
+    StopEvent,
+    RotateEvent {
+        position: u64,
+        next_file: String,
+    },
+    IntvarEvent {
+        kind: u8,
+        value: u64,
+    },
+    RandEvent {
+        seed1: u64,
+        seed2: u64,
+    },
+    UserVarEvent {
+        name: String,
+        // None for NULL; otherwise the value type (STRING/REAL/INT/DECIMAL_RESULT), charset and raw bytes
+        #[serde(with = "crate::serde_repr::user_var_value")]
+        value: Option<UserVarValue>,
+    },
+    XidEvent {
+        xid: u64,
+    },
+    AppendBlockEvent {
+        file_id: u32,
+        #[serde(with = "crate::serde_repr::hex_bytes")]
+        block: Vec<u8>,
+    },
+    BeginLoadQueryEvent {
+        file_id: u32,
+        #[serde(with = "crate::serde_repr::hex_bytes")]
+        block: Vec<u8>,
+    },
+    DeleteFileEvent {
+        file_id: u32,
+    },
+    ExecuteLoadQueryEvent {
+        thread_id: u32,
+        exec_time: u32,
+        error_code: u16,
+        schema: String,
+        query: String,
+        file_id: u32,
+        start_pos: u32,
+        end_pos: u32,
+        dup_handling: u8,
+    },
+    TableMapEvent(TableMapEvent),
+    WriteRowsEvent(RowsEvent),
+    UpdateRowsEvent(RowsEvent),
+    DeleteRowsEvent(RowsEvent),
+    IncidentEvent {
+        incident_type: u16,
+        message: String,
+    },
+    HeartbeatLogEvent {
+        log_ident: String,
+    },
+    IgnorableLogEvent,
+    RowsQueryLogEvent {
+        query: String,
+    },
+    GtidLogEvent(GtidEvent),
+    AnonymousGtidLogEvent(GtidEvent),
+    PreviousGtidsLogEvent {
+        #[serde(with = "crate::serde_repr::gtid_set")]
+        gtids: GtidSet,
+    },
+    XaPrepareLogEvent {
+        one_phase: bool,
+        format_id: i32,
+        #[serde(with = "crate::serde_repr::hex_bytes")]
+        gtrid: Vec<u8>,
+        #[serde(with = "crate::serde_repr::hex_bytes")]
+        bqual: Vec<u8>,
+    },
+    TransactionPayloadEvent {
+        compression_type: u64,
+        uncompressed_size: u64,
+        #[serde(with = "crate::serde_repr::hex_bytes")]
+        payload: Vec<u8>,
+    },
+    // the statement behind the rows events that follow, with binlog_annotate_row_events
+    AnnotateRowsEvent {
+        query: String,
+    },
+    // the oldest binlog still needed for crash recovery
+    BinlogCheckpointEvent {
+        file: String,
+    },
+    // The server id of the GTID is the event's. Unless `flags` has MARIADB_GTID_STANDALONE
+    // the GTID also opens the transaction, in place of a BEGIN.
+    MariaDbGtidEvent {
+        domain_id: u32,
+        seq_no: u64,
+        flags: u8,
+        // transactions sharing one group committed together and may be applied in parallel
+        commit_id: Option<u64>,
+    },
+    // the GTID state at the start of a binlog, MariaDB's Previous_gtids
+    MariaDbGtidListEvent {
+        gtids: MariaDbGtidList,
+    },
+    // known but not (yet) decoded types; the raw body is still available from Event::data()
+    Unsupported(TypeCode),
+    // a body lenient mode couldn't decode
+    Unparsed { type_code: TypeCode, reason: String },
 }
 
 impl Debug for Event {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(f, "Event {{ timestamp: {:?}, type_code: {:?}, server_id: {:?}, data_len: {:?}, offset: {:?}, flags: {:?}, event_length: {:?} }}",
                self.timestamp(),
-               self.timestamp(),
+               self.type_code(),
                self.server_id,
-               self.data.len(),
+               self.body_len(),
                self.offset(),
                self.flags(),
                self.event_length()
@@ -124,27 +450,106 @@ impl Debug for Event {
     }
 }
 
+// one line: `#4 QueryEvent 2024-01-31 09:05:01 UTC, server 1, 42 bytes, next 46`
+impl fmt::Display for Event {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "#{} {:?} {} UTC, server {}, {} bytes, next {}",
+               self.offset(),
+               self.type_code(),
+               utc_datetime(self.timestamp()),
+               self.server_id(),
+               self.event_length(),
+               self.next_position()
+        )
+    }
+}
+
+// An event serializes as its header, offset, body without the checksum, and checksum; the body
+// is kept raw since decoding rows needs the table maps before it (see Event::parsed).
+#[derive(Serialize, Deserialize)]
+#[serde(rename = "Event")]
+struct EventRepr<'a> {
+    #[serde(flatten)]
+    header: EventHeader,
+    offset: u64,
+    #[serde(with = "crate::serde_repr::hex_bytes")]
+    data: Cow<'a, [u8]>,
+    checksum: Option<u32>,
+}
+
+impl serde::Serialize for Event {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let data = self.try_data().map_err(serde::ser::Error::custom)?;
+        let repr = EventRepr { header: self.header(), offset: self.offset, data: Cow::Borrowed(data), checksum: self.checksum() };
+        serde::Serialize::serialize(&repr, serializer)
+    }
+}
+
+impl<'de> serde::Deserialize<'de> for Event {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let EventRepr { header, offset, data, checksum } = serde::Deserialize::deserialize(deserializer)?;
+        let mut data = data.into_owned();
+        let checksum_len = match checksum {
+            Some(checksum) => {
+                data.extend_from_slice(&checksum.to_le_bytes());
+                4
+            }
+            None => 0,
+        };
+        Ok(Event {
+            timestamp: header.timestamp,
+            type_code: header.type_code,
+            server_id: header.server_id,
+            event_length: header.event_length,
+            next_position: header.next_position,
+            flags: header.flags,
+            data: EventBody::Loaded(data),
+            offset,
+            checksum_len,
+            diagnostics: vec![],
+        })
+    }
+}
+
 impl Event {
     pub fn parse<R: Read>(reader: &mut R, offset: u64) -> Result<Self, EventParseError> {
+        Self::parse_with_mode(reader, offset, ParseMode::Strict)
+    }
 
-        // https://dev.mysql.com/doc/internals/en/binary-log-versions.html
-        let mut event_header = [0u8; 19];
-        match reader.read_exact(&mut event_header) {
-            Ok(_) => {}
-            Err(e) => { return Err(e.into()) }
+    pub fn parse_with_mode<R: Read>(reader: &mut R, offset: u64, mode: ParseMode) -> Result<Self, EventParseError> {
+
+        let mut event_header = [0u8; EventHeader::LEN];
+        let read = read_full(reader, &mut event_header)?;
+        if read < EventHeader::LEN {
+            return Err(EventParseError::TruncatedEvent {
+                offset,
+                expected_len: EventHeader::LEN as u32,
+                available: read as u64,
+            });
         }
 
-        let mut cursor = Cursor::new(event_header);
-        let timestamp = cursor.read_u32::<LittleEndian>().unwrap();
-        let type_code = TypeCode::from_byte(cursor.read_u8().unwrap());
-        let server_id = cursor.read_u32::<LittleEndian>().unwrap();
-        let event_length = cursor.read_u32::<LittleEndian>().unwrap();
-        let next_position = cursor.read_u32::<LittleEndian>().unwrap();
-        let flags = cursor.read_u16::<LittleEndian>().unwrap();
-        let data_length: usize = (event_length - 19) as usize;
+        let header = EventHeader::parse(&event_header);
+        let EventHeader { timestamp, type_code, server_id, next_position, flags, .. } = header;
+        let event_length = header.checked_length(offset, mode)?;
+        let mut diagnostics = vec![];
+        if type_code == TypeCode::UnknownEvent {
+            let error = EventParseError::UnknownEventType { offset, type_code: event_header[4] };
+            match mode {
+                ParseMode::Strict => return Err(error),
+                ParseMode::Lenient => diagnostics.push(error),
+            }
+        }
+        let data_length = event_length as usize - EventHeader::LEN;
 
         let mut data = vec![0u8; data_length];
-        reader.read_exact(&mut data).unwrap();
+        let read = read_full(reader, &mut data)?;
+        if read < data_length {
+            return Err(EventParseError::TruncatedEvent {
+                offset,
+                expected_len: event_length,
+                available: (EventHeader::LEN + read) as u64,
+            });
+        }
 
         Ok(Event {
             timestamp,
@@ -153,11 +558,35 @@ impl Event {
             event_length,
             next_position,
             flags,
-            data,
-            offset
+            data: EventBody::Loaded(data),
+            offset,
+            checksum_len: 0,
+            diagnostics,
         })
     }
 
+    // an event whose body stays in `source` until data() is first called
+    pub(crate) fn deferred(header: EventHeader, offset: u64, source: Arc<dyn BodySource>, verify_checksum: bool) -> Self {
+        Event {
+            timestamp: header.timestamp,
+            type_code: header.type_code,
+            server_id: header.server_id,
+            event_length: header.event_length,
+            next_position: header.next_position,
+            flags: header.flags,
+            data: EventBody::Deferred {
+                source,
+                offset: offset + EventHeader::LEN as u64,
+                len: header.event_length as usize - EventHeader::LEN,
+                verify_checksum,
+                loaded: OnceLock::new(),
+            },
+            offset,
+            checksum_len: 0,
+            diagnostics: vec![],
+        }
+    }
+
     pub fn parse_event_data_by_type_code(type_code: TypeCode, data: &[u8]) -> Result<Option<EventData>, EventParseError> {
 
         let mut cursor = Cursor::new(data);
@@ -165,31 +594,321 @@ impl Event {
         // https://dev.mysql.com/doc/internals/en/event-data-for-specific-event-types.html
         match type_code {
             TypeCode::FormatDescriptionEvent => {
-                let binlog_version = cursor.read_u16::<LittleEndian>().unwrap();
-                let server_version_beg = cursor.position() as usize;
-                let server_version_end = server_version_beg
-                    + data[server_version_beg..(server_version_beg + 50)]
-                    .iter()
-                    .position(|&u| u == 0)
-                    .unwrap();
-                let server_version = std::str::from_utf8(&data[server_version_beg..server_version_end]).unwrap().to_owned();
-                cursor.set_position(cursor.position() + 50);
-                let create_timestamp = cursor.read_u32::<LittleEndian>().unwrap();
-                let common_header_len = cursor.read_u8().unwrap();
-                let event_type_header_len = &data[cursor.position() as usize..];
-                cursor.set_position(cursor.position() as u64 + event_type_header_len.len() as u64);
+                // https://dev.mysql.com/doc/internals/en/format-description-event.html
+                let binlog_version = cursor.read_u16::<LittleEndian>().field("binlog_version")?;
+                let server_version = read_fixed_string(&mut cursor, 50, "server_version")?;
+                let create_timestamp = cursor.read_u32::<LittleEndian>().field("create_timestamp")?;
+                let common_header_len = cursor.read_u8().field("common_header_len")?;
+                // one length per type code, then the checksum algorithm byte on 5.6.1+; `data` is
+                // expected without the trailing CRC32, as the readers return it
+                let mut post_header_lengths = data[cursor.position() as usize..].to_vec();
+                if has_checksum_alg(server_version.as_bytes()) {
+                    post_header_lengths.pop();
+                }
 
                 Ok(Some(EventData::FormatDescriptionEvent {
                     binlog_version,
                     server_version,
                     create_timestamp,
                     common_header_len,
+                    post_header_lengths,
+                }))
+            }
+            TypeCode::QueryEvent => {
+                // https://dev.mysql.com/doc/internals/en/query-event.html
+                let thread_id = cursor.read_u32::<LittleEndian>().field("thread_id")?;
+                let exec_time = cursor.read_u32::<LittleEndian>().field("exec_time")?;
+                let schema_len = cursor.read_u8().field("schema_len")? as usize;
+                let error_code = cursor.read_u16::<LittleEndian>().field("error_code")?;
+                let status_vars_len = cursor.read_u16::<LittleEndian>().field("status_vars_len")? as usize;
+                let schema_beg = cursor.position() as usize + status_vars_len;
+                let schema_end = schema_beg + schema_len;
+                // skip the NUL terminating the schema name
+                let query_beg = schema_end + 1;
+                if query_beg > data.len() {
+                    return Err(EventParseError::BadLength {
+                        field: "schema",
+                        len: schema_len as u64,
+                        available: data.len().saturating_sub(schema_beg) as u64,
+                    });
+                }
+                let schema = String::from_utf8_lossy(&data[schema_beg..schema_end]).into_owned();
+                let query = String::from_utf8_lossy(&data[query_beg..]).into_owned();
+
+                Ok(Some(EventData::QueryEvent {
+                    thread_id,
+                    exec_time,
+                    error_code,
+                    schema,
+                    query,
+                }))
+            }
+            TypeCode::StartEventV3 => {
+                // https://dev.mysql.com/doc/internals/en/start-event-v3.html
+                let binlog_version = cursor.read_u16::<LittleEndian>().field("binlog_version")?;
+                let server_version = read_fixed_string(&mut cursor, 50, "server_version")?;
+                let create_timestamp = cursor.read_u32::<LittleEndian>().field("create_timestamp")?;
+                Ok(Some(EventData::StartEventV3 {
+                    binlog_version,
+                    server_version,
+                    create_timestamp,
+                }))
+            }
+            TypeCode::StopEvent => Ok(Some(EventData::StopEvent)),
+            TypeCode::RotateEvent => {
+                // https://dev.mysql.com/doc/internals/en/rotate-event.html
+                let position = cursor.read_u64::<LittleEndian>().field("position")?;
+                let next_file = read_utf8(data[cursor.position() as usize..].to_vec(), "next_file")?;
+                Ok(Some(EventData::RotateEvent { position, next_file }))
+            }
+            TypeCode::IntvarEvent => {
+                // https://dev.mysql.com/doc/internals/en/intvar-event.html
+                let kind = cursor.read_u8().field("kind")?;
+                let value = cursor.read_u64::<LittleEndian>().field("value")?;
+                Ok(Some(EventData::IntvarEvent { kind, value }))
+            }
+            TypeCode::RandEvent => {
+                // https://dev.mysql.com/doc/internals/en/rand-event.html
+                let seed1 = cursor.read_u64::<LittleEndian>().field("seed1")?;
+                let seed2 = cursor.read_u64::<LittleEndian>().field("seed2")?;
+                Ok(Some(EventData::RandEvent { seed1, seed2 }))
+            }
+            TypeCode::UserVarEvent => {
+                // https://dev.mysql.com/doc/internals/en/user-var-event.html
+                let name_len = cursor.read_u32::<LittleEndian>().field("name_len")? as usize;
+                let name = String::from_utf8_lossy(&read_sized(&mut cursor, name_len, "name")?).into_owned();
+                let is_null = cursor.read_u8().field("is_null")? != 0;
+                let value = if is_null {
+                    None
+                } else {
+                    let value_type = cursor.read_u8().field("value_type")?;
+                    let charset = cursor.read_u32::<LittleEndian>().field("charset")?;
+                    let value_len = cursor.read_u32::<LittleEndian>().field("value_len")? as usize;
+                    Some((value_type, charset, read_sized(&mut cursor, value_len, "value")?))
+                };
+                Ok(Some(EventData::UserVarEvent { name, value }))
+            }
+            TypeCode::XidEvent => {
+                // https://dev.mysql.com/doc/internals/en/xid-event.html
+                let xid = cursor.read_u64::<LittleEndian>().field("xid")?;
+                Ok(Some(EventData::XidEvent { xid }))
+            }
+            TypeCode::AppendBlockEvent | TypeCode::BeginLoadQueryEvent => {
+                // https://dev.mysql.com/doc/internals/en/append-block-event.html
+                let file_id = cursor.read_u32::<LittleEndian>().field("file_id")?;
+                let block = data[cursor.position() as usize..].to_vec();
+                if type_code == TypeCode::AppendBlockEvent {
+                    Ok(Some(EventData::AppendBlockEvent { file_id, block }))
+                } else {
+                    Ok(Some(EventData::BeginLoadQueryEvent { file_id, block }))
+                }
+            }
+            TypeCode::DeleteFileEvent => {
+                let file_id = cursor.read_u32::<LittleEndian>().field("file_id")?;
+                Ok(Some(EventData::DeleteFileEvent { file_id }))
+            }
+            TypeCode::ExecuteLoadQueryEvent => {
+                // https://dev.mysql.com/doc/internals/en/execute-load-query-event.html
+                let thread_id = cursor.read_u32::<LittleEndian>().field("thread_id")?;
+                let exec_time = cursor.read_u32::<LittleEndian>().field("exec_time")?;
+                let schema_len = cursor.read_u8().field("schema_len")? as usize;
+                let error_code = cursor.read_u16::<LittleEndian>().field("error_code")?;
+                let status_vars_len = cursor.read_u16::<LittleEndian>().field("status_vars_len")? as u64;
+                let file_id = cursor.read_u32::<LittleEndian>().field("file_id")?;
+                let start_pos = cursor.read_u32::<LittleEndian>().field("start_pos")?;
+                let end_pos = cursor.read_u32::<LittleEndian>().field("end_pos")?;
+                let dup_handling = cursor.read_u8().field("dup_handling")?;
+                cursor.set_position(cursor.position() + status_vars_len);
+                let schema = String::from_utf8_lossy(&read_sized(&mut cursor, schema_len, "schema")?).into_owned();
+                cursor.read_u8().field("schema")?;
+                let query = String::from_utf8_lossy(&data[cursor.position() as usize..]).into_owned();
+                Ok(Some(EventData::ExecuteLoadQueryEvent {
+                    thread_id,
+                    exec_time,
+                    error_code,
+                    schema,
+                    query,
+                    file_id,
+                    start_pos,
+                    end_pos,
+                    dup_handling,
                 }))
             }
+            TypeCode::TableMapEvent => Ok(Some(EventData::TableMapEvent(TableMapEvent::parse(data)?))),
+            TypeCode::IncidentEvent => {
+                // https://dev.mysql.com/doc/internals/en/incident-event.html
+                let incident_type = cursor.read_u16::<LittleEndian>().field("incident_type")?;
+                let message_len = cursor.read_u8().field("message_len")? as usize;
+                let message = String::from_utf8_lossy(&read_sized(&mut cursor, message_len, "message")?).into_owned();
+                Ok(Some(EventData::IncidentEvent { incident_type, message }))
+            }
+            TypeCode::HeartbeatLogEvent => {
+                let log_ident = read_utf8(data.to_vec(), "log_ident")?;
+                Ok(Some(EventData::HeartbeatLogEvent { log_ident }))
+            }
+            TypeCode::IgnorableLogEvent => Ok(Some(EventData::IgnorableLogEvent)),
+            TypeCode::RowsQueryLogEvent => {
+                // https://dev.mysql.com/doc/internals/en/rows-query-event.html
+                // the length byte is truncated for queries over 255 bytes, so the rest of the body is used
+                cursor.read_u8().field("query_len")?;
+                let query = String::from_utf8_lossy(&data[cursor.position() as usize..]).into_owned();
+                Ok(Some(EventData::RowsQueryLogEvent { query }))
+            }
+            TypeCode::GtidLogEvent => Ok(Some(EventData::GtidLogEvent(parse_gtid_event(&mut cursor)?))),
+            TypeCode::AnonymousGtidLogEvent => Ok(Some(EventData::AnonymousGtidLogEvent(parse_gtid_event(&mut cursor)?))),
+            TypeCode::PreviousGtidsLogEvent => {
+                // https://dev.mysql.com/doc/dev/mysql-server/latest/classbinary__log_1_1Previous__gtids__event.html
+                let sid_count = cursor.read_u64::<LittleEndian>().field("sid_count")?;
+                let mut gtids = GtidSet::new();
+                for _ in 0..sid_count {
+                    let mut sid = [0u8; 16];
+                    cursor.read_exact(&mut sid).field("sid")?;
+                    let interval_count = cursor.read_u64::<LittleEndian>().field("interval_count")?;
+                    for _ in 0..interval_count {
+                        let start = cursor.read_i64::<LittleEndian>().field("start")?;
+                        let end = cursor.read_i64::<LittleEndian>().field("end")?;
+                        gtids.add_interval(sid, start, end);
+                    }
+                }
+                Ok(Some(EventData::PreviousGtidsLogEvent { gtids }))
+            }
+            TypeCode::XaPrepareLogEvent => {
+                // https://dev.mysql.com/doc/dev/mysql-server/latest/classbinary__log_1_1XA__prepare__event.html
+                let one_phase = cursor.read_u8().field("one_phase")? != 0;
+                let format_id = cursor.read_i32::<LittleEndian>().field("format_id")?;
+                let gtrid_len = cursor.read_i32::<LittleEndian>().field("gtrid_len")? as usize;
+                let bqual_len = cursor.read_i32::<LittleEndian>().field("bqual_len")? as usize;
+                let gtrid = read_sized(&mut cursor, gtrid_len, "gtrid")?;
+                let bqual = read_sized(&mut cursor, bqual_len, "bqual")?;
+                Ok(Some(EventData::XaPrepareLogEvent { one_phase, format_id, gtrid, bqual }))
+            }
+            TypeCode::TransactionPayloadEvent => {
+                // https://dev.mysql.com/doc/dev/mysql-server/latest/classbinary__log_1_1Transaction__payload__event.html
+                let mut compression_type = 0;
+                let mut uncompressed_size = 0;
+                loop {
+                    let field_type = read_lenenc_int(&mut cursor).field("field_type")?;
+                    if field_type == 0 {
+                        break;
+                    }
+                    let field_len = read_lenenc_int(&mut cursor).field("field_len")? as usize;
+                    let mut field = &read_sized(&mut cursor, field_len, "field")?[..];
+                    match field_type {
+                        2 => compression_type = read_lenenc_int(&mut field).field("compression_type")?,
+                        3 => uncompressed_size = read_lenenc_int(&mut field).field("uncompressed_size")?,
+                        // payload size is implied by the event length
+                        _ => {}
+                    }
+                }
+                let payload = data[cursor.position() as usize..].to_vec();
+                Ok(Some(EventData::TransactionPayloadEvent { compression_type, uncompressed_size, payload }))
+            }
+            TypeCode::AnnotateRowsEvent => {
+                Ok(Some(EventData::AnnotateRowsEvent { query: String::from_utf8_lossy(data).into_owned() }))
+            }
+            TypeCode::BinlogCheckpointEvent => {
+                let file_len = cursor.read_u32::<LittleEndian>().field("file_len")? as usize;
+                let file = read_utf8(read_sized(&mut cursor, file_len, "file")?, "file")?;
+                Ok(Some(EventData::BinlogCheckpointEvent { file }))
+            }
+            TypeCode::MariaDbGtidEvent => {
+                // https://mariadb.com/kb/en/gtid_event/
+                let seq_no = cursor.read_u64::<LittleEndian>().field("seq_no")?;
+                let domain_id = cursor.read_u32::<LittleEndian>().field("domain_id")?;
+                let flags = cursor.read_u8().field("flags")?;
+                let commit_id = if flags & MARIADB_GTID_GROUP_COMMIT_ID != 0 {
+                    Some(cursor.read_u64::<LittleEndian>().field("commit_id")?)
+                } else {
+                    None
+                };
+                Ok(Some(EventData::MariaDbGtidEvent { domain_id, seq_no, flags, commit_id }))
+            }
+            TypeCode::MariaDbGtidListEvent => {
+                // https://mariadb.com/kb/en/gtid_list_event/; the top 4 bits of the count are flags
+                let count = cursor.read_u32::<LittleEndian>().field("gtid_count")? & 0x0fff_ffff;
+                let mut gtids = MariaDbGtidList::new();
+                for _ in 0..count {
+                    gtids.push(MariaDbGtid {
+                        domain_id: cursor.read_u32::<LittleEndian>().field("domain_id")?,
+                        server_id: cursor.read_u32::<LittleEndian>().field("server_id")?,
+                        seq_no: cursor.read_u64::<LittleEndian>().field("seq_no")?,
+                    });
+                }
+                Ok(Some(EventData::MariaDbGtidListEvent { gtids }))
+            }
             _ => { Ok(None) }
         }
     }
 
+    // Decodes the body into its typed form. Rows events are decoded against the table maps the
+    // context has seen so far, so events must be fed through the same context in binlog order.
+    // With ParseMode::Lenient in the context's options a body that can't be decoded comes back
+    // as EventData::Unparsed.
+    pub fn parsed(&self, ctx: &mut ParseContext) -> Result<EventData, EventParseError> {
+        let result = self.parse_data(ctx).map_err(|e| EventParseError::InEvent {
+            offset: self.offset,
+            type_code: self.type_code,
+            event_length: self.event_length,
+            source: Box::new(e),
+        });
+        match result {
+            Err(e) if ctx.options().mode == ParseMode::Lenient => Ok(EventData::Unparsed {
+                type_code: self.type_code,
+                reason: e.to_string(),
+            }),
+            result => result,
+        }
+    }
+
+    fn parse_data(&self, ctx: &mut ParseContext) -> Result<EventData, EventParseError> {
+        let data = self.try_data()?;
+        let event_data = match RowsEventKind::from_type_code(self.type_code) {
+            Some(kind) => {
+                let table_id = peek_table_id(data, ctx.table_id_len(self.type_code))?;
+                let rows = ctx.parse_rows(self.type_code, table_id, data)?;
+                match kind {
+                    RowsEventKind::Write => EventData::WriteRowsEvent(rows),
+                    RowsEventKind::Update => EventData::UpdateRowsEvent(rows),
+                    RowsEventKind::Delete => EventData::DeleteRowsEvent(rows),
+                }
+            }
+            None if self.type_code == TypeCode::TableMapEvent => {
+                let table_id_len = ctx.table_id_len(TypeCode::TableMapEvent);
+                let mut table_map = TableMapEvent::parse_with_table_id_len(data, table_id_len)?;
+                // given with the names the schema tracker knows, like the copy the rows are read with
+                if let Some(schema) = ctx.schema() {
+                    table_map.resolve_with(schema);
+                }
+                EventData::TableMapEvent(table_map)
+            }
+            None => match Event::parse_event_data_by_type_code(self.type_code, data)? {
+                Some(event_data) => event_data,
+                None => EventData::Unsupported(self.type_code),
+            },
+        };
+        ctx.observe(&event_data);
+        Ok(event_data)
+    }
+
+    // the events compressed inside a TransactionPayloadEvent; zstd payloads need the `zstd` feature
+    pub fn payload_events(&self) -> Result<PayloadEvents, EventParseError> {
+        match Event::parse_event_data_by_type_code(self.type_code, self.try_data()?)? {
+            Some(EventData::TransactionPayloadEvent { compression_type, payload, .. }) => PayloadEvents::new(compression_type, payload),
+            _ => Err(EventParseError::UnexpectedEventType(self.type_code)),
+        }
+    }
+
+    pub fn header(&self) -> EventHeader {
+        EventHeader {
+            timestamp: self.timestamp,
+            type_code: self.type_code,
+            server_id: self.server_id,
+            event_length: self.event_length,
+            next_position: self.next_position,
+            flags: self.flags,
+        }
+    }
+
     pub fn type_code(&self) -> TypeCode {
         self.type_code
     }
@@ -198,12 +917,118 @@ impl Event {
         self.timestamp
     }
 
+    pub fn server_id(&self) -> u32 {
+        self.server_id
+    }
+
     pub fn next_position(&self) -> u64 {
         u64::from(self.next_position)
     }
 
-    pub fn data(&self) -> &Vec<u8> {
-        &self.data
+    // Panics if a deferred body can't be read or fails its checksum; use try_data() for lazily
+    // loaded events.
+    pub fn data(&self) -> &[u8] {
+        self.try_data().expect("failed to read deferred event body")
+    }
+
+    pub fn try_data(&self) -> Result<&[u8], EventParseError> {
+        let body = self.raw_body()?;
+        Ok(&body[..body.len().saturating_sub(self.checksum_len)])
+    }
+
+    // the body as stored, including the trailing checksum when there is one
+    pub fn raw_body(&self) -> Result<&[u8], EventParseError> {
+        match &self.data {
+            EventBody::Loaded(data) => Ok(data),
+            EventBody::Deferred { source, offset, len, verify_checksum, loaded } => {
+                if let Some(data) = loaded.get() {
+                    return Ok(data);
+                }
+                let data = source.read_body(*offset, *len)?;
+                if *verify_checksum && self.checksum_len == ChecksumAlgorithm::CRC32_LEN {
+                    verify_crc32(self.offset, &self.header().to_bytes(), &data)?;
+                }
+                Ok(loaded.get_or_init(|| data))
+            }
+        }
+    }
+
+    // a multi-line description of the event and its parsed `data`, for debugging
+    pub fn pretty<'a>(&'a self, data: &'a EventData) -> PrettyEvent<'a> {
+        PrettyEvent::new(self, data)
+    }
+
+    // the event as stored: header, body and checksum
+    pub fn to_bytes(&self) -> Result<Vec<u8>, EventParseError> {
+        let mut bytes = self.header().to_bytes().to_vec();
+        bytes.extend_from_slice(self.raw_body()?);
+        Ok(bytes)
+    }
+
+    // Replaces the body, with event_length, next_position and the checksum, when the event has
+    // one, recomputed to match, so to_bytes() and BinlogWriter give a valid event. An artificial
+    // event's next_position of 0 stays 0.
+    pub fn set_data(&mut self, data: Vec<u8>) {
+        let mut body = data;
+        self.event_length = (EventHeader::LEN + body.len() + self.checksum_len) as u32;
+        if self.next_position != 0 {
+            self.next_position = (self.offset as u32).wrapping_add(self.event_length);
+        }
+        if self.checksum_len == ChecksumAlgorithm::CRC32_LEN {
+            let mut header = self.header();
+            if self.type_code == TypeCode::FormatDescriptionEvent {
+                // checksummed as if the file were closed, see ChecksumVerifier::check
+                header.flags &= !LOG_EVENT_BINLOG_IN_USE_F;
+            }
+            let checksum = crc32(&header.to_bytes(), &body);
+            body.extend_from_slice(&checksum.to_le_bytes());
+        }
+        self.data = EventBody::Loaded(body);
+    }
+
+    // The event moved to `offset`, its length, next position and checksum recomputed from the
+    // body; see encode::encode_event. At its own offset, that's to_bytes().
+    pub fn encode(&self, offset: u64) -> Result<Vec<u8>, EncodeError> {
+        encode_event(self.header(), self.try_data()?, offset, self.checksum_len == ChecksumAlgorithm::CRC32_LEN)
+    }
+
+    pub fn is_loaded(&self) -> bool {
+        match &self.data {
+            EventBody::Loaded(_) => true,
+            EventBody::Deferred { loaded, .. } => loaded.get().is_some(),
+        }
+    }
+
+    pub fn body_offset(&self) -> u64 {
+        self.offset + EventHeader::LEN as u64
+    }
+
+    // length of data(), i.e. without the header and checksum
+    pub fn body_len(&self) -> usize {
+        (self.event_length as usize).saturating_sub(EventHeader::LEN + self.checksum_len)
+    }
+
+    // the stored CRC32 when binlog_checksum is on; None without one, or if a deferred body can't be read
+    pub fn checksum(&self) -> Option<u32> {
+        if self.checksum_len != 4 {
+            return None;
+        }
+        let body = self.raw_body().ok()?;
+        let checksum = body.get(body.len().checked_sub(4)?..)?;
+        Some(u32::from_le_bytes([checksum[0], checksum[1], checksum[2], checksum[3]]))
+    }
+
+    pub(crate) fn set_checksum_len(&mut self, len: usize) {
+        self.checksum_len = len;
+    }
+
+    // what lenient mode let through instead of failing on, e.g. a checksum mismatch
+    pub fn diagnostics(&self) -> &[EventParseError] {
+        &self.diagnostics
+    }
+
+    pub(crate) fn add_diagnostic(&mut self, error: EventParseError) {
+        self.diagnostics.push(error);
     }
 
     pub fn flags(&self) -> u16 {
@@ -214,6 +1039,12 @@ impl Event {
         self.event_length
     }
 
+    // only meaningful on a FormatDescriptionEvent: the file was still open for writing (or the
+    // server crashed) when this was read
+    pub fn is_binlog_in_use(&self) -> bool {
+        self.type_code == TypeCode::FormatDescriptionEvent && self.flags & LOG_EVENT_BINLOG_IN_USE_F != 0
+    }
+
     pub fn offset(&self) -> u64 {
         self.offset
     }
@@ -223,22 +1054,480 @@ impl Event {
 
 
 
+fn peek_table_id(data: &[u8], table_id_len: usize) -> Result<u64, EventParseError> {
+    (&data[..data.len().min(table_id_len)]).read_uint::<LittleEndian>(table_id_len).field("table_id")
+}
+
+fn read_fixed_string<R: Read>(reader: &mut R, len: usize, field: &'static str) -> Result<String, EventParseError> {
+    let bytes = read_sized(reader, len, field)?;
+    let end = bytes.iter().position(|&b| b == 0).unwrap_or(bytes.len());
+    Ok(String::from_utf8_lossy(&bytes[..end]).into_owned())
+}
+
+fn parse_gtid_event(cursor: &mut Cursor<&[u8]>) -> Result<GtidEvent, EventParseError> {
+    let flags = cursor.read_u8().field("flags")?;
+    let mut sid = [0u8; 16];
+    cursor.read_exact(&mut sid).field("sid")?;
+    let gno = cursor.read_i64::<LittleEndian>().field("gno")?;
+    let mut gtid = GtidEvent {
+        flags,
+        sid,
+        gno,
+        last_committed: None,
+        sequence_number: None,
+        immediate_commit_timestamp: None,
+        original_commit_timestamp: None,
+        transaction_length: None,
+    };
+    let remaining = |cursor: &Cursor<&[u8]>| cursor.get_ref().len() as u64 - cursor.position().min(cursor.get_ref().len() as u64);
+
+    // logical clock, 5.7+; lt_type is always 2
+    if remaining(cursor) >= 17 {
+        cursor.read_u8().field("lt_type")?;
+        gtid.last_committed = Some(cursor.read_i64::<LittleEndian>().field("last_committed")?);
+        gtid.sequence_number = Some(cursor.read_i64::<LittleEndian>().field("sequence_number")?);
+    }
+    // commit timestamps, 8.0.1+; the top bit says whether the original timestamp follows
+    if remaining(cursor) >= 7 {
+        let immediate = cursor.read_uint::<LittleEndian>(7).field("immediate_commit_timestamp")?;
+        let has_original = immediate & (1 << 55) != 0;
+        let immediate = immediate & !(1 << 55);
+        gtid.immediate_commit_timestamp = Some(immediate);
+        gtid.original_commit_timestamp = Some(if has_original { cursor.read_uint::<LittleEndian>(7).field("original_commit_timestamp")? } else { immediate });
+    }
+    // transaction length, 8.0.2+
+    if remaining(cursor) >= 1 {
+        gtid.transaction_length = Some(read_lenenc_int(cursor).field("transaction_length")?);
+    }
+    Ok(gtid)
+}
+
 #[cfg(test)]
-mod tests {
-    use crate::event::{Event, TypeCode};
+pub(crate) mod tests {
+    use crate::context::ParseContext;
+    use crate::errors::EventParseError;
+    use crate::event::{Event, EventData, TypeCode};
+    use crate::gtid::MariaDbGtid;
+    use crate::options::{ParseMode, ParseOptions};
+    use crate::value::Value;
     use std::fs::File;
+    use std::io::{Seek, SeekFrom};
+
+    // a hand-written event: a header from server id 1, then `body` with no checksum
+    pub(crate) fn event_bytes(type_code: u8, body: &[u8]) -> Vec<u8> {
+        timed_event_bytes(0, type_code, body, 0)
+    }
+
+    pub(crate) fn timed_event_bytes(timestamp: u32, type_code: u8, body: &[u8], next_position: u32) -> Vec<u8> {
+        let mut bytes = timestamp.to_le_bytes().to_vec();
+        bytes.extend_from_slice(&[type_code, 1, 0, 0, 0]);
+        bytes.extend_from_slice(&(19 + body.len() as u32).to_le_bytes());
+        bytes.extend_from_slice(&next_position.to_le_bytes());
+        bytes.extend_from_slice(&[0, 0]);
+        bytes.extend_from_slice(body);
+        bytes
+    }
+
+    // each (type code, body) as an event that happened at `timestamp`, parsed at offset 4
+    pub(crate) fn parse_events(timestamp: u32, events: &[(u8, Vec<u8>)]) -> Vec<Event> {
+        events.iter()
+            .map(|(type_code, body)| Event::parse(&mut &timed_event_bytes(timestamp, *type_code, body, 0)[..], 4).unwrap())
+            .collect()
+    }
+
+    // A TableMapEvent body for test.users, table id 108: a type per column, their metadata and
+    // nullable bitmap, then optional metadata like column names (binlog_row_metadata=FULL).
+    pub(crate) fn users_table_map(types: &[u8], meta: &[u8], nullable: u8, optional: &[u8]) -> Vec<u8> {
+        table_map_body(108, "test", "users", types, meta, nullable, optional)
+    }
+
+    pub(crate) fn table_map_body(table_id: u8, schema: &str, table: &str, types: &[u8], meta: &[u8], nullable: u8, optional: &[u8]) -> Vec<u8> {
+        let mut body = vec![table_id, 0, 0, 0, 0, 0, 1, 0];
+        for name in [schema, table] {
+            body.push(name.len() as u8);
+            body.extend_from_slice(name.as_bytes());
+            body.push(0);
+        }
+        body.push(types.len() as u8);
+        body.extend_from_slice(types);
+        body.push(meta.len() as u8);
+        body.extend_from_slice(meta);
+        body.push(nullable);
+        body.extend_from_slice(optional);
+        body
+    }
+
+    // a v2 rows event body for test.users with `columns` columns; `rows` starts with the
+    // columns-present bitmap (both, for updates)
+    pub(crate) fn users_rows(columns: u8, rows: &[u8]) -> Vec<u8> {
+        rows_body(108, columns, rows)
+    }
+
+    pub(crate) fn rows_body(table_id: u8, columns: u8, rows: &[u8]) -> Vec<u8> {
+        let mut body = vec![table_id, 0, 0, 0, 0, 0, 1, 0, 2, 0, columns];
+        body.extend_from_slice(rows);
+        body
+    }
 
     #[test]
     fn test_aa() {
         //given
         let path = "tests/asset/mysql-bin.100746";
         let mut reader = File::open(path).unwrap();
+        // events start after the magic number
+        reader.seek(SeekFrom::Start(4)).unwrap();
 
         //when
-        let event = Event::parse(&mut reader, 0).unwrap();
+        let event = Event::parse(&mut reader, 4).unwrap();
         println!("event: {:?}", event);
 
         //then
         assert_eq!(event.type_code, TypeCode::FormatDescriptionEvent);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_parsed_rows_event_uses_table_map_from_context() {
+        //given
+        let table_map = users_table_map(&[3], &[], 0x00, &[]);
+        let write_rows = users_rows(1, &[0x01, 0x00, 7, 0, 0, 0]);
+        let mut bytes = event_bytes(19, &table_map);
+        bytes.extend(event_bytes(30, &write_rows));
+        let mut reader = &bytes[..];
+        let mut ctx = ParseContext::new();
+
+        //when
+        let first = Event::parse(&mut reader, 4).unwrap().parsed(&mut ctx).unwrap();
+        let second = Event::parse(&mut reader, 4).unwrap().parsed(&mut ctx).unwrap();
+
+        //then
+        assert!(matches!(first, EventData::TableMapEvent(_)));
+        match second {
+            EventData::WriteRowsEvent(rows) => assert_eq!(rows.rows[0].after.as_ref().unwrap().get(0), Some(&Value::Int(7))),
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_parsed_without_table_map_and_simple_events() {
+        //given
+        let write_rows = users_rows(1, &[0x01, 0x00, 7, 0, 0, 0]);
+        let mut rotate = 4u64.to_le_bytes().to_vec();
+        rotate.extend_from_slice(b"mysql-bin.000002");
+        let mut bytes = event_bytes(30, &write_rows);
+        bytes.extend(event_bytes(4, &rotate));
+        bytes.extend(event_bytes(16, &42u64.to_le_bytes()));
+        let mut reader = &bytes[..];
+        let mut ctx = ParseContext::new();
+
+        //when
+        let rows = Event::parse(&mut reader, 4).unwrap().parsed(&mut ctx);
+        let rotate = Event::parse(&mut reader, 4).unwrap().parsed(&mut ctx).unwrap();
+        let xid = Event::parse(&mut reader, 4).unwrap().parsed(&mut ctx).unwrap();
+
+        //then
+        assert!(rows.is_err());
+        assert_eq!(rotate, EventData::RotateEvent { position: 4, next_file: "mysql-bin.000002".to_owned() });
+        assert_eq!(xid, EventData::XidEvent { xid: 42 });
+    }
+
+    #[test]
+    fn test_mariadb_events() {
+        //given
+        let mut gtid = 100u64.to_le_bytes().to_vec();
+        gtid.extend_from_slice(&[1, 0, 0, 0, 0x02]);
+        gtid.extend_from_slice(&7u64.to_le_bytes());
+        let mut gtid_list = (2u32 | 0x1000_0000).to_le_bytes().to_vec();
+        for (domain_id, server_id, seq_no) in [(0u32, 1u32, 99u64), (1, 2, 5)].iter() {
+            gtid_list.extend_from_slice(&domain_id.to_le_bytes());
+            gtid_list.extend_from_slice(&server_id.to_le_bytes());
+            gtid_list.extend_from_slice(&seq_no.to_le_bytes());
+        }
+        let mut checkpoint = 16u32.to_le_bytes().to_vec();
+        checkpoint.extend_from_slice(b"mysql-bin.000002");
+        let mut bytes = event_bytes(162, &gtid);
+        bytes.extend(event_bytes(163, &gtid_list));
+        bytes.extend(event_bytes(161, &checkpoint));
+        bytes.extend(event_bytes(160, b"INSERT INTO t VALUES (1)"));
+        let mut reader = &bytes[..];
+        let mut ctx = ParseContext::new();
+
+        //when
+        let events: Vec<_> = (0..4).map(|_| Event::parse(&mut reader, 4).unwrap()).collect();
+        let data: Vec<_> = events.iter().map(|e| e.parsed(&mut ctx).unwrap()).collect();
+
+        //then
+        assert_eq!(events[0].type_code(), TypeCode::MariaDbGtidEvent);
+        assert_eq!(events[0].type_code().to_byte(), 162);
+        assert_eq!(data[0], EventData::MariaDbGtidEvent { domain_id: 1, seq_no: 100, flags: 0x02, commit_id: Some(7) });
+        assert_eq!(data[1], EventData::MariaDbGtidListEvent {
+            gtids: vec![
+                MariaDbGtid { domain_id: 0, server_id: 1, seq_no: 99 },
+                MariaDbGtid { domain_id: 1, server_id: 2, seq_no: 5 },
+            ].into(),
+        });
+        assert_eq!(data[2], EventData::BinlogCheckpointEvent { file: "mysql-bin.000002".to_owned() });
+        assert_eq!(data[3], EventData::AnnotateRowsEvent { query: "INSERT INTO t VALUES (1)".to_owned() });
+    }
+
+    #[test]
+    fn test_unknown_type_and_unparseable_body_in_lenient_mode() {
+        //given
+        let mut bytes = event_bytes(0xc8, &[1, 2, 3]);
+        bytes.extend(event_bytes(16, &[42]));
+        let mut ctx = ParseContext::with_options(ParseOptions::new().mode(ParseMode::Lenient));
+
+        //when
+        let strict = Event::parse(&mut &bytes[..], 4);
+        let mut reader = &bytes[..];
+        let unknown = Event::parse_with_mode(&mut reader, 4, ParseMode::Lenient).unwrap();
+        let xid = Event::parse_with_mode(&mut reader, 26, ParseMode::Lenient).unwrap().parsed(&mut ctx).unwrap();
+
+        //then
+        assert!(matches!(strict, Err(EventParseError::UnknownEventType { offset: 4, type_code: 0xc8 })));
+        assert_eq!(unknown.type_code(), TypeCode::UnknownEvent);
+        assert_eq!(unknown.data(), &[1, 2, 3]);
+        assert!(matches!(unknown.diagnostics(), [EventParseError::UnknownEventType { type_code: 0xc8, .. }]));
+        assert!(matches!(xid, EventData::Unparsed { type_code: TypeCode::XidEvent, .. }));
+    }
+
+    #[test]
+    fn test_parse_errors_name_the_event_and_field() {
+        //given
+        let mut rotate = 4u64.to_le_bytes().to_vec();
+        rotate.extend_from_slice(b"mysql-bin.\xff");
+        let mut bytes = event_bytes(16, &[42, 0, 0]);
+        bytes.extend(event_bytes(4, &rotate));
+        bytes.extend(event_bytes(14, &[0xff, 0xff, 0, 0, b'a']));
+        let mut reader = &bytes[..];
+        let mut ctx = ParseContext::new();
+
+        //when
+        let xid = Event::parse(&mut reader, 4).unwrap().parsed(&mut ctx).unwrap_err();
+        let rotate = Event::parse(&mut reader, 26).unwrap().parsed(&mut ctx).unwrap_err();
+        let user_var = Event::parse(&mut reader, 64).unwrap().parsed(&mut ctx).unwrap_err();
+
+        //then
+        assert!(matches!(
+            xid,
+            EventParseError::InEvent { offset: 4, type_code: TypeCode::XidEvent, event_length: 22, ref source }
+                if matches!(**source, EventParseError::UnexpectedEof { field: "xid" })
+        ));
+        assert!(matches!(rotate, EventParseError::InEvent { ref source, .. } if matches!(**source, EventParseError::BadUtf8 { field: "next_file" })));
+        assert!(matches!(
+            user_var,
+            EventParseError::InEvent { ref source, .. } if matches!(**source, EventParseError::BadLength { field: "name", len: 0xffff, available: 1 })
+        ));
+        assert_eq!(xid.to_string(), "in XidEvent event at 4 (22 bytes): body ends while reading xid");
+    }
+
+    #[test]
+    fn test_post_header_lengths_drive_table_id_width() {
+        //given
+        let mut fde = 1u16.to_le_bytes().to_vec();
+        let mut server_version = b"5.1.3".to_vec();
+        server_version.resize(50, 0);
+        fde.extend(server_version);
+        fde.extend_from_slice(&[0, 0, 0, 0, 19]);
+        let mut post_header_lengths = vec![0u8; 27];
+        post_header_lengths[18] = 6;
+        post_header_lengths[22] = 6;
+        fde.extend(post_header_lengths);
+        let mut table_map = vec![108, 0, 0, 0, 1, 0];
+        table_map.extend_from_slice(b"\x04test\x00\x05users\x00");
+        table_map.extend_from_slice(&[3, 3, 15, 1, 2, 0x80, 0x00, 0x06]);
+        let write_rows = [108, 0, 0, 0, 1, 0, 3, 0x07, 0x06, 7, 0, 0, 0];
+        let mut bytes = event_bytes(15, &fde);
+        bytes.extend(event_bytes(19, &table_map));
+        bytes.extend(event_bytes(23, &write_rows));
+        let mut reader = &bytes[..];
+        let mut ctx = ParseContext::new();
+
+        //when
+        Event::parse(&mut reader, 4).unwrap().parsed(&mut ctx).unwrap();
+        let table_map = Event::parse(&mut reader, 4).unwrap().parsed(&mut ctx).unwrap();
+        let rows = Event::parse(&mut reader, 4).unwrap().parsed(&mut ctx).unwrap();
+
+        //then
+        assert_eq!(ctx.post_header_len(TypeCode::TableMapEvent), Some(6));
+        assert!(matches!(table_map, EventData::TableMapEvent(ref t) if t.table_id == 108 && t.table == "users"));
+        match rows {
+            EventData::WriteRowsEvent(rows) => assert_eq!(rows.rows[0].after.as_ref().unwrap().get(0), Some(&Value::Int(7))),
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_checksum_stripped_from_data() {
+        //given
+        let mut binlog_file = crate::binlog_file::BinlogFile::from_path("tests/asset/mysql-bin.100746").unwrap();
+        let events: Vec<_> = binlog_file.events().map(|e| e.unwrap()).collect();
+        let mut ctx = ParseContext::new();
+
+        //when
+        let parsed: Vec<_> = events.iter().map(|e| e.parsed(&mut ctx).unwrap()).collect();
+
+        //then
+        let rotate = events.last().unwrap();
+        assert_eq!(rotate.data().len() + 4, rotate.raw_body().unwrap().len());
+        assert!(rotate.checksum().is_some());
+        assert_eq!(parsed[14], EventData::RotateEvent { position: 4, next_file: "mysql-bin.100747".to_owned() });
+        assert_eq!(parsed[8], EventData::XidEvent { xid: 42 });
+        match &parsed[7] {
+            EventData::WriteRowsEvent(rows) => {
+                assert_eq!(rows.rows.len(), 2);
+                assert_eq!(rows.rows[0].after.as_ref().unwrap().get(1), Some(&Value::String("alice".to_owned())));
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_truncated_bodies_do_not_panic() {
+        //given
+        let mut binlog_file = crate::binlog_file::BinlogFile::from_path("tests/asset/mysql-bin.100746").unwrap();
+        let events: Vec<_> = binlog_file.events().map(|e| e.unwrap()).collect();
+
+        //when
+        //then
+        for event in &events {
+            let data = event.data();
+            assert!(Event::parse_event_data_by_type_code(event.type_code(), data).is_ok());
+            for len in 0..data.len() {
+                // every prefix must come back as a value or an error, never a panic
+                let _ = Event::parse_event_data_by_type_code(event.type_code(), &data[..len]);
+            }
+        }
+        assert!(Event::parse_event_data_by_type_code(TypeCode::FormatDescriptionEvent, &[4, 0, b'8']).is_err());
+    }
+
+    #[test]
+    fn test_bad_rows_metadata_does_not_panic() {
+        //given
+        let table_map = |column_type: u8, meta: &[u8]| event_bytes(19, &users_table_map(&[column_type], meta, 0x00, &[]));
+        let write_rows = |columns_present: u8| {
+            let mut rows = vec![columns_present];
+            if columns_present != 0 {
+                rows.push(0x00);
+            }
+            rows.extend_from_slice(&[0xaa; 16]);
+            event_bytes(30, &users_rows(1, &rows))
+        };
+        let cases = vec![
+            ("blob length width 0", table_map(252, &[0]), write_rows(1)),
+            ("blob length width 9", table_map(252, &[9]), write_rows(1)),
+            // 0xaaaaaaaa bytes, which must not be allocated before they're found missing
+            ("longblob length past the end", table_map(252, &[4]), write_rows(1)),
+            ("json length past the end", table_map(245, &[4]), write_rows(1)),
+            ("enum width 0", table_map(254, &[247, 0]), write_rows(1)),
+            ("enum width 9", table_map(254, &[247, 9]), write_rows(1)),
+            ("decimal scale > precision", table_map(246, &[2, 5]), write_rows(1)),
+            ("no columns present", table_map(3, &[]), write_rows(0)),
+        ];
+
+        //when
+        //then
+        for (case, table_map, rows) in cases {
+            for mode in [ParseMode::Strict, ParseMode::Lenient] {
+                let mut ctx = ParseContext::with_options(ParseOptions::new().mode(mode));
+                let table_map = Event::parse(&mut &table_map[..], 4).unwrap();
+                let rows = Event::parse(&mut &rows[..], 4).unwrap();
+                assert!(matches!(table_map.parsed(&mut ctx), Ok(EventData::TableMapEvent(_))), "{}", case);
+                let parsed = rows.parsed(&mut ctx);
+                match mode {
+                    ParseMode::Strict => assert!(parsed.is_err(), "{}: {:?}", case, parsed),
+                    ParseMode::Lenient => assert!(matches!(parsed, Ok(EventData::Unparsed { .. })), "{}: {:?}", case, parsed),
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_table_maps_named_by_the_schema_tracker() {
+        //given
+        let events: Vec<Event> = crate::binlog_file::BinlogFile::from_path("tests/asset/mysql-bin.100746").unwrap()
+            .events()
+            .map(|e| e.unwrap())
+            .collect();
+        let mut ctx = ParseContext::new().track_schema(crate::schema::SchemaTracker::new());
+
+        //when
+        let parsed: Vec<EventData> = events.iter().map(|e| e.parsed(&mut ctx).unwrap()).collect();
+
+        //then
+        let table_map = parsed.iter().find_map(|data| match data {
+            EventData::TableMapEvent(table_map) => Some(table_map),
+            _ => None,
+        }).unwrap();
+        let names: Vec<_> = table_map.columns.iter().map(|c| c.name.as_deref()).collect();
+        assert_eq!(names, vec![Some("id"), Some("name"), Some("age")]);
+    }
+
+    #[test]
+    fn test_serde_round_trip() {
+        //given
+        let events: Vec<Event> = crate::binlog_file::BinlogFile::from_path("tests/asset/mysql-bin.100746").unwrap()
+            .events()
+            .map(|e| e.unwrap())
+            .collect();
+        let mut ctx = ParseContext::new();
+        let parsed: Vec<EventData> = events.iter().map(|e| e.parsed(&mut ctx).unwrap()).collect();
+
+        //when
+        let json = serde_json::to_string(&events).unwrap();
+        let back: Vec<Event> = serde_json::from_str(&json).unwrap();
+        let parsed_json = serde_json::to_string(&parsed).unwrap();
+        let parsed_back: Vec<EventData> = serde_json::from_str(&parsed_json).unwrap();
+
+        //then
+        assert_eq!(back.len(), events.len());
+        for (a, b) in events.iter().zip(&back) {
+            assert_eq!(a.header(), b.header());
+            assert_eq!((a.offset(), a.data(), a.checksum()), (b.offset(), b.data(), b.checksum()));
+        }
+        assert_eq!(parsed_back, parsed);
+        assert!(parsed_json.contains(r#""next_file":"mysql-bin.100747""#));
+    }
+
+    #[test]
+    fn test_set_data_keeps_the_event_valid() {
+        //given
+        let path = "tests/asset/mysql-bin.100746";
+        let mut events: Vec<Event> = crate::binlog_file::BinlogFile::from_path(path).unwrap().events().map(|e| e.unwrap()).collect();
+        let query = &mut events[3];
+        let mut data = query.data().to_vec();
+        data.extend_from_slice(b" COMMENT 'set'");
+
+        //when
+        query.set_data(data.clone());
+        let mut bytes = b"\xfebin".to_vec();
+        for event in &events[..4] {
+            bytes.extend_from_slice(&event.to_bytes().unwrap());
+        }
+        let reread: Vec<Event> = crate::binlog_file::BinlogFile::from_reader(std::io::Cursor::new(bytes)).unwrap()
+            .events()
+            .collect::<Result<_, _>>()
+            .unwrap();
+
+        //then
+        assert_eq!(reread.len(), 4);
+        assert_eq!(reread[3].data(), &data[..]);
+        assert_eq!(reread[3].event_length(), 245 + 14);
+        assert_eq!(reread[3].next_position(), 219 + 245 + 14);
+    }
+
+    #[test]
+    fn test_to_bytes_gives_the_file_back() {
+        //given
+        let path = "tests/asset/mysql-bin.100746";
+        let file = std::fs::read(path).unwrap();
+
+        //when
+        let bytes: Vec<u8> = crate::binlog_file::BinlogFile::from_path(path).unwrap()
+            .events()
+            .flat_map(|e| e.unwrap().to_bytes().unwrap())
+            .collect();
+
+        //then
+        assert_eq!(bytes, file[4..]);
+    }
+}
@@ -0,0 +1,190 @@
+use std::collections::HashMap;
+use std::io::Write;
+use serde_json::{Map, Value as Json};
+use crate::errors::ExportError;
+use crate::event::{Event, EventData};
+use crate::handler::EventHandler;
+use crate::rows::{RowImage, RowsEvent};
+use crate::value::Value;
+
+// What each line of NdjsonWriter's output stands for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Granularity {
+    // {"timestamp", "type_code", "server_id", "event_length", "next_position", "flags", "offset", "data"}
+    #[default]
+    Events,
+    // {"timestamp", "server_id", "offset", "schema", "table", "kind", "before", "after"} per changed row,
+    // images being objects by column name when the table map has the names and arrays otherwise
+    Rows,
+}
+
+// Writes parsed events, or the row changes in them, as one JSON object per line:
+//
+//     let mut writer = NdjsonWriter::new(std::io::stdout().lock())
+//         .granularity(Granularity::Rows)
+//         .fields(&["schema", "table", "after"]);
+//     handler::run(BinlogFile::from_path(path)?.events(), &mut ParseContext::new(), &mut writer)?;
+pub struct NdjsonWriter<W: Write> {
+    writer: W,
+    granularity: Granularity,
+    // top-level keys to keep, all of them when None
+    fields: Option<Vec<String>>,
+    // schema and table of the table maps seen, for row records
+    tables: HashMap<u64, (String, String)>,
+}
+
+impl<W: Write> NdjsonWriter<W> {
+    pub fn new(writer: W) -> Self {
+        NdjsonWriter { writer, granularity: Granularity::default(), fields: None, tables: HashMap::new() }
+    }
+
+    pub fn granularity(mut self, granularity: Granularity) -> Self {
+        self.granularity = granularity;
+        self
+    }
+
+    // keep only these keys of each record, in the record's order; unknown names are ignored
+    pub fn fields(mut self, fields: &[&str]) -> Self {
+        self.fields = Some(fields.iter().map(|f| (*f).to_owned()).collect());
+        self
+    }
+
+    pub fn write_event(&mut self, event: &Event, data: &EventData) -> Result<(), ExportError> {
+        if let EventData::TableMapEvent(table_map) = data {
+            self.tables.insert(table_map.table_id, (table_map.schema.clone(), table_map.table.clone()));
+        }
+        match self.granularity {
+            Granularity::Events => {
+                let mut record = match serde_json::to_value(event.header())? {
+                    Json::Object(record) => record,
+                    _ => Map::new(),
+                };
+                record.insert("offset".to_owned(), event.offset().into());
+                record.insert("data".to_owned(), serde_json::to_value(data)?);
+                self.write_record(record)
+            }
+            Granularity::Rows => match data {
+                EventData::WriteRowsEvent(rows) | EventData::UpdateRowsEvent(rows) | EventData::DeleteRowsEvent(rows) => {
+                    self.write_rows(event, rows)
+                }
+                _ => Ok(()),
+            },
+        }
+    }
+
+    pub fn flush(&mut self) -> Result<(), ExportError> {
+        Ok(self.writer.flush()?)
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+
+    fn write_rows(&mut self, event: &Event, rows: &RowsEvent) -> Result<(), ExportError> {
+        let (schema, table) = match self.tables.get(&rows.table_id) {
+            Some((schema, table)) => (Json::from(schema.as_str()), Json::from(table.as_str())),
+            None => (Json::Null, Json::Null),
+        };
+        for change in &rows.rows {
+            let mut record = Map::new();
+            record.insert("timestamp".to_owned(), event.timestamp().into());
+            record.insert("server_id".to_owned(), event.server_id().into());
+            record.insert("offset".to_owned(), event.offset().into());
+            record.insert("schema".to_owned(), schema.clone());
+            record.insert("table".to_owned(), table.clone());
+            record.insert("kind".to_owned(), serde_json::to_value(rows.kind)?);
+            record.insert("before".to_owned(), change.before.as_ref().map(image_json).unwrap_or_default());
+            record.insert("after".to_owned(), change.after.as_ref().map(image_json).unwrap_or_default());
+            self.write_record(record)?;
+        }
+        Ok(())
+    }
+
+    fn write_record(&mut self, mut record: Map<String, Json>) -> Result<(), ExportError> {
+        if let Some(fields) = &self.fields {
+            record.retain(|key, _| fields.iter().any(|f| f == key));
+        }
+        serde_json::to_writer(&mut self.writer, &record)?;
+        self.writer.write_all(b"\n")?;
+        Ok(())
+    }
+}
+
+impl<W: Write> EventHandler for NdjsonWriter<W> {
+    type Error = ExportError;
+
+    fn on_event(&mut self, event: &Event, data: &EventData) -> Result<(), Self::Error> {
+        self.write_event(event, data)
+    }
+}
+
+pub(crate) fn image_json(image: &RowImage) -> Json {
+    match image.as_map() {
+        Some(columns) => Json::Object(columns.into_iter().map(|(name, value)| (name.to_owned(), plain_json(value))).collect()),
+        None => Json::Array(image.values().iter().map(|v| v.as_ref().map(plain_json).unwrap_or_default()).collect()),
+    }
+}
+
+// A row value as plain JSON rather than serde's {"Int": 1}: NULL as null, numbers as numbers, and
+// the rest as the strings Value serializes to (temporal values formatted, bytes in hex).
+pub(crate) fn plain_json(value: &Value) -> Json {
+    match serde_json::to_value(value) {
+        Ok(Json::Object(tagged)) => tagged.into_iter().next().map(|(_, v)| v).unwrap_or_default(),
+        // Null, and floats JSON can't hold
+        _ => Json::Null,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::context::ParseContext;
+    use crate::errors::BinlogFileError;
+    use crate::event::Event;
+    use crate::event::tests::{event_bytes, users_rows, users_table_map};
+    use crate::export::{Granularity, NdjsonWriter};
+    use crate::handler::run;
+
+    fn export(mut writer: NdjsonWriter<Vec<u8>>) -> Vec<serde_json::Value> {
+        let table_map = users_table_map(&[3], &[], 0x00, &[]);
+        let write_rows = users_rows(1, &[0x01, 0x00, 7, 0, 0, 0, 0x00, 8, 0, 0, 0]);
+        let mut bytes = event_bytes(19, &table_map);
+        bytes.extend(event_bytes(30, &write_rows));
+        bytes.extend(event_bytes(16, &42u64.to_le_bytes()));
+        let mut reader = &bytes[..];
+        let events = std::iter::from_fn(|| {
+            if reader.is_empty() { None } else { Some(Event::parse(&mut reader, 4).map_err(BinlogFileError::from)) }
+        });
+        run(events, &mut ParseContext::new(), &mut writer).unwrap();
+        let output = String::from_utf8(writer.into_inner()).unwrap();
+        output.lines().map(|line| serde_json::from_str(line).unwrap()).collect()
+    }
+
+    #[test]
+    fn test_export_events() {
+        //given
+        let writer = NdjsonWriter::new(vec![]).fields(&["type_code", "data"]);
+
+        //when
+        let records = export(writer);
+
+        //then
+        assert_eq!(records.len(), 3);
+        assert_eq!(records[2], serde_json::json!({"type_code": "XidEvent", "data": {"XidEvent": {"xid": 42}}}));
+        assert_eq!(records[0]["data"]["TableMapEvent"]["table"], "users");
+    }
+
+    #[test]
+    fn test_export_rows() {
+        //given
+        let writer = NdjsonWriter::new(vec![]).granularity(Granularity::Rows).fields(&["schema", "table", "kind", "after"]);
+
+        //when
+        let records = export(writer);
+
+        //then
+        assert_eq!(records, vec![
+            serde_json::json!({"schema": "test", "table": "users", "kind": "Write", "after": [7]}),
+            serde_json::json!({"schema": "test", "table": "users", "kind": "Write", "after": [8]}),
+        ]);
+    }
+}
@@ -0,0 +1,538 @@
+use std::collections::{HashMap, HashSet};
+use std::io::{Cursor, Read};
+use byteorder::{LittleEndian, ReadBytesExt};
+use regex::Regex;
+use crate::errors::{BinlogFileError, EventParseError};
+use crate::event::{Event, EventHeader, TypeCode};
+use crate::gtid::GtidSet;
+use crate::rows::RowsEventKind;
+use crate::utils::read_bytes;
+
+#[derive(Debug, Clone)]
+pub enum TableRule {
+    Exact(String, String),
+    // replicate-wild-do-table style: LIKE patterns for the schema and table, e.g. shop.orders_%
+    Wild(Regex, Regex),
+    // matched against "schema.table"
+    Regex(Regex),
+}
+
+impl TableRule {
+    // `%` matches any run of characters, `_` any single one and `\` escapes either;
+    // the pattern is split into schema and table at the first unescaped `.`
+    pub fn wild(pattern: &str) -> Self {
+        let (schema, table) = split_wild_pattern(pattern);
+        TableRule::Wild(like_to_regex(&schema), like_to_regex(&table))
+    }
+
+    pub fn matches(&self, schema: &str, table: &str) -> bool {
+        match self {
+            TableRule::Exact(s, t) => s == schema && t == table,
+            TableRule::Wild(s, t) => s.is_match(schema) && t.is_match(table),
+            TableRule::Regex(re) => re.is_match(&format!("{}.{}", schema, table)),
+        }
+    }
+}
+
+fn split_wild_pattern(pattern: &str) -> (String, String) {
+    let mut escaped = false;
+    for (i, c) in pattern.char_indices() {
+        match c {
+            '\\' if !escaped => escaped = true,
+            '.' if !escaped => return (pattern[..i].to_owned(), pattern[i + 1..].to_owned()),
+            _ => escaped = false,
+        }
+    }
+    // no table part matches every table of the schema
+    (pattern.to_owned(), "%".to_owned())
+}
+
+fn like_to_regex(pattern: &str) -> Regex {
+    let mut re = String::from("^");
+    let mut chars = pattern.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '%' => re.push_str(".*"),
+            '_' => re.push('.'),
+            '\\' => match chars.next() {
+                Some(escaped) => re.push_str(&regex::escape(&escaped.to_string())),
+                None => re.push_str(&regex::escape("\\")),
+            },
+            c => re.push_str(&regex::escape(&c.to_string())),
+        }
+    }
+    re.push('$');
+    // every literal is escaped, so the generated pattern always compiles
+    Regex::new(&re).expect("escaped LIKE pattern is a valid regex")
+}
+
+// Selects events by header fields (type, server id, time, position) and by the schema/table
+// they touch. Header rules are checked first, so with BinlogFile::lazy_events() the bodies of
+// rejected events are never read. Schema/table rules apply to table map and rows events (via
+// the preceding table map) and to query events (via their default schema); other events such
+// as GTIDs and XIDs always pass them so transaction boundaries stay intact. GTID rules select
+// whole transactions, every event from a GTID event up to the next.
+#[derive(Debug, Clone, Default)]
+pub struct EventFilter {
+    include_types: HashSet<TypeCode>,
+    exclude_types: HashSet<TypeCode>,
+    include_schemas: HashSet<String>,
+    exclude_schemas: HashSet<String>,
+    include_wild_schemas: Vec<Regex>,
+    exclude_wild_schemas: Vec<Regex>,
+    include_tables: Vec<TableRule>,
+    exclude_tables: Vec<TableRule>,
+    include_server_ids: HashSet<u32>,
+    exclude_server_ids: HashSet<u32>,
+    start_timestamp: Option<u32>,
+    stop_timestamp: Option<u32>,
+    start_position: Option<u64>,
+    stop_position: Option<u64>,
+    include_gtids: Option<GtidSet>,
+    exclude_gtids: Option<GtidSet>,
+}
+
+impl EventFilter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn include_type(mut self, type_code: TypeCode) -> Self {
+        self.include_types.insert(type_code);
+        self
+    }
+
+    pub fn exclude_type(mut self, type_code: TypeCode) -> Self {
+        self.exclude_types.insert(type_code);
+        self
+    }
+
+    pub fn include_schema(mut self, schema: &str) -> Self {
+        self.include_schemas.insert(schema.to_owned());
+        self
+    }
+
+    pub fn exclude_schema(mut self, schema: &str) -> Self {
+        self.exclude_schemas.insert(schema.to_owned());
+        self
+    }
+
+    // a LIKE pattern as in TableRule::wild, e.g. shop_%
+    pub fn include_wild_schema(mut self, pattern: &str) -> Self {
+        self.include_wild_schemas.push(like_to_regex(pattern));
+        self
+    }
+
+    pub fn exclude_wild_schema(mut self, pattern: &str) -> Self {
+        self.exclude_wild_schemas.push(like_to_regex(pattern));
+        self
+    }
+
+    pub fn include_table(self, schema: &str, table: &str) -> Self {
+        self.include_table_rule(TableRule::Exact(schema.to_owned(), table.to_owned()))
+    }
+
+    pub fn exclude_table(self, schema: &str, table: &str) -> Self {
+        self.exclude_table_rule(TableRule::Exact(schema.to_owned(), table.to_owned()))
+    }
+
+    pub fn include_wild_table(self, pattern: &str) -> Self {
+        self.include_table_rule(TableRule::wild(pattern))
+    }
+
+    pub fn exclude_wild_table(self, pattern: &str) -> Self {
+        self.exclude_table_rule(TableRule::wild(pattern))
+    }
+
+    pub fn include_table_regex(self, regex: Regex) -> Self {
+        self.include_table_rule(TableRule::Regex(regex))
+    }
+
+    pub fn exclude_table_regex(self, regex: Regex) -> Self {
+        self.exclude_table_rule(TableRule::Regex(regex))
+    }
+
+    pub fn include_table_rule(mut self, rule: TableRule) -> Self {
+        self.include_tables.push(rule);
+        self
+    }
+
+    pub fn exclude_table_rule(mut self, rule: TableRule) -> Self {
+        self.exclude_tables.push(rule);
+        self
+    }
+
+    pub fn include_server_id(mut self, server_id: u32) -> Self {
+        self.include_server_ids.insert(server_id);
+        self
+    }
+
+    pub fn exclude_server_id(mut self, server_id: u32) -> Self {
+        self.exclude_server_ids.insert(server_id);
+        self
+    }
+
+    // inclusive, in seconds since the epoch like the header timestamp
+    pub fn start_timestamp(mut self, timestamp: u32) -> Self {
+        self.start_timestamp = Some(timestamp);
+        self
+    }
+
+    // exclusive
+    pub fn stop_timestamp(mut self, timestamp: u32) -> Self {
+        self.stop_timestamp = Some(timestamp);
+        self
+    }
+
+    // inclusive event offset
+    pub fn start_position(mut self, position: u64) -> Self {
+        self.start_position = Some(position);
+        self
+    }
+
+    // exclusive; iteration ends at the first event at or past it
+    pub fn stop_position(mut self, position: u64) -> Self {
+        self.stop_position = Some(position);
+        self
+    }
+
+    // only the transactions whose GTID is in `gtids`, as mysqlbinlog --include-gtids; anonymous
+    // ones are skipped
+    pub fn include_gtids(mut self, gtids: GtidSet) -> Self {
+        self.include_gtids = Some(gtids);
+        self
+    }
+
+    // skip the transactions whose GTID is in `gtids`, as mysqlbinlog --exclude-gtids
+    pub fn exclude_gtids(mut self, gtids: GtidSet) -> Self {
+        self.exclude_gtids = Some(gtids);
+        self
+    }
+
+    pub fn matches_header(&self, header: &EventHeader, offset: u64) -> bool {
+        (self.include_types.is_empty() || self.include_types.contains(&header.type_code))
+            && !self.exclude_types.contains(&header.type_code)
+            && (self.include_server_ids.is_empty() || self.include_server_ids.contains(&header.server_id))
+            && !self.exclude_server_ids.contains(&header.server_id)
+            && self.start_timestamp.is_none_or(|t| header.timestamp >= t)
+            && self.stop_timestamp.is_none_or(|t| header.timestamp < t)
+            && self.start_position.is_none_or(|p| offset >= p)
+            && self.stop_position.is_none_or(|p| offset < p)
+    }
+
+    pub fn matches_schema(&self, schema: &str) -> bool {
+        let included = (self.include_schemas.is_empty() && self.include_wild_schemas.is_empty())
+            || self.include_schemas.contains(schema)
+            || self.include_wild_schemas.iter().any(|re| re.is_match(schema));
+        included
+            && !self.exclude_schemas.contains(schema)
+            && !self.exclude_wild_schemas.iter().any(|re| re.is_match(schema))
+    }
+
+    pub fn matches_table(&self, schema: &str, table: &str) -> bool {
+        self.matches_schema(schema)
+            && (self.include_tables.is_empty() || self.include_tables.iter().any(|r| r.matches(schema, table)))
+            && !self.exclude_tables.iter().any(|r| r.matches(schema, table))
+    }
+
+    pub fn matches_gtid(&self, sid: &[u8; 16], gno: i64) -> bool {
+        self.include_gtids.as_ref().is_none_or(|gtids| gtids.contains(sid, gno))
+            && !self.exclude_gtids.as_ref().is_some_and(|gtids| gtids.contains(sid, gno))
+    }
+
+    fn has_gtid_rules(&self) -> bool {
+        self.include_gtids.is_some() || self.exclude_gtids.is_some()
+    }
+
+    fn has_table_rules(&self) -> bool {
+        !(self.include_schemas.is_empty()
+            && self.exclude_schemas.is_empty()
+            && self.include_wild_schemas.is_empty()
+            && self.exclude_wild_schemas.is_empty()
+            && self.include_tables.is_empty()
+            && self.exclude_tables.is_empty())
+    }
+
+    fn is_past_stop(&self, offset: u64) -> bool {
+        self.stop_position.is_some_and(|p| offset >= p)
+    }
+}
+
+// Iterator adapter applying an EventFilter, see EventIter::with_filter and LazyEventIter::with_filter.
+pub struct FilteredEvents<It> {
+    inner: It,
+    filter: EventFilter,
+    // table id -> (schema, table) from the table maps seen so far
+    tables: HashMap<u64, (String, String)>,
+    // whether the transaction being read fails the GTID rules
+    skipping_transaction: bool,
+}
+
+impl<It> FilteredEvents<It> {
+    pub fn new(inner: It, filter: EventFilter) -> Self {
+        FilteredEvents {
+            inner,
+            filter,
+            tables: HashMap::new(),
+            skipping_transaction: false,
+        }
+    }
+
+    pub fn filter(&self) -> &EventFilter {
+        &self.filter
+    }
+
+    pub fn into_inner(self) -> It {
+        self.inner
+    }
+
+    fn accept(&mut self, event: &Event) -> Result<bool, EventParseError> {
+        let type_code = event.type_code();
+        let track_table = self.filter.has_table_rules() && type_code == TypeCode::TableMapEvent;
+        if track_table {
+            let (table_id, schema, table) = peek_table_map_names(event.try_data()?)?;
+            self.tables.insert(table_id, (schema, table));
+        }
+        if self.filter.has_gtid_rules() {
+            match type_code {
+                TypeCode::GtidLogEvent => {
+                    let (sid, gno) = peek_gtid(event.try_data()?)?;
+                    self.skipping_transaction = !self.filter.matches_gtid(&sid, gno);
+                }
+                TypeCode::AnonymousGtidLogEvent => self.skipping_transaction = self.filter.include_gtids.is_some(),
+                _ => {}
+            }
+            if self.skipping_transaction && !is_between_transactions(type_code) {
+                return Ok(false);
+            }
+        }
+        if !self.filter.matches_header(&event.header(), event.offset()) {
+            return Ok(false);
+        }
+        if !self.filter.has_table_rules() {
+            return Ok(true);
+        }
+
+        if type_code == TypeCode::QueryEvent {
+            // like mysqlbinlog, transaction boundaries pass whatever their schema so they stay paired with the Xid
+            let (schema, query) = peek_query(event.try_data()?)?;
+            return Ok(is_transaction_keyword(query) || self.filter.matches_schema(&schema));
+        }
+        if track_table || RowsEventKind::from_type_code(type_code).is_some() {
+            let table_id = Cursor::new(event.try_data()?).read_u48::<LittleEndian>()?;
+            return Ok(match self.tables.get(&table_id) {
+                Some((schema, table)) => self.filter.matches_table(schema, table),
+                None => false,
+            });
+        }
+        Ok(true)
+    }
+}
+
+impl<It> Iterator for FilteredEvents<It> where
+    It: Iterator<Item = Result<Event, BinlogFileError>>
+{
+    type Item = Result<Event, BinlogFileError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let event = match self.inner.next()? {
+                Ok(event) => event,
+                Err(e) => return Some(Err(e)),
+            };
+            if self.filter.is_past_stop(event.offset()) {
+                return None;
+            }
+            match self.accept(&event) {
+                Ok(true) => return Some(Ok(event)),
+                Ok(false) => continue,
+                Err(e) => return Some(Err(e.into())),
+            }
+        }
+    }
+}
+
+// just the names, without decoding the column metadata
+pub(crate) fn peek_table_map_names(data: &[u8]) -> Result<(u64, String, String), EventParseError> {
+    let mut cursor = Cursor::new(data);
+    let table_id = cursor.read_u48::<LittleEndian>()?;
+    cursor.read_u16::<LittleEndian>()?;
+    let schema_len = cursor.read_u8()? as usize;
+    let schema = String::from_utf8_lossy(&read_bytes(&mut cursor, schema_len)?).into_owned();
+    cursor.read_u8()?;
+    let table_len = cursor.read_u8()? as usize;
+    let table = String::from_utf8_lossy(&read_bytes(&mut cursor, table_len)?).into_owned();
+    Ok((table_id, schema, table))
+}
+
+// the sid and transaction number after the flags byte
+fn peek_gtid(data: &[u8]) -> Result<([u8; 16], i64), EventParseError> {
+    let mut cursor = Cursor::new(data);
+    cursor.read_u8()?;
+    let mut sid = [0u8; 16];
+    cursor.read_exact(&mut sid)?;
+    Ok((sid, cursor.read_i64::<LittleEndian>()?))
+}
+
+fn is_between_transactions(type_code: TypeCode) -> bool {
+    matches!(type_code,
+        TypeCode::FormatDescriptionEvent | TypeCode::PreviousGtidsLogEvent | TypeCode::RotateEvent | TypeCode::StopEvent)
+}
+
+// https://dev.mysql.com/doc/internals/en/query-event.html
+pub(crate) fn peek_query_schema(data: &[u8]) -> Result<String, EventParseError> {
+    Ok(peek_query(data)?.0)
+}
+
+// the default schema and the statement after its NUL terminator
+fn peek_query(data: &[u8]) -> Result<(String, &[u8]), EventParseError> {
+    let mut cursor = Cursor::new(data);
+    cursor.set_position(8);
+    let schema_len = cursor.read_u8()? as usize;
+    cursor.read_u16::<LittleEndian>()?;
+    let status_vars_len = cursor.read_u16::<LittleEndian>()?;
+    cursor.set_position(cursor.position() + u64::from(status_vars_len));
+    let schema = String::from_utf8_lossy(&read_bytes(&mut cursor, schema_len)?).into_owned();
+    cursor.read_u8()?;
+    Ok((schema, &data[cursor.position() as usize..]))
+}
+
+fn is_transaction_keyword(query: &[u8]) -> bool {
+    let starts_with = |keyword: &str| query.len() >= keyword.len() && query[..keyword.len()].eq_ignore_ascii_case(keyword.as_bytes());
+    query.eq_ignore_ascii_case(b"BEGIN") || query.eq_ignore_ascii_case(b"COMMIT")
+        || starts_with("ROLLBACK") || starts_with("SAVEPOINT") || starts_with("XA ")
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::binlog_file::BinlogFile;
+    use crate::context::ParseContext;
+    use crate::event::{EventData, TypeCode};
+    use crate::event::tests::parse_events;
+    use crate::filter::{EventFilter, FilteredEvents, TableRule};
+    use crate::gtid::GtidSet;
+    use regex::Regex;
+
+    #[test]
+    fn test_filter_by_type_and_position() {
+        //given
+        let path = "tests/asset/mysql-bin.100746";
+        let mut binlog_file = BinlogFile::from_path(path).unwrap();
+        let offsets: Vec<u64> = binlog_file.events().map(|e| e.unwrap().offset()).collect();
+        let filter = EventFilter::new()
+            .include_type(TypeCode::XidEvent)
+            .include_type(TypeCode::QueryEvent)
+            .stop_position(offsets[13]);
+
+        //when
+        let events: Vec<_> = binlog_file.events().with_filter(filter).collect::<Result<_, _>>().unwrap();
+
+        //then
+        let types: Vec<_> = events.iter().map(|e| e.type_code()).collect();
+        assert_eq!(types, vec![TypeCode::QueryEvent, TypeCode::QueryEvent, TypeCode::XidEvent, TypeCode::QueryEvent]);
+    }
+
+    #[test]
+    fn test_filter_by_table() {
+        //given
+        let path = "tests/asset/mysql-bin.100746";
+        let included = EventFilter::new().include_table("test", "users").include_type(TypeCode::WriteRowsEventV2).include_type(TypeCode::UpdateRowsEventV2);
+        let excluded = EventFilter::new().exclude_table("test", "users");
+
+        //when
+        let rows: Vec<_> = BinlogFile::from_path(path).unwrap().lazy_events().with_filter(included).map(|e| e.unwrap()).collect();
+        let rest: Vec<_> = BinlogFile::from_path(path).unwrap().events().with_filter(excluded).map(|e| e.unwrap()).collect();
+
+        //then
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rest.len(), 11);
+        assert!(rest.iter().all(|e| e.type_code() != TypeCode::TableMapEvent));
+    }
+
+    #[test]
+    fn test_transaction_boundaries_pass_schema_rules() {
+        //given
+        let path = "tests/asset/mysql-bin.100746";
+        let filter = EventFilter::new().include_schema("nope");
+        let mut ctx = ParseContext::new();
+
+        //when
+        let queries: Vec<_> = BinlogFile::from_path(path).unwrap().events().with_filter(filter).map(|e| e.unwrap())
+            .filter_map(|e| match e.parsed(&mut ctx).unwrap() {
+                EventData::QueryEvent { query, .. } => Some(query),
+                _ => None,
+            })
+            .collect();
+
+        //then
+        assert_eq!(queries, vec!["BEGIN"; 2]);
+    }
+
+    #[test]
+    fn test_wild_and_regex_table_rules() {
+        //given
+        let orders = TableRule::wild("shop.orders_%");
+        let escaped = TableRule::wild("shop.a\\_b");
+        let filter = EventFilter::new()
+            .include_wild_table("shop.orders_%")
+            .exclude_table_regex(Regex::new(r"^shop\.orders_archive_\d+$").unwrap());
+        let schemas = EventFilter::new().include_wild_schema("shop_%").exclude_wild_schema("shop\\_test");
+
+        //when
+        //then
+        assert!(orders.matches("shop", "orders_2021"));
+        assert!(!orders.matches("shop", "orders"));
+        assert!(!orders.matches("shopx", "orders_2021"));
+        assert!(escaped.matches("shop", "a_b"));
+        assert!(!escaped.matches("shop", "axb"));
+        assert!(filter.matches_table("shop", "orders_eu"));
+        assert!(!filter.matches_table("shop", "orders_archive_2019"));
+        assert!(!filter.matches_table("shop", "customers"));
+        assert!(schemas.matches_schema("shop_eu"));
+        assert!(!schemas.matches_schema("shop_test"));
+        assert!(!schemas.matches_schema("billing"));
+        assert!(schemas.matches_table("shop_us", "orders"));
+    }
+
+    #[test]
+    fn test_wild_rule_suppresses_table_map() {
+        //given
+        let path = "tests/asset/mysql-bin.100746";
+        let filter = EventFilter::new().exclude_wild_table("te_t.u%");
+
+        //when
+        let events: Vec<_> = BinlogFile::from_path(path).unwrap().events().with_filter(filter).map(|e| e.unwrap()).collect();
+
+        //then
+        assert_eq!(events.len(), 11);
+        assert!(events.iter().all(|e| e.type_code() != TypeCode::TableMapEvent));
+    }
+
+    #[test]
+    fn test_filter_by_gtid() {
+        //given
+        let gtid = |gno: i64| [&[0x01][..], &[0x11; 16], &gno.to_le_bytes()].concat();
+        let events = || parse_events(0, &[
+            (33, gtid(1)),
+            (16, 1u64.to_le_bytes().to_vec()),
+            (33, gtid(2)),
+            (16, 2u64.to_le_bytes().to_vec()),
+            (34, gtid(0)),
+            (16, 3u64.to_le_bytes().to_vec()),
+            (4, vec![0; 8]),
+        ]);
+        let second: GtidSet = "11111111-1111-1111-1111-111111111111:2".parse().unwrap();
+        let types = |filter: EventFilter| FilteredEvents::new(events().into_iter().map(Ok), filter)
+            .map(|e| e.unwrap().type_code())
+            .collect::<Vec<_>>();
+
+        //when
+        let included = types(EventFilter::new().include_gtids(second.clone()));
+        let excluded = types(EventFilter::new().exclude_gtids(second));
+
+        //then
+        assert_eq!(included, vec![TypeCode::GtidLogEvent, TypeCode::XidEvent, TypeCode::RotateEvent]);
+        assert_eq!(excluded.len(), 5);
+        assert_eq!(excluded[2], TypeCode::AnonymousGtidLogEvent);
+    }
+}
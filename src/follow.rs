@@ -0,0 +1,301 @@
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use crate::binlog_series::file_name;
+use crate::checksum::ChecksumVerifier;
+use crate::encryption;
+use crate::errors::BinlogFileError;
+use crate::position::BinlogPosition;
+use crate::event::{Event, EventData, EventHeader, TypeCode};
+use crate::options::ParseMode;
+
+// `tail -f` for binlogs: reads the active file as the server appends to it, waiting at the end
+// for the next complete event, and moves to the next file when a Rotate event is written.
+// With the `notify` feature the wait wakes up on file system events, otherwise it polls.
+pub struct BinlogFollower {
+    path: PathBuf,
+    file: File,
+    offset: u64,
+    poll_interval: Duration,
+    idle_timeout: Option<Duration>,
+    idle_since: Option<Instant>,
+    rotate_to: Option<PathBuf>,
+    waker: Waker,
+    checksum: ChecksumVerifier,
+    finished: bool,
+}
+
+impl BinlogFollower {
+    pub fn new<P: AsRef<Path>>(path: P) -> Result<Self, BinlogFileError> {
+        Self::from_position(path, 4)
+    }
+
+    // resume at an event boundary in `path`, e.g. a saved checkpoint
+    pub fn from_position<P: AsRef<Path>>(path: P, offset: u64) -> Result<Self, BinlogFileError> {
+        let path = path.as_ref().to_path_buf();
+        let mut file = File::open(&path).map_err(BinlogFileError::OpenError)?;
+        let mut checksum = ChecksumVerifier::default();
+        if offset > 4 {
+            // the FormatDescriptionEvent at 4 says whether the events after it end with a checksum
+            let mut magic = [0u8; 4];
+            file.read_exact(&mut magic)?;
+            encryption::check_magic(magic, &mut file)?;
+            let mut header = [0u8; EventHeader::LEN];
+            file.read_exact(&mut header)?;
+            let mut event = Event::parse(&mut (&header[..]).chain(&mut file), 4)?;
+            checksum.check(&header, &mut event, ParseMode::Strict)?;
+        }
+        let waker = Waker::new(path.parent().unwrap_or_else(|| Path::new(".")));
+        Ok(BinlogFollower {
+            path,
+            file,
+            offset,
+            poll_interval: Duration::from_millis(200),
+            idle_timeout: None,
+            idle_since: None,
+            rotate_to: None,
+            waker,
+            checksum,
+            finished: false,
+        })
+    }
+
+    // how long to sleep between checks when no notification arrives
+    pub fn poll_interval(mut self, interval: Duration) -> Self {
+        self.poll_interval = interval;
+        self
+    }
+
+    // end the iteration after this long without a new event; by default it waits forever
+    pub fn idle_timeout(mut self, timeout: Duration) -> Self {
+        self.idle_timeout = Some(timeout);
+        self
+    }
+
+    pub fn current_file(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn offset(&self) -> u64 {
+        self.offset
+    }
+
+    // checkpoint for resuming after the last returned event
+    pub fn position(&self) -> BinlogPosition {
+        match &self.rotate_to {
+            Some(next) => BinlogPosition::new(&file_name(next), 4),
+            None => BinlogPosition::new(&file_name(&self.path), self.offset),
+        }
+    }
+
+    fn available(&self) -> Result<u64, BinlogFileError> {
+        Ok(self.file.metadata()?.len().saturating_sub(self.offset))
+    }
+
+    // Ok(None) when the next event hasn't been completely written yet
+    fn try_read_event(&mut self) -> Result<Option<Event>, BinlogFileError> {
+        if let Some(next) = self.rotate_to.take() {
+            // the server writes the Rotate before creating the next file
+            if !next.exists() {
+                self.rotate_to = Some(next);
+                return Ok(None);
+            }
+            self.file = File::open(&next).map_err(BinlogFileError::OpenError)?;
+            self.path = next;
+            self.offset = 4;
+        }
+
+        if self.offset < 4 {
+            return Err(BinlogFileError::BadPosition(self.offset));
+        }
+        if self.offset == 4 {
+            if self.file.metadata()?.len() < 4 {
+                return Ok(None);
+            }
+            let mut magic = [0u8; 4];
+            self.file.seek(SeekFrom::Start(0))?;
+            self.file.read_exact(&mut magic)?;
+            encryption::check_magic(magic, &mut self.file)?;
+        }
+
+        let available = self.available()?;
+        if available < EventHeader::LEN as u64 {
+            return Ok(None);
+        }
+        self.file.seek(SeekFrom::Start(self.offset))?;
+        let mut header = [0u8; EventHeader::LEN];
+        self.file.read_exact(&mut header)?;
+        let event_length = u64::from(EventHeader::parse(&header).checked_length(self.offset, ParseMode::Strict)?);
+        if available < event_length {
+            return Ok(None);
+        }
+
+        let mut event = Event::parse(&mut (&header[..]).chain(&mut self.file), self.offset)?;
+        self.checksum.check(&header, &mut event, ParseMode::Strict)?;
+        self.offset += event_length;
+        if event.type_code() == TypeCode::RotateEvent {
+            if let Some(EventData::RotateEvent { next_file, .. }) =
+                Event::parse_event_data_by_type_code(TypeCode::RotateEvent, event.try_data()?)?
+            {
+                let dir = self.path.parent().unwrap_or_else(|| Path::new(""));
+                self.rotate_to = Some(dir.join(next_file));
+            }
+        }
+        Ok(Some(event))
+    }
+
+    fn read_event(&mut self) -> Result<Option<Event>, BinlogFileError> {
+        loop {
+            if let Some(event) = self.try_read_event()? {
+                self.idle_since = None;
+                return Ok(Some(event));
+            }
+            let idle_since = *self.idle_since.get_or_insert_with(Instant::now);
+            if self.idle_timeout.is_some_and(|t| idle_since.elapsed() >= t) {
+                return Ok(None);
+            }
+            self.waker.wait(self.poll_interval);
+        }
+    }
+}
+
+impl Iterator for BinlogFollower {
+    type Item = Result<Event, BinlogFileError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.finished {
+            return None;
+        }
+        match self.read_event() {
+            Ok(Some(event)) => Some(Ok(event)),
+            Ok(None) => {
+                self.finished = true;
+                None
+            }
+            Err(e) => {
+                self.finished = true;
+                Some(Err(e))
+            }
+        }
+    }
+}
+
+#[cfg(feature = "notify")]
+struct Waker {
+    // kept alive for as long as the follower; None if the platform watcher couldn't start
+    watcher: Option<(notify::RecommendedWatcher, std::sync::mpsc::Receiver<()>)>,
+}
+
+#[cfg(feature = "notify")]
+impl Waker {
+    fn new(dir: &Path) -> Self {
+        use notify::Watcher;
+
+        let (tx, rx) = std::sync::mpsc::channel();
+        let watcher = notify::recommended_watcher(move |_| {
+            let _ = tx.send(());
+        })
+            .and_then(|mut w| w.watch(dir, notify::RecursiveMode::NonRecursive).map(|_| w))
+            .ok();
+        Waker { watcher: watcher.map(|w| (w, rx)) }
+    }
+
+    fn wait(&self, timeout: Duration) {
+        match &self.watcher {
+            Some((_, rx)) => {
+                let _ = rx.recv_timeout(timeout);
+                // coalesce the burst of notifications a single write produces
+                while rx.try_recv().is_ok() {}
+            }
+            None => std::thread::sleep(timeout),
+        }
+    }
+}
+
+#[cfg(not(feature = "notify"))]
+struct Waker;
+
+#[cfg(not(feature = "notify"))]
+impl Waker {
+    fn new(_dir: &Path) -> Self {
+        Waker
+    }
+
+    fn wait(&self, timeout: Duration) {
+        std::thread::sleep(timeout);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::OpenOptions;
+    use std::io::Write;
+    use std::time::Duration;
+    use crate::binlog_file::BinlogFile;
+    use crate::event::TypeCode;
+    use crate::event::tests::event_bytes;
+    use crate::follow::BinlogFollower;
+
+    #[test]
+    fn test_follow_growing_binlog_across_rotate() {
+        //given
+        let dir = std::env::temp_dir().join(format!("binlog-follow-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let first_path = dir.join("mysql-bin.000001");
+        let mut first = vec![0xfe, 0x62, 0x69, 0x6e];
+        first.extend(event_bytes(16, &1u64.to_le_bytes()));
+        std::fs::write(&first_path, first).unwrap();
+        let follower = BinlogFollower::new(&first_path).unwrap()
+            .poll_interval(Duration::from_millis(5))
+            .idle_timeout(Duration::from_millis(500));
+
+        let writer_dir = dir.clone();
+        let writer = std::thread::spawn(move || {
+            let mut file = OpenOptions::new().append(true).open(writer_dir.join("mysql-bin.000001")).unwrap();
+            let xid = event_bytes(16, &2u64.to_le_bytes());
+            std::thread::sleep(Duration::from_millis(30));
+            // an event written in two pieces must not be returned half-done
+            file.write_all(&xid[..10]).unwrap();
+            file.flush().unwrap();
+            std::thread::sleep(Duration::from_millis(30));
+            file.write_all(&xid[10..]).unwrap();
+            let mut rotate = 4u64.to_le_bytes().to_vec();
+            rotate.extend_from_slice(b"mysql-bin.000002");
+            file.write_all(&event_bytes(4, &rotate)).unwrap();
+            std::thread::sleep(Duration::from_millis(30));
+            let mut second = vec![0xfe, 0x62, 0x69, 0x6e];
+            second.extend(event_bytes(16, &3u64.to_le_bytes()));
+            std::fs::write(writer_dir.join("mysql-bin.000002"), second).unwrap();
+        });
+
+        //when
+        let events: Vec<_> = follower.map(|e| e.unwrap()).collect();
+        writer.join().unwrap();
+
+        //then
+        let types: Vec<_> = events.iter().map(|e| e.type_code()).collect();
+        assert_eq!(types, vec![TypeCode::XidEvent, TypeCode::XidEvent, TypeCode::RotateEvent, TypeCode::XidEvent]);
+        assert_eq!(events[3].data(), &3u64.to_le_bytes());
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_follow_from_the_middle_of_a_checksummed_file() {
+        //given
+        let path = "tests/asset/mysql-bin.100746";
+        let expected: Vec<_> = BinlogFile::from_path(path).unwrap().events_from(529).unwrap().map(|e| e.unwrap()).collect();
+        let follower = BinlogFollower::from_position(path, 529).unwrap().idle_timeout(Duration::from_millis(20));
+
+        //when
+        let events: Vec<_> = follower.map(|e| e.unwrap()).collect();
+
+        //then
+        assert_eq!(events.len(), expected.len());
+        for (event, expected) in events.iter().zip(&expected) {
+            assert_eq!(event.offset(), expected.offset());
+            assert_eq!(event.data(), expected.data());
+        }
+        assert_eq!(events.last().unwrap().type_code(), TypeCode::RotateEvent);
+    }
+}
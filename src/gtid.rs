@@ -0,0 +1,538 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::convert::TryFrom;
+use std::iter::FromIterator;
+use std::str::FromStr;
+use serde_derive::{Deserialize, Serialize};
+use crate::errors::GtidParseError;
+use crate::event::SidIntervals;
+
+// Set of MySQL GTIDs: per server uuid, sorted and merged [start, end) ranges of transaction
+// numbers, the same shape the server keeps in Previous_gtids events.
+#[derive(Debug, Clone, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub struct GtidSet {
+    sets: BTreeMap<[u8; 16], Vec<(i64, i64)>>,
+}
+
+impl GtidSet {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn from_sid_intervals(sids: &[SidIntervals]) -> Self {
+        let mut set = GtidSet::new();
+        for (sid, intervals) in sids {
+            for (start, end) in intervals {
+                set.add_interval(*sid, *start, *end);
+            }
+        }
+        set
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.sets.is_empty()
+    }
+
+    pub fn contains(&self, sid: &[u8; 16], gno: i64) -> bool {
+        self.sets.get(sid)
+            .is_some_and(|intervals| intervals.iter().any(|(start, end)| *start <= gno && gno < *end))
+    }
+
+    // i64::MAX is past the largest gno a server hands out and can't end a [start, end) range,
+    // so a corrupt event claiming it adds nothing
+    pub fn add(&mut self, sid: [u8; 16], gno: i64) {
+        self.add_interval(sid, gno, gno.saturating_add(1));
+    }
+
+    // adds [start, end), merging with the ranges it touches
+    pub fn add_interval(&mut self, sid: [u8; 16], start: i64, end: i64) {
+        if start >= end {
+            return;
+        }
+        let intervals = self.sets.entry(sid).or_default();
+        intervals.push((start, end));
+        intervals.sort_unstable();
+        let mut merged: Vec<(i64, i64)> = Vec::with_capacity(intervals.len());
+        for (start, end) in intervals.drain(..) {
+            match merged.last_mut() {
+                Some(last) if start <= last.1 => last.1 = last.1.max(end),
+                _ => merged.push((start, end)),
+            }
+        }
+        *intervals = merged;
+    }
+
+    pub fn intervals(&self, sid: &[u8; 16]) -> &[(i64, i64)] {
+        self.sets.get(sid).map_or(&[], |i| i.as_slice())
+    }
+
+    pub fn sids(&self) -> impl Iterator<Item = &[u8; 16]> {
+        self.sets.keys()
+    }
+
+    // adds every GTID of `other`
+    pub fn add_set(&mut self, other: &GtidSet) {
+        for (sid, intervals) in &other.sets {
+            for (start, end) in intervals {
+                self.add_interval(*sid, *start, *end);
+            }
+        }
+    }
+
+    // the GTIDs in either set, e.g. two streams' transactions, counting the ones both have once
+    pub fn union(&self, other: &GtidSet) -> GtidSet {
+        let mut set = self.clone();
+        set.add_set(other);
+        set
+    }
+
+    // the GTIDs of this set that aren't in `other`, e.g. what a replica executing `other` is missing
+    pub fn subtract(&self, other: &GtidSet) -> GtidSet {
+        let mut set = GtidSet::new();
+        for (sid, intervals) in &self.sets {
+            let removed = other.intervals(sid);
+            let mut kept = vec![];
+            for &(first, end) in intervals {
+                let mut start = first;
+                for &(removed_start, removed_end) in removed.iter().filter(|(s, e)| *s < end && *e > first) {
+                    if removed_start > start {
+                        kept.push((start, removed_start));
+                    }
+                    start = start.max(removed_end);
+                }
+                if start < end {
+                    kept.push((start, end));
+                }
+            }
+            if !kept.is_empty() {
+                set.sets.insert(*sid, kept);
+            }
+        }
+        set
+    }
+
+    // the GTIDs in both sets
+    pub fn intersect(&self, other: &GtidSet) -> GtidSet {
+        let mut set = GtidSet::new();
+        for (sid, intervals) in &self.sets {
+            let mut common = vec![];
+            for &(start, end) in intervals {
+                for &(other_start, other_end) in other.intervals(sid) {
+                    let (start, end) = (start.max(other_start), end.min(other_end));
+                    if start < end {
+                        common.push((start, end));
+                    }
+                }
+            }
+            if !common.is_empty() {
+                set.sets.insert(*sid, common);
+            }
+        }
+        set
+    }
+
+    // whether every GTID of `other` is in this set, e.g. a replica having executed up to a checkpoint
+    pub fn contains_set(&self, other: &GtidSet) -> bool {
+        other.subtract(self).is_empty()
+    }
+
+    // the number of transactions in the set
+    pub fn count(&self) -> u64 {
+        self.sets.values().flatten().map(|(start, end)| (end - start) as u64).sum()
+    }
+
+    // the binary form Previous_gtids events and COM_BINLOG_DUMP_GTID use: a sid count, then
+    // each sid with its interval count and [start, end) pairs, all little-endian
+    pub fn encode(&self) -> Vec<u8> {
+        let mut bytes = vec![];
+        bytes.extend_from_slice(&(self.sets.len() as u64).to_le_bytes());
+        for (sid, intervals) in &self.sets {
+            bytes.extend_from_slice(sid);
+            bytes.extend_from_slice(&(intervals.len() as u64).to_le_bytes());
+            for (start, end) in intervals {
+                bytes.extend_from_slice(&start.to_le_bytes());
+                bytes.extend_from_slice(&end.to_le_bytes());
+            }
+        }
+        bytes
+    }
+}
+
+// Whether a reader that has executed `checkpoint` can resume from a binlog file, given the file's
+// Previous_gtids, or from a server, given its @@gtid_purged: the transactions before the file, or
+// purged from the server, can't be read any more, so the checkpoint needs to have all of them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ResumeCheck {
+    Safe,
+    // the transactions the checkpoint lacks that can't be read any more; resuming skips them
+    Missing(GtidSet),
+    // the file has no Previous_gtids, as before MySQL 5.6
+    Unknown,
+}
+
+impl ResumeCheck {
+    pub fn new(checkpoint: &GtidSet, unavailable: &GtidSet) -> Self {
+        let missing = unavailable.subtract(checkpoint);
+        match missing.is_empty() {
+            true => ResumeCheck::Safe,
+            false => ResumeCheck::Missing(missing),
+        }
+    }
+
+    pub fn is_safe(&self) -> bool {
+        *self == ResumeCheck::Safe
+    }
+}
+
+// MariaDB's GTIDs: replication domain, originating server and sequence number, written 0-1-100
+// https://mariadb.com/kb/en/gtid/
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct MariaDbGtid {
+    pub domain_id: u32,
+    pub server_id: u32,
+    pub seq_no: u64,
+}
+
+impl fmt::Display for MariaDbGtid {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}-{}-{}", self.domain_id, self.server_id, self.seq_no)
+    }
+}
+
+impl FromStr for MariaDbGtid {
+    type Err = GtidParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let bad = || GtidParseError::BadMariaDbGtid(s.to_owned());
+        let mut parts = s.trim().splitn(3, '-').map(|part| part.parse::<u64>().map_err(|_| bad()));
+        let mut next = || parts.next().unwrap_or_else(|| Err(bad()));
+        let domain_id = u32::try_from(next()?).map_err(|_| bad())?;
+        let server_id = u32::try_from(next()?).map_err(|_| bad())?;
+        let seq_no = next()?;
+        Ok(MariaDbGtid { domain_id, server_id, seq_no })
+    }
+}
+
+// A MariaDB GTID position: the last GTID of each replication domain, as in @@gtid_slave_pos, or
+// of each server of a domain, as in Gtid_list events. Written comma separated: 0-1-100,1-2-5.
+// Sequence numbers only grow within a domain, so positions compare domain by domain.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct MariaDbGtidList {
+    gtids: Vec<MariaDbGtid>,
+}
+
+impl MariaDbGtidList {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.gtids.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.gtids.len()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &MariaDbGtid> {
+        self.gtids.iter()
+    }
+
+    pub fn push(&mut self, gtid: MariaDbGtid) {
+        self.gtids.push(gtid);
+    }
+
+    // the latest GTID of `domain_id`
+    pub fn domain(&self, domain_id: u32) -> Option<&MariaDbGtid> {
+        self.gtids.iter().filter(|gtid| gtid.domain_id == domain_id).max_by_key(|gtid| gtid.seq_no)
+    }
+
+    // whether the position is at or past `gtid` in its domain
+    pub fn contains(&self, gtid: &MariaDbGtid) -> bool {
+        self.domain(gtid.domain_id).is_some_and(|latest| latest.seq_no >= gtid.seq_no)
+    }
+
+    // whether the position is at or past `other` in each of its domains
+    pub fn contains_list(&self, other: &MariaDbGtidList) -> bool {
+        other.gtids.iter().all(|gtid| self.contains(gtid))
+    }
+
+    // moves the position of the GTID's domain to it, as replicating its transaction does
+    pub fn update(&mut self, gtid: MariaDbGtid) {
+        // in the place of the domain's first
+        let first = self.gtids.iter().position(|g| g.domain_id == gtid.domain_id);
+        self.gtids.retain(|g| g.domain_id != gtid.domain_id);
+        match first {
+            Some(i) => self.gtids.insert(i, gtid),
+            None => self.gtids.push(gtid),
+        }
+    }
+}
+
+impl From<Vec<MariaDbGtid>> for MariaDbGtidList {
+    fn from(gtids: Vec<MariaDbGtid>) -> Self {
+        MariaDbGtidList { gtids }
+    }
+}
+
+impl FromIterator<MariaDbGtid> for MariaDbGtidList {
+    fn from_iter<T: IntoIterator<Item = MariaDbGtid>>(iter: T) -> Self {
+        MariaDbGtidList { gtids: iter.into_iter().collect() }
+    }
+}
+
+impl fmt::Display for MariaDbGtidList {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, gtid) in self.gtids.iter().enumerate() {
+            if i > 0 {
+                f.write_str(",")?;
+            }
+            write!(f, "{}", gtid)?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for MariaDbGtidList {
+    type Err = GtidParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.split(',').map(str::trim).filter(|p| !p.is_empty()).map(str::parse).collect()
+    }
+}
+
+pub fn format_uuid(sid: &[u8; 16]) -> String {
+    let hex: String = sid.iter().map(|b| format!("{:02x}", b)).collect();
+    format!("{}-{}-{}-{}-{}", &hex[..8], &hex[8..12], &hex[12..16], &hex[16..20], &hex[20..])
+}
+
+pub fn parse_uuid(s: &str) -> Result<[u8; 16], GtidParseError> {
+    let hex: String = s.chars().filter(|c| *c != '-').collect();
+    if hex.len() != 32 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(GtidParseError::BadUuid(s.to_owned()));
+    }
+    let mut sid = [0u8; 16];
+    for (i, byte) in sid.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&hex[i * 2..i * 2 + 2], 16).map_err(|_| GtidParseError::BadUuid(s.to_owned()))?;
+    }
+    Ok(sid)
+}
+
+// uuid:1-5:7,uuid2:1-3 as printed by SELECT @@gtid_executed; intervals are inclusive in text
+impl fmt::Display for GtidSet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, (sid, intervals)) in self.sets.iter().enumerate() {
+            if i > 0 {
+                f.write_str(",")?;
+            }
+            f.write_str(&format_uuid(sid))?;
+            for (start, end) in intervals {
+                if end - start == 1 {
+                    write!(f, ":{}", start)?;
+                } else {
+                    write!(f, ":{}-{}", start, end - 1)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for GtidSet {
+    type Err = GtidParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut set = GtidSet::new();
+        // the server wraps long sets over several lines
+        for part in s.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+            let mut fields = part.split(':');
+            let sid = parse_uuid(fields.next().unwrap_or_default().trim())?;
+            for interval in fields {
+                let bad_interval = || GtidParseError::BadInterval(interval.to_owned());
+                let (start, end) = match interval.split_once('-') {
+                    Some((start, end)) => (start, end),
+                    None => (interval, interval),
+                };
+                let start: i64 = start.trim().parse().map_err(|_| bad_interval())?;
+                let end: i64 = end.trim().parse().map_err(|_| bad_interval())?;
+                if start < 1 || end < start {
+                    return Err(bad_interval());
+                }
+                // kept as [start, end + 1), so the last gno is one short of i64::MAX, as the server's is
+                let end = end.checked_add(1).ok_or_else(bad_interval)?;
+                set.add_interval(sid, start, end);
+            }
+        }
+        Ok(set)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::event::{Event, EventData, TypeCode};
+    use crate::gtid::{GtidSet, MariaDbGtid, MariaDbGtidList, ResumeCheck};
+
+    #[test]
+    fn test_add_merges_intervals() {
+        //given
+        let sid = [7u8; 16];
+        let mut set = GtidSet::new();
+
+        //when
+        set.add_interval(sid, 1, 4);
+        set.add_interval(sid, 6, 8);
+        set.add(sid, 4);
+        set.add(sid, 5);
+
+        //then
+        assert_eq!(set.intervals(&sid), &[(1, 8)]);
+        assert!(set.contains(&sid, 7));
+        assert!(!set.contains(&sid, 8));
+        assert!(!set.contains(&[0u8; 16], 1));
+        set.add(sid, i64::MAX);
+        assert_eq!(set.intervals(&sid), &[(1, 8)]);
+    }
+
+    #[test]
+    fn test_set_operations() {
+        //given
+        let a: GtidSet = "3e11fa47-71ca-11e1-9e33-c80aa9429562:1-10:20-30,00000000-0000-0000-0000-000000000001:1-3".parse().unwrap();
+        let b: GtidSet = "3e11fa47-71ca-11e1-9e33-c80aa9429562:5-7:10-25,00000000-0000-0000-0000-000000000002:1".parse().unwrap();
+
+        //when
+        let union = a.union(&b);
+        let missing = a.subtract(&b);
+        let common = a.intersect(&b);
+
+        //then
+        assert_eq!(union.to_string(), "00000000-0000-0000-0000-000000000001:1-3,00000000-0000-0000-0000-000000000002:1,3e11fa47-71ca-11e1-9e33-c80aa9429562:1-30");
+        assert_eq!(missing.to_string(), "00000000-0000-0000-0000-000000000001:1-3,3e11fa47-71ca-11e1-9e33-c80aa9429562:1-4:8-9:26-30");
+        assert_eq!(common.to_string(), "3e11fa47-71ca-11e1-9e33-c80aa9429562:5-7:10:20-25");
+        assert_eq!(missing.union(&common).union(&b.subtract(&a)), union);
+        assert!(union.contains_set(&a) && union.contains_set(&b));
+        assert!(!a.contains_set(&b));
+        assert!(a.contains_set(&GtidSet::new()));
+        assert!(b.subtract(&union).is_empty());
+        assert_eq!((a.count(), common.count(), union.count()), (24, 10, 34));
+    }
+
+    #[test]
+    fn test_resume_check() {
+        //given
+        let previous_gtids: GtidSet = "3e11fa47-71ca-11e1-9e33-c80aa9429562:1-100".parse().unwrap();
+        let caught_up: GtidSet = "3e11fa47-71ca-11e1-9e33-c80aa9429562:1-120,00000000-0000-0000-0000-000000000001:1".parse().unwrap();
+        let behind: GtidSet = "3e11fa47-71ca-11e1-9e33-c80aa9429562:1-40:45-60".parse().unwrap();
+
+        //when
+        let safe = ResumeCheck::new(&caught_up, &previous_gtids);
+        let purged = ResumeCheck::new(&behind, &previous_gtids);
+
+        //then
+        assert!(safe.is_safe());
+        assert_eq!(purged, ResumeCheck::Missing("3e11fa47-71ca-11e1-9e33-c80aa9429562:41-44:61-100".parse().unwrap()));
+        assert!(!purged.is_safe());
+        assert!(ResumeCheck::new(&GtidSet::new(), &GtidSet::new()).is_safe());
+    }
+
+    #[test]
+    fn test_parse_and_format() {
+        //given
+        let text = "3E11FA47-71CA-11E1-9E33-C80AA9429562:1-5:7:6,\n00000000-0000-0000-0000-000000000001:3";
+
+        //when
+        let set: GtidSet = text.parse().unwrap();
+
+        //then
+        assert_eq!(set.to_string(), "00000000-0000-0000-0000-000000000001:3,3e11fa47-71ca-11e1-9e33-c80aa9429562:1-7");
+        assert_eq!(set.to_string().parse::<GtidSet>().unwrap(), set);
+        assert!("nope:1".parse::<GtidSet>().is_err());
+        assert!("3e11fa47-71ca-11e1-9e33-c80aa9429562:5-1".parse::<GtidSet>().is_err());
+        assert!("3e11fa47-71ca-11e1-9e33-c80aa9429562:1-9223372036854775807".parse::<GtidSet>().is_err());
+    }
+
+    #[test]
+    fn test_parse_mariadb_gtid() {
+        //given
+        let text = "0-1-100";
+
+        //when
+        let gtid: MariaDbGtid = text.parse().unwrap();
+
+        //then
+        assert_eq!(gtid, MariaDbGtid { domain_id: 0, server_id: 1, seq_no: 100 });
+        assert_eq!(gtid.to_string(), text);
+        assert!("0-1".parse::<MariaDbGtid>().is_err());
+        assert!("0-1-x".parse::<MariaDbGtid>().is_err());
+        assert!("4294967296-1-1".parse::<MariaDbGtid>().is_err());
+    }
+
+    #[test]
+    fn test_mariadb_gtid_list() {
+        //given
+        let text = "0-1-100, 1-2-5,0-3-90";
+
+        //when
+        let mut list: MariaDbGtidList = text.parse().unwrap();
+        let before = list.clone();
+        list.update("0-3-101".parse().unwrap());
+        list.update("2-3-1".parse().unwrap());
+
+        //then
+        assert_eq!(before.to_string(), "0-1-100,1-2-5,0-3-90");
+        assert_eq!(before.domain(0), Some(&MariaDbGtid { domain_id: 0, server_id: 1, seq_no: 100 }));
+        assert!(before.contains(&"0-9-100".parse().unwrap()));
+        assert!(!before.contains(&"1-2-6".parse().unwrap()));
+        assert!(!before.contains(&"2-1-1".parse().unwrap()));
+        assert_eq!(list.to_string(), "0-3-101,1-2-5,2-3-1");
+        assert!(list.contains_list(&before));
+        assert!(!before.contains_list(&list));
+        assert!("".parse::<MariaDbGtidList>().unwrap().is_empty());
+        assert!("0-1-100,x".parse::<MariaDbGtidList>().is_err());
+    }
+
+    #[test]
+    fn test_encode_as_previous_gtids() {
+        //given
+        let set: GtidSet = "3e11fa47-71ca-11e1-9e33-c80aa9429562:1-5:7,00000000-0000-0000-0000-000000000001:3".parse().unwrap();
+
+        //when
+        let encoded = set.encode();
+        let decoded = Event::parse_event_data_by_type_code(TypeCode::PreviousGtidsLogEvent, &encoded).unwrap();
+
+        //then
+        assert_eq!(encoded.len(), 8 + 2 * (16 + 8) + 3 * 16);
+        match &decoded {
+            Some(EventData::PreviousGtidsLogEvent { gtids }) => assert_eq!(gtids, &set),
+            other => panic!("unexpected {:?}", other),
+        }
+        let json = serde_json::to_string(&decoded).unwrap();
+        assert!(json.contains(r#"{"sid":"3e11fa47-71ca-11e1-9e33-c80aa9429562","intervals":[[1,6],[7,8]]}"#), "{}", json);
+        assert_eq!(serde_json::from_str::<Option<EventData>>(&json).unwrap(), decoded);
+    }
+
+    #[test]
+    fn test_format_is_canonical() {
+        //given
+        let uuid = "3e11fa47-71ca-11e1-9e33-c80aa9429562";
+        let texts = [
+            (format!("{}:1-3:2-5", uuid), format!("{}:1-5", uuid)),
+            (format!("{}:4-6:1-3", uuid), format!("{}:1-6", uuid)),
+            (format!("{}:7:1-3:5", uuid), format!("{}:1-3:5:7", uuid)),
+            (format!("{0}:1,{0}:2", uuid), format!("{}:1-2", uuid)),
+            (format!("{}:9223372036854775806", uuid), format!("{}:9223372036854775806", uuid)),
+            (String::new(), String::new()),
+        ];
+
+        //when
+        let sets: Vec<GtidSet> = texts.iter().map(|(text, _)| text.parse().unwrap()).collect();
+
+        //then
+        for (set, (text, canonical)) in sets.iter().zip(&texts) {
+            assert_eq!(&set.to_string(), canonical, "{}", text);
+            assert_eq!(&set.to_string().parse::<GtidSet>().unwrap(), set, "{}", text);
+        }
+        assert!(format!("{}:1-9223372036854775807", uuid).parse::<GtidSet>().is_err());
+        assert!(format!("{}:0-3", uuid).parse::<GtidSet>().is_err());
+    }
+}
@@ -0,0 +1,147 @@
+use crate::context::ParseContext;
+use crate::errors::BinlogFileError;
+use crate::event::{Event, EventData, GtidEvent};
+use crate::rows::RowsEvent;
+use crate::table_map::TableMapEvent;
+
+// Callback alternative to iterating and matching on EventData. Every method defaults to doing
+// nothing; on_event dispatches to the typed methods and can be overridden to see everything.
+pub trait EventHandler {
+    type Error: From<BinlogFileError>;
+
+    fn on_event(&mut self, event: &Event, data: &EventData) -> Result<(), Self::Error> {
+        match data {
+            EventData::FormatDescriptionEvent { .. } => self.on_format_description(event, data),
+            EventData::QueryEvent { schema, query, .. } => self.on_query(event, schema, query),
+            EventData::TableMapEvent(table_map) => self.on_table_map(event, table_map),
+            EventData::WriteRowsEvent(rows) => self.on_write_rows(event, rows),
+            EventData::UpdateRowsEvent(rows) => self.on_update_rows(event, rows),
+            EventData::DeleteRowsEvent(rows) => self.on_delete_rows(event, rows),
+            EventData::XidEvent { xid } => self.on_xid(event, *xid),
+            EventData::GtidLogEvent(gtid) | EventData::AnonymousGtidLogEvent(gtid) => self.on_gtid(event, gtid),
+            EventData::RotateEvent { position, next_file } => self.on_rotate(event, *position, next_file),
+            EventData::HeartbeatLogEvent { .. } => self.on_heartbeat(event),
+            _ => self.on_other(event, data),
+        }
+    }
+
+    fn on_format_description(&mut self, _event: &Event, _data: &EventData) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    fn on_query(&mut self, _event: &Event, _schema: &str, _query: &str) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    fn on_table_map(&mut self, _event: &Event, _table_map: &TableMapEvent) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    fn on_write_rows(&mut self, _event: &Event, _rows: &RowsEvent) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    fn on_update_rows(&mut self, _event: &Event, _rows: &RowsEvent) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    fn on_delete_rows(&mut self, _event: &Event, _rows: &RowsEvent) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    fn on_xid(&mut self, _event: &Event, _xid: u64) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    // GTID and anonymous GTID events
+    fn on_gtid(&mut self, _event: &Event, _gtid: &GtidEvent) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    fn on_rotate(&mut self, _event: &Event, _position: u64, _next_file: &str) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    fn on_heartbeat(&mut self, _event: &Event) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    fn on_other(&mut self, _event: &Event, _data: &EventData) -> Result<(), Self::Error> {
+        Ok(())
+    }
+}
+
+// Parses each event through `ctx` and hands it to `handler`, stopping at the first error.
+pub fn run<It, H>(events: It, ctx: &mut ParseContext, handler: &mut H) -> Result<(), H::Error> where
+    It: IntoIterator<Item = Result<Event, BinlogFileError>>,
+    H: EventHandler + ?Sized
+{
+    for event in events {
+        let event = event?;
+        let data = event.parsed(ctx).map_err(BinlogFileError::from)?;
+        handler.on_event(&event, &data)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::context::ParseContext;
+    use crate::errors::BinlogFileError;
+    use crate::event::Event;
+    use crate::event::tests::{event_bytes, users_rows, users_table_map};
+    use crate::handler::{run, EventHandler};
+    use crate::rows::RowsEvent;
+
+    #[derive(Default)]
+    struct Counter {
+        queries: Vec<String>,
+        written_rows: usize,
+        xids: Vec<u64>,
+    }
+
+    impl EventHandler for Counter {
+        type Error = BinlogFileError;
+
+        fn on_query(&mut self, _event: &Event, _schema: &str, query: &str) -> Result<(), Self::Error> {
+            self.queries.push(query.to_owned());
+            Ok(())
+        }
+
+        fn on_write_rows(&mut self, _event: &Event, rows: &RowsEvent) -> Result<(), Self::Error> {
+            self.written_rows += rows.rows.len();
+            Ok(())
+        }
+
+        fn on_xid(&mut self, _event: &Event, xid: u64) -> Result<(), Self::Error> {
+            self.xids.push(xid);
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_run_handler() {
+        //given
+        let mut query = vec![0, 0, 0, 0, 0, 0, 0, 0, 4, 0, 0, 0, 0];
+        query.extend_from_slice(b"test\x00BEGIN");
+        let table_map = users_table_map(&[3], &[], 0x00, &[]);
+        let write_rows = users_rows(1, &[0x01, 0x00, 7, 0, 0, 0, 0x00, 8, 0, 0, 0]);
+        let mut bytes = event_bytes(2, &query);
+        bytes.extend(event_bytes(19, &table_map));
+        bytes.extend(event_bytes(30, &write_rows));
+        bytes.extend(event_bytes(16, &42u64.to_le_bytes()));
+        let mut reader = &bytes[..];
+        let events = std::iter::from_fn(|| {
+            if reader.is_empty() { None } else { Some(Event::parse(&mut reader, 4).map_err(BinlogFileError::from)) }
+        });
+        let mut counter = Counter::default();
+
+        //when
+        run(events, &mut ParseContext::new(), &mut counter).unwrap();
+
+        //then
+        assert_eq!(counter.queries, vec!["BEGIN".to_owned()]);
+        assert_eq!(counter.written_rows, 2);
+        assert_eq!(counter.xids, vec![42]);
+    }
+}
@@ -0,0 +1,169 @@
+use serde_json::{Map, Number, Value as Json};
+use crate::utils::base64;
+
+// MySQL's binary JSON, as JSON columns are stored in rows events.
+// https://dev.mysql.com/doc/dev/mysql-server/latest/json__binary_8h.html
+const SMALL_OBJECT: u8 = 0x00;
+const LARGE_OBJECT: u8 = 0x01;
+const SMALL_ARRAY: u8 = 0x02;
+const LARGE_ARRAY: u8 = 0x03;
+const LITERAL: u8 = 0x04;
+const INT16: u8 = 0x05;
+const UINT16: u8 = 0x06;
+const INT32: u8 = 0x07;
+const UINT32: u8 = 0x08;
+const INT64: u8 = 0x09;
+const UINT64: u8 = 0x0a;
+const DOUBLE: u8 = 0x0b;
+const STRING: u8 = 0x0c;
+const OPAQUE: u8 = 0x0f;
+
+// The document in a JSON column's bytes, None when they're malformed. An empty value, which the
+// server writes for a JSON NULL set by some older versions, is null. Opaque values (DECIMAL and
+// temporal values inside the document) are given the way mysqlbinlog shows them,
+// "base64:type<n>:<data>".
+pub(crate) fn decode(bytes: &[u8]) -> Option<Json> {
+    match bytes.split_first() {
+        None => Some(Json::Null),
+        Some((&type_byte, data)) => value(type_byte, data),
+    }
+}
+
+// A document as MySQL prints it, e.g. in mysqlbinlog output: {"a": [1, 2]} with a space after
+// each colon and comma.
+pub(crate) fn mysql_text(document: &Json) -> String {
+    match document {
+        Json::Array(values) => format!("[{}]", values.iter().map(mysql_text).collect::<Vec<_>>().join(", ")),
+        Json::Object(members) => {
+            let members: Vec<String> = members.iter()
+                .map(|(key, value)| format!("{}: {}", Json::from(key.as_str()), mysql_text(value)))
+                .collect();
+            format!("{{{}}}", members.join(", "))
+        }
+        scalar => scalar.to_string(),
+    }
+}
+
+fn value(type_byte: u8, data: &[u8]) -> Option<Json> {
+    match type_byte {
+        SMALL_OBJECT => container(data, false, true),
+        LARGE_OBJECT => container(data, true, true),
+        SMALL_ARRAY => container(data, false, false),
+        LARGE_ARRAY => container(data, true, false),
+        LITERAL => match data.first()? {
+            0 => Some(Json::Null),
+            1 => Some(Json::Bool(true)),
+            2 => Some(Json::Bool(false)),
+            _ => None,
+        },
+        INT16 => Some(i16::from_le_bytes(fixed(data)?).into()),
+        UINT16 => Some(u16::from_le_bytes(fixed(data)?).into()),
+        INT32 => Some(i32::from_le_bytes(fixed(data)?).into()),
+        UINT32 => Some(u32::from_le_bytes(fixed(data)?).into()),
+        INT64 => Some(i64::from_le_bytes(fixed(data)?).into()),
+        UINT64 => Some(u64::from_le_bytes(fixed(data)?).into()),
+        DOUBLE => Number::from_f64(f64::from_le_bytes(fixed(data)?)).map(Json::Number),
+        STRING => {
+            let (len, data) = varlen(data)?;
+            Some(Json::String(String::from_utf8_lossy(data.get(..len)?).into_owned()))
+        }
+        OPAQUE => {
+            let (&column_type, data) = data.split_first()?;
+            let (len, data) = varlen(data)?;
+            Some(Json::String(format!("base64:type{}:{}", column_type, base64(data.get(..len)?))))
+        }
+        _ => None,
+    }
+}
+
+// An object or array: element count and byte size, for objects the key entries (offset and
+// length), then an entry per value, either the value itself for small scalars or its offset.
+// Offsets count from the start of `data`.
+fn container(data: &[u8], large: bool, object: bool) -> Option<Json> {
+    let word = if large { 4 } else { 2 };
+    let count = uint(data, 0, word)?;
+    let size = uint(data, word, word)?;
+    let data = data.get(..size)?;
+    let key_entries = 2 * word;
+    let value_entries = key_entries + if object { count * (word + 2) } else { 0 };
+    let mut values = Vec::with_capacity(count.min(data.len()));
+    for i in 0..count {
+        let entry = value_entries + i * (1 + word);
+        let type_byte = *data.get(entry)?;
+        let inlined = match type_byte {
+            LITERAL | INT16 | UINT16 => true,
+            INT32 | UINT32 => large,
+            _ => false,
+        };
+        let value = if inlined {
+            value(type_byte, data.get(entry + 1..entry + 1 + word)?)?
+        } else {
+            value(type_byte, data.get(uint(data, entry + 1, word)?..)?)?
+        };
+        values.push(value);
+    }
+    if !object {
+        return Some(Json::Array(values));
+    }
+    let mut map = Map::new();
+    for (i, value) in values.into_iter().enumerate() {
+        let entry = key_entries + i * (word + 2);
+        let offset = uint(data, entry, word)?;
+        let len = uint(data, entry + word, 2)?;
+        let key = String::from_utf8_lossy(data.get(offset..offset + len)?).into_owned();
+        map.insert(key, value);
+    }
+    Some(Json::Object(map))
+}
+
+fn uint(data: &[u8], at: usize, len: usize) -> Option<usize> {
+    let bytes = data.get(at..at + len)?;
+    Some(bytes.iter().rev().fold(0usize, |n, b| n << 8 | usize::from(*b)))
+}
+
+fn fixed<const N: usize>(data: &[u8]) -> Option<[u8; N]> {
+    let mut bytes = [0u8; N];
+    bytes.copy_from_slice(data.get(..N)?);
+    Some(bytes)
+}
+
+// a length in 7-bit groups, low group first, the high bit marking that another follows
+fn varlen(data: &[u8]) -> Option<(usize, &[u8])> {
+    let mut len = 0usize;
+    for (i, b) in data.iter().enumerate().take(5) {
+        len |= usize::from(b & 0x7f) << (7 * i);
+        if b & 0x80 == 0 {
+            return Some((len, &data[i + 1..]));
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::json_binary::{decode, mysql_text};
+
+    #[test]
+    fn test_decode_document() {
+        //given
+        // {"a": [1, true, "x"], "b": 3.5}, as written by MySQL 8.0
+        let bytes = [
+            0x00, 0x02, 0x00, 0x2b, 0x00,
+            0x12, 0x00, 0x01, 0x00, 0x13, 0x00, 0x01, 0x00,
+            0x02, 0x14, 0x00, 0x0b, 0x23, 0x00,
+            b'a', b'b',
+            0x03, 0x00, 0x0f, 0x00, 0x05, 0x01, 0x00, 0x04, 0x01, 0x00, 0x0c, 0x0d, 0x00, 0x01, b'x',
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x0c, 0x40,
+        ];
+
+        //when
+        let document = decode(&bytes);
+        let malformed = decode(&[0x00, 0x05, 0x00]);
+
+        //then
+        assert_eq!(document, Some(serde_json::json!({"a": [1, true, "x"], "b": 3.5})));
+        assert_eq!(mysql_text(&document.unwrap()), r#"{"a": [1, true, "x"], "b": 3.5}"#);
+        assert_eq!(malformed, None);
+        assert_eq!(decode(&[]), Some(serde_json::Value::Null));
+    }
+}
@@ -1,6 +1,63 @@
-mod event;
-mod binlog_file;
-mod errors;
+pub mod event;
+pub mod anonymize;
+pub mod avro;
+#[cfg(feature = "arrow")]
+pub mod arrow;
+pub mod binlog_file;
+pub mod binlog_reader;
+pub mod binlog_series;
+pub mod binlog_writer;
+pub mod builder;
+pub mod errors;
+pub mod schema;
+pub mod sql;
+pub mod column;
+pub mod value;
+pub mod table_map;
+pub mod rows;
+pub mod charset;
+pub mod checksum;
+pub mod canal;
+pub mod client;
+#[cfg(feature = "async")]
+pub mod async_client;
+pub mod compression;
+pub mod options;
+pub mod context;
+pub mod csv;
+pub mod debezium;
+#[cfg(any(feature = "mysql", feature = "mysql_async"))]
+pub mod driver;
+pub mod encode;
+pub mod encryption;
+pub mod export;
+pub mod filter;
+pub mod follow;
+pub mod gtid;
+pub mod handler;
+pub mod maxwell;
+pub mod merge;
+pub mod mysqlbinlog;
+pub mod parser;
+#[cfg(feature = "parquet")]
+pub mod parquet;
+pub mod payload;
+pub mod position;
+pub mod pretty;
+#[cfg(feature = "protobuf")]
+pub mod protobuf;
+pub mod rewrite;
+pub mod split;
+pub mod synthetic;
+#[cfg(feature = "tls")]
+pub mod tls;
+pub mod transaction;
+pub mod verify;
+mod auth;
+mod json_binary;
+mod protocol;
+mod serde_repr;
+mod utils;
 
 #[cfg(test)]
 mod tests {
@@ -0,0 +1,173 @@
+use std::collections::HashMap;
+use serde_json::{Map, Value as Json};
+use crate::event::{Event, EventData};
+use crate::export::plain_json;
+use crate::rows::{RowImage, RowsEventKind};
+use crate::table_map::{TableMapColumn, TableMapEvent};
+use crate::utils::{base64, civil_from_days};
+use crate::value::Value;
+
+// Row changes as Maxwell's Daemon writes them, one JSON object per row:
+//
+//     {"database":"test","table":"users","type":"update","ts":1449786310,"xid":940752,"commit":true,
+//      "data":{"id":1,"name":"new"},"old":{"name":"old"}}
+//
+// Maxwell only knows the xid once the transaction commits, so the rows of a transaction are
+// returned together on its XID event, the last marked "commit". `old` holds the previous value
+// of the columns an update changed. Values follow Maxwell's conventions: DATE, TIME, DATETIME and
+// TIMESTAMP (in UTC) as strings, DECIMAL as a number, binary as base64, ENUM as its label and SET
+// as an array of labels when the table map carries them, BIT(1) as a boolean and wider BITs as a
+// number. JSON columns stay in MySQL's binary encoding, base64.
+#[derive(Debug, Default)]
+pub struct MaxwellRecords {
+    tables: HashMap<u64, TableMapEvent>,
+    // the current transaction's rows, waiting for its xid
+    pending: Vec<Map<String, Json>>,
+}
+
+impl MaxwellRecords {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // the records of the transaction `data` commits, none for other events
+    pub fn records(&mut self, event: &Event, data: &EventData) -> Vec<Json> {
+        match data {
+            EventData::TableMapEvent(table_map) => {
+                self.tables.insert(table_map.table_id, table_map.clone());
+            }
+            EventData::WriteRowsEvent(rows) | EventData::UpdateRowsEvent(rows) | EventData::DeleteRowsEvent(rows) => {
+                if let Some(table_map) = self.tables.get(&rows.table_id) {
+                    for change in &rows.rows {
+                        let (kind, data, old) = match (rows.kind, &change.before, &change.after) {
+                            (RowsEventKind::Write, _, Some(after)) => ("insert", row_json(after, table_map), None),
+                            (RowsEventKind::Delete, Some(before), _) => ("delete", row_json(before, table_map), None),
+                            (RowsEventKind::Update, Some(before), Some(after)) => {
+                                ("update", row_json(after, table_map), Some(old_json(before, after, table_map)))
+                            }
+                            _ => continue,
+                        };
+                        let mut record = Map::new();
+                        record.insert("database".to_owned(), table_map.schema.as_str().into());
+                        record.insert("table".to_owned(), table_map.table.as_str().into());
+                        record.insert("type".to_owned(), kind.into());
+                        record.insert("ts".to_owned(), event.timestamp().into());
+                        record.insert("data".to_owned(), data);
+                        if let Some(old) = old {
+                            record.insert("old".to_owned(), old);
+                        }
+                        self.pending.push(record);
+                    }
+                }
+            }
+            EventData::XidEvent { xid } => return self.commit(Some(*xid)),
+            // non-transactional tables commit with a statement instead
+            EventData::QueryEvent { query, .. } if query.eq_ignore_ascii_case("COMMIT") => return self.commit(None),
+            _ => {}
+        }
+        vec![]
+    }
+
+    fn commit(&mut self, xid: Option<u64>) -> Vec<Json> {
+        let count = self.pending.len();
+        self.pending.drain(..).enumerate().map(|(i, mut record)| {
+            let data = record.remove("data").unwrap_or_default();
+            let old = record.remove("old");
+            if let Some(xid) = xid {
+                record.insert("xid".to_owned(), xid.into());
+            }
+            if i + 1 == count {
+                record.insert("commit".to_owned(), true.into());
+            }
+            record.insert("data".to_owned(), data);
+            if let Some(old) = old {
+                record.insert("old".to_owned(), old);
+            }
+            Json::Object(record)
+        }).collect()
+    }
+}
+
+fn column_name(table_map: &TableMapEvent, index: usize) -> String {
+    match table_map.columns.get(index).and_then(|c| c.name.as_ref()) {
+        Some(name) => name.clone(),
+        None => format!("@{}", index + 1),
+    }
+}
+
+fn row_json(image: &RowImage, table_map: &TableMapEvent) -> Json {
+    let columns: Map<String, Json> = image.values().iter().enumerate()
+        .filter_map(|(i, value)| Some((column_name(table_map, i), maxwell_value(value.as_ref()?, table_map.columns.get(i)))))
+        .collect();
+    Json::Object(columns)
+}
+
+fn old_json(before: &RowImage, after: &RowImage, table_map: &TableMapEvent) -> Json {
+    let columns: Map<String, Json> = before.values().iter().enumerate()
+        .filter(|(i, value)| after.values().get(*i).is_some_and(|after| after.is_some() && after != *value))
+        .filter_map(|(i, value)| Some((column_name(table_map, i), maxwell_value(value.as_ref()?, table_map.columns.get(i)))))
+        .collect();
+    Json::Object(columns)
+}
+
+fn maxwell_value(value: &Value, column: Option<&TableMapColumn>) -> Json {
+    match value {
+        Value::Decimal(v) => v.parse::<f64>().map(Json::from).unwrap_or_else(|_| v.as_str().into()),
+        Value::Bytes(v) | Value::Json(v) => base64(v).into(),
+        Value::Timestamp { seconds, micros } => {
+            let (year, month, day) = civil_from_days(i64::from(*seconds) / 86400);
+            let time = seconds % 86400;
+            let fraction = if *micros == 0 { String::new() } else { format!(".{:06}", micros).trim_end_matches('0').to_owned() };
+            format!("{:04}-{:02}-{:02} {:02}:{:02}:{:02}{}", year, month, day, time / 3600, time / 60 % 60, time % 60, fraction).into()
+        }
+        Value::Bit(v) => match column {
+            Some(c) if c.meta == 1 => v.iter().any(|b| *b != 0).into(),
+            _ => v.iter().take(8).fold(0u64, |n, b| n << 8 | u64::from(*b)).into(),
+        },
+        Value::Enum(index) => match column.and_then(|c| c.enum_values.as_ref()) {
+            Some(labels) => labels.get((*index as usize).wrapping_sub(1)).map(|l| l.as_str()).unwrap_or("").into(),
+            None => (*index).into(),
+        },
+        Value::Set(bits) => match column.and_then(|c| c.set_values.as_ref()) {
+            Some(labels) => labels.iter().enumerate()
+                .filter(|(i, _)| *i < 64 && bits & (1 << i) != 0)
+                .map(|(_, l)| Json::from(l.as_str()))
+                .collect(),
+            None => (*bits).into(),
+        },
+        // numbers, strings, and DATE/TIME/DATETIME in the same text as Value's serde form
+        _ => plain_json(value),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::context::ParseContext;
+    use crate::event::Event;
+    use crate::event::tests::{parse_events, users_rows, users_table_map};
+    use crate::maxwell::MaxwellRecords;
+
+    #[test]
+    fn test_transaction_records() {
+        //given
+        // test.users (id INT, created DATE) with column names
+        let table_map = users_table_map(&[3, 10], &[], 0x00, b"\x04\x0b\x02id\x07created");
+        let write_rows = users_rows(2, &[0x03, 0x00, 7, 0, 0, 0, 0x3f, 0xd0, 0x0f]);
+        // created from 2024-01-31 to 2024-02-01
+        let update_rows = users_rows(2, &[0x03, 0x03, 0x00, 7, 0, 0, 0, 0x3f, 0xd0, 0x0f, 0x00, 7, 0, 0, 0, 0x41, 0xd0, 0x0f]);
+        let events: Vec<Event> = parse_events(16, &[(19, table_map), (30, write_rows), (31, update_rows), (16, 42u64.to_le_bytes().to_vec())]);
+        let mut ctx = ParseContext::new();
+        let mut maxwell = MaxwellRecords::new();
+
+        //when
+        let per_event: Vec<_> = events.iter().map(|e| maxwell.records(e, &e.parsed(&mut ctx).unwrap())).collect();
+
+        //then
+        assert!(per_event[..3].iter().all(|records| records.is_empty()));
+        assert_eq!(serde_json::to_string(&per_event[3]).unwrap(), concat!(
+            r#"[{"database":"test","table":"users","type":"insert","ts":16,"xid":42,"data":{"id":7,"created":"2024-01-31"}},"#,
+            r#"{"database":"test","table":"users","type":"update","ts":16,"xid":42,"commit":true,"#,
+            r#""data":{"id":7,"created":"2024-02-01"},"old":{"created":"2024-01-31"}}]"#,
+        ));
+    }
+}
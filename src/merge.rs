@@ -0,0 +1,153 @@
+use std::io::Write;
+use crate::binlog_series::{file_name, BinlogSeries};
+use crate::binlog_writer::BinlogWriter;
+use crate::errors::MergeError;
+use crate::event::{Event, EventData, TypeCode};
+use crate::position::BinlogPosition;
+use crate::split::PositionMap;
+
+// Concatenates the files of a series into one binlog, as if the server had never rotated: the
+// output has the first file's FormatDescriptionEvent and Previous_gtids, and only the last
+// file's closing Rotate or Stop. Everything else is copied as it is, with positions and
+// checksums fixed up by a BinlogWriter, so the files have to share an event format, which their
+// FormatDescriptionEvents are checked for.
+//
+//     let series = BinlogSeries::from_dir(dir, "mysql-bin")?;
+//     let summary = merge(series, "merged.000001", BufWriter::new(File::create(output)?))?;
+pub fn merge<W: Write>(series: BinlogSeries, output: &str, writer: W) -> Result<MergeSummary, MergeError> {
+    let mut series = series;
+    let format_description = series.next().ok_or(MergeError::Empty)??;
+    let mut file = series.current_file().map(file_name).unwrap_or_default();
+    let format = event_format(&format_description, &file)?;
+    let mut binlog = BinlogWriter::new(writer, &format_description)?;
+    let mut summary = MergeSummary { files: 1, ..MergeSummary::default() };
+    summary.positions.insert(&file, format_description.offset(), BinlogPosition::new(output, 4));
+    let mut end = format_description.offset() + u64::from(format_description.event_length());
+    // the Rotate or Stop that closed the file before, written only if no other file follows
+    let mut closing: Option<Event> = None;
+    while let Some(event) = series.next() {
+        let event = event?;
+        let name = series.current_file().map(file_name).unwrap_or_default();
+        if name != file {
+            summary.positions.insert(&file, end, BinlogPosition::new(output, binlog.offset()));
+            summary.files += 1;
+            file = name;
+            if event_format(&event, &file)? != format {
+                return Err(MergeError::IncompatibleFormat(file));
+            }
+            closing = None;
+        }
+        summary.positions.insert(&file, event.offset(), BinlogPosition::new(output, binlog.offset()));
+        end = event.offset() + u64::from(event.event_length());
+        match event.type_code() {
+            TypeCode::FormatDescriptionEvent => {}
+            TypeCode::PreviousGtidsLogEvent if summary.files > 1 => {}
+            TypeCode::RotateEvent | TypeCode::StopEvent => closing = Some(event),
+            _ => {
+                if let Some(closing) = closing.take() {
+                    binlog.write_event(&closing)?;
+                }
+                binlog.write_event(&event)?;
+            }
+        }
+    }
+    if let Some(closing) = closing {
+        binlog.write_event(&closing)?;
+    }
+    summary.positions.insert(&file, end, BinlogPosition::new(output, binlog.offset()));
+    summary.end_offset = binlog.offset();
+    binlog.finish()?;
+    Ok(summary)
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MergeSummary {
+    pub files: usize,
+    // the size of the merged binlog
+    pub end_offset: u64,
+    pub positions: PositionMap,
+}
+
+// what events of a file look like: the binlog version and the post-header length of each type
+fn event_format(event: &Event, file: &str) -> Result<(u16, Vec<u8>), MergeError> {
+    if event.type_code() != TypeCode::FormatDescriptionEvent {
+        return Err(MergeError::NoFormatDescription(file.to_owned()));
+    }
+    match Event::parse_event_data_by_type_code(TypeCode::FormatDescriptionEvent, event.try_data()?)? {
+        Some(EventData::FormatDescriptionEvent { binlog_version, post_header_lengths, .. }) => Ok((binlog_version, post_header_lengths)),
+        _ => Err(MergeError::NoFormatDescription(file.to_owned())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+    use crate::binlog_file::BinlogFile;
+    use crate::binlog_series::BinlogSeries;
+    use crate::errors::MergeError;
+    use crate::event::TypeCode;
+    use crate::merge::merge;
+    use crate::position::BinlogPosition;
+    use crate::synthetic::SyntheticBinlog;
+    use crate::value::Value;
+
+    #[test]
+    fn test_merge_series() {
+        //given
+        let dir = std::env::temp_dir().join(format!("binlog-merge-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        SyntheticBinlog::new()
+            .query("shop", "CREATE TABLE item (id INT PRIMARY KEY)")
+            .insert("shop", "item", vec![vec![Value::Int(1)]])
+            .write_to(dir.join("mysql-bin.000001"))
+            .unwrap();
+        std::fs::copy("tests/asset/mysql-bin.100746", dir.join("mysql-bin.000002")).unwrap();
+        let first_size = std::fs::metadata(dir.join("mysql-bin.000001")).unwrap().len();
+        let series = BinlogSeries::from_dir(&dir, "mysql-bin").unwrap();
+
+        //when
+        let summary = merge(series, "merged.000001", vec![]);
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        //then
+        let summary = summary.unwrap();
+        assert_eq!(summary.files, 2);
+        // the first file's Rotate, and the second's FDE and Previous_gtids, are left out
+        let first_rotate = summary.positions.iter().filter(|(file, _, _)| *file == "mysql-bin.000001").last().unwrap().2.pos;
+        assert_eq!(summary.positions.get("mysql-bin.000002", 4), Some(&BinlogPosition::new("merged.000001", first_rotate)));
+        assert_eq!(summary.positions.get("mysql-bin.000002", 154).unwrap().pos, first_rotate);
+        assert_eq!(summary.positions.get("mysql-bin.000002", 1064).unwrap().pos, summary.end_offset);
+        assert!(first_rotate < first_size);
+        assert_eq!(summary.end_offset, first_rotate + (1064 - 154));
+    }
+
+    #[test]
+    fn test_merged_binlog_is_valid() {
+        //given
+        let dir = std::env::temp_dir().join(format!("binlog-merge-valid-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        SyntheticBinlog::new()
+            .query("shop", "CREATE TABLE item (id INT PRIMARY KEY)")
+            .write_to(dir.join("mysql-bin.000001"))
+            .unwrap();
+        std::fs::copy("tests/asset/mysql-bin.100746", dir.join("mysql-bin.000002")).unwrap();
+        let mut merged = vec![];
+
+        //when
+        let summary = merge(BinlogSeries::from_dir(&dir, "mysql-bin").unwrap(), "merged.000001", &mut merged);
+        let empty = merge(BinlogSeries::from_dir(&dir, "nothing").unwrap(), "merged.000001", vec![]);
+        std::fs::remove_dir_all(&dir).unwrap();
+        let mut file = BinlogFile::from_reader(Cursor::new(merged)).unwrap();
+        let report = file.verify().unwrap();
+        let types: Vec<TypeCode> = file.events().map(|e| e.unwrap().type_code()).collect();
+
+        //then
+        assert!(summary.is_ok());
+        assert!(matches!(empty, Err(MergeError::Empty)));
+        assert!(report.is_ok(), "{:?}", report.problems);
+        assert_eq!(types.iter().filter(|t| **t == TypeCode::FormatDescriptionEvent).count(), 1);
+        assert_eq!(types.iter().filter(|t| **t == TypeCode::PreviousGtidsLogEvent).count(), 1);
+        assert_eq!(types.iter().filter(|t| **t == TypeCode::RotateEvent).count(), 1);
+        assert_eq!(types.len(), 5 + 15 - 3);
+    }
+}
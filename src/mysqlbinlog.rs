@@ -0,0 +1,675 @@
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::io::Write;
+use crate::charset::Charset;
+use crate::column::{real_string_type, ColumnType};
+use crate::context::ParseContext;
+use crate::errors::{BinlogFileError, ExportError};
+use crate::event::{Event, EventData, GtidEvent, MARIADB_GTID_STANDALONE};
+use crate::gtid::format_uuid;
+use crate::handler::EventHandler;
+use crate::json_binary;
+use crate::rows::{RowImage, RowsEvent, RowsEventKind};
+use crate::table_map::{TableMapColumn, TableMapEvent};
+use crate::utils::{base64, civil_from_days};
+use crate::value::Value;
+
+// end of each statement, after mysqlbinlog's DELIMITER /*!*/;
+const DELIMITER: &str = "/*!*/;";
+
+// How MysqlbinlogWriter gives row-based events, mysqlbinlog's --base64-output.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Base64Output {
+    // only as the ### pseudo-SQL, for reading
+    #[default]
+    DecodeRows,
+    // also as BINLOG '...' statements of the raw events, FormatDescriptionEvent, table maps and
+    // rows events, so the output can be replayed with `mysql < output.sql`
+    Auto,
+}
+
+// Renders events as `mysqlbinlog --base64-output=DECODE-ROWS -vv` does, for scripts and people
+// used to reading that:
+//
+//     # at 235
+//     #240131 23:59:59 server id 1  end_log_pos 290 CRC32 0x0bd4c3a2 	Table_map: `shop`.`orders` mapped to number 92
+//     # at 290
+//     #240131 23:59:59 server id 1  end_log_pos 334 CRC32 0x1b6a2e31 	Write_rows: table id 92 flags: STMT_END_F
+//     ### INSERT INTO `shop`.`orders`
+//     ### SET
+//     ###   @1=7 /* INT meta=0 nullable=0 is_null=0 */
+//
+// With Base64Output::Auto the row changes are also given as BINLOG statements, which makes the
+// output replayable, including after filtering or rewriting events before they're written.
+//
+// Times are given in UTC where mysqlbinlog uses the local time zone, and the session variables
+// of query events aren't decoded, so only SET TIMESTAMP precedes a statement.
+//
+//     let mut writer = MysqlbinlogWriter::new(std::io::stdout().lock());
+//     handler::run(BinlogFile::from_path(path)?.events(), &mut ParseContext::new(), &mut writer)?;
+//     writer.finish()?;
+pub struct MysqlbinlogWriter<W: Write> {
+    writer: W,
+    verbosity: u8,
+    base64_output: Base64Output,
+    // base64 of the table maps and rows events of the statement being written
+    pending_base64: Vec<String>,
+    tables: HashMap<u64, TableMapEvent>,
+    // the events compressed in TransactionPayloadEvents are parsed apart from the stream's own
+    payload_ctx: ParseContext,
+    schema: Option<String>,
+    started: bool,
+    gtids: bool,
+    hexdump: bool,
+}
+
+impl<W: Write> MysqlbinlogWriter<W> {
+    pub fn new(writer: W) -> Self {
+        MysqlbinlogWriter {
+            writer,
+            verbosity: 2,
+            base64_output: Base64Output::default(),
+            pending_base64: vec![],
+            tables: HashMap::new(),
+            payload_ctx: ParseContext::new(),
+            schema: None,
+            started: false,
+            gtids: false,
+            hexdump: false,
+        }
+    }
+
+    // like -v (1: ### pseudo-SQL for row changes) and -vv (2, the default: with column types);
+    // 0 leaves row changes out
+    pub fn verbosity(mut self, verbosity: u8) -> Self {
+        self.verbosity = verbosity;
+        self
+    }
+
+    pub fn base64_output(mut self, base64_output: Base64Output) -> Self {
+        self.base64_output = base64_output;
+        self
+    }
+
+    // like --hexdump: each event's bytes under its header line, the common header field by field
+    pub fn hexdump(mut self, hexdump: bool) -> Self {
+        self.hexdump = hexdump;
+        self
+    }
+
+    pub fn write_event(&mut self, event: &Event, data: &EventData) -> Result<(), ExportError> {
+        if !self.started {
+            self.started = true;
+            self.writer.write_all(b"/*!50530 SET @@SESSION.PSEUDO_SLAVE_MODE=1*/;\n")?;
+            self.writer.write_all(b"/*!50003 SET @OLD_COMPLETION_TYPE=@@COMPLETION_TYPE,COMPLETION_TYPE=0*/;\n")?;
+            self.writer.write_all(b"DELIMITER /*!*/;\n")?;
+        }
+        if let EventData::HeartbeatLogEvent { .. } = data {
+            return Ok(());
+        }
+        writeln!(self.writer, "# at {}", event.offset())?;
+        let text = self.event_text(event, data)?;
+        write!(self.writer, "#{} server id {}  end_log_pos {} ", short_datetime(event.timestamp()), event.server_id(), event.next_position())?;
+        if let Some(checksum) = event.checksum() {
+            write!(self.writer, "CRC32 0x{:08x} ", checksum)?;
+        }
+        if self.hexdump {
+            let bytes = event.to_bytes().map_err(BinlogFileError::from)?;
+            write!(self.writer, "\n{}# ", hexdump(&bytes, event.offset()))?;
+        }
+        writeln!(self.writer, "\t{}", text)?;
+        Ok(())
+    }
+
+    // writes the closing lines mysqlbinlog ends its output with
+    pub fn finish(mut self) -> Result<W, ExportError> {
+        if self.gtids {
+            writeln!(self.writer, "SET @@SESSION.GTID_NEXT= 'AUTOMATIC' /* added by mysqlbinlog */ {}", DELIMITER)?;
+        }
+        if self.started {
+            self.writer.write_all(b"DELIMITER ;\n# End of log file\n")?;
+            self.writer.write_all(b"/*!50003 SET COMPLETION_TYPE=@OLD_COMPLETION_TYPE*/;\n")?;
+            self.writer.write_all(b"/*!50530 SET @@SESSION.PSEUDO_SLAVE_MODE=0*/;\n")?;
+        }
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+
+    // the header line's description and the lines following it
+    fn event_text(&mut self, event: &Event, data: &EventData) -> Result<String, ExportError> {
+        let text = match data {
+            EventData::FormatDescriptionEvent { binlog_version, server_version, create_timestamp, .. }
+            | EventData::StartEventV3 { binlog_version, server_version, create_timestamp } => {
+                let mut text = format!("Start: binlog v {}, server v {} created {}", binlog_version, server_version, short_datetime(*create_timestamp));
+                if *create_timestamp != 0 {
+                    text.push_str(" at startup");
+                }
+                if event.is_binlog_in_use() {
+                    text.push_str("\n# Warning: this binlog is either in use or was not closed properly.");
+                }
+                if *create_timestamp != 0 {
+                    text.push_str(&format!("\nROLLBACK{}", DELIMITER));
+                }
+                if self.base64_output == Base64Output::Auto {
+                    text.push('\n');
+                    text.push_str(&binlog_statement(&[event])?);
+                }
+                text
+            }
+            EventData::QueryEvent { thread_id, exec_time, error_code, schema, query }
+            | EventData::ExecuteLoadQueryEvent { thread_id, exec_time, error_code, schema, query, .. } => {
+                let mut text = format!("Query\tthread_id={}\texec_time={}\terror_code={}", thread_id, exec_time, error_code);
+                if !schema.is_empty() && self.schema.as_deref() != Some(schema.as_str()) {
+                    text.push_str(&format!("\nuse `{}`{}", schema, DELIMITER));
+                    self.schema = Some(schema.clone());
+                }
+                text.push_str(&format!("\nSET TIMESTAMP={}{}\n{}\n{}", event.timestamp(), DELIMITER, query, DELIMITER));
+                text
+            }
+            EventData::StopEvent => "Stop".to_owned(),
+            EventData::RotateEvent { position, next_file } => format!("Rotate to {}  pos: {}", next_file, position),
+            EventData::IntvarEvent { kind, value } => {
+                let name = if *kind == 2 { "INSERT_ID" } else { "LAST_INSERT_ID" };
+                format!("Intvar\nSET {}={}{}", name, value, DELIMITER)
+            }
+            EventData::RandEvent { seed1, seed2 } => format!("Rand\nSET @@RAND_SEED1={}, @@RAND_SEED2={}{}", seed1, seed2, DELIMITER),
+            EventData::UserVarEvent { name, value } => format!("User_var\nSET @`{}`:={}{}", name, user_var_text(value.as_ref()), DELIMITER),
+            EventData::XidEvent { xid } => format!("Xid = {}\nCOMMIT{}", xid, DELIMITER),
+            EventData::TableMapEvent(table_map) => {
+                self.tables.insert(table_map.table_id, table_map.clone());
+                if self.base64_output == Base64Output::Auto {
+                    self.pending_base64.push(base64(&event.to_bytes().map_err(BinlogFileError::from)?));
+                }
+                format!("Table_map: `{}`.`{}` mapped to number {}", table_map.schema, table_map.table, table_map.table_id)
+            }
+            EventData::WriteRowsEvent(rows) | EventData::UpdateRowsEvent(rows) | EventData::DeleteRowsEvent(rows) => {
+                let name = match rows.kind {
+                    RowsEventKind::Write => "Write_rows",
+                    RowsEventKind::Update => "Update_rows",
+                    RowsEventKind::Delete => "Delete_rows",
+                };
+                let mut text = format!("{}: table id {}", name, rows.table_id);
+                // STMT_END_F
+                let statement_end = rows.flags & 0x0001 != 0;
+                if statement_end {
+                    text.push_str(" flags: STMT_END_F");
+                }
+                if self.base64_output == Base64Output::Auto {
+                    self.pending_base64.push(base64(&event.to_bytes().map_err(BinlogFileError::from)?));
+                    if statement_end {
+                        text.push('\n');
+                        text.push_str(&base64_statement(&std::mem::take(&mut self.pending_base64)));
+                    }
+                }
+                if let Some(table_map) = self.tables.get(&rows.table_id) {
+                    text.push_str(&rows_text(rows, table_map, self.verbosity));
+                }
+                text
+            }
+            EventData::IncidentEvent { incident_type, message } => format!("Incident: {} ({})", message, incident_type),
+            EventData::RowsQueryLogEvent { query } => format!("Rows_query\n# {}", query.replace('\n', "\n# ")),
+            EventData::AnnotateRowsEvent { query } => format!("Annotate_rows:\n#Q> {}", query.replace('\n', "\n#Q> ")),
+            EventData::GtidLogEvent(gtid) => {
+                self.gtids = true;
+                format!("{}\nSET @@SESSION.GTID_NEXT= '{}:{}'{}", gtid_text("GTID", gtid), format_uuid(&gtid.sid), gtid.gno, DELIMITER)
+            }
+            EventData::AnonymousGtidLogEvent(gtid) => {
+                self.gtids = true;
+                format!("{}\nSET @@SESSION.GTID_NEXT= 'ANONYMOUS'{}", gtid_text("Anonymous_GTID", gtid), DELIMITER)
+            }
+            EventData::PreviousGtidsLogEvent { gtids } => {
+                match gtids.is_empty() {
+                    true => "Previous-GTIDs\n# [empty]".to_owned(),
+                    false => format!("Previous-GTIDs\n# {}", gtids.to_string().replace(',', ",\n# ")),
+                }
+            }
+            EventData::XaPrepareLogEvent { one_phase, format_id, gtrid, bqual } => {
+                let xid = format!("X'{}',X'{}',{}", hex(gtrid), hex(bqual), format_id);
+                let statement = if *one_phase { format!("XA COMMIT {} ONE PHASE", xid) } else { format!("XA PREPARE {}", xid) };
+                format!("XA PREPARE\n{}\n{}", statement, DELIMITER)
+            }
+            EventData::TransactionPayloadEvent { compression_type, uncompressed_size, payload } => {
+                let compression = if *compression_type == crate::payload::COMPRESSION_ZSTD { "ZSTD" } else { "NONE" };
+                let mut text = format!(
+                    "Transaction_Payload\tpayload_size={}\tcompression_type={}\tuncompressed_size={}\n# Start of compressed events!",
+                    payload.len(), compression, uncompressed_size
+                );
+                let mut inner = MysqlbinlogWriter::new(vec![])
+                    .verbosity(self.verbosity)
+                    .base64_output(self.base64_output)
+                    .hexdump(self.hexdump);
+                inner.tables = std::mem::take(&mut self.tables);
+                inner.started = true;
+                for payload_event in event.payload_events().map_err(BinlogFileError::from)? {
+                    let payload_event = payload_event?;
+                    let payload_data = payload_event.parsed(&mut self.payload_ctx).map_err(BinlogFileError::from)?;
+                    inner.write_event(&payload_event, &payload_data)?;
+                }
+                self.tables = std::mem::take(&mut inner.tables);
+                self.gtids |= inner.gtids;
+                text.push('\n');
+                text.push_str(String::from_utf8_lossy(&inner.writer).trim_end());
+                text.push_str("\n# End of compressed events!");
+                text
+            }
+            EventData::BinlogCheckpointEvent { file } => format!("Binlog checkpoint {}", file),
+            EventData::MariaDbGtidEvent { domain_id, seq_no, flags, .. } => {
+                let standalone = flags & MARIADB_GTID_STANDALONE != 0;
+                let mut text = format!("GTID {}-{}-{}{}", domain_id, event.server_id(), seq_no, if standalone { "" } else { " trans" });
+                text.push_str(&format!("\n/*!100001 SET @@session.gtid_domain_id={}*/{}", domain_id, DELIMITER));
+                text.push_str(&format!("\n/*!100001 SET @@session.server_id={}*/{}", event.server_id(), DELIMITER));
+                text.push_str(&format!("\n/*!100001 SET @@session.gtid_seq_no={}*/{}", seq_no, DELIMITER));
+                if !standalone {
+                    text.push_str(&format!("\nSTART TRANSACTION\n{}", DELIMITER));
+                }
+                text
+            }
+            EventData::MariaDbGtidListEvent { gtids } => {
+                format!("Gtid list [{}]", gtids.to_string().replace(',', ",\n# "))
+            }
+            EventData::Unparsed { type_code, reason } => format!("{:?}\n# not decoded: {}", type_code, reason),
+            _ => format!("{:?}", event.type_code()),
+        };
+        Ok(text)
+    }
+}
+
+impl<W: Write> EventHandler for MysqlbinlogWriter<W> {
+    type Error = ExportError;
+
+    fn on_event(&mut self, event: &Event, data: &EventData) -> Result<(), Self::Error> {
+        self.write_event(event, data)
+    }
+}
+
+// A BINLOG statement replaying `events` on a server, as mysqlbinlog writes them: the events'
+// bytes in base64, in lines of 76 characters. A rows event has to come in the same statement as
+// its table map, and the binlog's FormatDescriptionEvent in one before them.
+pub fn binlog_statement(events: &[&Event]) -> Result<String, ExportError> {
+    let encoded = events.iter()
+        .map(|event| Ok(base64(&event.to_bytes().map_err(BinlogFileError::from)?)))
+        .collect::<Result<Vec<_>, ExportError>>()?;
+    Ok(base64_statement(&encoded))
+}
+
+fn base64_statement(encoded: &[String]) -> String {
+    let mut statement = String::from("BINLOG '");
+    for event in encoded {
+        for line in event.as_bytes().chunks(76) {
+            statement.push('\n');
+            statement.push_str(&String::from_utf8_lossy(line));
+        }
+    }
+    statement.push_str("\n'");
+    statement.push_str(DELIMITER);
+    statement
+}
+
+fn gtid_text(name: &str, gtid: &GtidEvent) -> String {
+    let mut text = name.to_owned();
+    if let (Some(last_committed), Some(sequence_number)) = (gtid.last_committed, gtid.sequence_number) {
+        text.push_str(&format!("\tlast_committed={}\tsequence_number={}", last_committed, sequence_number));
+    }
+    // FLAG_MAY_HAVE_SBR
+    let rbr_only = gtid.flags & 0x01 == 0;
+    text.push_str(if rbr_only { "\trbr_only=yes" } else { "\trbr_only=no" });
+    if let Some(timestamp) = gtid.original_commit_timestamp {
+        text.push_str(&format!("\toriginal_committed_timestamp={}", timestamp));
+    }
+    if let Some(timestamp) = gtid.immediate_commit_timestamp {
+        text.push_str(&format!("\timmediate_commit_timestamp={}", timestamp));
+    }
+    if let Some(length) = gtid.transaction_length {
+        text.push_str(&format!("\ttransaction_length={}", length));
+    }
+    if rbr_only {
+        text.push_str(&format!("\n/*!50718 SET TRANSACTION ISOLATION LEVEL READ COMMITTED*/{}", DELIMITER));
+    }
+    text
+}
+
+// the ### lines under a rows event
+fn rows_text(rows: &RowsEvent, table_map: &TableMapEvent, verbosity: u8) -> String {
+    if verbosity == 0 {
+        return String::new();
+    }
+    let table = format!("`{}`.`{}`", table_map.schema, table_map.table);
+    let mut text = String::new();
+    for change in &rows.rows {
+        match (rows.kind, &change.before, &change.after) {
+            (RowsEventKind::Write, _, Some(after)) => {
+                text.push_str(&format!("\n### INSERT INTO {}\n### SET", table));
+                text.push_str(&image_text(after, table_map, verbosity));
+            }
+            (RowsEventKind::Update, Some(before), Some(after)) => {
+                text.push_str(&format!("\n### UPDATE {}\n### WHERE", table));
+                text.push_str(&image_text(before, table_map, verbosity));
+                text.push_str("\n### SET");
+                text.push_str(&image_text(after, table_map, verbosity));
+            }
+            (RowsEventKind::Delete, Some(before), _) => {
+                text.push_str(&format!("\n### DELETE FROM {}\n### WHERE", table));
+                text.push_str(&image_text(before, table_map, verbosity));
+            }
+            _ => {}
+        }
+    }
+    text
+}
+
+fn image_text(image: &RowImage, table_map: &TableMapEvent, verbosity: u8) -> String {
+    let mut text = String::new();
+    for (i, value) in image.values().iter().enumerate() {
+        let value = match value {
+            Some(value) => value,
+            None => continue,
+        };
+        let column = table_map.columns.get(i);
+        text.push_str(&format!("\n###   @{}={}", i + 1, value_text(value, column)));
+        if let (2, Some(column)) = (verbosity, column) {
+            text.push_str(&format!(
+                " /* {} meta={} nullable={} is_null={} */",
+                type_text(column), column.meta, u8::from(column.nullable), u8::from(*value == Value::Null)
+            ));
+        }
+    }
+    text
+}
+
+// a value as log_event_print_value writes it
+fn value_text(value: &Value, column: Option<&TableMapColumn>) -> String {
+    let fsp = column.map(|c| match c.column_type {
+        ColumnType::Time2 | ColumnType::DateTime2 | ColumnType::Timestamp2 => usize::from(c.meta).min(6),
+        _ => 0,
+    }).unwrap_or(0);
+    let fraction = |micros: u32| match fsp {
+        0 => String::new(),
+        fsp => format!(".{:0width$}", micros / 10u32.pow(6 - fsp as u32), width = fsp),
+    };
+    match value {
+        Value::Null => "NULL".to_owned(),
+        Value::Int(v) => v.to_string(),
+        Value::UInt(v) => v.to_string(),
+        // printf's %-20g and %-.20g
+        Value::Float(v) => format!("{:<20}", format_g(f64::from(*v), 6)),
+        Value::Double(v) => format_g(*v, 20),
+        Value::Decimal(v) => v.clone(),
+        Value::String(v) => quoted(v.as_bytes()),
+        Value::Bytes(v) => quoted(v),
+        Value::Year(v) => v.to_string(),
+        Value::Date { year, month, day } => format!("'{:04}:{:02}:{:02}'", year, month, day),
+        Value::Time { negative, hours, minutes, seconds, micros } => {
+            format!("'{}{:02}:{:02}:{:02}{}'", if *negative { "-" } else { "" }, hours, minutes, seconds, fraction(*micros))
+        }
+        Value::DateTime { year, month, day, hour, minute, second, micros } => {
+            format!("'{:04}-{:02}-{:02} {:02}:{:02}:{:02}{}'", year, month, day, hour, minute, second, fraction(*micros))
+        }
+        Value::Timestamp { seconds, micros } => format!("{}{}", seconds, fraction(*micros)),
+        Value::Bit(v) => {
+            let bits: String = v.iter().map(|b| format!("{:08b}", b)).collect();
+            let width = column.map(|c| usize::from(c.meta & 0xff) * 8 + usize::from(c.meta >> 8)).unwrap_or(bits.len());
+            format!("b'{}'", &bits[bits.len().saturating_sub(width)..])
+        }
+        Value::Enum(v) => v.to_string(),
+        Value::Set(v) => {
+            let bytes = column.map(|c| usize::from(real_string_type(c.meta).1)).unwrap_or(8).clamp(1, 8);
+            format!("b'{:0width$b}'", v, width = bytes * 8)
+        }
+        Value::Json(v) => match json_binary::decode(v) {
+            Some(document) => quoted(json_binary::mysql_text(&document).as_bytes()),
+            None => quoted(v),
+        },
+    }
+}
+
+// 'text', with control characters, quotes and backslashes as \x..
+fn quoted(bytes: &[u8]) -> String {
+    let mut text = String::from("'");
+    let mut start = 0;
+    for (i, b) in bytes.iter().enumerate() {
+        if *b < 0x20 || *b == 0x7f || *b == b'\'' || *b == b'\\' {
+            text.push_str(&String::from_utf8_lossy(&bytes[start..i]));
+            text.push_str(&format!("\\x{:02x}", b));
+            start = i + 1;
+        }
+    }
+    text.push_str(&String::from_utf8_lossy(&bytes[start..]));
+    text.push('\'');
+    text
+}
+
+// the type in the /* ... */ comments of -vv
+fn type_text(column: &TableMapColumn) -> String {
+    let meta = column.meta;
+    match column.real_type() {
+        ColumnType::Tiny => "TINYINT".to_owned(),
+        ColumnType::Short => "SHORTINT".to_owned(),
+        ColumnType::Int24 => "MEDIUMINT".to_owned(),
+        ColumnType::Long => "INT".to_owned(),
+        ColumnType::LongLong => "LONGINT".to_owned(),
+        ColumnType::Float => "FLOAT".to_owned(),
+        ColumnType::Double => "DOUBLE".to_owned(),
+        ColumnType::NewDecimal => format!("DECIMAL({},{})", meta >> 8, meta & 0xff),
+        ColumnType::Decimal => "DECIMAL".to_owned(),
+        ColumnType::String => format!("STRING({})", real_string_type(meta).1),
+        ColumnType::VarChar | ColumnType::VarString => format!("VARSTRING({})", meta),
+        ColumnType::Blob | ColumnType::TinyBlob | ColumnType::MediumBlob | ColumnType::LongBlob => match meta {
+            1 => "TINYBLOB/TINYTEXT".to_owned(),
+            2 => "BLOB/TEXT".to_owned(),
+            3 => "MEDIUMBLOB/MEDIUMTEXT".to_owned(),
+            4 => "LONGBLOB/LONGTEXT".to_owned(),
+            _ => format!("BLOB(unknown length {})", meta),
+        },
+        ColumnType::Date | ColumnType::NewDate => "DATE".to_owned(),
+        ColumnType::Time => "TIME".to_owned(),
+        ColumnType::Time2 => format!("TIME({})", meta),
+        ColumnType::DateTime => "DATETIME".to_owned(),
+        ColumnType::DateTime2 => format!("DATETIME({})", meta),
+        ColumnType::Timestamp => "TIMESTAMP".to_owned(),
+        ColumnType::Timestamp2 => format!("TIMESTAMP({})", meta),
+        ColumnType::Year => "YEAR".to_owned(),
+        ColumnType::Bit => format!("BIT({})", usize::from(meta & 0xff) * 8 + usize::from(meta >> 8)),
+        ColumnType::Enum => format!("ENUM({} bytes)", real_string_type(meta).1),
+        ColumnType::Set => format!("SET({} bytes)", real_string_type(meta).1),
+        ColumnType::Json => "JSON".to_owned(),
+        ColumnType::Geometry => "GEOMETRY".to_owned(),
+        other => format!("{:?}", other).to_uppercase(),
+    }
+}
+
+// printf's %.<precision>g: the shorter of fixed and exponent notation, without trailing zeros
+fn format_g(v: f64, precision: usize) -> String {
+    if v == 0.0 || !v.is_finite() {
+        return v.to_string();
+    }
+    let precision = precision.max(1);
+    let scientific = format!("{:.*e}", precision - 1, v);
+    let (mantissa, exponent) = scientific.split_once('e').unwrap_or((&scientific, "0"));
+    let exponent: i32 = exponent.parse().unwrap_or(0);
+    let trim = |s: &str| match s.contains('.') {
+        true => s.trim_end_matches('0').trim_end_matches('.').to_owned(),
+        false => s.to_owned(),
+    };
+    if exponent < -4 || exponent >= precision as i32 {
+        let sign = if exponent < 0 { '-' } else { '+' };
+        format!("{}e{}{:02}", trim(mantissa), sign, exponent.abs())
+    } else {
+        trim(&format!("{:.*}", (precision as i32 - 1 - exponent) as usize, v))
+    }
+}
+
+// UserVarEvent's value for its SET statement
+fn user_var_text(value: Option<&(u8, u32, Vec<u8>)>) -> String {
+    let (value_type, charset, bytes) = match value {
+        Some(value) => value,
+        None => return "NULL".to_owned(),
+    };
+    match value_type {
+        // REAL_RESULT
+        1 if bytes.len() == 8 => format_g(f64::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3], bytes[4], bytes[5], bytes[6], bytes[7]]), 14),
+        // INT_RESULT
+        2 if bytes.len() == 8 => i64::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3], bytes[4], bytes[5], bytes[6], bytes[7]]).to_string(),
+        // STRING_RESULT, introduced by the charset
+        0 => {
+            let charset = u16::try_from(*charset).ok().and_then(Charset::from_collation_id).map(|c| c.name()).unwrap_or("binary");
+            format!("_{} 0x{}", charset, hex(bytes))
+        }
+        // DECIMAL_RESULT, in its binary form
+        _ => format!("0x{}", hex(bytes)),
+    }
+}
+
+// The 19-byte common header split into its fields, then the rest 16 bytes a line with the
+// letters and digits alongside, each line starting with its offset in hex, as mysqlbinlog has it.
+fn hexdump(bytes: &[u8], offset: u64) -> String {
+    let field = |from: usize, to: usize| bytes[from..to].iter().map(|b| format!("{:02x}", b)).collect::<Vec<_>>().join(" ");
+    let mut text = String::from("# Position  Timestamp   Type   Master ID        Size      Master Pos    Flags \n");
+    text.push_str(&format!(
+        "# {:8x} {}   {}   {}   {}   {}   {}\n",
+        offset, field(0, 4), field(4, 5), field(5, 9), field(9, 13), field(13, 17), field(17, 19),
+    ));
+    for (i, line) in bytes[19..].chunks(16).enumerate() {
+        // a wider gap after the eighth byte
+        let hex: String = line.iter().enumerate()
+            .map(|(j, b)| if j < 8 { format!("{:02x} ", b) } else { format!(" {:02x}", b) })
+            .collect();
+        let chars: String = line.iter().map(|&b| if b.is_ascii_alphanumeric() { char::from(b) } else { '.' }).collect();
+        text.push_str(&format!("# {:8x} {:<48} |{}|\n", offset + 19 + 16 * i as u64, hex, chars));
+    }
+    text
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+// YYMMDD HH:MM:SS, the hour padded with a space
+fn short_datetime(timestamp: u32) -> String {
+    let (year, month, day) = civil_from_days(i64::from(timestamp / 86400));
+    let time = timestamp % 86400;
+    format!("{:02}{:02}{:02} {:2}:{:02}:{:02}", year % 100, month, day, time / 3600, time / 60 % 60, time % 60)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::context::ParseContext;
+    use crate::event::Event;
+    use crate::event::tests::{timed_event_bytes, users_rows, users_table_map};
+    use crate::handler::run;
+    use crate::mysqlbinlog::{format_g, Base64Output, MysqlbinlogWriter};
+    use crate::utils::base64;
+
+    fn binlog_bytes() -> Vec<u8> {
+        // test.users (id INT NOT NULL, name VARCHAR(20), created DATE)
+        let table_map = users_table_map(&[3, 15, 10], &[80, 0], 0x06, &[]);
+        let mut update_rows = users_rows(3, &[0x07, 0x07]);
+        update_rows.extend_from_slice(b"\x00\x07\x00\x00\x00\x04it's\x3f\xd0\x0f");
+        update_rows.extend_from_slice(b"\x02\x07\x00\x00\x00\x41\xd0\x0f");
+        let mut query = vec![9, 0, 0, 0, 0, 0, 0, 0, 4, 0, 0, 0, 0];
+        query.extend_from_slice(b"test\x00BEGIN");
+        let mut bytes = vec![];
+        for (type_code, body) in [(2, query), (19, table_map), (31, update_rows), (16, 42u64.to_le_bytes().to_vec())] {
+            // at 2024-01-31 09:05:01 UTC, each event's next_position where the one after it starts
+            let next_position = 4 + bytes.len() as u32 + 19 + body.len() as u32;
+            bytes.extend(timed_event_bytes(1706691901, type_code, &body, next_position));
+        }
+        bytes
+    }
+
+    fn output(mut writer: MysqlbinlogWriter<Vec<u8>>) -> String {
+        let bytes = binlog_bytes();
+        let mut reader = &bytes[..];
+        let events = std::iter::from_fn(|| {
+            let offset = 4 + (bytes.len() - reader.len()) as u64;
+            if reader.is_empty() { None } else { Some(Event::parse(&mut reader, offset).map_err(Into::into)) }
+        });
+        run(events, &mut ParseContext::new(), &mut writer).unwrap();
+        String::from_utf8(writer.finish().unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_decoded_rows_output() {
+        //given
+        let writer = MysqlbinlogWriter::new(vec![]);
+
+        //when
+        let output = output(writer);
+
+        //then
+        let lines: Vec<&str> = output.lines().collect();
+        assert_eq!(lines[3], "# at 4");
+        assert_eq!(lines[4], "#240131  9:05:01 server id 1  end_log_pos 46 \tQuery\tthread_id=9\texec_time=0\terror_code=0");
+        assert_eq!(&lines[5..9], ["use `test`/*!*/;", "SET TIMESTAMP=1706691901/*!*/;", "BEGIN", "/*!*/;"]);
+        assert!(lines[10].ends_with("\tTable_map: `test`.`users` mapped to number 108"));
+        assert!(lines[12].ends_with("\tUpdate_rows: table id 108 flags: STMT_END_F"));
+        assert_eq!(&lines[13..22], [
+            "### UPDATE `test`.`users`",
+            "### WHERE",
+            "###   @1=7 /* INT meta=0 nullable=0 is_null=0 */",
+            "###   @2='it\\x27s' /* VARSTRING(80) meta=80 nullable=1 is_null=0 */",
+            "###   @3='2024:01:31' /* DATE meta=0 nullable=1 is_null=0 */",
+            "### SET",
+            "###   @1=7 /* INT meta=0 nullable=0 is_null=0 */",
+            "###   @2=NULL /* VARSTRING(80) meta=80 nullable=1 is_null=1 */",
+            "###   @3='2024:02:01' /* DATE meta=0 nullable=1 is_null=0 */",
+        ]);
+        assert!(lines[23].ends_with("\tXid = 42"));
+        assert_eq!(&lines[24..], [
+            "COMMIT/*!*/;",
+            "DELIMITER ;",
+            "# End of log file",
+            "/*!50003 SET COMPLETION_TYPE=@OLD_COMPLETION_TYPE*/;",
+            "/*!50530 SET @@SESSION.PSEUDO_SLAVE_MODE=0*/;",
+        ]);
+    }
+
+    #[test]
+    fn test_base64_output() {
+        //given
+        let writer = MysqlbinlogWriter::new(vec![]).base64_output(Base64Output::Auto).verbosity(0);
+        let bytes = binlog_bytes();
+        // the table map and the rows event, each on its own line as they're under 76 characters
+        let table_map = base64(&bytes[42..90]);
+        let rows = base64(&bytes[90..bytes.len() - 27]);
+
+        //when
+        let output = output(writer);
+
+        //then
+        let lines: Vec<&str> = output.lines().collect();
+        assert!(lines[12].ends_with("\tUpdate_rows: table id 108 flags: STMT_END_F"));
+        assert_eq!(lines[13], "BINLOG '");
+        assert_eq!(&lines[14..17], [table_map.as_str(), rows.as_str(), "'/*!*/;"]);
+        assert_eq!(lines[17], format!("# at {}", 4 + bytes.len() - 27));
+    }
+
+    #[test]
+    fn test_hexdump() {
+        //given
+        let writer = MysqlbinlogWriter::new(vec![]).hexdump(true).verbosity(0);
+
+        //when
+        let output = output(writer);
+
+        //then
+        let lines: Vec<&str> = output.lines().collect();
+        assert_eq!(&lines[3..9], [
+            "# at 4",
+            "#240131  9:05:01 server id 1  end_log_pos 46 ",
+            "# Position  Timestamp   Type   Master ID        Size      Master Pos    Flags ",
+            "#        4 3d 0d ba 65   02   01 00 00 00   2a 00 00 00   2e 00 00 00   00 00",
+            "#       17 09 00 00 00 00 00 00 00  04 00 00 00 00 74 65 73 |.............tes|",
+            "#       27 74 00 42 45 47 49 4e                             |t.BEGIN|",
+        ]);
+        assert_eq!(lines[9], "# \tQuery\tthread_id=9\texec_time=0\terror_code=0");
+    }
+
+    #[test]
+    fn test_printf_g() {
+        assert_eq!(format_g(1.5, 6), "1.5");
+        assert_eq!(format_g(0.1, 20), "0.10000000000000000555");
+        assert_eq!(format_g(1e20, 6), "1e+20");
+        assert_eq!(format_g(0.00001234, 6), "1.234e-05");
+        assert_eq!(format_g(123456789.0, 6), "1.23457e+08");
+    }
+}
@@ -0,0 +1,48 @@
+use crate::charset::Charset;
+
+#[derive(Debug, Clone)]
+pub struct ParseOptions {
+    // used for text columns whose table map carries no charset (binlog_row_metadata=MINIMAL)
+    pub default_charset: Charset,
+    // whether a body that fails to decode is an error or EventData::Unparsed
+    pub mode: ParseMode,
+}
+
+impl Default for ParseOptions {
+    fn default() -> Self {
+        ParseOptions {
+            default_charset: Charset::Utf8mb4,
+            mode: ParseMode::Strict,
+        }
+    }
+}
+
+impl ParseOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn default_charset(mut self, charset: Charset) -> Self {
+        self.default_charset = charset;
+        self
+    }
+
+    pub fn mode(mut self, mode: ParseMode) -> Self {
+        self.mode = mode;
+        self
+    }
+}
+
+// How readers treat input that doesn't follow the format. Lenient mode hands on the raw event
+// with a diagnostics() entry where strict mode would fail on an unknown type code or a checksum
+// mismatch.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum ParseMode {
+    // fail on the first inconsistency
+    #[default]
+    Strict,
+    // recover where the damage can be worked around, e.g. an event_length corrupted while
+    // next_position still says where the event ends. Also needed for relay logs, whose
+    // next_position values refer to the source server's binlog.
+    Lenient,
+}
@@ -0,0 +1,204 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use ::parquet::arrow::ArrowWriter;
+use ::parquet::file::properties::WriterProperties;
+use crate::arrow::RecordBatchBuilder;
+use crate::errors::ExportError;
+use crate::event::{Event, EventData};
+use crate::rows::RowsEvent;
+use crate::table_map::TableMapEvent;
+
+// rows buffered before they're written out as a row group's worth of columns
+const DEFAULT_BATCH_SIZE: usize = 8192;
+
+// Writes one table's row changes to a Parquet file, with the columns of arrow::table_schema.
+pub struct ParquetTableWriter<W: Write + Send> {
+    builder: RecordBatchBuilder,
+    writer: ArrowWriter<W>,
+    batch_size: usize,
+}
+
+impl<W: Write + Send> ParquetTableWriter<W> {
+    pub fn new(writer: W, table_map: &TableMapEvent) -> Result<Self, ExportError> {
+        Self::with_properties(writer, table_map, WriterProperties::default())
+    }
+
+    // e.g. to pick the compression, with parquet's own compression features enabled
+    pub fn with_properties(writer: W, table_map: &TableMapEvent, properties: WriterProperties) -> Result<Self, ExportError> {
+        let builder = RecordBatchBuilder::new(table_map);
+        let writer = ArrowWriter::try_new(writer, builder.schema(), Some(properties))?;
+        Ok(ParquetTableWriter { builder, writer, batch_size: DEFAULT_BATCH_SIZE })
+    }
+
+    pub fn batch_size(mut self, rows: usize) -> Self {
+        self.batch_size = rows.max(1);
+        self
+    }
+
+    // adds the changes of `rows`, which must be of this writer's table
+    pub fn append(&mut self, rows: &RowsEvent) -> Result<(), ExportError> {
+        self.builder.append(rows);
+        if self.builder.len() >= self.batch_size {
+            self.writer.write(&self.builder.finish()?)?;
+        }
+        Ok(())
+    }
+
+    // writes what's buffered and the file footer
+    pub fn finish(mut self) -> Result<W, ExportError> {
+        if !self.builder.is_empty() {
+            self.writer.write(&self.builder.finish()?)?;
+        }
+        Ok(self.writer.into_inner()?)
+    }
+}
+
+// Archives the change history of every table in the binlog to a directory, one Parquet file per
+// table named <schema>.<table>.parquet. When a table's layout changes (an ALTER TABLE between
+// its table maps) its file is closed and the rows continue in <schema>.<table>.1.parquet, and so on.
+//
+//     let mut exporter = ParquetExporter::new("/archive/changes")?;
+//     handler::run(BinlogFile::from_path(path)?.events(), &mut ParseContext::new(), &mut exporter)?;
+//     let written = exporter.finish()?;
+pub struct ParquetExporter {
+    dir: PathBuf,
+    tables: HashMap<u64, TableMapEvent>,
+    // per schema and table: the open file, the table map it was opened for, and its path
+    writers: HashMap<(String, String), (ParquetTableWriter<File>, TableMapEvent, PathBuf)>,
+    // files opened so far per table, numbering the next one
+    generations: HashMap<(String, String), usize>,
+    written: Vec<PathBuf>,
+    batch_size: usize,
+}
+
+impl ParquetExporter {
+    pub fn new<P: AsRef<Path>>(dir: P) -> Result<Self, ExportError> {
+        std::fs::create_dir_all(dir.as_ref())?;
+        Ok(ParquetExporter {
+            dir: dir.as_ref().to_owned(),
+            tables: HashMap::new(),
+            writers: HashMap::new(),
+            generations: HashMap::new(),
+            written: vec![],
+            batch_size: DEFAULT_BATCH_SIZE,
+        })
+    }
+
+    pub fn batch_size(mut self, rows: usize) -> Self {
+        self.batch_size = rows.max(1);
+        self
+    }
+
+    pub fn write_event(&mut self, _event: &Event, data: &EventData) -> Result<(), ExportError> {
+        let rows = match data {
+            EventData::TableMapEvent(table_map) => {
+                self.tables.insert(table_map.table_id, table_map.clone());
+                return Ok(());
+            }
+            EventData::WriteRowsEvent(rows) | EventData::UpdateRowsEvent(rows) | EventData::DeleteRowsEvent(rows) => rows,
+            _ => return Ok(()),
+        };
+        let table_map = match self.tables.get(&rows.table_id) {
+            Some(table_map) => table_map,
+            None => return Ok(()),
+        };
+        let key = (table_map.schema.clone(), table_map.table.clone());
+        let same_layout = self.writers.get(&key).is_some_and(|(_, open, _)| same_layout(open, table_map));
+        if !same_layout {
+            if let Some((writer, _, path)) = self.writers.remove(&key) {
+                writer.finish()?;
+                self.written.push(path);
+            }
+            let generation = self.generations.entry(key.clone()).or_insert(0);
+            let name = match *generation {
+                0 => format!("{}.{}.parquet", key.0, key.1),
+                n => format!("{}.{}.{}.parquet", key.0, key.1, n),
+            };
+            *generation += 1;
+            let path = self.dir.join(name);
+            let writer = ParquetTableWriter::new(File::create(&path)?, table_map)?.batch_size(self.batch_size);
+            self.writers.insert(key.clone(), (writer, table_map.clone(), path));
+        }
+        match self.writers.get_mut(&key) {
+            Some((writer, _, _)) => writer.append(rows),
+            None => Ok(()),
+        }
+    }
+
+    // closes the files and returns all written, in the order they were finished
+    pub fn finish(mut self) -> Result<Vec<PathBuf>, ExportError> {
+        let mut open: Vec<_> = self.writers.drain().map(|(_, open)| open).collect();
+        open.sort_by(|a, b| a.2.cmp(&b.2));
+        for (writer, _, path) in open {
+            writer.finish()?.sync_all()?;
+            self.written.push(path);
+        }
+        Ok(self.written)
+    }
+}
+
+impl crate::handler::EventHandler for ParquetExporter {
+    type Error = ExportError;
+
+    fn on_event(&mut self, event: &Event, data: &EventData) -> Result<(), Self::Error> {
+        self.write_event(event, data)
+    }
+}
+
+fn same_layout(a: &TableMapEvent, b: &TableMapEvent) -> bool {
+    a.columns.len() == b.columns.len()
+        && a.columns.iter().zip(&b.columns).all(|(a, b)| {
+            (a.column_type, a.meta, a.unsigned, &a.name) == (b.column_type, b.meta, b.unsigned, &b.name)
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::File;
+    use arrow_array::{Array, Int32Array};
+    use ::parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+    use crate::context::ParseContext;
+    use crate::event::Event;
+    use crate::event::tests::{timed_event_bytes, users_rows, users_table_map};
+    use crate::handler::run;
+    use crate::parquet::ParquetExporter;
+
+    #[test]
+    fn test_export_table_history() {
+        //given
+        let dir = std::env::temp_dir().join(format!("parquet-export-{}", std::process::id()));
+        let insert = users_rows(1, &[0x01, 0x00, 7, 0, 0, 0, 0x00, 8, 0, 0, 0]);
+        // after ALTER TABLE users ADD c2 INT
+        let insert_wider = users_rows(2, &[0x03, 0x00, 9, 0, 0, 0, 1, 0, 0, 0]);
+        let table_map = |types: &[u8]| users_table_map(types, &[], 0x00, &[]);
+        let mut bytes = vec![];
+        for (type_code, body) in [(19, table_map(&[3])), (30, insert), (19, table_map(&[3, 3])), (30, insert_wider)] {
+            bytes.extend(timed_event_bytes(16, type_code, &body, 0));
+        }
+        let mut reader = &bytes[..];
+        let events = std::iter::from_fn(|| {
+            if reader.is_empty() { None } else { Some(Event::parse(&mut reader, 4).map_err(Into::into)) }
+        });
+        let mut exporter = ParquetExporter::new(&dir).unwrap();
+
+        //when
+        run(events, &mut ParseContext::new(), &mut exporter).unwrap();
+        let written = exporter.finish().unwrap();
+        let batches: Vec<_> = written.iter()
+            .map(|path| {
+                let reader = ParquetRecordBatchReaderBuilder::try_new(File::open(path).unwrap()).unwrap().build().unwrap();
+                reader.map(|batch| batch.unwrap()).collect::<Vec<_>>()
+            })
+            .collect();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        //then
+        assert_eq!(written, vec![dir.join("test.users.parquet"), dir.join("test.users.1.parquet")]);
+        assert_eq!(batches[0][0].num_rows(), 2);
+        let ids = batches[0][0].column_by_name("@1").unwrap().as_any().downcast_ref::<Int32Array>().unwrap();
+        assert_eq!(ids.values().to_vec(), vec![7, 8]);
+        assert_eq!(batches[1][0].num_columns(), 3);
+    }
+}
@@ -0,0 +1,207 @@
+use crate::binlog_reader::is_concatenated_binlog;
+use crate::checksum::ChecksumVerifier;
+use crate::encryption::{self, EncryptionHeader, BINLOG_MAGIC, ENCRYPTED_BINLOG_MAGIC};
+use crate::errors::{BinlogFileError, EventParseError};
+use crate::event::{Event, EventHeader};
+use crate::options::ParseMode;
+
+// Push-based parser with no Read/Seek requirement: bytes go in through feed() in whatever
+// chunks the transport delivers and complete events come out, partial ones stay buffered.
+#[derive(Debug, Clone)]
+pub struct Parser {
+    buffer: Vec<u8>,
+    offset: u64,
+    expect_magic: bool,
+    checksum: ChecksumVerifier,
+    mode: ParseMode,
+}
+
+impl Default for Parser {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Parser {
+    // for a binlog from its first byte, magic number included
+    pub fn new() -> Self {
+        Parser {
+            buffer: vec![],
+            offset: 0,
+            expect_magic: true,
+            checksum: ChecksumVerifier::default(),
+            mode: ParseMode::Strict,
+        }
+    }
+
+    // for a stream that starts at an event boundary, e.g. a replication stream from `offset`
+    pub fn at_offset(offset: u64) -> Self {
+        Parser {
+            buffer: vec![],
+            offset,
+            expect_magic: false,
+            checksum: ChecksumVerifier::default(),
+            mode: ParseMode::Strict,
+        }
+    }
+
+    pub fn parse_mode(mut self, mode: ParseMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    // offset of the first byte not yet consumed into an event
+    pub fn offset(&self) -> u64 {
+        self.offset
+    }
+
+    pub fn buffered_len(&self) -> usize {
+        self.buffer.len()
+    }
+
+    // An event that fails to parse stays buffered, after the events before it are returned,
+    // so the next feed() or finish() reports it.
+    pub fn feed(&mut self, bytes: &[u8]) -> Result<Vec<Event>, BinlogFileError> {
+        self.buffer.extend_from_slice(bytes);
+        let mut events = vec![];
+        let mut consumed = 0;
+        let result = self.drain_events(&mut consumed, &mut events);
+        self.buffer.drain(..consumed);
+        match result {
+            Err(_) if !events.is_empty() => Ok(events),
+            result => result.map(|_| events),
+        }
+    }
+
+    // call once the input has ended; fails if it stopped in the middle of an event, or at one
+    // that couldn't be parsed
+    pub fn finish(&self) -> Result<(), BinlogFileError> {
+        if self.buffer.is_empty() {
+            return Ok(());
+        }
+        self.clone().feed(&[])?;
+        let expected_len = match self.buffer.get(9..13) {
+            Some(len) if !self.expect_magic => u32::from_le_bytes([len[0], len[1], len[2], len[3]]),
+            _ => EventHeader::LEN as u32,
+        };
+        Err(EventParseError::TruncatedEvent {
+            offset: self.offset,
+            expected_len,
+            available: self.buffer.len() as u64,
+        }.into())
+    }
+
+    fn drain_events(&mut self, consumed: &mut usize, events: &mut Vec<Event>) -> Result<(), BinlogFileError> {
+        if self.expect_magic {
+            if self.buffer.len() < BINLOG_MAGIC.len() {
+                return Ok(());
+            }
+            let mut magic = [0u8; 4];
+            magic.copy_from_slice(&self.buffer[..4]);
+            // wait for the whole encryption header, to report the key it names
+            if magic == ENCRYPTED_BINLOG_MAGIC && self.buffer.len() < EncryptionHeader::LEN {
+                return Ok(());
+            }
+            encryption::check_magic(magic, &mut &self.buffer[4..])?;
+            self.expect_magic = false;
+            *consumed = 4;
+            self.offset += 4;
+        }
+
+        loop {
+            let rest = &self.buffer[*consumed..];
+            if rest.len() < EventHeader::LEN {
+                return Ok(());
+            }
+            if is_concatenated_binlog(rest) {
+                *consumed += BINLOG_MAGIC.len();
+                self.offset = 4;
+                self.checksum = ChecksumVerifier::default();
+                continue;
+            }
+            let mut header = [0u8; EventHeader::LEN];
+            header.copy_from_slice(&rest[..EventHeader::LEN]);
+            let event_length = EventHeader::parse(&header).checked_length(self.offset, self.mode)? as usize;
+            if rest.len() < event_length {
+                return Ok(());
+            }
+
+            let mut event = Event::parse_with_mode(&mut &rest[..event_length], self.offset, self.mode)?;
+            self.checksum.check(&header, &mut event, self.mode)?;
+            events.push(event);
+            *consumed += event_length;
+            self.offset += event_length as u64;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::binlog_file::BinlogFile;
+    use crate::errors::{BinlogFileError, EventParseError};
+    use crate::parser::Parser;
+
+    #[test]
+    fn test_feed_in_small_chunks() {
+        //given
+        let path = "tests/asset/mysql-bin.100746";
+        let bytes = std::fs::read(path).unwrap();
+        let expected: Vec<_> = BinlogFile::from_path(path).unwrap().events().map(|e| e.unwrap()).collect();
+        let mut parser = Parser::new();
+
+        //when
+        let mut events = vec![];
+        for chunk in bytes.chunks(7) {
+            events.extend(parser.feed(chunk).unwrap());
+        }
+
+        //then
+        assert_eq!(events.len(), expected.len());
+        assert_eq!(events[6].offset(), expected[6].offset());
+        assert_eq!(events[6].data(), expected[6].data());
+        assert_eq!(parser.offset(), bytes.len() as u64);
+        assert!(parser.finish().is_ok());
+    }
+
+    #[test]
+    fn test_feed_partial_and_bad_magic() {
+        //given
+        let bytes = std::fs::read("tests/asset/mysql-bin.100746").unwrap();
+        let mut parser = Parser::new();
+
+        //when
+        let events = parser.feed(&bytes[..50]).unwrap();
+        let bad_magic = Parser::new().feed(b"GIF89a");
+
+        //then
+        assert!(events.is_empty());
+        assert_eq!(parser.buffered_len(), 46);
+        assert!(parser.finish().is_err());
+        assert!(bad_magic.is_err());
+    }
+
+    #[test]
+    fn test_events_before_a_bad_one_are_kept() {
+        //given
+        let mut bytes = std::fs::read("tests/asset/mysql-bin.100746").unwrap();
+        // the event at 529
+        bytes[529 + 30] ^= 0xff;
+        let mut parser = Parser::new();
+        let is_mismatch_at_529 = |error: &BinlogFileError| matches!(
+            error,
+            BinlogFileError::EventParseError(EventParseError::ChecksumMismatch { offset: 529, .. })
+        );
+
+        //when
+        let events = parser.feed(&bytes).unwrap();
+        let again = parser.feed(&[]);
+        let finished = parser.finish();
+
+        //then
+        assert_eq!(events.len(), 5);
+        assert_eq!(events.last().unwrap().offset() + u64::from(events.last().unwrap().event_length()), 529);
+        assert_eq!(parser.offset(), 529);
+        assert!(is_mismatch_at_529(&again.unwrap_err()));
+        assert!(is_mismatch_at_529(&finished.unwrap_err()));
+    }
+}
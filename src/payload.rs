@@ -0,0 +1,118 @@
+use std::io::{Cursor, Read};
+use crate::binlog_file::read_header;
+use crate::errors::{BinlogFileError, EventParseError};
+use crate::event::Event;
+use crate::options::ParseMode;
+
+// https://dev.mysql.com/doc/dev/mysql-server/latest/classbinary__log_1_1Transaction__payload__event.html
+pub const COMPRESSION_ZSTD: u64 = 0;
+pub const COMPRESSION_NONE: u64 = 255;
+
+// The events inside a TransactionPayloadEvent (binlog_transaction_compression=ON), decompressed
+// as they're read so a large transaction is never held uncompressed in memory. Offsets are
+// relative to the start of the uncompressed payload; inner events carry no checksums.
+pub struct PayloadEvents {
+    reader: Box<dyn Read + Send>,
+    offset: u64,
+    finished: bool,
+}
+
+impl PayloadEvents {
+    pub fn new(compression_type: u64, payload: Vec<u8>) -> Result<Self, EventParseError> {
+        let payload = Cursor::new(payload);
+        let reader: Box<dyn Read + Send> = match compression_type {
+            COMPRESSION_NONE => Box::new(payload),
+            #[cfg(feature = "zstd")]
+            COMPRESSION_ZSTD => Box::new(zstd::stream::read::Decoder::new(payload)?),
+            other => return Err(EventParseError::UnsupportedType {
+                field: "payload compression",
+                type_code: other as u8,
+            }),
+        };
+        Ok(PayloadEvents {
+            reader,
+            offset: 0,
+            finished: false,
+        })
+    }
+
+    fn read_event(&mut self) -> Result<Option<Event>, BinlogFileError> {
+        let header = match read_header(&mut self.reader, self.offset)? {
+            Some(header) => header,
+            None => return Ok(None),
+        };
+        // next_position in inner events refers to the enclosing binlog, if it's set at all
+        let event = Event::parse_with_mode(&mut (&header[..]).chain(&mut self.reader), self.offset, ParseMode::Lenient)?;
+        self.offset += u64::from(event.event_length());
+        Ok(Some(event))
+    }
+}
+
+impl Iterator for PayloadEvents {
+    type Item = Result<Event, BinlogFileError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.finished {
+            return None;
+        }
+        match self.read_event() {
+            Ok(Some(event)) => Some(Ok(event)),
+            Ok(None) => {
+                self.finished = true;
+                None
+            }
+            Err(e) => {
+                self.finished = true;
+                Some(Err(e))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::context::ParseContext;
+    use crate::event::{Event, EventData, TypeCode};
+    use crate::event::tests::event_bytes;
+
+    fn payload_event(compression_type: u8, uncompressed_size: u8, payload: &[u8]) -> Event {
+        // compression type and uncompressed size fields, then the end marker
+        let mut body = vec![2, 1, compression_type, 3, 1, uncompressed_size, 0];
+        body.extend_from_slice(payload);
+        Event::parse(&mut &event_bytes(40, &body)[..], 4).unwrap()
+    }
+
+    #[test]
+    fn test_uncompressed_payload_events() {
+        //given
+        let mut inner = event_bytes(2, &[0; 13]);
+        inner.extend(event_bytes(16, &7u64.to_le_bytes()));
+        let event = payload_event(255, inner.len() as u8, &inner);
+        let mut ctx = ParseContext::new();
+
+        //when
+        let events: Vec<_> = event.payload_events().unwrap().map(|e| e.unwrap()).collect();
+
+        //then
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[1].type_code(), TypeCode::XidEvent);
+        assert_eq!(events[1].offset(), 32);
+        assert_eq!(events[1].parsed(&mut ctx).unwrap(), EventData::XidEvent { xid: 7 });
+    }
+
+    #[cfg(feature = "zstd")]
+    #[test]
+    fn test_zstd_payload_events() {
+        //given
+        let inner = event_bytes(16, &9u64.to_le_bytes());
+        let compressed = zstd::encode_all(&inner[..], 3).unwrap();
+        let event = payload_event(0, inner.len() as u8, &compressed);
+
+        //when
+        let events: Vec<_> = event.payload_events().unwrap().map(|e| e.unwrap()).collect();
+
+        //then
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].type_code(), TypeCode::XidEvent);
+    }
+}
@@ -0,0 +1,145 @@
+use std::cmp::Ordering;
+use std::fmt;
+use std::path::Path;
+use std::str::FromStr;
+use crate::errors::{CheckpointError, PositionParseError};
+use crate::gtid::GtidSet;
+
+// Where a reader is in a binlog series: the next event to read is at `pos` in `file`, and
+// `gtid_set` (when known) is everything executed up to that point.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BinlogPosition {
+    pub file: String,
+    pub pos: u64,
+    pub gtid_set: Option<GtidSet>,
+}
+
+impl BinlogPosition {
+    pub fn new(file: &str, pos: u64) -> Self {
+        BinlogPosition {
+            file: file.to_owned(),
+            pos,
+            gtid_set: None,
+        }
+    }
+
+    pub fn with_gtid_set(mut self, gtid_set: GtidSet) -> Self {
+        self.gtid_set = Some(gtid_set);
+        self
+    }
+
+    // mysql-bin.000123 -> 123; the server pads to 6 digits but goes on past 999999
+    fn sequence(&self) -> Option<u64> {
+        let (_, suffix) = self.file.rsplit_once('.')?;
+        suffix.parse().ok()
+    }
+
+    fn base_name(&self) -> &str {
+        self.file.rsplit_once('.').map_or(&self.file, |(base, _)| base)
+    }
+
+    // Writes the position to `path` through a temporary file and a rename, so a crash never
+    // leaves a half-written checkpoint behind.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), CheckpointError> {
+        let path = path.as_ref();
+        let mut tmp = path.as_os_str().to_owned();
+        tmp.push(".tmp");
+        std::fs::write(&tmp, format!("{}\n", self))?;
+        std::fs::rename(&tmp, path)?;
+        Ok(())
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, CheckpointError> {
+        Ok(std::fs::read_to_string(path)?.trim().parse()?)
+    }
+}
+
+impl Ord for BinlogPosition {
+    fn cmp(&self, other: &Self) -> Ordering {
+        let file_order = match (self.sequence(), other.sequence()) {
+            (Some(a), Some(b)) if self.base_name() == other.base_name() => a.cmp(&b),
+            _ => self.file.cmp(&other.file),
+        };
+        file_order
+            .then_with(|| self.pos.cmp(&other.pos))
+            .then_with(|| self.file.cmp(&other.file))
+            .then_with(|| self.gtid_set.cmp(&other.gtid_set))
+    }
+}
+
+impl PartialOrd for BinlogPosition {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+// mysql-bin.000003:1234, followed by the GTID set after a space when there is one
+impl fmt::Display for BinlogPosition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.file, self.pos)?;
+        if let Some(gtid_set) = &self.gtid_set {
+            write!(f, " {}", gtid_set)?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for BinlogPosition {
+    type Err = PositionParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let (file_pos, gtid_set) = match s.split_once(char::is_whitespace) {
+            Some((file_pos, gtid_set)) => (file_pos, Some(gtid_set.parse()?)),
+            None => (s, None),
+        };
+        let (file, pos) = file_pos.rsplit_once(':')
+            .filter(|(file, _)| !file.is_empty())
+            .ok_or_else(|| PositionParseError::MissingPosition(s.to_owned()))?;
+        let pos = pos.parse().map_err(|_| PositionParseError::BadPosition(pos.to_owned()))?;
+        Ok(BinlogPosition {
+            file: file.to_owned(),
+            pos,
+            gtid_set,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::gtid::GtidSet;
+    use crate::position::BinlogPosition;
+
+    #[test]
+    fn test_order_and_round_trip() {
+        //given
+        let gtid_set: GtidSet = "3e11fa47-71ca-11e1-9e33-c80aa9429562:1-5".parse().unwrap();
+        let early = BinlogPosition::new("mysql-bin.999999", 1000);
+        let late = BinlogPosition::new("mysql-bin.1000000", 4).with_gtid_set(gtid_set);
+
+        //when
+        let parsed: BinlogPosition = late.to_string().parse().unwrap();
+
+        //then
+        assert!(early < late);
+        assert!(BinlogPosition::new("mysql-bin.000001", 120) < BinlogPosition::new("mysql-bin.000001", 154));
+        assert_eq!(late.to_string(), "mysql-bin.1000000:4 3e11fa47-71ca-11e1-9e33-c80aa9429562:1-5");
+        assert_eq!(parsed, late);
+        assert!("mysql-bin.000001".parse::<BinlogPosition>().is_err());
+    }
+
+    #[test]
+    fn test_save_and_load() {
+        //given
+        let path = std::env::temp_dir().join(format!("binlog-position-{}", std::process::id()));
+        let position = BinlogPosition::new("mysql-bin.000042", 8812);
+
+        //when
+        position.save(&path).unwrap();
+        let loaded = BinlogPosition::load(&path).unwrap();
+
+        //then
+        assert_eq!(loaded, position);
+        std::fs::remove_file(path).unwrap();
+    }
+}
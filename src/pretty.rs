@@ -0,0 +1,241 @@
+use std::fmt;
+use crate::context::ParseContext;
+use crate::event::{Event, EventData};
+use crate::gtid::format_uuid;
+use crate::rows::{RowImage, RowsEventKind};
+use crate::utils::utc_datetime;
+
+// Multi-line description of an event for a REPL or debug log, from Event::pretty:
+//
+//     #290 UpdateRowsEventV2, 53 bytes, next 343
+//       timestamp  2024-01-31 09:05:01 UTC (1706691901)
+//       server id  1
+//       flags      0x0000
+//       checksum   0x1b6a2e31
+//       table      test.users (id 108)
+//       rows       1 updated
+//         [1]  id=7, name='old'
+//           -> id=7, name='new'
+//
+// Rows events name their table when given the ParseContext they were parsed with, and show the
+// first ten rows unless told otherwise.
+pub struct PrettyEvent<'a> {
+    event: &'a Event,
+    data: &'a EventData,
+    ctx: Option<&'a ParseContext>,
+    max_rows: usize,
+}
+
+impl<'a> PrettyEvent<'a> {
+    pub(crate) fn new(event: &'a Event, data: &'a EventData) -> Self {
+        PrettyEvent { event, data, ctx: None, max_rows: 10 }
+    }
+
+    pub fn context(mut self, ctx: &'a ParseContext) -> Self {
+        self.ctx = Some(ctx);
+        self
+    }
+
+    pub fn max_rows(mut self, max_rows: usize) -> Self {
+        self.max_rows = max_rows;
+        self
+    }
+
+    // label and text of each line after the timestamp, server id, flags and checksum
+    fn payload(&self) -> Vec<(&'static str, String)> {
+        match self.data {
+            EventData::FormatDescriptionEvent { binlog_version, server_version, create_timestamp, .. }
+            | EventData::StartEventV3 { binlog_version, server_version, create_timestamp } => {
+                let mut lines = vec![("server", server_version.clone()), ("binlog", format!("v{}", binlog_version))];
+                if *create_timestamp != 0 {
+                    lines.push(("created", format!("{} UTC", utc_datetime(*create_timestamp))));
+                }
+                if self.event.is_binlog_in_use() {
+                    lines.push(("in use", "yes, or the server didn't close it".to_owned()));
+                }
+                lines
+            }
+            EventData::QueryEvent { thread_id, exec_time, error_code, schema, query } => vec![
+                ("schema", schema.clone()),
+                ("query", summarized(query)),
+                ("thread", thread_id.to_string()),
+                ("exec time", format!("{}s", exec_time)),
+                ("error code", error_code.to_string()),
+            ],
+            EventData::RotateEvent { position, next_file } => vec![("next file", format!("{} at {}", next_file, position))],
+            EventData::IntvarEvent { kind, value } => {
+                vec![(if *kind == 2 { "insert id" } else { "last insert id" }, value.to_string())]
+            }
+            EventData::RandEvent { seed1, seed2 } => vec![("seeds", format!("{}, {}", seed1, seed2))],
+            EventData::UserVarEvent { name, value } => vec![
+                ("variable", format!("@{}", name)),
+                ("value", match value {
+                    Some((_, _, bytes)) => format!("{} bytes", bytes.len()),
+                    None => "NULL".to_owned(),
+                }),
+            ],
+            EventData::XidEvent { xid } => vec![("xid", xid.to_string())],
+            EventData::TableMapEvent(table_map) => {
+                let columns: Vec<String> = table_map.columns.iter().enumerate()
+                    .map(|(i, c)| match &c.name {
+                        Some(name) => format!("{} {:?}", name, c.real_type()),
+                        None => format!("@{} {:?}", i + 1, c.real_type()),
+                    })
+                    .collect();
+                vec![
+                    ("table", format!("{}.{} (id {})", table_map.schema, table_map.table, table_map.table_id)),
+                    ("columns", columns.join(", ")),
+                ]
+            }
+            EventData::WriteRowsEvent(rows) | EventData::UpdateRowsEvent(rows) | EventData::DeleteRowsEvent(rows) => {
+                let table = match self.ctx.and_then(|ctx| ctx.table_map(rows.table_id)) {
+                    Some(table_map) => format!("{}.{} (id {})", table_map.schema, table_map.table, rows.table_id),
+                    None => format!("id {}", rows.table_id),
+                };
+                let verb = match rows.kind {
+                    RowsEventKind::Write => "inserted",
+                    RowsEventKind::Update => "updated",
+                    RowsEventKind::Delete => "deleted",
+                };
+                vec![("table", table), ("rows", format!("{} {}", rows.rows.len(), verb))]
+            }
+            EventData::IncidentEvent { incident_type, message } => vec![("incident", format!("{} ({})", message, incident_type))],
+            EventData::RowsQueryLogEvent { query } | EventData::AnnotateRowsEvent { query } => vec![("query", summarized(query))],
+            EventData::GtidLogEvent(gtid) | EventData::AnonymousGtidLogEvent(gtid) => {
+                let mut lines = vec![("gtid", format!("{}:{}", format_uuid(&gtid.sid), gtid.gno))];
+                if let (Some(last_committed), Some(sequence_number)) = (gtid.last_committed, gtid.sequence_number) {
+                    lines.push(("logical clock", format!("last committed {}, sequence number {}", last_committed, sequence_number)));
+                }
+                if let Some(micros) = gtid.immediate_commit_timestamp {
+                    lines.push(("committed", format!("{} UTC", utc_datetime((micros / 1_000_000) as u32))));
+                }
+                lines
+            }
+            EventData::PreviousGtidsLogEvent { gtids } => {
+                vec![("gtids", if gtids.is_empty() { "(empty)".to_owned() } else { gtids.to_string() })]
+            }
+            EventData::XaPrepareLogEvent { one_phase, format_id, gtrid, bqual } => vec![
+                ("xid", format!("gtrid {} bytes, bqual {} bytes, format {}", gtrid.len(), bqual.len(), format_id)),
+                ("one phase", one_phase.to_string()),
+            ],
+            EventData::TransactionPayloadEvent { compression_type, uncompressed_size, payload } => vec![
+                ("payload", format!("{} bytes, {} uncompressed", payload.len(), uncompressed_size)),
+                ("compression", if *compression_type == crate::payload::COMPRESSION_ZSTD { "zstd".to_owned() } else { compression_type.to_string() }),
+            ],
+            EventData::BinlogCheckpointEvent { file } => vec![("file", file.clone())],
+            EventData::MariaDbGtidEvent { domain_id, seq_no, flags, commit_id } => {
+                let mut lines = vec![
+                    ("gtid", format!("{}-{}-{}", domain_id, self.event.server_id(), seq_no)),
+                    ("gtid flags", format!("0x{:02x}", flags)),
+                ];
+                if let Some(commit_id) = commit_id {
+                    lines.push(("commit id", commit_id.to_string()));
+                }
+                lines
+            }
+            EventData::MariaDbGtidListEvent { gtids } => {
+                vec![("gtids", gtids.to_string())]
+            }
+            EventData::HeartbeatLogEvent { log_ident } => vec![("file", log_ident.clone())],
+            EventData::Unparsed { reason, .. } => vec![("not decoded", reason.clone())],
+            _ => vec![("body", format!("{} bytes", self.event.body_len()))],
+        }
+    }
+}
+
+impl fmt::Display for PrettyEvent<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let event = self.event;
+        writeln!(f, "#{} {:?}, {} bytes, next {}", event.offset(), event.type_code(), event.event_length(), event.next_position())?;
+        writeln!(f, "  {:<10} {} UTC ({})", "timestamp", utc_datetime(event.timestamp()), event.timestamp())?;
+        writeln!(f, "  {:<10} {}", "server id", event.server_id())?;
+        writeln!(f, "  {:<10} 0x{:04x}", "flags", event.flags())?;
+        if let Some(checksum) = event.checksum() {
+            writeln!(f, "  {:<10} 0x{:08x}", "checksum", checksum)?;
+        }
+        for (label, text) in self.payload() {
+            writeln!(f, "  {:<10} {}", label, text)?;
+        }
+        let rows = match self.data {
+            EventData::WriteRowsEvent(rows) | EventData::UpdateRowsEvent(rows) | EventData::DeleteRowsEvent(rows) => rows,
+            _ => return Ok(()),
+        };
+        for (i, change) in rows.rows.iter().take(self.max_rows).enumerate() {
+            let number = format!("[{}]", i + 1);
+            match (&change.before, &change.after) {
+                (Some(before), Some(after)) => {
+                    writeln!(f, "    {:<4} {}", number, image_text(before))?;
+                    writeln!(f, "      -> {}", image_text(after))?;
+                }
+                (Some(image), None) | (None, Some(image)) => writeln!(f, "    {:<4} {}", number, image_text(image))?,
+                (None, None) => {}
+            }
+        }
+        if rows.rows.len() > self.max_rows {
+            writeln!(f, "    ... {} more", rows.rows.len() - self.max_rows)?;
+        }
+        Ok(())
+    }
+}
+
+// name=value (or @n=value) for the columns the image has
+fn image_text(image: &RowImage) -> String {
+    let columns: Vec<String> = image.values().iter().enumerate()
+        .filter_map(|(i, value)| {
+            let value = value.as_ref()?;
+            Some(match image.column_names().and_then(|names| names.get(i)) {
+                Some(name) => format!("{}={}", name, value),
+                None => format!("@{}={}", i + 1, value),
+            })
+        })
+        .collect();
+    columns.join(", ")
+}
+
+// the statement on one line, cut after 200 characters
+fn summarized(query: &str) -> String {
+    let line = query.split_whitespace().collect::<Vec<_>>().join(" ");
+    match line.char_indices().nth(200) {
+        Some((cut, _)) => format!("{}...", &line[..cut]),
+        None => line,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::context::ParseContext;
+    use crate::event::tests::{parse_events, users_rows, users_table_map};
+
+    #[test]
+    fn test_pretty_rows_event() {
+        //given
+        // test.users (id INT, name VARCHAR(20)) with column names
+        let table_map = users_table_map(&[3, 15], &[80, 0], 0x02, b"\x04\x08\x02id\x04name");
+        let mut update_rows = users_rows(2, &[0x03, 0x03]);
+        update_rows.extend_from_slice(b"\x00\x07\x00\x00\x00\x03old\x00\x07\x00\x00\x00\x03new");
+        update_rows.extend_from_slice(b"\x00\x08\x00\x00\x00\x01a\x02\x08\x00\x00\x00");
+        // at 2024-01-31 09:05:01 UTC
+        let mut events = parse_events(1706691901, &[(19, table_map), (31, update_rows)]).into_iter();
+        let (table_map, update_rows) = (events.next().unwrap(), events.next().unwrap());
+        let mut ctx = ParseContext::new();
+        table_map.parsed(&mut ctx).unwrap();
+
+        //when
+        let data = update_rows.parsed(&mut ctx).unwrap();
+        let text = update_rows.pretty(&data).context(&ctx).max_rows(1).to_string();
+        let line = update_rows.to_string();
+
+        //then
+        assert_eq!(text, "#4 UpdateRowsEventV2, 62 bytes, next 0\n  \
+            timestamp  2024-01-31 09:05:01 UTC (1706691901)\n  \
+            server id  1\n  \
+            flags      0x0000\n  \
+            table      test.users (id 108)\n  \
+            rows       2 updated\n    \
+            [1]  id=7, name='old'\n      \
+            -> id=7, name='new'\n    \
+            ... 1 more\n");
+        assert_eq!(line, "#4 UpdateRowsEventV2 2024-01-31 09:05:01 UTC, server 1, 62 bytes, next 0");
+        assert!(format!("{:?}", update_rows).contains("type_code: UpdateRowsEventV2"));
+    }
+}
@@ -0,0 +1,326 @@
+use crate::context::ParseContext;
+use crate::errors::EventParseError;
+use crate::event::{self, EventData};
+use crate::export::plain_json;
+use crate::gtid::{format_uuid, MariaDbGtid};
+use crate::rows::{RowImage, RowsEvent, RowsEventKind};
+use crate::table_map::TableMapEvent;
+use crate::transaction;
+use crate::value;
+
+// The messages of proto/binlog.proto, which they have to be kept in step with, written out
+// for prost so building doesn't need protoc. Encode them with prost::Message::encode_to_vec().
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct EventHeader {
+    #[prost(uint32, tag = "1")]
+    pub timestamp: u32,
+    #[prost(uint32, tag = "2")]
+    pub type_code: u32,
+    #[prost(uint32, tag = "3")]
+    pub server_id: u32,
+    #[prost(uint32, tag = "4")]
+    pub event_length: u32,
+    #[prost(uint32, tag = "5")]
+    pub next_position: u32,
+    #[prost(uint32, tag = "6")]
+    pub flags: u32,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct Event {
+    #[prost(message, optional, tag = "1")]
+    pub header: Option<EventHeader>,
+    #[prost(uint64, tag = "2")]
+    pub offset: u64,
+    #[prost(bytes = "vec", tag = "3")]
+    pub data: Vec<u8>,
+    #[prost(uint32, optional, tag = "4")]
+    pub checksum: Option<u32>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct Timestamp {
+    #[prost(uint32, tag = "1")]
+    pub seconds: u32,
+    #[prost(uint32, tag = "2")]
+    pub micros: u32,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct Value {
+    #[prost(oneof = "ValueKind", tags = "1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17")]
+    pub kind: Option<ValueKind>,
+}
+
+#[derive(Clone, PartialEq, prost::Oneof)]
+pub enum ValueKind {
+    #[prost(bool, tag = "1")]
+    Null(bool),
+    #[prost(sint64, tag = "2")]
+    Int(i64),
+    #[prost(uint64, tag = "3")]
+    Uint(u64),
+    #[prost(float, tag = "4")]
+    Float(f32),
+    #[prost(double, tag = "5")]
+    Double(f64),
+    #[prost(string, tag = "6")]
+    Decimal(String),
+    #[prost(string, tag = "7")]
+    String(String),
+    #[prost(bytes = "vec", tag = "8")]
+    Bytes(Vec<u8>),
+    #[prost(uint32, tag = "9")]
+    Year(u32),
+    #[prost(string, tag = "10")]
+    Date(String),
+    #[prost(string, tag = "11")]
+    Time(String),
+    #[prost(string, tag = "12")]
+    Datetime(String),
+    #[prost(message, tag = "13")]
+    Timestamp(Timestamp),
+    #[prost(bytes = "vec", tag = "14")]
+    Bit(Vec<u8>),
+    #[prost(uint32, tag = "15")]
+    Enum(u32),
+    #[prost(uint64, tag = "16")]
+    Set(u64),
+    #[prost(bytes = "vec", tag = "17")]
+    Json(Vec<u8>),
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct Column {
+    #[prost(uint32, tag = "1")]
+    pub index: u32,
+    #[prost(string, tag = "2")]
+    pub name: String,
+    #[prost(message, optional, tag = "3")]
+    pub value: Option<Value>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct Row {
+    #[prost(message, repeated, tag = "1")]
+    pub columns: Vec<Column>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, prost::Enumeration)]
+#[repr(i32)]
+pub enum RowChangeKind {
+    Write = 0,
+    Update = 1,
+    Delete = 2,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct RowChange {
+    #[prost(string, tag = "1")]
+    pub schema: String,
+    #[prost(string, tag = "2")]
+    pub table: String,
+    #[prost(enumeration = "RowChangeKind", tag = "3")]
+    pub kind: i32,
+    #[prost(message, optional, tag = "4")]
+    pub before: Option<Row>,
+    #[prost(message, optional, tag = "5")]
+    pub after: Option<Row>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct Transaction {
+    #[prost(string, tag = "1")]
+    pub gtid: String,
+    #[prost(uint32, tag = "2")]
+    pub begin_timestamp: u32,
+    #[prost(uint64, tag = "3")]
+    pub start_offset: u64,
+    #[prost(uint64, tag = "4")]
+    pub end_offset: u64,
+    #[prost(string, repeated, tag = "5")]
+    pub statements: Vec<String>,
+    #[prost(message, repeated, tag = "6")]
+    pub changes: Vec<RowChange>,
+    #[prost(uint64, optional, tag = "7")]
+    pub xid: Option<u64>,
+}
+
+impl From<event::EventHeader> for EventHeader {
+    fn from(header: event::EventHeader) -> Self {
+        EventHeader {
+            timestamp: header.timestamp,
+            type_code: u32::from(header.type_code.to_byte()),
+            server_id: header.server_id,
+            event_length: header.event_length,
+            next_position: header.next_position,
+            flags: u32::from(header.flags),
+        }
+    }
+}
+
+impl Event {
+    pub fn from_event(event: &event::Event) -> Result<Self, EventParseError> {
+        Ok(Event {
+            header: Some(event.header().into()),
+            offset: event.offset(),
+            data: event.try_data()?.to_vec(),
+            checksum: event.checksum(),
+        })
+    }
+}
+
+impl From<&value::Value> for Value {
+    fn from(value: &value::Value) -> Self {
+        let text = || match plain_json(value) {
+            serde_json::Value::String(s) => s,
+            other => other.to_string(),
+        };
+        let kind = match value {
+            value::Value::Null => ValueKind::Null(true),
+            value::Value::Int(v) => ValueKind::Int(*v),
+            value::Value::UInt(v) => ValueKind::Uint(*v),
+            value::Value::Float(v) => ValueKind::Float(*v),
+            value::Value::Double(v) => ValueKind::Double(*v),
+            value::Value::Decimal(v) => ValueKind::Decimal(v.clone()),
+            value::Value::String(v) => ValueKind::String(v.clone()),
+            value::Value::Bytes(v) => ValueKind::Bytes(v.clone()),
+            value::Value::Year(v) => ValueKind::Year(u32::from(*v)),
+            value::Value::Date { .. } => ValueKind::Date(text()),
+            value::Value::Time { .. } => ValueKind::Time(text()),
+            value::Value::DateTime { .. } => ValueKind::Datetime(text()),
+            value::Value::Timestamp { seconds, micros } => ValueKind::Timestamp(Timestamp { seconds: *seconds, micros: *micros }),
+            value::Value::Bit(v) => ValueKind::Bit(v.clone()),
+            value::Value::Enum(v) => ValueKind::Enum(u32::from(*v)),
+            value::Value::Set(v) => ValueKind::Set(*v),
+            value::Value::Json(v) => ValueKind::Json(v.clone()),
+        };
+        Value { kind: Some(kind) }
+    }
+}
+
+impl RowChange {
+    // one message per changed row of `rows`, against the table map it refers to
+    pub fn from_rows(rows: &RowsEvent, table_map: &TableMapEvent) -> Vec<Self> {
+        let kind = match rows.kind {
+            RowsEventKind::Write => RowChangeKind::Write,
+            RowsEventKind::Update => RowChangeKind::Update,
+            RowsEventKind::Delete => RowChangeKind::Delete,
+        };
+        rows.rows.iter().map(|change| RowChange {
+            schema: table_map.schema.clone(),
+            table: table_map.table.clone(),
+            kind: kind as i32,
+            before: change.before.as_ref().map(Row::from),
+            after: change.after.as_ref().map(Row::from),
+        }).collect()
+    }
+}
+
+impl From<&RowImage> for Row {
+    fn from(image: &RowImage) -> Self {
+        let names = image.column_names();
+        let columns = image.values().iter().enumerate()
+            .filter_map(|(i, value)| Some(Column {
+                index: i as u32,
+                name: names.and_then(|n| n.get(i)).cloned().unwrap_or_default(),
+                value: Some(Value::from(value.as_ref()?)),
+            }))
+            .collect();
+        Row { columns }
+    }
+}
+
+impl Transaction {
+    // parses the transaction's events through `ctx`, which must have seen the events before it
+    pub fn from_transaction(tx: &transaction::Transaction, ctx: &mut ParseContext) -> Result<Self, EventParseError> {
+        let mut message = Transaction {
+            gtid: String::new(),
+            begin_timestamp: tx.events().first().map_or(0, |e| e.timestamp()),
+            start_offset: tx.start_offset(),
+            end_offset: tx.end_offset(),
+            statements: vec![],
+            changes: vec![],
+            xid: None,
+        };
+        for event in tx.events() {
+            match event.parsed(ctx)? {
+                EventData::GtidLogEvent(gtid) => message.gtid = format!("{}:{}", format_uuid(&gtid.sid), gtid.gno),
+                EventData::MariaDbGtidEvent { domain_id, seq_no, .. } => {
+                    message.gtid = MariaDbGtid { domain_id, server_id: event.server_id(), seq_no }.to_string();
+                }
+                EventData::QueryEvent { query, .. } if !matches!(query.as_str(), "BEGIN" | "COMMIT") => message.statements.push(query),
+                EventData::RowsQueryLogEvent { query } | EventData::AnnotateRowsEvent { query } => message.statements.push(query),
+                EventData::WriteRowsEvent(rows) | EventData::UpdateRowsEvent(rows) | EventData::DeleteRowsEvent(rows) => {
+                    if let Some(table_map) = ctx.table_map(rows.table_id) {
+                        message.changes.extend(RowChange::from_rows(&rows, table_map));
+                    }
+                }
+                EventData::XidEvent { xid } => message.xid = Some(xid),
+                _ => {}
+            }
+        }
+        Ok(message)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use prost::Message;
+    use crate::binlog_file::BinlogFile;
+    use crate::context::ParseContext;
+    use crate::event::Event;
+    use crate::event::tests::{timed_event_bytes, users_rows, users_table_map};
+    use crate::protobuf;
+    use crate::transaction::TransactionItem;
+
+    #[test]
+    fn test_event_round_trip() {
+        //given
+        let event = BinlogFile::from_path("tests/asset/mysql-bin.100746").unwrap().events().next().unwrap().unwrap();
+
+        //when
+        let bytes = protobuf::Event::from_event(&event).unwrap().encode_to_vec();
+        let decoded = protobuf::Event::decode(&bytes[..]).unwrap();
+
+        //then
+        assert_eq!(decoded.offset, 4);
+        assert_eq!(decoded.header.unwrap().type_code, 15);
+        assert_eq!(decoded.data, event.data());
+        assert_eq!(decoded.checksum, event.checksum());
+    }
+
+    #[test]
+    fn test_transaction_message() {
+        //given
+        let mut begin = vec![0, 0, 0, 0, 0, 0, 0, 0, 4, 0, 0, 0, 0];
+        begin.extend_from_slice(b"test\x00BEGIN");
+        let table_map = users_table_map(&[3], &[], 0x00, &[]);
+        let write_rows = users_rows(1, &[0x01, 0x00, 7, 0, 0, 0]);
+        let mut bytes = vec![];
+        for (type_code, body) in [(2, &begin[..]), (19, &table_map[..]), (30, &write_rows[..]), (16, &42u64.to_le_bytes()[..])] {
+            bytes.extend(timed_event_bytes(16, type_code, body, 0));
+        }
+        let mut reader = &bytes[..];
+        let events = std::iter::from_fn(|| {
+            if reader.is_empty() { None } else { Some(Event::parse(&mut reader, 4).map_err(Into::into)) }
+        });
+        let tx = match crate::transaction::Transactions::new(events).next().unwrap().unwrap() {
+            TransactionItem::Transaction(tx) => tx,
+            item => panic!("{:?}", item),
+        };
+
+        //when
+        let message = protobuf::Transaction::from_transaction(&tx, &mut ParseContext::new()).unwrap();
+        let decoded = protobuf::Transaction::decode(&message.encode_to_vec()[..]).unwrap();
+
+        //then
+        assert_eq!(decoded, message);
+        assert_eq!(message.xid, Some(42));
+        assert_eq!(message.begin_timestamp, 16);
+        assert_eq!(message.changes.len(), 1);
+        let column = &message.changes[0].after.as_ref().unwrap().columns[0];
+        assert_eq!(column.value, Some(protobuf::Value { kind: Some(protobuf::ValueKind::Int(7)) }));
+    }
+}
@@ -0,0 +1,398 @@
+use std::io::{Read, Write};
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+use crate::errors::ClientError;
+use crate::utils::{read_lenenc_bytes, read_lenenc_int};
+
+// https://dev.mysql.com/doc/dev/mysql-server/latest/group__group__cs__capabilities__flags.html
+pub(crate) const CLIENT_LONG_PASSWORD: u32 = 0x1;
+pub(crate) const CLIENT_PROTOCOL_41: u32 = 0x200;
+#[cfg(feature = "tls")]
+pub(crate) const CLIENT_SSL: u32 = 0x800;
+pub(crate) const CLIENT_TRANSACTIONS: u32 = 0x2000;
+pub(crate) const CLIENT_SECURE_CONNECTION: u32 = 0x8000;
+pub(crate) const CLIENT_PLUGIN_AUTH: u32 = 0x8_0000;
+
+pub(crate) const COM_QUERY: u8 = 0x03;
+pub(crate) const COM_BINLOG_DUMP: u8 = 0x12;
+pub(crate) const COM_REGISTER_SLAVE: u8 = 0x15;
+pub(crate) const COM_BINLOG_DUMP_GTID: u8 = 0x1e;
+
+const OK_PACKET: u8 = 0x00;
+const EOF_PACKET: u8 = 0xfe;
+const ERR_PACKET: u8 = 0xff;
+const NULL_COLUMN: u8 = 0xfb;
+const UTF8MB4_GENERAL_CI: u8 = 45;
+// the largest packet, reassembled, this client accepts: the most max_allowed_packet can be
+const MAX_PACKET_SIZE: u32 = 0x4000_0000;
+// longer payloads are split into packets of this length, ended by a shorter (maybe empty) one
+const MAX_PAYLOAD_LEN: usize = 0xff_ffff;
+
+// anything a connection can run over: a TcpStream, or a TLS session over one
+pub(crate) trait Transport: Read + Write + Send {}
+
+impl<T: Read + Write + Send> Transport for T {}
+
+// MySQL client/server packets: a 3 byte payload length and a sequence id, then the payload.
+// https://dev.mysql.com/doc/dev/mysql-server/latest/page_protocol_basic_packets.html
+pub(crate) struct Connection {
+    stream: Box<dyn Transport>,
+    seq: u8,
+    max_packet_size: usize,
+}
+
+impl Connection {
+    pub(crate) fn new(stream: Box<dyn Transport>) -> Self {
+        Connection { stream, seq: 0, max_packet_size: MAX_PACKET_SIZE as usize }
+    }
+
+    // a whole payload, however many packets it was split into (rows events of big
+    // transactions or BLOBs easily exceed 16 MB)
+    pub(crate) fn read_packet(&mut self) -> Result<Vec<u8>, ClientError> {
+        let mut payload = vec![];
+        loop {
+            let mut header = [0u8; 4];
+            self.stream.read_exact(&mut header)?;
+            let len = u32::from_le_bytes([header[0], header[1], header[2], 0]) as usize;
+            self.seq = header[3].wrapping_add(1);
+            let start = payload.len();
+            if start + len > self.max_packet_size {
+                return Err(ClientError::PacketTooLarge(self.max_packet_size));
+            }
+            payload.resize(start + len, 0);
+            self.stream.read_exact(&mut payload[start..])?;
+            if len < MAX_PAYLOAD_LEN {
+                return Ok(payload);
+            }
+        }
+    }
+
+    pub(crate) fn write_packet(&mut self, payload: &[u8]) -> Result<(), ClientError> {
+        let mut chunks = payload.chunks(MAX_PAYLOAD_LEN);
+        loop {
+            let chunk = chunks.next().unwrap_or(&[]);
+            let len = (chunk.len() as u32).to_le_bytes();
+            self.stream.write_all(&[len[0], len[1], len[2], self.seq])?;
+            self.stream.write_all(chunk)?;
+            self.seq = self.seq.wrapping_add(1);
+            if chunk.len() < MAX_PAYLOAD_LEN {
+                break;
+            }
+        }
+        self.stream.flush()?;
+        Ok(())
+    }
+
+    // swaps the transport, e.g. for a TLS session over it, keeping the sequence going
+    #[cfg(feature = "tls")]
+    pub(crate) fn upgrade<F>(self, wrap: F) -> Result<Self, ClientError> where
+        F: FnOnce(Box<dyn Transport>) -> Result<Box<dyn Transport>, ClientError>
+    {
+        Ok(Connection {
+            stream: wrap(self.stream)?,
+            seq: self.seq,
+            max_packet_size: self.max_packet_size,
+        })
+    }
+
+    // each command starts a new sequence
+    pub(crate) fn write_command(&mut self, command: u8, body: &[u8]) -> Result<(), ClientError> {
+        self.seq = 0;
+        let mut payload = Vec::with_capacity(body.len() + 1);
+        payload.push(command);
+        payload.extend_from_slice(body);
+        self.write_packet(&payload)
+    }
+
+    // for commands answered with a plain OK
+    pub(crate) fn read_ok(&mut self) -> Result<(), ClientError> {
+        let packet = self.read_packet()?;
+        match packet.first() {
+            Some(&OK_PACKET) => Ok(()),
+            Some(&ERR_PACKET) => Err(parse_err_packet(&packet)),
+            _ => Err(ClientError::BadPacket("OK")),
+        }
+    }
+
+    // for statements with no result set
+    pub(crate) fn execute(&mut self, sql: &str) -> Result<(), ClientError> {
+        self.write_command(COM_QUERY, sql.as_bytes())?;
+        self.read_ok()
+    }
+
+    // The rows of a text result set, NULL as None. Without CLIENT_DEPRECATE_EOF the column
+    // definitions and the rows each end with an EOF packet.
+    // https://dev.mysql.com/doc/dev/mysql-server/latest/page_protocol_com_query_response_text_resultset.html
+    pub(crate) fn query(&mut self, sql: &str) -> Result<Vec<Vec<Option<String>>>, ClientError> {
+        self.write_command(COM_QUERY, sql.as_bytes())?;
+        let packet = self.read_packet()?;
+        let column_count = match packet.first() {
+            Some(&OK_PACKET) => return Ok(vec![]),
+            Some(&ERR_PACKET) => return Err(parse_err_packet(&packet)),
+            _ => read_lenenc_int(&mut &packet[..]).map_err(|_| ClientError::BadPacket("column count"))?,
+        };
+        for _ in 0..column_count {
+            self.read_packet()?;
+        }
+        if !is_eof(&self.read_packet()?) {
+            return Err(ClientError::BadPacket("EOF"));
+        }
+        let mut rows = vec![];
+        loop {
+            let packet = self.read_packet()?;
+            if is_eof(&packet) {
+                return Ok(rows);
+            }
+            if packet.first() == Some(&ERR_PACKET) {
+                return Err(parse_err_packet(&packet));
+            }
+            let mut reader = &packet[..];
+            let mut row = vec![];
+            for _ in 0..column_count {
+                if reader.first() == Some(&NULL_COLUMN) {
+                    reader = &reader[1..];
+                    row.push(None);
+                } else {
+                    let value = read_lenenc_bytes(&mut reader).map_err(|_| ClientError::BadPacket("row"))?;
+                    row.push(Some(String::from_utf8_lossy(&value).into_owned()));
+                }
+            }
+            rows.push(row);
+        }
+    }
+}
+
+// a row can start with 0xfe too, but is then at least 9 bytes long
+pub(crate) fn is_eof(packet: &[u8]) -> bool {
+    packet.first() == Some(&EOF_PACKET) && packet.len() < 9
+}
+
+// https://dev.mysql.com/doc/dev/mysql-server/latest/page_protocol_basic_err_packet.html
+pub(crate) fn parse_err_packet(packet: &[u8]) -> ClientError {
+    let mut reader = packet.get(1..).unwrap_or_default();
+    let code = reader.read_u16::<LittleEndian>().unwrap_or(0);
+    // '#' and a five character SQL state precede the message
+    let (sql_state, message) = match (reader.first(), reader.get(1..6), reader.get(6..)) {
+        (Some(b'#'), Some(sql_state), Some(message)) => (Some(String::from_utf8_lossy(sql_state).into_owned()), message),
+        _ => (None, reader),
+    };
+    ClientError::Server {
+        code,
+        sql_state,
+        message: String::from_utf8_lossy(message).into_owned(),
+    }
+}
+
+// https://dev.mysql.com/doc/dev/mysql-server/latest/page_protocol_connection_phase_packets_protocol_handshake_v10.html
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Handshake {
+    pub(crate) server_version: String,
+    pub(crate) connection_id: u32,
+    pub(crate) capabilities: u32,
+    // the challenge password scrambles are computed against
+    pub(crate) auth_plugin_data: Vec<u8>,
+    pub(crate) auth_plugin_name: String,
+}
+
+impl Handshake {
+    pub(crate) fn parse(packet: &[u8]) -> Result<Self, ClientError> {
+        if packet.first() == Some(&ERR_PACKET) {
+            return Err(parse_err_packet(packet));
+        }
+        let bad = |_| ClientError::BadPacket("handshake");
+        let mut reader = packet;
+        if reader.read_u8().map_err(bad)? != 10 {
+            return Err(ClientError::BadPacket("handshake"));
+        }
+        let server_version = read_nul_string(&mut reader).ok_or(ClientError::BadPacket("handshake"))?;
+        let connection_id = reader.read_u32::<LittleEndian>().map_err(bad)?;
+        let mut auth_plugin_data = reader.get(..8).ok_or(ClientError::BadPacket("handshake"))?.to_vec();
+        // the first 8 bytes of the challenge and a filler byte
+        reader = reader.get(9..).ok_or(ClientError::BadPacket("handshake"))?;
+        let mut capabilities = u32::from(reader.read_u16::<LittleEndian>().map_err(bad)?);
+        let mut auth_plugin_name = String::new();
+        // servers from before 4.1 stop here
+        if !reader.is_empty() {
+            let _charset = reader.read_u8().map_err(bad)?;
+            let _status = reader.read_u16::<LittleEndian>().map_err(bad)?;
+            capabilities |= u32::from(reader.read_u16::<LittleEndian>().map_err(bad)?) << 16;
+            let auth_plugin_data_len = usize::from(reader.read_u8().map_err(bad)?);
+            reader = reader.get(10..).ok_or(ClientError::BadPacket("handshake"))?;
+            if capabilities & CLIENT_SECURE_CONNECTION != 0 {
+                let len = auth_plugin_data_len.saturating_sub(8).max(13).min(reader.len());
+                // the last byte is a NUL terminator, not part of the challenge
+                let part = &reader[..len];
+                auth_plugin_data.extend_from_slice(part.strip_suffix(&[0]).unwrap_or(part));
+                reader = &reader[len..];
+            }
+            if capabilities & CLIENT_PLUGIN_AUTH != 0 {
+                auth_plugin_name = read_nul_string(&mut reader).unwrap_or_else(|| String::from_utf8_lossy(reader).into_owned());
+            }
+        }
+        Ok(Handshake {
+            server_version,
+            connection_id,
+            capabilities,
+            auth_plugin_data,
+            auth_plugin_name,
+        })
+    }
+}
+
+// https://dev.mysql.com/doc/dev/mysql-server/latest/page_protocol_connection_phase_packets_protocol_handshake_response.html
+pub(crate) fn handshake_response(capabilities: u32, user: &str, auth_response: &[u8], auth_plugin_name: &str) -> Vec<u8> {
+    let mut packet = ssl_request(capabilities);
+    packet.extend_from_slice(user.as_bytes());
+    packet.push(0);
+    packet.push(auth_response.len() as u8);
+    packet.extend_from_slice(auth_response);
+    if capabilities & CLIENT_PLUGIN_AUTH != 0 {
+        packet.extend_from_slice(auth_plugin_name.as_bytes());
+        packet.push(0);
+    }
+    packet
+}
+
+// the fixed start of the handshake response, sent alone before switching to TLS
+// https://dev.mysql.com/doc/dev/mysql-server/latest/page_protocol_connection_phase_packets_protocol_ssl_request.html
+pub(crate) fn ssl_request(capabilities: u32) -> Vec<u8> {
+    let mut packet = vec![];
+    packet.write_u32::<LittleEndian>(capabilities).unwrap();
+    packet.write_u32::<LittleEndian>(MAX_PACKET_SIZE).unwrap();
+    packet.push(UTF8MB4_GENERAL_CI);
+    packet.extend_from_slice(&[0; 23]);
+    packet
+}
+
+pub(crate) fn read_nul_string(reader: &mut &[u8]) -> Option<String> {
+    let end = reader.iter().position(|&b| b == 0)?;
+    let s = String::from_utf8_lossy(&reader[..end]).into_owned();
+    *reader = &reader[end + 1..];
+    Some(s)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::errors::ClientError;
+    use std::io::{Cursor, Read, Write};
+    use std::sync::{Arc, Mutex};
+    use crate::protocol::{parse_err_packet, Connection, Handshake, CLIENT_PLUGIN_AUTH, MAX_PAYLOAD_LEN};
+
+    // reads from `input` and appends what's written to `output`
+    struct Loopback {
+        input: Cursor<Vec<u8>>,
+        output: Arc<Mutex<Vec<u8>>>,
+    }
+
+    impl Read for Loopback {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            self.input.read(buf)
+        }
+    }
+
+    impl Write for Loopback {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.output.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_parse_handshake_and_err_packets() {
+        //given
+        let mut handshake = vec![10];
+        handshake.extend_from_slice(b"8.0.36\0");
+        handshake.extend_from_slice(&7u32.to_le_bytes());
+        handshake.extend_from_slice(b"abcdefgh\0");
+        handshake.extend_from_slice(&0xffffu16.to_le_bytes());
+        handshake.push(45);
+        handshake.extend_from_slice(&2u16.to_le_bytes());
+        handshake.extend_from_slice(&0x00ffu16.to_le_bytes());
+        handshake.push(21);
+        handshake.extend_from_slice(&[0; 10]);
+        handshake.extend_from_slice(b"ijklmnopqrst\0");
+        handshake.extend_from_slice(b"mysql_native_password\0");
+        let mut err = vec![0xff];
+        err.extend_from_slice(&1236u16.to_le_bytes());
+        err.extend_from_slice(b"#HY000Could not find first log file name in binary log index file");
+
+        //when
+        let handshake = Handshake::parse(&handshake).unwrap();
+        let err = parse_err_packet(&err);
+
+        //then
+        assert_eq!(handshake.server_version, "8.0.36");
+        assert_eq!(handshake.connection_id, 7);
+        assert_ne!(handshake.capabilities & CLIENT_PLUGIN_AUTH, 0);
+        assert_eq!(handshake.auth_plugin_data, b"abcdefghijklmnopqrst");
+        assert_eq!(handshake.auth_plugin_name, "mysql_native_password");
+        assert!(matches!(err, ClientError::Server { code: 1236, ref sql_state, ref message }
+            if sql_state.as_deref() == Some("HY000") && message.starts_with("Could not find")));
+    }
+
+    #[test]
+    fn test_short_handshakes_are_bad_packets() {
+        //given
+        let mut handshake = vec![10];
+        handshake.extend_from_slice(b"5.7.44\0");
+        handshake.extend_from_slice(&7u32.to_le_bytes());
+        handshake.extend_from_slice(b"abcdefgh\0");
+        handshake.extend_from_slice(&0xffffu16.to_le_bytes());
+        handshake.push(45);
+        handshake.extend_from_slice(&2u16.to_le_bytes());
+        handshake.extend_from_slice(&0x00ffu16.to_le_bytes());
+        handshake.push(21);
+        handshake.extend_from_slice(&[0; 10]);
+        handshake.extend_from_slice(b"ijklmnopqrst\0");
+
+        //when
+        // every prefix has to come back as a handshake or an error, never a panic
+        let parsed: Vec<_> = (0..handshake.len()).map(|len| Handshake::parse(&handshake[..len])).collect();
+
+        //then
+        // up to the challenge's filler byte
+        assert!(parsed[..21].iter().all(|h| matches!(h, Err(ClientError::BadPacket("handshake")))));
+    }
+
+    #[test]
+    fn test_payloads_over_16mb_span_packets() {
+        //given
+        let long: Vec<u8> = (0..2 * MAX_PAYLOAD_LEN + 10).map(|i| i as u8).collect();
+        let exact = vec![7u8; MAX_PAYLOAD_LEN];
+        let output = Arc::new(Mutex::new(vec![]));
+        let mut writer = Connection::new(Box::new(Loopback { input: Cursor::new(vec![]), output: output.clone() }));
+
+        //when
+        writer.write_packet(&long).unwrap();
+        writer.write_packet(&exact).unwrap();
+        let framed = output.lock().unwrap().clone();
+        let mut reader = Connection::new(Box::new(Loopback { input: Cursor::new(framed.clone()), output }));
+        let read = [reader.read_packet().unwrap(), reader.read_packet().unwrap()];
+
+        //then
+        // 3 packets for the long payload, and an empty one after a payload of exactly the maximum
+        assert_eq!(framed.len(), long.len() + exact.len() + 4 * (3 + 2));
+        assert_eq!(&framed[..4], &[0xff, 0xff, 0xff, 0]);
+        assert_eq!(&framed[framed.len() - 4..], &[0, 0, 0, 4]);
+        assert_eq!(read, [long, exact]);
+    }
+
+    #[test]
+    fn test_payloads_over_max_packet_size_are_refused() {
+        //given
+        let output = Arc::new(Mutex::new(vec![]));
+        let mut writer = Connection::new(Box::new(Loopback { input: Cursor::new(vec![]), output: output.clone() }));
+        writer.write_packet(&vec![7u8; MAX_PAYLOAD_LEN + 10]).unwrap();
+        let framed = output.lock().unwrap().clone();
+        let mut reader = Connection::new(Box::new(Loopback { input: Cursor::new(framed), output }));
+        reader.max_packet_size = MAX_PAYLOAD_LEN + 9;
+
+        //when
+        let read = reader.read_packet();
+
+        //then
+        assert!(matches!(read, Err(ClientError::PacketTooLarge(max)) if max == MAX_PAYLOAD_LEN + 9));
+    }
+}
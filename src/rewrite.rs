@@ -0,0 +1,368 @@
+use std::collections::{HashMap, HashSet};
+use std::convert::TryFrom;
+use std::io::{Cursor, Write};
+use byteorder::{LittleEndian, ReadBytesExt};
+use crate::binlog_writer::BinlogWriter;
+use crate::errors::{BinlogFileError, EventParseError, RewriteError};
+use crate::event::{Event, EventData, TypeCode};
+use crate::filter::{peek_query_schema, peek_table_map_names, TableRule};
+use crate::rows::RowsEventKind;
+use crate::transaction::{role, Role, TransactionItem, Transactions};
+
+// set on the last rows event of a statement
+const STMT_END_F: u16 = 0x0001;
+
+// Copies one binlog file to another, leaving out what matches the exclusion rules, with
+// positions and checksums fixed up by a BinlogWriter. Rules on the origin of a change, its
+// server id, the session (thread id) of its statements or its time, drop the whole transaction;
+// the binlog doesn't record which user made a change. Table rules drop the table maps and rows
+// events of those tables from the transactions they're in, and the transactions left with no
+// changes. Statements (DDL, or DML logged as statements) are kept: which tables they change
+// isn't known without parsing the SQL.
+//
+// Renames change the schema and table a table map names, like replicate-rewrite-db and
+// replicate-rewrite-table on a replica, and optionally the default schema of statements;
+// names written in the statements themselves stay as they are. The rules above match the
+// original names.
+//
+// A dropped transaction's GTID is dropped with it, so the output's GTIDs have a gap where it was.
+//
+//     let rewriter = Rewriter::new().exclude_wild_table("shop.audit_%").exclude_server_id(3).rewrite_db("shop", "shop_staging");
+//     let summary = rewriter.rewrite(BinlogFile::from_path(input)?.events(), File::create(output)?)?;
+#[derive(Debug, Clone, Default)]
+pub struct Rewriter {
+    exclude_tables: Vec<TableRule>,
+    exclude_server_ids: HashSet<u32>,
+    exclude_thread_ids: HashSet<u32>,
+    // [start, stop) timestamps
+    exclude_windows: Vec<(u32, u32)>,
+    rename_schemas: HashMap<String, String>,
+    rename_tables: HashMap<(String, String), (String, String)>,
+    rewrite_query_schemas: bool,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RewriteSummary {
+    pub transactions: usize,
+    pub dropped_transactions: usize,
+    // all events left out, those of dropped transactions included
+    pub dropped_events: usize,
+    // the size of the rewritten binlog
+    pub end_offset: u64,
+}
+
+impl Rewriter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn exclude_table(self, schema: &str, table: &str) -> Self {
+        self.exclude_table_rule(TableRule::Exact(schema.to_owned(), table.to_owned()))
+    }
+
+    pub fn exclude_wild_table(self, pattern: &str) -> Self {
+        self.exclude_table_rule(TableRule::wild(pattern))
+    }
+
+    pub fn exclude_table_rule(mut self, rule: TableRule) -> Self {
+        self.exclude_tables.push(rule);
+        self
+    }
+
+    pub fn exclude_server_id(mut self, server_id: u32) -> Self {
+        self.exclude_server_ids.insert(server_id);
+        self
+    }
+
+    // the connection id a session's statements are logged with, as in SHOW PROCESSLIST
+    pub fn exclude_thread_id(mut self, thread_id: u32) -> Self {
+        self.exclude_thread_ids.insert(thread_id);
+        self
+    }
+
+    // transactions starting at or after `start` and before `stop`
+    pub fn exclude_window(mut self, start: u32, stop: u32) -> Self {
+        self.exclude_windows.push((start, stop));
+        self
+    }
+
+    // changes to the tables of `from` are written as changes to those of `to`
+    pub fn rewrite_db(mut self, from: &str, to: &str) -> Self {
+        self.rename_schemas.insert(from.to_owned(), to.to_owned());
+        self
+    }
+
+    // takes precedence over rewrite_db for the table
+    pub fn rename_table(mut self, schema: &str, table: &str, new_schema: &str, new_table: &str) -> Self {
+        self.rename_tables.insert((schema.to_owned(), table.to_owned()), (new_schema.to_owned(), new_table.to_owned()));
+        self
+    }
+
+    // whether rewrite_db also applies to the default schema statements are run in
+    pub fn rewrite_query_schemas(mut self, rewrite: bool) -> Self {
+        self.rewrite_query_schemas = rewrite;
+        self
+    }
+
+    // Writes `events`, those of one binlog file from its FormatDescriptionEvent on, to `writer`
+    // without what the rules exclude.
+    pub fn rewrite<It, W>(&self, events: It, writer: W) -> Result<RewriteSummary, RewriteError> where
+        It: Iterator<Item = Result<Event, BinlogFileError>>,
+        W: Write
+    {
+        let mut events = events;
+        let format_description = events.next().ok_or(RewriteError::Empty)??;
+        let mut binlog = BinlogWriter::new(writer, &format_description)?;
+        let mut summary = RewriteSummary::default();
+        // table id -> whether the table is excluded
+        let mut tables = HashMap::new();
+        for item in Transactions::new(events) {
+            let events = match item? {
+                TransactionItem::Transaction(transaction) => transaction.into_events(),
+                TransactionItem::Event(event) => {
+                    binlog.write_event(&event)?;
+                    continue;
+                }
+            };
+            summary.transactions += 1;
+            let count = events.len();
+            let kept = match self.is_excluded(&events)? {
+                true => vec![],
+                false => self.without_excluded_tables(events, &mut tables)?,
+            };
+            summary.dropped_events += count - kept.len();
+            if kept.is_empty() {
+                summary.dropped_transactions += 1;
+            }
+            for mut event in kept {
+                if let Some(renamed) = self.renamed(&event)? {
+                    event.set_data(renamed);
+                }
+                binlog.write_event(&event)?;
+            }
+        }
+        summary.end_offset = binlog.offset();
+        binlog.finish()?;
+        Ok(summary)
+    }
+
+    // the payload of a table map or query event with its names rewritten, None when they stay
+    fn renamed(&self, event: &Event) -> Result<Option<Vec<u8>>, RewriteError> {
+        match event.type_code() {
+            TypeCode::TableMapEvent if !self.rename_tables.is_empty() || !self.rename_schemas.is_empty() => {
+                let data = event.try_data()?;
+                let (_, schema, table) = peek_table_map_names(data)?;
+                let (new_schema, new_table) = match self.rename_tables.get(&(schema.clone(), table.clone())) {
+                    Some((new_schema, new_table)) => (new_schema.as_str(), new_table.as_str()),
+                    None => match self.rename_schemas.get(&schema) {
+                        Some(new_schema) => (new_schema.as_str(), table.as_str()),
+                        None => return Ok(None),
+                    },
+                };
+                // table id and flags, then each name as a length, the name and a NUL
+                let schema_len = usize::from(data[8]);
+                let names_end = 8 + 1 + schema_len + 1 + 1 + usize::from(data[10 + schema_len]) + 1;
+                let mut payload = data[..8].to_vec();
+                for name in [new_schema, new_table] {
+                    payload.push(u8::try_from(name.len()).map_err(|_| RewriteError::NameTooLong(name.to_owned()))?);
+                    payload.extend_from_slice(name.as_bytes());
+                    payload.push(0);
+                }
+                payload.extend_from_slice(&data[names_end..]);
+                Ok(Some(payload))
+            }
+            TypeCode::QueryEvent if self.rewrite_query_schemas => {
+                let data = event.try_data()?;
+                let schema = peek_query_schema(data)?;
+                let new_schema = match self.rename_schemas.get(&schema) {
+                    Some(new_schema) => new_schema,
+                    None => return Ok(None),
+                };
+                // https://dev.mysql.com/doc/internals/en/query-event.html
+                let schema_start = 13 + usize::from(u16::from_le_bytes([data[11], data[12]]));
+                let mut payload = data[..8].to_vec();
+                payload.push(u8::try_from(new_schema.len()).map_err(|_| RewriteError::NameTooLong(new_schema.clone()))?);
+                payload.extend_from_slice(&data[9..schema_start]);
+                payload.extend_from_slice(new_schema.as_bytes());
+                payload.extend_from_slice(&data[schema_start + usize::from(data[8])..]);
+                Ok(Some(payload))
+            }
+            _ => Ok(None),
+        }
+    }
+
+    // whether the rules on where and when a transaction comes from drop it
+    fn is_excluded(&self, events: &[Event]) -> Result<bool, EventParseError> {
+        let first = &events[0];
+        if self.exclude_server_ids.contains(&first.server_id())
+            || self.exclude_windows.iter().any(|(start, stop)| (*start..*stop).contains(&first.timestamp())) {
+            return Ok(true);
+        }
+        if self.exclude_thread_ids.is_empty() {
+            return Ok(false);
+        }
+        for event in events.iter().filter(|e| e.type_code() == TypeCode::QueryEvent) {
+            if let Some(EventData::QueryEvent { thread_id, .. }) = Event::parse_event_data_by_type_code(TypeCode::QueryEvent, event.try_data()?)? {
+                if self.exclude_thread_ids.contains(&thread_id) {
+                    return Ok(true);
+                }
+            }
+        }
+        Ok(false)
+    }
+
+    // The transaction's events without the table maps and rows events of excluded tables; a
+    // statement's last rows event, when dropped, hands its STMT_END_F to the one kept before it.
+    // None at all when nothing changes any more.
+    fn without_excluded_tables(&self, events: Vec<Event>, tables: &mut HashMap<u64, bool>) -> Result<Vec<Event>, EventParseError> {
+        if self.exclude_tables.is_empty() {
+            return Ok(events);
+        }
+        let mut kept: Vec<Event> = Vec::with_capacity(events.len());
+        let (mut dropped_rows, mut changes) = (false, false);
+        // the rows event kept last in the statement being read
+        let mut last_rows = None;
+        for event in events {
+            let type_code = event.type_code();
+            let is_rows = RowsEventKind::from_type_code(type_code).is_some();
+            if type_code == TypeCode::TableMapEvent {
+                let (table_id, schema, table) = peek_table_map_names(event.try_data()?)?;
+                let excluded = self.exclude_tables.iter().any(|rule| rule.matches(&schema, &table));
+                tables.insert(table_id, excluded);
+                if excluded {
+                    continue;
+                }
+            } else if is_rows {
+                let (table_id, flags) = peek_rows_header(event.try_data()?)?;
+                if tables.get(&table_id).copied().unwrap_or(false) {
+                    dropped_rows = true;
+                    if flags & STMT_END_F != 0 {
+                        if let Some(i) = last_rows.take() {
+                            set_statement_end(&mut kept[i])?;
+                        }
+                    }
+                    continue;
+                }
+                changes = true;
+                last_rows = match flags & STMT_END_F {
+                    0 => Some(kept.len()),
+                    _ => None,
+                };
+            } else if matches!(role(&event)?, Role::Statement) {
+                changes = true;
+            }
+            kept.push(event);
+        }
+        if dropped_rows && !changes {
+            kept.clear();
+        }
+        Ok(kept)
+    }
+}
+
+fn set_statement_end(event: &mut Event) -> Result<(), EventParseError> {
+    let mut data = event.try_data()?.to_vec();
+    data[6] |= STMT_END_F as u8;
+    event.set_data(data);
+    Ok(())
+}
+
+// the table id and flags every rows event starts with
+fn peek_rows_header(data: &[u8]) -> Result<(u64, u16), EventParseError> {
+    let mut cursor = Cursor::new(data);
+    Ok((cursor.read_u48::<LittleEndian>()?, cursor.read_u16::<LittleEndian>()?))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::binlog_file::BinlogFile;
+    use crate::context::ParseContext;
+    use crate::event::{EventData, TypeCode};
+    use super::{RewriteSummary, Rewriter};
+
+    fn rewrite(rewriter: Rewriter) -> (RewriteSummary, Vec<TypeCode>) {
+        let mut file = BinlogFile::from_path("tests/asset/mysql-bin.100746").unwrap();
+        let mut out = vec![];
+        let summary = rewriter.rewrite(file.events(), &mut out).unwrap();
+        let mut rewritten = BinlogFile::from_reader(std::io::Cursor::new(out)).unwrap();
+        let report = rewritten.verify().unwrap();
+        assert!(report.is_ok(), "{:?}", report.problems);
+        assert_eq!(report.end_offset, summary.end_offset);
+        let types = rewritten.events().map(|e| e.unwrap().type_code()).collect();
+        (summary, types)
+    }
+
+    #[test]
+    fn test_rewrite_without_tables() {
+        //given
+        let other_table = Rewriter::new().exclude_table("test", "orders");
+        let users = Rewriter::new().exclude_wild_table("test.user%");
+
+        //when
+        let (kept, kept_types) = rewrite(other_table);
+        let (dropped, dropped_types) = rewrite(users);
+
+        //then
+        assert_eq!(kept, RewriteSummary { transactions: 3, dropped_transactions: 0, dropped_events: 0, end_offset: 1064 });
+        assert_eq!(kept_types.len(), 15);
+        assert_eq!(dropped.dropped_transactions, 2);
+        assert_eq!(dropped.dropped_events, 5 + 5);
+        assert_eq!(dropped_types, [
+            TypeCode::FormatDescriptionEvent,
+            TypeCode::PreviousGtidsLogEvent,
+            TypeCode::AnonymousGtidLogEvent,
+            TypeCode::QueryEvent,
+            TypeCode::RotateEvent,
+        ]);
+    }
+
+    #[test]
+    fn test_rewrite_names() {
+        //given
+        let path = "tests/asset/mysql-bin.100746";
+        let table_names = |rewriter: Rewriter| {
+            let mut out = vec![];
+            rewriter.rewrite(BinlogFile::from_path(path).unwrap().events(), &mut out).unwrap();
+            let mut ctx = ParseContext::new();
+            let mut names = vec![];
+            for event in BinlogFile::from_reader(std::io::Cursor::new(out)).unwrap().events() {
+                match event.unwrap().parsed(&mut ctx).unwrap() {
+                    EventData::TableMapEvent(table_map) => names.push(format!("{}.{}", table_map.schema, table_map.table)),
+                    EventData::QueryEvent { schema, query, .. } if query != "BEGIN" => names.push(format!("query in {}", schema)),
+                    _ => {}
+                }
+            }
+            names
+        };
+
+        //when
+        let db = table_names(Rewriter::new().rewrite_db("test", "staging"));
+        let db_and_queries = table_names(Rewriter::new().rewrite_db("test", "staging").rewrite_query_schemas(true));
+        let table = table_names(Rewriter::new().rewrite_db("test", "staging").rename_table("test", "users", "accounts", "members"));
+
+        //then
+        assert_eq!(db, ["query in test", "staging.users", "staging.users"]);
+        assert_eq!(db_and_queries, ["query in staging", "staging.users", "staging.users"]);
+        assert_eq!(table, ["query in test", "accounts.members", "accounts.members"]);
+    }
+
+    #[test]
+    fn test_rewrite_without_transactions() {
+        //given
+        let server = Rewriter::new().exclude_server_id(1);
+        let session = Rewriter::new().exclude_thread_id(7).exclude_thread_id(8);
+        let window = Rewriter::new().exclude_window(1_633_046_400, 1_633_046_401);
+        let other_window = Rewriter::new().exclude_window(0, 1_633_046_400);
+
+        //when
+        let results = [rewrite(server), rewrite(session), rewrite(window)];
+        let (_, other_window_types) = rewrite(other_window);
+
+        //then
+        for (summary, types) in &results {
+            assert_eq!(summary.dropped_transactions, 3);
+            assert_eq!(types, &[TypeCode::FormatDescriptionEvent, TypeCode::PreviousGtidsLogEvent, TypeCode::RotateEvent]);
+        }
+        assert_eq!(other_window_types.len(), 15);
+    }
+}
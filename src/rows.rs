@@ -0,0 +1,325 @@
+use std::io::Cursor;
+use std::sync::Arc;
+use byteorder::{LittleEndian, ReadBytesExt};
+use indexmap::IndexMap;
+use serde_derive::{Deserialize, Serialize};
+use crate::errors::EventParseError;
+use crate::event::TypeCode;
+use crate::options::ParseOptions;
+use crate::table_map::TableMapEvent;
+use crate::utils::{read_bitmap, read_lenenc_int, FieldContext};
+use crate::value::{parse_value, Value};
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum RowsEventKind {
+    Write,
+    Update,
+    Delete,
+}
+
+impl RowsEventKind {
+    pub fn from_type_code(type_code: TypeCode) -> Option<Self> {
+        match type_code {
+            TypeCode::WriteRowsEventV1 | TypeCode::WriteRowsEventV2 | TypeCode::PreGaWriteRowsEvent => Some(RowsEventKind::Write),
+            TypeCode::UpdateRowsEventV1 | TypeCode::UpdateRowsEventV2 | TypeCode::PreGaUpdateRowsEvent => Some(RowsEventKind::Update),
+            TypeCode::DeleteRowsEventV1 | TypeCode::DeleteRowsEventV2 | TypeCode::PreGaDeleteRowsEvent => Some(RowsEventKind::Delete),
+            _ => None,
+        }
+    }
+}
+
+// one row as it appears in a before or after image; columns left out by binlog_row_image=MINIMAL are None
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RowImage {
+    values: Vec<Option<Value>>,
+    column_names: Option<Arc<[String]>>,
+}
+
+impl RowImage {
+    pub fn values(&self) -> &[Option<Value>] {
+        &self.values
+    }
+
+    pub fn get(&self, index: usize) -> Option<&Value> {
+        self.values.get(index).and_then(|v| v.as_ref())
+    }
+
+    pub fn get_by_name(&self, name: &str) -> Option<&Value> {
+        let index = self.column_names.as_ref()?.iter().position(|c| c == name)?;
+        self.get(index)
+    }
+
+    pub fn column_names(&self) -> Option<&[String]> {
+        self.column_names.as_deref()
+    }
+
+    // column name -> value in column order, for the columns present in this image
+    pub fn as_map(&self) -> Option<IndexMap<&str, &Value>> {
+        let names = self.column_names.as_ref()?;
+        Some(
+            names.iter()
+                .zip(&self.values)
+                .filter_map(|(name, value)| value.as_ref().map(|v| (name.as_str(), v)))
+                .collect()
+        )
+    }
+
+    pub fn into_values(self) -> Vec<Option<Value>> {
+        self.values
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RowChange {
+    pub before: Option<RowImage>,
+    pub after: Option<RowImage>,
+}
+
+// https://dev.mysql.com/doc/internals/en/rows-event.html
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RowsEvent {
+    pub kind: RowsEventKind,
+    pub table_id: u64,
+    pub flags: u16,
+    pub column_count: usize,
+    pub rows: Vec<RowChange>,
+}
+
+impl RowsEvent {
+    pub fn parse(type_code: TypeCode, data: &[u8], table_map: &TableMapEvent) -> Result<Self, EventParseError> {
+        Self::parse_with_options(type_code, data, table_map, &ParseOptions::default())
+    }
+
+    pub fn parse_with_options(type_code: TypeCode, data: &[u8], table_map: &TableMapEvent, options: &ParseOptions) -> Result<Self, EventParseError> {
+        Self::parse_with_table_id_len(type_code, data, table_map, options, 6)
+    }
+
+    pub(crate) fn parse_with_table_id_len(
+        type_code: TypeCode,
+        data: &[u8],
+        table_map: &TableMapEvent,
+        options: &ParseOptions,
+        table_id_len: usize,
+    ) -> Result<Self, EventParseError> {
+        let mut cursor = Cursor::new(data);
+        let header = RowsHeader::parse(&mut cursor, type_code, table_map, table_id_len)?;
+        let (kind, before_present, after_present) = (header.kind, &header.before_present, &header.after_present);
+
+        let column_names: Option<Arc<[String]>> = table_map.column_names().map(Arc::from);
+        let mut rows = vec![];
+        while (cursor.position() as usize) < data.len() {
+            let start = cursor.position();
+            let row = match kind {
+                RowsEventKind::Write => RowChange {
+                    before: None,
+                    after: Some(parse_row_image(&mut cursor, table_map, after_present, &column_names, options)?),
+                },
+                RowsEventKind::Delete => RowChange {
+                    before: Some(parse_row_image(&mut cursor, table_map, before_present, &column_names, options)?),
+                    after: None,
+                },
+                RowsEventKind::Update => RowChange {
+                    before: Some(parse_row_image(&mut cursor, table_map, before_present, &column_names, options)?),
+                    after: Some(parse_row_image(&mut cursor, table_map, after_present, &column_names, options)?),
+                },
+            };
+            // with no columns present a row takes no bytes, and the rows would never end
+            if cursor.position() == start {
+                return Err(EventParseError::NoColumnsPresent(header.table_id));
+            }
+            rows.push(row);
+        }
+
+        Ok(RowsEvent {
+            kind,
+            table_id: header.table_id,
+            flags: header.flags,
+            column_count: header.column_count,
+            rows,
+        })
+    }
+}
+
+// Where the values of a rows event are in its body: (column index, start, end) for every
+// present, non-NULL value of every image, in order.
+pub(crate) fn value_spans(
+    type_code: TypeCode,
+    data: &[u8],
+    table_map: &TableMapEvent,
+    table_id_len: usize,
+) -> Result<Vec<(usize, usize, usize)>, EventParseError> {
+    let mut cursor = Cursor::new(data);
+    let header = RowsHeader::parse(&mut cursor, type_code, table_map, table_id_len)?;
+    let options = ParseOptions::default();
+    let mut spans = vec![];
+    while (cursor.position() as usize) < data.len() {
+        let row_start = cursor.position();
+        let mut images = vec![];
+        if header.kind != RowsEventKind::Write {
+            images.push(&header.before_present);
+        }
+        if header.kind != RowsEventKind::Delete {
+            images.push(&header.after_present);
+        }
+        for present in images {
+            let present_count = present.iter().filter(|p| **p).count();
+            let mut nulls = read_bitmap(&mut cursor, present_count).field("null_bitmap")?.into_iter();
+            for (i, (column, present)) in table_map.columns.iter().zip(present).enumerate() {
+                if !present || nulls.next().unwrap_or(false) {
+                    continue;
+                }
+                let start = cursor.position() as usize;
+                parse_value(&mut cursor, column, &options)?;
+                spans.push((i, start, cursor.position() as usize));
+            }
+        }
+        if cursor.position() == row_start {
+            return Err(EventParseError::NoColumnsPresent(header.table_id));
+        }
+    }
+    Ok(spans)
+}
+
+// what comes before the rows: which table, and which columns each image has
+struct RowsHeader {
+    kind: RowsEventKind,
+    table_id: u64,
+    flags: u16,
+    column_count: usize,
+    before_present: Vec<bool>,
+    after_present: Vec<bool>,
+}
+
+impl RowsHeader {
+    fn parse(cursor: &mut Cursor<&[u8]>, type_code: TypeCode, table_map: &TableMapEvent, table_id_len: usize) -> Result<Self, EventParseError> {
+        let kind = RowsEventKind::from_type_code(type_code)
+            .ok_or(EventParseError::UnexpectedEventType(type_code))?;
+        let table_id = cursor.read_uint::<LittleEndian>(table_id_len).field("table_id")?;
+        let flags = cursor.read_u16::<LittleEndian>().field("flags")?;
+        if matches!(type_code, TypeCode::WriteRowsEventV2 | TypeCode::UpdateRowsEventV2 | TypeCode::DeleteRowsEventV2) {
+            // the length includes its own two bytes
+            let extra_data_len = cursor.read_u16::<LittleEndian>().field("extra_data_len")?;
+            cursor.set_position(cursor.position() + u64::from(extra_data_len.saturating_sub(2)));
+        }
+
+        let column_count = read_lenenc_int(cursor).field("column_count")? as usize;
+        if column_count != table_map.columns.len() {
+            return Err(EventParseError::ColumnCountMismatch(table_id, table_map.columns.len(), column_count));
+        }
+        let before_present = read_bitmap(cursor, column_count).field("columns_present")?;
+        let after_present = match kind {
+            RowsEventKind::Update => read_bitmap(cursor, column_count).field("columns_present")?,
+            _ => before_present.clone(),
+        };
+        Ok(RowsHeader { kind, table_id, flags, column_count, before_present, after_present })
+    }
+}
+
+fn parse_row_image(
+    cursor: &mut Cursor<&[u8]>,
+    table_map: &TableMapEvent,
+    present: &[bool],
+    column_names: &Option<Arc<[String]>>,
+    options: &ParseOptions,
+) -> Result<RowImage, EventParseError> {
+    let present_count = present.iter().filter(|p| **p).count();
+    let null_bitmap = read_bitmap(cursor, present_count).field("null_bitmap")?;
+    let mut nulls = null_bitmap.into_iter();
+
+    let mut values = Vec::with_capacity(present.len());
+    for (column, present) in table_map.columns.iter().zip(present) {
+        if !present {
+            values.push(None);
+            continue;
+        }
+        if nulls.next().unwrap_or(false) {
+            values.push(Some(Value::Null));
+        } else {
+            let value = parse_value(cursor, column, options).map_err(|e| match e {
+                EventParseError::Io(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => EventParseError::UnexpectedEof { field: "row value" },
+                e => e,
+            })?;
+            values.push(Some(value));
+        }
+    }
+
+    Ok(RowImage {
+        values,
+        column_names: column_names.clone(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::charset::Charset;
+    use crate::event::TypeCode;
+    use crate::event::tests::{users_rows, users_table_map as users_table_map_body};
+    use crate::options::ParseOptions;
+    use crate::rows::RowsEvent;
+    use crate::schema::SchemaTracker;
+    use crate::table_map::TableMapEvent;
+    use crate::value::Value;
+
+    fn users_table_map() -> TableMapEvent {
+        TableMapEvent::parse(&users_table_map_body(&[3, 15, 1], &[0x80, 0x00], 0x06, &[])).unwrap()
+    }
+
+    #[test]
+    fn test_parse_update_rows_as_map() {
+        //given
+        let mut tracker = SchemaTracker::new();
+        tracker.apply_query("test", "CREATE TABLE users (id int NOT NULL PRIMARY KEY, name varchar(32), age tinyint unsigned)").unwrap();
+        let mut table_map = users_table_map();
+        assert!(table_map.resolve_with(&tracker));
+
+        let mut data = users_rows(3, &[0x07, 0x07]);
+        data.extend_from_slice(&[0x04, 2, 0, 0, 0, 3]);
+        data.extend_from_slice(b"bob");
+        data.extend_from_slice(&[0x00, 2, 0, 0, 0, 5]);
+        data.extend_from_slice(b"bobby");
+        data.push(200);
+
+        //when
+        let rows_event = RowsEvent::parse(TypeCode::UpdateRowsEventV2, &data, &table_map).unwrap();
+
+        //then
+        assert_eq!(rows_event.rows.len(), 1);
+        let before = rows_event.rows[0].before.as_ref().unwrap().as_map().unwrap();
+        let after = rows_event.rows[0].after.as_ref().unwrap().as_map().unwrap();
+        assert_eq!(before.keys().copied().collect::<Vec<_>>(), vec!["id", "name", "age"]);
+        assert_eq!(before["age"], &Value::Null);
+        assert_eq!(after["name"], &Value::String("bobby".to_owned()));
+        assert_eq!(after["age"], &Value::UInt(200));
+    }
+
+    #[test]
+    fn test_as_map_without_column_names() {
+        //given
+        let table_map = users_table_map();
+        let data = users_rows(3, &[0x07, 0x06, 1, 0, 0, 0]);
+
+        //when
+        let rows_event = RowsEvent::parse(TypeCode::WriteRowsEventV2, &data, &table_map).unwrap();
+
+        //then
+        let after = rows_event.rows[0].after.as_ref().unwrap();
+        assert_eq!(after.get(0), Some(&Value::Int(1)));
+        assert!(after.as_map().is_none());
+    }
+
+    #[test]
+    fn test_parse_rows_with_default_charset() {
+        //given
+        let table_map = users_table_map();
+        let options = ParseOptions::new().default_charset(Charset::Latin1);
+        let data = users_rows(3, &[0x03, 0x00, 1, 0, 0, 0, 4, b'c', b'a', b'f', 0xe9]);
+
+        //when
+        let rows_event = RowsEvent::parse_with_options(TypeCode::WriteRowsEventV2, &data, &table_map, &options).unwrap();
+
+        //then
+        let after = rows_event.rows[0].after.as_ref().unwrap();
+        assert_eq!(after.get(1), Some(&Value::String("café".to_owned())));
+        assert_eq!(after.get(2), None);
+    }
+}
@@ -0,0 +1,428 @@
+use std::collections::BTreeMap;
+use std::io::{Read, Write};
+use serde_derive::{Deserialize, Serialize};
+use sqlparser::ast::{
+    AlterTableOperation, ColumnDef, ColumnOption, CreateTableLikeKind, DataType, Ident, MySQLColumnPosition,
+    ObjectName, ObjectNamePart, ObjectType, RenameTableNameKind, Statement, TableConstraint,
+};
+use sqlparser::dialect::MySqlDialect;
+use sqlparser::parser::Parser;
+use crate::errors::SchemaError;
+use crate::event::{Event, EventData};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ColumnDefinition {
+    pub name: String,
+    pub data_type: String,
+    pub unsigned: bool,
+    pub nullable: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TableDefinition {
+    pub schema: String,
+    pub name: String,
+    pub columns: Vec<ColumnDefinition>,
+    pub primary_key: Vec<String>,
+}
+
+impl TableDefinition {
+    pub fn column_names(&self) -> Vec<&str> {
+        self.columns.iter().map(|c| c.name.as_str()).collect()
+    }
+
+    fn column_index(&self, name: &str) -> Option<usize> {
+        // column names are case-insensitive in mysql
+        self.columns.iter().position(|c| c.name.eq_ignore_ascii_case(name))
+    }
+}
+
+// anything that can answer "what does schema.table look like right now"
+pub trait SchemaProvider {
+    fn table_definition(&self, schema: &str, table: &str) -> Option<&TableDefinition>;
+}
+
+// Evolves table definitions by replaying the DDL found in QueryEvents.
+// https://dev.mysql.com/doc/refman/5.7/en/binary-log-formats.html (DDL is always logged as a statement)
+#[derive(Debug, Clone, Default)]
+pub struct SchemaTracker {
+    tables: BTreeMap<(String, String), TableDefinition>,
+}
+
+// what gets persisted so a restarted process can pick up mid-binlog-series
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SchemaSnapshot {
+    pub version: u32,
+    pub tables: Vec<TableDefinition>,
+}
+
+const SNAPSHOT_VERSION: u32 = 1;
+
+impl SchemaProvider for SchemaTracker {
+    fn table_definition(&self, schema: &str, table: &str) -> Option<&TableDefinition> {
+        self.tables.get(&(schema.to_owned(), table.to_owned()))
+    }
+}
+
+impl SchemaTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn tables(&self) -> impl Iterator<Item = &TableDefinition> {
+        self.tables.values()
+    }
+
+    pub fn add_table(&mut self, table: TableDefinition) {
+        self.tables.insert((table.schema.clone(), table.name.clone()), table);
+    }
+
+    pub fn snapshot(&self) -> SchemaSnapshot {
+        SchemaSnapshot {
+            version: SNAPSHOT_VERSION,
+            tables: self.tables.values().cloned().collect(),
+        }
+    }
+
+    pub fn from_snapshot(snapshot: SchemaSnapshot) -> Result<Self, SchemaError> {
+        if snapshot.version != SNAPSHOT_VERSION {
+            return Err(SchemaError::UnsupportedSnapshotVersion(snapshot.version));
+        }
+
+        let mut tracker = Self::new();
+        for table in snapshot.tables {
+            tracker.add_table(table);
+        }
+        Ok(tracker)
+    }
+
+    pub fn to_json(&self) -> Result<String, SchemaError> {
+        Ok(serde_json::to_string(&self.snapshot())?)
+    }
+
+    pub fn from_json(json: &str) -> Result<Self, SchemaError> {
+        Self::from_snapshot(serde_json::from_str(json)?)
+    }
+
+    pub fn write_snapshot<W: Write>(&self, writer: W) -> Result<(), SchemaError> {
+        Ok(serde_json::to_writer(writer, &self.snapshot())?)
+    }
+
+    pub fn read_snapshot<R: Read>(reader: R) -> Result<Self, SchemaError> {
+        Self::from_snapshot(serde_json::from_reader(reader)?)
+    }
+
+    pub fn process_event(&mut self, event: &Event) -> Result<(), SchemaError> {
+        if let Some(EventData::QueryEvent { schema, query, .. }) =
+            Event::parse_event_data_by_type_code(event.type_code(), event.try_data()?)?
+        {
+            self.apply_query(&schema, &query)?;
+        }
+        Ok(())
+    }
+
+    pub fn apply_query(&mut self, default_schema: &str, query: &str) -> Result<(), SchemaError> {
+        if !is_tracked_ddl(query) {
+            return Ok(());
+        }
+
+        for statement in Parser::parse_sql(&MySqlDialect {}, query)? {
+            self.apply_statement(default_schema, statement)?;
+        }
+        Ok(())
+    }
+
+    fn apply_statement(&mut self, default_schema: &str, statement: Statement) -> Result<(), SchemaError> {
+        match statement {
+            Statement::CreateTable(create) => {
+                let (schema, name) = qualified_name(default_schema, &create.name);
+                if create.if_not_exists && self.tables.contains_key(&(schema.clone(), name.clone())) {
+                    return Ok(());
+                }
+
+                let like = match &create.like {
+                    Some(CreateTableLikeKind::Plain(like)) | Some(CreateTableLikeKind::Parenthesized(like)) => Some(&like.name),
+                    None => None,
+                };
+                let table = match like {
+                    Some(source) => {
+                        let (source_schema, source_name) = qualified_name(default_schema, source);
+                        let mut table = self.existing_table(&source_schema, &source_name)?.clone();
+                        table.schema = schema;
+                        table.name = name;
+                        table
+                    }
+                    None => {
+                        let mut table = TableDefinition {
+                            schema,
+                            name,
+                            columns: create.columns.iter().map(column_definition).collect(),
+                            primary_key: create.columns.iter()
+                                .filter(|c| c.options.iter().any(|o| matches!(o.option, ColumnOption::PrimaryKey(_))))
+                                .map(|c| c.name.value.clone())
+                                .collect(),
+                        };
+                        for constraint in &create.constraints {
+                            if let TableConstraint::PrimaryKey(pk) = constraint {
+                                table.primary_key = pk.columns.iter().map(|c| c.column.expr.to_string().trim_matches('`').to_owned()).collect();
+                            }
+                        }
+                        table
+                    }
+                };
+                self.add_table(table);
+            }
+            Statement::AlterTable(alter) => {
+                let (schema, name) = qualified_name(default_schema, &alter.name);
+                let mut table = match self.tables.remove(&(schema.clone(), name.clone())) {
+                    Some(table) => table,
+                    None if alter.if_exists => return Ok(()),
+                    None => return Err(SchemaError::UnknownTable(schema, name)),
+                };
+                let result = alter.operations.into_iter()
+                    .try_for_each(|operation| apply_alter_operation(&mut table, default_schema, operation));
+                // re-insert before surfacing errors so a bad operation doesn't lose the table
+                self.add_table(table);
+                result?;
+            }
+            Statement::Drop { object_type: ObjectType::Table, names, .. } => {
+                for name in &names {
+                    self.tables.remove(&qualified_name(default_schema, name));
+                }
+            }
+            Statement::Drop { object_type: ObjectType::Schema, names, .. } |
+            Statement::Drop { object_type: ObjectType::Database, names, .. } => {
+                for name in &names {
+                    let schema = last_ident(name);
+                    self.tables.retain(|(s, _), _| *s != schema);
+                }
+            }
+            Statement::RenameTable(renames) => {
+                // renames inside one statement are applied left to right, which allows swaps
+                for rename in renames {
+                    let from = qualified_name(default_schema, &rename.old_name);
+                    let to = qualified_name(default_schema, &rename.new_name);
+                    let mut table = self.tables.remove(&from)
+                        .ok_or_else(|| SchemaError::UnknownTable(from.0.clone(), from.1.clone()))?;
+                    table.schema = to.0;
+                    table.name = to.1;
+                    self.add_table(table);
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn existing_table(&self, schema: &str, name: &str) -> Result<&TableDefinition, SchemaError> {
+        self.table_definition(schema, name)
+            .ok_or_else(|| SchemaError::UnknownTable(schema.to_owned(), name.to_owned()))
+    }
+}
+
+fn apply_alter_operation(table: &mut TableDefinition, default_schema: &str, operation: AlterTableOperation) -> Result<(), SchemaError> {
+    match operation {
+        AlterTableOperation::AddColumn { column_def, column_position, .. } => {
+            let column = column_definition(&column_def);
+            insert_column(table, column, column_position.as_ref())?;
+        }
+        AlterTableOperation::DropColumn { column_names, if_exists, .. } => {
+            for column_name in column_names {
+                match table.column_index(&column_name.value) {
+                    Some(index) => { table.columns.remove(index); }
+                    None if if_exists => {}
+                    None => return Err(unknown_column(table, &column_name)),
+                }
+                table.primary_key.retain(|c| !c.eq_ignore_ascii_case(&column_name.value));
+            }
+        }
+        AlterTableOperation::RenameColumn { old_column_name, new_column_name } => {
+            let index = table.column_index(&old_column_name.value)
+                .ok_or_else(|| unknown_column(table, &old_column_name))?;
+            table.columns[index].name = new_column_name.value.clone();
+            rename_primary_key_column(table, &old_column_name.value, &new_column_name.value);
+        }
+        AlterTableOperation::ChangeColumn { old_name, new_name, data_type, options, column_position } => {
+            let index = table.column_index(&old_name.value)
+                .ok_or_else(|| unknown_column(table, &old_name))?;
+            let column = build_column_definition(&new_name, &data_type, &options);
+            replace_column(table, index, column, column_position.as_ref())?;
+            rename_primary_key_column(table, &old_name.value, &new_name.value);
+        }
+        AlterTableOperation::ModifyColumn { col_name, data_type, options, column_position } => {
+            let index = table.column_index(&col_name.value)
+                .ok_or_else(|| unknown_column(table, &col_name))?;
+            let column = build_column_definition(&col_name, &data_type, &options);
+            replace_column(table, index, column, column_position.as_ref())?;
+        }
+        AlterTableOperation::RenameTable { table_name } => {
+            let new_name = match &table_name {
+                RenameTableNameKind::As(name) | RenameTableNameKind::To(name) => name,
+            };
+            let (schema, name) = qualified_name(default_schema, new_name);
+            table.schema = schema;
+            table.name = name;
+        }
+        AlterTableOperation::AddConstraint { constraint: TableConstraint::PrimaryKey(pk), .. } => {
+            table.primary_key = pk.columns.iter().map(|c| c.column.expr.to_string().trim_matches('`').to_owned()).collect();
+        }
+        AlterTableOperation::DropPrimaryKey { .. } => {
+            table.primary_key.clear();
+        }
+        // index, constraint, partition and option changes don't affect the row layout
+        _ => {}
+    }
+    Ok(())
+}
+
+fn insert_column(table: &mut TableDefinition, column: ColumnDefinition, position: Option<&MySQLColumnPosition>) -> Result<(), SchemaError> {
+    match position {
+        None => table.columns.push(column),
+        Some(MySQLColumnPosition::First) => table.columns.insert(0, column),
+        Some(MySQLColumnPosition::After(after)) => {
+            let index = table.column_index(&after.value)
+                .ok_or_else(|| unknown_column(table, after))?;
+            table.columns.insert(index + 1, column);
+        }
+    }
+    Ok(())
+}
+
+fn replace_column(table: &mut TableDefinition, index: usize, column: ColumnDefinition, position: Option<&MySQLColumnPosition>) -> Result<(), SchemaError> {
+    match position {
+        None => {
+            table.columns[index] = column;
+            Ok(())
+        }
+        Some(_) => {
+            table.columns.remove(index);
+            insert_column(table, column, position)
+        }
+    }
+}
+
+fn rename_primary_key_column(table: &mut TableDefinition, old_name: &str, new_name: &str) {
+    for column in table.primary_key.iter_mut() {
+        if column.eq_ignore_ascii_case(old_name) {
+            *column = new_name.to_owned();
+        }
+    }
+}
+
+fn unknown_column(table: &TableDefinition, column: &Ident) -> SchemaError {
+    SchemaError::UnknownColumn(column.value.clone(), table.schema.clone(), table.name.clone())
+}
+
+fn column_definition(column: &ColumnDef) -> ColumnDefinition {
+    let options: Vec<ColumnOption> = column.options.iter().map(|o| o.option.clone()).collect();
+    build_column_definition(&column.name, &column.data_type, &options)
+}
+
+fn build_column_definition(name: &Ident, data_type: &DataType, options: &[ColumnOption]) -> ColumnDefinition {
+    let data_type = data_type.to_string();
+    let not_null = options.iter().any(|o| matches!(o, ColumnOption::NotNull | ColumnOption::PrimaryKey(_)));
+    ColumnDefinition {
+        name: name.value.clone(),
+        unsigned: data_type.to_uppercase().contains("UNSIGNED"),
+        data_type,
+        nullable: !not_null,
+    }
+}
+
+fn last_ident(name: &ObjectName) -> String {
+    match name.0.last() {
+        Some(ObjectNamePart::Identifier(ident)) => ident.value.clone(),
+        Some(part) => part.to_string(),
+        None => String::new(),
+    }
+}
+
+fn qualified_name(default_schema: &str, name: &ObjectName) -> (String, String) {
+    let table = last_ident(name);
+    let schema = match name.0.len() {
+        n if n >= 2 => match &name.0[n - 2] {
+            ObjectNamePart::Identifier(ident) => ident.value.clone(),
+            part => part.to_string(),
+        },
+        _ => default_schema.to_owned(),
+    };
+    (schema, table)
+}
+
+// only hand the statements that can change a row layout to sqlparser; the binlog also carries
+// BEGIN, GRANT, CREATE PROCEDURE, ... which we neither need nor can always parse
+fn is_tracked_ddl(query: &str) -> bool {
+    let mut rest = query.trim_start();
+    while let Some(comment) = rest.strip_prefix("/*") {
+        rest = match comment.find("*/") {
+            Some(end) => comment[end + 2..].trim_start(),
+            None => return false,
+        };
+    }
+
+    let words: Vec<String> = rest.split_whitespace().take(3).map(|w| w.to_ascii_uppercase()).collect();
+    let words: Vec<&str> = words.iter().map(|w| w.as_str()).collect();
+    matches!(
+        words.as_slice(),
+        ["CREATE", "TABLE", ..] | ["ALTER", "TABLE", ..] | ["DROP", "TABLE", ..] | ["RENAME", "TABLE", ..]
+            | ["DROP", "DATABASE", ..] | ["DROP", "SCHEMA", ..]
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::schema::{SchemaTracker, SchemaProvider};
+
+    #[test]
+    fn test_track_create_alter_drop_table() {
+        //given
+        let mut tracker = SchemaTracker::new();
+
+        //when
+        tracker.apply_query("test", "CREATE TABLE `users` (`id` int NOT NULL, `name` varchar(32) DEFAULT NULL, PRIMARY KEY (`id`)) ENGINE=InnoDB").unwrap();
+        tracker.apply_query("test", "ALTER TABLE users ADD COLUMN age tinyint unsigned AFTER id, CHANGE COLUMN name full_name varchar(64)").unwrap();
+        tracker.apply_query("test", "BEGIN").unwrap();
+        tracker.apply_query("other", "CREATE TABLE test.orders (id bigint)").unwrap();
+        tracker.apply_query("test", "DROP TABLE `orders` /* generated by server */").unwrap();
+
+        //then
+        let users = tracker.table_definition("test", "users").unwrap();
+        assert_eq!(users.column_names(), vec!["id", "age", "full_name"]);
+        assert_eq!(users.primary_key, vec!["id"]);
+        assert!(!users.columns[0].nullable);
+        assert!(users.columns[1].unsigned);
+        assert!(tracker.table_definition("test", "orders").is_none());
+    }
+
+    #[test]
+    fn test_track_rename_table() {
+        //given
+        let mut tracker = SchemaTracker::new();
+        tracker.apply_query("test", "CREATE TABLE a (x int)").unwrap();
+        tracker.apply_query("test", "CREATE TABLE b LIKE a").unwrap();
+
+        //when
+        tracker.apply_query("test", "RENAME TABLE a TO tmp, b TO a, tmp TO b").unwrap();
+        tracker.apply_query("test", "ALTER TABLE b RENAME TO c").unwrap();
+
+        //then
+        assert!(tracker.table_definition("test", "a").is_some());
+        assert!(tracker.table_definition("test", "b").is_none());
+        assert_eq!(tracker.table_definition("test", "c").unwrap().column_names(), vec!["x"]);
+    }
+
+    #[test]
+    fn test_schema_snapshot_round_trip() {
+        //given
+        let mut tracker = SchemaTracker::new();
+        tracker.apply_query("test", "CREATE TABLE users (id int unsigned NOT NULL PRIMARY KEY, name text)").unwrap();
+
+        //when
+        let json = tracker.to_json().unwrap();
+        let restored = SchemaTracker::from_json(&json).unwrap();
+
+        //then
+        assert_eq!(restored.snapshot(), tracker.snapshot());
+        assert_eq!(restored.table_definition("test", "users").unwrap().primary_key, vec!["id"]);
+    }
+}
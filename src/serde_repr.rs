@@ -0,0 +1,132 @@
+use std::fmt;
+use serde::de::{self, Deserializer, SeqAccess, Visitor};
+use serde::ser::Serializer;
+use serde_derive::{Deserialize, Serialize};
+use crate::event::{SidIntervals, UserVarValue};
+use crate::gtid::{format_uuid, parse_uuid, GtidSet};
+
+// Raw bytes as a lowercase hex string in human readable formats such as JSON, and as bytes in
+// binary ones, for #[serde(with = "crate::serde_repr::hex_bytes")].
+pub(crate) mod hex_bytes {
+    use super::*;
+
+    pub(crate) fn serialize<S: Serializer>(bytes: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+        if serializer.is_human_readable() {
+            serializer.serialize_str(&to_hex(bytes))
+        } else {
+            serializer.serialize_bytes(bytes)
+        }
+    }
+
+    pub(crate) fn deserialize<'de, D: Deserializer<'de>, T: From<Vec<u8>>>(deserializer: D) -> Result<T, D::Error> {
+        let bytes = if deserializer.is_human_readable() {
+            deserializer.deserialize_str(BytesVisitor)?
+        } else {
+            deserializer.deserialize_byte_buf(BytesVisitor)?
+        };
+        Ok(bytes.into())
+    }
+}
+
+// a server uuid as 3e11fa47-71ca-11e1-9e33-c80aa9429562
+pub(crate) mod uuid {
+    use super::*;
+
+    pub(crate) fn serialize<S: Serializer>(sid: &[u8; 16], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&format_uuid(sid))
+    }
+
+    pub(crate) fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<[u8; 16], D::Error> {
+        let s: String = serde::Deserialize::deserialize(deserializer)?;
+        parse_uuid(&s).map_err(de::Error::custom)
+    }
+}
+
+// PreviousGtidsLogEvent's set, a list of {"sid": uuid, "intervals": [[start, end], ..]}
+pub(crate) mod gtid_set {
+    use super::*;
+
+    #[derive(Serialize, Deserialize)]
+    struct Repr {
+        #[serde(with = "super::uuid")]
+        sid: [u8; 16],
+        intervals: Vec<(i64, i64)>,
+    }
+
+    pub(crate) fn serialize<S: Serializer>(gtids: &GtidSet, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(gtids.sids().map(|sid| Repr { sid: *sid, intervals: gtids.intervals(sid).to_vec() }))
+    }
+
+    pub(crate) fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<GtidSet, D::Error> {
+        let gtids: Vec<Repr> = serde::Deserialize::deserialize(deserializer)?;
+        let sids: Vec<SidIntervals> = gtids.into_iter().map(|r| (r.sid, r.intervals)).collect();
+        Ok(GtidSet::from_sid_intervals(&sids))
+    }
+}
+
+// UserVarEvent's value, {"value_type": .., "charset": .., "bytes": hex} or null for NULL
+pub(crate) mod user_var_value {
+    use super::*;
+
+    #[derive(Serialize, Deserialize)]
+    struct Repr {
+        value_type: u8,
+        charset: u32,
+        #[serde(with = "super::hex_bytes")]
+        bytes: Vec<u8>,
+    }
+
+    pub(crate) fn serialize<S: Serializer>(value: &Option<UserVarValue>, serializer: S) -> Result<S::Ok, S::Error> {
+        let repr = value.as_ref().map(|(value_type, charset, bytes)| Repr {
+            value_type: *value_type,
+            charset: *charset,
+            bytes: bytes.clone(),
+        });
+        serde::Serialize::serialize(&repr, serializer)
+    }
+
+    pub(crate) fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<UserVarValue>, D::Error> {
+        let repr: Option<Repr> = serde::Deserialize::deserialize(deserializer)?;
+        Ok(repr.map(|r| (r.value_type, r.charset, r.bytes)))
+    }
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+struct BytesVisitor;
+
+impl<'de> Visitor<'de> for BytesVisitor {
+    type Value = Vec<u8>;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a hex string or bytes")
+    }
+
+    fn visit_str<E: de::Error>(self, v: &str) -> Result<Self::Value, E> {
+        if !v.len().is_multiple_of(2) || !v.bytes().all(|b| b.is_ascii_hexdigit()) {
+            return Err(E::invalid_value(de::Unexpected::Str(v), &self));
+        }
+        (0..v.len()).step_by(2)
+            .map(|i| u8::from_str_radix(&v[i..i + 2], 16).map_err(E::custom))
+            .collect()
+    }
+
+    fn visit_bytes<E: de::Error>(self, v: &[u8]) -> Result<Self::Value, E> {
+        Ok(v.to_vec())
+    }
+
+    fn visit_byte_buf<E: de::Error>(self, v: Vec<u8>) -> Result<Self::Value, E> {
+        Ok(v)
+    }
+
+    // formats without a bytes type hand them over as a sequence
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        let mut bytes = Vec::with_capacity(seq.size_hint().unwrap_or(0));
+        while let Some(b) = seq.next_element()? {
+            bytes.push(b);
+        }
+        Ok(bytes)
+    }
+}
@@ -0,0 +1,345 @@
+use std::collections::BTreeMap;
+use std::io::Write;
+use crate::binlog_writer::BinlogWriter;
+use crate::context::ParseContext;
+use crate::errors::{BinlogFileError, SplitError};
+use crate::event::{Event, EventData, EventHeader, TypeCode};
+use crate::gtid::GtidSet;
+use crate::position::BinlogPosition;
+use crate::transaction::{TransactionItem, Transactions};
+
+// Cuts one binlog file into pieces that are binlogs of their own: each starts with the input's
+// FormatDescriptionEvent and, when the input had one, a Previous_gtids of everything before it,
+// and ends with a Rotate to the next piece. Transactions are never cut, so one bigger than
+// max_size gets a piece to itself. Positions are counted afresh in each piece and returned as a
+// PositionMap from the input's.
+//
+//     let splitter = Splitter::new().max_size(64 << 20).max_duration(3600);
+//     let summary = splitter.split("mysql-bin.000042", BinlogFile::from_path(input)?.events(), |n| {
+//         let name = format!("mysql-bin.000042.{:06}", n);
+//         Ok((name.clone(), BufWriter::new(File::create(dir.join(name))?)))
+//     })?;
+#[derive(Debug, Clone, Default)]
+pub struct Splitter {
+    max_size: Option<u64>,
+    max_transactions: Option<usize>,
+    max_duration: Option<u32>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SplitSummary {
+    pub pieces: Vec<SplitPiece>,
+    pub positions: PositionMap,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SplitPiece {
+    pub name: String,
+    pub transactions: usize,
+    pub size: u64,
+}
+
+// Where the events of a split or merge went: the position each input event had, and the one it
+// has in the output. Events that weren't copied, like the FormatDescriptionEvents and Rotates
+// between merged files, map to where the event after them went, and the end of each input file
+// to the end of what it was copied to, so a checkpoint taken against the input can be resumed
+// from in the output.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PositionMap {
+    positions: BTreeMap<(String, u64), BinlogPosition>,
+}
+
+impl PositionMap {
+    // the new position of the event at `pos` in `file`
+    pub fn get(&self, file: &str, pos: u64) -> Option<&BinlogPosition> {
+        self.positions.get(&(file.to_owned(), pos))
+    }
+
+    // a checkpoint moved to the output, keeping its GTID set
+    pub fn translate(&self, position: &BinlogPosition) -> Option<BinlogPosition> {
+        let mut translated = self.get(&position.file, position.pos)?.clone();
+        translated.gtid_set = position.gtid_set.clone();
+        Some(translated)
+    }
+
+    // (file, pos, new position), by input file name and position
+    pub fn iter(&self) -> impl Iterator<Item = (&str, u64, &BinlogPosition)> {
+        self.positions.iter().map(|((file, pos), new)| (file.as_str(), *pos, new))
+    }
+
+    pub fn len(&self) -> usize {
+        self.positions.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.positions.is_empty()
+    }
+
+    pub(crate) fn insert(&mut self, file: &str, pos: u64, new: BinlogPosition) {
+        self.positions.insert((file.to_owned(), pos), new);
+    }
+}
+
+impl Splitter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // start a new piece before a transaction would take the current one past this many bytes
+    pub fn max_size(mut self, bytes: u64) -> Self {
+        self.max_size = Some(bytes);
+        self
+    }
+
+    // start a new piece once the current one has this many transactions
+    pub fn max_transactions(mut self, count: usize) -> Self {
+        self.max_transactions = Some(count);
+        self
+    }
+
+    // start a new piece with the first transaction this many seconds after the current one's first
+    pub fn max_duration(mut self, seconds: u32) -> Self {
+        self.max_duration = Some(seconds);
+        self
+    }
+
+    // Splits `events`, those of the binlog file named `input` from its FormatDescriptionEvent on.
+    // `create` is called with 1, 2, ... for each piece and returns its file name, which the
+    // Rotates and the position map use, and where to write it.
+    pub fn split<It, W, F>(&self, input: &str, events: It, create: F) -> Result<SplitSummary, SplitError> where
+        It: Iterator<Item = Result<Event, BinlogFileError>>,
+        W: Write,
+        F: FnMut(usize) -> std::io::Result<(String, W)>
+    {
+        let mut split = Split {
+            splitter: self,
+            input,
+            create,
+            ctx: ParseContext::new(),
+            format_description: None,
+            previous_gtids: None,
+            executed: GtidSet::new(),
+            piece: None,
+            end: 0,
+            summary: SplitSummary::default(),
+        };
+        for item in Transactions::new(events) {
+            match item? {
+                TransactionItem::Transaction(transaction) => {
+                    let events = transaction.events();
+                    let size = events.iter().map(|e| u64::from(e.event_length())).sum();
+                    let timestamp = events.first().map_or(0, Event::timestamp);
+                    if split.is_full(size, timestamp) {
+                        split.close_piece()?;
+                    }
+                    for event in events {
+                        split.write(event)?;
+                    }
+                    if let Some(piece) = &mut split.piece {
+                        piece.transactions += 1;
+                        piece.first_timestamp.get_or_insert(timestamp);
+                    }
+                }
+                TransactionItem::Event(event) => split.write_standalone(event)?,
+            }
+        }
+        if let Some(piece) = split.piece.take() {
+            split.summary.positions.insert(input, split.end, BinlogPosition::new(&piece.name, piece.writer.offset()));
+            split.summary.pieces.push(piece.finish()?);
+        }
+        Ok(split.summary)
+    }
+}
+
+struct Split<'a, W: Write, F> {
+    splitter: &'a Splitter,
+    input: &'a str,
+    create: F,
+    ctx: ParseContext,
+    format_description: Option<Event>,
+    // the input's own, as the header of the ones written to later pieces
+    previous_gtids: Option<Event>,
+    // GTIDs up to the piece being written
+    executed: GtidSet,
+    piece: Option<Piece<W>>,
+    // where the last input event ended
+    end: u64,
+    summary: SplitSummary,
+}
+
+struct Piece<W: Write> {
+    name: String,
+    writer: BinlogWriter<W>,
+    transactions: usize,
+    first_timestamp: Option<u32>,
+    last_timestamp: u32,
+}
+
+impl<W, F> Split<'_, W, F> where
+    W: Write,
+    F: FnMut(usize) -> std::io::Result<(String, W)>
+{
+    // whether a transaction of `size` bytes starting at `timestamp` goes to a new piece
+    fn is_full(&self, size: u64, timestamp: u32) -> bool {
+        let splitter = self.splitter;
+        match &self.piece {
+            Some(piece) if piece.transactions > 0 => {
+                splitter.max_size.is_some_and(|max| piece.writer.offset() + size > max)
+                    || splitter.max_transactions.is_some_and(|max| piece.transactions >= max)
+                    || splitter.max_duration.zip(piece.first_timestamp)
+                        .is_some_and(|(max, first)| timestamp.saturating_sub(first) >= max)
+            }
+            _ => false,
+        }
+    }
+
+    fn open_piece(&mut self, name: String, writer: W) -> Result<&mut Piece<W>, SplitError> {
+        let format_description = self.format_description.as_ref().ok_or(SplitError::NoFormatDescription)?;
+        let writer = BinlogWriter::new(writer, format_description)?;
+        let mut piece = Piece { name, writer, transactions: 0, first_timestamp: None, last_timestamp: format_description.timestamp() };
+        match self.summary.pieces.is_empty() {
+            // the FormatDescriptionEvent isn't copied, it's the one every piece starts with
+            true => self.summary.positions.insert(self.input, format_description.offset(), BinlogPosition::new(&piece.name, 4)),
+            // the first piece has the input's own
+            false => if let Some(previous_gtids) = &self.previous_gtids {
+                piece.writer.write(previous_gtids.header(), &self.executed.encode())?;
+            },
+        }
+        Ok(self.piece.insert(piece))
+    }
+
+    // ends the piece being written with a Rotate to the next one, whose events then follow
+    fn close_piece(&mut self) -> Result<(), SplitError> {
+        let mut piece = match self.piece.take() {
+            Some(piece) => piece,
+            None => return Ok(()),
+        };
+        let (name, writer) = (self.create)(self.summary.pieces.len() + 2)?;
+        let header = EventHeader {
+            timestamp: piece.last_timestamp,
+            type_code: TypeCode::RotateEvent,
+            server_id: self.format_description.as_ref().map_or(0, Event::server_id),
+            event_length: 0,
+            next_position: 0,
+            flags: 0,
+        };
+        let mut body = 4u64.to_le_bytes().to_vec();
+        body.extend_from_slice(name.as_bytes());
+        piece.writer.write(header, &body)?;
+        self.summary.pieces.push(piece.finish()?);
+        self.open_piece(name, writer)?;
+        Ok(())
+    }
+
+    fn write(&mut self, event: &Event) -> Result<(), SplitError> {
+        if event.type_code() == TypeCode::GtidLogEvent {
+            if let EventData::GtidLogEvent(gtid) = event.parsed(&mut self.ctx)? {
+                self.executed.add(gtid.sid, gtid.gno);
+            }
+        }
+        if self.piece.is_none() {
+            let (name, writer) = (self.create)(1)?;
+            self.open_piece(name, writer)?;
+        }
+        self.end = event.offset() + u64::from(event.event_length());
+        let piece = match &mut self.piece {
+            Some(piece) => piece,
+            None => return Ok(()),
+        };
+        self.summary.positions.insert(self.input, event.offset(), BinlogPosition::new(&piece.name, piece.writer.offset()));
+        piece.last_timestamp = event.timestamp();
+        piece.writer.write(event.header(), event.try_data()?)?;
+        Ok(())
+    }
+
+    // Events outside transactions go to the piece being written, so the first piece keeps the
+    // input's Previous_gtids and the last its closing Rotate. A FormatDescriptionEvent opens
+    // the pieces after it rather than being copied.
+    fn write_standalone(&mut self, event: Event) -> Result<(), SplitError> {
+        match event.type_code() {
+            TypeCode::FormatDescriptionEvent => {
+                event.parsed(&mut self.ctx)?;
+                self.end = event.offset() + u64::from(event.event_length());
+                self.format_description = Some(event);
+                Ok(())
+            }
+            TypeCode::PreviousGtidsLogEvent => {
+                if let EventData::PreviousGtidsLogEvent { gtids } = event.parsed(&mut self.ctx)? {
+                    self.executed = gtids;
+                }
+                self.write(&event)?;
+                self.previous_gtids = Some(event);
+                Ok(())
+            }
+            _ => self.write(&event),
+        }
+    }
+}
+
+impl<W: Write> Piece<W> {
+    fn finish(self) -> Result<SplitPiece, SplitError> {
+        let size = self.writer.offset();
+        self.writer.finish()?;
+        Ok(SplitPiece { name: self.name, transactions: self.transactions, size })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+    use crate::binlog_file::BinlogFile;
+    use crate::binlog_writer::BinlogWriter;
+    use crate::builder::{GtidEventBuilder, QueryEventBuilder};
+    use crate::position::BinlogPosition;
+    use crate::split::Splitter;
+
+    #[test]
+    fn test_split_by_duration() {
+        //given
+        let mut fixture = BinlogFile::from_path("tests/asset/mysql-bin.100746").unwrap();
+        let format_description = fixture.events().next().unwrap().unwrap();
+        let mut writer = BinlogWriter::new(vec![], &format_description).unwrap();
+        for seconds in [0, 10, 7200] {
+            let timestamp = format_description.timestamp() + seconds;
+            writer.write_event(&GtidEventBuilder::anonymous().timestamp(timestamp).build().unwrap()).unwrap();
+            writer.write_event(&QueryEventBuilder::new("DROP TABLE t").timestamp(timestamp).build().unwrap()).unwrap();
+        }
+        let bytes = writer.finish().unwrap();
+        let input_size = bytes.len() as u64;
+        let mut input = BinlogFile::from_reader(Cursor::new(bytes)).unwrap();
+        let splitter = Splitter::new().max_duration(3600);
+
+        //when
+        let summary = splitter.split("input.000001", input.events(), |n| Ok((format!("piece.{:06}", n), vec![]))).unwrap();
+
+        //then
+        let pieces: Vec<(&str, usize)> = summary.pieces.iter().map(|p| (p.name.as_str(), p.transactions)).collect();
+        assert_eq!(pieces, [("piece.000001", 2), ("piece.000002", 1)]);
+        let (_, end, last) = summary.positions.iter().last().unwrap();
+        assert_eq!(end, input_size);
+        assert_eq!(last, &BinlogPosition::new("piece.000002", summary.pieces[1].size));
+    }
+
+    #[test]
+    fn test_split_maps_positions() {
+        //given
+        let path = "tests/asset/mysql-bin.100746";
+        let splitter = Splitter::new().max_transactions(2);
+
+        //when
+        let summary = splitter.split("mysql-bin.100746", BinlogFile::from_path(path).unwrap().events(), |n| {
+            Ok((format!("piece.{:06}", n), vec![]))
+        }).unwrap();
+
+        //then
+        assert_eq!(summary.pieces.len(), 2);
+        assert_eq!(summary.positions.len(), 15 + 1);
+        assert_eq!(summary.positions.get("mysql-bin.100746", 154), Some(&BinlogPosition::new("piece.000001", 154)));
+        // the pieces after the first have an FDE and an empty Previous_gtids before the events
+        let second = summary.positions.get("mysql-bin.100746", 740).unwrap();
+        assert_eq!(second.file, "piece.000002");
+        assert_eq!(second.pos, 4 + 119 + 31);
+        let translated = summary.positions.translate(&BinlogPosition::new("mysql-bin.100746", 1017)).unwrap();
+        assert_eq!(translated.file, "piece.000002");
+    }
+}
@@ -0,0 +1,397 @@
+use std::collections::HashMap;
+use crate::errors::BinlogFileError;
+use crate::event::{Event, EventData};
+use crate::handler::EventHandler;
+use crate::export::plain_json;
+use crate::json_binary;
+use crate::rows::{RowImage, RowsEvent, RowsEventKind};
+use crate::table_map::{TableMapColumn, TableMapEvent};
+use crate::value::Value;
+
+// Which columns the WHERE clause of UPDATE and DELETE matches the row on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum KeyColumns {
+    // the primary key when the table map has it (binlog_row_metadata=FULL) and the before image
+    // holds it, else every column of the before image
+    #[default]
+    PrimaryKey,
+    // every column of the before image, which also catches rows changed since
+    AllColumns,
+}
+
+// Executable INSERT, UPDATE and DELETE statements for the changes in rows events, as binlog2sql
+// makes them:
+//
+//     INSERT INTO `shop`.`orders` (`id`, `note`) VALUES (7, 'it\'s');
+//     UPDATE `shop`.`orders` SET `id`=7, `note`='new' WHERE `id`=7;
+//     DELETE FROM `shop`.`orders` WHERE `id`=7;
+//
+// A WHERE clause over the whole before image ends in LIMIT 1, as it may match duplicate rows.
+// Column names come from the table map, so the server must log them (binlog_row_metadata=FULL)
+// or the table maps be resolved against a SchemaProvider; otherwise columns are named `@1`,
+// `@2`... and the statements only show what changed. TIMESTAMP values are written with
+// FROM_UNIXTIME, so they depend on the session time zone as the original change did.
+#[derive(Debug, Default)]
+pub struct SqlStatements {
+    tables: HashMap<u64, TableMapEvent>,
+    key_columns: KeyColumns,
+}
+
+impl SqlStatements {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn key_columns(mut self, key_columns: KeyColumns) -> Self {
+        self.key_columns = key_columns;
+        self
+    }
+
+    // the statements for a rows event, one per row, and none for other events
+    pub fn statements(&mut self, _event: &Event, data: &EventData) -> Vec<String> {
+        match data {
+            EventData::TableMapEvent(table_map) => {
+                self.tables.insert(table_map.table_id, table_map.clone());
+                vec![]
+            }
+            EventData::WriteRowsEvent(rows) | EventData::UpdateRowsEvent(rows) | EventData::DeleteRowsEvent(rows) => {
+                match self.tables.get(&rows.table_id) {
+                    Some(table_map) => self.rows_statements(table_map, rows),
+                    None => vec![],
+                }
+            }
+            _ => vec![],
+        }
+    }
+
+    pub fn rows_statements(&self, table_map: &TableMapEvent, rows: &RowsEvent) -> Vec<String> {
+        rows.rows.iter()
+            .filter_map(|change| match (rows.kind, &change.before, &change.after) {
+                (RowsEventKind::Write, _, Some(after)) => Some(self.insert(table_map, after)),
+                (RowsEventKind::Update, Some(before), Some(after)) => Some(self.update(table_map, before, after)),
+                (RowsEventKind::Delete, Some(before), _) => Some(self.delete(table_map, before)),
+                _ => None,
+            })
+            .collect()
+    }
+
+    // the statements undoing a rows event: DELETE for inserted rows, INSERT for deleted ones, and
+    // UPDATE back to the before image, in reverse row order. Needs full images (binlog_row_image=FULL).
+    pub fn flashback_statements(&self, table_map: &TableMapEvent, rows: &RowsEvent) -> Vec<String> {
+        rows.rows.iter()
+            .rev()
+            .filter_map(|change| match (rows.kind, &change.before, &change.after) {
+                (RowsEventKind::Write, _, Some(after)) => Some(self.delete(table_map, after)),
+                (RowsEventKind::Update, Some(before), Some(after)) => Some(self.update(table_map, after, before)),
+                (RowsEventKind::Delete, Some(before), _) => Some(self.insert(table_map, before)),
+                _ => None,
+            })
+            .collect()
+    }
+
+    pub fn insert(&self, table_map: &TableMapEvent, after: &RowImage) -> String {
+        let present = present_columns(after);
+        let names: Vec<String> = present.iter().map(|&i| quote_identifier(&column_name(table_map, i))).collect();
+        let values: Vec<String> = present.iter().map(|&i| column_literal(table_map, after, i)).collect();
+        format!("INSERT INTO {} ({}) VALUES ({});", table_name(table_map), names.join(", "), values.join(", "))
+    }
+
+    pub fn update(&self, table_map: &TableMapEvent, before: &RowImage, after: &RowImage) -> String {
+        let set: Vec<String> = present_columns(after).into_iter()
+            .map(|i| format!("{}={}", quote_identifier(&column_name(table_map, i)), column_literal(table_map, after, i)))
+            .collect();
+        format!("UPDATE {} SET {} {};", table_name(table_map), set.join(", "), self.where_clause(table_map, before))
+    }
+
+    pub fn delete(&self, table_map: &TableMapEvent, before: &RowImage) -> String {
+        format!("DELETE FROM {} {};", table_name(table_map), self.where_clause(table_map, before))
+    }
+
+    fn where_clause(&self, table_map: &TableMapEvent, before: &RowImage) -> String {
+        let by_key = self.key_columns == KeyColumns::PrimaryKey
+            && !table_map.primary_key.is_empty()
+            && table_map.primary_key.iter().all(|&i| before.values().get(i).is_some_and(|v| v.is_some()));
+        let columns = if by_key { table_map.primary_key.clone() } else { present_columns(before) };
+        let conditions: Vec<String> = columns.iter()
+            .map(|&i| {
+                let name = quote_identifier(&column_name(table_map, i));
+                match before.get(i) {
+                    Some(Value::Null) | None => format!("{} IS NULL", name),
+                    Some(_) => format!("{}={}", name, column_literal(table_map, before, i)),
+                }
+            })
+            .collect();
+        match by_key {
+            true => format!("WHERE {}", conditions.join(" AND ")),
+            false => format!("WHERE {} LIMIT 1", conditions.join(" AND ")),
+        }
+    }
+}
+
+// Collects the statements rolling back the row changes read, by transaction (XID or COMMIT), to
+// be given newest first once the stretch of binlog to undo has been read:
+//
+//     let mut flashback = Flashback::new();
+//     for event in BinlogFile::from_path(path)?.with_filter(filter).events() {
+//         let event = event?;
+//         flashback.write_event(&event, &event.parsed(&mut ctx)?);
+//     }
+//     std::fs::write("rollback.sql", flashback.script())?;
+#[derive(Debug, Default)]
+pub struct Flashback {
+    sql: SqlStatements,
+    committed: Vec<Vec<String>>,
+    current: Vec<String>,
+}
+
+impl Flashback {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn key_columns(mut self, key_columns: KeyColumns) -> Self {
+        self.sql = self.sql.key_columns(key_columns);
+        self
+    }
+
+    pub fn write_event(&mut self, _event: &Event, data: &EventData) {
+        match data {
+            EventData::TableMapEvent(table_map) => {
+                self.sql.tables.insert(table_map.table_id, table_map.clone());
+            }
+            EventData::WriteRowsEvent(rows) | EventData::UpdateRowsEvent(rows) | EventData::DeleteRowsEvent(rows) => {
+                if let Some(table_map) = self.sql.tables.get(&rows.table_id) {
+                    self.current.extend(self.sql.flashback_statements(table_map, rows).into_iter().rev());
+                }
+            }
+            EventData::XidEvent { .. } => self.commit(),
+            EventData::QueryEvent { query, .. } if query.eq_ignore_ascii_case("COMMIT") => self.commit(),
+            _ => {}
+        }
+    }
+
+    // the undoing transactions, the last one read first, each with its statements in reverse
+    // order; changes after the last commit read count as a transaction of their own
+    pub fn finish(mut self) -> Vec<Vec<String>> {
+        self.commit();
+        self.committed.reverse();
+        self.committed
+    }
+
+    // finish() as a script, each transaction between BEGIN and COMMIT
+    pub fn script(self) -> String {
+        let mut script = String::new();
+        for transaction in self.finish() {
+            script.push_str("BEGIN;\n");
+            for statement in transaction {
+                script.push_str(&statement);
+                script.push('\n');
+            }
+            script.push_str("COMMIT;\n");
+        }
+        script
+    }
+
+    fn commit(&mut self) {
+        if !self.current.is_empty() {
+            let mut statements = std::mem::take(&mut self.current);
+            statements.reverse();
+            self.committed.push(statements);
+        }
+    }
+}
+
+impl EventHandler for Flashback {
+    type Error = BinlogFileError;
+
+    fn on_event(&mut self, event: &Event, data: &EventData) -> Result<(), Self::Error> {
+        self.write_event(event, data);
+        Ok(())
+    }
+}
+
+// `name`, with backticks in it doubled
+pub fn quote_identifier(name: &str) -> String {
+    format!("`{}`", name.replace('`', "``"))
+}
+
+// 'text' with quotes, backslashes and the control characters mysql_real_escape_string escapes
+pub fn quote_string(text: &str) -> String {
+    let mut quoted = String::with_capacity(text.len() + 2);
+    quoted.push('\'');
+    for c in text.chars() {
+        match c {
+            '\'' => quoted.push_str("\\'"),
+            '\\' => quoted.push_str("\\\\"),
+            '\0' => quoted.push_str("\\0"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\x1a' => quoted.push_str("\\Z"),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('\'');
+    quoted
+}
+
+// A value as a SQL literal for a column of `column`'s type: binary strings as X'...', BIT as
+// b'...', ENUM and SET by label when the table map has the labels, JSON as its text.
+pub fn literal(value: &Value, column: Option<&TableMapColumn>) -> String {
+    match value {
+        Value::Null => "NULL".to_owned(),
+        Value::Int(v) => v.to_string(),
+        Value::UInt(v) => v.to_string(),
+        Value::Float(v) => v.to_string(),
+        Value::Double(v) => v.to_string(),
+        Value::Decimal(v) => v.clone(),
+        Value::Year(v) => v.to_string(),
+        Value::String(v) => quote_string(v),
+        Value::Bytes(v) => hex_literal(v),
+        Value::Timestamp { seconds: 0, micros: 0 } => "'0000-00-00 00:00:00'".to_owned(),
+        Value::Timestamp { seconds, micros: 0 } => format!("FROM_UNIXTIME({})", seconds),
+        Value::Timestamp { seconds, micros } => format!("FROM_UNIXTIME({}.{:06})", seconds, micros),
+        Value::Bit(v) => format!("b'{}'", v.iter().map(|b| format!("{:08b}", b)).collect::<String>()),
+        Value::Enum(index) => match column.and_then(|c| c.enum_values.as_ref()).and_then(|l| l.get((*index as usize).wrapping_sub(1))) {
+            Some(label) => quote_string(label),
+            None => index.to_string(),
+        },
+        Value::Set(bits) => match column.and_then(|c| c.set_values.as_ref()) {
+            Some(labels) => {
+                let members: Vec<&str> = labels.iter().enumerate()
+                    .filter(|(i, _)| *i < 64 && bits & (1 << i) != 0)
+                    .map(|(_, l)| l.as_str())
+                    .collect();
+                quote_string(&members.join(","))
+            }
+            None => bits.to_string(),
+        },
+        Value::Json(v) => match json_binary::decode(v) {
+            Some(document) => quote_string(&document.to_string()),
+            None => hex_literal(v),
+        },
+        // DATE, TIME and DATETIME in the text Value serializes to
+        Value::Date { .. } | Value::Time { .. } | Value::DateTime { .. } => match plain_json(value) {
+            serde_json::Value::String(text) => quote_string(&text),
+            _ => "NULL".to_owned(),
+        },
+    }
+}
+
+fn hex_literal(bytes: &[u8]) -> String {
+    format!("X'{}'", bytes.iter().map(|b| format!("{:02X}", b)).collect::<String>())
+}
+
+fn table_name(table_map: &TableMapEvent) -> String {
+    format!("{}.{}", quote_identifier(&table_map.schema), quote_identifier(&table_map.table))
+}
+
+fn column_name(table_map: &TableMapEvent, index: usize) -> String {
+    match table_map.columns.get(index).and_then(|c| c.name.as_ref()) {
+        Some(name) => name.clone(),
+        None => format!("@{}", index + 1),
+    }
+}
+
+fn column_literal(table_map: &TableMapEvent, image: &RowImage, index: usize) -> String {
+    match image.get(index) {
+        Some(value) => literal(value, table_map.columns.get(index)),
+        None => "NULL".to_owned(),
+    }
+}
+
+// the columns the image has, all but those left out by binlog_row_image=MINIMAL or NOBLOB
+fn present_columns(image: &RowImage) -> Vec<usize> {
+    image.values().iter().enumerate().filter(|(_, v)| v.is_some()).map(|(i, _)| i).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::context::ParseContext;
+    use crate::event::Event;
+    use crate::event::tests::{parse_events, users_rows, users_table_map};
+    use crate::sql::{literal, Flashback, KeyColumns, SqlStatements};
+    use crate::value::Value;
+
+    fn events() -> Vec<Event> {
+        // test.users (id INT PRIMARY KEY, name VARCHAR(20)) with column names
+        let table_map = users_table_map(&[3, 15], &[20, 0], 0x02, b"\x04\x08\x02id\x04name\x08\x01\x00");
+        let insert = users_rows(2, b"\x03\x00\x07\x00\x00\x00\x04it's");
+        let update = users_rows(2, b"\x03\x03\x00\x07\x00\x00\x00\x04it's\x02\x07\x00\x00\x00");
+        let delete = users_rows(2, &[0x03, 0x02, 7, 0, 0, 0]);
+        let xid = 42u64.to_le_bytes().to_vec();
+        parse_events(16, &[(19, table_map), (30, insert), (16, xid.clone()), (31, update), (32, delete), (16, xid)])
+    }
+
+    fn statements(mut sql: SqlStatements) -> Vec<String> {
+        let mut ctx = ParseContext::new();
+        events().iter().flat_map(|e| sql.statements(e, &e.parsed(&mut ctx).unwrap())).collect()
+    }
+
+    #[test]
+    fn test_statements_from_rows() {
+        //given
+        let by_key = SqlStatements::new();
+        let by_image = SqlStatements::new().key_columns(KeyColumns::AllColumns);
+
+        //when
+        let by_key = statements(by_key);
+        let by_image = statements(by_image);
+
+        //then
+        assert_eq!(by_key, vec![
+            "INSERT INTO `test`.`users` (`id`, `name`) VALUES (7, 'it\\'s');",
+            "UPDATE `test`.`users` SET `id`=7, `name`=NULL WHERE `id`=7;",
+            "DELETE FROM `test`.`users` WHERE `id`=7;",
+        ]);
+        assert_eq!(by_image[1], "UPDATE `test`.`users` SET `id`=7, `name`=NULL WHERE `id`=7 AND `name`='it\\'s' LIMIT 1;");
+        assert_eq!(by_image[2], "DELETE FROM `test`.`users` WHERE `id`=7 AND `name` IS NULL LIMIT 1;");
+    }
+
+    #[test]
+    fn test_flashback_in_reverse_order() {
+        //given
+        let mut flashback = Flashback::new();
+        let mut ctx = ParseContext::new();
+
+        //when
+        for event in events() {
+            flashback.write_event(&event, &event.parsed(&mut ctx).unwrap());
+        }
+        let script = flashback.script();
+
+        //then
+        assert_eq!(script, "BEGIN;\n\
+            INSERT INTO `test`.`users` (`id`, `name`) VALUES (7, NULL);\n\
+            UPDATE `test`.`users` SET `id`=7, `name`='it\\'s' WHERE `id`=7;\n\
+            COMMIT;\n\
+            BEGIN;\n\
+            DELETE FROM `test`.`users` WHERE `id`=7;\n\
+            COMMIT;\n");
+    }
+
+    #[test]
+    fn test_literals() {
+        //given
+        let values = [
+            Value::String("a\\b\n\0".to_owned()),
+            Value::Bytes(vec![0xde, 0xad]),
+            Value::Bit(vec![0x01, 0x02]),
+            Value::Timestamp { seconds: 1700000000, micros: 500 },
+            Value::DateTime { year: 2024, month: 1, day: 31, hour: 23, minute: 59, second: 59, micros: 0 },
+            Value::Json(vec![0x0c, 0x02, b'h', b'i']),
+        ];
+
+        //when
+        let literals: Vec<String> = values.iter().map(|v| literal(v, None)).collect();
+
+        //then
+        assert_eq!(literals, vec![
+            "'a\\\\b\\n\\0'",
+            "X'DEAD'",
+            "b'0000000100000010'",
+            "FROM_UNIXTIME(1700000000.000500)",
+            "'2024-01-31 23:59:59'",
+            "'\"hi\"'",
+        ]);
+    }
+}
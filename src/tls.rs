@@ -0,0 +1,172 @@
+use std::convert::TryFrom;
+use std::sync::Arc;
+use rustls::pki_types::{CertificateDer, ServerName};
+use rustls::{ClientConfig, ClientConnection, RootCertStore, StreamOwned};
+use crate::errors::ClientError;
+use crate::protocol::Transport;
+
+// How the replication client secures its connection. The server's certificate is always
+// verified, against the given CA or else the Mozilla roots, and must name the host connected
+// to (or `server_name`), like ssl-mode=VERIFY_IDENTITY.
+//
+//     let tls = TlsConfig::new()
+//         .ca_pem(&std::fs::read("rds-ca-bundle.pem")?)
+//         .client_cert(&std::fs::read("client-cert.pem")?, &std::fs::read("client-key.pem")?);
+//     let events = ReplicationClient::new("db1.example.com:3306").tls(tls).connect()?;
+#[derive(Debug, Clone, Default)]
+pub struct TlsConfig {
+    ca_pem: Option<Vec<u8>>,
+    client_cert: Option<(Vec<u8>, Vec<u8>)>,
+    server_name: Option<String>,
+}
+
+impl TlsConfig {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // trust only the certificates in `pem`, e.g. a cloud provider's CA bundle
+    pub fn ca_pem(mut self, pem: &[u8]) -> Self {
+        self.ca_pem = Some(pem.to_vec());
+        self
+    }
+
+    // for accounts created with REQUIRE X509 or REQUIRE SUBJECT
+    pub fn client_cert(mut self, cert_pem: &[u8], key_pem: &[u8]) -> Self {
+        self.client_cert = Some((cert_pem.to_vec(), key_pem.to_vec()));
+        self
+    }
+
+    // the name the certificate must have, when connecting by a different name or an IP
+    pub fn server_name(mut self, name: &str) -> Self {
+        self.server_name = Some(name.to_owned());
+        self
+    }
+
+    // runs the TLS handshake over `stream` and returns the session
+    pub(crate) fn connect(&self, stream: Box<dyn Transport>, host: &str) -> Result<Box<dyn Transport>, ClientError> {
+        let name = self.server_name.as_deref().unwrap_or(host).to_owned();
+        let name = ServerName::try_from(name).map_err(|e| ClientError::Tls(e.to_string()))?;
+        let mut session = ClientConnection::new(Arc::new(self.client_config()?), name)
+            .map_err(|e| ClientError::Tls(e.to_string()))?;
+        let mut stream = stream;
+        // finish the handshake now so a bad certificate is reported as such, not as an I/O error later
+        while session.is_handshaking() {
+            session.complete_io(&mut stream).map_err(|e| ClientError::Tls(e.to_string()))?;
+        }
+        Ok(Box::new(StreamOwned::new(session, stream)))
+    }
+
+    fn client_config(&self) -> Result<ClientConfig, ClientError> {
+        let tls_error = |e: &dyn std::fmt::Display| ClientError::Tls(e.to_string());
+        let mut roots = RootCertStore::empty();
+        match &self.ca_pem {
+            Some(pem) => {
+                for cert in read_certs(pem)? {
+                    roots.add(cert).map_err(|e| tls_error(&e))?;
+                }
+            }
+            None => roots.extend(webpki_roots::TLS_SERVER_ROOTS.iter().cloned()),
+        }
+        let builder = ClientConfig::builder_with_provider(Arc::new(rustls::crypto::ring::default_provider()))
+            .with_safe_default_protocol_versions()
+            .map_err(|e| tls_error(&e))?
+            .with_root_certificates(roots);
+        match &self.client_cert {
+            Some((cert_pem, key_pem)) => {
+                let key = rustls_pemfile::private_key(&mut &key_pem[..])
+                    .map_err(|e| tls_error(&e))?
+                    .ok_or_else(|| ClientError::Tls("no private key in client key PEM".to_owned()))?;
+                builder.with_client_auth_cert(read_certs(cert_pem)?, key).map_err(|e| tls_error(&e))
+            }
+            None => Ok(builder.with_no_client_auth()),
+        }
+    }
+}
+
+fn read_certs(pem: &[u8]) -> Result<Vec<CertificateDer<'static>>, ClientError> {
+    let certs = rustls_pemfile::certs(&mut &pem[..]).collect::<Result<Vec<_>, _>>()
+        .map_err(|e| ClientError::Tls(e.to_string()))?;
+    if certs.is_empty() {
+        return Err(ClientError::Tls("no certificates in PEM".to_owned()));
+    }
+    Ok(certs)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use rustls::pki_types::{CertificateDer, PrivateKeyDer, PrivatePkcs8KeyDer};
+    use rustls::{ServerConfig, ServerConnection, StreamOwned};
+    use crate::client::tests::{fake_server, handshake, read_packet, write_packet};
+    use crate::client::ReplicationClient;
+    use crate::errors::ClientError;
+    use crate::tls::TlsConfig;
+
+    fn server_config(cert: &rcgen::CertifiedKey) -> Arc<ServerConfig> {
+        let key = PrivateKeyDer::Pkcs8(PrivatePkcs8KeyDer::from(cert.key_pair.serialize_der()));
+        let config = ServerConfig::builder_with_provider(Arc::new(rustls::crypto::ring::default_provider()))
+            .with_safe_default_protocol_versions()
+            .unwrap()
+            .with_no_client_auth()
+            .with_single_cert(vec![CertificateDer::from(cert.cert.der().to_vec())], key)
+            .unwrap();
+        Arc::new(config)
+    }
+
+    #[test]
+    fn test_full_auth_over_tls_sends_password() {
+        //given
+        let cert = rcgen::generate_simple_self_signed(vec!["localhost".to_owned()]).unwrap();
+        let ca = cert.cert.pem();
+        let config = server_config(&cert);
+        let (addr, server) = fake_server(move |mut stream| {
+            write_packet(&mut stream, 0, &handshake("caching_sha2_password", &[1; 20]));
+            let ssl_request = read_packet(&mut stream);
+            assert_eq!(ssl_request.len(), 32);
+            assert_ne!(u32::from_le_bytes([ssl_request[0], ssl_request[1], ssl_request[2], ssl_request[3]]) & 0x800, 0);
+            let mut stream = StreamOwned::new(ServerConnection::new(config).unwrap(), stream);
+            read_packet(&mut stream);
+            write_packet(&mut stream, 3, &[0x01, 0x04]);
+            assert_eq!(read_packet(&mut stream), b"secret\0");
+            write_packet(&mut stream, 5, &[0, 0, 0, 2, 0, 0, 0]);
+            read_packet(&mut stream);
+            write_packet(&mut stream, 1, &[0xff, 0xcb, 0x04, b'n', b'o']);
+        });
+        let tls = TlsConfig::new().ca_pem(ca.as_bytes()).server_name("localhost");
+
+        //when
+        let result = ReplicationClient::new(&addr).password("secret").tls(tls).connect();
+        server.join().unwrap();
+
+        //then
+        assert!(matches!(result, Err(ClientError::Server { code: 1227, .. })));
+    }
+
+    #[test]
+    fn test_certificate_must_name_the_host() {
+        //given
+        let cert = rcgen::generate_simple_self_signed(vec!["db1.example.com".to_owned()]).unwrap();
+        let ca = cert.cert.pem();
+        let config = server_config(&cert);
+        let (addr, server) = fake_server(move |mut stream| {
+            write_packet(&mut stream, 0, &handshake("caching_sha2_password", &[1; 20]));
+            read_packet(&mut stream);
+            let mut session = ServerConnection::new(config).unwrap();
+            // the client gives up on the certificate; the server only sees the connection end
+            while session.is_handshaking() {
+                if session.complete_io(&mut stream).is_err() {
+                    break;
+                }
+            }
+        });
+        let tls = TlsConfig::new().ca_pem(ca.as_bytes()).server_name("db2.example.com");
+
+        //when
+        let result = ReplicationClient::new(&addr).tls(tls).connect();
+        server.join().unwrap();
+
+        //then
+        assert!(matches!(result, Err(ClientError::Tls(ref message)) if message.contains("certificate")), "{:?}", result.err());
+    }
+}
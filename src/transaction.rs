@@ -0,0 +1,594 @@
+use std::collections::VecDeque;
+use std::fmt;
+use crate::context::ParseContext;
+use crate::errors::{BinlogFileError, EventParseError};
+use crate::event::{Event, EventData, TypeCode, MARIADB_GTID_STANDALONE};
+use crate::gtid::{format_uuid, GtidSet, MariaDbGtid};
+use crate::options::ParseMode;
+use crate::rows::{RowChange, RowsEventKind};
+
+// The events of one transaction in binlog order, from its GTID (or BEGIN) through XID/COMMIT.
+// Its GTID, SQL and row changes are parsed out of them on request.
+#[derive(Debug)]
+pub struct Transaction {
+    events: Vec<Event>,
+    complete: bool,
+}
+
+impl Transaction {
+    pub fn events(&self) -> &[Event] {
+        &self.events
+    }
+
+    pub fn into_events(self) -> Vec<Event> {
+        self.events
+    }
+
+    pub fn gtid_event(&self) -> Option<&Event> {
+        self.events.first()
+            .filter(|e| matches!(e.type_code(), TypeCode::GtidLogEvent | TypeCode::AnonymousGtidLogEvent | TypeCode::MariaDbGtidEvent))
+    }
+
+    pub fn start_offset(&self) -> u64 {
+        self.events.first().map_or(0, |e| e.offset())
+    }
+
+    // position right after the last event, i.e. where to resume once this transaction is applied
+    pub fn end_offset(&self) -> u64 {
+        self.events.last().map_or(0, |e| e.offset() + u64::from(e.event_length()))
+    }
+
+    // false when the stream ended (or the next GTID arrived) before the commit
+    pub fn is_complete(&self) -> bool {
+        self.complete
+    }
+
+    // None for anonymous transactions and those without a GTID event at all
+    pub fn gtid(&self) -> Result<Option<TransactionGtid>, EventParseError> {
+        let event = match self.gtid_event() {
+            Some(event) => event,
+            None => return Ok(None),
+        };
+        let gtid = match Event::parse_event_data_by_type_code(event.type_code(), event.try_data()?)? {
+            Some(EventData::GtidLogEvent(gtid)) => Some(TransactionGtid::Mysql { sid: gtid.sid, gno: gtid.gno }),
+            Some(EventData::MariaDbGtidEvent { domain_id, seq_no, .. }) => {
+                Some(TransactionGtid::MariaDb(MariaDbGtid { domain_id, server_id: event.server_id(), seq_no }))
+            }
+            _ => None,
+        };
+        Ok(gtid)
+    }
+
+    // when the transaction began, from its first event's header
+    pub fn timestamp(&self) -> u32 {
+        self.events.first().map_or(0, |e| e.timestamp())
+    }
+
+    // The SQL behind the rows events, one per statement, as logged with binlog_rows_query_log_events
+    // (RowsQuery) or MariaDB's binlog_annotate_row_events (Annotate_rows).
+    pub fn rows_queries(&self) -> Result<Vec<String>, EventParseError> {
+        let mut queries = vec![];
+        for event in self.events.iter().filter(|e| matches!(e.type_code(), TypeCode::RowsQueryLogEvent | TypeCode::AnnotateRowsEvent)) {
+            match Event::parse_event_data_by_type_code(event.type_code(), event.try_data()?)? {
+                Some(EventData::RowsQueryLogEvent { query }) | Some(EventData::AnnotateRowsEvent { query }) => queries.push(query),
+                _ => {}
+            }
+        }
+        Ok(queries)
+    }
+
+    // the XID, XA PREPARE or COMMIT/ROLLBACK query that ended the transaction; None for a
+    // statement that is a transaction of its own, and for incomplete transactions
+    pub fn commit_event(&self) -> Option<&Event> {
+        self.events.last().filter(|e| self.complete && matches!(role(e), Ok(Role::Commit)))
+    }
+
+    pub fn xid(&self) -> Result<Option<u64>, EventParseError> {
+        match self.commit_event() {
+            Some(event) if event.type_code() == TypeCode::XidEvent => {
+                match Event::parse_event_data_by_type_code(TypeCode::XidEvent, event.try_data()?)? {
+                    Some(EventData::XidEvent { xid }) => Ok(Some(xid)),
+                    _ => Ok(None),
+                }
+            }
+            _ => Ok(None),
+        }
+    }
+
+    // The row changes in binlog order, grouped by table in the order the tables were first
+    // changed. The transaction's own table maps go into `ctx`, which brings the post-header
+    // lengths, options and schema tracking; compressed transaction payloads are unpacked.
+    //
+    //     let mut ctx = ParseContext::new();
+    //     for table in transaction.table_changes(&mut ctx)? {
+    //         println!("{}.{}: {} rows", table.schema, table.table, table.changes.len());
+    //     }
+    pub fn table_changes(&self, ctx: &mut ParseContext) -> Result<Vec<TableChanges>, BinlogFileError> {
+        let mut tables = vec![];
+        for event in &self.events {
+            if event.type_code() == TypeCode::TransactionPayloadEvent {
+                for inner in event.payload_events()? {
+                    collect_changes(&inner?, ctx, &mut tables)?;
+                }
+            } else {
+                collect_changes(event, ctx, &mut tables)?;
+            }
+        }
+        Ok(tables)
+    }
+}
+
+fn collect_changes(event: &Event, ctx: &mut ParseContext, tables: &mut Vec<TableChanges>) -> Result<(), EventParseError> {
+    let kind = match RowsEventKind::from_type_code(event.type_code()) {
+        Some(kind) => kind,
+        None if event.type_code() == TypeCode::TableMapEvent => {
+            event.parsed(ctx)?;
+            return Ok(());
+        }
+        None => return Ok(()),
+    };
+    let rows = match event.parsed(ctx)? {
+        EventData::WriteRowsEvent(rows) | EventData::UpdateRowsEvent(rows) | EventData::DeleteRowsEvent(rows) => rows,
+        // unparsed, in lenient mode
+        _ => return Ok(()),
+    };
+    let (schema, table) = match ctx.table_map(rows.table_id) {
+        Some(table_map) => (table_map.schema.clone(), table_map.table.clone()),
+        None => return Ok(()),
+    };
+    let index = match tables.iter().position(|t: &TableChanges| t.schema == schema && t.table == table) {
+        Some(index) => index,
+        None => {
+            tables.push(TableChanges { schema, table, changes: vec![] });
+            tables.len() - 1
+        }
+    };
+    tables[index].changes.extend(rows.rows.into_iter().map(|row| (kind, row)));
+    Ok(())
+}
+
+// MySQL's server uuid and transaction number, or MariaDB's domain-server-sequence
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TransactionGtid {
+    Mysql { sid: [u8; 16], gno: i64 },
+    MariaDb(MariaDbGtid),
+}
+
+impl fmt::Display for TransactionGtid {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TransactionGtid::Mysql { sid, gno } => write!(f, "{}:{}", format_uuid(sid), gno),
+            TransactionGtid::MariaDb(gtid) => gtid.fmt(f),
+        }
+    }
+}
+
+// what a transaction did to one table, in binlog order
+#[derive(Debug, Clone, PartialEq)]
+pub struct TableChanges {
+    pub schema: String,
+    pub table: String,
+    pub changes: Vec<(RowsEventKind, RowChange)>,
+}
+
+#[derive(Debug)]
+pub enum TransactionItem {
+    Transaction(Transaction),
+    // events outside any transaction: format description, previous GTIDs, rotate, heartbeat, ...
+    Event(Event),
+}
+
+// Groups a raw event stream into transactions. Events that never belong to a transaction are
+// passed through as they arrive, so one showing up mid-transaction is yielded before it.
+pub struct Transactions<It> {
+    inner: It,
+    current: Vec<Event>,
+    in_begin: bool,
+    ready: VecDeque<TransactionItem>,
+    finished: bool,
+}
+
+pub(crate) enum Role {
+    Start,
+    Begin,
+    // starts a transaction and opens it, as MariaDB's GTIDs do in place of a BEGIN
+    StartBegin,
+    Commit,
+    Statement,
+    Member,
+    Standalone,
+}
+
+impl<It> Transactions<It> where
+    It: Iterator<Item = Result<Event, BinlogFileError>>
+{
+    pub fn new(inner: It) -> Self {
+        Transactions {
+            inner,
+            current: vec![],
+            in_begin: false,
+            ready: VecDeque::new(),
+            finished: false,
+        }
+    }
+
+    fn flush(&mut self, complete: bool) {
+        if !self.current.is_empty() {
+            let events = std::mem::take(&mut self.current);
+            self.ready.push_back(TransactionItem::Transaction(Transaction { events, complete }));
+        }
+        self.in_begin = false;
+    }
+
+    fn push(&mut self, event: Event) -> Result<(), EventParseError> {
+        match role(&event)? {
+            Role::Start => {
+                self.flush(false);
+                self.current.push(event);
+            }
+            Role::Begin => {
+                self.current.push(event);
+                self.in_begin = true;
+            }
+            Role::StartBegin => {
+                self.flush(false);
+                self.current.push(event);
+                self.in_begin = true;
+            }
+            Role::Commit => {
+                self.current.push(event);
+                self.flush(true);
+            }
+            // a statement outside BEGIN (DDL, or DML under autocommit with statement logging)
+            // is a transaction of its own
+            Role::Statement => {
+                self.current.push(event);
+                if !self.in_begin {
+                    self.flush(true);
+                }
+            }
+            Role::Member if !self.current.is_empty() => self.current.push(event),
+            Role::Member | Role::Standalone => self.ready.push_back(TransactionItem::Event(event)),
+        }
+        Ok(())
+    }
+}
+
+pub(crate) fn role(event: &Event) -> Result<Role, EventParseError> {
+    let role = match event.type_code() {
+        TypeCode::GtidLogEvent | TypeCode::AnonymousGtidLogEvent => Role::Start,
+        TypeCode::MariaDbGtidEvent => match event.try_data()?.get(12) {
+            Some(flags) if flags & MARIADB_GTID_STANDALONE == 0 => Role::StartBegin,
+            _ => Role::Start,
+        },
+        TypeCode::XidEvent | TypeCode::XaPrepareLogEvent => Role::Commit,
+        TypeCode::QueryEvent => {
+            let query = match Event::parse_event_data_by_type_code(TypeCode::QueryEvent, event.try_data()?)? {
+                Some(EventData::QueryEvent { query, .. }) => query,
+                _ => String::new(),
+            };
+            let keyword: String = query.trim_start().chars().take_while(|c| c.is_ascii_alphabetic()).collect();
+            if keyword.eq_ignore_ascii_case("BEGIN") {
+                Role::Begin
+            } else if keyword.eq_ignore_ascii_case("COMMIT") || keyword.eq_ignore_ascii_case("ROLLBACK") {
+                Role::Commit
+            } else {
+                Role::Statement
+            }
+        }
+        TypeCode::FormatDescriptionEvent
+        | TypeCode::StartEventV3
+        | TypeCode::StopEvent
+        | TypeCode::RotateEvent
+        | TypeCode::PreviousGtidsLogEvent
+        | TypeCode::HeartbeatLogEvent
+        | TypeCode::HeartbeatLogEventV2
+        | TypeCode::IncidentEvent
+        | TypeCode::BinlogCheckpointEvent
+        | TypeCode::MariaDbGtidListEvent
+        | TypeCode::StartEncryptionEvent => Role::Standalone,
+        _ => Role::Member,
+    };
+    Ok(role)
+}
+
+impl<It> Iterator for Transactions<It> where
+    It: Iterator<Item = Result<Event, BinlogFileError>>
+{
+    type Item = Result<TransactionItem, BinlogFileError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(item) = self.ready.pop_front() {
+                return Some(Ok(item));
+            }
+            if self.finished {
+                return None;
+            }
+            match self.inner.next() {
+                Some(Ok(event)) => {
+                    if let Err(e) = self.push(event) {
+                        self.finished = true;
+                        return Some(Err(e.into()));
+                    }
+                }
+                Some(Err(e)) => {
+                    self.finished = true;
+                    return Some(Err(e));
+                }
+                None => {
+                    self.finished = true;
+                    self.flush(false);
+                }
+            }
+        }
+    }
+}
+
+// The GTIDs executed as of the events read so far, for checkpoints: a Previous_gtids joins the
+// set (starting it, when it wasn't known), and each transaction's GTID joins it once the
+// transaction's commit has been read, so a checkpoint taken mid-transaction doesn't count it.
+#[derive(Debug, Clone, Default)]
+pub struct GtidTracker {
+    executed: Option<GtidSet>,
+    pending: Option<([u8; 16], i64)>,
+    in_begin: bool,
+    in_transaction: bool,
+}
+
+impl GtidTracker {
+    // nothing known until a Previous_gtids is read
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // starting with `executed` known, e.g. when reading from a GTID checkpoint
+    pub fn with_executed(executed: GtidSet) -> Self {
+        GtidTracker { executed: Some(executed), ..Self::default() }
+    }
+
+    pub fn executed(&self) -> Option<&GtidSet> {
+        self.executed.as_ref()
+    }
+
+    // follows `event`, returning whether the position after it is between transactions
+    pub fn track(&mut self, event: &Event) -> Result<bool, EventParseError> {
+        let committed = match role(event)? {
+            Role::Start => {
+                self.pending = match Event::parse_event_data_by_type_code(event.type_code(), event.try_data()?)? {
+                    Some(EventData::GtidLogEvent(gtid)) => Some((gtid.sid, gtid.gno)),
+                    _ => None,
+                };
+                self.in_transaction = true;
+                false
+            }
+            Role::Begin | Role::StartBegin => {
+                self.in_begin = true;
+                self.in_transaction = true;
+                false
+            }
+            Role::Commit => true,
+            Role::Statement => !self.in_begin,
+            Role::Standalone if event.type_code() == TypeCode::PreviousGtidsLogEvent => {
+                if let Some(EventData::PreviousGtidsLogEvent { gtids }) = Event::parse_event_data_by_type_code(event.type_code(), event.try_data()?)? {
+                    self.executed.get_or_insert_with(GtidSet::new).add_set(&gtids);
+                }
+                !self.in_transaction
+            }
+            Role::Member | Role::Standalone => !self.in_transaction,
+        };
+        if committed {
+            self.in_begin = false;
+            self.in_transaction = false;
+            if let (Some((sid, gno)), Some(executed)) = (self.pending.take(), &mut self.executed) {
+                executed.add(sid, gno);
+            }
+        }
+        Ok(committed)
+    }
+
+    // track() for readers, which in lenient mode hand on events whose bodies don't parse
+    pub(crate) fn track_read(&mut self, event: &Event, mode: ParseMode) -> Result<(), EventParseError> {
+        match self.track(event) {
+            Err(e) if mode == ParseMode::Strict => Err(e),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::binlog_file::BinlogFile;
+    use crate::context::ParseContext;
+    use crate::event::{Event, TypeCode};
+    use crate::event::tests::parse_events;
+    use crate::binlog_reader::BinlogReader;
+    use crate::rows::RowsEventKind;
+    use crate::synthetic::SyntheticBinlog;
+    use crate::transaction::{GtidTracker, Transaction, TransactionGtid, TransactionItem, Transactions};
+    use crate::value::Value;
+
+    fn transactions(binlog: &[u8]) -> Vec<Transaction> {
+        BinlogReader::from_reader(binlog).unwrap().transactions().filter_map(|item| match item.unwrap() {
+            TransactionItem::Transaction(t) => Some(t),
+            TransactionItem::Event(_) => None,
+        }).collect()
+    }
+
+    #[test]
+    fn test_group_transactions() {
+        //given
+        let path = "tests/asset/mysql-bin.100746";
+        let mut binlog_file = BinlogFile::from_path(path).unwrap();
+
+        //when
+        let items: Vec<_> = binlog_file.events().transactions().collect::<Result<_, _>>().unwrap();
+
+        //then
+        let transactions: Vec<_> = items.iter().filter_map(|i| match i {
+            TransactionItem::Transaction(t) => Some(t),
+            TransactionItem::Event(_) => None,
+        }).collect();
+        assert_eq!(items.len(), 6);
+        assert_eq!(transactions.len(), 3);
+        // CREATE TABLE under its own GTID
+        assert_eq!(transactions[0].events().len(), 2);
+        assert_eq!(transactions[1].events().len(), 5);
+        assert_eq!(transactions[1].events().last().unwrap().type_code(), TypeCode::XidEvent);
+        assert!(transactions.iter().all(|t| t.is_complete() && t.gtid_event().is_some()));
+        assert_eq!(transactions[2].start_offset(), transactions[1].end_offset());
+        assert!(matches!(items.last(), Some(TransactionItem::Event(e)) if e.type_code() == TypeCode::RotateEvent));
+    }
+
+    #[test]
+    fn test_group_mariadb_transactions() {
+        //given
+        let gtid = |seq_no: u64, flags: u8| [&seq_no.to_le_bytes()[..], &[0, 0, 0, 0, flags]].concat();
+        let query = |sql: &str| [&[0; 14][..], sql.as_bytes()].concat();
+        // a DDL under a standalone GTID, then a transaction MariaDB opens with its GTID, no BEGIN
+        let events = parse_events(0, &[
+            (162, gtid(1, 0x01)),
+            (2, query("CREATE TABLE t (a INT)")),
+            (162, gtid(2, 0x00)),
+            (160, b"INSERT INTO t VALUES (1)".to_vec()),
+            (2, query("INSERT INTO t VALUES (1)")),
+            (16, 42u64.to_le_bytes().to_vec()),
+        ]);
+
+        //when
+        let items: Vec<_> = Transactions::new(events.into_iter().map(Ok))
+            .collect::<Result<_, _>>()
+            .unwrap();
+
+        //then
+        let sizes: Vec<_> = items.iter().map(|i| match i {
+            TransactionItem::Transaction(t) => t.events().len(),
+            TransactionItem::Event(_) => 0,
+        }).collect();
+        assert_eq!(sizes, vec![2, 4]);
+        assert!(matches!(&items[1], TransactionItem::Transaction(t) if t.is_complete() && t.gtid_event().is_some()));
+    }
+
+    #[test]
+    fn test_track_executed_gtids() {
+        //given
+        let sid = [0x3e; 16];
+        let binlog = SyntheticBinlog::new()
+            .gtids(sid)
+            .query("shop", "CREATE TABLE item (id INT PRIMARY KEY)")
+            .insert("shop", "item", vec![vec![Value::Int(1)]])
+            .to_bytes()
+            .unwrap();
+        let mut reader = BinlogReader::from_reader(&binlog[..]).unwrap();
+        let mut tracker = GtidTracker::with_executed("3e3e3e3e-3e3e-3e3e-3e3e-3e3e3e3e3e3e:5".parse().unwrap());
+
+        //when
+        let mut seen = vec![];
+        while let Some(event) = reader.next() {
+            let event = event.unwrap();
+            let boundary = tracker.track(&event).unwrap();
+            seen.push((event.type_code(), boundary, reader.executed_gtids().map(|set| set.to_string())));
+        }
+
+        //then
+        let executed = |text: &str| Some(text.to_owned());
+        assert_eq!(seen, [
+            (TypeCode::FormatDescriptionEvent, true, None),
+            (TypeCode::PreviousGtidsLogEvent, true, executed("")),
+            (TypeCode::GtidLogEvent, false, executed("")),
+            (TypeCode::QueryEvent, true, executed("3e3e3e3e-3e3e-3e3e-3e3e-3e3e3e3e3e3e:1")),
+            (TypeCode::GtidLogEvent, false, executed("3e3e3e3e-3e3e-3e3e-3e3e-3e3e3e3e3e3e:1")),
+            (TypeCode::QueryEvent, false, executed("3e3e3e3e-3e3e-3e3e-3e3e-3e3e3e3e3e3e:1")),
+            (TypeCode::TableMapEvent, false, executed("3e3e3e3e-3e3e-3e3e-3e3e-3e3e3e3e3e3e:1")),
+            (TypeCode::WriteRowsEventV2, false, executed("3e3e3e3e-3e3e-3e3e-3e3e-3e3e3e3e3e3e:1")),
+            (TypeCode::XidEvent, true, executed("3e3e3e3e-3e3e-3e3e-3e3e-3e3e3e3e3e3e:1-2")),
+            (TypeCode::RotateEvent, true, executed("3e3e3e3e-3e3e-3e3e-3e3e-3e3e3e3e3e3e:1-2")),
+        ]);
+        assert_eq!(tracker.executed().unwrap().to_string(), "3e3e3e3e-3e3e-3e3e-3e3e-3e3e3e3e3e3e:1-2:5");
+    }
+
+    #[test]
+    fn test_transaction_parts() {
+        //given
+        let sid = [0x3e; 16];
+        let binlog = SyntheticBinlog::new()
+            .gtids(sid)
+            .timestamp(1700000000)
+            .query("shop", "CREATE TABLE item (id INT PRIMARY KEY, name VARCHAR(20))")
+            .insert("shop", "item", vec![vec![Value::Int(1), Value::String("pen".to_owned())]])
+            .to_bytes()
+            .unwrap();
+        let transactions = transactions(&binlog);
+
+        //when
+        let ddl = &transactions[0];
+        let insert = &transactions[1];
+        let changes = insert.table_changes(&mut ParseContext::new()).unwrap();
+
+        //then
+        assert_eq!(insert.gtid().unwrap(), Some(TransactionGtid::Mysql { sid, gno: 2 }));
+        assert_eq!(insert.gtid().unwrap().unwrap().to_string(), "3e3e3e3e-3e3e-3e3e-3e3e-3e3e3e3e3e3e:2");
+        assert_eq!(insert.timestamp(), 1700000000);
+        assert_eq!(insert.commit_event().map(|e| e.type_code()), Some(TypeCode::XidEvent));
+        assert_eq!(insert.xid().unwrap(), Some(2));
+        assert_eq!(changes.len(), 1);
+        assert_eq!((changes[0].schema.as_str(), changes[0].table.as_str()), ("shop", "item"));
+        assert_eq!(changes[0].changes[0].0, RowsEventKind::Write);
+        assert_eq!(changes[0].changes[0].1.after.as_ref().unwrap().values(), &[Some(Value::Int(1)), Some(Value::String("pen".to_owned()))]);
+        // a DDL commits itself
+        assert!(ddl.commit_event().is_none());
+        assert_eq!(ddl.xid().unwrap(), None);
+        assert!(ddl.table_changes(&mut ParseContext::new()).unwrap().is_empty());
+        assert!(insert.rows_queries().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_table_changes_grouped_by_table() {
+        //given
+        let binlog = SyntheticBinlog::new()
+            .checksum(false)
+            .query("shop", "CREATE TABLE item (id INT PRIMARY KEY)")
+            .query("shop", "CREATE TABLE stock (id INT PRIMARY KEY)")
+            .insert("shop", "item", vec![vec![Value::Int(1)]])
+            .insert("shop", "stock", vec![vec![Value::Int(2)]])
+            .update("shop", "item", vec![(vec![Value::Int(1)], vec![Value::Int(3)])])
+            .to_bytes()
+            .unwrap();
+        let events: Vec<Event> = BinlogReader::from_reader(&binlog[..]).unwrap().map(|e| e.unwrap()).collect();
+        let mut rows_query = vec![0, 0, 0, 0, 29, 1, 0, 0, 0, 42, 0, 0, 0, 0, 0, 0, 0, 0, 0, 22];
+        rows_query.extend_from_slice(b"UPDATE item SET id = 3");
+        // the three statements as one transaction: GTID, BEGIN, the rows and the last XID
+        let begin = events.iter().position(|e| e.type_code() == TypeCode::TableMapEvent).unwrap() - 2;
+        let xid = events.iter().rposition(|e| e.type_code() == TypeCode::XidEvent).unwrap();
+        let mut transaction = vec![];
+        for (i, event) in events.into_iter().enumerate() {
+            let rows = matches!(event.type_code(), TypeCode::TableMapEvent | TypeCode::WriteRowsEventV2 | TypeCode::UpdateRowsEventV2);
+            if i == begin || i == begin + 1 || (i > begin && rows) || i == xid {
+                transaction.push(event);
+            }
+            if i == begin + 1 {
+                transaction.push(Event::parse(&mut &rows_query[..], 4).unwrap());
+            }
+        }
+
+        //when
+        let mut items = Transactions::new(transaction.into_iter().map(Ok));
+        let transaction = match items.next() {
+            Some(Ok(TransactionItem::Transaction(t))) => t,
+            _ => panic!("expected a transaction"),
+        };
+        let changes = transaction.table_changes(&mut ParseContext::new()).unwrap();
+
+        //then
+        assert!(items.next().is_none());
+        assert_eq!(transaction.gtid().unwrap(), None);
+        assert_eq!(transaction.rows_queries().unwrap(), vec!["UPDATE item SET id = 3".to_owned()]);
+        assert_eq!(transaction.xid().unwrap(), Some(5));
+        let tables: Vec<_> = changes.iter().map(|t| t.table.as_str()).collect();
+        assert_eq!(tables, vec!["item", "stock"]);
+        let kinds: Vec<_> = changes[0].changes.iter().map(|(kind, _)| *kind).collect();
+        assert_eq!(kinds, vec![RowsEventKind::Write, RowsEventKind::Update]);
+        assert_eq!(changes[0].changes[1].1.before.as_ref().unwrap().values(), &[Some(Value::Int(1))]);
+        assert_eq!(changes[1].changes.len(), 1);
+    }
+}
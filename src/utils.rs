@@ -0,0 +1,174 @@
+use std::io::{self, Read};
+use byteorder::{LittleEndian, ReadBytesExt};
+use crate::errors::EventParseError;
+
+// https://dev.mysql.com/doc/internals/en/integer.html#packet-Protocol::LengthEncodedInteger
+pub(crate) fn read_lenenc_int<R: Read>(reader: &mut R) -> io::Result<u64> {
+    match reader.read_u8()? {
+        0xfc => Ok(u64::from(reader.read_u16::<LittleEndian>()?)),
+        0xfd => Ok(u64::from(reader.read_u24::<LittleEndian>()?)),
+        0xfe => reader.read_u64::<LittleEndian>(),
+        b => Ok(u64::from(b)),
+    }
+}
+
+// the inverse of read_lenenc_int, with the shortest encoding that fits
+pub(crate) fn write_lenenc_int(out: &mut Vec<u8>, v: u64) {
+    match v {
+        0..=0xfa => out.push(v as u8),
+        0xfb..=0xffff => {
+            out.push(0xfc);
+            out.extend_from_slice(&(v as u16).to_le_bytes());
+        }
+        0x1_0000..=0xff_ffff => {
+            out.push(0xfd);
+            out.extend_from_slice(&(v as u32).to_le_bytes()[..3]);
+        }
+        _ => {
+            out.push(0xfe);
+            out.extend_from_slice(&v.to_le_bytes());
+        }
+    }
+}
+
+pub(crate) fn write_lenenc_bytes(out: &mut Vec<u8>, bytes: &[u8]) {
+    write_lenenc_int(out, bytes.len() as u64);
+    out.extend_from_slice(bytes);
+}
+
+// grown as the bytes arrive rather than allocated up front, in case `len` is corrupt
+pub(crate) fn read_bytes<R: Read>(reader: &mut R, len: usize) -> io::Result<Vec<u8>> {
+    let mut bytes = vec![];
+    reader.take(len as u64).read_to_end(&mut bytes)?;
+    if bytes.len() < len {
+        return Err(io::ErrorKind::UnexpectedEof.into());
+    }
+    Ok(bytes)
+}
+
+pub(crate) fn read_lenenc_bytes<R: Read>(reader: &mut R) -> io::Result<Vec<u8>> {
+    let len = read_lenenc_int(reader)? as usize;
+    read_bytes(reader, len)
+}
+
+pub(crate) fn read_lenenc_string<R: Read>(reader: &mut R) -> io::Result<String> {
+    Ok(String::from_utf8_lossy(&read_lenenc_bytes(reader)?).into_owned())
+}
+
+pub(crate) fn read_bitmap<R: Read>(reader: &mut R, bits: usize) -> io::Result<Vec<bool>> {
+    let bytes = read_bytes(reader, bits.div_ceil(8))?;
+    Ok((0..bits).map(|i| bytes[i / 8] & (1 << (i % 8)) != 0).collect())
+}
+
+pub(crate) fn write_bitmap(out: &mut Vec<u8>, bits: &[bool]) {
+    let start = out.len();
+    out.resize(start + bits.len().div_ceil(8), 0);
+    for (i, _) in bits.iter().enumerate().filter(|(_, bit)| **bit) {
+        out[start + i / 8] |= 1 << (i % 8);
+    }
+}
+
+// like read_exact, but reports how much was read instead of failing at end of input
+pub(crate) fn read_full<R: Read>(reader: &mut R, buf: &mut [u8]) -> std::io::Result<usize> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        match reader.read(&mut buf[filled..]) {
+            Ok(0) => break,
+            Ok(n) => filled += n,
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(filled)
+}
+
+// names the field a read was for, so running off the end of a body says what was being decoded
+pub(crate) trait FieldContext<T> {
+    fn field(self, name: &'static str) -> Result<T, EventParseError>;
+}
+
+impl<T> FieldContext<T> for io::Result<T> {
+    fn field(self, name: &'static str) -> Result<T, EventParseError> {
+        self.map_err(|e| match e.kind() {
+            io::ErrorKind::UnexpectedEof => EventParseError::UnexpectedEof { field: name },
+            _ => EventParseError::Io(e),
+        })
+    }
+}
+
+// `len` bytes announced by a length field; a corrupt length fails instead of allocating it up front
+pub(crate) fn read_sized<R: Read>(reader: &mut R, len: usize, field: &'static str) -> Result<Vec<u8>, EventParseError> {
+    let mut bytes = vec![];
+    reader.take(len as u64).read_to_end(&mut bytes)?;
+    if bytes.len() < len {
+        return Err(EventParseError::BadLength { field, len: len as u64, available: bytes.len() as u64 });
+    }
+    Ok(bytes)
+}
+
+pub(crate) fn read_utf8(bytes: Vec<u8>, field: &'static str) -> Result<String, EventParseError> {
+    String::from_utf8(bytes).map_err(|_| EventParseError::BadUtf8 { field })
+}
+
+// https://howardhinnant.github.io/date_algorithms.html
+pub(crate) fn days_from_civil(year: i64, month: u8, day: u8) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let month = i64::from(month);
+    let day_of_year = (153 * (if month > 2 { month - 3 } else { month + 9 }) + 2) / 5 + i64::from(day) - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146097 + day_of_era - 719468
+}
+
+pub(crate) fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let days = days + 719468;
+    let era = days.div_euclid(146097);
+    let day_of_era = days - era * 146097;
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+// seconds since the epoch as YYYY-MM-DD HH:MM:SS in UTC
+pub(crate) fn utc_datetime(seconds: u32) -> String {
+    let (year, month, day) = civil_from_days(i64::from(seconds / 86400));
+    let time = seconds % 86400;
+    format!("{:04}-{:02}-{:02} {:02}:{:02}:{:02}", year, month, day, time / 3600, time / 60 % 60, time % 60)
+}
+
+pub(crate) fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut out = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let n = (u32::from(chunk[0]) << 16) | (u32::from(*chunk.get(1).unwrap_or(&0)) << 8) | u32::from(*chunk.get(2).unwrap_or(&0));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(ALPHABET[(n >> (18 - 6 * i) & 0x3f) as usize] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
+// DECIMAL text as an integer of `scale` implied decimals: "-1.5" is -150 at scale 2. None when
+// it has more decimals than that or doesn't fit in 38 digits.
+pub(crate) fn unscaled_decimal(text: &str, scale: u8) -> Option<i128> {
+    let (negative, digits) = match text.strip_prefix('-') {
+        Some(digits) => (true, digits),
+        None => (false, text),
+    };
+    let (integer, fraction) = digits.split_once('.').unwrap_or((digits, ""));
+    if fraction.len() > scale as usize {
+        return None;
+    }
+    let padded = format!("{}{}{}", integer, fraction, "0".repeat(scale as usize - fraction.len()));
+    let unscaled: i128 = padded.parse().ok()?;
+    Some(if negative { -unscaled } else { unscaled })
+}
@@ -0,0 +1,784 @@
+use std::convert::TryFrom;
+use std::fmt;
+use std::io::Read;
+use byteorder::{BigEndian, LittleEndian, ReadBytesExt};
+use serde_derive::{Deserialize, Serialize};
+use crate::charset::Charset;
+use crate::column::{real_string_type, ColumnType};
+use crate::errors::EventParseError;
+use crate::options::ParseOptions;
+use crate::table_map::TableMapColumn;
+use crate::json_binary;
+use crate::utils::{read_sized, utc_datetime};
+
+// serialized with temporal values as 2024-01-31, -838:59:59.000001 and 2024-01-31 23:59:59.5
+// strings, and raw bytes as hex in human readable formats
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(into = "ValueRepr", try_from = "ValueRepr")]
+pub enum Value {
+    Null,
+    Int(i64),
+    UInt(u64),
+    Float(f32),
+    Double(f64),
+    Decimal(String),
+    String(String),
+    Bytes(Vec<u8>),
+    Year(u16),
+    Date { year: u16, month: u8, day: u8 },
+    Time { negative: bool, hours: u32, minutes: u8, seconds: u8, micros: u32 },
+    DateTime { year: u16, month: u8, day: u8, hour: u8, minute: u8, second: u8, micros: u32 },
+    Timestamp { seconds: u32, micros: u32 },
+    Bit(Vec<u8>),
+    Enum(u16),
+    Set(u64),
+    Json(Vec<u8>),
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename = "Value")]
+enum ValueRepr {
+    Null,
+    Int(i64),
+    UInt(u64),
+    Float(f32),
+    Double(f64),
+    Decimal(String),
+    String(String),
+    Bytes(#[serde(with = "crate::serde_repr::hex_bytes")] Vec<u8>),
+    Year(u16),
+    Date(String),
+    Time(String),
+    DateTime(String),
+    Timestamp { seconds: u32, micros: u32 },
+    Bit(#[serde(with = "crate::serde_repr::hex_bytes")] Vec<u8>),
+    Enum(u16),
+    Set(u64),
+    Json(#[serde(with = "crate::serde_repr::hex_bytes")] Vec<u8>),
+}
+
+impl From<Value> for ValueRepr {
+    fn from(value: Value) -> Self {
+        match value {
+            Value::Null => ValueRepr::Null,
+            Value::Int(v) => ValueRepr::Int(v),
+            Value::UInt(v) => ValueRepr::UInt(v),
+            Value::Float(v) => ValueRepr::Float(v),
+            Value::Double(v) => ValueRepr::Double(v),
+            Value::Decimal(v) => ValueRepr::Decimal(v),
+            Value::String(v) => ValueRepr::String(v),
+            Value::Bytes(v) => ValueRepr::Bytes(v),
+            Value::Year(v) => ValueRepr::Year(v),
+            Value::Date { year, month, day } => ValueRepr::Date(format!("{:04}-{:02}-{:02}", year, month, day)),
+            Value::Time { negative, hours, minutes, seconds, micros } => {
+                let sign = if negative { "-" } else { "" };
+                ValueRepr::Time(format!("{}{:02}:{:02}:{:02}{}", sign, hours, minutes, seconds, fraction(micros)))
+            }
+            Value::DateTime { year, month, day, hour, minute, second, micros } => ValueRepr::DateTime(format!(
+                "{:04}-{:02}-{:02} {:02}:{:02}:{:02}{}", year, month, day, hour, minute, second, fraction(micros)
+            )),
+            Value::Timestamp { seconds, micros } => ValueRepr::Timestamp { seconds, micros },
+            Value::Bit(v) => ValueRepr::Bit(v),
+            Value::Enum(v) => ValueRepr::Enum(v),
+            Value::Set(v) => ValueRepr::Set(v),
+            Value::Json(v) => ValueRepr::Json(v),
+        }
+    }
+}
+
+impl TryFrom<ValueRepr> for Value {
+    type Error = String;
+
+    fn try_from(repr: ValueRepr) -> Result<Self, Self::Error> {
+        Ok(match repr {
+            ValueRepr::Null => Value::Null,
+            ValueRepr::Int(v) => Value::Int(v),
+            ValueRepr::UInt(v) => Value::UInt(v),
+            ValueRepr::Float(v) => Value::Float(v),
+            ValueRepr::Double(v) => Value::Double(v),
+            ValueRepr::Decimal(v) => Value::Decimal(v),
+            ValueRepr::String(v) => Value::String(v),
+            ValueRepr::Bytes(v) => Value::Bytes(v),
+            ValueRepr::Year(v) => Value::Year(v),
+            ValueRepr::Date(s) => {
+                let (year, month, day) = parse_date(&s).ok_or_else(|| format!("bad DATE {:?}", s))?;
+                Value::Date { year, month, day }
+            }
+            ValueRepr::Time(s) => {
+                let (negative, time) = match s.strip_prefix('-') {
+                    Some(time) => (true, time),
+                    None => (false, s.as_str()),
+                };
+                let (hours, minutes, seconds, micros) = parse_time(time).ok_or_else(|| format!("bad TIME {:?}", s))?;
+                Value::Time { negative, hours, minutes, seconds, micros }
+            }
+            ValueRepr::DateTime(s) => {
+                let bad = || format!("bad DATETIME {:?}", s);
+                let (date, time) = s.split_once(' ').ok_or_else(bad)?;
+                let (year, month, day) = parse_date(date).ok_or_else(bad)?;
+                let (hour, minute, second, micros) = parse_time(time).ok_or_else(bad)?;
+                let hour = u8::try_from(hour).map_err(|_| bad())?;
+                Value::DateTime { year, month, day, hour, minute, second, micros }
+            }
+            ValueRepr::Timestamp { seconds, micros } => Value::Timestamp { seconds, micros },
+            ValueRepr::Bit(v) => Value::Bit(v),
+            ValueRepr::Enum(v) => Value::Enum(v),
+            ValueRepr::Set(v) => Value::Set(v),
+            ValueRepr::Json(v) => Value::Json(v),
+        })
+    }
+}
+
+// For people rather than programs: NULL, numbers, 'quoted text', 0x-hex bytes, b'...' bits,
+// temporal values as in serde and TIMESTAMP in UTC, and JSON documents as text.
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let hex = |f: &mut fmt::Formatter<'_>, bytes: &[u8]| {
+            f.write_str("0x")?;
+            bytes.iter().try_for_each(|b| write!(f, "{:02x}", b))
+        };
+        match self {
+            Value::Null => f.write_str("NULL"),
+            Value::Int(v) => write!(f, "{}", v),
+            Value::UInt(v) => write!(f, "{}", v),
+            Value::Float(v) => write!(f, "{}", v),
+            Value::Double(v) => write!(f, "{}", v),
+            Value::Decimal(v) => f.write_str(v),
+            Value::String(v) => write!(f, "'{}'", v.escape_debug()),
+            Value::Bytes(v) => hex(f, v),
+            Value::Year(v) => write!(f, "{}", v),
+            Value::Date { year, month, day } => write!(f, "{:04}-{:02}-{:02}", year, month, day),
+            Value::Time { negative, hours, minutes, seconds, micros } => {
+                write!(f, "{}{:02}:{:02}:{:02}{}", if *negative { "-" } else { "" }, hours, minutes, seconds, fraction(*micros))
+            }
+            Value::DateTime { year, month, day, hour, minute, second, micros } => {
+                write!(f, "{:04}-{:02}-{:02} {:02}:{:02}:{:02}{}", year, month, day, hour, minute, second, fraction(*micros))
+            }
+            Value::Timestamp { seconds, micros } => write!(f, "{}{} UTC", utc_datetime(*seconds), fraction(*micros)),
+            Value::Bit(v) => {
+                f.write_str("b'")?;
+                v.iter().try_for_each(|b| write!(f, "{:08b}", b))?;
+                f.write_str("'")
+            }
+            Value::Enum(v) => write!(f, "{}", v),
+            Value::Set(v) => write!(f, "{}", v),
+            Value::Json(v) => match json_binary::decode(v) {
+                Some(document) => f.write_str(&json_binary::mysql_text(&document)),
+                None => hex(f, v),
+            },
+        }
+    }
+}
+
+// ".ffffff" with trailing zeros dropped, or nothing for whole seconds
+fn fraction(micros: u32) -> String {
+    if micros == 0 {
+        return String::new();
+    }
+    format!(".{:06}", micros).trim_end_matches('0').to_owned()
+}
+
+fn parse_date(s: &str) -> Option<(u16, u8, u8)> {
+    let mut parts = s.splitn(3, '-');
+    Some((parts.next()?.parse().ok()?, parts.next()?.parse().ok()?, parts.next()?.parse().ok()?))
+}
+
+fn parse_time(s: &str) -> Option<(u32, u8, u8, u32)> {
+    let (hms, micros) = match s.split_once('.') {
+        Some((hms, digits)) if !digits.is_empty() && digits.len() <= 6 && digits.bytes().all(|b| b.is_ascii_digit()) => {
+            (hms, format!("{:0<6}", digits).parse().ok()?)
+        }
+        Some(_) => return None,
+        None => (s, 0),
+    };
+    let mut parts = hms.splitn(3, ':');
+    Some((parts.next()?.parse().ok()?, parts.next()?.parse().ok()?, parts.next()?.parse().ok()?, micros))
+}
+
+// https://dev.mysql.com/doc/internals/en/binary-protocol-value.html
+// https://github.com/mysql/mysql-server/blob/8.0/libbinlogevents/src/binary_log_funcs.cpp
+pub(crate) fn parse_value<R: Read>(reader: &mut R, column: &TableMapColumn, options: &ParseOptions) -> Result<Value, EventParseError> {
+    let meta = column.meta;
+    let charset = match column.charset {
+        Some(id) => Charset::from_collation_id(id),
+        None => None,
+    };
+    let value = match column.column_type {
+        ColumnType::Tiny => int_value(reader.read_i8()? as i64, column.unsigned, 8),
+        ColumnType::Short => int_value(reader.read_i16::<LittleEndian>()? as i64, column.unsigned, 16),
+        ColumnType::Int24 => int_value(reader.read_i24::<LittleEndian>()? as i64, column.unsigned, 24),
+        ColumnType::Long => int_value(reader.read_i32::<LittleEndian>()? as i64, column.unsigned, 32),
+        ColumnType::LongLong => int_value(reader.read_i64::<LittleEndian>()?, column.unsigned, 64),
+        ColumnType::Float => Value::Float(reader.read_f32::<LittleEndian>()?),
+        ColumnType::Double => Value::Double(reader.read_f64::<LittleEndian>()?),
+        ColumnType::NewDecimal => {
+            let precision = (meta >> 8) as usize;
+            let scale = (meta & 0xff) as usize;
+            Value::Decimal(parse_decimal(reader, precision, scale)?)
+        }
+        ColumnType::Year => {
+            let year = reader.read_u8()?;
+            Value::Year(if year == 0 { 0 } else { 1900 + u16::from(year) })
+        }
+        ColumnType::Date | ColumnType::NewDate => {
+            let v = reader.read_u24::<LittleEndian>()?;
+            Value::Date { year: (v >> 9) as u16, month: ((v >> 5) & 0x0f) as u8, day: (v & 0x1f) as u8 }
+        }
+        ColumnType::Time => {
+            let v = reader.read_i24::<LittleEndian>()?;
+            let abs = v.unsigned_abs();
+            Value::Time {
+                negative: v < 0,
+                hours: abs / 10000,
+                minutes: ((abs / 100) % 100) as u8,
+                seconds: (abs % 100) as u8,
+                micros: 0,
+            }
+        }
+        ColumnType::Time2 => parse_time2(reader, meta as u8)?,
+        ColumnType::DateTime => {
+            let v = reader.read_u64::<LittleEndian>()?;
+            let date = v / 1_000_000;
+            let time = v % 1_000_000;
+            Value::DateTime {
+                year: (date / 10000) as u16,
+                month: ((date / 100) % 100) as u8,
+                day: (date % 100) as u8,
+                hour: (time / 10000) as u8,
+                minute: ((time / 100) % 100) as u8,
+                second: (time % 100) as u8,
+                micros: 0,
+            }
+        }
+        ColumnType::DateTime2 => {
+            let packed = reader.read_uint::<BigEndian>(5)? as i64 - 0x80_0000_0000;
+            let micros = read_fractional(reader, meta as u8)?;
+            let ymd = packed >> 17;
+            let ym = ymd >> 5;
+            let hms = packed % (1 << 17);
+            Value::DateTime {
+                year: (ym / 13) as u16,
+                month: (ym % 13) as u8,
+                day: (ymd % (1 << 5)) as u8,
+                hour: (hms >> 12) as u8,
+                minute: ((hms >> 6) % (1 << 6)) as u8,
+                second: (hms % (1 << 6)) as u8,
+                micros,
+            }
+        }
+        ColumnType::Timestamp => Value::Timestamp { seconds: reader.read_u32::<LittleEndian>()?, micros: 0 },
+        ColumnType::Timestamp2 => {
+            let seconds = reader.read_u32::<BigEndian>()?;
+            let micros = read_fractional(reader, meta as u8)?;
+            Value::Timestamp { seconds, micros }
+        }
+        ColumnType::VarChar | ColumnType::VarString => {
+            let len = if meta < 256 { usize::from(reader.read_u8()?) } else { usize::from(reader.read_u16::<LittleEndian>()?) };
+            string_value(read_sized(reader, len, "varchar")?, charset, options, true)
+        }
+        ColumnType::String => {
+            let (real_type, len) = real_string_type(meta);
+            match real_type {
+                ColumnType::Enum => Value::Enum(read_width(reader, usize::from(len), "enum width")? as u16),
+                ColumnType::Set => Value::Set(read_width(reader, usize::from(len), "set width")?),
+                _ => {
+                    let len = if len < 256 { usize::from(reader.read_u8()?) } else { usize::from(reader.read_u16::<LittleEndian>()?) };
+                    string_value(read_sized(reader, len, "char")?, charset, options, true)
+                }
+            }
+        }
+        ColumnType::Enum => Value::Enum(read_width(reader, usize::from(meta & 0xff), "enum width")? as u16),
+        ColumnType::Set => Value::Set(read_width(reader, usize::from(meta & 0xff), "set width")?),
+        ColumnType::Bit => {
+            let bits = usize::from(meta >> 8);
+            let bytes = usize::from(meta & 0xff);
+            Value::Bit(read_sized(reader, (bytes * 8 + bits).div_ceil(8), "bit")?)
+        }
+        ColumnType::Blob | ColumnType::TinyBlob | ColumnType::MediumBlob | ColumnType::LongBlob => {
+            let len = read_width(reader, usize::from(meta), "blob length width")? as usize;
+            string_value(read_sized(reader, len, "blob")?, charset, options, false)
+        }
+        ColumnType::Geometry => {
+            let len = read_width(reader, usize::from(meta), "geometry length width")? as usize;
+            Value::Bytes(read_sized(reader, len, "geometry")?)
+        }
+        ColumnType::Json => {
+            let len = read_width(reader, usize::from(meta), "json length width")? as usize;
+            Value::Json(read_sized(reader, len, "json")?)
+        }
+        ColumnType::Null => Value::Null,
+        other => return Err(EventParseError::UnsupportedType { field: "column", type_code: other.to_byte() }),
+    };
+    Ok(value)
+}
+
+// a little endian number as wide as table-map metadata says, which has to be 1 to 8 bytes
+fn read_width<R: Read>(reader: &mut R, width: usize, field: &'static str) -> Result<u64, EventParseError> {
+    if !(1..=8).contains(&width) {
+        return Err(EventParseError::BadMetadata { field, meta: width as u64 });
+    }
+    Ok(reader.read_uint::<LittleEndian>(width)?)
+}
+
+// The inverse of parse_value: appends `value` as a row event stores it in `column`. False, with
+// nothing written, when the value isn't one parse_value gives for the column or doesn't fit it,
+// like 300 for a TINYINT or more fractional digits than the column keeps. Text is written as
+// UTF-8 whatever the column's charset.
+pub(crate) fn write_value(out: &mut Vec<u8>, column: &TableMapColumn, value: &Value) -> bool {
+    let meta = column.meta;
+    let fits = |len: usize, max_len: u64| len as u64 <= max_len;
+    match (column.column_type, value) {
+        (ColumnType::Tiny, _) => return write_int(out, value, 1, column.unsigned),
+        (ColumnType::Short, _) => return write_int(out, value, 2, column.unsigned),
+        (ColumnType::Int24, _) => return write_int(out, value, 3, column.unsigned),
+        (ColumnType::Long, _) => return write_int(out, value, 4, column.unsigned),
+        (ColumnType::LongLong, _) => return write_int(out, value, 8, column.unsigned),
+        (ColumnType::Float, Value::Float(v)) => out.extend_from_slice(&v.to_le_bytes()),
+        (ColumnType::Double, Value::Double(v)) => out.extend_from_slice(&v.to_le_bytes()),
+        (ColumnType::NewDecimal, Value::Decimal(text)) => {
+            match decimal_bytes(text, usize::from(meta >> 8), usize::from(meta & 0xff)) {
+                Some(bytes) => out.extend_from_slice(&bytes),
+                None => return false,
+            }
+        }
+        (ColumnType::Year, Value::Year(year)) => match year {
+            0 => out.push(0),
+            1901..=2155 => out.push((year - 1900) as u8),
+            _ => return false,
+        },
+        (ColumnType::Date | ColumnType::NewDate, Value::Date { year, month, day }) => {
+            let v = u32::from(*year) << 9 | u32::from(*month) << 5 | u32::from(*day);
+            out.extend_from_slice(&v.to_le_bytes()[..3]);
+        }
+        (ColumnType::Time2, Value::Time { negative, hours, minutes, seconds, micros }) => {
+            if !fractional_fits(*micros, meta as u8) {
+                return false;
+            }
+            let hms = i64::from(*hours) << 12 | i64::from(*minutes) << 6 | i64::from(*seconds);
+            let packed = (hms << 24) + i64::from(*micros);
+            write_time2(out, if *negative { -packed } else { packed }, meta as u8);
+        }
+        (ColumnType::DateTime2, Value::DateTime { year, month, day, hour, minute, second, micros }) => {
+            if !fractional_fits(*micros, meta as u8) {
+                return false;
+            }
+            let ymd = (i64::from(*year) * 13 + i64::from(*month)) << 5 | i64::from(*day);
+            let hms = i64::from(*hour) << 12 | i64::from(*minute) << 6 | i64::from(*second);
+            let packed = (ymd << 17 | hms) + 0x80_0000_0000;
+            out.extend_from_slice(&packed.to_be_bytes()[3..]);
+            write_fractional(out, *micros, meta as u8);
+        }
+        (ColumnType::Timestamp2, Value::Timestamp { seconds, micros }) => {
+            if !fractional_fits(*micros, meta as u8) {
+                return false;
+            }
+            out.extend_from_slice(&seconds.to_be_bytes());
+            write_fractional(out, *micros, meta as u8);
+        }
+        (ColumnType::Timestamp, Value::Timestamp { seconds, micros: 0 }) => out.extend_from_slice(&seconds.to_le_bytes()),
+        (ColumnType::VarChar | ColumnType::VarString, Value::String(_) | Value::Bytes(_)) => {
+            let bytes = text_bytes(value);
+            if !fits(bytes.len(), u64::from(meta)) {
+                return false;
+            }
+            write_length(out, bytes.len(), if meta < 256 { 1 } else { 2 });
+            out.extend_from_slice(bytes);
+        }
+        (ColumnType::String, _) => {
+            let (real_type, len) = real_string_type(meta);
+            match (real_type, value) {
+                (ColumnType::Enum, Value::Enum(v)) if len <= 2 => out.extend_from_slice(&v.to_le_bytes()[..usize::from(len)]),
+                (ColumnType::Set, Value::Set(v)) if len <= 8 => out.extend_from_slice(&v.to_le_bytes()[..usize::from(len)]),
+                (ColumnType::Enum | ColumnType::Set, _) => return false,
+                (_, Value::String(_) | Value::Bytes(_)) => {
+                    let bytes = text_bytes(value);
+                    if !fits(bytes.len(), u64::from(len)) {
+                        return false;
+                    }
+                    write_length(out, bytes.len(), if len < 256 { 1 } else { 2 });
+                    out.extend_from_slice(bytes);
+                }
+                _ => return false,
+            }
+        }
+        (ColumnType::Bit, Value::Bit(bytes)) => {
+            if bytes.len() != (usize::from(meta & 0xff) * 8 + usize::from(meta >> 8)).div_ceil(8) {
+                return false;
+            }
+            out.extend_from_slice(bytes);
+        }
+        (ColumnType::Blob | ColumnType::TinyBlob | ColumnType::MediumBlob | ColumnType::LongBlob, Value::String(_) | Value::Bytes(_))
+        | (ColumnType::Geometry, Value::Bytes(_))
+        | (ColumnType::Json, Value::Json(_)) => {
+            let bytes = match value {
+                Value::Json(bytes) => bytes.as_slice(),
+                _ => text_bytes(value),
+            };
+            if meta == 0 || meta > 4 || !fits(bytes.len(), (1u64 << (8 * meta)) - 1) {
+                return false;
+            }
+            write_length(out, bytes.len(), usize::from(meta));
+            out.extend_from_slice(bytes);
+        }
+        _ => return false,
+    }
+    true
+}
+
+fn write_int(out: &mut Vec<u8>, value: &Value, len: usize, unsigned: bool) -> bool {
+    let v = match value {
+        Value::Int(v) => i128::from(*v),
+        Value::UInt(v) => i128::from(*v),
+        _ => return false,
+    };
+    let bits = 8 * len as u32;
+    let (min, max) = if unsigned { (0, (1i128 << bits) - 1) } else { (-(1i128 << (bits - 1)), (1i128 << (bits - 1)) - 1) };
+    if v < min || v > max {
+        return false;
+    }
+    out.extend_from_slice(&(v as u64).to_le_bytes()[..len]);
+    true
+}
+
+fn text_bytes(value: &Value) -> &[u8] {
+    match value {
+        Value::String(s) => s.as_bytes(),
+        Value::Bytes(b) => b,
+        _ => &[],
+    }
+}
+
+// a little endian length prefix of `len` bytes
+fn write_length(out: &mut Vec<u8>, value_len: usize, len: usize) {
+    out.extend_from_slice(&(value_len as u64).to_le_bytes()[..len]);
+}
+
+fn fractional_fits(micros: u32, fsp: u8) -> bool {
+    let unit = match fsp {
+        1 | 2 => 10000,
+        3 | 4 => 100,
+        5 | 6 => 1,
+        _ => 1_000_000,
+    };
+    micros < 1_000_000 && micros.is_multiple_of(unit)
+}
+
+fn write_fractional(out: &mut Vec<u8>, micros: u32, fsp: u8) {
+    match fsp {
+        1 | 2 => out.push((micros / 10000) as u8),
+        3 | 4 => out.extend_from_slice(&((micros / 100) as u16).to_be_bytes()),
+        5 | 6 => out.extend_from_slice(&micros.to_be_bytes()[1..]),
+        _ => {}
+    }
+}
+
+// https://github.com/mysql/mysql-server/blob/8.0/mysys/my_time.cc (my_time_packed_to_binary)
+fn write_time2(out: &mut Vec<u8>, packed: i64, fsp: u8) {
+    let int_part = ((packed >> 24) + 0x80_0000) as u32;
+    let frac = packed % (1 << 24);
+    match fsp {
+        1 | 2 => {
+            out.extend_from_slice(&int_part.to_be_bytes()[1..]);
+            out.push((frac / 10000) as i8 as u8);
+        }
+        3 | 4 => {
+            out.extend_from_slice(&int_part.to_be_bytes()[1..]);
+            out.extend_from_slice(&((frac / 100) as i16).to_be_bytes());
+        }
+        5 | 6 => out.extend_from_slice(&(packed + 0x8000_0000_0000).to_be_bytes()[2..]),
+        _ => out.extend_from_slice(&int_part.to_be_bytes()[1..]),
+    }
+}
+
+fn int_value(v: i64, unsigned: bool, bits: u32) -> Value {
+    if !unsigned {
+        return Value::Int(v);
+    }
+    let mask = if bits == 64 { u64::MAX } else { (1u64 << bits) - 1 };
+    Value::UInt(v as u64 & mask)
+}
+
+// Text columns without charset information are decoded with the configured default charset;
+// BLOBs only become strings when the table map says they carry a non-binary charset (TEXT columns).
+fn string_value(bytes: Vec<u8>, charset: Option<Charset>, options: &ParseOptions, text_by_default: bool) -> Value {
+    let charset = match charset {
+        Some(charset) => charset,
+        None if text_by_default => options.default_charset,
+        None => return Value::Bytes(bytes),
+    };
+    match charset.decode(&bytes) {
+        Some(s) => Value::String(s),
+        None => Value::Bytes(bytes),
+    }
+}
+
+// fractional seconds are stored big endian in (fsp + 1) / 2 bytes
+fn read_fractional<R: Read>(reader: &mut R, fsp: u8) -> Result<u32, EventParseError> {
+    let micros = match fsp {
+        1 | 2 => reader.read_u8()? as u32 * 10000,
+        3 | 4 => reader.read_u16::<BigEndian>()? as u32 * 100,
+        5 | 6 => reader.read_u24::<BigEndian>()?,
+        _ => 0,
+    };
+    Ok(micros)
+}
+
+// https://github.com/mysql/mysql-server/blob/8.0/mysys/my_time.cc (my_time_packed_from_binary)
+fn parse_time2<R: Read>(reader: &mut R, fsp: u8) -> Result<Value, EventParseError> {
+    let packed: i64 = match fsp {
+        1 | 2 => {
+            let mut int_part = reader.read_u24::<BigEndian>()? as i64 - 0x80_0000;
+            let mut frac = reader.read_u8()? as i64;
+            if int_part < 0 && frac != 0 {
+                int_part += 1;
+                frac -= 0x100;
+            }
+            (int_part << 24) + frac * 10000
+        }
+        3 | 4 => {
+            let mut int_part = reader.read_u24::<BigEndian>()? as i64 - 0x80_0000;
+            let mut frac = reader.read_u16::<BigEndian>()? as i64;
+            if int_part < 0 && frac != 0 {
+                int_part += 1;
+                frac -= 0x10000;
+            }
+            (int_part << 24) + frac * 100
+        }
+        5 | 6 => reader.read_uint::<BigEndian>(6)? as i64 - 0x8000_0000_0000,
+        _ => (reader.read_u24::<BigEndian>()? as i64 - 0x80_0000) << 24,
+    };
+
+    let negative = packed < 0;
+    let packed = packed.abs();
+    let hms = packed >> 24;
+    Ok(Value::Time {
+        negative,
+        hours: ((hms >> 12) % (1 << 10)) as u32,
+        minutes: ((hms >> 6) % (1 << 6)) as u8,
+        seconds: (hms % (1 << 6)) as u8,
+        micros: (packed % (1 << 24)) as u32,
+    })
+}
+
+const DIGITS_PER_INTEGER: usize = 9;
+// bytes taken by a group of fewer than nine digits
+const COMPRESSED_BYTES: [usize; 10] = [0, 1, 1, 2, 2, 3, 3, 4, 4, 4];
+
+// https://github.com/mysql/mysql-server/blob/8.0/strings/decimal.cc (bin2decimal)
+fn parse_decimal<R: Read>(reader: &mut R, precision: usize, scale: usize) -> Result<String, EventParseError> {
+    // DECIMAL(65, 30) at most
+    if scale > precision || precision > 65 {
+        return Err(EventParseError::BadMetadata { field: "decimal precision and scale", meta: (precision << 8 | scale) as u64 });
+    }
+    let integral = precision - scale;
+    let uncompressed_integral = integral / DIGITS_PER_INTEGER;
+    let uncompressed_fractional = scale / DIGITS_PER_INTEGER;
+    let compressed_integral = integral - uncompressed_integral * DIGITS_PER_INTEGER;
+    let compressed_fractional = scale - uncompressed_fractional * DIGITS_PER_INTEGER;
+    let len = uncompressed_integral * 4 + COMPRESSED_BYTES[compressed_integral]
+        + uncompressed_fractional * 4 + COMPRESSED_BYTES[compressed_fractional];
+
+    let mut bytes = read_sized(reader, len, "decimal")?;
+    if bytes.is_empty() {
+        return Ok("0".to_owned());
+    }
+    // the sign is the inverted top bit; negative numbers additionally have every bit inverted
+    let negative = bytes[0] & 0x80 == 0;
+    bytes[0] ^= 0x80;
+    if negative {
+        bytes.iter_mut().for_each(|b| *b = !*b);
+    }
+
+    let mut cursor = &bytes[..];
+    let mut take = |n: usize| -> Result<u32, EventParseError> {
+        Ok(cursor.read_uint::<BigEndian>(n)? as u32)
+    };
+
+    let mut integral_digits = String::new();
+    if compressed_integral > 0 {
+        integral_digits.push_str(&take(COMPRESSED_BYTES[compressed_integral])?.to_string());
+    }
+    for _ in 0..uncompressed_integral {
+        integral_digits.push_str(&format!("{:09}", take(4)?));
+    }
+    let integral_digits = integral_digits.trim_start_matches('0');
+
+    let mut fractional_digits = String::new();
+    for _ in 0..uncompressed_fractional {
+        fractional_digits.push_str(&format!("{:09}", take(4)?));
+    }
+    if compressed_fractional > 0 {
+        let v = take(COMPRESSED_BYTES[compressed_fractional])?;
+        fractional_digits.push_str(&format!("{:0width$}", v, width = compressed_fractional));
+    }
+
+    let mut decimal = String::new();
+    if negative {
+        decimal.push('-');
+    }
+    decimal.push_str(if integral_digits.is_empty() { "0" } else { integral_digits });
+    if scale > 0 {
+        decimal.push('.');
+        decimal.push_str(&fractional_digits);
+    }
+    Ok(decimal)
+}
+
+// https://github.com/mysql/mysql-server/blob/8.0/strings/decimal.cc (decimal2bin); None when
+// `text` isn't a number or has more digits than DECIMAL(precision, scale) holds
+fn decimal_bytes(text: &str, precision: usize, scale: usize) -> Option<Vec<u8>> {
+    let (negative, digits) = match text.strip_prefix('-') {
+        Some(digits) => (true, digits),
+        None => (false, text),
+    };
+    let (integer, fraction) = digits.split_once('.').unwrap_or((digits, ""));
+    let integer = integer.trim_start_matches('0');
+    let integral = precision.checked_sub(scale)?;
+    if !(integer.bytes().chain(fraction.bytes()).all(|b| b.is_ascii_digit())) || integer.len() > integral || fraction.len() > scale {
+        return None;
+    }
+    let integer = format!("{:0>width$}", integer, width = integral);
+    let fraction = format!("{:0<width$}", fraction, width = scale);
+
+    let mut bytes = vec![];
+    let mut push = |group: &str| {
+        let len = if group.len() == DIGITS_PER_INTEGER { 4 } else { COMPRESSED_BYTES[group.len()] };
+        let v: u32 = group.parse().unwrap_or(0);
+        bytes.extend_from_slice(&v.to_be_bytes()[4 - len..]);
+    };
+    let leading = integral % DIGITS_PER_INTEGER;
+    if leading > 0 {
+        push(&integer[..leading]);
+    }
+    for i in (leading..integral).step_by(DIGITS_PER_INTEGER) {
+        push(&integer[i..i + DIGITS_PER_INTEGER]);
+    }
+    for i in (0..scale).step_by(DIGITS_PER_INTEGER) {
+        push(&fraction[i..scale.min(i + DIGITS_PER_INTEGER)]);
+    }
+    // see parse_decimal; minus zero is written as zero
+    if negative && (integer.bytes().chain(fraction.bytes()).any(|b| b != b'0')) {
+        bytes.iter_mut().for_each(|b| *b = !*b);
+    }
+    if let Some(first) = bytes.first_mut() {
+        *first ^= 0x80;
+    }
+    Some(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::column::ColumnType;
+    use crate::options::ParseOptions;
+    use crate::table_map::TableMapColumn;
+    use crate::value::{decimal_bytes, parse_decimal, parse_time2, parse_value, write_value, Value};
+
+    #[test]
+    fn test_parse_decimal() {
+        //given
+        // DECIMAL(10,4) 1234.5678 and -1234.5678
+        let positive = [0x80, 0x04, 0xd2, 0x16, 0x2e];
+        let negative = [0x7f, 0xfb, 0x2d, 0xe9, 0xd1];
+
+        //when
+        let p = parse_decimal(&mut &positive[..], 10, 4).unwrap();
+        let n = parse_decimal(&mut &negative[..], 10, 4).unwrap();
+
+        //then
+        assert_eq!(p, "1234.5678");
+        assert_eq!(n, "-1234.5678");
+    }
+
+    #[test]
+    fn test_write_value_round_trips() {
+        //given
+        let column = |column_type: ColumnType, meta: u16, unsigned: bool| TableMapColumn {
+            column_type,
+            meta,
+            nullable: true,
+            unsigned,
+            charset: Some(255),
+            name: None,
+            enum_values: None,
+            set_values: None,
+        };
+        let values = vec![
+            (column(ColumnType::Tiny, 0, false), Value::Int(-128)),
+            (column(ColumnType::Int24, 0, true), Value::UInt(16_777_215)),
+            (column(ColumnType::LongLong, 0, true), Value::UInt(u64::MAX)),
+            (column(ColumnType::Double, 8, false), Value::Double(-2.5)),
+            (column(ColumnType::NewDecimal, 10 << 8 | 4, false), Value::Decimal("-1234.5678".to_owned())),
+            (column(ColumnType::NewDecimal, 30 << 8 | 12, false), Value::Decimal("123456789012345678.000000000001".to_owned())),
+            (column(ColumnType::Year, 0, false), Value::Year(2021)),
+            (column(ColumnType::Date, 0, false), Value::Date { year: 2024, month: 2, day: 29 }),
+            (column(ColumnType::Time2, 2, false), Value::Time { negative: true, hours: 1, minutes: 2, seconds: 3, micros: 450000 }),
+            (column(ColumnType::Time2, 6, false), Value::Time { negative: true, hours: 838, minutes: 59, seconds: 59, micros: 1 }),
+            (column(ColumnType::DateTime2, 3, false), Value::DateTime { year: 2021, month: 10, day: 1, hour: 23, minute: 59, second: 58, micros: 123000 }),
+            (column(ColumnType::Timestamp2, 6, false), Value::Timestamp { seconds: 1633046400, micros: 999999 }),
+            (column(ColumnType::VarChar, 1020, false), Value::String("héllo".to_owned())),
+            (column(ColumnType::String, 0xce_fc, false), Value::String("x".repeat(300))),
+            (column(ColumnType::Blob, 2, false), Value::String("text".to_owned())),
+        ];
+
+        //when
+        let round_trips: Vec<_> = values.iter()
+            .map(|(column, value)| {
+                let mut bytes = vec![];
+                assert!(write_value(&mut bytes, column, value), "{:?}", value);
+                let mut reader = &bytes[..];
+                let back = parse_value(&mut reader, column, &ParseOptions::default()).unwrap();
+                assert!(reader.is_empty(), "{:?} left {:?}", value, reader);
+                back
+            })
+            .collect();
+        let mut unused = vec![];
+        let too_big = write_value(&mut unused, &column(ColumnType::Tiny, 0, true), &Value::UInt(256));
+        let too_precise = write_value(&mut unused, &column(ColumnType::DateTime2, 1, false), &Value::DateTime { year: 2021, month: 1, day: 1, hour: 0, minute: 0, second: 0, micros: 1 });
+        let too_long = write_value(&mut unused, &column(ColumnType::VarChar, 4, false), &Value::String("hello".to_owned()));
+
+        //then
+        assert_eq!(round_trips, values.into_iter().map(|(_, value)| value).collect::<Vec<_>>());
+        assert_eq!(decimal_bytes("1234.5678", 10, 4), Some(vec![0x80, 0x04, 0xd2, 0x16, 0x2e]));
+        assert_eq!(decimal_bytes("12.345", 10, 2), None);
+        assert!(!too_big && !too_precise && !too_long);
+        assert!(unused.is_empty());
+    }
+
+    #[test]
+    fn test_parse_time2() {
+        //given
+        // TIME(0) '-838:59:59' and '12:34:56'
+        let negative = [0x4b, 0x91, 0x05];
+        let positive = [0x80, 0xc8, 0xb8];
+
+        //when
+        let n = parse_time2(&mut &negative[..], 0).unwrap();
+        let p = parse_time2(&mut &positive[..], 0).unwrap();
+
+        //then
+        assert_eq!(n, Value::Time { negative: true, hours: 838, minutes: 59, seconds: 59, micros: 0 });
+        assert_eq!(p, Value::Time { negative: false, hours: 12, minutes: 34, seconds: 56, micros: 0 });
+    }
+
+    #[test]
+    fn test_serde_temporal_and_bytes() {
+        //given
+        let values = vec![
+            Value::Date { year: 2024, month: 1, day: 31 },
+            Value::Time { negative: true, hours: 838, minutes: 59, seconds: 59, micros: 1 },
+            Value::DateTime { year: 2024, month: 1, day: 31, hour: 23, minute: 59, second: 59, micros: 500000 },
+            Value::Bytes(vec![0xde, 0xad]),
+            Value::Null,
+        ];
+
+        //when
+        let json = serde_json::to_string(&values).unwrap();
+        let back: Vec<Value> = serde_json::from_str(&json).unwrap();
+        let bad = serde_json::from_str::<Value>(r#"{"Date":"2024-01"}"#);
+
+        //then
+        assert_eq!(json, r#"[{"Date":"2024-01-31"},{"Time":"-838:59:59.000001"},{"DateTime":"2024-01-31 23:59:59.5"},{"Bytes":"dead"},"Null"]"#);
+        assert_eq!(back, values);
+        assert!(bad.is_err());
+    }
+}
@@ -0,0 +1,187 @@
+use std::fmt;
+use std::io::{Read, Seek, SeekFrom};
+use crate::checksum::{ChecksumAlgorithm, ChecksumVerifier};
+use crate::errors::{BinlogFileError, EventParseError};
+use crate::event::{Event, EventHeader, TypeCode};
+use crate::options::ParseMode;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Problem {
+    // unknown type code or impossible length; nothing after it can be trusted
+    BadHeader,
+    NextPositionMismatch { expected: u64, actual: u32 },
+    ChecksumMismatch { expected: u32, actual: u32 },
+    Truncated { expected_len: u32, available: u64 },
+    // the file doesn't end with a Rotate or Stop event: still being written, or cut short
+    MissingTerminalEvent,
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Problem::BadHeader => write!(f, "not an event header"),
+            Problem::NextPositionMismatch { expected, actual } => {
+                write!(f, "next position is {} but the event ends at {}", actual, expected)
+            }
+            Problem::ChecksumMismatch { expected, actual } => {
+                write!(f, "checksum mismatch: stored {:#010x}, computed {:#010x}", expected, actual)
+            }
+            Problem::Truncated { expected_len, available } => {
+                write!(f, "event of {} bytes cut off after {}", expected_len, available)
+            }
+            Problem::MissingTerminalEvent => write!(f, "no Rotate or Stop event at the end"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct VerifyReport {
+    pub event_count: usize,
+    pub first_timestamp: Option<u32>,
+    pub last_timestamp: Option<u32>,
+    pub checksum_algorithm: ChecksumAlgorithm,
+    // offset right after the last complete event
+    pub end_offset: u64,
+    pub terminal_event: Option<TypeCode>,
+    // offset of the event each problem was found at
+    pub problems: Vec<(u64, Problem)>,
+}
+
+impl VerifyReport {
+    pub fn is_ok(&self) -> bool {
+        self.problems.is_empty()
+    }
+}
+
+pub(crate) fn verify<I: Seek + Read>(file: &mut I, start: u64) -> Result<VerifyReport, BinlogFileError> {
+    let file_len = file.seek(SeekFrom::End(0))?;
+    let mut report = VerifyReport {
+        event_count: 0,
+        first_timestamp: None,
+        last_timestamp: None,
+        checksum_algorithm: ChecksumAlgorithm::Off,
+        end_offset: start,
+        terminal_event: None,
+        problems: vec![],
+    };
+    let mut checksum = ChecksumVerifier::default();
+    let mut last_type = None;
+    let mut offset = start;
+
+    while offset < file_len {
+        let available = file_len - offset;
+        if available < EventHeader::LEN as u64 {
+            report.problems.push((offset, Problem::Truncated { expected_len: EventHeader::LEN as u32, available }));
+            break;
+        }
+        file.seek(SeekFrom::Start(offset))?;
+        let mut header_bytes = [0u8; EventHeader::LEN];
+        file.read_exact(&mut header_bytes)?;
+        let header = EventHeader::parse(&header_bytes);
+        if header.type_code == TypeCode::UnknownEvent
+            || (header.event_length as usize) < EventHeader::LEN
+            || header.event_length > EventHeader::MAX_EVENT_LENGTH
+        {
+            report.problems.push((offset, Problem::BadHeader));
+            break;
+        }
+        if available < u64::from(header.event_length) {
+            report.problems.push((offset, Problem::Truncated { expected_len: header.event_length, available }));
+            break;
+        }
+        let expected_next = offset + u64::from(header.event_length);
+        if header.next_position != 0 && u64::from(header.next_position) != expected_next {
+            report.problems.push((offset, Problem::NextPositionMismatch { expected: expected_next, actual: header.next_position }));
+        }
+
+        // leniently, since a mismatched next position is reported above rather than fatal
+        let mut event = Event::parse_with_mode(&mut (&header_bytes[..]).chain(&mut *file), offset, ParseMode::Lenient)?;
+        match checksum.check(&header_bytes, &mut event, ParseMode::Strict) {
+            Ok(()) => {}
+            Err(EventParseError::ChecksumMismatch { expected, actual, .. }) => {
+                report.problems.push((offset, Problem::ChecksumMismatch { expected, actual }));
+            }
+            Err(e) => return Err(e.into()),
+        }
+
+        // the FDE's timestamp is when the file was opened, which still bounds what's in it
+        report.first_timestamp.get_or_insert(header.timestamp);
+        report.last_timestamp = Some(header.timestamp);
+        report.event_count += 1;
+        report.end_offset = expected_next;
+        last_type = Some(header.type_code);
+        offset = expected_next;
+    }
+
+    report.checksum_algorithm = checksum.algorithm();
+    report.terminal_event = last_type.filter(|t| matches!(t, TypeCode::RotateEvent | TypeCode::StopEvent));
+    if report.terminal_event.is_none() && report.problems.is_empty() {
+        report.problems.push((report.end_offset, Problem::MissingTerminalEvent));
+    }
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+    use crate::binlog_file::BinlogFile;
+    use crate::checksum::ChecksumAlgorithm;
+    use crate::event::TypeCode;
+    use crate::verify::Problem;
+
+    #[test]
+    fn test_verify_clean_file() {
+        //given
+        let mut binlog_file = BinlogFile::from_path("tests/asset/mysql-bin.100746").unwrap();
+
+        //when
+        let report = binlog_file.verify().unwrap();
+
+        //then
+        assert!(report.is_ok());
+        assert_eq!(report.event_count, 15);
+        assert_eq!(report.first_timestamp, Some(1633046400));
+        assert_eq!(report.checksum_algorithm, ChecksumAlgorithm::Crc32);
+        assert_eq!(report.terminal_event, Some(TypeCode::RotateEvent));
+        assert_eq!(report.end_offset, 1064);
+    }
+
+    #[test]
+    fn test_verify_reports_problems() {
+        //given
+        let mut bytes = std::fs::read("tests/asset/mysql-bin.100746").unwrap();
+        // corrupt the CREATE TABLE query and cut the Rotate short
+        bytes[319] ^= 0xff;
+        bytes.truncate(1040);
+        let mut binlog_file = BinlogFile::from_reader(Cursor::new(bytes)).unwrap();
+
+        //when
+        let report = binlog_file.verify().unwrap();
+
+        //then
+        assert_eq!(report.event_count, 14);
+        assert!(matches!(report.problems[0], (219, Problem::ChecksumMismatch { .. })));
+        assert_eq!(report.problems[1], (1017, Problem::Truncated { expected_len: 47, available: 23 }));
+        assert_eq!(report.problems[1].1.to_string(), "event of 47 bytes cut off after 23");
+        assert_eq!(report.terminal_event, None);
+    }
+
+    #[test]
+    fn test_verify_reports_wrong_next_position() {
+        //given
+        let mut bytes = std::fs::read("tests/asset/mysql-bin.100746").unwrap();
+        // the CREATE TABLE query at 219 says the next event is at 1
+        bytes[219 + 13..219 + 17].copy_from_slice(&1u32.to_le_bytes());
+        let mut binlog_file = BinlogFile::from_reader(Cursor::new(bytes)).unwrap();
+
+        //when
+        let report = binlog_file.verify().unwrap();
+
+        //then
+        assert_eq!(report.event_count, 15);
+        assert_eq!(report.end_offset, 1064);
+        assert!(matches!(report.problems[0], (219, Problem::NextPositionMismatch { actual: 1, .. })));
+        assert!(matches!(report.problems[1], (219, Problem::ChecksumMismatch { .. })));
+        assert_eq!(report.problems.len(), 2);
+    }
+}